@@ -0,0 +1,92 @@
+//! `dedupe_alerts`: one failure notification per failing streak and one when
+//! it ends, instead of one per failed run. The streak is kept beside the
+//! job's history so a restart doesn't re-alert.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+const ALERT_FILE: &str = "alert.json";
+
+/// The current failing streak, absent while the job succeeds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Streak {
+    since: DateTime<Utc>,
+    failures: u32,
+    notified_at: DateTime<Utc>,
+}
+
+/// What to send for a finished run.
+#[derive(Debug, PartialEq)]
+pub enum Alert {
+    Failure,
+    /// First success after `failures` failed runs
+    Recovered { failing_since: DateTime<Utc>, failures: u32 },
+    None,
+}
+
+/// Updates the streak in `dir` with a run that finished at `now`.
+/// `renotify_after` re-sends the failure while the streak lasts that long
+/// past the last notification.
+pub fn update(dir: &Path, success: bool, now: DateTime<Utc>, renotify_after: Option<Duration>) -> Result<Alert> {
+    let path = dir.join(ALERT_FILE);
+    let streak: Option<Streak> = match std::fs::read(&path) {
+        Ok(data) => serde_json::from_slice(&data).ok(),
+        Err(_) => None,
+    };
+
+    let (alert, next) = match (streak, success) {
+        (None, true) => return Ok(Alert::None),
+        (Some(streak), true) => (Alert::Recovered { failing_since: streak.since, failures: streak.failures }, None),
+        (None, false) => (Alert::Failure, Some(Streak { since: now, failures: 1, notified_at: now })),
+        (Some(mut streak), false) => {
+            streak.failures += 1;
+            let due = renotify_after.is_some_and(|after| {
+                (now - streak.notified_at).to_std().is_ok_and(|elapsed| elapsed >= after)
+            });
+            if due {
+                streak.notified_at = now;
+            }
+            (if due { Alert::Failure } else { Alert::None }, Some(streak))
+        }
+    };
+
+    match next {
+        Some(streak) => {
+            std::fs::create_dir_all(dir)?;
+            std::fs::write(&path, serde_json::to_vec(&streak)?)?;
+        }
+        None => std::fs::remove_file(&path)?,
+    }
+    Ok(alert)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn one_alert_per_streak() {
+        let dir = tempfile::tempdir().unwrap();
+        let at = |hour| Utc.with_ymd_and_hms(2025, 1, 1, hour, 0, 0).unwrap();
+        let renotify = Some(Duration::from_secs(6 * 3600));
+
+        assert_eq!(update(dir.path(), true, at(0), renotify).unwrap(), Alert::None);
+        assert_eq!(update(dir.path(), false, at(1), renotify).unwrap(), Alert::Failure);
+        assert_eq!(update(dir.path(), false, at(2), renotify).unwrap(), Alert::None);
+        assert_eq!(update(dir.path(), false, at(7), renotify).unwrap(), Alert::Failure);
+        assert_eq!(update(dir.path(), false, at(8), renotify).unwrap(), Alert::None);
+        assert_eq!(
+            update(dir.path(), true, at(9), renotify).unwrap(),
+            Alert::Recovered { failing_since: at(1), failures: 4 }
+        );
+        assert_eq!(update(dir.path(), true, at(10), renotify).unwrap(), Alert::None);
+
+        // Without renotify_after, one alert however long it fails
+        assert_eq!(update(dir.path(), false, at(11), None).unwrap(), Alert::Failure);
+        assert_eq!(update(dir.path(), false, at(23), None).unwrap(), Alert::None);
+    }
+}
@@ -0,0 +1,227 @@
+//! `slo`: compares a finished run with the job's recent runs and reports
+//! deviations worth a look before they turn into failures: a run much slower
+//! than usual, or the failure rate crossing its threshold. Independently of
+//! `slo`, [`degrading`] flags jobs that need retries more and more often.
+
+use super::report::{JobRunReport, RunOutcome};
+use crate::config::SloConfig;
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+/// Successful runs needed before their median duration is trusted
+const MIN_DURATION_SAMPLES: usize = 5;
+
+/// Successful runs in each half of the window [`degrading`] compares
+const TREND_RUNS: usize = 10;
+/// Recent successes that needed a retry before a job counts as degrading
+const TREND_MIN_RETRIED: usize = 3;
+/// How many more than in the earlier half
+const TREND_MIN_INCREASE: usize = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    /// Took more than `duration_factor` times the median successful run
+    Slow { duration: Duration, median: Duration },
+    /// The failure rate over the last `runs` runs rose above `failure_rate`
+    FailureRate { failed: usize, runs: usize },
+}
+
+impl Anomaly {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Anomaly::Slow { .. } => "slow",
+            Anomaly::FailureRate { .. } => "failure_rate",
+        }
+    }
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anomaly::Slow { duration, median } => write!(
+                f,
+                "took {:.1}s, {:.1}x the median of {:.1}s",
+                duration.as_secs_f64(),
+                duration.as_secs_f64() / median.as_secs_f64(),
+                median.as_secs_f64()
+            ),
+            Anomaly::FailureRate { failed, runs } => write!(f, "{} of the last {} runs failed", failed, runs),
+        }
+    }
+}
+
+/// Successful runs increasingly needing a retry: the job still works, but
+/// is likely to start failing outright.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Degrading {
+    /// Recent successful runs that needed more than one attempt
+    pub retried: usize,
+    pub runs: usize,
+    /// The same for the successful runs before those
+    pub previous_retried: usize,
+    pub previous_runs: usize,
+}
+
+impl fmt::Display for Degrading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of the last {} successful runs needed a retry ({} of {} before)",
+            self.retried, self.runs, self.previous_retried, self.previous_runs
+        )
+    }
+}
+
+/// Compares the retry rate of the latest successful runs in `runs` (oldest
+/// first) with the successful runs before them. Needs a full window of each.
+pub fn degrading(runs: &[JobRunReport]) -> Option<Degrading> {
+    let successes: Vec<&JobRunReport> =
+        runs.iter().filter(|r| r.is_success() && !r.imported).rev().take(TREND_RUNS * 2).collect();
+    if successes.len() < TREND_RUNS * 2 {
+        return None;
+    }
+    let (recent, previous) = successes.split_at(TREND_RUNS);
+    let retried = |runs: &[&JobRunReport]| runs.iter().filter(|r| r.attempts > 1).count();
+    let trend = Degrading {
+        retried: retried(recent),
+        runs: recent.len(),
+        previous_retried: retried(previous),
+        previous_runs: previous.len(),
+    };
+    let rising = trend.retried >= TREND_MIN_RETRIED && trend.retried >= trend.previous_retried + TREND_MIN_INCREASE;
+    rising.then_some(trend)
+}
+
+/// Anomalies of `report` against `previous` (the job's history, oldest first,
+/// without `report`). Interrupted and skipped runs say nothing about the job
+/// and are ignored.
+pub fn detect(slo: &SloConfig, report: &JobRunReport, previous: &[JobRunReport]) -> Vec<Anomaly> {
+    let ignored = |r: &JobRunReport| matches!(r.outcome, RunOutcome::Interrupted | RunOutcome::Skipped);
+    if ignored(report) {
+        return Vec::new();
+    }
+    let recent: Vec<&JobRunReport> = previous
+        .iter()
+        .filter(|r| !ignored(r))
+        .rev()
+        .take(slo.runs)
+        .collect();
+    let mut anomalies = Vec::new();
+
+    if let Some(factor) = slo.duration_factor.filter(|_| report.is_success()) {
+        let mut durations: Vec<Duration> = recent.iter().filter(|r| r.is_success()).map(|r| r.duration).collect();
+        if durations.len() >= MIN_DURATION_SAMPLES {
+            durations.sort();
+            let median = durations[durations.len() / 2];
+            if !median.is_zero() && report.duration.as_secs_f64() > median.as_secs_f64() * factor {
+                anomalies.push(Anomaly::Slow { duration: report.duration, median });
+            }
+        }
+    }
+
+    // Alert on crossing only, not on every run while the rate stays high
+    if let Some(threshold) = slo.failure_rate {
+        let above = |runs: &[&JobRunReport]| {
+            let failed = runs.iter().filter(|r| !r.is_success()).count();
+            (runs.len() == slo.runs && failed as f64 > runs.len() as f64 * threshold).then_some(failed)
+        };
+        let window: Vec<&JobRunReport> = std::iter::once(report).chain(recent.iter().copied().take(slo.runs - 1)).collect();
+        if let (Some(failed), None) = (above(&window), above(&recent)) {
+            anomalies.push(Anomaly::FailureRate { failed, runs: window.len() });
+        }
+    }
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::job::report::FailureClass;
+    use chrono::Utc;
+
+    fn run(secs: u64, outcome: RunOutcome) -> JobRunReport {
+        JobRunReport {
+            job_id: "etl".to_string(),
+            run_id: "20260101T000000Z-00000001".to_string(),
+            run_number: None,
+            commit: None,
+            started_at: Utc::now(),
+            duration: Duration::from_secs(secs),
+            attempts: 1,
+            outcome,
+            exit_code: None,
+            error: None,
+            failure_class: (outcome != RunOutcome::Success).then_some(FailureClass::Other),
+            fallback: false,
+            stdout_tail: String::new(),
+            stderr_tail: String::new(),
+            omitted_bytes: 0,
+            outside_writes: vec![],
+            imported: false,
+            timings: None,
+            trigger: None,
+            overrides: Vec::new(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    fn slo(duration_factor: Option<f64>, failure_rate: Option<f64>) -> SloConfig {
+        SloConfig { duration_factor, failure_rate, runs: 4 }
+    }
+
+    #[test]
+    fn slow_run_against_median() {
+        let slo = SloConfig { runs: 6, ..slo(Some(3.0), None) };
+        let history: Vec<JobRunReport> = [10, 12, 9, 11, 10, 300].map(|s| run(s, RunOutcome::Success)).into();
+        // The median (11s) ignores the single outlier
+        assert_eq!(detect(&slo, &run(30, RunOutcome::Success), &history), vec![]);
+        assert_eq!(
+            detect(&slo, &run(40, RunOutcome::Success), &history),
+            vec![Anomaly::Slow { duration: Duration::from_secs(40), median: Duration::from_secs(11) }]
+        );
+        // Too few successful runs to judge
+        assert_eq!(detect(&slo, &run(100, RunOutcome::Success), &history[..3]), vec![]);
+    }
+
+    #[test]
+    fn failure_rate_alerts_when_crossed() {
+        let slo = slo(None, Some(0.5));
+        let mut history = vec![run(1, RunOutcome::Success); 3];
+        history.push(run(1, RunOutcome::Failed));
+        // 2 of 4 is not above half
+        assert_eq!(detect(&slo, &run(1, RunOutcome::Failed), &history), vec![]);
+        history.push(run(1, RunOutcome::Failed));
+        let anomaly = detect(&slo, &run(1, RunOutcome::Failed), &history);
+        assert_eq!(anomaly, vec![Anomaly::FailureRate { failed: 3, runs: 4 }]);
+        assert_eq!(anomaly[0].to_string(), "3 of the last 4 runs failed");
+        // Already above: no repeat
+        history.push(run(1, RunOutcome::Failed));
+        assert_eq!(detect(&slo, &run(1, RunOutcome::Failed), &history), vec![]);
+    }
+
+    #[test]
+    fn degrading_when_retries_creep_up() {
+        let retried = |attempts| JobRunReport { attempts, ..run(1, RunOutcome::Success) };
+        let mut history: Vec<JobRunReport> = (0..10).map(|i| retried(if i == 0 { 2 } else { 1 })).collect();
+        history.extend((0..10).map(|i| retried(if i % 3 == 0 { 3 } else { 1 })));
+        // Failures don't count towards either window
+        history.push(run(1, RunOutcome::Failed));
+        let trend = degrading(&history).unwrap();
+        assert_eq!(trend, Degrading { retried: 4, runs: 10, previous_retried: 1, previous_runs: 10 });
+        assert_eq!(trend.to_string(), "4 of the last 10 successful runs needed a retry (1 of 10 before)");
+
+        history.truncate(20);
+        history[19].attempts = 1;
+        // 3 of 10 after 1 of 10 is the threshold
+        assert!(degrading(&history).is_some());
+        history[10].attempts = 1;
+        assert_eq!(degrading(&history), None);
+
+        // Steadily flaky is not degrading
+        let steady: Vec<JobRunReport> = (0..20).map(|i| retried(if i % 2 == 0 { 2 } else { 1 })).collect();
+        assert_eq!(degrading(&steady), None);
+        // Too little history to judge
+        assert_eq!(degrading(&steady[10..]), None);
+    }
+}
@@ -0,0 +1,102 @@
+//! `artifacts`: files a run wrote under its work dir that match the job's
+//! globs are copied to `<history>/artifacts/<run-id>/` (paths relative to the
+//! work dir kept), then handed to the optional `upload` command. Folders of
+//! all but the newest `keep` runs are removed.
+
+use crate::config::{self, ArtifactsConfig};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const ARTIFACTS_DIR: &str = "artifacts";
+
+/// File times come from a coarse clock and can trail the run's start
+const MTIME_SLACK: Duration = Duration::from_secs(1);
+
+/// The artifacts folder of run `run_id`.
+pub fn run_dir(history_dir: &Path, run_id: &str) -> PathBuf {
+    history_dir.join(ARTIFACTS_DIR).join(run_id)
+}
+
+/// Copies the files matching `config.paths` under `work_dir` that were
+/// modified at or after `since` into `dest`. Returns their relative paths.
+pub fn collect(config: &ArtifactsConfig, work_dir: &Path, dest: &Path, since: SystemTime) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for pattern in &config.paths {
+        files.extend(config::glob(work_dir, pattern)?);
+    }
+    files.sort();
+    files.dedup();
+
+    let since = since.checked_sub(MTIME_SLACK).unwrap_or(since);
+    let mut collected = Vec::new();
+    for file in files {
+        let modified = fs::metadata(&file).and_then(|m| m.modified());
+        if !modified.is_ok_and(|modified| modified >= since) {
+            continue;
+        }
+        let Ok(relative) = file.strip_prefix(work_dir) else {
+            continue;
+        };
+        let target = dest.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&file, &target).with_context(|| format!("Failed to copy {}", file.display()))?;
+        collected.push(relative.to_string_lossy().into_owned());
+    }
+    Ok(collected)
+}
+
+/// Removes the artifacts folders of all but the newest `keep` runs.
+pub fn prune(history_dir: &Path, keep: usize) -> Result<()> {
+    let Ok(entries) = fs::read_dir(history_dir.join(ARTIFACTS_DIR)) else {
+        return Ok(());
+    };
+    let mut runs: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).filter(|p| p.is_dir()).collect();
+    // Run IDs start with their start time
+    runs.sort();
+    runs.reverse();
+    for dir in runs.into_iter().skip(keep) {
+        fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_files_written_during_the_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let work_dir = dir.path().join("run");
+        fs::create_dir_all(work_dir.join("reports/daily")).unwrap();
+        let started = SystemTime::now();
+        let old = fs::File::create(work_dir.join("reports/old.csv")).unwrap();
+        old.set_modified(started - Duration::from_secs(3600)).unwrap();
+        fs::write(work_dir.join("reports/new.csv"), "a,b").unwrap();
+        fs::write(work_dir.join("reports/daily/sales.csv"), "c,d").unwrap();
+        fs::write(work_dir.join("reports/notes.txt"), "").unwrap();
+
+        let config = ArtifactsConfig {
+            paths: vec!["reports/*.csv".to_string(), "reports/**/*.csv".to_string()],
+            keep: 2,
+            upload: None,
+        };
+        let history = dir.path().join("history");
+        let dest = run_dir(&history, "20260101T000000Z-00000001");
+        let collected = collect(&config, &work_dir, &dest, started).unwrap();
+        assert_eq!(collected, ["reports/daily/sales.csv", "reports/new.csv"]);
+        assert_eq!(fs::read_to_string(dest.join("reports/daily/sales.csv")).unwrap(), "c,d");
+        assert!(!dest.join("reports/old.csv").exists());
+
+        for run_id in ["20260102T000000Z-00000002", "20260103T000000Z-00000003"] {
+            fs::create_dir_all(run_dir(&history, run_id)).unwrap();
+        }
+        prune(&history, config.keep).unwrap();
+        assert!(!dest.exists());
+        assert!(run_dir(&history, "20260103T000000Z-00000003").exists());
+    }
+}
@@ -0,0 +1,259 @@
+//! Audit mode: records file writes a job makes outside its workspace.
+//!
+//! The command runs under `strace -f -y`, tracing successful file syscalls
+//! into a temporary file. Afterwards, every path that was created, opened
+//! for writing, removed, renamed or re-permissioned is resolved and kept if
+//! it lies outside the allowed directories (job dir, history dir).
+
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Max paths recorded per run (the rest are counted in the last entry)
+const MAX_PATHS: usize = 200;
+
+/// Pseudo filesystems whose writes are never reported
+const IGNORED_PREFIXES: &[&str] = &["/dev/", "/proc/", "/sys/"];
+
+/// Returns true if `strace` is available.
+pub fn available() -> bool {
+    std::process::Command::new("strace")
+        .arg("-V")
+        .output()
+        .is_ok_and(|o| o.status.success())
+}
+
+/// Argv prefix running a command under strace, appending the trace to
+/// `trace_path` (so retries and fallback accumulate in one trace).
+pub fn strace_wrapper(trace_path: &Path) -> Vec<String> {
+    ["strace", "-f", "-qq", "-y", "-A", "-e", "trace=%file", "-e", "status=successful", "-o"]
+        .into_iter()
+        .map(String::from)
+        .chain([trace_path.display().to_string(), "--".to_string()])
+        .collect()
+}
+
+/// Temporary trace file, removed on drop.
+pub struct AuditTrace {
+    path: PathBuf,
+}
+
+impl AuditTrace {
+    pub fn new(job_id: &str) -> Self {
+        let suffix: u32 = rand::Rng::r#gen(&mut rand::thread_rng());
+        let path = std::env::temp_dir().join(format!("rollcron-audit-{}-{:08x}.trace", job_id, suffix));
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Paths written outside `allowed` (sorted, deduplicated, capped).
+    pub fn outside_writes(&self, work_dir: &Path, allowed: &[PathBuf]) -> Vec<String> {
+        let trace = std::fs::read_to_string(&self.path).unwrap_or_default();
+        outside_writes(&trace, work_dir, allowed)
+    }
+}
+
+impl Drop for AuditTrace {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn outside_writes(trace: &str, work_dir: &Path, allowed: &[PathBuf]) -> Vec<String> {
+    let paths: BTreeSet<String> = trace
+        .lines()
+        .flat_map(|line| written_paths(line, work_dir))
+        .filter(|p| !allowed.iter().any(|dir| p.starts_with(dir)))
+        .map(|p| p.display().to_string())
+        .filter(|p| !IGNORED_PREFIXES.iter().any(|prefix| p.starts_with(prefix)))
+        .collect();
+
+    let mut paths: Vec<String> = paths.into_iter().collect();
+    if paths.len() > MAX_PATHS {
+        let more = paths.len() - MAX_PATHS;
+        paths.truncate(MAX_PATHS);
+        paths.push(format!("... and {} more", more));
+    }
+    paths
+}
+
+/// Argument layout of a mutating file syscall: `(dirfd index, path index)` pairs.
+fn path_args(syscall: &str) -> Option<&'static [(Option<usize>, usize)]> {
+    Some(match syscall {
+        "creat" | "truncate" | "mkdir" | "rmdir" | "unlink" | "chmod" | "chown" | "lchown"
+        | "mknod" | "utime" | "utimes" => &[(None, 0)],
+        "mkdirat" | "unlinkat" | "fchmodat" | "fchmodat2" | "fchownat" | "mknodat" | "utimensat" => {
+            &[(Some(0), 1)]
+        }
+        "rename" => &[(None, 0), (None, 1)],
+        "renameat" | "renameat2" => &[(Some(0), 1), (Some(2), 3)],
+        "link" | "symlink" => &[(None, 1)],
+        "linkat" => &[(Some(2), 3)],
+        "symlinkat" => &[(Some(1), 2)],
+        _ => return None,
+    })
+}
+
+/// Paths a trace line wrote to. Lines look like
+/// `1234  openat(AT_FDCWD</work>, "out.txt", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 3</work/out.txt>`.
+fn written_paths(line: &str, work_dir: &Path) -> Vec<PathBuf> {
+    // Strip the PID prefix added by -f
+    let line = line.trim_start();
+    let line = line.split_once(char::is_whitespace).map_or(line, |(pid, rest)| {
+        if pid.chars().all(|c| c.is_ascii_digit()) { rest.trim_start() } else { line }
+    });
+    let Some((syscall, rest)) = line.split_once('(') else {
+        return Vec::new();
+    };
+    let args = split_args(rest);
+
+    let layout: &[(Option<usize>, usize)] = match syscall {
+        "open" if args.get(1).is_some_and(|f| is_write_flags(f)) => &[(None, 0)],
+        "openat" | "openat2" if args.get(2).is_some_and(|f| is_write_flags(f)) => &[(Some(0), 1)],
+        "open" | "openat" | "openat2" => return Vec::new(),
+        other => match path_args(other) {
+            Some(layout) => layout,
+            None => return Vec::new(),
+        },
+    };
+
+    layout
+        .iter()
+        .filter_map(|&(dirfd, path)| {
+            let path = parse_string(args.get(path)?)?;
+            let base = dirfd
+                .and_then(|i| args.get(i))
+                .and_then(|fd| fd_path(fd))
+                .unwrap_or_else(|| work_dir.to_path_buf());
+            Some(normalize(&base.join(path)))
+        })
+        .collect()
+}
+
+fn is_write_flags(flags: &str) -> bool {
+    ["O_WRONLY", "O_RDWR", "O_CREAT", "O_TRUNC"]
+        .iter()
+        .any(|f| flags.contains(f))
+}
+
+/// Splits the argument list at top-level commas, stopping at the closing paren.
+fn split_args(s: &str) -> Vec<&str> {
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '<' | '{' | '[' => depth += 1,
+            '>' | '}' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(s[start..i].trim());
+                start = i + 1;
+            }
+            ')' if depth == 0 => {
+                args.push(s[start..i].trim());
+                return args;
+            }
+            _ => {}
+        }
+    }
+    // Unfinished call: keep what we have
+    args.push(s[start..].trim());
+    args
+}
+
+/// Decodes a quoted strace string argument (NULL and truncated strings yield None).
+fn parse_string(arg: &str) -> Option<String> {
+    let inner = arg.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            other => out.push(other),
+        }
+    }
+    Some(out)
+}
+
+/// Extracts the path decoration `-y` adds to fds: `AT_FDCWD</work>` or `3</work>`.
+fn fd_path(arg: &str) -> Option<PathBuf> {
+    let start = arg.find('<')?;
+    let end = arg.rfind('>')?;
+    (start < end).then(|| PathBuf::from(&arg[start + 1..end]))
+}
+
+/// Lexically resolves `.` and `..` components.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+// strace is Linux-only and the fixtures use Unix paths
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    const TRACE: &str = r#"100  openat(AT_FDCWD</work/run>, "out.txt", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 3</work/run/out.txt>
+100  openat(AT_FDCWD</work/run>, "/etc/passwd", O_RDONLY|O_CLOEXEC) = 3</etc/passwd>
+100  openat(AT_FDCWD</work/run>, "/etc/app.conf", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 3</etc/app.conf>
+101  openat(AT_FDCWD</work/run>, "/dev/null", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 3</dev/null>
+101  mkdir("../../tmp/cache", 0777) = 0
+101  renameat2(AT_FDCWD</work/run>, "a", AT_FDCWD</var/lib>, "b, c", RENAME_NOREPLACE) = 0
+101  unlinkat(4</home/me>, ".lock", 0) = 0
+101  newfstatat(AT_FDCWD</work/run>, "/usr/bin/ls", {st_mode=S_IFREG|0755, ...}, 0) = 0
+"#;
+
+    #[test]
+    fn reports_writes_outside_allowed_dirs() {
+        let writes = outside_writes(TRACE, Path::new("/work/run"), &[PathBuf::from("/work")]);
+        assert_eq!(
+            writes,
+            vec!["/etc/app.conf", "/home/me/.lock", "/tmp/cache", "/var/lib/b, c"]
+        );
+    }
+
+    #[test]
+    fn split_args_respects_quotes_and_brackets() {
+        let args = split_args(r#"AT_FDCWD</a, b>, "x,\"y", {st_mode=S_IFREG, ...}, 0) = 0"#);
+        assert_eq!(args, vec!["AT_FDCWD</a, b>", r#""x,\"y""#, "{st_mode=S_IFREG, ...}", "0"]);
+        assert_eq!(parse_string(args[1]).unwrap(), "x,\"y");
+    }
+
+    #[test]
+    fn caps_number_of_paths() {
+        let trace: String = (0..MAX_PATHS + 5)
+            .map(|i| format!("1 creat(\"/out/{:04}\", 0644) = 3\n", i))
+            .collect();
+        let writes = outside_writes(&trace, Path::new("/work"), &[]);
+        assert_eq!(writes.len(), MAX_PATHS + 1);
+        assert_eq!(writes.last().unwrap(), "... and 5 more");
+    }
+}
@@ -0,0 +1,165 @@
+//! `circuit_breaker`: after `failures` consecutive failed runs the breaker
+//! opens and scheduled runs stop (`pause`) or thin out (`backoff`) until a run
+//! succeeds or it is reset (`ctl resume`, or the next commit). The count is
+//! kept beside the job's history so a restart doesn't close it.
+
+use crate::config::{BreakerAction, CircuitBreakerConfig};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+const BREAKER_FILE: &str = "breaker.json";
+
+/// Consecutive failed runs, absent while the job succeeds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    pub failures: u32,
+    /// When the breaker opened (None while below the threshold)
+    pub open_since: Option<DateTime<Utc>>,
+}
+
+/// How a finished run changed the breaker.
+#[derive(Debug, PartialEq)]
+pub enum Transition {
+    Opened { failures: u32 },
+    Closed,
+    None,
+}
+
+fn read(dir: &Path) -> Option<State> {
+    serde_json::from_slice(&std::fs::read(dir.join(BREAKER_FILE)).ok()?).ok()
+}
+
+/// The breaker's state in `dir` if it is open.
+pub fn open(dir: &Path) -> Option<State> {
+    read(dir).filter(|state| state.open_since.is_some())
+}
+
+/// Counts a run that finished at `now`: a success closes the breaker, a
+/// failure opens it once `config.failures` are consecutive.
+pub fn record(dir: &Path, config: &CircuitBreakerConfig, success: bool, now: DateTime<Utc>) -> Result<Transition> {
+    let path = dir.join(BREAKER_FILE);
+    let state = read(dir);
+    if success {
+        if state.is_none() {
+            return Ok(Transition::None);
+        }
+        std::fs::remove_file(&path)?;
+        let was_open = state.is_some_and(|state| state.open_since.is_some());
+        return Ok(if was_open { Transition::Closed } else { Transition::None });
+    }
+
+    let mut state = state.unwrap_or(State { failures: 0, open_since: None });
+    state.failures += 1;
+    let opened = state.open_since.is_none() && state.failures >= config.failures;
+    if opened {
+        state.open_since = Some(now);
+    }
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&path, serde_json::to_vec(&state)?)?;
+    Ok(if opened { Transition::Opened { failures: state.failures } } else { Transition::None })
+}
+
+/// Forgets the failures. Returns true if the breaker was open.
+pub fn reset(dir: &Path) -> Result<bool> {
+    let was_open = open(dir).is_some();
+    match std::fs::remove_file(dir.join(BREAKER_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(was_open),
+    }
+}
+
+/// Why an open breaker holds back a scheduled run at `now`, if it does.
+/// `last_started` is the start of the job's latest run.
+pub fn hold(
+    config: &CircuitBreakerConfig,
+    state: &State,
+    last_started: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    match config.action {
+        BreakerAction::Pause => Some(format!(
+            "circuit breaker open after {} consecutive failures; `rollcron ctl resume` resets it",
+            state.failures
+        )),
+        BreakerAction::Backoff => {
+            let delay = backoff_delay(config, state.failures);
+            let elapsed = last_started.map_or(Duration::MAX, |last| (now - last).to_std().unwrap_or_default());
+            (elapsed < delay).then(|| {
+                format!(
+                    "circuit breaker backing off after {} consecutive failures, next run allowed in {}s",
+                    state.failures,
+                    (delay - elapsed).as_secs().max(1)
+                )
+            })
+        }
+    }
+}
+
+/// `backoff` when the breaker opens, doubled for each failure since.
+fn backoff_delay(config: &CircuitBreakerConfig, failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(config.failures).min(31);
+    config.backoff.saturating_mul(1 << doublings).min(config.max_backoff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn config(action: BreakerAction) -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failures: 3,
+            action,
+            backoff: Duration::from_secs(600),
+            max_backoff: Duration::from_secs(3600),
+        }
+    }
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 0, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(BreakerAction::Pause);
+        assert_eq!(record(dir.path(), &config, false, at(0)).unwrap(), Transition::None);
+        assert_eq!(record(dir.path(), &config, true, at(1)).unwrap(), Transition::None);
+        assert_eq!(record(dir.path(), &config, false, at(2)).unwrap(), Transition::None);
+        assert_eq!(record(dir.path(), &config, false, at(3)).unwrap(), Transition::None);
+        assert!(open(dir.path()).is_none());
+        assert_eq!(record(dir.path(), &config, false, at(4)).unwrap(), Transition::Opened { failures: 3 });
+        assert_eq!(record(dir.path(), &config, false, at(5)).unwrap(), Transition::None);
+        assert_eq!(open(dir.path()), Some(State { failures: 4, open_since: Some(at(4)) }));
+
+        assert_eq!(record(dir.path(), &config, true, at(6)).unwrap(), Transition::Closed);
+        assert!(open(dir.path()).is_none());
+
+        for minute in 7..10 {
+            record(dir.path(), &config, false, at(minute)).unwrap();
+        }
+        assert!(reset(dir.path()).unwrap());
+        assert!(!reset(dir.path()).unwrap());
+        assert!(open(dir.path()).is_none());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let backoff = config(BreakerAction::Backoff);
+        let state = |failures| State { failures, open_since: Some(at(0)) };
+        assert_eq!(backoff_delay(&backoff, 3), Duration::from_secs(600));
+        assert_eq!(backoff_delay(&backoff, 4), Duration::from_secs(1200));
+        assert_eq!(backoff_delay(&backoff, 6), Duration::from_secs(3600));
+        assert_eq!(backoff_delay(&backoff, 100), Duration::from_secs(3600));
+
+        assert!(hold(&backoff, &state(3), Some(at(0)), at(9)).is_some());
+        assert!(hold(&backoff, &state(3), Some(at(0)), at(10)).is_none());
+        assert!(hold(&backoff, &state(4), Some(at(0)), at(10)).is_some());
+        assert!(hold(&backoff, &state(3), None, at(0)).is_none());
+        // Paused until reset, however long ago the last run was
+        assert!(hold(&config(BreakerAction::Pause), &state(3), Some(at(0)), at(59)).is_some());
+    }
+}
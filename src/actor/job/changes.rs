@@ -0,0 +1,81 @@
+//! `notify_on_output_change`: cron's MAILTO for monitoring scripts. The
+//! output of the job's latest successful run is kept beside its history; a
+//! successful run whose output differs sends `output_changed` with the lines
+//! added and removed.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::Path;
+
+const LAST_OUTPUT_FILE: &str = "last_output";
+
+/// Lines that appeared in or disappeared from the output, in output order.
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Keeps `output` as the latest successful run's. Returns how it differs
+/// from the previous one; None for the first run or unchanged lines.
+pub fn record(dir: &Path, output: &[u8]) -> Result<Option<Change>> {
+    let path = dir.join(LAST_OUTPUT_FILE);
+    let previous = std::fs::read(&path).ok();
+    if previous.as_deref() == Some(output) {
+        return Ok(None);
+    }
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&path, output)?;
+    let change = previous.map(|previous| diff(&String::from_utf8_lossy(&previous), &String::from_utf8_lossy(output)));
+    Ok(change.filter(|change| !change.added.is_empty() || !change.removed.is_empty()))
+}
+
+/// Compares the outputs as multisets of lines, so reordered lines don't count
+/// and a repeated line counts once per occurrence.
+fn diff(old: &str, new: &str) -> Change {
+    Change { added: missing_from(old, new), removed: missing_from(new, old) }
+}
+
+/// Lines of `b` left over after matching each line of `a` once.
+fn missing_from(a: &str, b: &str) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for line in a.lines() {
+        *counts.entry(line).or_default() += 1;
+    }
+    b.lines()
+        .filter(|line| match counts.get_mut(line) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_changed_output_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = dir.path().join("disk-check");
+        assert_eq!(record(&history, b"sda ok\nsdb ok\n").unwrap(), None);
+        assert_eq!(record(&history, b"sda ok\nsdb ok\n").unwrap(), None);
+        assert_eq!(
+            record(&history, b"sda ok\nsdb FAILING\nsdc ok\n").unwrap(),
+            Some(Change {
+                added: vec!["sdb FAILING".to_string(), "sdc ok".to_string()],
+                removed: vec!["sdb ok".to_string()],
+            })
+        );
+        // Compared with the latest output, not the first
+        assert_eq!(
+            record(&history, b"sdc ok\nsda ok\n").unwrap(),
+            Some(Change { added: vec![], removed: vec!["sdb FAILING".to_string()] })
+        );
+        assert_eq!(record(&history, b"sda ok\nsdc ok\n").unwrap(), None);
+    }
+}
@@ -0,0 +1,130 @@
+//! Failure classification: a coarse category for every failed run, so
+//! recurring failure modes can be counted (metrics) and routed (webhook
+//! `filter.failure_classes`).
+//!
+//! Heuristics, in order: the outcome (timeout, memory limit), the signal that
+//! ended the process, well-known error messages in the output, then the exit
+//! codes shells use for unusable commands.
+
+use super::report::{FailureClass, RunOutcome};
+use std::process::ExitStatus;
+
+/// Shell exit codes: command found but not executable, command not found
+const EXIT_NOT_EXECUTABLE: i32 = 126;
+const EXIT_NOT_FOUND: i32 = 127;
+
+const PERMISSION_PATTERNS: [&str; 6] = [
+    "permission denied",
+    "operation not permitted",
+    "access denied",
+    "access is denied",
+    "eacces",
+    "read-only file system",
+];
+
+const NETWORK_PATTERNS: [&str; 14] = [
+    "connection refused",
+    "connection reset",
+    "connection timed out",
+    "could not resolve host",
+    "name or service not known",
+    "temporary failure in name resolution",
+    "no such host",
+    "network is unreachable",
+    "no route to host",
+    "host is unreachable",
+    "tls handshake",
+    "ssl_connect",
+    "econnrefused",
+    "econnreset",
+];
+
+const NOT_FOUND_PATTERNS: [&str; 2] = ["command not found", "no such file or directory"];
+
+/// Category of a finished run; `None` on success. `status` is the exit status
+/// when the process ran to completion, `messages` the error and output tails.
+pub fn classify(outcome: RunOutcome, status: Option<&ExitStatus>, messages: &[&str]) -> Option<FailureClass> {
+    let class = match outcome {
+        RunOutcome::Success | RunOutcome::Skipped => return None,
+        RunOutcome::Timeout | RunOutcome::Stalled => FailureClass::Timeout,
+        RunOutcome::OomKilled => FailureClass::OomKill,
+        RunOutcome::Interrupted => FailureClass::Killed,
+        RunOutcome::Failed | RunOutcome::ExecError => match status.and_then(signal) {
+            // rollcron only SIGKILLs on timeout, classified above; the kernel
+            // OOM killer is the usual sender otherwise
+            Some(SIGKILL) => FailureClass::OomKill,
+            Some(_) => FailureClass::Killed,
+            None => from_output(status.and_then(ExitStatus::code), messages),
+        },
+    };
+    Some(class)
+}
+
+fn from_output(code: Option<i32>, messages: &[&str]) -> FailureClass {
+    let text = messages.join("\n").to_lowercase();
+    let mentions = |patterns: &[&str]| patterns.iter().any(|p| text.contains(p));
+    if mentions(&PERMISSION_PATTERNS) || code == Some(EXIT_NOT_EXECUTABLE) {
+        FailureClass::PermissionDenied
+    } else if mentions(&NETWORK_PATTERNS) {
+        FailureClass::Network
+    } else if code == Some(EXIT_NOT_FOUND) || (code.is_none() && mentions(&NOT_FOUND_PATTERNS)) {
+        FailureClass::CommandNotFound
+    } else {
+        FailureClass::Other
+    }
+}
+
+const SIGKILL: i32 = 9;
+
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn status(raw: i32) -> ExitStatus {
+        use std::os::unix::process::ExitStatusExt;
+        ExitStatus::from_raw(raw)
+    }
+
+    #[test]
+    fn outcome_decides_first() {
+        assert_eq!(classify(RunOutcome::Success, None, &["connection refused"]), None);
+        assert_eq!(classify(RunOutcome::Timeout, None, &[]), Some(FailureClass::Timeout));
+        assert_eq!(classify(RunOutcome::OomKilled, None, &[]), Some(FailureClass::OomKill));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signals_and_exit_codes() {
+        let failed = |raw, text| classify(RunOutcome::Failed, Some(&status(raw)), &[text]);
+        assert_eq!(failed(9, ""), Some(FailureClass::OomKill));
+        assert_eq!(failed(15, "permission denied"), Some(FailureClass::Killed));
+        assert_eq!(failed(126 << 8, ""), Some(FailureClass::PermissionDenied));
+        assert_eq!(failed(127 << 8, "sh: 1: deploy: not found"), Some(FailureClass::CommandNotFound));
+        assert_eq!(failed(1 << 8, "No such file or directory: data.csv"), Some(FailureClass::Other));
+        assert_eq!(failed(1 << 8, ""), Some(FailureClass::Other));
+    }
+
+    #[test]
+    fn output_patterns() {
+        let class = |text| classify(RunOutcome::Failed, None, &["exit code Some(1)", text]);
+        assert_eq!(class("curl: (6) Could not resolve host: example.com"), Some(FailureClass::Network));
+        assert_eq!(class("psql: error: connection refused"), Some(FailureClass::Network));
+        assert_eq!(class("rm: cannot remove '/etc/x': Permission denied"), Some(FailureClass::PermissionDenied));
+        assert_eq!(
+            classify(RunOutcome::ExecError, None, &["exec error: No such file or directory (os error 2)"]),
+            Some(FailureClass::CommandNotFound)
+        );
+    }
+}
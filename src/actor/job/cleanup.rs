@@ -0,0 +1,74 @@
+//! Retention for files a job produces (`cleanup`).
+//!
+//! After a successful run, files matching `cleanup.path` are sorted by
+//! modification time and all but the newest `cleanup.keep` are deleted.
+
+use crate::config::{self, CleanupConfig};
+use crate::env;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Deletes old files matching `cleanup.path`, resolved against `job_dir`
+/// unless absolute. Returns the deleted paths.
+pub fn prune(job_dir: &Path, cleanup: &CleanupConfig) -> Result<Vec<PathBuf>> {
+    let pattern = env::expand_string(&cleanup.path);
+    let root = if Path::new(&pattern).is_absolute() { Path::new("/") } else { job_dir };
+
+    let mut files: Vec<_> = config::glob(root, &pattern)?
+        .into_iter()
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((modified, path))
+        })
+        .collect();
+    // Newest first; equal times fall back to the path so the order is stable
+    files.sort_by(|a, b| b.cmp(a));
+
+    let mut removed = Vec::new();
+    for (_, path) in files.into_iter().skip(cleanup.keep) {
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        removed.push(path);
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn prune_keeps_newest_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("backups")).unwrap();
+        let now = SystemTime::now();
+        for (i, name) in ["a.tar", "b.tar", "c.tar", "d.tar"].iter().enumerate() {
+            let path = dir.path().join("backups").join(name);
+            let file = fs::File::create(&path).unwrap();
+            file.set_modified(now - Duration::from_secs(3600 * (4 - i as u64))).unwrap();
+        }
+        fs::write(dir.path().join("backups/notes.txt"), "").unwrap();
+
+        let cleanup = CleanupConfig {
+            path: "backups/*.tar".to_string(),
+            keep: 2,
+        };
+        let removed = prune(dir.path(), &cleanup).unwrap();
+        assert_eq!(removed, [dir.path().join("backups/b.tar"), dir.path().join("backups/a.tar")]);
+
+        let mut left: Vec<_> = fs::read_dir(dir.path().join("backups"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["c.tar", "d.tar", "notes.txt"]);
+
+        // Absolute patterns ignore the job directory
+        let absolute = CleanupConfig {
+            path: format!("{}/backups/*.tar", dir.path().display()),
+            keep: 1,
+        };
+        assert_eq!(prune(Path::new("/nonexistent"), &absolute).unwrap(), [dir.path().join("backups/c.tar")]);
+    }
+}
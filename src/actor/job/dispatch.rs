@@ -0,0 +1,186 @@
+//! Global run slots (`runner.max_concurrent_runs`).
+//!
+//! A run holds a slot from its first attempt until it finishes (retries and
+//! fallback included). When every slot is taken, runs wait for one in
+//! priority order, then in the order they arrived. Running jobs are never
+//! preempted.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::oneshot;
+
+#[derive(Default)]
+struct State {
+    limit: Option<usize>,
+    running: usize,
+    waiting: BinaryHeap<Waiter>,
+    next_seq: u64,
+}
+
+struct Waiter {
+    priority: i32,
+    seq: u64,
+    ready: oneshot::Sender<Slot>,
+}
+
+impl Ord for Waiter {
+    /// Higher priority first, then earlier arrival
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority).then(other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+/// Pool of run slots shared by every job.
+#[derive(Clone, Default)]
+pub struct RunSlots(Arc<Mutex<State>>);
+
+/// A taken slot, released on drop.
+pub struct Slot(RunSlots);
+
+/// A run waiting for a slot.
+pub struct Pending(oneshot::Receiver<Slot>);
+
+pub enum Acquire {
+    Ready(Slot),
+    Queued(Pending),
+}
+
+impl RunSlots {
+    /// The daemon-wide pool.
+    pub fn global() -> &'static RunSlots {
+        static SLOTS: OnceLock<RunSlots> = OnceLock::new();
+        SLOTS.get_or_init(Default::default)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Takes a slot or queues for one. `limit` is the current
+    /// `runner.max_concurrent_runs` and applies from this call on.
+    pub fn acquire(&self, priority: i32, limit: Option<usize>) -> Acquire {
+        let mut state = self.lock();
+        state.limit = limit;
+        self.grant(&mut state);
+        if state.waiting.is_empty() && state.has_free() {
+            state.running += 1;
+            return Acquire::Ready(Slot(self.clone()));
+        }
+        let (ready, rx) = oneshot::channel();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.waiting.push(Waiter { priority, seq, ready });
+        Acquire::Queued(Pending(rx))
+    }
+
+    /// Hands free slots to waiters.
+    fn grant(&self, state: &mut State) {
+        while state.has_free() {
+            let Some(waiter) = state.waiting.pop() else {
+                break;
+            };
+            state.running += 1;
+            if let Err(slot) = waiter.ready.send(Slot(self.clone())) {
+                // The run was cancelled while queued; its slot was never used
+                std::mem::forget(slot);
+                state.running -= 1;
+            }
+        }
+    }
+}
+
+impl State {
+    fn has_free(&self) -> bool {
+        self.limit.is_none_or(|limit| self.running < limit)
+    }
+}
+
+impl Pending {
+    pub async fn wait(self) -> Slot {
+        match self.0.await {
+            Ok(slot) => slot,
+            Err(_) => unreachable!("waiters are only removed by granting them a slot"),
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let slots = self.0.clone();
+        let mut state = slots.lock();
+        state.running = state.running.saturating_sub(1);
+        slots.grant(&mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ready(acquire: Acquire) -> Slot {
+        match acquire {
+            Acquire::Ready(slot) => slot,
+            Acquire::Queued(_) => panic!("expected a free slot"),
+        }
+    }
+
+    fn queued(acquire: Acquire) -> Pending {
+        match acquire {
+            Acquire::Ready(_) => panic!("expected to wait"),
+            Acquire::Queued(pending) => pending,
+        }
+    }
+
+    #[tokio::test]
+    async fn slots_are_granted_by_priority_then_arrival() {
+        let slots = RunSlots::default();
+        let first = ready(slots.acquire(0, Some(1)));
+
+        let low = queued(slots.acquire(-10, Some(1)));
+        let normal_a = queued(slots.acquire(0, Some(1)));
+        let high = queued(slots.acquire(10, Some(1)));
+        let normal_b = queued(slots.acquire(0, Some(1)));
+        let cancelled = queued(slots.acquire(20, Some(1)));
+        drop(cancelled);
+
+        drop(first);
+        let slot = high.wait().await;
+        drop(slot);
+        let slot = normal_a.wait().await;
+        drop(slot);
+        let slot = normal_b.wait().await;
+        drop(slot);
+        let slot = low.wait().await;
+        drop(slot);
+        assert_eq!(slots.lock().running, 0);
+
+        // Without a limit nothing waits
+        let _a = ready(slots.acquire(0, None));
+        let _b = ready(slots.acquire(0, None));
+    }
+
+    #[tokio::test]
+    async fn raising_the_limit_releases_waiters() {
+        let slots = RunSlots::default();
+        let _first = ready(slots.acquire(0, Some(1)));
+        let waiting = queued(slots.acquire(0, Some(1)));
+        let _third = ready(slots.acquire(0, Some(3)));
+        let _second = waiting.wait().await;
+        assert_eq!(slots.lock().running, 3);
+    }
+}
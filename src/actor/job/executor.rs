@@ -1,33 +1,58 @@
-use chrono::{Local, Utc};
+use anyhow::{anyhow, Context as _};
+use chrono::{DateTime, Local, Utc};
 use rand::Rng;
 use std::collections::HashMap;
 use std::fs::{self, File, OpenOptions};
 use std::io::Write;
-use std::path::{Path, PathBuf};
-use std::time::{Duration, Instant};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::process::Command;
 use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
-use crate::config::{Job, RetryConfig, RunnerConfig, TimezoneConfig};
+use super::alert::{self, Alert};
+use super::breaker::{self, Transition};
+use super::changes;
+use super::anomaly;
+use super::artifacts;
+use super::audit::{self, AuditTrace};
+use super::classify::classify;
+use super::cleanup;
+use super::dispatch::{Acquire, RunSlots};
+use super::identity::{self, RunAs};
+use super::limits::LimitScope;
+use super::live;
+use super::output::{capture, Activity, Captured, RunBuffer, Tap};
+use super::process;
+use super::sandbox::Sandbox;
+use super::security::Confinement;
+use super::report::{generate_run_id, tail, JobRunReport, PhaseTimings, RunOutcome, RunStart, OUTPUT_TAIL_BYTES};
+use crate::config::{
+    ArtifactsConfig, BreakerAction, CommandLine, Compression, Job, LogFlush, NotifyEvent, RetryConfig, RunnerConfig, Shell, TimezoneConfig,
+    WorkingDir,
+};
+use crate::clock;
 use crate::env;
+use crate::facts;
+use crate::events::{self, Event};
+use crate::export;
 use crate::git;
-use crate::webhook::{self, BuildFailure, JobFailure};
+use crate::history;
+use crate::otel::Span;
+use crate::secrets;
+use crate::webhook::{
+    self, BuildFailure, CircuitOpen, JobAnomaly, JobFailure, JobRecovered, JobSkipped, JobStart, OutputChanged,
+};
 
 /// Default jitter ratio when not explicitly configured (25% of base delay)
 const AUTO_JITTER_RATIO: u32 = 25;
 
-/// Grace period to wait after SIGTERM before sending SIGKILL
-const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
-
 /// Result of a build operation.
 #[derive(Debug)]
 pub enum BuildResult {
     Success,
     Failed {
-        #[allow(dead_code)]
         error: String,
-        #[allow(dead_code)]
         stderr: String,
     },
     NoBuild,
@@ -42,11 +67,10 @@ pub async fn execute_build(job: &Job, sot_path: &Path, runner: &RunnerConfig) ->
     };
 
     let build_dir = git::get_build_dir(sot_path, &job.id);
-    let job_dir = git::get_job_dir(sot_path, &job.id);
     let mut log_file = job
         .log_file
         .as_ref()
-        .and_then(|p| create_log_file(&job_dir, p, job.log_max_size));
+        .and_then(|p| create_log_file(job, &git::get_log_dir(sot_path, &job.id), p));
 
     info!(
         target: "rollcron::job",
@@ -60,15 +84,13 @@ pub async fn execute_build(job: &Job, sot_path: &Path, runner: &RunnerConfig) ->
     }
 
     let start_time = Instant::now();
-    let result = run_build_command(job, build_config, &build_dir, sot_path, runner).await;
+    let result = run_build_command(job, build_config, &build_dir, sot_path, runner, log_file.as_ref()).await;
     let duration = start_time.elapsed();
 
     match &result {
         BuildCommandResult::Completed(output) if output.status.success() => {
             info!(target: "rollcron::job", job_id = %job.id, "Build completed");
             if let Some(ref mut file) = log_file {
-                let _ = file.write_all(&output.stdout);
-                let _ = file.write_all(&output.stderr);
                 let marker = format!("Build finished (success) [{}]", format_duration(duration));
                 write_log_marker(file, &runner.timezone, job.timezone.as_ref(), &marker);
             }
@@ -84,8 +106,6 @@ pub async fn execute_build(job: &Job, sot_path: &Path, runner: &RunnerConfig) ->
             );
 
             if let Some(ref mut file) = log_file {
-                let _ = file.write_all(&output.stdout);
-                let _ = file.write_all(&output.stderr);
                 let marker = format!("Build finished (failed, exit code {:?}) [{}]", output.status.code(), format_duration(duration));
                 write_log_marker(file, &runner.timezone, job.timezone.as_ref(), &marker);
             }
@@ -95,17 +115,18 @@ pub async fn execute_build(job: &Job, sot_path: &Path, runner: &RunnerConfig) ->
                 let failure = BuildFailure {
                     job_id: &job.id,
                     job_name: &job.name,
+                    source: &job.source,
                     error: format!("exit code {:?}", output.status.code()),
                     stderr: stderr.clone(),
                 };
 
-                let runner_env = load_runner_env_vars(sot_path, runner);
-                for wh in &job.webhook {
+                let runner_env = env::load_runner_env(sot_path, runner).await;
+                for wh in job.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::BuildFailure, Some(job), runner)) {
                     let url = wh.to_url(runner_env.as_ref());
                     if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
                         continue;
                     }
-                    webhook::send_build_failure(&url, &failure).await;
+                    webhook::send_build_failure(&url, wh.template.as_deref(), &failure).await;
                 }
             }
 
@@ -127,17 +148,18 @@ pub async fn execute_build(job: &Job, sot_path: &Path, runner: &RunnerConfig) ->
                 let failure = BuildFailure {
                     job_id: &job.id,
                     job_name: &job.name,
+                    source: &job.source,
                     error: format!("exec error: {}", e),
                     stderr: String::new(),
                 };
 
-                let runner_env = load_runner_env_vars(sot_path, runner);
-                for wh in &job.webhook {
+                let runner_env = env::load_runner_env(sot_path, runner).await;
+                for wh in job.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::BuildFailure, Some(job), runner)) {
                     let url = wh.to_url(runner_env.as_ref());
                     if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
                         continue;
                     }
-                    webhook::send_build_failure(&url, &failure).await;
+                    webhook::send_build_failure(&url, wh.template.as_deref(), &failure).await;
                 }
             }
 
@@ -159,17 +181,18 @@ pub async fn execute_build(job: &Job, sot_path: &Path, runner: &RunnerConfig) ->
                 let failure = BuildFailure {
                     job_id: &job.id,
                     job_name: &job.name,
+                    source: &job.source,
                     error: format!("timeout after {:?}", build_config.timeout),
                     stderr: String::new(),
                 };
 
-                let runner_env = load_runner_env_vars(sot_path, runner);
-                for wh in &job.webhook {
+                let runner_env = env::load_runner_env(sot_path, runner).await;
+                for wh in job.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::BuildFailure, Some(job), runner)) {
                     let url = wh.to_url(runner_env.as_ref());
                     if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
                         continue;
                     }
-                    webhook::send_build_failure(&url, &failure).await;
+                    webhook::send_build_failure(&url, wh.template.as_deref(), &failure).await;
                 }
             }
 
@@ -187,27 +210,52 @@ async fn run_build_command(
     build_dir: &Path,
     sot_path: &Path,
     runner: &RunnerConfig,
+    spool: Option<&File>,
 ) -> BuildCommandResult {
-    let env_vars = match merge_env_vars_for_build(job, build_dir, sot_path, runner) {
+    if clock::stub_jobs() {
+        return BuildCommandResult::Completed(stub_output());
+    }
+
+    // Secret references and encrypted env files shell out to vault/aws/age/sops
+    let (owned_job, owned_build_dir, owned_sot_path, owned_runner) =
+        (job.clone(), build_dir.to_path_buf(), sot_path.to_path_buf(), runner.clone());
+    let env_vars = tokio::task::spawn_blocking(move || {
+        merge_env_vars_for_build(&owned_job, &owned_build_dir, &owned_sot_path, &owned_runner)
+    })
+    .await
+    .unwrap_or_else(|e| Err(anyhow::anyhow!("env task failed: {}", e)));
+    let env_vars = match env_vars {
         Ok(vars) => vars,
         Err(e) => {
             return BuildCommandResult::ExecError(format!("Failed to load environment: {}", e));
         }
     };
 
-    let work_dir = resolve_work_dir(build_dir, &job.id, &build_config.working_dir);
+    let work_dir = match resolve_work_dir(build_dir, build_config.working_dir.as_ref(), &runner.allowed_work_dirs) {
+        Ok(dir) => dir,
+        Err(e) => return BuildCommandResult::ExecError(format!("Invalid working_dir: {:#}", e)),
+    };
 
-    let mut cmd = Command::new("sh");
-    cmd.args(["-c", &build_config.command])
-        .current_dir(&work_dir)
+    let writable = [build_dir, work_dir.as_path()];
+    // `_scope` lives until the build exits
+    let Confined { mut cmd, limit_scope: _scope, account_env } =
+        match confined_command(job, &build_config.command, &work_dir, &writable, None) {
+            Ok(confined) => confined,
+            Err(e) => return BuildCommandResult::ExecError(e),
+        };
+    cmd.current_dir(&work_dir)
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped());
 
+    // Job env can still override them
+    for (key, value) in account_env {
+        cmd.env(key, value);
+    }
     for (key, value) in env_vars {
         cmd.env(key, value);
     }
 
-    let mut child = match cmd.spawn() {
+    let mut child = match process::spawn(&mut cmd) {
         Ok(c) => c,
         Err(e) => return BuildCommandResult::ExecError(e.to_string()),
     };
@@ -215,26 +263,8 @@ async fn run_build_command(
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
 
-    let stdout_task = tokio::spawn(async move {
-        match stdout {
-            Some(mut out) => {
-                let mut buf = Vec::new();
-                let _ = tokio::io::AsyncReadExt::read_to_end(&mut out, &mut buf).await;
-                buf
-            }
-            None => Vec::new(),
-        }
-    });
-    let stderr_task = tokio::spawn(async move {
-        match stderr {
-            Some(mut err) => {
-                let mut buf = Vec::new();
-                let _ = tokio::io::AsyncReadExt::read_to_end(&mut err, &mut buf).await;
-                buf
-            }
-            None => Vec::new(),
-        }
-    });
+    let stdout_task = tokio::spawn(capture(stdout, job.max_output, None, spool_handle(spool), None));
+    let stderr_task = tokio::spawn(capture(stderr, job.max_output, None, spool_handle(spool), None));
 
     let wait_result = tokio::time::timeout(build_config.timeout, child.wait()).await;
 
@@ -244,13 +274,13 @@ async fn run_build_command(
             let stderr = stderr_task.await.unwrap_or_default();
             BuildCommandResult::Completed(std::process::Output {
                 status,
-                stdout,
-                stderr,
+                stdout: stdout.data,
+                stderr: stderr.data,
             })
         }
         Ok(Err(e)) => BuildCommandResult::ExecError(e.to_string()),
         Err(_) => {
-            graceful_kill(&mut child, &job.id).await;
+            child.terminate(&job.id).await;
             BuildCommandResult::Timeout
         }
     }
@@ -274,30 +304,26 @@ fn merge_env_vars_for_build(
     if let Some(env_file_path) = &runner.env_file {
         let expanded = env::expand_string(env_file_path);
         let full_path = sot_path.join(&expanded);
-        let vars = env::load_env_from_path(&full_path)?;
+        let vars = secrets::load_env_file(&full_path, &runner.secrets)?;
         env_vars.extend(vars);
     }
 
-    // 2. Merge runner.env (with shell expansion on values)
+    // 2. Merge runner.env (shell-expanded; secret references resolved)
     if let Some(runner_env) = &runner.env {
-        for (k, v) in runner_env {
-            env_vars.insert(k.clone(), env::expand_string(v));
-        }
+        env::merge_env_values(&mut env_vars, runner_env)?;
     }
 
-    // 3. Merge job.env_file (loaded from build_dir)
-    if let Some(env_file_path) = &job.env_file {
+    // 3. Merge job.env_file, then job.env_files in order (loaded from build_dir)
+    for env_file_path in job.env_file.iter().chain(&job.env_files) {
         let expanded = env::expand_string(env_file_path);
         let full_path = build_dir.join(&expanded);
-        let vars = env::load_env_from_path(&full_path)?;
+        let vars = secrets::load_env_file(&full_path, &runner.secrets)?;
         env_vars.extend(vars);
     }
 
-    // 4. Merge job.env (with shell expansion on values)
+    // 4. Merge job.env (shell-expanded; secret references resolved)
     if let Some(job_env) = &job.env {
-        for (k, v) in job_env {
-            env_vars.insert(k.clone(), env::expand_string(v));
-        }
+        env::merge_env_values(&mut env_vars, job_env)?;
     }
 
     // 5. Merge build.env_file (loaded from build_dir)
@@ -305,32 +331,113 @@ fn merge_env_vars_for_build(
         if let Some(env_file_path) = &build.env_file {
             let expanded = env::expand_string(env_file_path);
             let full_path = build_dir.join(&expanded);
-            let vars = env::load_env_from_path(&full_path)?;
+            let vars = secrets::load_env_file(&full_path, &runner.secrets)?;
             env_vars.extend(vars);
         }
 
-        // 6. Merge build.env (with shell expansion on values)
+        // 6. Merge build.env (shell-expanded; secret references resolved)
         if let Some(build_env) = &build.env {
-            for (k, v) in build_env {
-                env_vars.insert(k.clone(), env::expand_string(v));
-            }
+            env::merge_env_values(&mut env_vars, build_env)?;
         }
     }
 
     Ok(env_vars)
 }
 
-pub async fn execute_job(job: &Job, sot_path: &Path, runner: &RunnerConfig) -> bool {
+pub async fn execute_job(job: &Job, sot_path: &Path, runner: &RunnerConfig, start: &RunStart) -> JobRunReport {
     let run_dir = git::get_run_dir(sot_path, &job.id);
     let job_dir = git::get_job_dir(sot_path, &job.id);
-    let work_dir = resolve_work_dir(&run_dir, &job.id, &job.working_dir);
+    let log_dir = git::get_log_dir(sot_path, &job.id);
     let mut log_file = job
         .log_file
         .as_ref()
-        .and_then(|p| create_log_file(&job_dir, p, job.log_max_size));
+        .and_then(|p| create_log_file(job, &log_dir, p));
+
+    let mut timings = PhaseTimings::default();
+    let queue_start = Instant::now();
+    let _slot = match RunSlots::global().acquire(job.priority, runner.max_concurrent_runs) {
+        Acquire::Ready(slot) => slot,
+        Acquire::Queued(pending) => {
+            info!(target: "rollcron::job", job_id = %job.id, priority = job.priority, "Waiting for a free run slot");
+            pending.wait().await
+        }
+    };
+    timings.queue_wait = queue_start.elapsed();
+
+    let started_at = clock::now();
+    let run_start = Instant::now();
+    // File times are real even under a simulated clock
+    let started_wall = SystemTime::now();
+    live::begin(&job.id, started_at);
+    let run_id = generate_run_id(started_at);
+    let commit = git::run_commit(sot_path, &job.id);
+    events::emit(Event::RunStarted { job_id: &job.id, run_id: &run_id });
+    // With `log.flush: run`, the log file only receives the finished run
+    let mut run_buffer = None;
+    if let (LogFlush::Run, Some(log)) = (job.log_flush, log_file.take()) {
+        match RunBuffer::create(&log_dir, &run_id) {
+            Ok(mut buffer) => {
+                log_file = buffer.file().try_clone().ok();
+                run_buffer = Some((buffer, log));
+            }
+            Err(e) => {
+                warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to buffer run output, streaming it");
+                log_file = Some(log);
+            }
+        }
+    }
+    let history_dir = git::get_history_dir(sot_path, &job.id);
+    let run_number = match history::next_run_number(&history_dir) {
+        Ok(number) => Some(number),
+        Err(e) => {
+            warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to allocate run number");
+            None
+        }
+    };
+    if let Err(e) = history::mark_running(&history_dir, &job.id, &run_id, run_number, started_at) {
+        warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to mark run in progress");
+    }
+    let mut run_span = Span::root("job.run").with_attr("job.id", job.id.as_str()).with_attr("job.name", job.name.as_str());
+    let audit_trace = match job.audit {
+        true if audit::available() => Some(AuditTrace::new(&job.id)),
+        true => {
+            warn!(target: "rollcron::job", job_id = %job.id, "Audit enabled but strace not found, running unaudited");
+            None
+        }
+        false => None,
+    };
+    let trace_path = audit_trace.as_ref().map(AuditTrace::path);
+    let context = RunContext {
+        run_dir: &run_dir,
+        trace_path,
+        spool: None,
+        run_number,
+        commit: commit.as_deref(),
+        extra_env: &[],
+    };
 
-    let max_attempts = job.retry.as_ref().map(|r| r.max + 1).unwrap_or(1);
+    // An unmet `condition` skips the command; one that can't be checked fails the run
+    let mut unmet = None;
     let mut last_result: Option<CommandResult> = None;
+    if let Some(condition) = &job.condition {
+        match check_condition(job, condition, context, sot_path, runner).await {
+            Ok(None) => {}
+            Ok(Some(reason)) => unmet = Some(reason),
+            Err(e) => last_result = Some(CommandResult::ExecError(format!("condition: {:#}", e))),
+        }
+    }
+    let runs = unmet.is_none() && last_result.is_none();
+    if let (Some(reason), Some(file)) = (&unmet, log_file.as_mut()) {
+        write_log_marker(file, &runner.timezone, job.timezone.as_ref(), &format!("Job skipped: {}", reason));
+    }
+    if runs && job.notify_on_start {
+        notify_started(job, sot_path, runner, started_at, &run_span).await;
+        timings.notify_start = run_start.elapsed();
+    }
+    let max_attempts = if runs { job.retry.as_ref().map(|r| r.max + 1).unwrap_or(1) } else { 0 };
+    let mut attempts = 0;
+    let mut success = false;
+    let mut last_omitted = 0;
 
     for attempt in 0..max_attempts {
         if attempt > 0 {
@@ -344,7 +451,16 @@ pub async fn execute_job(job: &Job, sot_path: &Path, runner: &RunnerConfig) -> b
                     delay = ?delay,
                     "Retrying"
                 );
+                events::emit(Event::RunRetry {
+                    job_id: &job.id,
+                    run_id: &run_id,
+                    attempt: attempt + 1,
+                    delay_ms: delay.as_millis() as u64,
+                });
+                let _wait_span = run_span.child("job.retry_wait").with_attr("retry.delay_ms", delay.as_millis() as i64);
+                let wait_start = Instant::now();
                 sleep(delay).await;
+                timings.retry_wait += wait_start.elapsed();
             }
         }
 
@@ -353,133 +469,747 @@ pub async fn execute_job(job: &Job, sot_path: &Path, runner: &RunnerConfig) -> b
             job_id = %job.id,
             name = %job.name,
             command = %job.command,
+            commit = commit.as_deref().map_or("unknown", git::short_sha),
             "Starting job"
         );
 
         if let Some(ref mut file) = log_file {
-            let marker = if attempt > 0 {
+            let mut marker = if attempt > 0 {
                 format!("Job started (retry {}/{})", attempt, max_attempts - 1)
             } else {
                 "Job started".to_string()
             };
+            if let Some(commit) = &commit {
+                marker.push_str(&format!(" at {}", git::short_sha(commit)));
+            }
             write_log_marker(file, &runner.timezone, job.timezone.as_ref(), &marker);
         }
 
+        let mut attempt_span = run_span.child("job.attempt").with_attr("job.attempt", attempt + 1);
         let start_time = Instant::now();
-        let result = run_command(job, &work_dir, sot_path, runner).await;
+        let (result, stats) =
+            run_command(job, &job.command, RunContext { spool: log_file.as_ref(), ..context }, sot_path, runner).await;
         let duration = start_time.elapsed();
-        let success = handle_result(job, &result, log_file.as_mut(), &runner.timezone, duration);
+        add_attempt(&mut timings, duration, &stats);
+        trace_result(&mut attempt_span, job, &result);
+        drop(attempt_span);
+        success = handle_result(job, &result, log_file.as_mut(), &runner.timezone, duration);
+        attempts = attempt + 1;
+        last_result = Some(result);
+        last_omitted = stats.omitted;
 
         if success {
-            return true;
+            break;
         }
 
-        last_result = Some(result);
-
         if attempt + 1 < max_attempts {
             debug!(target: "rollcron::job", job_id = %job.id, "Will retry...");
         }
     }
 
-    // All retries exhausted - send webhook notifications if configured
-    if !job.webhook.is_empty() {
-        let (error, stderr) = match &last_result {
-            Some(CommandResult::Completed(output)) => {
-                let err = format!("exit code {:?}", output.status.code());
-                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                (err, stderr)
-            }
-            Some(CommandResult::ExecError(e)) => (format!("exec error: {}", e), String::new()),
-            Some(CommandResult::Timeout) => {
-                (format!("timeout after {:?}", job.timeout), String::new())
-            }
-            None => ("unknown error".to_string(), String::new()),
-        };
+    // Primary command exhausted its retries - run the fallback once
+    let mut fallback = false;
+    if let (false, Some(fallback_command)) = (success, job.fallback_command.as_ref().filter(|_| runs)) {
+        warn!(
+            target: "rollcron::job",
+            job_id = %job.id,
+            command = %fallback_command,
+            "Primary command failed, running fallback"
+        );
 
-        let failure = JobFailure {
-            job_id: &job.id,
-            job_name: &job.name,
-            error,
-            stderr,
-            attempts: max_attempts,
-        };
+        if let Some(ref mut file) = log_file {
+            write_log_marker(file, &runner.timezone, job.timezone.as_ref(), "Fallback started");
+        }
+
+        let mut fallback_span = run_span.child("job.fallback");
+        let start_time = Instant::now();
+        let (result, stats) =
+            run_command(job, fallback_command, RunContext { spool: log_file.as_ref(), ..context }, sot_path, runner).await;
+        let duration = start_time.elapsed();
+        add_attempt(&mut timings, duration, &stats);
+        trace_result(&mut fallback_span, job, &result);
+        drop(fallback_span);
+        handle_result(job, &result, log_file.as_mut(), &runner.timezone, duration);
+        last_result = Some(result);
+        last_omitted = stats.omitted;
+        fallback = true;
+    }
+
+    if let Some((buffer, mut log)) = run_buffer {
+        if let Err(e) = buffer.flush_into(&mut log) {
+            warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to write buffered output to the log file");
+        }
+    }
+    live::finish(&job.id);
+    let mut report = build_report(job, started_at, run_start.elapsed(), attempts, last_result.as_ref());
+    report.run_id = run_id;
+    report.run_number = run_number;
+    report.commit = commit.clone();
+    report.fallback = fallback;
+    report.omitted_bytes = last_omitted;
+    report.trigger = Some(start.trigger);
+    report.overrides = start.overrides.clone();
+    if let Some(reason) = unmet {
+        info!(target: "rollcron::job", job_id = %job.id, reason = %reason, "Skipped, condition not met");
+        report.outcome = RunOutcome::Skipped;
+        report.error = Some(reason);
+        report.failure_class = None;
+    }
+    let skipped = report.outcome == RunOutcome::Skipped;
+    run_span.set_attr("run.id", report.run_id.as_str());
+    run_span.set_attr("run.outcome", report.outcome.as_str());
+    run_span.set_attr("run.attempts", report.attempts);
+    if let Some(error) = &report.error {
+        run_span.fail(error);
+    }
+
+    if let Some(config) = job.artifacts.as_ref().filter(|_| !skipped) {
+        report.artifacts =
+            collect_artifacts(job, config, context, &history_dir, &report.run_id, started_wall, sot_path, runner).await;
+    }
 
-        let runner_env = load_runner_env_vars(sot_path, runner);
-        for wh in &job.webhook {
-            let url = wh.to_url(runner_env.as_ref());
-            if url.contains('$') {
-                warn!(
-                    target: "rollcron::webhook",
+    if let Some(trace) = &audit_trace {
+        let allowed = [git::get_job_dir(sot_path, &job.id), history_dir.clone(), log_dir];
+        let work_dir = resolve_work_dir(&run_dir, job.working_dir.as_ref(), &runner.allowed_work_dirs).unwrap_or(run_dir);
+        report.outside_writes = trace.outside_writes(&work_dir, &allowed);
+        if !report.outside_writes.is_empty() {
+            warn!(
+                target: "rollcron::job",
+                job_id = %job.id,
+                count = report.outside_writes.len(),
+                "Job wrote outside its workspace"
+            );
+        }
+    }
+    let alert = if skipped {
+        Alert::None
+    } else if !job.dedupe_alerts {
+        if report.is_success() { Alert::None } else { Alert::Failure }
+    } else {
+        alert::update(&history_dir, report.is_success(), clock::now(), job.renotify_after).unwrap_or_else(|e| {
+            warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to update alert state");
+            if report.is_success() { Alert::None } else { Alert::Failure }
+        })
+    };
+    if report.is_success() {
+        if let Some(cleanup_config) = &job.cleanup {
+            match cleanup::prune(&job_dir, cleanup_config) {
+                Ok(removed) if !removed.is_empty() => {
+                    info!(target: "rollcron::job", job_id = %job.id, count = removed.len(), "Removed old files (cleanup)");
+                }
+                Ok(_) => {}
+                Err(e) => warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Cleanup failed"),
+            }
+        }
+    }
+    let notify_start = Instant::now();
+    match alert {
+        // All retries exhausted - send webhook notifications if configured
+        Alert::Failure => notify_failure(job, sot_path, runner, &report, &run_span).await,
+        Alert::Recovered { failing_since, failures } => {
+            info!(target: "rollcron::job", job_id = %job.id, failures, "Job recovered");
+            notify_recovered(job, sot_path, runner, &report.run_id, failing_since, failures).await;
+        }
+        Alert::None if !report.is_success() && !skipped => {
+            debug!(target: "rollcron::job", job_id = %job.id, "Still failing, notification deduplicated");
+        }
+        Alert::None => {}
+    }
+    let output = combined_output(last_result.as_ref());
+    if job.notify_on_output_change && report.is_success() {
+        match changes::record(&history_dir, &output) {
+            Ok(Some(change)) => {
+                info!(
+                    target: "rollcron::job",
                     job_id = %job.id,
-                    url = %url,
-                    "Webhook URL contains unexpanded variable, skipping"
+                    added = change.added.len(),
+                    removed = change.removed.len(),
+                    "Output changed"
                 );
-                continue;
+                events::emit(Event::OutputChanged {
+                    job_id: &job.id,
+                    run_id: &report.run_id,
+                    added: change.added.len(),
+                    removed: change.removed.len(),
+                });
+                notify_output_changed(job, sot_path, runner, &report.run_id, &change).await;
             }
-            if !url.starts_with("http://") && !url.starts_with("https://") {
-                warn!(
-                    target: "rollcron::webhook",
+            Ok(None) => {}
+            Err(e) => warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to compare output with the previous run"),
+        }
+    }
+    if let Some(config) = job.circuit_breaker.as_ref().filter(|_| !skipped) {
+        match breaker::record(&history_dir, config, report.is_success(), clock::now()) {
+            Ok(Transition::Opened { failures }) => {
+                error!(
+                    target: "rollcron::job",
                     job_id = %job.id,
-                    url = %url,
-                    "Webhook URL must start with http:// or https://, skipping"
+                    failures,
+                    action = ?config.action,
+                    "Circuit breaker opened"
                 );
-                continue;
+                events::emit(Event::CircuitOpened { job_id: &job.id, failures });
+                notify_circuit_open(job, sot_path, runner, &report.run_id, failures).await;
             }
-            webhook::send_job_failure(&url, &failure).await;
+            Ok(Transition::Closed) => {
+                info!(target: "rollcron::job", job_id = %job.id, "Circuit breaker closed");
+                events::emit(Event::CircuitClosed { job_id: &job.id });
+            }
+            Ok(Transition::None) => {}
+            Err(e) => warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to update circuit breaker"),
+        }
+    }
+    timings.notify = notify_start.elapsed();
+    if let Some(slo) = &job.slo {
+        let previous = history::read_runs(&history_dir).unwrap_or_default();
+        let anomalies = anomaly::detect(slo, &report, &previous);
+        let notify_start = Instant::now();
+        for anomaly in &anomalies {
+            let message = anomaly.to_string();
+            warn!(target: "rollcron::job", job_id = %job.id, run_id = %report.run_id, kind = anomaly.kind(), "Run anomaly: {}", message);
+            events::emit(Event::RunAnomaly { job_id: &job.id, run_id: &report.run_id, kind: anomaly.kind(), message: &message });
+            notify_anomaly(job, sot_path, runner, &report.run_id, message).await;
         }
+        timings.notify += notify_start.elapsed();
+    }
+    run_span.set_attr("run.queue_wait_ms", timings.queue_wait.as_millis() as i64);
+    run_span.set_attr("run.overhead_ms", timings.overhead().as_millis() as i64);
+    report.timings = Some(timings);
+    events::emit(Event::run_finished(&report));
+
+    match history::record(&history_dir, &report, &output, job.log_compress, job.log_compress_level) {
+        Ok(()) => export::record(runner.export.as_ref(), &report),
+        Err(e) => warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to record run history"),
     }
 
-    false
+    report
 }
 
-fn resolve_work_dir(base_dir: &Path, job_id: &str, working_dir: &Option<String>) -> PathBuf {
-    match working_dir {
-        Some(dir) => {
-            let expanded = env::expand_string(dir);
-            let work_path = base_dir.join(&expanded);
-            match (work_path.canonicalize(), base_dir.canonicalize()) {
-                (Ok(resolved), Ok(base)) if resolved.starts_with(&base) => resolved,
-                _ => {
-                    warn!(
-                        target: "rollcron::job",
-                        job_id = %job_id,
-                        working_dir = %dir,
-                        "Invalid working_dir: path traversal or non-existent"
-                    );
-                    base_dir.to_path_buf()
-                }
+/// Copies the run's artifacts into its folder and runs `artifacts.upload`
+/// on it. Returns the collected files; failures are logged.
+#[allow(clippy::too_many_arguments)]
+async fn collect_artifacts(
+    job: &Job,
+    config: &ArtifactsConfig,
+    context: RunContext<'_>,
+    history_dir: &Path,
+    run_id: &str,
+    since: SystemTime,
+    sot_path: &Path,
+    runner: &RunnerConfig,
+) -> Vec<String> {
+    let Ok(work_dir) = resolve_work_dir(context.run_dir, job.working_dir.as_ref(), &runner.allowed_work_dirs) else {
+        return Vec::new();
+    };
+    let dir = artifacts::run_dir(history_dir, run_id);
+    let collected = match artifacts::collect(config, &work_dir, &dir, since) {
+        Ok(collected) => collected,
+        Err(e) => {
+            warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to collect artifacts");
+            return Vec::new();
+        }
+    };
+    if let Err(e) = artifacts::prune(history_dir, config.keep) {
+        warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to remove old artifacts");
+    }
+    if collected.is_empty() {
+        return collected;
+    }
+    info!(target: "rollcron::job", job_id = %job.id, count = collected.len(), dir = %dir.display(), "Collected artifacts");
+    if let Some(command) = &config.upload {
+        match upload_artifacts(job, command, &dir, context, run_id, sot_path, runner).await {
+            Ok(()) => info!(target: "rollcron::job", job_id = %job.id, "Uploaded artifacts"),
+            Err(e) => warn!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to upload artifacts"),
+        }
+    }
+    collected
+}
+
+/// Runs `command` the way the job's command runs (same account, confinement,
+/// limits and stubbing), with `ROLLCRON_ARTIFACTS_DIR` and `ROLLCRON_RUN_ID`.
+async fn upload_artifacts(
+    job: &Job,
+    command: &str,
+    dir: &Path,
+    context: RunContext<'_>,
+    run_id: &str,
+    sot_path: &Path,
+    runner: &RunnerConfig,
+) -> anyhow::Result<()> {
+    let extra_env = [("ROLLCRON_ARTIFACTS_DIR", dir.display().to_string()), ("ROLLCRON_RUN_ID", run_id.to_string())];
+    // The audit trace belongs to the command's last attempt
+    let context = RunContext { trace_path: None, spool: None, extra_env: &extra_env, ..context };
+    let (result, _) = run_command(job, &CommandLine::Line(command.to_string()), context, sot_path, runner).await;
+    match result {
+        CommandResult::Completed(output) if output.status.success() => Ok(()),
+        CommandResult::Completed(output) => anyhow::bail!(
+            "exit code {:?}: {}",
+            output.status.code(),
+            tail(&output.stderr, OUTPUT_TAIL_BYTES).trim_end()
+        ),
+        CommandResult::OomKilled(_) => anyhow::bail!("{}", oom_message(job)),
+        CommandResult::ExecError(e) => anyhow::bail!("{}", e),
+        CommandResult::Timeout => anyhow::bail!("timeout after {:?}", job.timeout),
+        CommandResult::Stalled { .. } => anyhow::bail!("{}", stall_message(job)),
+    }
+}
+
+/// Runs `condition` the way the command runs (same account, confinement,
+/// limits and stubbing). Returns why the run is skipped when it exits nonzero.
+async fn check_condition(
+    job: &Job,
+    condition: &str,
+    context: RunContext<'_>,
+    sot_path: &Path,
+    runner: &RunnerConfig,
+) -> anyhow::Result<Option<String>> {
+    let (result, _) = run_command(job, &CommandLine::Line(condition.to_string()), context, sot_path, runner).await;
+    let output = match result {
+        CommandResult::Completed(output) => output,
+        CommandResult::OomKilled(_) => anyhow::bail!("{}", oom_message(job)),
+        CommandResult::ExecError(e) => anyhow::bail!("{}", e),
+        CommandResult::Timeout => anyhow::bail!("timeout after {:?}", job.timeout),
+        CommandResult::Stalled { .. } => anyhow::bail!("{}", stall_message(job)),
+    };
+    if output.status.success() {
+        return Ok(None);
+    }
+    let mut reason = match output.status.code() {
+        Some(code) => format!("condition exited with {}", code),
+        None => "condition was killed by a signal".to_string(),
+    };
+    let stderr = tail(&output.stderr, OUTPUT_TAIL_BYTES);
+    if !stderr.trim().is_empty() {
+        reason.push_str(&format!(": {}", stderr.trim_end()));
+    }
+    Ok(Some(reason))
+}
+
+/// Sends the failure notification to every webhook whose filter accepts it.
+async fn notify_failure(job: &Job, sot_path: &Path, runner: &RunnerConfig, report: &JobRunReport, run_span: &Span) {
+    if job.webhook.is_empty() {
+        return;
+    }
+    let failure = JobFailure {
+        job_id: &job.id,
+        job_name: &job.name,
+        source: &job.source,
+        run_id: &report.run_id,
+        started_at: report.started_at,
+        duration: report.duration,
+        exit_code: report.exit_code,
+        error: report.error.clone().unwrap_or_else(|| "unknown error".to_string()),
+        stdout: report.stdout_tail.clone(),
+        stderr: report.stderr_tail.clone(),
+        attempts: report.attempts,
+        class: report.failure_class,
+        commit: report.commit.as_deref(),
+    };
+
+    let runner_env = env::load_runner_env(sot_path, runner).await;
+    for wh in job.webhook.iter().filter(|wh| webhook::accepts_failure(wh, job, runner, report.failure_class)) {
+        let url = wh.to_url(runner_env.as_ref());
+        if url.contains('$') {
+            warn!(
+                target: "rollcron::webhook",
+                job_id = %job.id,
+                url = %url,
+                "Webhook URL contains unexpanded variable, skipping"
+            );
+            continue;
+        }
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            warn!(
+                target: "rollcron::webhook",
+                job_id = %job.id,
+                url = %url,
+                "Webhook URL must start with http:// or https://, skipping"
+            );
+            continue;
+        }
+        let mut notify_span = run_span.child("notify.webhook");
+        if !webhook::send_job_failure(&url, wh.template.as_deref(), &failure).await {
+            notify_span.fail("delivery failed");
+        }
+    }
+}
+
+/// Sends the `job_recovered` notification to every webhook whose filter accepts it.
+async fn notify_recovered(
+    job: &Job,
+    sot_path: &Path,
+    runner: &RunnerConfig,
+    run_id: &str,
+    failing_since: DateTime<Utc>,
+    failures: u32,
+) {
+    let recovered = JobRecovered {
+        job_id: &job.id,
+        job_name: &job.name,
+        run_id,
+        failing_since,
+        failures,
+    };
+    let runner_env = env::load_runner_env(sot_path, runner).await;
+    for wh in job.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::JobRecovered, Some(job), runner)) {
+        let url = wh.to_url(runner_env.as_ref());
+        if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
+            continue;
+        }
+        webhook::send_job_recovered(&url, wh.template.as_deref(), &recovered).await;
+    }
+}
+
+/// Sends the `circuit_open` notification to every webhook whose filter accepts it.
+async fn notify_circuit_open(job: &Job, sot_path: &Path, runner: &RunnerConfig, run_id: &str, failures: u32) {
+    let action = match job.circuit_breaker.as_ref().map(|c| c.action) {
+        Some(BreakerAction::Backoff) => "backoff",
+        _ => "pause",
+    };
+    let open = CircuitOpen {
+        job_id: &job.id,
+        job_name: &job.name,
+        run_id,
+        failures,
+        action,
+    };
+    let runner_env = env::load_runner_env(sot_path, runner).await;
+    for wh in job.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::CircuitOpen, Some(job), runner)) {
+        let url = wh.to_url(runner_env.as_ref());
+        if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
+            continue;
+        }
+        webhook::send_circuit_open(&url, wh.template.as_deref(), &open).await;
+    }
+}
+
+/// Sends the `output_changed` notification to every webhook whose filter accepts it.
+async fn notify_output_changed(job: &Job, sot_path: &Path, runner: &RunnerConfig, run_id: &str, change: &changes::Change) {
+    let changed = OutputChanged {
+        job_id: &job.id,
+        job_name: &job.name,
+        run_id,
+        added: &change.added,
+        removed: &change.removed,
+    };
+    let runner_env = env::load_runner_env(sot_path, runner).await;
+    for wh in job.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::OutputChanged, Some(job), runner)) {
+        let url = wh.to_url(runner_env.as_ref());
+        if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
+            continue;
+        }
+        webhook::send_output_changed(&url, wh.template.as_deref(), &changed).await;
+    }
+}
+
+/// Sends the `job_anomaly` notification to every webhook whose filter accepts it.
+async fn notify_anomaly(job: &Job, sot_path: &Path, runner: &RunnerConfig, run_id: &str, message: String) {
+    let anomaly = JobAnomaly {
+        job_id: &job.id,
+        job_name: &job.name,
+        run_id,
+        message,
+    };
+    let runner_env = env::load_runner_env(sot_path, runner).await;
+    for wh in job.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::JobAnomaly, Some(job), runner)) {
+        let url = wh.to_url(runner_env.as_ref());
+        if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
+            continue;
+        }
+        webhook::send_job_anomaly(&url, wh.template.as_deref(), &anomaly).await;
+    }
+}
+
+/// Sends the `job_started` notification to every webhook whose filter accepts it.
+async fn notify_started(job: &Job, sot_path: &Path, runner: &RunnerConfig, started_at: DateTime<Utc>, run_span: &Span) {
+    let start = JobStart {
+        job_id: &job.id,
+        job_name: &job.name,
+        started_at,
+        expected_duration: job.expected_duration,
+    };
+    let runner_env = env::load_runner_env(sot_path, runner).await;
+    for wh in job.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::JobStarted, Some(job), runner)) {
+        let url = wh.to_url(runner_env.as_ref());
+        if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
+            continue;
+        }
+        let mut notify_span = run_span.child("notify.webhook");
+        if !webhook::send_job_started(&url, wh.template.as_deref(), &start).await {
+            notify_span.fail("delivery failed");
+        }
+    }
+}
+
+/// Sends the `job_skipped` notification to every webhook whose filter accepts it.
+pub(super) async fn notify_skipped(job: &Job, sot_path: &Path, runner: &RunnerConfig, reason: &str) {
+    let skipped = JobSkipped {
+        job_id: &job.id,
+        job_name: &job.name,
+        reason,
+    };
+    let runner_env = env::load_runner_env(sot_path, runner).await;
+    for wh in job.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::JobSkipped, Some(job), runner)) {
+        let url = wh.to_url(runner_env.as_ref());
+        if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
+            continue;
+        }
+        webhook::send_job_skipped(&url, wh.template.as_deref(), &skipped).await;
+    }
+}
+
+/// Records a command's exit code or failure on its span.
+fn trace_result(span: &mut Span, job: &Job, result: &CommandResult) {
+    match result {
+        CommandResult::Completed(output) => {
+            if let Some(code) = output.status.code() {
+                span.set_attr("process.exit_code", i64::from(code));
+            }
+            if let Some(reason) = completed_failure(job, output) {
+                span.fail(reason);
             }
         }
-        None => base_dir.to_path_buf(),
+        CommandResult::OomKilled(_) => span.fail("out of memory"),
+        CommandResult::ExecError(e) => span.fail(e),
+        CommandResult::Timeout => span.fail("timeout"),
+        CommandResult::Stalled { .. } => span.fail("stalled"),
     }
 }
 
+/// Output persisted to run history: stdout followed by stderr.
+fn combined_output(result: Option<&CommandResult>) -> Vec<u8> {
+    let Some(CommandResult::Completed(output)) = result else {
+        return Vec::new();
+    };
+    let mut buf = Vec::with_capacity(output.stdout.len() + output.stderr.len() + 32);
+    buf.extend_from_slice(b"=== stdout ===\n");
+    buf.extend_from_slice(&output.stdout);
+    buf.extend_from_slice(b"\n=== stderr ===\n");
+    buf.extend_from_slice(&output.stderr);
+    buf
+}
+
+fn build_report(
+    job: &Job,
+    started_at: DateTime<Utc>,
+    duration: Duration,
+    attempts: u32,
+    result: Option<&CommandResult>,
+) -> JobRunReport {
+    let status = match result {
+        Some(CommandResult::Completed(output) | CommandResult::OomKilled(output)) => Some(&output.status),
+        _ => None,
+    };
+    let (outcome, exit_code, error, stdout_tail, stderr_tail) = match result {
+        Some(CommandResult::Completed(output)) => {
+            let error = completed_failure(job, output);
+            let outcome = if error.is_none() { RunOutcome::Success } else { RunOutcome::Failed };
+            (
+                outcome,
+                output.status.code(),
+                error,
+                tail(&output.stdout, OUTPUT_TAIL_BYTES),
+                tail(&output.stderr, OUTPUT_TAIL_BYTES),
+            )
+        }
+        Some(CommandResult::OomKilled(output)) => (
+            RunOutcome::OomKilled,
+            output.status.code(),
+            Some(oom_message(job)),
+            tail(&output.stdout, OUTPUT_TAIL_BYTES),
+            tail(&output.stderr, OUTPUT_TAIL_BYTES),
+        ),
+        Some(CommandResult::ExecError(e)) => (
+            RunOutcome::ExecError,
+            None,
+            Some(format!("exec error: {}", e)),
+            String::new(),
+            String::new(),
+        ),
+        Some(CommandResult::Timeout) => (
+            RunOutcome::Timeout,
+            None,
+            Some(format!("timeout after {:?}", job.timeout)),
+            String::new(),
+            String::new(),
+        ),
+        Some(CommandResult::Stalled { stdout, stderr }) => (
+            RunOutcome::Stalled,
+            None,
+            Some(stall_message(job)),
+            tail(stdout, OUTPUT_TAIL_BYTES),
+            tail(stderr, OUTPUT_TAIL_BYTES),
+        ),
+        None => (
+            RunOutcome::ExecError,
+            None,
+            Some("unknown error".to_string()),
+            String::new(),
+            String::new(),
+        ),
+    };
+
+    let failure_class = classify(outcome, status, &[error.as_deref().unwrap_or_default(), &stderr_tail, &stdout_tail]);
+
+    JobRunReport {
+        job_id: job.id.clone(),
+        run_id: generate_run_id(started_at),
+        run_number: None,
+        commit: None,
+        started_at,
+        duration,
+        attempts,
+        outcome,
+        exit_code,
+        error,
+        failure_class,
+        fallback: false,
+        stdout_tail,
+        stderr_tail,
+        omitted_bytes: 0,
+        outside_writes: Vec::new(),
+        imported: false,
+        timings: None,
+        trigger: None,
+        overrides: Vec::new(),
+        artifacts: Vec::new(),
+    }
+}
+
+/// The directory a command runs in: `working_dir` under `base_dir`, or an
+/// absolute path under one of `runner.allowed_work_dirs`. With `create`, a
+/// missing directory is made first; otherwise it is an error, never a silent
+/// fallback to `base_dir`.
+fn resolve_work_dir(base_dir: &Path, working_dir: Option<&WorkingDir>, allowed: &[PathBuf]) -> anyhow::Result<PathBuf> {
+    let Some(dir) = working_dir else {
+        return Ok(base_dir.to_path_buf());
+    };
+    let expanded = env::expand_string(&dir.path);
+    let absolute = Path::new(&expanded).is_absolute();
+    let roots: Vec<&Path> = match absolute {
+        true => allowed.iter().map(PathBuf::as_path).collect(),
+        false => vec![base_dir],
+    };
+    let outside = || match absolute {
+        true => anyhow!("'{}' is not under runner.allowed_work_dirs", dir.path),
+        false => anyhow!("'{}' is outside the job directory", dir.path),
+    };
+    let path = base_dir.join(&expanded);
+    // Checked before anything is created, then again once symlinks are resolved
+    if !roots.iter().any(|root| normalize(&path).starts_with(normalize(root))) {
+        return Err(outside());
+    }
+    if dir.create {
+        fs::create_dir_all(&path).with_context(|| format!("failed to create '{}'", path.display()))?;
+    }
+    let resolved = path.canonicalize().with_context(|| format!("'{}' does not exist", path.display()))?;
+    if !resolved.is_dir() {
+        anyhow::bail!("'{}' is not a directory", path.display());
+    }
+    if !roots.iter().filter_map(|root| root.canonicalize().ok()).any(|root| resolved.starts_with(root)) {
+        return Err(outside());
+    }
+    Ok(resolved)
+}
+
+/// `path` with `.` and `..` resolved lexically
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// What the attempts of one run share besides the job's config
+#[derive(Clone, Copy)]
+struct RunContext<'a> {
+    run_dir: &'a Path,
+    trace_path: Option<&'a Path>,
+    /// The job's log file, which output is streamed into
+    spool: Option<&'a File>,
+    /// `ROLLCRON_RUN_NUMBER` (None if it could not be allocated)
+    run_number: Option<u64>,
+    /// `ROLLCRON_COMMIT`
+    commit: Option<&'a str>,
+    /// Set after the job's env, e.g. `ROLLCRON_ARTIFACTS_DIR` for `artifacts.upload`
+    extra_env: &'a [(&'static str, String)],
+}
+
 async fn run_command(
     job: &Job,
-    work_dir: &Path,
+    command: &CommandLine,
+    context: RunContext<'_>,
     sot_path: &Path,
     runner: &RunnerConfig,
-) -> CommandResult {
-    let env_vars = match merge_env_vars(job, work_dir, sot_path, runner) {
+) -> (CommandResult, AttemptStats) {
+    let RunContext { run_dir, trace_path, spool, run_number, commit, extra_env } = context;
+    let mut stats = AttemptStats::default();
+    if clock::stub_jobs() {
+        return (CommandResult::Completed(stub_output()), stats);
+    }
+
+    // Per attempt: a directory on a volume may come back in time for a retry
+    let work_dir = match resolve_work_dir(run_dir, job.working_dir.as_ref(), &runner.allowed_work_dirs) {
+        Ok(dir) => dir,
+        Err(e) => return (CommandResult::ExecError(format!("Invalid working_dir: {:#}", e)), stats),
+    };
+    let work_dir = work_dir.as_path();
+
+    let env_start = Instant::now();
+    // Secret references and encrypted env files shell out to vault/aws/age/sops
+    let (owned_job, owned_work_dir, owned_sot_path, owned_runner) =
+        (job.clone(), work_dir.to_path_buf(), sot_path.to_path_buf(), runner.clone());
+    let env_vars = tokio::task::spawn_blocking(move || {
+        merge_env_vars(&owned_job, &owned_work_dir, &owned_sot_path, &owned_runner)
+    })
+    .await
+    .unwrap_or_else(|e| Err(anyhow::anyhow!("env task failed: {}", e)));
+    stats.env_load = env_start.elapsed();
+    let env_vars = match env_vars {
         Ok(vars) => vars,
         Err(e) => {
-            return CommandResult::ExecError(format!("Failed to load environment: {}", e));
+            return (CommandResult::ExecError(format!("Failed to load environment: {}", e)), stats);
         }
     };
 
-    let mut cmd = Command::new("sh");
-    cmd.args(["-c", &job.command])
-        .current_dir(work_dir)
+    let writable: Vec<&Path> = [Some(run_dir), Some(work_dir), trace_path.and_then(Path::parent)].into_iter().flatten().collect();
+    let Confined { mut cmd, limit_scope, account_env } =
+        match confined_command(job, command, work_dir, &writable, trace_path) {
+            Ok(confined) => confined,
+            Err(e) => return (CommandResult::ExecError(e), stats),
+        };
+    cmd.current_dir(work_dir)
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped());
 
+    // Job env can still override them
+    for (key, value) in account_env {
+        cmd.env(key, value);
+    }
+    cmd.envs(facts::get().env());
+    if let Some(number) = run_number {
+        cmd.env("ROLLCRON_RUN_NUMBER", number.to_string());
+    }
+    if let Some(commit) = commit {
+        cmd.env("ROLLCRON_COMMIT", commit);
+    }
     for (key, value) in env_vars {
         cmd.env(key, value);
     }
+    for (key, value) in extra_env {
+        cmd.env(key, value);
+    }
 
-    let mut child = match cmd.spawn() {
+    let mut child = match process::spawn(&mut cmd) {
         Ok(c) => c,
-        Err(e) => return CommandResult::ExecError(e.to_string()),
+        Err(e) => return (CommandResult::ExecError(e.to_string()), stats),
     };
 
     // Take stdout/stderr handles before waiting
@@ -487,118 +1217,179 @@ async fn run_command(
     let stderr = child.stderr.take();
 
     // Spawn tasks to read output concurrently (prevents buffer deadlock)
-    let stdout_task = tokio::spawn(async move {
-        match stdout {
-            Some(mut out) => {
-                let mut buf = Vec::new();
-                let _ = tokio::io::AsyncReadExt::read_to_end(&mut out, &mut buf).await;
-                buf
-            }
-            None => Vec::new(),
-        }
-    });
-    let stderr_task = tokio::spawn(async move {
-        match stderr {
-            Some(mut err) => {
-                let mut buf = Vec::new();
-                let _ = tokio::io::AsyncReadExt::read_to_end(&mut err, &mut buf).await;
-                buf
-            }
-            None => Vec::new(),
-        }
-    });
+    let activity = Activity::new();
+    let stdout_tap = Some(Tap::new(&job.id, "stdout"));
+    let stderr_tap = Some(Tap::new(&job.id, "stderr"));
+    let stdout_task = tokio::spawn(capture(stdout, job.max_output, stdout_tap, spool_handle(spool), Some(activity.clone())));
+    let stderr_task = tokio::spawn(capture(stderr, job.max_output, stderr_tap, spool_handle(spool), Some(activity.clone())));
 
     // Wait for process with timeout
-    let wait_result = tokio::time::timeout(job.timeout, child.wait()).await;
+    let wait_result = tokio::time::timeout(job.timeout, wait_active(&mut child, &activity, job.stall_timeout)).await;
 
     match wait_result {
-        Ok(Ok(status)) => {
-            let stdout = stdout_task.await.unwrap_or_default();
-            let stderr = stderr_task.await.unwrap_or_default();
-            CommandResult::Completed(std::process::Output {
+        Ok(Some(Ok(status))) => {
+            let stdout: Captured = stdout_task.await.unwrap_or_default();
+            let stderr: Captured = stderr_task.await.unwrap_or_default();
+            stats.omitted = stdout.omitted + stderr.omitted;
+            let output = std::process::Output {
                 status,
-                stdout,
-                stderr,
-            })
+                stdout: stdout.data,
+                stderr: stderr.data,
+            };
+            let result = match limit_scope {
+                Some(scope) if scope.oom_killed(&status) => CommandResult::OomKilled(output),
+                _ => CommandResult::Completed(output),
+            };
+            (result, stats)
+        }
+        Ok(Some(Err(e))) => (CommandResult::ExecError(e.to_string()), stats),
+        Ok(None) => {
+            child.terminate(&job.id).await;
+            // The pipes close with the process group; keep what it printed before stalling
+            let collect = async { (stdout_task.await.unwrap_or_default(), stderr_task.await.unwrap_or_default()) };
+            let (stdout, stderr) = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, collect).await.unwrap_or_default();
+            stats.omitted = stdout.omitted + stderr.omitted;
+            (CommandResult::Stalled { stdout: stdout.data, stderr: stderr.data }, stats)
         }
-        Ok(Err(e)) => CommandResult::ExecError(e.to_string()),
         Err(_) => {
             // Timeout occurred - attempt graceful shutdown
-            graceful_kill(&mut child, &job.id).await;
-            CommandResult::Timeout
+            child.terminate(&job.id).await;
+            (CommandResult::Timeout, stats)
         }
     }
 }
 
-/// Attempts graceful shutdown: SIGTERM first, then SIGKILL after grace period.
-#[cfg(unix)]
-async fn graceful_kill(child: &mut tokio::process::Child, job_id: &str) {
-    use nix::sys::signal::{kill, Signal};
-    use nix::unistd::Pid;
+/// A build or run command set up under the job's `limits`, `user`/`group`,
+/// `security` and `sandbox`
+struct Confined {
+    cmd: Command,
+    /// Kept until the command exits (the cgroup is removed on drop)
+    limit_scope: Option<LimitScope>,
+    /// `HOME`, `USER` and `LOGNAME` of the account, for the caller to set before the job's env
+    account_env: Vec<(&'static str, String)>,
+}
 
-    let Some(pid) = child.id() else {
-        return; // Process already exited
+/// Builds `command` confined the way the job asks. The sandbox leaves
+/// `writable` writable; `trace_path` adds the `audit` strace wrapper.
+fn confined_command(
+    job: &Job,
+    command: &CommandLine,
+    work_dir: &Path,
+    writable: &[&Path],
+    trace_path: Option<&Path>,
+) -> Result<Confined, String> {
+    // Fresh scope per attempt so OOM accounting starts at zero
+    let limit_scope = match job.limits.as_ref().map(|l| LimitScope::new(&job.id, l)).transpose() {
+        Ok(scope) => scope,
+        Err(e) => return Err(format!("Failed to apply limits: {:#}", e)),
     };
-    let pid = Pid::from_raw(pid as i32);
+    // Looked up per attempt too: the account may be fixed before a retry
+    let run_as = match RunAs::resolve(job.user.as_deref(), job.group.as_deref()) {
+        Ok(run_as) => run_as.filter(|run_as| !run_as.is_current()),
+        Err(e) => return Err(format!("Invalid user/group: {:#}", e)),
+    };
+    // Root switches in the child, anyone else through sudo
+    let switch_in_child = run_as.is_some() && identity::is_root();
+    // The cgroup wrapper would run unprivileged or sandboxed
+    let join_in_child = (switch_in_child || job.sandbox.is_some()) && matches!(limit_scope, Some(LimitScope::Cgroup(_)));
+    let mut wrapper = match &limit_scope {
+        Some(scope) if !join_in_child => scope.wrapper(),
+        _ => vec![],
+    };
+    if let Some(trace_path) = trace_path {
+        wrapper.extend(audit::strace_wrapper(trace_path));
+    }
+    if let Some(run_as) = run_as.as_ref().filter(|_| !switch_in_child) {
+        wrapper.extend(run_as.sudo_wrapper());
+    }
 
-    // Send SIGTERM for graceful shutdown
-    if kill(pid, Signal::SIGTERM).is_ok() {
-        debug!(target: "rollcron::job", job_id = %job_id, "Sent SIGTERM, waiting for graceful exit");
+    let mut cmd = shell_command(job.shell, command, &wrapper);
+    if let (Some(scope), true) = (&limit_scope, join_in_child) {
+        // Before the switch below
+        scope.join_in_child(&mut cmd);
+    }
+    let account_env = run_as.as_ref().map(RunAs::env).unwrap_or_default();
+    let confinement = match &job.security {
+        Some(security) => match Confinement::new(security, work_dir) {
+            Ok(confinement) => Some(confinement),
+            Err(e) => return Err(format!("Failed to apply security profile: {:#}", e)),
+        },
+        None => None,
+    };
+    let confinement = match run_as.filter(|_| switch_in_child) {
+        Some(run_as) => Some(confinement.unwrap_or_default().run_as(run_as)),
+        None => confinement,
+    };
+    let confinement = match &job.sandbox {
+        Some(config) => match Sandbox::new(config, work_dir, writable) {
+            Ok(sandbox) => Some(confinement.unwrap_or_default().sandbox(sandbox)),
+            Err(e) => return Err(format!("Failed to set up sandbox: {:#}", e)),
+        },
+        None => confinement,
+    };
+    if let Some(confinement) = confinement {
+        confinement.apply(&mut cmd);
+    }
+    Ok(Confined { cmd, limit_scope, account_env })
+}
 
-        // Wait for process to exit gracefully
-        if tokio::time::timeout(GRACEFUL_SHUTDOWN_TIMEOUT, child.wait())
-            .await
-            .is_ok()
-        {
-            debug!(target: "rollcron::job", job_id = %job_id, "Process exited gracefully after SIGTERM");
-            return;
+/// How long a stalled run's output may take to drain after it is killed
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Waits for `child` to exit. `None` if it goes `stall_timeout` without
+/// output first.
+async fn wait_active(
+    child: &mut process::GroupChild,
+    activity: &Activity,
+    stall_timeout: Option<Duration>,
+) -> Option<std::io::Result<std::process::ExitStatus>> {
+    let Some(stall_timeout) = stall_timeout else {
+        return Some(child.wait().await);
+    };
+    loop {
+        let idle = activity.idle();
+        if idle >= stall_timeout {
+            return None;
+        }
+        tokio::select! {
+            status = child.wait() => return Some(status),
+            _ = sleep(stall_timeout - idle) => {}
         }
-
-        // Grace period expired - force kill
-        warn!(target: "rollcron::job", job_id = %job_id, "Grace period expired, sending SIGKILL");
     }
-
-    // Send SIGKILL
-    let _ = child.kill().await;
-    let _ = child.wait().await;
 }
 
-#[cfg(not(unix))]
-async fn graceful_kill(child: &mut tokio::process::Child, _job_id: &str) {
-    // On non-Unix platforms, just kill immediately
-    let _ = child.kill().await;
-    let _ = child.wait().await;
+/// A handle to the log file for one output stream (O_APPEND keeps writes whole).
+fn spool_handle(log_file: Option<&File>) -> Option<File> {
+    log_file.and_then(|file| file.try_clone().ok())
 }
 
-/// Load runner-level env vars for webhook URL expansion.
-/// Returns None on error (webhook will fall back to process env).
-fn load_runner_env_vars(
-    sot_path: &Path,
-    runner: &RunnerConfig,
-) -> Option<HashMap<String, String>> {
-    let mut env_vars = HashMap::new();
-
-    // Load runner.env_file
-    if let Some(env_file_path) = &runner.env_file {
-        let expanded = env::expand_string(env_file_path);
-        let full_path = sot_path.join(&expanded);
-        match env::load_env_from_path(&full_path) {
-            Ok(vars) => env_vars.extend(vars),
-            Err(e) => {
-                warn!(target: "rollcron::webhook", error = %e, "Failed to load runner env_file");
-                return None;
-            }
-        }
+/// Successful empty result used instead of running commands in a stubbed simulation.
+fn stub_output() -> std::process::Output {
+    std::process::Output {
+        status: std::process::ExitStatus::default(),
+        stdout: Vec::new(),
+        stderr: Vec::new(),
     }
+}
 
-    // Merge runner.env
-    if let Some(runner_env) = &runner.env {
-        for (k, v) in runner_env {
-            env_vars.insert(k.clone(), env::expand_string(v));
-        }
+/// Builds the command running `command` through `shell` (or directly for
+/// argv commands and `shell: none`), behind the `wrapper` argv prefix if any.
+fn shell_command(shell: Shell, command: &CommandLine, wrapper: &[String]) -> Command {
+    #[cfg(windows)]
+    if let (Shell::Cmd, CommandLine::Line(line), true) = (shell, command, wrapper.is_empty()) {
+        // cmd.exe parses its own command line; Rust's argument quoting would break it
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").raw_arg(line);
+        return cmd;
     }
-
-    Some(env_vars)
+    let argv = command.argv(shell);
+    let (program, args) = match wrapper.split_first() {
+        Some((program, args)) => (program.as_str(), args.iter().map(String::as_str).chain(argv).collect()),
+        None => (argv[0], argv[1..].to_vec()),
+    };
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    cmd
 }
 
 fn merge_env_vars(
@@ -613,85 +1404,102 @@ fn merge_env_vars(
     if let Some(env_file_path) = &runner.env_file {
         let expanded = env::expand_string(env_file_path);
         let full_path = sot_path.join(&expanded);
-        let vars = env::load_env_from_path(&full_path)?;
+        let vars = secrets::load_env_file(&full_path, &runner.secrets)?;
         env_vars.extend(vars);
     }
 
-    // 2. Merge runner.env (with shell expansion on values)
+    // 2. Merge runner.env (shell-expanded; secret references resolved)
     if let Some(runner_env) = &runner.env {
-        for (k, v) in runner_env {
-            env_vars.insert(k.clone(), env::expand_string(v));
-        }
+        env::merge_env_values(&mut env_vars, runner_env)?;
     }
 
-    // 3. Merge job.env_file (loaded from work_dir)
-    if let Some(env_file_path) = &job.env_file {
+    // 3. Merge job.env_file, then job.env_files in order (loaded from work_dir)
+    for env_file_path in job.env_file.iter().chain(&job.env_files) {
         let expanded = env::expand_string(env_file_path);
         let full_path = work_dir.join(&expanded);
-        let vars = env::load_env_from_path(&full_path)?;
+        let vars = secrets::load_env_file(&full_path, &runner.secrets)?;
         env_vars.extend(vars);
     }
 
-    // 4. Merge job.env (with shell expansion on values)
+    // 4. Merge job.env (shell-expanded; secret references resolved)
     if let Some(job_env) = &job.env {
-        for (k, v) in job_env {
-            env_vars.insert(k.clone(), env::expand_string(v));
-        }
+        env::merge_env_values(&mut env_vars, job_env)?;
     }
 
     // 5. Merge run.env_file (loaded from work_dir)
     if let Some(env_file_path) = &job.run_env_file {
         let expanded = env::expand_string(env_file_path);
         let full_path = work_dir.join(&expanded);
-        let vars = env::load_env_from_path(&full_path)?;
+        let vars = secrets::load_env_file(&full_path, &runner.secrets)?;
         env_vars.extend(vars);
     }
 
-    // 6. Merge run.env (with shell expansion on values)
+    // 6. Merge run.env (shell-expanded; secret references resolved)
     if let Some(run_env) = &job.run_env {
-        for (k, v) in run_env {
-            env_vars.insert(k.clone(), env::expand_string(v));
-        }
+        env::merge_env_values(&mut env_vars, run_env)?;
     }
 
     Ok(env_vars)
 }
 
+/// Side measurements of one `run_command` call.
+#[derive(Default)]
+struct AttemptStats {
+    /// Output bytes dropped (`run.max_output`)
+    omitted: u64,
+    env_load: Duration,
+}
+
+/// Splits an attempt's wall time into env loading and execution.
+fn add_attempt(timings: &mut PhaseTimings, duration: Duration, stats: &AttemptStats) {
+    timings.env_load += stats.env_load;
+    timings.execution += duration.saturating_sub(stats.env_load);
+}
+
 enum CommandResult {
     Completed(std::process::Output),
+    /// Killed by the OOM killer for exceeding `limits.memory`
+    OomKilled(std::process::Output),
     ExecError(String),
     Timeout,
+    /// No output for `run.stall_timeout`; output up to the kill
+    Stalled { stdout: Vec<u8>, stderr: Vec<u8> },
 }
 
 fn handle_result(job: &Job, result: &CommandResult, log_file: Option<&mut File>, runner_tz: &TimezoneConfig, duration: Duration) -> bool {
     match result {
         CommandResult::Completed(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let success = output.status.success();
+            let failure = completed_failure(job, output);
 
             if let Some(file) = log_file {
-                let _ = file.write_all(stdout.as_bytes());
-                let _ = file.write_all(stderr.as_bytes());
-                let marker = if success {
-                    format!("Job finished (success) [{}]", format_duration(duration))
-                } else {
-                    format!("Job finished (failed, exit code {:?}) [{}]", output.status.code(), format_duration(duration))
+                let marker = match &failure {
+                    None => format!("Job finished (success) [{}]", format_duration(duration)),
+                    Some(reason) => format!("Job finished (failed, {}) [{}]", reason, format_duration(duration)),
                 };
                 write_log_marker(file, runner_tz, job.timezone.as_ref(), &marker);
             }
 
-            if success {
-                info!(target: "rollcron::job", job_id = %job.id, "Completed");
-            } else {
-                error!(
+            match &failure {
+                None => info!(target: "rollcron::job", job_id = %job.id, "Completed"),
+                Some(reason) => error!(
                     target: "rollcron::job",
                     job_id = %job.id,
                     exit_code = ?output.status.code(),
+                    reason = %reason,
                     "Failed"
-                );
+                ),
+            }
+            failure.is_none()
+        }
+        CommandResult::OomKilled(_) => {
+            let message = oom_message(job);
+            error!(target: "rollcron::job", job_id = %job.id, "{}", message);
+            if let Some(file) = log_file {
+                let _ = writeln!(file, "[rollcron] {}", message);
+                let marker = format!("Job finished (out of memory) [{}]", format_duration(duration));
+                write_log_marker(file, runner_tz, job.timezone.as_ref(), &marker);
             }
-            success
+            false
         }
         CommandResult::ExecError(e) => {
             error!(target: "rollcron::job", job_id = %job.id, error = %e, "Failed to execute");
@@ -711,6 +1519,52 @@ fn handle_result(job: &Job, result: &CommandResult, log_file: Option<&mut File>,
             }
             false
         }
+        CommandResult::Stalled { .. } => {
+            let message = stall_message(job);
+            error!(target: "rollcron::job", job_id = %job.id, "{}", message);
+            if let Some(file) = log_file {
+                let _ = writeln!(file, "[rollcron] {}", message);
+                let marker = format!("Job finished (stalled) [{}]", format_duration(duration));
+                write_log_marker(file, runner_tz, job.timezone.as_ref(), &marker);
+            }
+            false
+        }
+    }
+}
+
+/// Why a completed run counts as failed: its exit code, or the first unmet
+/// `success_when` criterion. Patterns see the output kept in memory
+/// (`run.max_output`).
+fn completed_failure(job: &Job, output: &std::process::Output) -> Option<String> {
+    let exit_failure = || format!("exit code {:?}", output.status.code());
+    // Stubbed runs have no output to match
+    let Some(criteria) = job.success_when.as_ref().filter(|_| !clock::stub_jobs()) else {
+        return (!output.status.success()).then(exit_failure);
+    };
+    if !output.status.code().is_some_and(|code| criteria.exit_codes.contains(&code)) {
+        return Some(exit_failure());
+    }
+    if let Some(pattern) = &criteria.stdout_matches {
+        if !pattern.is_match(&String::from_utf8_lossy(&output.stdout)) {
+            return Some(format!("stdout does not match /{}/", pattern));
+        }
+    }
+    if let Some(pattern) = &criteria.stderr_not_matches {
+        if pattern.is_match(&String::from_utf8_lossy(&output.stderr)) {
+            return Some(format!("stderr matches /{}/", pattern));
+        }
+    }
+    None
+}
+
+fn stall_message(job: &Job) -> String {
+    format!("stalled: no output for {:?}", job.stall_timeout.unwrap_or_default())
+}
+
+fn oom_message(job: &Job) -> String {
+    match job.limits.and_then(|l| l.memory) {
+        Some(memory) => format!("killed: out of memory (limits.memory = {} bytes)", memory),
+        None => "killed: out of memory".to_string(),
     }
 }
 
@@ -737,19 +1591,42 @@ fn generate_jitter(max: Duration) -> Duration {
 
 // === Logging ===
 
-fn rotate_log_file(path: &Path, max_size: u64) {
+fn rotate_log_file(path: &Path, max_size: u64, compression: Compression, level: Option<u32>) {
     if let Ok(meta) = fs::metadata(path) {
         if meta.len() >= max_size {
             let old_path = path.with_extension("log.old");
+            for c in [Compression::Gzip, Compression::Zstd] {
+                let _ = fs::remove_file(path.with_extension(format!("log.old{}", c.extension())));
+            }
             let _ = fs::remove_file(&old_path);
             let _ = fs::rename(path, &old_path);
+            if compression != Compression::None {
+                compress_rotated_log(&old_path, compression, level);
+            }
+        }
+    }
+}
+
+/// Replaces `<file>.old` with `<file>.old.gz` / `<file>.old.zst`.
+fn compress_rotated_log(old_path: &Path, compression: Compression, level: Option<u32>) {
+    let compressed_path = old_path.with_extension(format!("old{}", compression.extension()));
+    let result = fs::read(old_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| history::compress(&data, compression, level))
+        .and_then(|data| fs::write(&compressed_path, data).map_err(anyhow::Error::from));
+    match result {
+        Ok(()) => {
+            let _ = fs::remove_file(old_path);
+        }
+        Err(e) => {
+            warn!(target: "rollcron::job", error = %e, "Failed to compress rotated log file");
         }
     }
 }
 
-fn create_log_file(job_dir: &Path, log_path: &str, max_size: u64) -> Option<File> {
+fn create_log_file(job: &Job, log_dir: &Path, log_path: &str) -> Option<File> {
     let expanded = env::expand_string(log_path);
-    let full_path = job_dir.join(&expanded);
+    let full_path = log_dir.join(&expanded);
 
     if let Some(parent) = full_path.parent() {
         if let Err(e) = fs::create_dir_all(parent) {
@@ -758,7 +1635,7 @@ fn create_log_file(job_dir: &Path, log_path: &str, max_size: u64) -> Option<File
         }
     }
 
-    rotate_log_file(&full_path, max_size);
+    rotate_log_file(&full_path, job.log_max_size, job.log_compress, job.log_compress_level);
 
     match OpenOptions::new()
         .create(true)
@@ -777,9 +1654,9 @@ fn format_timestamp(runner_tz: &TimezoneConfig, job_tz: Option<&TimezoneConfig>)
     let fmt = "%Y-%m-%d %H:%M:%S %Z";
     let tz = job_tz.unwrap_or(runner_tz);
     match tz {
-        TimezoneConfig::Utc => Utc::now().format(fmt).to_string(),
-        TimezoneConfig::Inherit => Local::now().format(fmt).to_string(),
-        TimezoneConfig::Named(tz) => Utc::now().with_timezone(tz).format(fmt).to_string(),
+        TimezoneConfig::Utc => clock::now().format(fmt).to_string(),
+        TimezoneConfig::Inherit => clock::now().with_timezone(&Local).format(fmt).to_string(),
+        TimezoneConfig::Named(tz) => clock::now().with_timezone(tz).format(fmt).to_string(),
     }
 }
 
@@ -805,7 +1682,8 @@ fn write_log_marker(file: &mut File, runner_tz: &TimezoneConfig, job_tz: Option<
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{Concurrency, TimezoneConfig};
+    use crate::actor::job::report::FailureClass;
+    use crate::config::{Concurrency, Severity, TimezoneConfig};
     use croner::Cron;
     use std::str::FromStr;
     use tempfile::tempdir;
@@ -814,22 +1692,57 @@ mod tests {
         Job {
             id: "test".to_string(),
             name: "Test Job".to_string(),
-            schedule: Cron::from_str("* * * * *").unwrap(),
+            source: "rollcron.yaml:2".to_string(),
+            schedule: Some(Cron::from_str("* * * * *").unwrap()),
             build: None,
-            command: cmd.to_string(),
+            command: CommandLine::Line(cmd.to_string()),
+            fallback_command: None,
+            shell: Shell::default(),
             timeout: Duration::from_secs(timeout_secs),
+            stall_timeout: None,
             concurrency: Concurrency::Skip,
             retry: None,
             working_dir: None,
             enabled: true,
             timezone: None,
             env_file: None,
+            env_files: vec![],
             env: None,
             run_env_file: None,
             run_env: None,
             webhook: vec![],
             log_file: None,
             log_max_size: 10 * 1024 * 1024,
+            log_compress: Compression::None,
+            log_compress_level: None,
+            log_flush: LogFlush::Stream,
+            max_output: 1024 * 1024,
+            audit: false,
+            labels: HashMap::new(),
+            severity: Severity::Error,
+            notify_on_start: false,
+            dedupe_alerts: false,
+            renotify_after: None,
+            notify_on_output_change: false,
+            expected_duration: None,
+            run_on_start: false,
+            pull_on_run: false,
+            min_interval: None,
+            circuit_breaker: None,
+            success_when: None,
+            artifacts: None,
+            condition: None,
+            limits: None,
+            security: None,
+            sandbox: None,
+            user: None,
+            group: None,
+            blackout: vec![],
+            cleanup: None,
+            priority: 0,
+            when: vec![],
+            guards: Default::default(),
+            slo: None,
         }
     }
 
@@ -839,6 +1752,22 @@ mod tests {
             env_file: None,
             env: None,
             webhook: vec![],
+            secrets: Default::default(),
+            shell: Default::default(),
+            ha: None,
+            long_job_threshold: Duration::from_secs(3600),
+            max_concurrent_runs: None,
+            remove_orphan_dirs: true,
+            metrics: Default::default(),
+            canary: None,
+            brake: None,
+            allowed_work_dirs: vec![],
+            on_resume: Default::default(),
+            allow_force_reset: true,
+            submodules: false,
+            lfs: false,
+            changelog: false,
+            export: None,
         }
     }
 
@@ -847,15 +1776,257 @@ mod tests {
         let job = make_job("echo test", 10);
         let dir = tempdir().unwrap();
         let runner = make_runner();
-        execute_job(&job, &dir.path().to_path_buf(), &runner).await;
+        let sot_path = dir.path().join("repo-00000000");
+        let report = execute_job(&job, &sot_path, &runner, &RunStart::default()).await;
+        assert_eq!(report.job_id, "test");
+        assert_eq!(report.attempts, 1);
+
+        let runs = history::read_runs(&git::get_history_dir(&sot_path, "test")).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].run_id, report.run_id);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn execute_job_records_phase_timings() {
+        let mut job = make_job("sleep 0.2; false", 10);
+        job.id = "phase_timings".to_string();
+        job.retry = Some(RetryConfig {
+            max: 1,
+            delay: Duration::from_millis(100),
+            jitter: Some(Duration::ZERO),
+        });
+        let dir = tempdir().unwrap();
+        let sot_path = dir.path().join("repo-00000000");
+        fs::create_dir_all(git::get_run_dir(&sot_path, &job.id)).unwrap();
+        let report = execute_job(&job, &sot_path, &make_runner(), &RunStart::default()).await;
+        assert_eq!(report.attempts, 2);
+
+        let timings = report.timings.clone().unwrap();
+        assert!(timings.execution >= Duration::from_millis(400), "{:?}", timings);
+        assert!(timings.retry_wait >= Duration::from_millis(100), "{:?}", timings);
+        assert!(timings.execution + timings.retry_wait <= report.duration);
+
+        let runs = history::read_runs(&git::get_history_dir(&sot_path, &job.id)).unwrap();
+        let recorded = runs[0].timings.clone().unwrap();
+        assert_eq!(recorded.execution.as_millis(), timings.execution.as_millis());
+        git::remove_job_dir(&sot_path, &job.id);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unmet_condition_skips_the_run() {
+        let mut job = make_job("touch ran", 10);
+        job.id = "condition".to_string();
+        job.condition = Some("test -f allow".to_string());
+        job.retry = Some(RetryConfig { max: 2, delay: Duration::ZERO, jitter: None });
+        let dir = tempdir().unwrap();
+        let sot_path = dir.path().join("repo-00000000");
+        let run_dir = git::get_run_dir(&sot_path, &job.id);
+        fs::create_dir_all(&run_dir).unwrap();
+
+        let report = execute_job(&job, &sot_path, &make_runner(), &RunStart::default()).await;
+        assert_eq!(report.outcome, RunOutcome::Skipped);
+        assert_eq!(report.attempts, 0);
+        assert_eq!(report.error.as_deref(), Some("condition exited with 1"));
+        assert_eq!(report.failure_class, None);
+        assert!(!run_dir.join("ran").exists());
+
+        fs::write(run_dir.join("allow"), "").unwrap();
+        let report = execute_job(&job, &sot_path, &make_runner(), &RunStart::default()).await;
+        assert_eq!(report.outcome, RunOutcome::Success);
+        assert!(run_dir.join("ran").exists());
+
+        let runs = history::read_runs(&git::get_history_dir(&sot_path, &job.id)).unwrap();
+        assert_eq!(runs.iter().map(|r| r.outcome).collect::<Vec<_>>(), [RunOutcome::Skipped, RunOutcome::Success]);
+        git::remove_job_dir(&sot_path, &job.id);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn build_runs_under_the_jobs_confinement() {
+        let mut job = make_job("true", 10);
+        job.id = "confined_build".to_string();
+        job.build = Some(crate::config::BuildConfig {
+            command: CommandLine::Line("touch built".to_string()),
+            timeout: Duration::from_secs(10),
+            env_file: None,
+            env: None,
+            working_dir: None,
+        });
+        job.security = Some(crate::config::SecurityConfig { seccomp: Some("missing.bpf".to_string()), apparmor: None });
+        let dir = tempdir().unwrap();
+        let sot_path = dir.path().join("repo-00000000");
+        let build_dir = git::get_build_dir(&sot_path, &job.id);
+        fs::create_dir_all(&build_dir).unwrap();
+
+        match execute_build(&job, &sot_path, &make_runner()).await {
+            BuildResult::Failed { error, .. } => {
+                assert!(error.contains("Failed to apply security profile"), "{}", error)
+            }
+            _ => panic!("build ran without its security profile"),
+        }
+        assert!(!build_dir.join("built").exists());
+        git::remove_job_dir(&sot_path, &job.id);
     }
 
+    #[cfg(unix)]
     #[tokio::test]
     async fn job_timeout() {
         let job = make_job("sleep 10", 1);
         let dir = tempdir().unwrap();
         let runner = make_runner();
-        execute_job(&job, &dir.path().to_path_buf(), &runner).await;
+        let report = execute_job(&job, &dir.path().join("repo-00000000"), &runner, &RunStart::default()).await;
+        assert!(!report.is_success());
+    }
+
+    #[tokio::test]
+    async fn job_stall_timeout() {
+        let mut job = make_job("echo started; sleep 10", 30);
+        job.stall_timeout = Some(Duration::from_secs(1));
+        let dir = tempdir().unwrap();
+        let sot_path = dir.path().join("repo-00000000");
+        fs::create_dir_all(git::get_run_dir(&sot_path, &job.id)).unwrap();
+        let runner = make_runner();
+        let started = Instant::now();
+        let report = execute_job(&job, &sot_path, &runner, &RunStart::default()).await;
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(report.outcome, RunOutcome::Stalled);
+        assert_eq!(report.failure_class, Some(FailureClass::Timeout));
+        assert_eq!(report.stdout_tail, "started\n");
+    }
+
+    #[cfg(unix)]
+    fn completed(code: i32, stdout: &str, stderr: &str) -> CommandResult {
+        use std::os::unix::process::ExitStatusExt;
+        CommandResult::Completed(std::process::Output {
+            status: std::process::ExitStatus::from_raw(code << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        })
+    }
+
+    #[test]
+    fn resolve_work_dir_fails_instead_of_falling_back() {
+        let dir = tempdir().unwrap();
+        let run_dir = dir.path().join("run");
+        let volume = dir.path().join("volume");
+        fs::create_dir_all(run_dir.join("scripts")).unwrap();
+        fs::create_dir_all(&volume).unwrap();
+        let wd = |path: &str, create: bool| WorkingDir { path: path.to_string(), create };
+        let resolve = |w: &WorkingDir, allowed: &[PathBuf]| resolve_work_dir(&run_dir, Some(w), allowed);
+
+        assert_eq!(resolve(&wd("./scripts", false), &[]).unwrap(), run_dir.join("scripts").canonicalize().unwrap());
+        let err = resolve(&wd("missing", false), &[]).unwrap_err().to_string();
+        assert!(err.ends_with("does not exist"), "{}", err);
+        assert!(resolve(&wd("out/daily", true), &[]).unwrap().ends_with("run/out/daily"));
+
+        // Never created outside the job directory
+        let err = resolve(&wd("../escape", true), &[]).unwrap_err().to_string();
+        assert_eq!(err, "'../escape' is outside the job directory");
+        assert!(!dir.path().join("escape").exists());
+
+        let absolute = volume.join("etl").display().to_string();
+        let err = resolve(&wd(&absolute, true), &[]).unwrap_err().to_string();
+        assert!(err.ends_with("is not under runner.allowed_work_dirs"), "{}", err);
+        assert!(resolve(&wd(&absolute, true), &[volume]).unwrap().ends_with("volume/etl"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn report_from_success() {
+        let job = make_job("true", 10);
+        let result = completed(0, "out", "");
+        let report = build_report(&job, Utc::now(), Duration::from_secs(1), 1, Some(&result));
+        assert_eq!(report.outcome, RunOutcome::Success);
+        assert_eq!(report.exit_code, Some(0));
+        assert_eq!(report.stdout_tail, "out");
+        assert!(report.error.is_none());
+        assert!(report.failure_class.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn report_from_failure() {
+        let job = make_job("false", 10);
+        let result = completed(2, "", "boom");
+        let report = build_report(&job, Utc::now(), Duration::from_secs(1), 3, Some(&result));
+        assert_eq!(report.outcome, RunOutcome::Failed);
+        assert_eq!(report.exit_code, Some(2));
+        assert_eq!(report.attempts, 3);
+        assert_eq!(report.stderr_tail, "boom");
+        assert_eq!(report.error.as_deref(), Some("exit code Some(2)"));
+        assert_eq!(report.failure_class, Some(FailureClass::Other));
+
+        let result = completed(6, "", "curl: (6) Could not resolve host: example.com");
+        let report = build_report(&job, Utc::now(), Duration::from_secs(1), 1, Some(&result));
+        assert_eq!(report.failure_class, Some(FailureClass::Network));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn report_with_success_when() {
+        let mut job = make_job("legacy-tool", 10);
+        job.success_when = Some(crate::config::SuccessWhen {
+            exit_codes: vec![0, 2],
+            stdout_matches: Some(regex::Regex::new("^OK").unwrap()),
+            stderr_not_matches: Some(regex::Regex::new("FATAL").unwrap()),
+        });
+        let report = |result| build_report(&job, Utc::now(), Duration::from_secs(1), 1, Some(&result));
+
+        assert_eq!(report(completed(2, "OK with warnings", "warning: x")).outcome, RunOutcome::Success);
+        let failed = report(completed(1, "OK", ""));
+        assert_eq!(failed.outcome, RunOutcome::Failed);
+        assert_eq!(failed.error.as_deref(), Some("exit code Some(1)"));
+        let silent = report(completed(0, "OK", "FATAL: disk full"));
+        assert_eq!(silent.outcome, RunOutcome::Failed);
+        assert_eq!(silent.exit_code, Some(0));
+        assert_eq!(silent.error.as_deref(), Some("stderr matches /FATAL/"));
+        assert_eq!(report(completed(0, "", "")).error.as_deref(), Some("stdout does not match /^OK/"));
+    }
+
+    #[test]
+    fn report_from_timeout() {
+        let job = make_job("sleep 10", 1);
+        let report = build_report(&job, Utc::now(), Duration::from_secs(1), 1, Some(&CommandResult::Timeout));
+        assert_eq!(report.outcome, RunOutcome::Timeout);
+        assert!(report.exit_code.is_none());
+        assert_eq!(report.failure_class, Some(FailureClass::Timeout));
+    }
+
+    #[tokio::test]
+    async fn shell_command_runs_through_shell() {
+        let output = shell_command(Shell::default(), &CommandLine::Line("echo hello".to_string()), &[])
+            .output()
+            .await
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hello");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn argv_command_skips_shell() {
+        let command = CommandLine::Argv(vec!["echo".to_string(), "$HOME; rm -rf x".to_string()]);
+        let output = shell_command(Shell::Sh, &command, &[]).output().await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "$HOME; rm -rf x");
+    }
+
+    #[test]
+    fn job_env_files_later_override_earlier() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join(".env"), "A=base\nB=base").unwrap();
+        std::fs::write(dir.path().join(".env.shared"), "B=shared\nC=shared").unwrap();
+        std::fs::write(dir.path().join(".env.production"), "C=production").unwrap();
+
+        let mut job = make_job("true", 10);
+        job.env_file = Some(".env".to_string());
+        job.env_files = vec![".env.shared".to_string(), ".env.production".to_string()];
+
+        let vars = merge_env_vars(&job, dir.path(), dir.path(), &make_runner()).unwrap();
+        assert_eq!(vars.get("A").map(String::as_str), Some("base"));
+        assert_eq!(vars.get("B").map(String::as_str), Some("shared"));
+        assert_eq!(vars.get("C").map(String::as_str), Some("production"));
     }
 
     #[test]
@@ -0,0 +1,170 @@
+//! Host guards (`guards`): free disk space and load average checked right
+//! before a run starts, so jobs don't pile onto a host that is already in
+//! trouble.
+//!
+//! A failed guard skips the run, or with `policy: defer` rechecks until the
+//! host recovers or `max_delay` runs out. Skipped runs are logged and sent to
+//! webhooks as `job_skipped`; they don't appear in run history.
+
+use super::executor;
+use crate::clock;
+use crate::config::{GuardPolicy, Job, RunnerConfig, format_size};
+use crate::events::{self, Event};
+use crate::git;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+/// How often a deferred run rechecks its guards
+const RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether `job` may start now, waiting first if its guards defer.
+/// Sends the `job_skipped` notification when it may not.
+pub async fn admit(job: &Job, sot_path: &Path, runner: &RunnerConfig) -> bool {
+    if !job.guards.is_set() || clock::stub_jobs() {
+        return true;
+    }
+    let job_dir = git::get_job_dir(sot_path, &job.id);
+    let Some(mut reason) = violation(job, &job_dir) else {
+        return true;
+    };
+
+    if job.guards.policy == GuardPolicy::Defer {
+        info!(target: "rollcron::job", job_id = %job.id, reason = %reason, "Deferred by guards");
+        let deadline = Instant::now() + job.guards.max_delay;
+        while let Some(left) = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+            tokio::time::sleep(left.min(RECHECK_INTERVAL)).await;
+            match violation(job, &job_dir) {
+                Some(r) => reason = r,
+                None => {
+                    info!(target: "rollcron::job", job_id = %job.id, "Guards passed, starting deferred run");
+                    return true;
+                }
+            }
+        }
+    }
+
+    warn!(target: "rollcron::job", job_id = %job.id, reason = %reason, "Skipped (guards)");
+    events::emit(Event::RunSkipped { job_id: &job.id, reason: &reason });
+    executor::notify_skipped(job, sot_path, runner, &reason).await;
+    false
+}
+
+/// Why the host can't start `job` right now, if it can't. Conditions that
+/// can't be measured on this host are treated as passing.
+pub fn violation(job: &Job, job_dir: &Path) -> Option<String> {
+    let guards = &job.guards;
+    if let Some(min) = guards.min_free_disk {
+        let disk = guards.disk_path.as_deref().unwrap_or(job_dir);
+        match free_disk(disk) {
+            Some(free) if free < min => {
+                return Some(match &guards.disk_path {
+                    Some(path) => format!(
+                        "free disk {} on {} is below min_free_disk {}",
+                        format_size(free),
+                        path.display(),
+                        format_size(min)
+                    ),
+                    None => format!("free disk {} is below min_free_disk {}", format_size(free), format_size(min)),
+                });
+            }
+            Some(_) => {}
+            None => debug!(target: "rollcron::job", job_id = %job.id, "Cannot measure free disk, ignoring min_free_disk"),
+        }
+    }
+    if let Some(max) = guards.max_loadavg {
+        match loadavg() {
+            Some(load) if load > max => {
+                return Some(format!("load average {:.2} is above max_loadavg {}", load, max));
+            }
+            Some(_) => {}
+            None => debug!(target: "rollcron::job", job_id = %job.id, "Cannot read load average, ignoring max_loadavg"),
+        }
+    }
+    None
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `dir`
+/// (or its nearest existing ancestor, before the first deploy).
+#[cfg(unix)]
+fn free_disk(dir: &Path) -> Option<u64> {
+    let stat = dir.ancestors().find_map(|d| nix::sys::statvfs::statvfs(d).ok())?;
+    #[allow(clippy::unnecessary_cast)] // the field types vary by platform
+    Some(stat.blocks_available() as u64 * stat.fragment_size() as u64)
+}
+
+#[cfg(not(unix))]
+fn free_disk(_dir: &Path) -> Option<u64> {
+    None
+}
+
+/// 1-minute load average (Linux)
+fn loadavg() -> Option<f64> {
+    let content = std::fs::read_to_string("/proc/loadavg").ok()?;
+    content.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    fn job_with_guards(guards: &str) -> Job {
+        let yaml = format!(
+            "jobs:\n  guarded:\n    schedule: \"* * * * *\"\n    run: echo\n    guards: {}\n",
+            guards
+        );
+        parse_config(&yaml).unwrap().1.remove(0)
+    }
+
+    #[test]
+    fn violation_reports_the_failed_condition() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(violation(&job_with_guards("{ min_free_disk: \"1K\" }"), dir.path()), None);
+
+        if free_disk(dir.path()).is_some() {
+            let reason = violation(&job_with_guards("{ min_free_disk: \"1000000G\" }"), dir.path()).unwrap();
+            assert!(reason.ends_with("is below min_free_disk 1000000.0G"), "{}", reason);
+            // Missing directories are measured on their nearest ancestor
+            assert!(violation(&job_with_guards("{ min_free_disk: \"1000000G\" }"), &dir.path().join("a/b")).is_some());
+            let guards = format!("{{ min_free_disk: \"1000000G\", disk_path: \"{}\" }}", dir.path().display());
+            let reason = violation(&job_with_guards(&guards), Path::new("/nonexistent")).unwrap();
+            assert!(reason.contains(&format!("on {} is below", dir.path().display())), "{}", reason);
+        }
+        if loadavg().is_some() {
+            let reason = violation(&job_with_guards("{ max_loadavg: 0.000001 }"), dir.path());
+            assert!(reason.is_none_or(|r| r.contains("above max_loadavg")));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn admit_defers_then_skips() {
+        let dir = tempfile::tempdir().unwrap();
+        let sot_path = dir.path().join("repo-00000000");
+        let (runner, _) = parse_config("jobs: {}").unwrap();
+        if free_disk(dir.path()).is_none() {
+            return;
+        }
+
+        let ok = job_with_guards("{ min_free_disk: \"1K\", policy: defer }");
+        assert!(admit(&ok, &sot_path, &runner).await);
+
+        let skip = job_with_guards("{ min_free_disk: \"1000000G\" }");
+        let start = Instant::now();
+        assert!(!admit(&skip, &sot_path, &runner).await);
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        let defer = job_with_guards("{ min_free_disk: \"1000000G\", policy: defer, max_delay: 2m }");
+        let start = Instant::now();
+        assert!(!admit(&defer, &sot_path, &runner).await);
+        assert_eq!(start.elapsed(), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn format_size_picks_largest_unit() {
+        assert_eq!(format_size(5 << 30), "5.0G");
+        assert_eq!(format_size(1536 << 10), "1.5M");
+        assert_eq!(format_size(900), "900B");
+    }
+}
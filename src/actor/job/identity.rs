@@ -0,0 +1,197 @@
+//! `user` / `group`: runs a job's command under another account.
+//!
+//! As root, the child switches to the account between fork and exec (see
+//! `Confinement`): supplementary groups, then gid, then uid, the way cron runs
+//! per-user crontabs. Otherwise the command is wrapped in `sudo -n -E -u <user>`,
+//! which needs a sudoers rule allowing it (with `SETENV`, for the job's
+//! environment).
+
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// The account a run switches to, looked up per attempt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunAs {
+    /// None when only `group` is set
+    pub user: Option<Account>,
+    pub gid: u32,
+    /// Supplementary groups: the user's, or just `gid`
+    pub groups: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Account {
+    pub name: String,
+    pub uid: u32,
+    /// Primary group
+    pub gid: u32,
+    pub home: PathBuf,
+}
+
+impl RunAs {
+    /// Looks up `user` and `group` (names or numeric IDs). None if neither is set.
+    #[cfg(unix)]
+    pub fn resolve(user: Option<&str>, group: Option<&str>) -> Result<Option<Self>> {
+        let user = match user {
+            Some(user) => Some(lookup_user(user)?),
+            None => None,
+        };
+        let gid = match (group, &user) {
+            (Some(group), _) => lookup_group(group)?,
+            (None, Some(account)) => account.gid,
+            (None, None) => return Ok(None),
+        };
+        let groups = match &user {
+            Some(account) => supplementary_groups(&account.name, gid)?,
+            None => vec![gid],
+        };
+        Ok(Some(RunAs { user, gid, groups }))
+    }
+
+    #[cfg(not(unix))]
+    pub fn resolve(user: Option<&str>, group: Option<&str>) -> Result<Option<Self>> {
+        if user.is_some() || group.is_some() {
+            anyhow::bail!("user and group require Unix");
+        }
+        Ok(None)
+    }
+
+    /// True if rollcron already runs with these IDs.
+    #[cfg(unix)]
+    pub fn is_current(&self) -> bool {
+        use nix::unistd::{Gid, Uid};
+        self.user.as_ref().is_none_or(|account| account.uid == Uid::effective().as_raw())
+            && self.gid == Gid::effective().as_raw()
+    }
+
+    #[cfg(not(unix))]
+    pub fn is_current(&self) -> bool {
+        true
+    }
+
+    /// `HOME`, `USER` and `LOGNAME` of the account, as cron sets them.
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        match &self.user {
+            Some(account) => vec![
+                ("HOME", account.home.display().to_string()),
+                ("USER", account.name.clone()),
+                ("LOGNAME", account.name.clone()),
+            ],
+            None => vec![],
+        }
+    }
+
+    /// Argv prefix switching accounts through sudo, for when rollcron is not root.
+    pub fn sudo_wrapper(&self) -> Vec<String> {
+        let mut args: Vec<String> = ["sudo", "-n", "-E"].map(String::from).into();
+        if let Some(account) = &self.user {
+            args.extend(["-u".to_string(), account.name.clone()]);
+        }
+        if self.user.as_ref().is_none_or(|account| account.gid != self.gid) {
+            args.extend(["-g".to_string(), format!("#{}", self.gid)]);
+        }
+        args.push("--".to_string());
+        args
+    }
+
+    /// Drops to the account. Called in the child between fork and exec, so
+    /// it only makes syscalls.
+    #[cfg(unix)]
+    pub fn switch(&self) -> std::io::Result<()> {
+        // SAFETY: plain syscalls on a valid buffer (gid_t is u32)
+        unsafe {
+            if libc::setgroups(self.groups.len() as _, self.groups.as_ptr()) != 0 || libc::setgid(self.gid) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if let Some(account) = &self.user {
+                if libc::setuid(account.uid) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// True if rollcron can switch accounts itself.
+pub fn is_root() -> bool {
+    #[cfg(unix)]
+    return nix::unistd::Uid::effective().is_root();
+    #[cfg(not(unix))]
+    return false;
+}
+
+#[cfg(unix)]
+fn lookup_user(user: &str) -> Result<Account> {
+    use nix::unistd::{Uid, User};
+    let found = match user.parse::<u32>() {
+        Ok(uid) => User::from_uid(Uid::from_raw(uid)),
+        Err(_) => User::from_name(user),
+    };
+    let found = found
+        .map_err(|e| anyhow::anyhow!("failed to look up user '{}': {}", user, e))?
+        .ok_or_else(|| anyhow::anyhow!("unknown user '{}'", user))?;
+    Ok(Account { name: found.name, uid: found.uid.as_raw(), gid: found.gid.as_raw(), home: found.dir })
+}
+
+/// A numeric group needs no entry in the group database.
+#[cfg(unix)]
+fn lookup_group(group: &str) -> Result<u32> {
+    use nix::unistd::Group;
+    if let Ok(gid) = group.parse::<u32>() {
+        return Ok(gid);
+    }
+    let found = Group::from_name(group)
+        .map_err(|e| anyhow::anyhow!("failed to look up group '{}': {}", group, e))?
+        .ok_or_else(|| anyhow::anyhow!("unknown group '{}'", group))?;
+    Ok(found.gid.as_raw())
+}
+
+#[cfg(all(unix, not(target_vendor = "apple")))]
+fn supplementary_groups(user: &str, gid: u32) -> Result<Vec<u32>> {
+    use nix::unistd::{getgrouplist, Gid};
+    let name = std::ffi::CString::new(user)?;
+    let groups = getgrouplist(&name, Gid::from_raw(gid))
+        .map_err(|e| anyhow::anyhow!("failed to list groups of '{}': {}", user, e))?;
+    Ok(groups.into_iter().map(Gid::as_raw).collect())
+}
+
+#[cfg(target_vendor = "apple")]
+fn supplementary_groups(_user: &str, gid: u32) -> Result<Vec<u32>> {
+    Ok(vec![gid])
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_users_by_name_and_id() {
+        assert_eq!(RunAs::resolve(None, None).unwrap(), None);
+
+        let root = RunAs::resolve(Some("root"), None).unwrap().unwrap();
+        let account = root.user.as_ref().unwrap();
+        assert_eq!((account.uid, root.gid), (0, 0));
+        assert!(root.groups.contains(&0));
+        assert_eq!(root.env()[0], ("HOME", account.home.display().to_string()));
+        assert_eq!(RunAs::resolve(Some("0"), None).unwrap(), Some(root));
+
+        let group_only = RunAs::resolve(None, Some("4242")).unwrap().unwrap();
+        assert_eq!((group_only.user, group_only.gid, group_only.groups), (None, 4242, vec![4242]));
+
+        let err = RunAs::resolve(Some("no-such-user-rollcron"), None).unwrap_err();
+        assert_eq!(err.to_string(), "unknown user 'no-such-user-rollcron'");
+        assert!(RunAs::resolve(Some("root"), Some("no-such-group-rollcron")).is_err());
+    }
+
+    #[test]
+    fn sudo_wrapper_names_what_changes() {
+        let account = Account { name: "backup".to_string(), uid: 34, gid: 34, home: PathBuf::from("/var/backups") };
+        let run_as = RunAs { user: Some(account.clone()), gid: 34, groups: vec![34] };
+        assert_eq!(run_as.sudo_wrapper(), ["sudo", "-n", "-E", "-u", "backup", "--"]);
+        let run_as = RunAs { user: Some(account), gid: 1001, groups: vec![1001] };
+        assert_eq!(run_as.sudo_wrapper(), ["sudo", "-n", "-E", "-u", "backup", "-g", "#1001", "--"]);
+        let run_as = RunAs { user: None, gid: 1001, groups: vec![1001] };
+        assert_eq!(run_as.sudo_wrapper(), ["sudo", "-n", "-E", "-g", "#1001", "--"]);
+    }
+}
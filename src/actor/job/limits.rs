@@ -0,0 +1,222 @@
+//! Resource limits: runs a job's command in a transient cgroup v2.
+//!
+//! When `/sys/fs/cgroup` is writable (typically as root), a leaf cgroup is
+//! created under `/sys/fs/cgroup/rollcron/` and the command moves itself into
+//! it before exec. Otherwise the command runs in a `systemd-run --user --scope`
+//! unit. Either way the limits are a wrapper around the command's argv, so
+//! they compose with the shell and audit mode.
+
+use crate::config::ResourceLimits;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// cpu.max period (microseconds)
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// Where a limited command runs. Removes its cgroup on drop.
+pub enum LimitScope {
+    /// Leaf cgroup managed by rollcron
+    Cgroup(PathBuf),
+    /// Transient systemd scope (cleaned up by systemd)
+    Systemd { limits: ResourceLimits },
+}
+
+impl LimitScope {
+    /// Sets up a scope for one attempt of `job_id`.
+    pub fn new(job_id: &str, limits: &ResourceLimits) -> Result<Self> {
+        if !cfg!(target_os = "linux") {
+            anyhow::bail!("resource limits require Linux (cgroup v2)");
+        }
+        match create_cgroup(Path::new(CGROUP_ROOT), job_id, limits) {
+            Ok(path) => Ok(LimitScope::Cgroup(path)),
+            Err(cgroup_err) if systemd_run_available() => {
+                tracing::debug!(target: "rollcron::job", job_id = %job_id, error = %cgroup_err, "Using systemd-run for limits");
+                Ok(LimitScope::Systemd { limits: *limits })
+            }
+            Err(cgroup_err) => Err(cgroup_err.context("cannot create cgroup and systemd-run is not available")),
+        }
+    }
+
+    /// Argv prefix that runs the command inside the scope.
+    pub fn wrapper(&self) -> Vec<String> {
+        match self {
+            // `$0` is the procs file; the shell moves itself there and execs the command
+            LimitScope::Cgroup(path) => vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                r#"echo $$ > "$0" && exec "$@""#.to_string(),
+                path.join("cgroup.procs").display().to_string(),
+            ],
+            LimitScope::Systemd { limits } => {
+                let mut args: Vec<String> = ["systemd-run", "--user", "--scope", "--quiet", "--collect"]
+                    .map(String::from)
+                    .into();
+                if let Some(memory) = limits.memory {
+                    args.push(format!("--property=MemoryMax={}", memory));
+                    args.push("--property=MemorySwapMax=0".to_string());
+                }
+                if let Some(cpu) = limits.cpu {
+                    args.push(format!("--property=CPUQuota={}%", (cpu * 100.0).round() as u64));
+                }
+                args.push("--".to_string());
+                args
+            }
+        }
+    }
+
+    /// Makes the child join the cgroup itself instead of through `wrapper()`,
+    /// for a command that drops root before exec (the wrapper would run
+    /// unprivileged). Only for `LimitScope::Cgroup`.
+    #[cfg(unix)]
+    pub fn join_in_child(&self, cmd: &mut tokio::process::Command) {
+        let LimitScope::Cgroup(path) = self else {
+            return;
+        };
+        let procs = std::ffi::CString::new(path.join("cgroup.procs").into_os_string().into_encoded_bytes())
+            .unwrap_or_default();
+        // SAFETY: the closure only makes syscalls (open, write, close) on data
+        // prepared before the fork
+        unsafe {
+            cmd.pre_exec(move || {
+                // "0" moves the writing process
+                let fd = libc::open(procs.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+                if fd < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                let written = libc::write(fd, c"0".as_ptr().cast(), 1);
+                let error = std::io::Error::last_os_error();
+                libc::close(fd);
+                if written == 1 { Ok(()) } else { Err(error) }
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn join_in_child(&self, _cmd: &mut tokio::process::Command) {}
+
+    /// True if the command was killed for exceeding its memory limit.
+    pub fn oom_killed(&self, status: &ExitStatus) -> bool {
+        match self {
+            LimitScope::Cgroup(path) => std::fs::read_to_string(path.join("memory.events"))
+                .map(|events| oom_kill_count(&events) > 0)
+                .unwrap_or(false),
+            // The scope is gone once the command exits; a SIGKILL under a
+            // memory limit is the OOM killer (timeouts are reported separately)
+            LimitScope::Systemd { limits } => limits.memory.is_some() && killed_by_sigkill(status),
+        }
+    }
+}
+
+impl Drop for LimitScope {
+    fn drop(&mut self) {
+        if let LimitScope::Cgroup(path) = self {
+            let _ = std::fs::remove_dir(path);
+        }
+    }
+}
+
+fn create_cgroup(root: &Path, job_id: &str, limits: &ResourceLimits) -> Result<PathBuf> {
+    if !root.join("cgroup.controllers").exists() {
+        anyhow::bail!("cgroup v2 is not mounted at {}", root.display());
+    }
+    let parent = root.join("rollcron");
+    std::fs::create_dir_all(&parent).with_context(|| format!("failed to create {}", parent.display()))?;
+    enable_controllers(root)?;
+    enable_controllers(&parent)?;
+
+    let suffix: u32 = rand::Rng::r#gen(&mut rand::thread_rng());
+    let path = parent.join(format!("{}-{:08x}", job_id, suffix));
+    std::fs::create_dir(&path).with_context(|| format!("failed to create {}", path.display()))?;
+
+    let write = |file: &str, value: String| {
+        std::fs::write(path.join(file), value).with_context(|| format!("failed to set {}", file))
+    };
+    let configured = (|| {
+        if let Some(memory) = limits.memory {
+            write("memory.max", memory.to_string())?;
+            // Without this, memory.max only pushes the job into swap
+            let _ = write("memory.swap.max", "0".to_string());
+        }
+        if let Some(cpu) = limits.cpu {
+            let quota = (cpu * CPU_PERIOD_US as f64).round().max(1000.0) as u64;
+            write("cpu.max", format!("{} {}", quota, CPU_PERIOD_US))?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = configured {
+        let _ = std::fs::remove_dir(&path);
+        return Err(e);
+    }
+    Ok(path)
+}
+
+fn enable_controllers(dir: &Path) -> Result<()> {
+    let control = dir.join("cgroup.subtree_control");
+    let enabled = std::fs::read_to_string(&control).unwrap_or_default();
+    if enabled.split_whitespace().any(|c| c == "memory") && enabled.split_whitespace().any(|c| c == "cpu") {
+        return Ok(());
+    }
+    std::fs::write(&control, "+memory +cpu")
+        .with_context(|| format!("failed to enable memory/cpu controllers in {}", dir.display()))
+}
+
+fn systemd_run_available() -> bool {
+    std::process::Command::new("systemd-run")
+        .arg("--version")
+        .output()
+        .is_ok_and(|o| o.status.success())
+}
+
+fn oom_kill_count(events: &str) -> u64 {
+    events
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|n| n.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(unix)]
+fn killed_by_sigkill(status: &ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal() == Some(9)
+}
+
+#[cfg(not(unix))]
+fn killed_by_sigkill(_status: &ExitStatus) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_oom_kill_count() {
+        let events = "low 0\nhigh 0\nmax 12\noom 1\noom_kill 1\noom_group_kill 0\n";
+        assert_eq!(oom_kill_count(events), 1);
+        assert_eq!(oom_kill_count("low 0\n"), 0);
+    }
+
+    #[test]
+    fn systemd_wrapper_sets_properties() {
+        let scope = LimitScope::Systemd {
+            limits: ResourceLimits { memory: Some(512 * 1024 * 1024), cpu: Some(0.5) },
+        };
+        let wrapper = scope.wrapper();
+        assert_eq!(wrapper[..3], ["systemd-run", "--user", "--scope"]);
+        assert!(wrapper.contains(&"--property=MemoryMax=536870912".to_string()));
+        assert!(wrapper.contains(&"--property=CPUQuota=50%".to_string()));
+        assert_eq!(wrapper.last().unwrap(), "--");
+    }
+
+    #[test]
+    fn cgroup_requires_v2_mount() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = ResourceLimits { memory: Some(1024), cpu: None };
+        let err = create_cgroup(dir.path(), "job", &limits).unwrap_err();
+        assert!(err.to_string().contains("cgroup v2 is not mounted"));
+    }
+}
@@ -0,0 +1,186 @@
+//! Output of the latest run of each job, readable while it runs (dashboard live tail).
+//!
+//! stdout and stderr are interleaved as they arrive. Only the last
+//! `LIVE_OUTPUT_BYTES` are kept; readers poll with the offset they have seen,
+//! or `follow` a run to receive its output as it is written (`rollcron logs -f`).
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::broadcast;
+
+/// Bytes of output kept per job
+const LIVE_OUTPUT_BYTES: usize = 64 * 1024;
+/// Chunks a follower may fall behind before it misses output
+const FOLLOW_CAPACITY: usize = 1024;
+
+#[derive(Debug, Default)]
+struct Buffer {
+    started_at: Option<DateTime<Utc>>,
+    running: bool,
+    /// Total bytes written during this run
+    end: u64,
+    data: Vec<u8>,
+    /// Followers of the job, kept across runs (None until the first follows)
+    followers: Option<broadcast::Sender<Follow>>,
+}
+
+/// What a follower receives
+#[derive(Debug, Clone, PartialEq)]
+pub enum Follow {
+    Output(Vec<u8>),
+    /// The run ended; no more output until the next one
+    Finished,
+}
+
+/// Output after a reader's offset
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveChunk {
+    pub started_at: Option<DateTime<Utc>>,
+    pub running: bool,
+    /// Offset of the first byte of `data`
+    pub offset: u64,
+    /// Offset to pass as `since` next time
+    pub end: u64,
+    pub data: Vec<u8>,
+}
+
+fn buffers() -> &'static Mutex<HashMap<String, Buffer>> {
+    static BUFFERS: OnceLock<Mutex<HashMap<String, Buffer>>> = OnceLock::new();
+    BUFFERS.get_or_init(Default::default)
+}
+
+/// Starts a new run, discarding the previous run's output.
+pub fn begin(job_id: &str, started_at: DateTime<Utc>) {
+    let mut buffers = buffers().lock().unwrap_or_else(|e| e.into_inner());
+    let followers = buffers.remove(job_id).and_then(|buffer| buffer.followers);
+    buffers.insert(
+        job_id.to_string(),
+        Buffer {
+            started_at: Some(started_at),
+            running: true,
+            followers,
+            ..Default::default()
+        },
+    );
+}
+
+pub fn append(job_id: &str, chunk: &[u8]) {
+    let mut buffers = buffers().lock().unwrap_or_else(|e| e.into_inner());
+    let Some(buffer) = buffers.get_mut(job_id) else {
+        return;
+    };
+    buffer.end += chunk.len() as u64;
+    buffer.data.extend_from_slice(chunk);
+    let excess = buffer.data.len().saturating_sub(LIVE_OUTPUT_BYTES);
+    buffer.data.drain(..excess);
+    if let Some(followers) = &buffer.followers {
+        // No receivers left is fine
+        let _ = followers.send(Follow::Output(chunk.to_vec()));
+    }
+}
+
+/// Marks the run finished; its output stays readable until the next run.
+pub fn finish(job_id: &str) {
+    let mut buffers = buffers().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(buffer) = buffers.get_mut(job_id) {
+        buffer.running = false;
+        if let Some(followers) = &buffer.followers {
+            let _ = followers.send(Follow::Finished);
+        }
+    }
+}
+
+/// Output retained after `since` and, if a run is in progress, a receiver
+/// for the rest of it. Both are taken under one lock, so nothing falls
+/// between them.
+pub fn follow(job_id: &str, since: u64) -> (LiveChunk, Option<broadcast::Receiver<Follow>>) {
+    let mut buffers = buffers().lock().unwrap_or_else(|e| e.into_inner());
+    let chunk = read_locked(&buffers, job_id, since);
+    let receiver = match buffers.get_mut(job_id) {
+        Some(buffer) if buffer.running => {
+            Some(buffer.followers.get_or_insert_with(|| broadcast::channel(FOLLOW_CAPACITY).0).subscribe())
+        }
+        _ => None,
+    };
+    (chunk, receiver)
+}
+
+/// Output written after `since`. If `since` is beyond the end (a new run
+/// started), everything retained is returned.
+pub fn read(job_id: &str, since: u64) -> LiveChunk {
+    read_locked(&buffers().lock().unwrap_or_else(|e| e.into_inner()), job_id, since)
+}
+
+fn read_locked(buffers: &HashMap<String, Buffer>, job_id: &str, since: u64) -> LiveChunk {
+    let Some(buffer) = buffers.get(job_id) else {
+        return LiveChunk { started_at: None, running: false, offset: 0, end: 0, data: Vec::new() };
+    };
+    let first = buffer.end - buffer.data.len() as u64;
+    let offset = if since > buffer.end { first } else { since.max(first) };
+    LiveChunk {
+        started_at: buffer.started_at,
+        running: buffer.running,
+        offset,
+        end: buffer.end,
+        data: buffer.data[(offset - first) as usize..].to_vec(),
+    }
+}
+
+/// Drops a removed job's output.
+pub fn remove(job_id: &str) {
+    buffers().lock().unwrap_or_else(|e| e.into_inner()).remove(job_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tail_follows_offsets_and_truncates() {
+        let job = "live-test";
+        begin(job, Utc::now());
+        append(job, b"hello ");
+        let chunk = read(job, 0);
+        assert_eq!((chunk.offset, chunk.end, chunk.data.as_slice()), (0, 6, &b"hello "[..]));
+        assert!(chunk.running);
+
+        append(job, b"world");
+        finish(job);
+        let chunk = read(job, 6);
+        assert_eq!(chunk.data, b"world");
+        assert!(!chunk.running);
+
+        append(job, &vec![b'x'; LIVE_OUTPUT_BYTES]);
+        let chunk = read(job, 0);
+        assert_eq!(chunk.offset, 11);
+        assert_eq!(chunk.data.len(), LIVE_OUTPUT_BYTES);
+
+        begin(job, Utc::now());
+        let chunk = read(job, 5000);
+        assert_eq!((chunk.offset, chunk.end), (0, 0), "new run restarts the reader");
+        remove(job);
+        assert_eq!(read(job, 0).started_at, None);
+    }
+
+    #[test]
+    fn followers_get_output_until_the_run_finishes() {
+        let job = "follow-test";
+        assert!(follow(job, 0).1.is_none(), "nothing to follow before a run");
+
+        begin(job, Utc::now());
+        append(job, b"before ");
+        let (chunk, receiver) = follow(job, 0);
+        let mut receiver = receiver.unwrap();
+        assert_eq!(chunk.data, b"before ");
+        append(job, b"after");
+        finish(job);
+        assert_eq!(receiver.try_recv().unwrap(), Follow::Output(b"after".to_vec()));
+        assert_eq!(receiver.try_recv().unwrap(), Follow::Finished);
+
+        // Finished runs are read, not followed
+        let (chunk, receiver) = follow(job, 7);
+        assert_eq!((chunk.data.as_slice(), receiver.is_none()), (&b"after"[..], true));
+        remove(job);
+    }
+}
@@ -1,11 +1,34 @@
+mod alert;
+mod anomaly;
+mod artifacts;
+mod audit;
+mod breaker;
+mod changes;
+mod classify;
+mod cleanup;
+mod dispatch;
 mod executor;
+mod guard;
+mod identity;
+mod limits;
+pub mod live;
+mod output;
+mod pause;
+mod process;
+pub mod report;
+mod sandbox;
+mod security;
 mod tick;
 
-use crate::actor::runner::{BuildCompleted as RunnerBuildCompleted, JobCompleted, JobFailed, RunnerActor};
-use crate::config::{Concurrency, Job, RunnerConfig};
-use crate::git;
-use chrono::Utc;
+use crate::actor::runner::{git_poll, BuildCompleted as RunnerBuildCompleted, JobCompleted, JobFailed, RunnerActor, SyncAttempted};
+use crate::config::{Concurrency, EnvValue, Job, ResumePolicy, RunnerConfig};
+use crate::events::{self, Event};
+use crate::otel::Span;
+use crate::{clock, git, ha, history, policy, source};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Duration;
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
 use tokio::time::{sleep_until, Instant};
@@ -13,8 +36,16 @@ use tracing::{error, info, warn};
 use xtra::prelude::*;
 use xtra::refcount::Weak;
 
-use executor::{execute_build, execute_job, BuildResult};
-use tick::next_occurrence;
+pub use anomaly::degrading;
+pub use artifacts::run_dir as artifacts_dir;
+pub use executor::{execute_build, execute_job, BuildResult};
+pub use report::{JobRunReport, RunStart, RunTrigger};
+use report::RunOutcome;
+pub use tick::{disposition, next_in_timezone, next_occurrence_from, Disposition};
+use tick::{missed_runs, next_run, next_run_from};
+
+/// What requested a run and the overrides it applies, carried until the run spawns
+type PendingStart = (RunTrigger, RunOverrides);
 
 /// Job Actor - manages a single job's lifecycle
 pub struct JobActor {
@@ -28,11 +59,21 @@ pub struct JobActor {
     scheduler_handle: Option<JoinHandle<()>>,
     config_tx: watch::Sender<(Job, RunnerConfig)>,
     stopping: bool,
+    paused: bool,
+    /// When an operator paused the job, persisted so it outlives a restart
+    paused_locally: Option<DateTime<Utc>>,
+    /// Host policy block reason (see `policy`); no build or run until approved
+    blocked: Option<String>,
     // Build state
     build_in_progress: bool,
     build_handle: Option<JoinHandle<()>>,
     pending_copy: bool,  // build done, waiting for execution to finish
-    pending_run: bool,   // tick arrived during first build, run after build completes
+    /// Run requested during the first build, started once it succeeds
+    pending_run: Option<PendingStart>,
+    /// `pull_on_run` found a new commit: run once it is built
+    pulled_run: Option<PendingStart>,
+    /// Start of the latest run, for `min_interval` (`None` until read from history)
+    last_started: Option<Option<DateTime<Utc>>>,
 }
 
 impl JobActor {
@@ -54,10 +95,15 @@ impl JobActor {
             scheduler_handle: None,
             config_tx,
             stopping: false,
+            paused: false,
+            paused_locally: None,
+            blocked: None,
             build_in_progress: false,
             build_handle: None,
             pending_copy: false,
-            pending_run: false,
+            pending_run: None,
+            pulled_run: None,
+            last_started: None,
         }
     }
 
@@ -66,7 +112,11 @@ impl JobActor {
             info!(target: "rollcron::job", job_id = %self.job.id, "Copying build to run directory");
             let build_dir = git::get_build_dir(&self.sot_path, &self.job.id);
             let run_dir = git::get_run_dir(&self.sot_path, &self.job.id);
-            git::copy_build_to_run(&build_dir, &run_dir)?;
+            let mut span = Span::root("git.archive").with_attr("job.id", self.job.id.as_str());
+            if let Err(e) = git::copy_build_to_run(&build_dir, &run_dir) {
+                span.fail(&e);
+                return Err(e);
+            }
             self.pending_copy = false;
             Ok(true)
         } else {
@@ -93,8 +143,13 @@ impl JobActor {
         }
 
         let mut config_rx = self.config_tx.subscribe();
+        let mut resumes = clock::resumes();
 
         self.scheduler_handle = Some(tokio::spawn(async move {
+            // A simulated clock can jump past several occurrences while the
+            // previous one is dispatched; continue from the last one fired
+            // so none are skipped.
+            let mut last_fired = None;
             loop {
                 let (job, runner) = config_rx.borrow_and_update().clone();
 
@@ -106,8 +161,12 @@ impl JobActor {
                     continue;
                 }
 
-                let next = match next_occurrence(&job, &runner) {
-                    Some(dt) => dt,
+                let next = match last_fired.filter(|_| clock::is_simulated()) {
+                    Some(last) => next_run_from(&job, &runner, last),
+                    None => next_run(&job, &runner),
+                };
+                let next = match next {
+                    Some(next) => next,
                     None => {
                         // No future occurrence, wait for config change
                         if config_rx.changed().await.is_err() {
@@ -117,33 +176,58 @@ impl JobActor {
                     }
                 };
 
-                let now = Utc::now();
-                let wait_duration = if next > now {
-                    (next - now).to_std().unwrap_or_default()
-                } else {
-                    std::time::Duration::ZERO
-                };
+                let wait_duration = clock::until(next.at);
                 let deadline = Instant::now() + wait_duration;
 
-                info!(
-                    target: "rollcron::job",
-                    job_id = %job.id,
-                    next = %next,
-                    wait_secs = wait_duration.as_secs(),
-                    "Scheduled"
-                );
+                if next.skipped > 0 {
+                    info!(target: "rollcron::job", job_id = %job.id, skipped = next.skipped, "Skipping runs in blackout window");
+                }
+                match &next.deferred_by {
+                    Some(window) => info!(
+                        target: "rollcron::job",
+                        job_id = %job.id,
+                        next = %next.at,
+                        window = %window,
+                        wait_secs = wait_duration.as_secs(),
+                        "Scheduled (deferred by blackout window)"
+                    ),
+                    None => info!(
+                        target: "rollcron::job",
+                        job_id = %job.id,
+                        next = %next.at,
+                        wait_secs = wait_duration.as_secs(),
+                        "Scheduled"
+                    ),
+                }
 
                 tokio::select! {
                     _ = sleep_until(deadline) => {
+                        last_fired = Some(next.at);
+                        // Where sleeps count suspended time, they all end at once on resume
+                        let late = clock::now() - next.at > chrono::Duration::from_std(clock::SUSPEND_THRESHOLD).unwrap_or_default();
+                        if late && !clock::is_simulated() && !coalesce_missed(&job, &runner, next.at) {
+                            continue;
+                        }
                         if addr.send(Execute).await.is_err() {
                             break;
                         }
                     }
+                    Ok(()) = resumes.changed() => {
+                        if next.at <= clock::now() && coalesce_missed(&job, &runner, next.at) {
+                            last_fired = Some(next.at);
+                            if addr.send(Execute).await.is_err() {
+                                break;
+                            }
+                        }
+                        // The monotonic deadline overshoots by the time suspended
+                        continue;
+                    }
                     result = config_rx.changed() => {
                         if result.is_err() {
                             break;
                         }
                         // Config changed, recalculate next occurrence
+                        last_fired = None;
                         continue;
                     }
                 }
@@ -154,6 +238,19 @@ impl JobActor {
     fn update_config(&mut self) {
         let _ = self.config_tx.send((self.job.clone(), self.runner.clone()));
     }
+
+    /// Re-evaluates the host policy for the current config and approval.
+    fn refresh_blocked(&mut self) {
+        let blocked = policy::blocked_reason(&self.job, &self.sot_path);
+        match (&self.blocked, &blocked) {
+            (None, Some(reason)) => {
+                warn!(target: "rollcron::job", job_id = %self.job.id, reason = %reason, "Job blocked by host policy")
+            }
+            (Some(_), None) => info!(target: "rollcron::job", job_id = %self.job.id, "Job unblocked"),
+            _ => {}
+        }
+        self.blocked = blocked;
+    }
 }
 
 impl Actor for JobActor {
@@ -162,6 +259,12 @@ impl Actor for JobActor {
     async fn started(&mut self, mailbox: &Mailbox<Self>) -> Result<(), Self::Stop> {
         let addr = mailbox.address();
         self.self_addr = Some(addr.clone());
+        self.refresh_blocked();
+        self.paused_locally = pause::load(&git::get_history_dir(&self.sot_path, &self.job.id));
+        if let Some(since) = self.paused_locally {
+            self.paused = true;
+            info!(target: "rollcron::job", job_id = %self.job.id, since = %since, "Job paused (persisted, `rollcron ctl resume` to resume)");
+        }
         self.start_scheduler(addr);
         info!(target: "rollcron::job", job_id = %self.job.id, "Job actor started");
         Ok(())
@@ -177,10 +280,27 @@ impl Actor for JobActor {
         for handle in self.handles.drain(..) {
             handle.abort();
         }
+        live::remove(&self.job.id);
         info!(target: "rollcron::job", job_id = %self.job.id, "Job actor stopped");
     }
 }
 
+/// Coalesces the runs due since `due` that the host slept through into at
+/// most one, per `runner.on_resume`. Returns whether to run now.
+fn coalesce_missed(job: &Job, runner: &RunnerConfig, due: DateTime<Utc>) -> bool {
+    let missed = missed_runs(job, runner, due, clock::now());
+    match runner.on_resume {
+        ResumePolicy::CatchUp => {
+            info!(target: "rollcron::job", job_id = %job.id, missed, "Catching up once after suspend");
+            true
+        }
+        ResumePolicy::Skip => {
+            info!(target: "rollcron::job", job_id = %job.id, missed, "Skipping runs missed during suspend");
+            false
+        }
+    }
+}
+
 // === Messages ===
 
 /// Signal from scheduler that it's time to execute the job
@@ -193,39 +313,234 @@ impl Handler<Execute> for JobActor {
         if self.stopping || !self.job.enabled {
             return;
         }
-
-        let Some(addr) = self.self_addr.clone() else {
+        if self.paused {
+            info!(target: "rollcron::job", job_id = %self.job.id, "Skipped (paused)");
             return;
-        };
+        }
+        if !ha::is_leader() {
+            info!(target: "rollcron::job", job_id = %self.job.id, "Skipped (standby)");
+            return;
+        }
+        if let Some(reason) = self.breaker_hold() {
+            info!(target: "rollcron::job", job_id = %self.job.id, "Skipped ({})", reason);
+            events::emit(Event::RunSkipped { job_id: &self.job.id, reason: &reason });
+            return;
+        }
 
         info!(target: "rollcron::job", job_id = %self.job.id, "Job triggered");
+        if let Err(e) = self.execute((RunTrigger::Schedule, RunOverrides::default())).await {
+            warn!(target: "rollcron::job", job_id = %self.job.id, "Skipped: {}", e);
+        }
+    }
+}
 
-        // Trigger build/sync if pending
-        if self.pending_sync && !self.build_in_progress {
-            self.start_build(addr.clone());
+/// Run once at daemon startup (`run_on_start`), in addition to the schedule
+pub struct RunOnStart;
+
+impl Handler<RunOnStart> for JobActor {
+    type Return = ();
+
+    async fn handle(&mut self, _msg: RunOnStart, _ctx: &mut Context<Self>) {
+        if self.stopping || !self.job.enabled || self.paused {
+            return;
+        }
+        if !ha::is_leader() {
+            info!(target: "rollcron::job", job_id = %self.job.id, "Skipped startup run (standby)");
+            return;
+        }
+        if let Some(reason) = self.breaker_hold() {
+            info!(target: "rollcron::job", job_id = %self.job.id, "Skipped startup run ({})", reason);
+            return;
         }
 
-        // Check if run directory exists
-        if !self.run_dir_exists() {
-            // First run: wait for build to complete, then run
-            if self.build_in_progress {
-                info!(
-                    target: "rollcron::job",
-                    job_id = %self.job.id,
-                    "Waiting for initial build to complete"
-                );
-                self.pending_run = true;
-            } else {
-                warn!(
-                    target: "rollcron::job",
-                    job_id = %self.job.id,
-                    "Skipped: run directory not ready and no build in progress"
-                );
+        info!(target: "rollcron::job", job_id = %self.job.id, "Running on startup");
+        if let Err(e) = self.execute((RunTrigger::Startup, RunOverrides::default())).await {
+            warn!(target: "rollcron::job", job_id = %self.job.id, "Skipped: {}", e);
+        }
+    }
+}
+
+/// Run the job now, outside its schedule (runs even while paused)
+#[derive(Default)]
+pub struct Trigger {
+    pub overrides: RunOverrides,
+}
+
+/// Settings a manual trigger changes for that one run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunOverrides {
+    /// Added to the run environment above `run.env`
+    pub env: BTreeMap<String, String>,
+    pub timeout: Option<Duration>,
+}
+
+impl RunOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.env.is_empty() && self.timeout.is_none()
+    }
+
+    /// Names of the overridden settings, e.g. `env.DATE`, `run.timeout`
+    pub fn names(&self) -> Vec<String> {
+        let env = self.env.keys().map(|key| format!("env.{}", key));
+        env.chain(self.timeout.map(|_| "run.timeout".to_string())).collect()
+    }
+
+    /// The first `env` key the job's `env` and `run.env` don't declare.
+    /// Only declared variables can be overridden, so a trigger can't inject
+    /// `LD_PRELOAD`, `PATH` and the like.
+    pub fn undeclared_env<'a>(&'a self, job: &Job) -> Option<&'a str> {
+        let declared = |key: &str| [&job.env, &job.run_env].into_iter().flatten().any(|env| env.contains_key(key));
+        self.env.keys().map(String::as_str).find(|key| !declared(key))
+    }
+
+    fn apply(&self, job: &mut Job) {
+        if !self.env.is_empty() {
+            let run_env = job.run_env.get_or_insert_with(Default::default);
+            for (key, value) in &self.env {
+                run_env.insert(key.clone(), EnvValue::Plain(value.clone()));
+            }
+        }
+        if let Some(timeout) = self.timeout {
+            job.timeout = timeout;
+        }
+    }
+}
+
+impl Handler<Trigger> for JobActor {
+    type Return = anyhow::Result<()>;
+
+    async fn handle(&mut self, msg: Trigger, _ctx: &mut Context<Self>) -> Self::Return {
+        if self.stopping {
+            anyhow::bail!("Job is stopping");
+        }
+        if !self.job.enabled {
+            anyhow::bail!("Job is disabled");
+        }
+        if !ha::is_leader() {
+            anyhow::bail!("This instance is an HA standby; trigger the leader");
+        }
+        if let Some(key) = msg.overrides.undeclared_env(&self.job) {
+            anyhow::bail!("env.{} can't be overridden: not declared in the job's env or run.env", key);
+        }
+
+        if msg.overrides.is_empty() {
+            info!(target: "rollcron::job", job_id = %self.job.id, "Job triggered manually");
+        } else {
+            let overrides = msg.overrides.names().join(",");
+            info!(target: "rollcron::job", job_id = %self.job.id, overrides = %overrides, "Job triggered manually");
+        }
+        self.execute((RunTrigger::Manual, msg.overrides)).await
+    }
+}
+
+/// Stop scheduled runs until resumed (manual triggers still run)
+pub struct Pause {
+    /// Keep the job paused across restarts (operator pauses, not the brake's)
+    pub persist: bool,
+}
+
+impl Handler<Pause> for JobActor {
+    type Return = ();
+
+    async fn handle(&mut self, msg: Pause, _ctx: &mut Context<Self>) {
+        if !self.paused {
+            self.paused = true;
+            info!(target: "rollcron::job", job_id = %self.job.id, "Job paused");
+        }
+        if msg.persist && self.paused_locally.is_none() {
+            let now = clock::now();
+            match pause::save(&git::get_history_dir(&self.sot_path, &self.job.id), now) {
+                Ok(()) => self.paused_locally = Some(now),
+                Err(e) => warn!(target: "rollcron::job", job_id = %self.job.id, error = %e, "Failed to persist pause"),
+            }
+        }
+    }
+}
+
+/// Resume scheduled runs, also resetting the `circuit_breaker`
+pub struct Resume;
+
+impl Handler<Resume> for JobActor {
+    type Return = ();
+
+    async fn handle(&mut self, _msg: Resume, _ctx: &mut Context<Self>) {
+        if self.paused {
+            self.paused = false;
+            info!(target: "rollcron::job", job_id = %self.job.id, "Job resumed");
+        }
+        if self.paused_locally.take().is_some() {
+            if let Err(e) = pause::clear(&git::get_history_dir(&self.sot_path, &self.job.id)) {
+                warn!(target: "rollcron::job", job_id = %self.job.id, error = %e, "Failed to clear persisted pause");
             }
-            return;
         }
+        self.reset_breaker("resumed");
+    }
+}
+
+/// Approve the job's commands flagged by the host policy.
+/// Returns false if nothing was flagged.
+pub struct Approve;
 
-        self.handle_trigger(addr).await;
+impl Handler<Approve> for JobActor {
+    type Return = anyhow::Result<bool>;
+
+    async fn handle(&mut self, _msg: Approve, _ctx: &mut Context<Self>) -> Self::Return {
+        if !policy::approve(&self.job, &self.sot_path)? {
+            return Ok(false);
+        }
+        info!(target: "rollcron::job", job_id = %self.job.id, "Flagged commands approved");
+        self.refresh_blocked();
+        Ok(true)
+    }
+}
+
+/// Snapshot of a job's config and scheduling state (for the status API)
+pub struct GetStatus;
+
+#[derive(Debug, Clone)]
+pub struct JobStatus {
+    pub job: Job,
+    pub runner: RunnerConfig,
+    pub paused: bool,
+    /// When an operator paused the job (persisted across restarts); None for
+    /// brake pauses
+    pub paused_locally: Option<DateTime<Utc>>,
+    /// Host policy block reason
+    pub blocked: Option<String>,
+    pub running: usize,
+    pub building: bool,
+    /// None if disabled or the schedule has no future occurrence
+    pub next_run: Option<DateTime<Utc>>,
+    /// When the `circuit_breaker` opened, if it is open
+    pub circuit_open: Option<DateTime<Utc>>,
+}
+
+impl Handler<GetStatus> for JobActor {
+    type Return = JobStatus;
+
+    async fn handle(&mut self, _msg: GetStatus, _ctx: &mut Context<Self>) -> Self::Return {
+        self.cleanup_finished_handles();
+        JobStatus {
+            job: self.job.clone(),
+            runner: self.runner.clone(),
+            paused: self.paused,
+            paused_locally: self.paused_locally,
+            blocked: self.blocked.clone(),
+            running: self.running_count(),
+            building: self.build_in_progress,
+            next_run: self
+                .job
+                .enabled
+                .then(|| next_run(&self.job, &self.runner))
+                .flatten()
+                .map(|next| next.at),
+            circuit_open: self
+                .job
+                .circuit_breaker
+                .as_ref()
+                .and_then(|_| breaker::open(&git::get_history_dir(&self.sot_path, &self.job.id)))
+                .and_then(|state| state.open_since),
+        }
     }
 }
 
@@ -242,7 +557,10 @@ impl Handler<Update> for JobActor {
         self.job = msg.job;
         self.runner = msg.runner;
         self.update_config();
+        self.refresh_blocked();
         info!(target: "rollcron::job", job_id = %self.job.id, "Job config updated");
+        // A new commit may carry the fix
+        self.reset_breaker("new commit");
     }
 }
 
@@ -257,6 +575,7 @@ impl Handler<SyncNeeded> for JobActor {
     async fn handle(&mut self, msg: SyncNeeded, _ctx: &mut Context<Self>) {
         self.sot_path = msg.sot_path;
         self.pending_sync = true;
+        self.refresh_blocked();
 
         // Start build immediately if not already in progress
         if !self.build_in_progress {
@@ -294,15 +613,23 @@ impl Handler<BuildCompleted> for JobActor {
             }
 
             // Run job if it was waiting for this build
-            if self.pending_run {
-                self.pending_run = false;
+            if let Some(start) = self.pending_run.take() {
                 if let Some(addr) = self.self_addr.clone() {
-                    self.handle_trigger(addr).await;
+                    self.handle_trigger(addr, start).await;
                 }
             }
         } else {
             warn!(target: "rollcron::job", job_id = %self.job.id, "Build failed, keeping old run directory");
-            self.pending_run = false;
+            self.pending_run = None;
+        }
+
+        // A pulled run waits for the last build queued, which has the pulled commit
+        if let Some(start) = self.pulled_run.take_if(|_| !self.pending_sync) {
+            match (msg.success, self.self_addr.clone()) {
+                (true, Some(addr)) => self.handle_trigger(addr, start).await,
+                (false, _) => warn!(target: "rollcron::job", job_id = %self.job.id, "Skipped: the pulled commit failed to build"),
+                (true, None) => {}
+            }
         }
 
         // If there's another pending sync (config changed during build), start another build
@@ -314,6 +641,42 @@ impl Handler<BuildCompleted> for JobActor {
     }
 }
 
+/// Internal message: the `pull_on_run` sync finished (`updated` if it found a new commit)
+struct Pulled {
+    updated: bool,
+    start: PendingStart,
+}
+
+impl Handler<Pulled> for JobActor {
+    type Return = ();
+
+    async fn handle(&mut self, msg: Pulled, _ctx: &mut Context<Self>) {
+        if self.stopping {
+            return;
+        }
+        let Some(addr) = self.self_addr.clone() else {
+            return;
+        };
+        if !msg.updated {
+            if let Err(e) = self.execute_current(addr, msg.start).await {
+                warn!(target: "rollcron::job", job_id = %self.job.id, "Skipped: {}", e);
+            }
+            return;
+        }
+        if let Some(reason) = &self.blocked {
+            warn!(target: "rollcron::job", job_id = %self.job.id, "Skipped: {}", reason);
+            return;
+        }
+        // Built after any build in progress, which may be of the previous commit
+        info!(target: "rollcron::job", job_id = %self.job.id, "Waiting for the pulled commit to build");
+        self.pending_sync = true;
+        self.pulled_run = Some(msg.start);
+        if !self.build_in_progress {
+            self.start_build(addr);
+        }
+    }
+}
+
 /// Internal message: try to copy build to run
 struct TryCopy;
 
@@ -327,6 +690,36 @@ impl Handler<TryCopy> for JobActor {
     }
 }
 
+/// Discard the job directory and rebuild it from the SoT at the current commit.
+/// Refused while the job is running or building.
+pub struct Resync;
+
+impl Handler<Resync> for JobActor {
+    type Return = anyhow::Result<()>;
+
+    async fn handle(&mut self, _msg: Resync, _ctx: &mut Context<Self>) -> Self::Return {
+        self.cleanup_finished_handles();
+        if self.stopping {
+            anyhow::bail!("Job is stopping");
+        }
+        if self.running_count() > 0 {
+            anyhow::bail!("Job is running; retry after it finishes");
+        }
+        if self.build_in_progress {
+            anyhow::bail!("Build in progress; retry after it finishes");
+        }
+
+        warn!(target: "rollcron::job", job_id = %self.job.id, "Discarding job directory for resync");
+        git::remove_job_dir(&self.sot_path, &self.job.id);
+        self.pending_copy = false;
+        self.pending_sync = true;
+        if let Some(addr) = self.self_addr.clone() {
+            self.start_build(addr);
+        }
+        Ok(())
+    }
+}
+
 /// Immediate shutdown
 pub struct Shutdown;
 
@@ -365,7 +758,8 @@ impl Handler<GracefulStop> for JobActor {
 
 impl JobActor {
     fn start_build(&mut self, addr: Address<Self, Weak>) {
-        if self.build_in_progress {
+        // A blocked job's build command is not run either
+        if self.build_in_progress || self.blocked.is_some() {
             return;
         }
 
@@ -379,22 +773,37 @@ impl JobActor {
         info!(target: "rollcron::job", job_id = %job.id, "Starting build process");
 
         let handle = tokio::spawn(async move {
+            let mut build_span = Span::root("job.build").with_attr("job.id", job.id.as_str());
+
             // Step 1: Sync build directory
             let build_dir = git::get_build_dir(&sot_path, &job.id);
-            if let Err(e) = git::sync_to_build_dir(&sot_path, &build_dir) {
+            let mut sync_span = build_span.child("git.worktree");
+            if let Err(e) = git::sync_to_build_dir(&sot_path, &build_dir, runner.submodules, runner.lfs) {
                 error!(target: "rollcron::job", job_id = %job.id, error = %e, "Build sync failed");
+                sync_span.fail(&e);
+                build_span.fail("build sync failed");
+                drop((sync_span, build_span));
                 let _ = addr.send(BuildCompleted { success: false }).await;
                 return;
             }
+            drop(sync_span);
 
             // Step 2: Run build command (if configured)
             let result = execute_build(&job, &sot_path, &runner).await;
 
             let success = match result {
-                BuildResult::Success => true,
+                BuildResult::Success => {
+                    events::emit(Event::BuildFinished { job_id: &job.id, success: true, error: None });
+                    true
+                }
                 BuildResult::NoBuild => true, // No build command, treat as success
-                BuildResult::Failed { .. } => false,
+                BuildResult::Failed { error, .. } => {
+                    events::emit(Event::BuildFinished { job_id: &job.id, success: false, error: Some(&error) });
+                    build_span.fail(error);
+                    false
+                }
             };
+            drop(build_span);
 
             let _ = addr.send(BuildCompleted { success }).await;
         });
@@ -402,13 +811,151 @@ impl JobActor {
         self.build_handle = Some(handle);
     }
 
-    async fn handle_trigger(&mut self, addr: Address<Self, Weak>) {
+    /// Starts a run (building first if needed). Errors if the job cannot run yet.
+    /// With `pull_on_run`, the repo is synced first and the run starts once that's done.
+    async fn execute(&mut self, start: PendingStart) -> anyhow::Result<()> {
+        let Some(addr) = self.self_addr.clone() else {
+            anyhow::bail!("Job actor not started");
+        };
+        if let Some(reason) = &self.blocked {
+            anyhow::bail!("{}", reason);
+        }
+        if let Some(reason) = self.throttled() {
+            events::emit(Event::RunSkipped { job_id: &self.job.id, reason: &reason });
+            anyhow::bail!("{}", reason);
+        }
+        if self.job.pull_on_run {
+            // Counts as started: a trigger during the pull must not run too
+            self.last_started = Some(Some(clock::now()));
+            self.pull_then_run(addr, start);
+            return Ok(());
+        }
+        self.execute_current(addr, start).await
+    }
+
+    /// Start of the latest run, read from history the first time.
+    fn last_started(&mut self) -> Option<DateTime<Utc>> {
+        *self.last_started.get_or_insert_with(|| {
+            let history_dir = git::get_history_dir(&self.sot_path, &self.job.id);
+            history::read_runs(&history_dir).ok()?.last().map(|run| run.started_at)
+        })
+    }
+
+    /// Why `min_interval` doesn't let a run start now, if it doesn't.
+    fn throttled(&mut self) -> Option<String> {
+        let min_interval = self.job.min_interval?;
+        let last = self.last_started()?;
+        let elapsed = (clock::now() - last).to_std().unwrap_or_default();
+        if elapsed >= min_interval {
+            return None;
+        }
+        Some(format!(
+            "min_interval: previous run started {}s ago, next allowed in {}s",
+            elapsed.as_secs(),
+            (min_interval - elapsed).as_secs().max(1)
+        ))
+    }
+
+    /// Why an open `circuit_breaker` holds back a scheduled run, if it does.
+    fn breaker_hold(&mut self) -> Option<String> {
+        let config = self.job.circuit_breaker.clone()?;
+        let state = breaker::open(&git::get_history_dir(&self.sot_path, &self.job.id))?;
+        let last = self.last_started();
+        breaker::hold(&config, &state, last, clock::now())
+    }
+
+    fn reset_breaker(&self, why: &str) {
+        if self.job.circuit_breaker.is_none() {
+            return;
+        }
+        match breaker::reset(&git::get_history_dir(&self.sot_path, &self.job.id)) {
+            Ok(true) => info!(target: "rollcron::job", job_id = %self.job.id, "Circuit breaker reset ({})", why),
+            Ok(false) => {}
+            Err(e) => warn!(target: "rollcron::job", job_id = %self.job.id, error = %e, "Failed to reset circuit breaker"),
+        }
+    }
+
+    /// Syncs the repo in the background, applies a new commit like the poll
+    /// loop would, then reports back with `Pulled`.
+    fn pull_then_run(&mut self, addr: Address<Self, Weak>, start: PendingStart) {
+        let job_id = self.job.id.clone();
+        let sot_path = self.sot_path.clone();
+        let runner = self.runner.clone();
+        let runner_addr = self.runner_addr.clone();
+        info!(target: "rollcron::job", job_id = %job_id, "Pulling before run");
+
+        tokio::spawn(async move {
+            let mut span = Span::root("git.pull").with_attr("job.id", job_id.as_str());
+            let path = sot_path.clone();
+            let result = tokio::task::spawn_blocking(move || source::sync(&path, &runner))
+                .await
+                .unwrap_or_else(|e| Err(anyhow::anyhow!("sync task failed: {}", e)));
+            let error = result.as_ref().err().map(|e| format!("{:#}", e));
+            match (&result, &error) {
+                (Ok(Some(range)), _) => span.set_attr("git.range", range.as_str()),
+                (_, Some(error)) => span.fail(error),
+                _ => {}
+            }
+            drop(span);
+            if let Some(runner_addr) = &runner_addr {
+                let _ = runner_addr.send(SyncAttempted { error: error.clone() }).await;
+            }
+
+            let updated = match result {
+                Ok(Some(range)) => {
+                    info!(target: "rollcron::job", job_id = %job_id, range = %range, "Pulled updates before run");
+                    if let Some(runner_addr) = &runner_addr {
+                        git_poll::apply_update(runner_addr, &sot_path, &range).await;
+                    }
+                    true
+                }
+                Ok(None) => false,
+                Err(_) => {
+                    let error = error.unwrap_or_default();
+                    warn!(target: "rollcron::job", job_id = %job_id, error = %error, "Pull before run failed, running the current commit");
+                    events::emit(Event::SyncFailed { error: &error });
+                    false
+                }
+            };
+            let _ = addr.send(Pulled { updated, start }).await;
+        });
+    }
+
+    /// Starts a run at the commit already checked out.
+    async fn execute_current(&mut self, addr: Address<Self, Weak>, start: PendingStart) -> anyhow::Result<()> {
+
+        // Trigger build/sync if pending
+        if self.pending_sync && !self.build_in_progress {
+            self.start_build(addr.clone());
+        }
+
+        // Check if run directory exists
+        if !self.run_dir_exists() {
+            // First run: wait for build to complete, then run
+            if self.build_in_progress {
+                info!(
+                    target: "rollcron::job",
+                    job_id = %self.job.id,
+                    "Waiting for initial build to complete"
+                );
+                // The first request waits; later ones before the build is done add no run
+                self.pending_run.get_or_insert(start);
+                return Ok(());
+            }
+            anyhow::bail!("run directory not ready and no build in progress");
+        }
+
+        self.handle_trigger(addr, start).await;
+        Ok(())
+    }
+
+    async fn handle_trigger(&mut self, addr: Address<Self, Weak>, start: PendingStart) {
         self.cleanup_finished_handles();
         let running_count = self.running_count();
 
         match self.job.concurrency {
             Concurrency::Parallel => {
-                self.spawn_job(addr);
+                self.spawn_job(addr, start);
             }
             Concurrency::Wait => {
                 if running_count > 0 {
@@ -418,9 +965,9 @@ impl JobActor {
                         running_count,
                         "Waiting for previous run(s) to complete"
                     );
-                    self.spawn_waiting_job(addr);
+                    self.spawn_waiting_job(addr, start);
                 } else {
-                    self.spawn_job(addr);
+                    self.spawn_job(addr, start);
                 }
             }
             Concurrency::Skip => {
@@ -432,7 +979,7 @@ impl JobActor {
                         "Skipped (instances still active)"
                     );
                 } else {
-                    self.spawn_job(addr);
+                    self.spawn_job(addr, start);
                 }
             }
             Concurrency::Replace => {
@@ -447,31 +994,38 @@ impl JobActor {
                         handle.abort();
                     }
                 }
-                self.spawn_job(addr);
+                self.spawn_job(addr, start);
             }
         }
     }
 
-    fn spawn_job(&mut self, self_addr: Address<Self, Weak>) {
-        let job_id = self.job.id.clone();
-        let job = self.job.clone();
+    /// The job with the trigger's overrides applied, and how it started.
+    fn job_to_start(&self, (trigger, overrides): PendingStart) -> (Job, RunStart) {
+        let mut job = self.job.clone();
+        overrides.apply(&mut job);
+        (job, RunStart { trigger, overrides: overrides.names() })
+    }
+
+    fn spawn_job(&mut self, self_addr: Address<Self, Weak>, start: PendingStart) {
+        self.last_started = Some(Some(clock::now()));
+        let (job, start) = self.job_to_start(start);
         let sot_path = self.sot_path.clone();
         let runner = self.runner.clone();
         let runner_addr = self.runner_addr.clone();
 
         let handle = tokio::spawn(async move {
-            let success = execute_job(&job, &sot_path, &runner).await;
+            if !guard::admit(&job, &sot_path, &runner).await {
+                let _ = self_addr.send(TryCopy).await;
+                return;
+            }
+            let report = execute_job(&job, &sot_path, &runner, &start).await;
 
             // Notify runner
             if let Some(addr) = runner_addr {
-                let msg = if success {
-                    Either::Left(JobCompleted { job_id })
+                if report.is_success() || report.outcome == RunOutcome::Skipped {
+                    let _ = addr.send(JobCompleted { report }).await;
                 } else {
-                    Either::Right(JobFailed { job_id })
-                };
-                match msg {
-                    Either::Left(m) => { let _ = addr.send(m).await; }
-                    Either::Right(m) => { let _ = addr.send(m).await; }
+                    let _ = addr.send(JobFailed { report }).await;
                 }
             }
 
@@ -482,9 +1036,9 @@ impl JobActor {
         self.handles.push(handle);
     }
 
-    fn spawn_waiting_job(&mut self, self_addr: Address<Self, Weak>) {
-        let job_id = self.job.id.clone();
-        let job = self.job.clone();
+    fn spawn_waiting_job(&mut self, self_addr: Address<Self, Weak>, start: PendingStart) {
+        self.last_started = Some(Some(clock::now()));
+        let (job, start) = self.job_to_start(start);
         let sot_path = self.sot_path.clone();
         let runner = self.runner.clone();
         let runner_addr = self.runner_addr.clone();
@@ -494,18 +1048,18 @@ impl JobActor {
             for prev_handle in previous_handles {
                 let _ = prev_handle.await;
             }
-            let success = execute_job(&job, &sot_path, &runner).await;
+            if !guard::admit(&job, &sot_path, &runner).await {
+                let _ = self_addr.send(TryCopy).await;
+                return;
+            }
+            let report = execute_job(&job, &sot_path, &runner, &start).await;
 
             // Notify runner
             if let Some(addr) = runner_addr {
-                let msg = if success {
-                    Either::Left(JobCompleted { job_id })
+                if report.is_success() || report.outcome == RunOutcome::Skipped {
+                    let _ = addr.send(JobCompleted { report }).await;
                 } else {
-                    Either::Right(JobFailed { job_id })
-                };
-                match msg {
-                    Either::Left(m) => { let _ = addr.send(m).await; }
-                    Either::Right(m) => { let _ = addr.send(m).await; }
+                    let _ = addr.send(JobFailed { report }).await;
                 }
             }
 
@@ -517,7 +1071,68 @@ impl JobActor {
     }
 }
 
-enum Either<L, R> {
-    Left(L),
-    Right(R),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    async fn wait_for(what: &str, mut done: impl FnMut() -> bool) {
+        for _ in 0..200 {
+            if done() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("timed out waiting for {}", what);
+    }
+
+    #[tokio::test]
+    async fn failed_build_drops_the_waiting_triggers_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let sot_path = dir.path().join("repo-00000000");
+        std::fs::create_dir(&sot_path).unwrap();
+        for args in [&["init", "-q"][..], &["commit", "-q", "--allow-empty", "-m", "init"]] {
+            let out = Command::new("git")
+                .args(["-c", "user.name=a", "-c", "user.email=a@b"])
+                .args(args)
+                .current_dir(&sot_path)
+                .output()
+                .unwrap();
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        }
+        // The first build fails, later ones succeed
+        let marker = dir.path().join("built-once");
+        let yaml = format!(
+            "jobs:\n  trigger_leak:\n    schedule: \"0 0 1 1 *\"\n    run: echo $X\n    env: {{ X: default }}\n    \
+             build: test -f {0} || {{ touch {0}; exit 1; }}\n",
+            marker.display()
+        );
+        let (runner, mut jobs) = crate::config::parse_config(&yaml).unwrap();
+        let job = jobs.remove(0);
+        // Left over if an earlier run of this test failed
+        git::remove_job_dir(&sot_path, &job.id);
+        let run_dir = git::get_run_dir(&sot_path, &job.id);
+        let history_dir = git::get_history_dir(&sot_path, &job.id);
+        let addr = xtra::spawn_tokio(JobActor::new(job, sot_path.clone(), runner, None), Mailbox::unbounded());
+
+        let overrides = RunOverrides { env: BTreeMap::from([("X".to_string(), "manual".to_string())]), timeout: None };
+        addr.send(Trigger { overrides }).await.unwrap().unwrap();
+        wait_for("the failed build", || marker.exists()).await;
+        // Refused until the failed build is done, then builds again
+        for _ in 0..200 {
+            if addr.send(Resync).await.unwrap().is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        wait_for("the second build", || run_dir.exists()).await;
+
+        addr.send(Execute).await.unwrap();
+        wait_for("the scheduled run", || history::read_runs(&history_dir).is_ok_and(|runs| !runs.is_empty())).await;
+        let runs = history::read_runs(&history_dir).unwrap();
+        assert_eq!(runs.len(), 1, "the triggered run must not have run");
+        assert_eq!(runs[0].trigger, Some(RunTrigger::Schedule));
+        assert!(runs[0].overrides.is_empty(), "{:?}", runs[0].overrides);
+        git::remove_job_dir(&sot_path, "trigger_leak");
+    }
 }
@@ -0,0 +1,287 @@
+//! Bounded capture of a child's stdout/stderr (`run.max_output`).
+//!
+//! Output is streamed: only the first and last `max_output / 2` bytes of each
+//! stream stay in memory, while the full stream can be spooled to the job's
+//! log file as it arrives (or, with `log.flush: run`, to a [`RunBuffer`]).
+
+use super::live;
+use crate::logging;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::time::Instant;
+
+/// Held while a buffered run is appended, so blocks never interleave
+static FLUSH_LOCK: Mutex<()> = Mutex::new(());
+
+/// Longest output line logged whole; longer ones are split
+const MAX_LOG_LINE: usize = 8192;
+
+/// One stream's retained output
+#[derive(Debug, Default, PartialEq)]
+pub struct Captured {
+    /// Head and tail, joined by an omission marker if anything was dropped
+    pub data: Vec<u8>,
+    /// Bytes dropped from the middle
+    pub omitted: u64,
+}
+
+/// Keeps the first and last `half` bytes written.
+struct HeadTail {
+    half: usize,
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    total: u64,
+}
+
+impl HeadTail {
+    fn new(limit: u64) -> Self {
+        Self {
+            half: usize::try_from(limit / 2).unwrap_or(usize::MAX),
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            total: 0,
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        self.total += chunk.len() as u64;
+        let take = self.half.saturating_sub(self.head.len()).min(chunk.len());
+        self.head.extend_from_slice(&chunk[..take]);
+        self.tail.extend(&chunk[take..]);
+        let excess = self.tail.len().saturating_sub(self.half);
+        self.tail.drain(..excess);
+    }
+
+    fn finish(self) -> Captured {
+        let omitted = self.total - (self.head.len() + self.tail.len()) as u64;
+        let mut data = self.head;
+        if omitted > 0 {
+            data.extend_from_slice(format!("\n[rollcron: {} bytes omitted]\n", omitted).as_bytes());
+        }
+        data.extend(self.tail);
+        Captured { data, omitted }
+    }
+}
+
+/// When a run last wrote to stdout or stderr (`run.stall_timeout`).
+#[derive(Clone)]
+pub struct Activity {
+    start: Instant,
+    /// Milliseconds after `start`
+    last: Arc<AtomicU64>,
+}
+
+impl Activity {
+    pub fn new() -> Self {
+        Self { start: Instant::now(), last: Arc::new(AtomicU64::new(0)) }
+    }
+
+    fn touch(&self) {
+        let elapsed = u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX);
+        self.last.fetch_max(elapsed, Ordering::Relaxed);
+    }
+
+    /// Time since the last output (or since the run started, before any).
+    pub fn idle(&self) -> Duration {
+        self.start.elapsed().saturating_sub(Duration::from_millis(self.last.load(Ordering::Relaxed)))
+    }
+}
+
+/// A run's stream as seen outside the capture: the job's live output, and
+/// the log line by line when the log target takes run output (journald, syslog).
+pub struct Tap {
+    job_id: String,
+    stream: &'static str,
+    /// Start of a line not yet ended
+    pending: Vec<u8>,
+}
+
+impl Tap {
+    pub fn new(job_id: &str, stream: &'static str) -> Self {
+        Self { job_id: job_id.to_string(), stream, pending: Vec::new() }
+    }
+
+    fn write(&mut self, chunk: &[u8]) {
+        live::append(&self.job_id, chunk);
+        if !logging::forwards_output() {
+            return;
+        }
+        self.pending.extend_from_slice(chunk);
+        while let Some(line) = self.next_line() {
+            self.log(&line);
+        }
+    }
+
+    /// Takes the next complete line (or `MAX_LOG_LINE` bytes of a longer one).
+    fn next_line(&mut self) -> Option<Vec<u8>> {
+        let (len, newline) = match self.pending.iter().position(|&b| b == b'\n') {
+            Some(pos) if pos < MAX_LOG_LINE => (pos, 1),
+            _ if self.pending.len() >= MAX_LOG_LINE => (MAX_LOG_LINE, 0),
+            _ => return None,
+        };
+        let line = self.pending[..len].to_vec();
+        self.pending.drain(..len + newline);
+        Some(line)
+    }
+
+    fn log(&self, line: &[u8]) {
+        logging::job_output(&self.job_id, self.stream, String::from_utf8_lossy(line).trim_end_matches('\r'));
+    }
+
+    /// Logs an unterminated last line.
+    fn finish(self) {
+        if !self.pending.is_empty() {
+            self.log(&self.pending);
+        }
+    }
+}
+
+/// Reads a child's stream to the end, keeping at most `limit` bytes. Each chunk
+/// is passed to `tap` and `spool`, and recorded in `activity`.
+pub async fn capture<R: AsyncRead + Unpin>(
+    reader: Option<R>,
+    limit: u64,
+    mut tap: Option<Tap>,
+    mut spool: Option<File>,
+    activity: Option<Activity>,
+) -> Captured {
+    let mut buf = HeadTail::new(limit);
+    let Some(mut reader) = reader else {
+        return buf.finish();
+    };
+    let mut chunk = [0u8; 8192];
+    loop {
+        match reader.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if let Some(activity) = &activity {
+                    activity.touch();
+                }
+                if let Some(tap) = &mut tap {
+                    tap.write(&chunk[..n]);
+                }
+                // A failing log file must not stop the capture
+                if let Some(file) = &mut spool {
+                    if file.write_all(&chunk[..n]).is_err() {
+                        spool = None;
+                    }
+                }
+                buf.push(&chunk[..n]);
+            }
+        }
+    }
+    if let Some(tap) = tap {
+        tap.finish();
+    }
+    buf.finish()
+}
+
+/// `log.flush: run`: a run's log lines collect in a hidden file next to the
+/// log and are appended to it in one block when the run ends. Live viewers
+/// still see the output as it arrives.
+pub struct RunBuffer {
+    path: PathBuf,
+    file: File,
+}
+
+impl RunBuffer {
+    pub fn create(log_dir: &Path, run_id: &str) -> io::Result<Self> {
+        let path = log_dir.join(format!(".{}.part", run_id));
+        let file = OpenOptions::new().create_new(true).read(true).append(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+
+    /// Where the run's output and markers are written until [`Self::flush_into`].
+    pub fn file(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Appends everything written so far to `log` and removes the buffer.
+    pub fn flush_into(mut self, log: &mut File) -> io::Result<()> {
+        self.file.rewind()?;
+        let _guard = FLUSH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        io::copy(&mut self.file, log)?;
+        log.flush()
+    }
+}
+
+impl Drop for RunBuffer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_head_and_tail_and_spools_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("out.log");
+        let input: Vec<u8> = (0..20_000u32).map(|i| b'a' + (i % 26) as u8).collect();
+
+        let spool = File::create(&log).unwrap();
+        let captured = capture(Some(&input[..]), 1024, None, Some(spool), None).await;
+        assert_eq!(captured.omitted, 20_000 - 1024);
+        let marker = format!("\n[rollcron: {} bytes omitted]\n", captured.omitted);
+        assert_eq!(captured.data[..512], input[..512]);
+        assert_eq!(captured.data[512..512 + marker.len()], *marker.as_bytes());
+        assert_eq!(captured.data[512 + marker.len()..], input[input.len() - 512..]);
+        assert_eq!(std::fs::read(&log).unwrap(), input);
+
+        let captured = capture(Some(&b"short"[..]), 1024, None, None, None).await;
+        assert_eq!(captured, Captured { data: b"short".to_vec(), omitted: 0 });
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn activity_tracks_last_output() {
+        let activity = Activity::new();
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(activity.idle(), Duration::from_secs(5));
+        capture(Some(&b"tick"[..]), 1024, None, None, Some(activity.clone())).await;
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(activity.idle(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn tap_splits_lines() {
+        let mut tap = Tap::new("job", "stdout");
+        tap.pending.extend_from_slice(b"one\ntwo\r\nthr");
+        assert_eq!(tap.next_line().unwrap(), b"one");
+        assert_eq!(tap.next_line().unwrap(), b"two\r");
+        assert_eq!(tap.next_line(), None);
+        assert_eq!(tap.pending, b"thr");
+
+        tap.pending = vec![b'x'; MAX_LOG_LINE + 10];
+        assert_eq!(tap.next_line().unwrap().len(), MAX_LOG_LINE);
+        assert_eq!(tap.pending.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn buffered_runs_are_appended_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("job.log");
+        let mut log = OpenOptions::new().create(true).append(true).open(&log_path).unwrap();
+        let mut first = RunBuffer::create(dir.path(), "run-1").unwrap();
+        let mut second = RunBuffer::create(dir.path(), "run-2").unwrap();
+
+        // Interleaved chunks from two concurrent runs
+        for i in 0..3 {
+            capture(Some(format!("a{}\n", i).as_bytes()), 1024, None, first.file().try_clone().ok(), None).await;
+            capture(Some(format!("b{}\n", i).as_bytes()), 1024, None, second.file().try_clone().ok(), None).await;
+        }
+        assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "");
+        second.flush_into(&mut log).unwrap();
+        first.flush_into(&mut log).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "b0\nb1\nb2\na0\na1\na2\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1, "buffers are removed");
+    }
+}
@@ -0,0 +1,54 @@
+//! Operator pauses (`rollcron ctl pause`) are kept beside the job's history,
+//! so a paused job stays paused across restarts until `ctl resume`. Pauses
+//! by the safety brake are not persisted: a restart releases the brake.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const PAUSE_FILE: &str = "paused.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Override {
+    since: DateTime<Utc>,
+}
+
+/// When the job was paused, if an override is stored in `dir`.
+pub fn load(dir: &Path) -> Option<DateTime<Utc>> {
+    let bytes = std::fs::read(dir.join(PAUSE_FILE)).ok()?;
+    serde_json::from_slice::<Override>(&bytes).ok().map(|o| o.since)
+}
+
+pub fn save(dir: &Path, since: DateTime<Utc>) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join(PAUSE_FILE), serde_json::to_vec(&Override { since })?)?;
+    Ok(())
+}
+
+pub fn clear(dir: &Path) -> Result<()> {
+    match std::fs::remove_file(dir.join(PAUSE_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn override_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = dir.path().join("backup");
+        assert_eq!(load(&history), None);
+        clear(&history).unwrap();
+
+        let since = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        save(&history, since).unwrap();
+        assert_eq!(load(&history), Some(since));
+        clear(&history).unwrap();
+        assert_eq!(load(&history), None);
+    }
+}
@@ -0,0 +1,146 @@
+//! Job processes and their process groups.
+//!
+//! On unix every build and run command starts in a new process group, so a
+//! timeout signals everything the command started (`a | b`, backgrounded
+//! children) rather than just the shell. A child that is dropped before it
+//! was reaped, because `replace` or a shutdown aborted its run, takes its
+//! whole group down with it.
+
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tracing::{debug, warn};
+
+/// Grace period to wait after SIGTERM before sending SIGKILL
+const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A spawned job command leading its own process group.
+pub struct GroupChild(Child);
+
+/// Spawns `cmd` as the leader of a new process group.
+pub fn spawn(cmd: &mut Command) -> io::Result<GroupChild> {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd.spawn().map(GroupChild)
+}
+
+impl GroupChild {
+    /// Stops the whole group: SIGTERM, then SIGKILL for whatever is left
+    /// once the leader exits or the grace period runs out.
+    #[cfg(unix)]
+    pub async fn terminate(&mut self, job_id: &str) {
+        use nix::sys::signal::Signal;
+
+        let Some(pgid) = self.0.id() else {
+            return; // Already reaped
+        };
+
+        if signal_group(pgid, Signal::SIGTERM) {
+            debug!(target: "rollcron::job", job_id = %job_id, "Sent SIGTERM to process group, waiting for graceful exit");
+            if tokio::time::timeout(GRACEFUL_SHUTDOWN_TIMEOUT, self.0.wait()).await.is_ok() {
+                debug!(target: "rollcron::job", job_id = %job_id, "Process exited gracefully after SIGTERM");
+            } else {
+                warn!(target: "rollcron::job", job_id = %job_id, "Grace period expired, sending SIGKILL");
+            }
+        }
+
+        // Children that ignored SIGTERM keep the group alive past the leader
+        signal_group(pgid, Signal::SIGKILL);
+        let _ = self.0.wait().await;
+    }
+
+    #[cfg(not(unix))]
+    pub async fn terminate(&mut self, _job_id: &str) {
+        // On non-Unix platforms, just kill immediately
+        let _ = self.0.kill().await;
+        let _ = self.0.wait().await;
+    }
+}
+
+/// Sends `signal` to every process in group `pgid`; false if none is left.
+#[cfg(unix)]
+fn signal_group(pgid: u32, signal: nix::sys::signal::Signal) -> bool {
+    nix::sys::signal::killpg(nix::unistd::Pid::from_raw(pgid as i32), signal).is_ok()
+}
+
+impl Deref for GroupChild {
+    type Target = Child;
+
+    fn deref(&self) -> &Child {
+        &self.0
+    }
+}
+
+impl DerefMut for GroupChild {
+    fn deref_mut(&mut self) -> &mut Child {
+        &mut self.0
+    }
+}
+
+impl Drop for GroupChild {
+    fn drop(&mut self) {
+        // A reaped leader's pgid may be reused, so only signal while it lives
+        if !matches!(self.0.try_wait(), Ok(None)) {
+            return;
+        }
+        #[cfg(unix)]
+        if let Some(pgid) = self.0.id() {
+            signal_group(pgid, nix::sys::signal::Signal::SIGKILL);
+        }
+        #[cfg(not(unix))]
+        let _ = self.0.start_kill();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    /// Running and not a zombie awaiting a reaper.
+    fn alive(pid: i32) -> bool {
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => !stat.rsplit(')').next().is_some_and(|rest| rest.trim_start().starts_with('Z')),
+            Err(_) if cfg!(target_os = "linux") => false,
+            Err(_) => nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), None).is_ok(),
+        }
+    }
+
+    /// Starts a shell with a backgrounded grandchild and returns the grandchild's pid.
+    async fn spawn_with_grandchild(script: &str) -> (GroupChild, i32) {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script).stdout(Stdio::piped());
+        let mut child = spawn(&mut cmd).unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let mut line = String::new();
+        BufReader::new(stdout).read_line(&mut line).await.unwrap();
+        (child, line.trim().parse().unwrap())
+    }
+
+    async fn wait_gone(pid: i32) {
+        for _ in 0..100 {
+            if !alive(pid) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("process {} is still running", pid);
+    }
+
+    #[tokio::test]
+    async fn terminate_kills_grandchildren() {
+        let (mut child, grandchild) = spawn_with_grandchild("sleep 30 >/dev/null & echo $!; wait").await;
+        assert!(alive(grandchild));
+        tokio::time::timeout(Duration::from_secs(5), child.terminate("test")).await.unwrap();
+        wait_gone(grandchild).await;
+    }
+
+    #[tokio::test]
+    async fn dropping_a_running_child_kills_its_group() {
+        let (child, grandchild) = spawn_with_grandchild("sleep 30 >/dev/null & echo $!; wait").await;
+        drop(child);
+        wait_gone(grandchild).await;
+    }
+}
@@ -0,0 +1,313 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Max bytes of stdout/stderr kept in a run report
+pub const OUTPUT_TAIL_BYTES: usize = 4096;
+
+/// Final outcome of a job run (after all retries)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Success,
+    Failed,
+    Timeout,
+    ExecError,
+    /// Killed by the OOM killer (`limits.memory` exceeded)
+    OomKilled,
+    /// rollcron stopped during the run; recorded when it next starts
+    Interrupted,
+    /// Killed after no output for `run.stall_timeout`
+    Stalled,
+    /// The job's `condition` wasn't met; the command didn't run
+    Skipped,
+}
+
+impl RunOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunOutcome::Success => "success",
+            RunOutcome::Failed => "failed",
+            RunOutcome::Timeout => "timeout",
+            RunOutcome::ExecError => "exec_error",
+            RunOutcome::OomKilled => "oom_killed",
+            RunOutcome::Interrupted => "interrupted",
+            RunOutcome::Stalled => "stalled",
+            RunOutcome::Skipped => "skipped",
+        }
+    }
+}
+
+/// Coarse category of a failed run, guessed from its outcome, exit status
+/// and output (see `classify`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    Timeout,
+    /// Over `limits.memory`, or SIGKILLed by something other than rollcron
+    OomKill,
+    /// Terminated by another signal
+    Killed,
+    PermissionDenied,
+    /// Connection, DNS or TLS errors in the output
+    Network,
+    CommandNotFound,
+    /// Non-zero exit without a recognizable cause
+    Other,
+}
+
+impl FailureClass {
+    pub const ALL: [FailureClass; 7] = [
+        FailureClass::Timeout,
+        FailureClass::OomKill,
+        FailureClass::Killed,
+        FailureClass::PermissionDenied,
+        FailureClass::Network,
+        FailureClass::CommandNotFound,
+        FailureClass::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureClass::Timeout => "timeout",
+            FailureClass::OomKill => "oom_kill",
+            FailureClass::Killed => "killed",
+            FailureClass::PermissionDenied => "permission_denied",
+            FailureClass::Network => "network",
+            FailureClass::CommandNotFound => "command_not_found",
+            FailureClass::Other => "other",
+        }
+    }
+}
+
+/// What started a run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunTrigger {
+    #[default]
+    Schedule,
+    /// `run_on_start`
+    Startup,
+    /// `ctl trigger`, the API or `rollcron run`
+    Manual,
+}
+
+impl RunTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunTrigger::Schedule => "schedule",
+            RunTrigger::Startup => "startup",
+            RunTrigger::Manual => "manual",
+        }
+    }
+}
+
+/// How a run was started, recorded in its report.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunStart {
+    pub trigger: RunTrigger,
+    /// Settings a manual trigger overrode for this run (`env.KEY`, `run.timeout`)
+    pub overrides: Vec<String>,
+}
+
+/// Structured result of a single job run, returned by `execute_job`.
+/// Serialized as one line of the job's run history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRunReport {
+    pub job_id: String,
+    pub run_id: String,
+    /// Sequence number of the run within its job, from 1 (absent for imported and older runs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_number: Option<u64>,
+    /// Commit the run directory was copied from (absent for imported and older runs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    pub started_at: DateTime<Utc>,
+    #[serde(rename = "duration_ms", with = "duration_millis")]
+    pub duration: Duration,
+    pub attempts: u32,
+    pub outcome: RunOutcome,
+    pub exit_code: Option<i32>,
+    /// Human-readable failure reason (None on success)
+    pub error: Option<String>,
+    /// Category of the failure (None on success, and for imported and older runs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_class: Option<FailureClass>,
+    /// True if the primary command failed and `fallback_command` ran;
+    /// outcome/exit_code/output then describe the fallback
+    #[serde(default)]
+    pub fallback: bool,
+    pub stdout_tail: String,
+    pub stderr_tail: String,
+    /// Output bytes dropped from the middle of stdout/stderr (`run.max_output`)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub omitted_bytes: u64,
+    /// Files written outside the job workspace (audit mode only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outside_writes: Vec<String>,
+    /// Imported from another scheduler (`rollcron history import`); no output retained
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub imported: bool,
+    /// Where the run's time went (absent for imported and older runs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<PhaseTimings>,
+    /// What started the run (absent for imported and older runs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<RunTrigger>,
+    /// Settings overridden by a manual trigger; the values are not recorded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<String>,
+    /// Files collected into the run's artifacts folder, relative to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<String>,
+}
+
+/// Time spent in each phase of a run. `queue_wait` precedes `started_at` and
+/// `notify` follows the end of `duration`; the rest fall within `duration`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    /// Waiting for a free slot (`runner.max_concurrent_runs`), before `started_at`
+    #[serde(rename = "queue_wait_ms", with = "duration_millis")]
+    pub queue_wait: Duration,
+    /// Delivering start notifications
+    #[serde(rename = "notify_start_ms", with = "duration_millis")]
+    pub notify_start: Duration,
+    /// Loading env files and resolving secrets, over all attempts
+    #[serde(rename = "env_load_ms", with = "duration_millis")]
+    pub env_load: Duration,
+    /// Commands running, over all attempts and the fallback
+    #[serde(rename = "execution_ms", with = "duration_millis")]
+    pub execution: Duration,
+    /// Backoff between attempts
+    #[serde(rename = "retry_wait_ms", with = "duration_millis")]
+    pub retry_wait: Duration,
+    /// Delivering failure notifications
+    #[serde(rename = "notify_ms", with = "duration_millis")]
+    pub notify: Duration,
+}
+
+impl PhaseTimings {
+    /// Run time not spent running commands.
+    pub fn overhead(&self) -> Duration {
+        self.notify_start + self.env_load + self.retry_wait + self.notify
+    }
+}
+
+impl JobRunReport {
+    pub fn is_success(&self) -> bool {
+        self.outcome == RunOutcome::Success
+    }
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+mod duration_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_millis)
+    }
+}
+
+/// Generates a run ID: `<UTC timestamp>-<random hex>` (sortable by start time).
+pub fn generate_run_id(started_at: DateTime<Utc>) -> String {
+    let suffix: u32 = rand::thread_rng().r#gen();
+    format!("{}-{:08x}", started_at.format("%Y%m%dT%H%M%SZ"), suffix)
+}
+
+/// Returns the last `max_bytes` of output as a lossy UTF-8 string.
+pub fn tail(output: &[u8], max_bytes: usize) -> String {
+    let start = output.len().saturating_sub(max_bytes);
+    let s = String::from_utf8_lossy(&output[start..]);
+    // A cut in the middle of a multi-byte char yields leading replacement chars
+    s.trim_start_matches('\u{FFFD}').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn tail_keeps_short_output() {
+        assert_eq!(tail(b"hello", 10), "hello");
+    }
+
+    #[test]
+    fn tail_truncates_from_start() {
+        assert_eq!(tail(b"0123456789", 4), "6789");
+    }
+
+    #[test]
+    fn tail_skips_split_multibyte_char() {
+        // "あい" is 6 bytes; cutting at 4 splits "あ"
+        assert_eq!(tail("あい".as_bytes(), 4), "い");
+    }
+
+    #[test]
+    fn report_json_roundtrip() {
+        let report = JobRunReport {
+            job_id: "backup".to_string(),
+            run_id: "20250115T103000Z-0000abcd".to_string(),
+            run_number: None,
+            commit: None,
+            started_at: Utc.with_ymd_and_hms(2025, 1, 15, 10, 30, 0).unwrap(),
+            duration: Duration::from_millis(1500),
+            attempts: 2,
+            outcome: RunOutcome::Timeout,
+            exit_code: None,
+            error: Some("timeout after 1s".to_string()),
+            failure_class: Some(FailureClass::Timeout),
+            fallback: false,
+            stdout_tail: String::new(),
+            stderr_tail: "err".to_string(),
+            omitted_bytes: 0,
+            outside_writes: Vec::new(),
+            imported: false,
+            timings: Some(PhaseTimings {
+                execution: Duration::from_millis(1200),
+                retry_wait: Duration::from_millis(250),
+                ..Default::default()
+            }),
+            trigger: Some(RunTrigger::Manual),
+            overrides: vec!["run.timeout".to_string()],
+            artifacts: Vec::new(),
+        };
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains(r#""duration_ms":1500"#));
+        assert!(json.contains(r#""outcome":"timeout""#));
+        assert!(json.contains(r#""failure_class":"timeout""#));
+        assert!(!json.contains("outside_writes"));
+        assert!(json.contains(r#""trigger":"manual","overrides":["run.timeout"]"#));
+
+        let parsed: JobRunReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.duration, report.duration);
+        assert_eq!(parsed.outcome, RunOutcome::Timeout);
+        assert_eq!(parsed.run_id, report.run_id);
+        assert!(json.contains(r#""retry_wait_ms":250"#));
+        assert_eq!(parsed.timings, report.timings);
+
+        // Runs recorded before timings existed still parse
+        let old = json.split(r#","timings""#).next().unwrap().to_string() + "}";
+        let parsed: JobRunReport = serde_json::from_str(&old).unwrap();
+        assert_eq!(parsed.timings, None);
+        assert_eq!((parsed.trigger, parsed.overrides.len()), (None, 0));
+    }
+
+    #[test]
+    fn run_id_starts_with_timestamp() {
+        let at = Utc.with_ymd_and_hms(2025, 1, 15, 10, 30, 0).unwrap();
+        let id = generate_run_id(at);
+        assert!(id.starts_with("20250115T103000Z-"));
+        assert_eq!(id.len(), "20250115T103000Z-".len() + 8);
+    }
+}
@@ -0,0 +1,298 @@
+//! `sandbox`: declarative filesystem and network restrictions for a job's
+//! build and run commands (Linux only).
+//!
+//! Paths are enforced with Landlock: the command may read and execute the
+//! system directories and `read` paths, and modify only its job directory and
+//! `write` paths. `network: false` installs a seccomp filter that refuses IPv4
+//! and IPv6 sockets (and io_uring, which could create them behind the filter's
+//! back); Unix sockets still work. The ruleset is built before the fork, so
+//! the child only makes syscalls.
+
+use super::security::Insn;
+use crate::config::SandboxConfig;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Read and execute access everywhere under these, when they exist
+const SYSTEM_READ: &[&str] = &["/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/proc", "/nix/store", "/run/current-system"];
+
+/// Device files any command may read and write
+const SYSTEM_DEVICES: &[&str] = &["/dev/null", "/dev/zero", "/dev/full", "/dev/random", "/dev/urandom"];
+
+// Landlock filesystem rights (linux/landlock.h)
+const ACCESS_EXECUTE: u64 = 1 << 0;
+const ACCESS_WRITE_FILE: u64 = 1 << 1;
+const ACCESS_READ_FILE: u64 = 1 << 2;
+const ACCESS_READ_DIR: u64 = 1 << 3;
+const ACCESS_TRUNCATE: u64 = 1 << 14;
+const ACCESS_IOCTL_DEV: u64 = 1 << 15;
+
+const READ: u64 = ACCESS_EXECUTE | ACCESS_READ_FILE | ACCESS_READ_DIR;
+
+/// Rights that apply to a file rather than a directory's entries
+const FILE_RIGHTS: u64 = ACCESS_EXECUTE | ACCESS_WRITE_FILE | ACCESS_READ_FILE | ACCESS_TRUNCATE | ACCESS_IOCTL_DEV;
+
+/// Restrictions for one attempt.
+#[derive(Debug)]
+pub struct Sandbox {
+    #[cfg(target_os = "linux")]
+    ruleset: std::os::fd::OwnedFd,
+    network_filter: Option<Vec<Insn>>,
+}
+
+impl Sandbox {
+    /// Builds the Landlock ruleset. `writable` are the job's own directories
+    /// (job directory, working directory, audit trace); relative `read` and
+    /// `write` paths are under `work_dir`.
+    pub fn new(config: &SandboxConfig, work_dir: &Path, writable: &[&Path]) -> Result<Self> {
+        let network_filter = match config.network {
+            true => None,
+            false => Some(network_filter()?),
+        };
+        let resolve = |path: &String| work_dir.join(crate::env::expand_string(path));
+        let mut rules: Vec<(PathBuf, bool)> = Vec::new();
+        rules.extend(SYSTEM_READ.iter().map(PathBuf::from).filter(|p| p.exists()).map(|p| (p, false)));
+        rules.extend(SYSTEM_DEVICES.iter().map(PathBuf::from).filter(|p| p.exists()).map(|p| (p, true)));
+        rules.extend(writable.iter().map(|p| (p.to_path_buf(), true)));
+        rules.extend(config.read.iter().map(|p| (resolve(p), false)));
+        rules.extend(config.write.iter().map(|p| (resolve(p), true)));
+        Self::with_rules(&rules, network_filter)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn with_rules(rules: &[(PathBuf, bool)], network_filter: Option<Vec<Insn>>) -> Result<Self> {
+        Ok(Sandbox { ruleset: landlock::ruleset(rules)?, network_filter })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn with_rules(_rules: &[(PathBuf, bool)], _network_filter: Option<Vec<Insn>>) -> Result<Self> {
+        anyhow::bail!("sandbox requires Linux")
+    }
+
+    /// The network filter, if any, for `Confinement` to install.
+    pub fn take_network_filter(&mut self) -> Option<Vec<Insn>> {
+        self.network_filter.take()
+    }
+
+    /// Enforces the ruleset on the calling process. Called in the child
+    /// between fork and exec.
+    #[cfg(target_os = "linux")]
+    pub fn restrict(&self, no_new_privs: bool) -> std::io::Result<()> {
+        use std::os::fd::AsRawFd;
+        // SAFETY: plain syscalls
+        unsafe {
+            if no_new_privs && libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if libc::syscall(libc::SYS_landlock_restrict_self, self.ruleset.as_raw_fd(), 0) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod landlock {
+    use super::{FILE_RIGHTS, READ};
+    use anyhow::{Context, Result};
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::PathBuf;
+
+    const CREATE_RULESET_VERSION: u32 = 1 << 0;
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Filesystem rights the kernel's Landlock ABI knows (all of them handled).
+    pub fn handled_access(abi: i64) -> u64 {
+        match abi {
+            1 => (1 << 13) - 1,
+            2 => (1 << 14) - 1,
+            3 | 4 => (1 << 15) - 1,
+            _ => (1 << 16) - 1,
+        }
+    }
+
+    /// A ruleset granting read (`false`) or full (`true`) access beneath each path.
+    pub fn ruleset(rules: &[(PathBuf, bool)]) -> Result<OwnedFd> {
+        // SAFETY: the version query takes no attribute
+        let abi = unsafe { libc::syscall(libc::SYS_landlock_create_ruleset, std::ptr::null::<u8>(), 0, CREATE_RULESET_VERSION) };
+        if abi < 1 {
+            anyhow::bail!("Landlock is not available on this host (needs Linux 5.13+ with landlock in the LSM list)");
+        }
+        let handled = handled_access(abi);
+        let attr = RulesetAttr { handled_access_fs: handled };
+        // SAFETY: valid attribute of the given size
+        let fd = unsafe {
+            libc::syscall(libc::SYS_landlock_create_ruleset, &attr as *const RulesetAttr, size_of::<RulesetAttr>(), 0)
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error()).context("failed to create Landlock ruleset");
+        }
+        // SAFETY: a new descriptor we own
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+
+        for (path, write) in rules {
+            let file = std::fs::OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_PATH | libc::O_CLOEXEC)
+                .open(path)
+                .with_context(|| format!("sandbox path {}", path.display()))?;
+            let mut access = if *write { handled } else { READ };
+            if !file.metadata().with_context(|| format!("sandbox path {}", path.display()))?.is_dir() {
+                access &= FILE_RIGHTS;
+            }
+            let rule = PathBeneathAttr { allowed_access: access & handled, parent_fd: file.as_raw_fd() };
+            // SAFETY: valid rule for an open ruleset
+            let added = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    ruleset.as_raw_fd(),
+                    RULE_PATH_BENEATH,
+                    &rule as *const PathBeneathAttr,
+                    0,
+                )
+            };
+            if added != 0 {
+                return Err(std::io::Error::last_os_error()).with_context(|| format!("sandbox path {}", path.display()));
+            }
+        }
+        Ok(ruleset)
+    }
+}
+
+// Classic BPF opcodes for the network filter
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JEQ_K: u16 = 0x15;
+const BPF_JGE_K: u16 = 0x35;
+const BPF_RET_K: u16 = 0x06;
+
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+
+/// `struct seccomp_data` offsets (args low word on little-endian)
+const DATA_NR: u32 = 0;
+const DATA_ARCH: u32 = 4;
+const DATA_ARG0: u32 = 16;
+
+const AF_INET: u32 = 2;
+const AF_INET6: u32 = 10;
+const EACCES: u32 = 13;
+
+/// (audit arch, socket, io_uring_setup, x32 syscall bit) of the build target
+fn native_syscalls() -> Result<(u32, u32, u32, Option<u32>)> {
+    if cfg!(target_arch = "x86_64") {
+        Ok((0xC000_003E, 41, 425, Some(0x4000_0000)))
+    } else if cfg!(target_arch = "aarch64") {
+        Ok((0xC000_00B7, 198, 425, None))
+    } else {
+        anyhow::bail!("sandbox.network is only supported on x86_64 and aarch64")
+    }
+}
+
+/// Refuses `socket(AF_INET | AF_INET6, ...)` and io_uring with EACCES, and
+/// every syscall of a foreign ABI (32-bit compat, x32).
+pub fn network_filter() -> Result<Vec<Insn>> {
+    let (arch, socket, io_uring_setup, x32_bit) = native_syscalls()?;
+    // Jumps name absolute targets, resolved below
+    enum Step {
+        Load(u32),
+        JumpIf { op: u16, k: u32, then: usize, otherwise: usize },
+        Ret(u32),
+    }
+    let (allow, deny) = (usize::MAX - 1, usize::MAX);
+    let mut steps = vec![Step::Load(DATA_ARCH), Step::JumpIf { op: BPF_JEQ_K, k: arch, then: 2, otherwise: deny }, Step::Load(DATA_NR)];
+    if let Some(bit) = x32_bit {
+        steps.push(Step::JumpIf { op: BPF_JGE_K, k: bit, then: deny, otherwise: steps.len() + 1 });
+    }
+    steps.push(Step::JumpIf { op: BPF_JEQ_K, k: io_uring_setup, then: deny, otherwise: steps.len() + 1 });
+    steps.push(Step::JumpIf { op: BPF_JEQ_K, k: socket, then: steps.len() + 1, otherwise: allow });
+    steps.push(Step::Load(DATA_ARG0));
+    steps.push(Step::JumpIf { op: BPF_JEQ_K, k: AF_INET, then: deny, otherwise: steps.len() + 1 });
+    steps.push(Step::JumpIf { op: BPF_JEQ_K, k: AF_INET6, then: deny, otherwise: allow });
+    let allow_at = steps.len();
+    steps.push(Step::Ret(SECCOMP_RET_ALLOW));
+    steps.push(Step::Ret(SECCOMP_RET_ERRNO | EACCES));
+
+    let target = |to: usize| match to {
+        t if t == allow => allow_at,
+        t if t == deny => allow_at + 1,
+        t => t,
+    };
+    Ok(steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| match *step {
+            Step::Load(offset) => Insn { code: BPF_LD_W_ABS, jt: 0, jf: 0, k: offset },
+            Step::JumpIf { op, k, then, otherwise } => Insn {
+                code: op,
+                jt: (target(then) - i - 1) as u8,
+                jf: (target(otherwise) - i - 1) as u8,
+                k,
+            },
+            Step::Ret(k) => Insn { code: BPF_RET_K, jt: 0, jf: 0, k },
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs a classic BPF program over a `seccomp_data`.
+    fn eval(program: &[Insn], nr: u32, arch: u32, arg0: u32) -> u32 {
+        let load = |offset: u32| match offset {
+            DATA_NR => nr,
+            DATA_ARCH => arch,
+            DATA_ARG0 => arg0,
+            _ => panic!("unexpected load at {}", offset),
+        };
+        let (mut pc, mut acc) = (0, 0);
+        loop {
+            let insn = program[pc];
+            pc += 1;
+            match insn.code {
+                BPF_LD_W_ABS => acc = load(insn.k),
+                BPF_JEQ_K => pc += if acc == insn.k { insn.jt } else { insn.jf } as usize,
+                BPF_JGE_K => pc += if acc >= insn.k { insn.jt } else { insn.jf } as usize,
+                BPF_RET_K => return insn.k,
+                code => panic!("unexpected opcode {:#x}", code),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn network_filter_refuses_inet_sockets() {
+        let (arch, socket, io_uring_setup, _) = native_syscalls().unwrap();
+        let program = network_filter().unwrap();
+        let denied = SECCOMP_RET_ERRNO | EACCES;
+        assert_eq!(eval(&program, socket, arch, AF_INET), denied);
+        assert_eq!(eval(&program, socket, arch, AF_INET6), denied);
+        assert_eq!(eval(&program, io_uring_setup, arch, 0), denied);
+        // AF_UNIX
+        assert_eq!(eval(&program, socket, arch, 1), SECCOMP_RET_ALLOW);
+        assert_eq!(eval(&program, 0, arch, AF_INET), SECCOMP_RET_ALLOW);
+        // i386 compat
+        assert_eq!(eval(&program, socket, 0x4000_0003, AF_INET), denied);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn handled_access_grows_with_abi() {
+        assert_eq!(landlock::handled_access(1), 0x1fff);
+        assert_eq!(landlock::handled_access(3) & ACCESS_TRUNCATE, ACCESS_TRUNCATE);
+        assert_eq!(landlock::handled_access(6) & ACCESS_IOCTL_DEV, ACCESS_IOCTL_DEV);
+    }
+}
@@ -0,0 +1,229 @@
+//! `security`: kernel confinement of a job's build and run commands (Linux only).
+//!
+//! Both are applied in the child between fork and exec, so they cover
+//! everything the command starts, including the limits and audit wrappers:
+//!
+//! - `seccomp`: a compiled BPF filter (an array of `struct sock_filter`, as
+//!   written by libseccomp's `seccomp_export_bpf`). Without root the kernel
+//!   requires `no_new_privs` for it, which also disables setuid binaries.
+//! - `apparmor`: a loaded AppArmor profile the command execs into.
+//!
+//! A `user`/`group` switch done by rollcron itself happens in between: after
+//! the profile change (which needs privileges), before the filter (which may
+//! not allow it). The `sandbox` follows the switch.
+
+use super::identity::RunAs;
+use super::sandbox::Sandbox;
+use crate::config::SecurityConfig;
+use anyhow::{Context, Result};
+use std::path::Path;
+use tokio::process::Command;
+
+/// Largest filter the kernel accepts (BPF_MAXINSNS)
+const MAX_FILTER_LEN: usize = 4096;
+
+/// Size of one `struct sock_filter` instruction
+const INSN_SIZE: usize = 8;
+
+/// One BPF instruction (`struct sock_filter`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Insn {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+/// Confinement for one attempt, read and checked before the fork so the
+/// child only makes syscalls.
+#[derive(Debug, Default)]
+pub struct Confinement {
+    filter: Option<Vec<Insn>>,
+    apparmor: Option<String>,
+    run_as: Option<RunAs>,
+    sandbox: Option<Sandbox>,
+}
+
+impl Confinement {
+    /// Loads the job's seccomp filter (relative to `work_dir`) and checks the
+    /// AppArmor profile is loaded.
+    pub fn new(config: &SecurityConfig, work_dir: &Path) -> Result<Self> {
+        if !cfg!(target_os = "linux") {
+            anyhow::bail!("security profiles require Linux");
+        }
+        let filter = match &config.seccomp {
+            Some(path) => {
+                let path = work_dir.join(crate::env::expand_string(path));
+                let bytes = std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+                Some(parse_filter(&bytes).with_context(|| format!("invalid seccomp filter {}", path.display()))?)
+            }
+            None => None,
+        };
+        if let Some(profile) = &config.apparmor {
+            check_apparmor(Path::new("/sys"), profile)?;
+        }
+        Ok(Confinement { filter, apparmor: config.apparmor.clone(), ..Default::default() })
+    }
+
+    /// Also switches to `run_as` (rollcron must be root).
+    pub fn run_as(mut self, run_as: RunAs) -> Self {
+        self.run_as = Some(run_as);
+        self
+    }
+
+    /// Also applies `sandbox`.
+    pub fn sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Makes `cmd` apply the confinement in the child before exec.
+    #[cfg(unix)]
+    pub fn apply(mut self, cmd: &mut Command) {
+        #[cfg(target_os = "linux")]
+        let to_kernel = |insns: Vec<Insn>| -> Vec<libc::sock_filter> {
+            insns.iter().map(|i| libc::sock_filter { code: i.code, jt: i.jt, jf: i.jf, k: i.k }).collect()
+        };
+        #[cfg(target_os = "linux")]
+        let filter = self.filter.map(to_kernel);
+        #[cfg(target_os = "linux")]
+        let network_filter = self.sandbox.as_mut().and_then(Sandbox::take_network_filter).map(to_kernel);
+        #[cfg(target_os = "linux")]
+        let sandbox = self.sandbox;
+        #[cfg(target_os = "linux")]
+        let apparmor = self.apparmor.map(|profile| format!("exec {}", profile).into_bytes());
+        let run_as = self.run_as;
+        // SAFETY: geteuid has no preconditions
+        #[cfg(target_os = "linux")]
+        let root = unsafe { libc::geteuid() } == 0 && run_as.as_ref().is_none_or(|r| r.user.is_none());
+        // SAFETY: the closure only makes syscalls (open, write, close, prctl,
+        // set*id) on data prepared before the fork
+        unsafe {
+            cmd.pre_exec(move || {
+                #[cfg(target_os = "linux")]
+                if let Some(request) = &apparmor {
+                    change_profile_on_exec(request)?;
+                }
+                if let Some(run_as) = &run_as {
+                    run_as.switch()?;
+                }
+                #[cfg(target_os = "linux")]
+                if let Some(sandbox) = &sandbox {
+                    sandbox.restrict(!root)?;
+                }
+                #[cfg(target_os = "linux")]
+                for filter in [&network_filter, &filter].into_iter().flatten() {
+                    install_filter(filter, !root)?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn apply(self, _cmd: &mut Command) {}
+}
+
+/// Parses a BPF program in native byte order.
+pub fn parse_filter(bytes: &[u8]) -> Result<Vec<Insn>> {
+    if bytes.is_empty() || bytes.len() % INSN_SIZE != 0 {
+        anyhow::bail!("{} bytes is not a whole number of {}-byte instructions", bytes.len(), INSN_SIZE);
+    }
+    if bytes.len() / INSN_SIZE > MAX_FILTER_LEN {
+        anyhow::bail!("{} instructions, the kernel accepts at most {}", bytes.len() / INSN_SIZE, MAX_FILTER_LEN);
+    }
+    Ok(bytes
+        .chunks_exact(INSN_SIZE)
+        .map(|b| Insn {
+            code: u16::from_ne_bytes([b[0], b[1]]),
+            jt: b[2],
+            jf: b[3],
+            k: u32::from_ne_bytes([b[4], b[5], b[6], b[7]]),
+        })
+        .collect())
+}
+
+/// Fails unless AppArmor is enabled and, when the profile list is readable
+/// (root), `profile` is loaded.
+fn check_apparmor(sys: &Path, profile: &str) -> Result<()> {
+    let enabled = std::fs::read_to_string(sys.join("module/apparmor/parameters/enabled")).unwrap_or_default();
+    if !enabled.starts_with('Y') {
+        anyhow::bail!("AppArmor is not enabled on this host");
+    }
+    if let Ok(profiles) = std::fs::read_to_string(sys.join("kernel/security/apparmor/profiles")) {
+        // One "name (mode)" per line
+        if !profiles.lines().any(|line| line.rsplit_once(" (").is_some_and(|(name, _)| name == profile)) {
+            anyhow::bail!("AppArmor profile '{}' is not loaded", profile);
+        }
+    }
+    Ok(())
+}
+
+/// Asks AppArmor to switch to the profile at the next exec.
+#[cfg(target_os = "linux")]
+fn change_profile_on_exec(request: &[u8]) -> std::io::Result<()> {
+    // The LSM-specific interface first (5.8+), then the shared one
+    for path in [c"/proc/self/attr/apparmor/exec", c"/proc/self/attr/exec"] {
+        // SAFETY: plain syscalls on a valid C string and buffer
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+        if fd < 0 {
+            continue;
+        }
+        let written = unsafe { libc::write(fd, request.as_ptr().cast(), request.len()) };
+        let error = std::io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        return if written == request.len() as isize { Ok(()) } else { Err(error) };
+    }
+    Err(std::io::Error::last_os_error())
+}
+
+#[cfg(target_os = "linux")]
+fn install_filter(filter: &[libc::sock_filter], no_new_privs: bool) -> std::io::Result<()> {
+    let program = libc::sock_fprog { len: filter.len() as u16, filter: filter.as_ptr() as *mut libc::sock_filter };
+    // SAFETY: prctl with a program that outlives the call
+    unsafe {
+        if no_new_privs && libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &program as *const libc::sock_fprog) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_native_endian_instructions() {
+        // BPF_RET | BPF_K, SECCOMP_RET_ALLOW
+        let mut bytes = Vec::new();
+        bytes.extend(0x06u16.to_ne_bytes());
+        bytes.extend([0, 0]);
+        bytes.extend(0x7fff_0000u32.to_ne_bytes());
+        assert_eq!(parse_filter(&bytes).unwrap(), vec![Insn { code: 0x06, jt: 0, jf: 0, k: 0x7fff_0000 }]);
+
+        assert!(parse_filter(&[]).is_err());
+        assert!(parse_filter(&bytes[..7]).is_err());
+        assert!(parse_filter(&vec![0; (MAX_FILTER_LEN + 1) * INSN_SIZE]).is_err());
+    }
+
+    #[test]
+    fn apparmor_profile_must_be_loaded() {
+        let sys = tempfile::tempdir().unwrap();
+        assert!(check_apparmor(sys.path(), "rollcron-etl").is_err());
+
+        std::fs::create_dir_all(sys.path().join("module/apparmor/parameters")).unwrap();
+        std::fs::write(sys.path().join("module/apparmor/parameters/enabled"), "Y\n").unwrap();
+        // Profile list unreadable (not root): the kernel has the last word
+        check_apparmor(sys.path(), "rollcron-etl").unwrap();
+
+        std::fs::create_dir_all(sys.path().join("kernel/security/apparmor")).unwrap();
+        std::fs::write(sys.path().join("kernel/security/apparmor/profiles"), "docker-default (enforce)\n").unwrap();
+        let err = check_apparmor(sys.path(), "rollcron-etl").unwrap_err();
+        assert_eq!(err.to_string(), "AppArmor profile 'rollcron-etl' is not loaded");
+        check_apparmor(sys.path(), "docker-default").unwrap();
+    }
+}
@@ -1,23 +1,138 @@
-use crate::config::{Job, RunnerConfig, TimezoneConfig};
-use chrono::{DateTime, Local, TimeZone, Utc};
+use crate::config::{BlackoutPolicy, BlackoutWindow, Job, RunnerConfig, TimezoneConfig};
+use chrono::{DateTime, Local, LocalResult, TimeZone, Timelike, Utc};
 use croner::Cron;
 
-/// Returns the next scheduled time for a job, or None if no future occurrence.
-pub fn next_occurrence(job: &Job, runner: &RunnerConfig) -> Option<DateTime<Utc>> {
-    next_occurrence_from(job, runner, Utc::now())
+/// Upper bound on blackout-skipped occurrences looked past for the next run
+const MAX_SKIPPED: usize = 10_000;
+
+/// What the scheduler does with an occurrence, given the job's blackout windows
+#[derive(Debug, Clone, PartialEq)]
+pub enum Disposition {
+    Run,
+    /// Inside a `skip` window (label)
+    Skip(String),
+    /// Inside `defer` windows only: run when the last of them ends
+    Defer(DateTime<Utc>, String),
 }
 
-/// Pure function: returns next scheduled time given a reference time.
+/// Returns the next start of a job, or None if no future occurrence.
+pub fn next_run(job: &Job, runner: &RunnerConfig) -> Option<NextRun> {
+    next_run_from(job, runner, crate::clock::now())
+}
+
+/// Pure function: returns next scheduled time given a reference time
+/// (None for `@reboot` jobs).
 pub fn next_occurrence_from(
     job: &Job,
     runner: &RunnerConfig,
     now: DateTime<Utc>,
 ) -> Option<DateTime<Utc>> {
-    let tz_config = job.timezone.as_ref().unwrap_or(&runner.timezone);
-    match tz_config {
-        TimezoneConfig::Utc => find_next_from(&job.schedule, Utc, now),
-        TimezoneConfig::Inherit => find_next_from(&job.schedule, Local, now),
-        TimezoneConfig::Named(tz) => find_next_from(&job.schedule, *tz, now),
+    let schedule = job.schedule.as_ref()?;
+    next_in_timezone(schedule, job.timezone.as_ref().unwrap_or(&runner.timezone), now)
+}
+
+/// Next occurrence of `schedule` after `now`, evaluated in `tz`.
+pub fn next_in_timezone(schedule: &Cron, tz: &TimezoneConfig, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match tz {
+        TimezoneConfig::Utc => find_next_from(schedule, Utc, now),
+        TimezoneConfig::Inherit => find_next_from(schedule, Local, now),
+        TimezoneConfig::Named(tz) => find_next_from(schedule, *tz, now),
+    }
+}
+
+/// Next start of a job once blackout windows are applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct NextRun {
+    /// When the run starts (the end of the window for deferred runs)
+    pub at: DateTime<Utc>,
+    /// Occurrences dropped by `skip` windows before it
+    pub skipped: usize,
+    /// Label of the `defer` window that moved it
+    pub deferred_by: Option<String>,
+}
+
+/// When the job actually starts next, after applying blackout windows.
+pub fn next_run_from(job: &Job, runner: &RunnerConfig, now: DateTime<Utc>) -> Option<NextRun> {
+    let mut cursor = now;
+    for skipped in 0..MAX_SKIPPED {
+        let at = next_occurrence_from(job, runner, cursor)?;
+        match disposition(job, runner, at) {
+            Disposition::Run => return Some(NextRun { at, skipped, deferred_by: None }),
+            Disposition::Defer(until, label) => return Some(NextRun { at: until, skipped, deferred_by: Some(label) }),
+            Disposition::Skip(_) => cursor = at,
+        }
+    }
+    None
+}
+
+/// Runs due from `first` (itself due) up to `now`, e.g. while the host slept.
+pub fn missed_runs(job: &Job, runner: &RunnerConfig, first: DateTime<Utc>, now: DateTime<Utc>) -> usize {
+    let mut missed = 1;
+    let mut cursor = first;
+    while missed < MAX_SKIPPED {
+        match next_run_from(job, runner, cursor) {
+            Some(next) if next.at <= now => {
+                missed += 1;
+                cursor = next.at;
+            }
+            _ => break,
+        }
+    }
+    missed
+}
+
+/// Checks an occurrence against the job's blackout windows, in the job's timezone.
+pub fn disposition(job: &Job, runner: &RunnerConfig, at: DateTime<Utc>) -> Disposition {
+    if job.blackout.is_empty() {
+        return Disposition::Run;
+    }
+    match job.timezone.as_ref().unwrap_or(&runner.timezone) {
+        TimezoneConfig::Utc => disposition_in(&job.blackout, Utc, at),
+        TimezoneConfig::Inherit => disposition_in(&job.blackout, Local, at),
+        TimezoneConfig::Named(tz) => disposition_in(&job.blackout, *tz, at),
+    }
+}
+
+fn disposition_in<Z: TimeZone>(windows: &[BlackoutWindow], tz: Z, at: DateTime<Utc>) -> Disposition {
+    let mut until = at;
+    let mut label = None;
+    // Deferring to the end of one window may land inside another
+    for _ in 0..=windows.len() {
+        let local = until.with_timezone(&tz).naive_local();
+        let local = local.with_nanosecond(0).unwrap_or(local);
+        let mut extended = false;
+        for window in windows {
+            let Some(remaining) = window.window.remaining(&local) else {
+                continue;
+            };
+            if window.policy == BlackoutPolicy::Skip {
+                return Disposition::Skip(window.window.label.clone());
+            }
+            let end = local_to_utc(&tz, local + chrono::Duration::seconds(remaining.into()));
+            if end > until {
+                until = end;
+                label = Some(window.window.label.clone());
+                extended = true;
+            }
+        }
+        if !extended {
+            break;
+        }
+    }
+    match label {
+        Some(label) => Disposition::Defer(until, label),
+        None => Disposition::Run,
+    }
+}
+
+/// Resolves a wall-clock time; inside a DST gap, the first valid time after it.
+fn local_to_utc<Z: TimeZone>(tz: &Z, local: chrono::NaiveDateTime) -> DateTime<Utc> {
+    let mut local = local;
+    loop {
+        match tz.from_local_datetime(&local) {
+            LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => return dt.with_timezone(&Utc),
+            LocalResult::None => local += chrono::Duration::minutes(15),
+        }
     }
 }
 
@@ -303,6 +418,23 @@ jobs:
         assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap());
     }
 
+    #[test]
+    fn missed_runs_counts_occurrences_slept_through() {
+        let yaml = r#"
+runner:
+  timezone: UTC
+jobs:
+  test:
+    schedule: "*/15 * * * *"
+    run: echo test
+"#;
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        let first = Utc.with_ymd_and_hms(2025, 1, 15, 1, 0, 0).unwrap();
+        assert_eq!(missed_runs(&jobs[0], &runner, first, first), 1);
+        let now = Utc.with_ymd_and_hms(2025, 1, 15, 4, 10, 0).unwrap();
+        assert_eq!(missed_runs(&jobs[0], &runner, first, now), 13);
+    }
+
     #[test]
     fn job_with_named_timezone() {
         let yaml = r#"
@@ -411,4 +543,55 @@ jobs:
         // Next Sunday is 2025-01-19
         assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 19, 7, 0, 0).unwrap());
     }
+
+    // ============================================================
+    // Blackout windows
+    // ============================================================
+
+    #[test]
+    fn weekly_blackout_skips_in_job_timezone() {
+        let yaml = r#"
+runner:
+  timezone: Asia/Tokyo
+  blackout:
+    - { from: "Sat 22:00", to: "Sun 02:00" }
+jobs:
+  test:
+    schedule: "0 * * * *"
+    run: echo test
+"#;
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        // 2025-01-18 is a Saturday; 22:00 Tokyo = 13:00 UTC
+        let at = |h| Utc.with_ymd_and_hms(2025, 1, 18, h, 0, 0).unwrap();
+        assert_eq!(disposition(&jobs[0], &runner, at(12)), Disposition::Run);
+        assert_eq!(disposition(&jobs[0], &runner, at(13)), Disposition::Skip("Sat 22:00-Sun 02:00".to_string()));
+        assert_eq!(disposition(&jobs[0], &runner, at(16)), Disposition::Skip("Sat 22:00-Sun 02:00".to_string()));
+        assert_eq!(disposition(&jobs[0], &runner, at(17)), Disposition::Run, "end is exclusive");
+        assert_eq!(
+            next_run_from(&jobs[0], &runner, at(12)),
+            Some(NextRun { at: at(17), skipped: 4, deferred_by: None })
+        );
+    }
+
+    #[test]
+    fn daily_blackout_defers_across_midnight_and_chains() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule: "*/30 * * * *"
+    run: echo test
+    blackout:
+      - { from: "23:00", to: "01:00", policy: defer }
+      - { from: "00:30", to: "02:00", policy: defer }
+"#;
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        let at = |d, h, m| Utc.with_ymd_and_hms(2025, 1, d, h, m, 0).unwrap();
+        assert_eq!(
+            disposition(&jobs[0], &runner, at(15, 23, 30)),
+            Disposition::Defer(at(16, 2, 0), "00:30-02:00".to_string())
+        );
+        assert_eq!(disposition(&jobs[0], &runner, at(16, 2, 0)), Disposition::Run);
+        let next = next_run_from(&jobs[0], &runner, at(15, 22, 45)).unwrap();
+        assert_eq!((next.at, next.skipped, next.deferred_by.as_deref()), (at(16, 2, 0), 0, Some("00:30-02:00")));
+    }
 }
@@ -0,0 +1,119 @@
+//! `runner.brake`: global safety brake. When more than `failure_rate` of the
+//! runs finished within `window` failed, something systemic is likely wrong
+//! (a bad host, a broken sync), so every job below `severity: critical` is
+//! paused until an operator releases the brake (`rollcron ctl brake --release`).
+
+use crate::config::BrakeConfig;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Recent run outcomes and, once tripped, what the brake paused
+#[derive(Debug, Default)]
+pub struct Brake {
+    /// (finished at, failed) of runs within the window
+    runs: VecDeque<(DateTime<Utc>, bool)>,
+    engaged: Option<Engaged>,
+}
+
+/// State of an engaged brake (for the status API)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Engaged {
+    pub since: DateTime<Utc>,
+    /// Failed and total runs in the window when it tripped
+    pub failed: usize,
+    pub runs: usize,
+    /// Jobs the brake paused, resumed on release (jobs paused before stay paused)
+    pub paused: Vec<String>,
+}
+
+impl Brake {
+    pub fn engaged(&self) -> Option<&Engaged> {
+        self.engaged.as_ref()
+    }
+
+    /// Records a run that finished at `at`. Returns true when this run trips
+    /// the brake; it then stays engaged until `release`.
+    pub fn record(&mut self, config: &BrakeConfig, at: DateTime<Utc>, failed: bool) -> bool {
+        self.runs.push_back((at, failed));
+        let window = chrono::Duration::from_std(config.window).unwrap_or(chrono::Duration::MAX);
+        while self.runs.front().is_some_and(|(t, _)| at - *t > window) {
+            self.runs.pop_front();
+        }
+        if self.engaged.is_some() || self.runs.len() < config.min_runs {
+            return false;
+        }
+        let failures = self.runs.iter().filter(|(_, failed)| *failed).count();
+        if failures as f64 > self.runs.len() as f64 * config.failure_rate {
+            self.engaged = Some(Engaged { since: at, failed: failures, runs: self.runs.len(), paused: Vec::new() });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Notes a job paused by the engaged brake, to resume it on release.
+    pub fn paused(&mut self, job_id: &str) {
+        if let Some(engaged) = &mut self.engaged {
+            if !engaged.paused.iter().any(|id| id == job_id) {
+                engaged.paused.push(job_id.to_string());
+            }
+        }
+    }
+
+    /// Disengages the brake and forgets the window, so the failures that
+    /// tripped it don't trip it again. Returns the state it was in.
+    pub fn release(&mut self) -> Option<Engaged> {
+        self.runs.clear();
+        self.engaged.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::time::Duration;
+
+    fn config() -> BrakeConfig {
+        BrakeConfig { failure_rate: 0.5, window: Duration::from_secs(600), min_runs: 4 }
+    }
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 0, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn trips_above_rate_with_enough_runs() {
+        let mut brake = Brake::default();
+        assert!(!brake.record(&config(), at(0), true));
+        assert!(!brake.record(&config(), at(1), true));
+        assert!(!brake.record(&config(), at(2), false));
+        // 3 of 4 failed
+        assert!(brake.record(&config(), at(3), true));
+        assert_eq!(brake.engaged().map(|e| (e.failed, e.runs)), Some((3, 4)));
+        // Stays engaged without tripping again
+        assert!(!brake.record(&config(), at(4), true));
+
+        brake.paused("etl");
+        brake.paused("etl");
+        assert_eq!(brake.release().unwrap().paused, vec!["etl"]);
+        assert!(brake.engaged().is_none());
+        assert!(!brake.record(&config(), at(5), true));
+    }
+
+    #[test]
+    fn old_runs_leave_the_window() {
+        let mut brake = Brake::default();
+        for minute in 0..3 {
+            brake.record(&config(), at(minute), true);
+        }
+        // The failures at 0..=2 are over 10 minutes old by 13
+        assert!(!brake.record(&config(), at(13), false));
+        assert!(!brake.record(&config(), at(14), false));
+        assert!(!brake.record(&config(), at(15), true));
+        // Half failed is not more than half
+        assert!(!brake.record(&config(), at(16), true));
+        assert!(brake.engaged().is_none());
+    }
+}
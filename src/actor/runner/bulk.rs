@@ -0,0 +1,144 @@
+//! Bulk job operations (pause/resume/trigger many jobs in one call).
+
+use crate::actor::job::{JobActor, Pause, Resume, RunOverrides, Trigger};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use xtra::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkAction {
+    Pause,
+    Resume,
+    Trigger,
+}
+
+impl BulkAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BulkAction::Pause => "pause",
+            BulkAction::Resume => "resume",
+            BulkAction::Trigger => "trigger",
+        }
+    }
+}
+
+/// Which jobs a bulk action applies to
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobSelector {
+    All,
+    Ids(Vec<String>),
+    /// `key:value` (or `key=value`) label match
+    Label { key: String, value: String },
+}
+
+impl JobSelector {
+    pub fn label(spec: &str) -> Result<Self, String> {
+        match spec.split_once([':', '=']) {
+            Some((key, value)) if !key.is_empty() => Ok(JobSelector::Label {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(format!("Invalid label selector '{}' (expected key:value)", spec)),
+        }
+    }
+
+    /// Resolves to sorted job IDs. Unknown IDs are an error so a typo
+    /// aborts the whole call instead of applying it partially.
+    pub fn resolve(&self, labels: &HashMap<String, HashMap<String, String>>) -> Result<Vec<String>, String> {
+        let mut ids: Vec<String> = match self {
+            JobSelector::All => labels.keys().cloned().collect(),
+            JobSelector::Ids(ids) => {
+                let unknown: Vec<&str> = ids
+                    .iter()
+                    .filter(|id| !labels.contains_key(*id))
+                    .map(String::as_str)
+                    .collect();
+                if !unknown.is_empty() {
+                    return Err(format!("Unknown job(s): {}", unknown.join(", ")));
+                }
+                ids.clone()
+            }
+            JobSelector::Label { key, value } => labels
+                .iter()
+                .filter(|(_, l)| l.get(key) == Some(value))
+                .map(|(id, _)| id.clone())
+                .collect(),
+        };
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+}
+
+/// Summarized result of a bulk action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkResult {
+    pub action: BulkAction,
+    pub matched: usize,
+    pub succeeded: Vec<String>,
+    /// job ID -> reason
+    pub failed: BTreeMap<String, String>,
+}
+
+/// Applies `action` to each job actor, collecting per-job outcomes.
+/// `overrides` only apply to `Trigger`.
+pub async fn apply(action: BulkAction, overrides: &RunOverrides, targets: Vec<(String, Address<JobActor>)>) -> BulkResult {
+    let mut result = BulkResult {
+        action,
+        matched: targets.len(),
+        succeeded: Vec::new(),
+        failed: BTreeMap::new(),
+    };
+
+    for (job_id, addr) in targets {
+        let outcome = match action {
+            BulkAction::Pause => addr.send(Pause { persist: true }).await.map(Ok),
+            BulkAction::Resume => addr.send(Resume).await.map(Ok),
+            BulkAction::Trigger => addr.send(Trigger { overrides: overrides.clone() }).await,
+        };
+        match outcome {
+            Ok(Ok(())) => result.succeeded.push(job_id),
+            Ok(Err(e)) => {
+                result.failed.insert(job_id, e.to_string());
+            }
+            Err(_) => {
+                result.failed.insert(job_id, "Job actor stopped".to_string());
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels() -> HashMap<String, HashMap<String, String>> {
+        HashMap::from([
+            ("a".to_string(), HashMap::from([("team".to_string(), "data".to_string())])),
+            ("b".to_string(), HashMap::from([("team".to_string(), "web".to_string())])),
+            ("c".to_string(), HashMap::from([("team".to_string(), "data".to_string())])),
+        ])
+    }
+
+    #[test]
+    fn select_all_sorted() {
+        assert_eq!(JobSelector::All.resolve(&labels()).unwrap(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn select_by_label() {
+        let selector = JobSelector::label("team:data").unwrap();
+        assert_eq!(selector.resolve(&labels()).unwrap(), vec!["a", "c"]);
+        let selector = JobSelector::label("team=web").unwrap();
+        assert_eq!(selector.resolve(&labels()).unwrap(), vec!["b"]);
+        assert!(JobSelector::label("team").is_err());
+    }
+
+    #[test]
+    fn unknown_ids_reject_whole_selection() {
+        let selector = JobSelector::Ids(vec!["a".to_string(), "zz".to_string()]);
+        assert_eq!(selector.resolve(&labels()).unwrap_err(), "Unknown job(s): zz");
+    }
+}
@@ -0,0 +1,229 @@
+//! `runner.canary`: a synthetic job run on its own schedule through every
+//! stage a real job goes through, alerting on the first one that fails.
+//!
+//! Stages: the last git sync succeeded recently enough, the job directory
+//! materializes from the current commit, the command runs, its run and output
+//! read back from history, and (with `ping_url`) the heartbeat is delivered.
+
+use super::{GetRunnerConfig, GetSyncStatus, SyncStatus};
+use crate::actor::job::{execute_job, next_occurrence_from, RunStart};
+use crate::config::{CanaryConfig, NotifyEvent, RunnerConfig, CANARY_OUTPUT, CONFIG_FILE};
+use crate::events::{self, Event};
+use crate::{clock, env, git, history, webhook};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+use xtra::prelude::*;
+use xtra::refcount::Weak;
+
+/// How often a disabled canary checks whether the config enabled it
+const CONFIG_RECHECK: Duration = Duration::from_secs(60);
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub async fn run<A>(sot_path: PathBuf, pull_interval: Duration, addr: Address<A, Weak>)
+where
+    A: Handler<GetRunnerConfig, Return = RunnerConfig> + Handler<GetSyncStatus, Return = (SyncStatus, PathBuf, Duration)>,
+{
+    loop {
+        let Ok(runner) = addr.send(GetRunnerConfig).await else {
+            return; // Runner stopped
+        };
+        let next = runner
+            .canary
+            .as_ref()
+            .and_then(|canary| next_occurrence_from(&canary.job, &runner, clock::now()));
+        let Some(at) = next else {
+            tokio::time::sleep(CONFIG_RECHECK).await;
+            continue;
+        };
+        tokio::time::sleep(clock::until(at)).await;
+
+        // The config may have changed while waiting
+        let Ok(runner) = addr.send(GetRunnerConfig).await else {
+            return;
+        };
+        let Ok((sync, _, _)) = addr.send(GetSyncStatus).await else {
+            return;
+        };
+        let Some(canary) = &runner.canary else {
+            continue;
+        };
+        let max_sync_age = canary.max_sync_age.unwrap_or(pull_interval * 2);
+        match check(canary, &runner, &sot_path, &sync, max_sync_age).await {
+            Ok(()) => {
+                info!(target: "rollcron::runner", "Canary passed");
+                events::emit(Event::CanaryPassed);
+            }
+            Err((stage, error)) => {
+                warn!(target: "rollcron::runner", stage = stage.as_str(), error = %error, "Canary failed");
+                events::emit(Event::CanaryFailed { stage: stage.as_str(), error: &error });
+                notify_failure(&sot_path, &runner, stage, &error).await;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Sync,
+    Materialize,
+    Exec,
+    History,
+    Ping,
+}
+
+impl Stage {
+    fn as_str(self) -> &'static str {
+        match self {
+            Stage::Sync => "sync",
+            Stage::Materialize => "materialize",
+            Stage::Exec => "exec",
+            Stage::History => "history",
+            Stage::Ping => "ping",
+        }
+    }
+}
+
+async fn check(
+    canary: &CanaryConfig,
+    runner: &RunnerConfig,
+    sot_path: &Path,
+    sync: &SyncStatus,
+    max_sync_age: Duration,
+) -> Result<(), (Stage, String)> {
+    check_sync(sync, max_sync_age).map_err(|e| (Stage::Sync, e))?;
+
+    let job = &canary.job;
+    let build_dir = git::get_build_dir(sot_path, &job.id);
+    let run_dir = git::get_run_dir(sot_path, &job.id);
+    git::sync_to_build_dir(sot_path, &build_dir, runner.submodules, runner.lfs)
+        .and_then(|()| git::copy_build_to_run(&build_dir, &run_dir))
+        .map_err(|e| (Stage::Materialize, format!("{:#}", e)))?;
+    if !run_dir.join(CONFIG_FILE).exists() {
+        return Err((Stage::Materialize, format!("{} missing from {}", CONFIG_FILE, run_dir.display())));
+    }
+
+    let report = execute_job(job, sot_path, runner, &RunStart::default()).await;
+    if !report.is_success() {
+        let error = report.error.unwrap_or_else(|| report.outcome.as_str().to_string());
+        return Err((Stage::Exec, error));
+    }
+
+    check_history(&git::get_history_dir(sot_path, &job.id), &report.run_id).map_err(|e| (Stage::History, e))?;
+
+    let runner_env = env::load_runner_env(sot_path, runner).await;
+    if let Some(url) = canary.ping_url(runner_env.as_ref()) {
+        ping(&url).await.map_err(|e| (Stage::Ping, e))?;
+    }
+    Ok(())
+}
+
+/// The last fetch succeeded, and not longer than `max_age` ago. Passes
+/// before the first fetch.
+fn check_sync(sync: &SyncStatus, max_age: Duration) -> Result<(), String> {
+    if let Some(error) = &sync.error {
+        return Err(format!("last git sync failed: {}", error));
+    }
+    if let Some(last) = sync.last_success {
+        let age = (clock::now() - last).to_std().unwrap_or_default();
+        if age > max_age {
+            return Err(format!("last successful git sync was {}s ago (max_sync_age {}s)", age.as_secs(), max_age.as_secs()));
+        }
+    }
+    Ok(())
+}
+
+/// The run is in history with the canary's output.
+fn check_history(dir: &Path, run_id: &str) -> Result<(), String> {
+    let runs = history::read_runs(dir).map_err(|e| format!("{:#}", e))?;
+    if !runs.iter().any(|run| run.run_id == run_id) {
+        return Err(format!("run {} not found in {}", run_id, dir.display()));
+    }
+    match history::read_output(dir, run_id) {
+        Ok(Some(output)) if String::from_utf8_lossy(&output).contains(CANARY_OUTPUT) => Ok(()),
+        Ok(_) => Err(format!("output of run {} not recorded", run_id)),
+        Err(e) => Err(format!("{:#}", e)),
+    }
+}
+
+async fn ping(url: &str) -> Result<(), String> {
+    let client = reqwest::Client::builder().timeout(PING_TIMEOUT).build().map_err(|e| e.to_string())?;
+    let resp = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status()));
+    }
+    Ok(())
+}
+
+/// Sends `canary_failure` to every runner webhook whose filter accepts it.
+async fn notify_failure(sot_path: &Path, runner: &RunnerConfig, stage: Stage, error: &str) {
+    let runner_env = env::load_runner_env(sot_path, runner).await;
+    for wh in runner.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::CanaryFailure, None, runner)) {
+        let url = wh.to_url(runner_env.as_ref());
+        if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
+            warn!(target: "rollcron::webhook", url = %url, "Invalid webhook URL, skipping");
+            continue;
+        }
+        webhook::send_canary_failure(&url, wh.template.as_deref(), stage.as_str(), error).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::job::report::RunOutcome;
+    use crate::actor::job::JobRunReport;
+
+    fn sync(error: Option<&str>, age_secs: i64) -> SyncStatus {
+        SyncStatus {
+            commit: None,
+            last_attempt: Some(clock::now()),
+            last_success: Some(clock::now() - chrono::Duration::seconds(age_secs)),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn sync_must_be_recent_and_successful() {
+        let max_age = Duration::from_secs(7200);
+        assert_eq!(check_sync(&sync(None, 60), max_age), Ok(()));
+        assert!(check_sync(&sync(None, 7300), max_age).unwrap_err().contains("7300s ago"));
+        assert!(check_sync(&sync(Some("network down"), 60), max_age).unwrap_err().contains("network down"));
+
+        let never = SyncStatus { commit: None, last_attempt: None, last_success: None, error: None };
+        assert_eq!(check_sync(&never, max_age), Ok(()));
+    }
+
+    #[test]
+    fn history_must_hold_run_and_output() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(check_history(dir.path(), "missing").is_err());
+
+        let report = JobRunReport {
+            job_id: "_canary".to_string(),
+            run_id: "20260101T000000Z-00000001".to_string(),
+            run_number: None,
+            commit: None,
+            started_at: clock::now(),
+            duration: Duration::from_millis(5),
+            attempts: 1,
+            outcome: RunOutcome::Success,
+            exit_code: Some(0),
+            error: None,
+            failure_class: None,
+            fallback: false,
+            stdout_tail: String::new(),
+            stderr_tail: String::new(),
+            omitted_bytes: 0,
+            outside_writes: vec![],
+            imported: false,
+            timings: None,
+            trigger: None,
+            overrides: Vec::new(),
+            artifacts: Vec::new(),
+        };
+        history::record(dir.path(), &report, b"rollcron-canary\n", Default::default(), None).unwrap();
+        assert_eq!(check_history(dir.path(), &report.run_id), Ok(()));
+        assert!(check_history(dir.path(), "20260101T000000Z-00000002").unwrap_err().contains("not found"));
+    }
+}
@@ -1,29 +1,59 @@
-use super::{ConfigUpdate, GetRunnerConfig};
-use crate::config::{self, RunnerConfig};
-use crate::{env, git, webhook};
-use std::collections::HashMap;
+use super::{ConfigUpdate, GetRunnerConfig, SyncAttempted};
+use crate::config::{self, NotifyEvent, RunnerConfig};
+use crate::events::{self, Event};
+use crate::otel::Span;
+use crate::{clock, env, source, webhook};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
-use tokio::time::interval;
+use tokio::time::{interval, MissedTickBehavior};
 use tracing::{error, info, warn};
 use xtra::prelude::*;
 use xtra::refcount::Weak;
 
-const CONFIG_FILE: &str = "rollcron.yaml";
-
 pub async fn run<A>(sot_path: PathBuf, pull_interval: Duration, addr: Address<A, Weak>)
 where
-    A: Handler<ConfigUpdate> + Handler<GetRunnerConfig, Return = RunnerConfig>,
+    A: Handler<ConfigUpdate> + Handler<GetRunnerConfig, Return = RunnerConfig> + Handler<SyncAttempted>,
 {
     let mut ticker = interval(pull_interval);
+    // Never burst through ticks missed while suspended
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut resumes = clock::resumes();
+    let mut last_sync = clock::now();
 
     loop {
-        ticker.tick().await;
+        tokio::select! {
+            _ = ticker.tick() => {}
+            Ok(()) = resumes.changed() => {
+                // One sync for the whole suspend, unless a tick just did it
+                ticker.reset();
+                if clock::now() - last_sync < chrono::Duration::from_std(clock::SUSPEND_CHECK).unwrap_or_default() {
+                    continue;
+                }
+                info!(target: "rollcron::runner", "Syncing after suspend");
+            }
+        }
+        last_sync = clock::now();
+
+        // Commits are checked against the config already applied, so one can't vouch for itself
+        let Ok(runner) = addr.send(GetRunnerConfig).await else {
+            return; // Runner stopped
+        };
 
-        let update_info = match git::sync_repo(&sot_path) {
+        let mut span = Span::root("git.pull").with_attr("repo.path", sot_path.display().to_string());
+        let result = source::sync(&sot_path, &runner);
+        let error = result.as_ref().err().map(|e| format!("{:#}", e));
+        match (&result, &error) {
+            (Ok(Some(range)), _) => span.set_attr("git.range", range.as_str()),
+            (_, Some(error)) => span.fail(error),
+            _ => {}
+        }
+        drop(span);
+        let _ = addr.send(SyncAttempted { error }).await;
+        let update_info = match result {
             Ok(r) => r,
             Err(e) => {
                 error!(target: "rollcron::runner", error = %e, "Git sync failed");
+                events::emit(Event::SyncFailed { error: &format!("{:#}", e) });
                 continue;
             }
         };
@@ -33,25 +63,36 @@ where
         };
 
         info!(target: "rollcron::runner", range = %range, "Pulled updates");
+        apply_update(&addr, &sot_path, &range).await;
+    }
+}
 
-        match load_config(&sot_path) {
-            Ok((runner, jobs)) => {
-                if let Err(e) = addr
-                    .send(ConfigUpdate {
-                        sot_path: sot_path.clone(),
-                        runner,
-                        jobs,
-                    })
-                    .await
-                {
-                    error!(target: "rollcron::runner", error = %e, "Failed to send config update");
-                }
-            }
-            Err(e) => {
-                error!(target: "rollcron::runner", error = %e, "Failed to reload config");
-                notify_config_error(&addr, &sot_path, &e.to_string()).await;
+/// Loads the config of a freshly pulled commit and hands it to the runner.
+/// Also used by `pull_on_run` jobs, which sync outside the poll loop.
+pub async fn apply_update<A>(addr: &Address<A, Weak>, sot_path: &Path, range: &str)
+where
+    A: Handler<ConfigUpdate> + Handler<GetRunnerConfig, Return = RunnerConfig>,
+{
+    events::emit(Event::RepoUpdated { range });
+
+    match config::load_config(sot_path) {
+        Ok((runner, jobs)) => {
+            if let Err(e) = addr
+                .send(ConfigUpdate {
+                    sot_path: sot_path.to_path_buf(),
+                    runner,
+                    jobs,
+                })
+                .await
+            {
+                error!(target: "rollcron::runner", error = %e, "Failed to send config update");
             }
         }
+        Err(e) => {
+            error!(target: "rollcron::runner", error = %e, "Failed to reload config");
+            events::emit(Event::ConfigError { error: &e.to_string() });
+            notify_config_error(addr, sot_path, &e.to_string()).await;
+        }
     }
 }
 
@@ -68,8 +109,8 @@ where
         return;
     }
 
-    let runner_env = load_runner_env(sot_path, &runner);
-    for wh in &runner.webhook {
+    let runner_env = env::load_runner_env(sot_path, &runner).await;
+    for wh in runner.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::ConfigError, None, &runner)) {
         let url = wh.to_url(runner_env.as_ref());
         if url.contains('$') {
             warn!(target: "rollcron::webhook", url = %url, "Webhook URL contains unexpanded variable, skipping");
@@ -79,37 +120,7 @@ where
             warn!(target: "rollcron::webhook", url = %url, "Webhook URL must start with http:// or https://, skipping");
             continue;
         }
-        webhook::send_config_error(&url, error).await;
+        webhook::send_config_error(&url, wh.template.as_deref(), error).await;
     }
 }
 
-fn load_runner_env(sot_path: &Path, runner: &RunnerConfig) -> Option<HashMap<String, String>> {
-    let mut env_vars = HashMap::new();
-
-    if let Some(env_file_path) = &runner.env_file {
-        let expanded = env::expand_string(env_file_path);
-        let full_path = sot_path.join(&expanded);
-        match env::load_env_from_path(&full_path) {
-            Ok(vars) => env_vars.extend(vars),
-            Err(e) => {
-                warn!(target: "rollcron::webhook", error = %e, "Failed to load runner env_file");
-                return None;
-            }
-        }
-    }
-
-    if let Some(runner_env) = &runner.env {
-        for (k, v) in runner_env {
-            env_vars.insert(k.clone(), env::expand_string(v));
-        }
-    }
-
-    Some(env_vars)
-}
-
-fn load_config(sot_path: &Path) -> anyhow::Result<(config::RunnerConfig, Vec<config::Job>)> {
-    let config_path = sot_path.join(CONFIG_FILE);
-    let content = std::fs::read_to_string(&config_path)
-        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", config_path.display(), e))?;
-    config::parse_config(&content)
-}
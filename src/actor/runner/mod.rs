@@ -1,26 +1,49 @@
-mod git_poll;
+pub mod brake;
+pub mod bulk;
+mod canary;
+pub mod git_poll;
 mod lifecycle;
 
-use crate::actor::job::{JobActor, Shutdown, SyncNeeded, Update};
-use crate::config::{self, Job, RunnerConfig};
-use std::collections::HashMap;
+use crate::actor::job::{
+    Approve, GetStatus, JobActor, JobRunReport, Pause, Resume, Resync, RunOnStart, RunOverrides, Shutdown, SyncNeeded,
+    Update,
+};
+use crate::actor::job::report::RunOutcome;
+use crate::config::{self, Job, LabelSelector, NotifyEvent, RunnerConfig, Severity};
+use crate::events::{self, Event};
+use crate::{clock, env, facts, git, metrics, webhook};
+use brake::{Brake, Engaged};
+use bulk::{BulkAction, BulkResult, JobSelector};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::time::Duration;
 use tokio::task::JoinHandle;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 use xtra::prelude::*;
 use xtra::refcount::Weak;
 
-const CONFIG_FILE: &str = "rollcron.yaml";
 
 /// Runner Actor - manages the lifecycle of all job actors
 pub struct RunnerActor {
     pull_interval: Duration,
     sot_path: PathBuf,
     runner_config: RunnerConfig,
+    /// `--only-labels`: run only jobs matching one of these (all if empty)
+    only_labels: Vec<LabelSelector>,
     job_actors: HashMap<String, Address<JobActor>>,
+    /// Labels of each job, for bulk selection
+    job_labels: HashMap<String, HashMap<String, String>>,
+    /// Jobs with `severity: critical`, which `runner.brake` leaves running
+    critical_jobs: HashSet<String>,
+    brake: Brake,
+    sync_status: SyncStatus,
+    /// Run metrics exported to `runner.metrics`
+    metrics: metrics::Registry,
     poll_handle: Option<JoinHandle<()>>,
     supervisor_handle: Option<JoinHandle<()>>,
+    canary_handle: Option<JoinHandle<()>>,
     self_addr: Option<Address<Self, Weak>>,
 }
 
@@ -29,20 +52,53 @@ impl RunnerActor {
         pull_interval: Duration,
         sot_path: PathBuf,
         runner_config: RunnerConfig,
+        only_labels: Vec<LabelSelector>,
     ) -> Self {
+        let sync_status = SyncStatus {
+            commit: git::head_commit(&sot_path),
+            last_attempt: None,
+            last_success: None,
+            error: None,
+        };
         Self {
             pull_interval,
             sot_path,
             runner_config,
+            only_labels,
             job_actors: HashMap::new(),
+            job_labels: HashMap::new(),
+            critical_jobs: HashSet::new(),
+            brake: Brake::default(),
+            sync_status,
+            metrics: metrics::Registry::default(),
             poll_handle: None,
             supervisor_handle: None,
+            canary_handle: None,
             self_addr: None,
         }
     }
 
+    /// Drops jobs excluded by `--only-labels` or whose `when` doesn't match this host.
+    fn select(&self, jobs: Vec<Job>) -> Vec<Job> {
+        jobs.into_iter()
+            .filter(|job| {
+                if !job.has_any_label(&self.only_labels) {
+                    debug!(target: "rollcron::runner", job_id = %job.id, "Not running job (excluded by --only-labels)");
+                    return false;
+                }
+                if let Some(reason) = job.host_mismatch(facts::get()) {
+                    debug!(target: "rollcron::runner", job_id = %job.id, reason = %reason, "Not running job (excluded by when)");
+                    return false;
+                }
+                true
+            })
+            .collect()
+    }
+
     fn spawn_job_actor(&mut self, job: Job) {
         let job_id = job.id.clone();
+        self.job_labels.insert(job_id.clone(), job.labels.clone());
+        self.set_critical(&job);
         let runner_addr = self.self_addr.clone();
         let actor = JobActor::new(
             job,
@@ -51,8 +107,75 @@ impl RunnerActor {
             runner_addr,
         );
         let addr = xtra::spawn_tokio(actor, Mailbox::unbounded());
+
+        // Jobs added while the brake is engaged start paused
+        if self.brake.engaged().is_some() && !self.critical_jobs.contains(&job_id) {
+            self.brake.paused(&job_id);
+            let addr = addr.clone();
+            tokio::spawn(async move {
+                let _ = addr.send(Pause { persist: false }).await;
+            });
+        }
         self.job_actors.insert(job_id, addr);
     }
+
+    fn set_critical(&mut self, job: &Job) {
+        if job.severity == Severity::Critical {
+            self.critical_jobs.insert(job.id.clone());
+        } else {
+            self.critical_jobs.remove(&job.id);
+        }
+    }
+
+    /// Feeds a finished run to `runner.brake`. When it trips, non-critical
+    /// jobs are paused in the background (job actors may be waiting on this
+    /// actor) and reported back with `BrakeApplied`.
+    fn check_brake(&mut self, report: &JobRunReport) {
+        let Some(config) = &self.runner_config.brake else {
+            return;
+        };
+        // A skipped run neither failed nor proved the job healthy
+        if report.outcome == RunOutcome::Skipped {
+            return;
+        }
+        if !self.brake.record(config, clock::now(), !report.is_success()) {
+            return;
+        }
+        let targets: Vec<(String, Address<JobActor>)> = self
+            .job_actors
+            .iter()
+            .filter(|(id, _)| !self.critical_jobs.contains(*id))
+            .map(|(id, addr)| (id.clone(), addr.clone()))
+            .collect();
+        let Some(self_addr) = self.self_addr.clone() else {
+            return;
+        };
+        tokio::spawn(async move {
+            let mut paused = Vec::new();
+            for (job_id, addr) in targets {
+                // Jobs an operator paused stay paused after release
+                if addr.send(GetStatus).await.is_ok_and(|s| !s.paused) && addr.send(Pause { persist: false }).await.is_ok() {
+                    paused.push(job_id);
+                }
+            }
+            paused.sort();
+            let _ = self_addr.send(BrakeApplied { paused }).await;
+        });
+    }
+
+    /// `runner.changelog`: notes an operator action on the running commit, in the background.
+    fn record_action(&self, action: String) {
+        if !self.runner_config.changelog {
+            return;
+        }
+        let sot_path = self.sot_path.clone();
+        let line = format!("{} {}", clock::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true), action);
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = git::record_changelog(&sot_path, &line) {
+                warn!(target: "rollcron::runner", error = %format!("{:#}", e), "Failed to record changelog note");
+            }
+        });
+    }
 }
 
 impl Actor for RunnerActor {
@@ -76,6 +199,13 @@ impl Actor for RunnerActor {
             lifecycle::supervise(supervisor_addr).await;
         }));
 
+        // Start canary loop (idle until runner.canary is configured)
+        let sot_path = self.sot_path.clone();
+        let canary_addr = addr.clone();
+        self.canary_handle = Some(tokio::spawn(async move {
+            canary::run(sot_path, pull_interval, canary_addr).await;
+        }));
+
         info!(target: "rollcron::runner", "Runner actor started");
         Ok(())
     }
@@ -87,6 +217,9 @@ impl Actor for RunnerActor {
         if let Some(handle) = self.supervisor_handle.take() {
             handle.abort();
         }
+        if let Some(handle) = self.canary_handle.take() {
+            handle.abort();
+        }
 
         // Shutdown all job actors (fire-and-forget)
         for (_, addr) in self.job_actors.drain() {
@@ -110,11 +243,19 @@ impl Handler<Initialize> for RunnerActor {
     type Return = ();
 
     async fn handle(&mut self, msg: Initialize, _ctx: &mut Context<Self>) {
-        for job in msg.jobs {
+        for job in self.select(msg.jobs) {
             let job_id = job.id.clone();
+            let run_on_start = job.run_on_start && job.enabled;
             // Job actor will handle initial build/sync via pending_sync flag
             info!(target: "rollcron::runner", job_id = %job_id, "Spawning job actor");
             self.spawn_job_actor(job);
+            if run_on_start {
+                if let Some(addr) = self.job_actors.get(&job_id).cloned() {
+                    tokio::spawn(async move {
+                        let _ = addr.send(RunOnStart).await;
+                    });
+                }
+            }
         }
     }
 }
@@ -131,9 +272,12 @@ impl Handler<ConfigUpdate> for RunnerActor {
 
     async fn handle(&mut self, msg: ConfigUpdate, _ctx: &mut Context<Self>) {
         self.sot_path = msg.sot_path.clone();
+        if msg.runner.ha != self.runner_config.ha {
+            warn!(target: "rollcron::runner", "runner.ha changed; restart rollcron to apply it");
+        }
         self.runner_config = msg.runner;
 
-        let new_job_ids: HashMap<String, Job> = msg.jobs.into_iter().map(|j| (j.id.clone(), j)).collect();
+        let new_job_ids: HashMap<String, Job> = self.select(msg.jobs).into_iter().map(|j| (j.id.clone(), j)).collect();
 
         // Find jobs to remove
         let to_remove: Vec<String> = self
@@ -144,20 +288,43 @@ impl Handler<ConfigUpdate> for RunnerActor {
             .collect();
 
         // Remove deleted jobs (fire-and-forget)
+        let mut stopping = Vec::new();
         for job_id in to_remove {
+            self.job_labels.remove(&job_id);
+            self.critical_jobs.remove(&job_id);
             if let Some(addr) = self.job_actors.remove(&job_id) {
                 info!(target: "rollcron::runner", job_id = %job_id, "Removing job actor");
-                tokio::spawn(async move {
-                    let _ = addr.send(Shutdown).await;
-                });
+                events::emit(Event::JobRemoved { job_id: &job_id });
+                stopping.push(addr);
             }
         }
 
+        // Then drop their directories, once their runs are aborted
+        let mut keep: Vec<String> = new_job_ids.keys().cloned().collect();
+        keep.extend(self.runner_config.canary.as_ref().map(|c| c.job.id.clone()));
+        let remove_orphans = self.runner_config.remove_orphan_dirs;
+        let sot_path = msg.sot_path.clone();
+        tokio::spawn(async move {
+            for addr in stopping {
+                let _ = addr.send(Shutdown).await;
+                addr.join().await;
+            }
+            if remove_orphans {
+                let removed = tokio::task::spawn_blocking(move || git::remove_orphan_job_dirs(&sot_path, &keep))
+                    .await
+                    .unwrap_or_default();
+                if !removed.is_empty() {
+                    info!(target: "rollcron::runner", jobs = %removed.join(","), "Removed directories of jobs no longer in the config");
+                }
+            }
+        });
+
         // Update or create jobs
         for (job_id, job) in new_job_ids {
-            if let Some(addr) = self.job_actors.get(&job_id) {
+            if let Some(addr) = self.job_actors.get(&job_id).cloned() {
                 // Update existing job (fire-and-forget)
-                let addr = addr.clone();
+                self.job_labels.insert(job_id.clone(), job.labels.clone());
+                self.set_critical(&job);
                 let sot_path = msg.sot_path.clone();
                 let runner = self.runner_config.clone();
                 tokio::spawn(async move {
@@ -167,6 +334,7 @@ impl Handler<ConfigUpdate> for RunnerActor {
             } else {
                 // Create new job - job actor will handle initial build/sync
                 info!(target: "rollcron::runner", job_id = %job_id, "Spawning new job actor");
+                events::emit(Event::JobAdded { job_id: &job_id });
                 self.spawn_job_actor(job);
             }
         }
@@ -207,19 +375,10 @@ impl Handler<RespawnJob> for RunnerActor {
         warn!(target: "rollcron::runner", job_id = %msg.job_id, "Respawning job actor after unexpected stop");
 
         // Re-read config to get job definition
-        let config_path = self.sot_path.join(CONFIG_FILE);
-        let content = match std::fs::read_to_string(&config_path) {
+        let (_, jobs) = match config::load_config(&self.sot_path) {
             Ok(c) => c,
             Err(e) => {
-                error!(target: "rollcron::runner", error = %e, "Failed to read config for respawn");
-                return;
-            }
-        };
-
-        let (_, jobs) = match config::parse_config(&content) {
-            Ok(c) => c,
-            Err(e) => {
-                error!(target: "rollcron::runner", error = %e, "Failed to parse config for respawn");
+                error!(target: "rollcron::runner", error = %e, "Failed to load config for respawn");
                 return;
             }
         };
@@ -230,6 +389,120 @@ impl Handler<RespawnJob> for RunnerActor {
     }
 }
 
+/// Discard and rebuild a job's directory (returns None for unknown jobs)
+pub struct ResyncJob {
+    pub job_id: String,
+}
+
+impl Handler<ResyncJob> for RunnerActor {
+    type Return = Option<anyhow::Result<()>>;
+
+    async fn handle(&mut self, msg: ResyncJob, _ctx: &mut Context<Self>) -> Self::Return {
+        let addr = self.job_actors.get(&msg.job_id)?;
+        let result = addr.send(Resync).await.unwrap_or_else(|_| Err(anyhow::anyhow!("Job actor stopped")));
+        if result.is_ok() {
+            self.record_action(format!("resync: {}", msg.job_id));
+        }
+        Some(result)
+    }
+}
+
+/// Approve a job blocked by the host policy (returns None for unknown jobs)
+pub struct ApproveJob {
+    pub job_id: String,
+}
+
+impl Handler<ApproveJob> for RunnerActor {
+    type Return = Option<anyhow::Result<bool>>;
+
+    async fn handle(&mut self, msg: ApproveJob, _ctx: &mut Context<Self>) -> Self::Return {
+        let addr = self.job_actors.get(&msg.job_id)?;
+        let result = addr.send(Approve).await.unwrap_or_else(|_| Err(anyhow::anyhow!("Job actor stopped")));
+        if matches!(result, Ok(true)) {
+            self.record_action(format!("approve: {}", msg.job_id));
+        }
+        Some(result)
+    }
+}
+
+/// Apply an action to every selected job. The selection is resolved
+/// up front, so an unknown job ID rejects the call before anything runs.
+pub struct Bulk {
+    pub action: BulkAction,
+    pub selector: JobSelector,
+    /// For `Trigger`: settings changed for these runs only
+    pub overrides: RunOverrides,
+}
+
+impl Handler<Bulk> for RunnerActor {
+    type Return = Result<BulkResult, String>;
+
+    async fn handle(&mut self, msg: Bulk, _ctx: &mut Context<Self>) -> Self::Return {
+        let ids = msg.selector.resolve(&self.job_labels)?;
+        let targets = ids
+            .into_iter()
+            .filter_map(|id| self.job_actors.get(&id).cloned().map(|addr| (id, addr)))
+            .collect();
+        let result = bulk::apply(msg.action, &msg.overrides, targets).await;
+        info!(
+            target: "rollcron::runner",
+            action = ?msg.action,
+            matched = result.matched,
+            failed = result.failed.len(),
+            "Bulk action applied"
+        );
+        if !result.succeeded.is_empty() {
+            let mut entry = format!("{}: {}", msg.action.as_str(), result.succeeded.join(", "));
+            if !msg.overrides.is_empty() {
+                entry.push_str(&format!(" (overriding {})", msg.overrides.names().join(", ")));
+            }
+            self.record_action(entry);
+        }
+        Ok(result)
+    }
+}
+
+/// State of the git poll loop
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncStatus {
+    /// Commit the jobs were last configured from
+    pub commit: Option<String>,
+    pub last_attempt: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    /// Error of the last attempt (None if it succeeded)
+    pub error: Option<String>,
+}
+
+/// Sent by the git poll loop after every fetch
+pub struct SyncAttempted {
+    pub error: Option<String>,
+}
+
+impl Handler<SyncAttempted> for RunnerActor {
+    type Return = ();
+
+    async fn handle(&mut self, msg: SyncAttempted, _ctx: &mut Context<Self>) {
+        let now = clock::now();
+        self.sync_status.last_attempt = Some(now);
+        if msg.error.is_none() {
+            self.sync_status.last_success = Some(now);
+            self.sync_status.commit = git::head_commit(&self.sot_path);
+        }
+        self.sync_status.error = msg.error;
+    }
+}
+
+/// Get the sync status, repo path and pull interval (for the status API)
+pub struct GetSyncStatus;
+
+impl Handler<GetSyncStatus> for RunnerActor {
+    type Return = (SyncStatus, PathBuf, Duration);
+
+    async fn handle(&mut self, _msg: GetSyncStatus, _ctx: &mut Context<Self>) -> Self::Return {
+        (self.sync_status.clone(), self.sot_path.clone(), self.pull_interval)
+    }
+}
+
 /// Get all job IDs for cleanup
 pub struct GetJobIds;
 
@@ -237,7 +510,8 @@ impl Handler<GetJobIds> for RunnerActor {
     type Return = Vec<String>;
 
     async fn handle(&mut self, _msg: GetJobIds, _ctx: &mut Context<Self>) -> Self::Return {
-        self.job_actors.keys().cloned().collect()
+        let canary = self.runner_config.canary.as_ref().map(|c| c.job.id.clone());
+        self.job_actors.keys().cloned().chain(canary).collect()
     }
 }
 
@@ -262,27 +536,137 @@ impl Handler<GracefulShutdown> for RunnerActor {
 
 /// Job execution completed successfully
 pub struct JobCompleted {
-    pub job_id: String,
+    pub report: JobRunReport,
 }
 
 impl Handler<JobCompleted> for RunnerActor {
     type Return = ();
 
     async fn handle(&mut self, msg: JobCompleted, _ctx: &mut Context<Self>) {
-        info!(target: "rollcron::runner", job_id = %msg.job_id, "Job completed");
+        let report = msg.report;
+        info!(
+            target: "rollcron::runner",
+            job_id = %report.job_id,
+            run_id = %report.run_id,
+            outcome = report.outcome.as_str(),
+            started_at = %report.started_at,
+            attempts = report.attempts,
+            fallback = report.fallback,
+            duration = ?report.duration,
+            "Job completed"
+        );
+        self.metrics.record(&report, &self.runner_config.metrics);
+        self.check_brake(&report);
     }
 }
 
 /// Job execution failed after all retries
 pub struct JobFailed {
-    pub job_id: String,
+    pub report: JobRunReport,
 }
 
 impl Handler<JobFailed> for RunnerActor {
     type Return = ();
 
     async fn handle(&mut self, msg: JobFailed, _ctx: &mut Context<Self>) {
-        warn!(target: "rollcron::runner", job_id = %msg.job_id, "Job failed");
+        let report = msg.report;
+        warn!(
+            target: "rollcron::runner",
+            job_id = %report.job_id,
+            run_id = %report.run_id,
+            outcome = report.outcome.as_str(),
+            exit_code = ?report.exit_code,
+            attempts = report.attempts,
+            fallback = report.fallback,
+            "Job failed"
+        );
+        self.metrics.record(&report, &self.runner_config.metrics);
+        self.check_brake(&report);
+    }
+}
+
+/// Sent once the jobs paused by a tripped `runner.brake` are paused
+struct BrakeApplied {
+    paused: Vec<String>,
+}
+
+impl Handler<BrakeApplied> for RunnerActor {
+    type Return = ();
+
+    async fn handle(&mut self, msg: BrakeApplied, _ctx: &mut Context<Self>) {
+        if self.brake.engaged().is_none() {
+            // Released before the pauses went through
+            resume(&self.job_actors, &msg.paused);
+            return;
+        };
+        for job_id in &msg.paused {
+            self.brake.paused(job_id);
+        }
+        let Some(engaged @ Engaged { failed, runs, .. }) = self.brake.engaged().cloned() else {
+            return;
+        };
+        let window = self.runner_config.brake.as_ref().map(|b| b.window).unwrap_or_default();
+        error!(
+            target: "rollcron::runner",
+            failed,
+            runs,
+            window = ?window,
+            paused = %engaged.paused.join(","),
+            "Safety brake engaged: too many runs failed, non-critical jobs paused until `rollcron ctl brake --release`"
+        );
+        events::emit(Event::BrakeEngaged { failed, runs, paused: &engaged.paused });
+
+        let sot_path = self.sot_path.clone();
+        let runner = self.runner_config.clone();
+        tokio::spawn(async move {
+            let runner_env = env::load_runner_env(&sot_path, &runner).await;
+            for wh in runner.webhook.iter().filter(|wh| webhook::accepts(wh, NotifyEvent::BrakeEngaged, None, &runner)) {
+                let url = wh.to_url(runner_env.as_ref());
+                if url.contains('$') || (!url.starts_with("http://") && !url.starts_with("https://")) {
+                    warn!(target: "rollcron::webhook", url = %url, "Invalid webhook URL, skipping");
+                    continue;
+                }
+                webhook::send_brake_engaged(&url, wh.template.as_deref(), failed, runs, window, &engaged.paused).await;
+            }
+        });
+    }
+}
+
+/// Resumes `job_ids` (fire-and-forget).
+fn resume(actors: &HashMap<String, Address<JobActor>>, job_ids: &[String]) {
+    for addr in job_ids.iter().filter_map(|id| actors.get(id)) {
+        let addr = addr.clone();
+        tokio::spawn(async move {
+            let _ = addr.send(Resume).await;
+        });
+    }
+}
+
+/// State of `runner.brake`: None if disabled, else the engaged state if tripped
+pub struct GetBrake;
+
+impl Handler<GetBrake> for RunnerActor {
+    type Return = Option<Option<Engaged>>;
+
+    async fn handle(&mut self, _msg: GetBrake, _ctx: &mut Context<Self>) -> Self::Return {
+        self.runner_config.brake.as_ref().map(|_| self.brake.engaged().cloned())
+    }
+}
+
+/// Release an engaged `runner.brake`, resuming the jobs it paused.
+/// Returns None if it wasn't engaged.
+pub struct ReleaseBrake;
+
+impl Handler<ReleaseBrake> for RunnerActor {
+    type Return = Option<Engaged>;
+
+    async fn handle(&mut self, _msg: ReleaseBrake, _ctx: &mut Context<Self>) -> Self::Return {
+        let engaged = self.brake.release()?;
+        resume(&self.job_actors, &engaged.paused);
+        info!(target: "rollcron::runner", resumed = %engaged.paused.join(","), "Safety brake released");
+        events::emit(Event::BrakeReleased { resumed: &engaged.paused });
+        self.record_action(format!("brake release: {}", engaged.paused.join(", ")));
+        Some(engaged)
     }
 }
 
@@ -0,0 +1,218 @@
+//! Minimal HTTP/1.1 request parsing and response writing.
+//! One request per connection (`Connection: close`), JSON bodies (plus the embedded dashboard page
+//! and chunked plain-text streams).
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header names lowercased
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Path split into non-empty segments, query string dropped.
+    pub fn segments(&self) -> Vec<&str> {
+        let path = self.path.split('?').next().unwrap_or("");
+        path.split('/').filter(|s| !s.is_empty()).collect()
+    }
+
+    /// Value of a query parameter (not percent-decoded).
+    pub fn query(&self, name: &str) -> Option<&str> {
+        let (_, query) = self.path.split_once('?')?;
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub body: Body,
+}
+
+pub enum Body {
+    Json(Value),
+    Html(&'static str),
+    /// Plain text sent with chunked encoding as it arrives, until the sender is dropped
+    Stream(mpsc::Receiver<Vec<u8>>),
+}
+
+impl Response {
+    pub fn ok(body: Value) -> Self {
+        Self { status: 200, body: Body::Json(body) }
+    }
+
+    pub fn html(page: &'static str) -> Self {
+        Self { status: 200, body: Body::Html(page) }
+    }
+
+    pub fn stream(chunks: mpsc::Receiver<Vec<u8>>) -> Self {
+        Self { status: 200, body: Body::Stream(chunks) }
+    }
+
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: Body::Json(serde_json::json!({ "error": message.into() })),
+        }
+    }
+}
+
+pub async fn read_request<R: AsyncRead + Unpin>(stream: R) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().context("Empty request")?.to_string();
+    let path = parts.next().context("Missing request path")?.to_string();
+
+    let mut content_length = 0;
+    let mut headers = Vec::new();
+    let mut header_bytes = request_line.len();
+    loop {
+        let mut line = String::new();
+        let n = reader.read_line(&mut line).await?;
+        header_bytes += n;
+        if header_bytes > MAX_HEADER_BYTES {
+            anyhow::bail!("Request headers too large");
+        }
+        let line = line.trim_end();
+        if n == 0 || line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let (name, value) = (name.trim().to_ascii_lowercase(), value.trim().to_string());
+            if name == "content-length" {
+                content_length = value.parse().context("Invalid Content-Length")?;
+            }
+            headers.push((name, value));
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        anyhow::bail!("Request body too large");
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(Request { method, path, headers, body })
+}
+
+pub async fn write_response<W: AsyncWrite + Unpin>(mut stream: W, response: Response) -> Result<()> {
+    let (content_type, body) = match response.body {
+        Body::Json(value) => ("application/json", serde_json::to_vec(&value)?),
+        Body::Html(page) => ("text/html; charset=utf-8", page.as_bytes().to_vec()),
+        Body::Stream(chunks) => return write_stream(stream, response.status, chunks).await,
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn write_stream<W: AsyncWrite + Unpin>(
+    mut stream: W,
+    status: u16,
+    mut chunks: mpsc::Receiver<Vec<u8>>,
+) -> Result<()> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain; charset=utf-8\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        status,
+        reason(status)
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.flush().await?;
+    while let Some(chunk) = chunks.recv().await {
+        if chunk.is_empty() {
+            continue;
+        }
+        stream.write_all(format!("{:x}\r\n", chunk.len()).as_bytes()).await?;
+        stream.write_all(&chunk).await?;
+        stream.write_all(b"\r\n").await?;
+        stream.flush().await?;
+    }
+    stream.write_all(b"0\r\n\r\n").await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn parse_request_with_body() {
+        let raw = b"POST /jobs/a/resync?x=1&y HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\n\r\n{}";
+        let req = read_request(&raw[..]).await.unwrap();
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.segments(), vec!["jobs", "a", "resync"]);
+        assert_eq!(req.query("x"), Some("1"));
+        assert_eq!(req.query("y"), Some(""));
+        assert_eq!(req.query("z"), None);
+        assert_eq!(req.header("Host"), Some("x"));
+        assert_eq!(req.body, b"{}");
+    }
+
+    #[tokio::test]
+    async fn write_json_response() {
+        let mut out = Vec::new();
+        write_response(&mut out, Response::error(404, "nope")).await.unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(text.ends_with(r#"{"error":"nope"}"#));
+    }
+
+    #[tokio::test]
+    async fn write_chunked_stream() {
+        let (tx, rx) = mpsc::channel(4);
+        tx.send(b"hello ".to_vec()).await.unwrap();
+        tx.send(Vec::new()).await.unwrap();
+        tx.send(b"world\n".to_vec()).await.unwrap();
+        drop(tx);
+        let mut out = Vec::new();
+        write_response(&mut out, Response::stream(rx)).await.unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(text.ends_with("\r\n\r\n6\r\nhello \r\n6\r\nworld\n\r\n0\r\n\r\n"));
+    }
+}
@@ -0,0 +1,547 @@
+//! Local HTTP API served by the daemon (used by `rollcron ctl`).
+//!
+//! - `GET /`: web dashboard (static page; its API calls carry the token)
+//! - `GET /jobs`, `GET /jobs/<id>`, `GET /jobs/<id>/runs?limit=N`, `GET /sync`
+//! - `GET /jobs/<id>/output?since=N`: live output of the latest run
+//! - `GET /jobs/<id>/output/stream?since=N`: the same as plain text, streamed until the run ends
+//! - `POST /jobs/<id>/resync|approve|pause|resume|trigger` (trigger: optional `{env, timeout}`)
+//! - `POST /jobs/bulk` with `{action, all | jobs | label}` (and `env`, `timeout` for `trigger`)
+//! - `GET /debug/log`, `POST /debug/log` with `{filter}`: view or replace the log filter
+//! - `GET /brake`, `POST /brake/release`: state of `runner.brake`, resume after it tripped
+//!
+//! With `--api-token`, every request except `GET /` needs `Authorization: Bearer <token>`.
+//! Without it the API is read-only. Requests must address the API by IP or `localhost`
+//! (no DNS rebinding), come from its own origin, and send POST bodies as JSON, so a web
+//! page can't drive it from the operator's browser. `--no-api` turns it off.
+
+mod http;
+mod status;
+
+use crate::actor::job::live::{self, Follow};
+use crate::actor::job::{GetStatus, RunOverrides};
+use crate::actor::runner::bulk::{BulkAction, JobSelector};
+use crate::actor::runner::{
+    ApproveJob, Bulk, GetBrake, GetJobActors, GetSyncStatus, ReleaseBrake, ResyncJob, RunnerActor,
+};
+use crate::config;
+use crate::describe::Lang;
+use crate::logging;
+use http::{Request, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use xtra::prelude::*;
+
+/// Embedded web dashboard
+const DASHBOARD: &str = include_str!("dashboard.html");
+
+/// Default listen address for the API (and `rollcron ctl` target)
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7171";
+
+/// Serves the control API until the runner stops.
+/// A bind failure is logged and leaves the daemon running without the API.
+pub async fn serve(addr: SocketAddr, runner: Address<RunnerActor>, token: Option<String>, origins: Vec<String>) {
+    let listener = match TcpListener::bind(addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            warn!(target: "rollcron::api", addr = %addr, error = %e, "Failed to bind control API");
+            return;
+        }
+    };
+    info!(target: "rollcron::api", addr = %addr, auth = token.is_some(), "Control API listening");
+    if token.is_none() {
+        info!(target: "rollcron::api", "No --api-token: the control API is read-only");
+    }
+    let token: Option<std::sync::Arc<str>> = token.map(Into::into);
+    let origins: std::sync::Arc<[String]> = origins.into();
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(target: "rollcron::api", error = %e, "Failed to accept connection");
+                continue;
+            }
+        };
+        if !runner.is_connected() {
+            break;
+        }
+        let runner = runner.clone();
+        let token = token.clone();
+        let origins = origins.clone();
+        tokio::spawn(async move {
+            let (read, write) = stream.into_split();
+            let response = match http::read_request(read).await {
+                Ok(req) if !same_origin(&req, addr.port(), &origins) => {
+                    warn!(target: "rollcron::api", peer = %peer, path = %req.path, "Rejected request from a foreign host or origin");
+                    Response::error(403, "Foreign Host or Origin")
+                }
+                Ok(req) if req.method == "GET" && req.segments().is_empty() => Response::html(DASHBOARD),
+                Ok(req) if !authorized(&req, token.as_deref()) => {
+                    warn!(target: "rollcron::api", peer = %peer, path = %req.path, "Rejected unauthorized request");
+                    Response::error(401, "Missing or invalid bearer token")
+                }
+                Ok(req) if req.method == "POST" && token.is_none() => {
+                    Response::error(403, "Read-only API: start the daemon with --api-token to allow changes")
+                }
+                Ok(req) if req.method == "POST" && !json_body(&req) => {
+                    Response::error(415, "POST requests must be sent as `Content-Type: application/json`")
+                }
+                Ok(req) => route(&req, &runner).await,
+                Err(e) => Response::error(400, e.to_string()),
+            };
+            if let Err(e) = http::write_response(write, response).await {
+                warn!(target: "rollcron::api", peer = %peer, error = %e, "Failed to write response");
+            }
+        });
+    }
+}
+
+/// Body of `POST /jobs/bulk`; exactly one of `all`, `jobs`, `label` selects the jobs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BulkRequest {
+    pub action: Option<BulkAction>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub all: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub jobs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(flatten)]
+    pub overrides: TriggerOverrides,
+}
+
+/// Settings a `trigger` changes for its runs only: `env` is added above
+/// `run.env`, `timeout` (`300`, `10m`, ...) replaces `run.timeout`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TriggerOverrides {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+}
+
+impl TriggerOverrides {
+    fn parse(&self) -> Result<RunOverrides, String> {
+        if let Some(key) = self.env.keys().find(|key| !valid_env_name(key)) {
+            return Err(format!("Invalid environment variable name '{}'", key));
+        }
+        let timeout = match &self.timeout {
+            None => None,
+            Some(t) => match config::parse_duration(t) {
+                Ok(timeout) if !timeout.is_zero() => Some(timeout),
+                Ok(_) => return Err("Invalid timeout '0': must be positive".to_string()),
+                Err(e) => return Err(format!("Invalid timeout '{}': {}", t, e)),
+            },
+        };
+        Ok(RunOverrides { env: self.env.clone(), timeout })
+    }
+}
+
+fn valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl BulkRequest {
+    fn selector(&self) -> Result<JobSelector, String> {
+        match (self.all, self.jobs.is_empty(), &self.label) {
+            (true, true, None) => Ok(JobSelector::All),
+            (false, false, None) => Ok(JobSelector::Ids(self.jobs.clone())),
+            (false, true, Some(label)) => JobSelector::label(label),
+            _ => Err("Specify exactly one of `all`, `jobs`, `label`".to_string()),
+        }
+    }
+}
+
+/// Body of `POST /debug/log`; a missing `filter` restores the startup filter.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LogFilterRequest {
+    pub filter: Option<String>,
+}
+
+/// True if no token is configured or the request carries it.
+fn authorized(req: &Request, token: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return true;
+    };
+    let Some(given) = req.header("authorization").and_then(|v| v.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    // Compare without short-circuiting on the first differing byte
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// True unless the request names a host other than an IP address or
+/// `localhost` on the API's port or the host of an `origins` entry
+/// (`--api-allowed-origin`), or comes from a page of another origin.
+fn same_origin(req: &Request, port: u16, origins: &[String]) -> bool {
+    // Only browsers send these, and they always send Host
+    let Some(host) = req.header("host") else {
+        return true;
+    };
+    let allowed_host = origins.iter().any(|origin| origin_host(origin).eq_ignore_ascii_case(host));
+    if !allowed_host {
+        let (name, host_port) = match host.rsplit_once(':') {
+            Some((name, p)) if !p.contains(']') => (name, p.parse::<u16>().ok()),
+            _ => (host, None),
+        };
+        let name = name.trim_start_matches('[').trim_end_matches(']');
+        let local = name.eq_ignore_ascii_case("localhost") || name.parse::<std::net::IpAddr>().is_ok();
+        if !local || host_port.unwrap_or(80) != port {
+            return false;
+        }
+    }
+    req.header("origin").is_none_or(|origin| {
+        origins.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin))
+            || origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://")) == Some(host)
+    })
+}
+
+/// `host[:port]` of an origin checked by `parse_origin`
+fn origin_host(origin: &str) -> &str {
+    origin.split_once("://").map_or(origin, |(_, host)| host)
+}
+
+/// Parses an `--api-allowed-origin` value: `http(s)://host[:port]`, without a path.
+pub fn parse_origin(s: &str) -> Result<String, String> {
+    let origin = s.trim_end_matches('/');
+    let host = origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://"));
+    match host {
+        Some(host) if !host.is_empty() && !host.contains(['/', '?', '#', '@']) => Ok(origin.to_ascii_lowercase()),
+        _ => Err(format!("expected an origin such as https://cron.example.com, got '{}'", s)),
+    }
+}
+
+/// Browsers can only send JSON to another origin after a preflight this API never answers.
+fn json_body(req: &Request) -> bool {
+    req.header("content-type")
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("application/json"))
+}
+
+async fn route(req: &Request, runner: &Address<RunnerActor>) -> Response {
+    match (req.method.as_str(), req.segments().as_slice()) {
+        ("GET", ["jobs"]) => list_jobs(runner, lang(req)).await,
+        ("GET", ["jobs", job_id]) => job(runner, job_id, lang(req)).await,
+        ("GET", ["jobs", job_id, "runs"]) => {
+            let limit = match req.query("limit").map(str::parse::<usize>) {
+                None => status::DEFAULT_RUNS_LIMIT,
+                Some(Ok(n)) => n.min(status::MAX_RUNS_LIMIT),
+                Some(Err(_)) => return Response::error(400, "Invalid `limit`"),
+            };
+            job_runs(runner, job_id, limit).await
+        }
+        ("GET", ["jobs", job_id, "output"]) => {
+            let since = match req.query("since").map(str::parse::<u64>) {
+                None => 0,
+                Some(Ok(n)) => n,
+                Some(Err(_)) => return Response::error(400, "Invalid `since`"),
+            };
+            job_output(runner, job_id, since).await
+        }
+        ("GET", ["jobs", job_id, "output", "stream"]) => {
+            let since = match req.query("since").map(str::parse::<u64>) {
+                None => 0,
+                Some(Ok(n)) => n,
+                Some(Err(_)) => return Response::error(400, "Invalid `since`"),
+            };
+            job_output_stream(runner, job_id, since).await
+        }
+        ("GET", ["sync"]) => match runner.send(GetSyncStatus).await {
+            Ok((sync, _, pull_interval)) => Response::ok(status::sync(&sync, pull_interval)),
+            Err(_) => Response::error(503, "Runner stopped"),
+        },
+        ("GET", ["brake"]) => match runner.send(GetBrake).await {
+            Ok(state) => Response::ok(status::brake(state.as_ref())),
+            Err(_) => Response::error(503, "Runner stopped"),
+        },
+        ("POST", ["brake", "release"]) => match runner.send(ReleaseBrake).await {
+            Ok(Some(engaged)) => Response::ok(serde_json::json!({ "status": "released", "resumed": engaged.paused })),
+            Ok(None) => Response::ok(serde_json::json!({ "status": "not engaged", "resumed": [] })),
+            Err(_) => Response::error(503, "Runner stopped"),
+        },
+        ("GET", ["debug", "log"]) => Response::ok(serde_json::json!({ "filter": logging::current_filter() })),
+        ("POST", ["debug", "log"]) => match serde_json::from_slice::<LogFilterRequest>(&req.body) {
+            Ok(body) => set_log_filter(body.filter.as_deref()),
+            Err(e) => Response::error(400, format!("Invalid body: {}", e)),
+        },
+        ("POST", ["jobs", "bulk"]) => match serde_json::from_slice::<BulkRequest>(&req.body) {
+            Ok(body) => match (body.action, body.selector(), body.overrides.parse()) {
+                (Some(action), _, Ok(overrides)) if action != BulkAction::Trigger && !overrides.is_empty() => {
+                    Response::error(400, format!("`env` and `timeout` only apply to trigger, not {}", action.as_str()))
+                }
+                (Some(action), Ok(selector), Ok(overrides)) => bulk(runner, action, selector, overrides).await,
+                (None, _, _) => Response::error(400, "Missing `action`"),
+                (_, Err(e), _) | (_, _, Err(e)) => Response::error(400, e),
+            },
+            Err(e) => Response::error(400, format!("Invalid body: {}", e)),
+        },
+        ("POST", ["jobs", job_id, "resync"]) => resync(runner, job_id).await,
+        ("POST", ["jobs", job_id, "approve"]) => approve(runner, job_id).await,
+        ("POST", ["jobs", job_id, "trigger"]) => {
+            let body = if req.body.is_empty() {
+                Ok(TriggerOverrides::default())
+            } else {
+                serde_json::from_slice::<TriggerOverrides>(&req.body).map_err(|e| format!("Invalid body: {}", e))
+            };
+            match body.and_then(|body| body.parse()) {
+                Ok(overrides) => bulk(runner, BulkAction::Trigger, JobSelector::Ids(vec![job_id.to_string()]), overrides).await,
+                Err(e) => Response::error(400, e),
+            }
+        }
+        ("POST", ["jobs", job_id, action @ ("pause" | "resume")]) => {
+            let action = if *action == "pause" { BulkAction::Pause } else { BulkAction::Resume };
+            bulk(runner, action, JobSelector::Ids(vec![job_id.to_string()]), RunOverrides::default()).await
+        }
+        (_, ["jobs", "bulk"] | ["jobs", _, "resync" | "approve" | "pause" | "resume" | "trigger"]) => {
+            Response::error(405, "Use POST")
+        }
+        (_, ["brake", "release"]) => Response::error(405, "Use POST"),
+        (_, ["jobs"] | ["jobs", _] | ["jobs", _, "runs" | "output"] | ["jobs", _, "output", "stream"] | ["sync"] | ["brake"]) => Response::error(405, "Use GET"),
+        (_, ["debug", "log"]) => Response::error(405, "Use GET or POST"),
+        _ => Response::error(404, format!("No route for {} {}", req.method, req.path)),
+    }
+}
+
+fn set_log_filter(spec: Option<&str>) -> Response {
+    let previous = logging::current_filter();
+    match logging::set_filter(spec) {
+        Ok(filter) => {
+            info!(target: "rollcron::api", filter = %filter, previous = ?previous, "Log filter changed");
+            Response::ok(serde_json::json!({ "filter": filter, "previous": previous }))
+        }
+        Err(e) => Response::error(400, format!("{:#}", e)),
+    }
+}
+
+/// `?lang=` (a language tag like `sv-SE`) for schedule descriptions, default English
+fn lang(req: &Request) -> Lang {
+    req.query("lang").map_or(Lang::En, Lang::from_locale)
+}
+
+async fn list_jobs(runner: &Address<RunnerActor>, lang: Lang) -> Response {
+    let (Ok(actors), Ok((_, sot_path, _))) = (runner.send(GetJobActors).await, runner.send(GetSyncStatus).await)
+    else {
+        return Response::error(503, "Runner stopped");
+    };
+    let mut ids: Vec<&String> = actors.keys().collect();
+    ids.sort();
+    let mut jobs = Vec::with_capacity(ids.len());
+    for id in ids {
+        // A job removed by a concurrent config update is simply left out
+        if let Ok(job_status) = actors[id].send(GetStatus).await {
+            jobs.push(status::job_summary(&job_status, &sot_path, lang));
+        }
+    }
+    Response::ok(serde_json::json!({ "jobs": jobs }))
+}
+
+async fn job(runner: &Address<RunnerActor>, job_id: &str, lang: Lang) -> Response {
+    match job_status(runner, job_id).await {
+        Ok((job_status, sot_path)) => Response::ok(status::job_detail(&job_status, &sot_path, lang)),
+        Err(response) => response,
+    }
+}
+
+async fn job_runs(runner: &Address<RunnerActor>, job_id: &str, limit: usize) -> Response {
+    match job_status(runner, job_id).await {
+        Ok((_, sot_path)) => Response::ok(status::job_runs(job_id, &sot_path, limit)),
+        Err(response) => response,
+    }
+}
+
+async fn job_output(runner: &Address<RunnerActor>, job_id: &str, since: u64) -> Response {
+    if let Err(response) = job_status(runner, job_id).await {
+        return response;
+    }
+    let chunk = live::read(job_id, since);
+    Response::ok(serde_json::json!({
+        "job_id": job_id,
+        "started_at": chunk.started_at,
+        "running": chunk.running,
+        "offset": chunk.offset,
+        "end": chunk.end,
+        "output": String::from_utf8_lossy(&chunk.data),
+    }))
+}
+
+/// Streams the output retained after `since`, then the rest of the run in
+/// progress as it is written. Ends with the run (at once if none is running).
+async fn job_output_stream(runner: &Address<RunnerActor>, job_id: &str, since: u64) -> Response {
+    if let Err(response) = job_status(runner, job_id).await {
+        return response;
+    }
+    let (chunk, receiver) = live::follow(job_id, since);
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        if tx.send(chunk.data).await.is_err() {
+            return;
+        }
+        let Some(mut receiver) = receiver else {
+            return;
+        };
+        loop {
+            let data = match receiver.recv().await {
+                Ok(Follow::Output(data)) => data,
+                Ok(Follow::Finished) | Err(RecvError::Closed) => return,
+                Err(RecvError::Lagged(missed)) => format!("\n[rollcron: {} chunks of output skipped]\n", missed).into_bytes(),
+            };
+            // The client went away
+            if tx.send(data).await.is_err() {
+                return;
+            }
+        }
+    });
+    Response::stream(rx)
+}
+
+async fn job_status(
+    runner: &Address<RunnerActor>,
+    job_id: &str,
+) -> Result<(crate::actor::job::JobStatus, std::path::PathBuf), Response> {
+    let (Ok(actors), Ok((_, sot_path, _))) = (runner.send(GetJobActors).await, runner.send(GetSyncStatus).await)
+    else {
+        return Err(Response::error(503, "Runner stopped"));
+    };
+    let unknown = || Response::error(404, format!("Unknown job '{}'", job_id));
+    let addr = actors.get(job_id).ok_or_else(unknown)?;
+    let job_status = addr.send(GetStatus).await.map_err(|_| unknown())?;
+    Ok((job_status, sot_path))
+}
+
+async fn bulk(
+    runner: &Address<RunnerActor>,
+    action: BulkAction,
+    selector: JobSelector,
+    overrides: RunOverrides,
+) -> Response {
+    match runner.send(Bulk { action, selector, overrides }).await {
+        Ok(Ok(result)) => match serde_json::to_value(&result) {
+            Ok(body) => Response::ok(body),
+            Err(e) => Response::error(500, e.to_string()),
+        },
+        Ok(Err(e)) => Response::error(400, e),
+        Err(_) => Response::error(503, "Runner stopped"),
+    }
+}
+
+async fn approve(runner: &Address<RunnerActor>, job_id: &str) -> Response {
+    match runner.send(ApproveJob { job_id: job_id.to_string() }).await {
+        Ok(Some(Ok(true))) => {
+            info!(target: "rollcron::api", job_id = %job_id, "Job approved");
+            Response::ok(serde_json::json!({ "job_id": job_id, "status": "approved" }))
+        }
+        Ok(Some(Ok(false))) => Response::ok(serde_json::json!({ "job_id": job_id, "status": "not blocked" })),
+        Ok(Some(Err(e))) => Response::error(500, format!("{:#}", e)),
+        Ok(None) => Response::error(404, format!("Unknown job '{}'", job_id)),
+        Err(_) => Response::error(503, "Runner stopped"),
+    }
+}
+
+async fn resync(runner: &Address<RunnerActor>, job_id: &str) -> Response {
+    info!(target: "rollcron::api", job_id = %job_id, "Resync requested");
+    match runner.send(ResyncJob { job_id: job_id.to_string() }).await {
+        Ok(Some(Ok(()))) => Response::ok(serde_json::json!({ "job_id": job_id, "status": "resyncing" })),
+        Ok(Some(Err(e))) => Response::error(409, e.to_string()),
+        Ok(None) => Response::error(404, format!("Unknown job '{}'", job_id)),
+        Err(_) => Response::error(503, "Runner stopped"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(authorization: Option<&str>) -> Request {
+        with_headers(&authorization.map(|v| ("authorization", v)).into_iter().collect::<Vec<_>>())
+    }
+
+    fn with_headers(headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: "POST".to_string(),
+            path: "/jobs/bulk".to_string(),
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn rejects_foreign_hosts_and_origins() {
+        let allowed = |headers: &[(&str, &str)]| same_origin(&with_headers(headers), 7171, &[]);
+        assert!(allowed(&[]));
+        assert!(allowed(&[("host", "127.0.0.1:7171")]));
+        assert!(allowed(&[("host", "localhost:7171"), ("origin", "http://localhost:7171")]));
+        assert!(allowed(&[("host", "[::1]:7171")]));
+        assert!(allowed(&[("host", "10.0.0.5:7171")]));
+        // DNS rebinding: a domain resolving to 127.0.0.1
+        assert!(!allowed(&[("host", "evil.example:7171")]));
+        assert!(!allowed(&[("host", "127.0.0.1:8080")]));
+        assert!(!allowed(&[("host", "127.0.0.1")]));
+        assert!(!allowed(&[("host", "127.0.0.1:7171"), ("origin", "https://evil.example")]));
+        assert!(!allowed(&[("host", "127.0.0.1:7171"), ("origin", "null")]));
+    }
+
+    #[test]
+    fn allowed_origins_admit_their_hosts() {
+        let origins = [parse_origin("https://Cron.example.com/").unwrap()];
+        let allowed = |headers: &[(&str, &str)]| same_origin(&with_headers(headers), 7171, &origins);
+        // Behind a reverse proxy passing the public Host through
+        assert!(allowed(&[("host", "cron.example.com"), ("origin", "https://cron.example.com")]));
+        // ... or rewriting it to the API's address
+        assert!(allowed(&[("host", "127.0.0.1:7171"), ("origin", "https://cron.example.com")]));
+        assert!(!allowed(&[("host", "evil.example"), ("origin", "https://evil.example")]));
+        assert!(!allowed(&[("host", "cron.example.com"), ("origin", "https://evil.example")]));
+        assert!(!allowed(&[("host", "cron.example.com:8443")]));
+
+        assert!(parse_origin("http://10.0.0.5:7171").is_ok());
+        assert!(parse_origin("cron.example.com").is_err());
+        assert!(parse_origin("https://cron.example.com/api").is_err());
+    }
+
+    #[test]
+    fn post_bodies_must_be_json() {
+        assert!(json_body(&with_headers(&[("content-type", "application/json")])));
+        assert!(json_body(&with_headers(&[("content-type", "application/json; charset=utf-8")])));
+        assert!(!json_body(&with_headers(&[("content-type", "text/plain")])));
+        assert!(!json_body(&with_headers(&[("content-type", "application/x-www-form-urlencoded")])));
+        assert!(!json_body(&with_headers(&[])));
+    }
+
+    #[test]
+    fn bearer_token_auth() {
+        assert!(authorized(&request(None), None));
+        assert!(authorized(&request(Some("Bearer s3cret")), Some("s3cret")));
+        assert!(!authorized(&request(None), Some("s3cret")));
+        assert!(!authorized(&request(Some("Bearer s3cre")), Some("s3cret")));
+        assert!(!authorized(&request(Some("s3cret")), Some("s3cret")));
+    }
+
+    #[test]
+    fn trigger_overrides() {
+        let body: BulkRequest =
+            serde_json::from_str(r#"{"action":"trigger","jobs":["etl"],"env":{"DATE":"2026-10-15"},"timeout":"300"}"#)
+                .unwrap();
+        let overrides = body.overrides.parse().unwrap();
+        assert_eq!(overrides.env["DATE"], "2026-10-15");
+        assert_eq!(overrides.timeout, Some(std::time::Duration::from_secs(300)));
+        assert_eq!(overrides.names(), ["env.DATE", "run.timeout"]);
+
+        // Only variables the job declares can be overridden
+        let yaml = "jobs:\n  etl:\n    schedule: \"* * * * *\"\n    run: { sh: ./etl, env: { DATE: today } }\n";
+        let job = config::parse_config(yaml).unwrap().1.remove(0);
+        assert_eq!(overrides.undeclared_env(&job), None);
+        let injected = RunOverrides { env: [("LD_PRELOAD".to_string(), "/tmp/x.so".to_string())].into(), timeout: None };
+        assert_eq!(injected.undeclared_env(&job), Some("LD_PRELOAD"));
+
+        let invalid = |json: &str| serde_json::from_str::<TriggerOverrides>(json).unwrap().parse().unwrap_err();
+        assert!(invalid(r#"{"env":{"BAD-NAME":"x"}}"#).contains("BAD-NAME"));
+        assert!(invalid(r#"{"timeout":"0"}"#).contains("positive"));
+        assert!(invalid(r#"{"timeout":"soon"}"#).contains("soon"));
+    }
+}
@@ -0,0 +1,227 @@
+//! Read-only JSON views of jobs, runs and git sync state.
+//!
+//! Only scheduling-relevant config is exposed: env values, env files and
+//! webhook URLs may carry secrets and are left out.
+
+use crate::actor::job::{degrading, JobRunReport, JobStatus};
+use crate::actor::runner::brake::Engaged;
+use crate::actor::runner::SyncStatus;
+use crate::config::TimezoneConfig;
+use crate::describe::{describe, Lang};
+use crate::{git, history};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+
+/// Runs included in `GET /jobs/<id>`
+const RECENT_RUNS: usize = 10;
+
+/// Default and max `limit` of `GET /jobs/<id>/runs`
+pub const DEFAULT_RUNS_LIMIT: usize = 20;
+pub const MAX_RUNS_LIMIT: usize = 1000;
+
+/// Entry of `GET /jobs`: identity, state and the last run.
+pub fn job_summary(status: &JobStatus, sot_path: &Path, lang: Lang) -> Value {
+    let runs = read_runs(sot_path, &status.job.id);
+    let mut view = summary_fields(status, lang);
+    view["last_run"] = runs.last().map_or(Value::Null, run_summary);
+    view["degrading"] = json!(degrading(&runs));
+    view
+}
+
+/// `GET /jobs/<id>`: summary, effective config and recent runs.
+pub fn job_detail(status: &JobStatus, sot_path: &Path, lang: Lang) -> Value {
+    let job = &status.job;
+    let runs = read_runs(sot_path, &job.id);
+    let mut view = summary_fields(status, lang);
+    view["last_run"] = runs.last().map_or(Value::Null, run_summary);
+    view["degrading"] = json!(degrading(&runs));
+    view["config"] = json!({
+        "command": job.command,
+        "fallback_command": job.fallback_command,
+        "condition": job.condition,
+        "shell": job.shell,
+        "build": job.build.as_ref().map(|b| json!({
+            "command": b.command,
+            "timeout_ms": millis(b.timeout),
+            "working_dir": b.working_dir.as_ref().map(|w| &w.path),
+        })),
+        "timeout_ms": millis(job.timeout),
+        "concurrency": job.concurrency,
+        "retry": job.retry.as_ref().map(|r| json!({
+            "max": r.max,
+            "delay_ms": millis(r.delay),
+            "jitter_ms": r.jitter.map(millis),
+        })),
+        "working_dir": job.working_dir.as_ref().map(|w| &w.path),
+        "timezone": timezone_name(job.timezone.as_ref().unwrap_or(&status.runner.timezone)),
+        "limits": job.limits.map(|l| json!({ "memory": l.memory, "cpu": l.cpu })),
+        "audit": job.audit,
+        "severity": job.severity,
+        "priority": job.priority,
+        "log_file": job.log_file,
+        "blackout": job.blackout.iter().map(|w| json!({ "window": w.window.label, "policy": w.policy })).collect::<Vec<_>>(),
+        "cleanup": job.cleanup,
+        "circuit_breaker": job.circuit_breaker.as_ref().map(|c| json!({
+            "failures": c.failures,
+            "action": c.action,
+            "backoff_ms": millis(c.backoff),
+            "max_backoff_ms": millis(c.max_backoff),
+        })),
+        "success_when": job.success_when.as_ref().map(|s| json!({
+            "exit_codes": s.exit_codes,
+            "stdout_matches": s.stdout_matches.as_ref().map(|r| r.as_str()),
+            "stderr_not_matches": s.stderr_not_matches.as_ref().map(|r| r.as_str()),
+        })),
+        "artifacts": job.artifacts.as_ref().map(|a| json!({
+            "paths": a.paths,
+            "keep": a.keep,
+            "upload": a.upload,
+        })),
+        "guards": job.guards.is_set().then(|| json!({
+            "min_free_disk": job.guards.min_free_disk,
+            "disk_path": job.guards.disk_path,
+            "max_loadavg": job.guards.max_loadavg,
+            "policy": job.guards.policy,
+            "max_delay_ms": millis(job.guards.max_delay),
+        })),
+    });
+    view["recent_runs"] = runs.iter().rev().take(RECENT_RUNS).map(run_summary).collect();
+    view
+}
+
+/// `GET /jobs/<id>/runs`: full reports, newest first.
+pub fn job_runs(job_id: &str, sot_path: &Path, limit: usize) -> Value {
+    let runs = read_runs(sot_path, job_id);
+    let runs: Vec<&JobRunReport> = runs.iter().rev().take(limit).collect();
+    json!({ "job_id": job_id, "runs": runs })
+}
+
+/// `GET /sync`
+pub fn sync(status: &SyncStatus, pull_interval: Duration) -> Value {
+    let mut view = serde_json::to_value(status).unwrap_or_default();
+    view["pull_interval_secs"] = json!(pull_interval.as_secs());
+    view
+}
+
+/// `GET /brake`; `state` is None when `runner.brake` is not configured.
+pub fn brake(state: Option<&Option<Engaged>>) -> Value {
+    match state {
+        None => json!({ "enabled": false, "engaged": false }),
+        Some(None) => json!({ "enabled": true, "engaged": false }),
+        Some(Some(engaged)) => {
+            let mut view = serde_json::to_value(engaged).unwrap_or_default();
+            view["enabled"] = json!(true);
+            view["engaged"] = json!(true);
+            view
+        }
+    }
+}
+
+fn summary_fields(status: &JobStatus, lang: Lang) -> Value {
+    let job = &status.job;
+    json!({
+        "id": job.id,
+        "name": job.name,
+        "source": job.source,
+        "schedule": job.schedule_str(),
+        "schedule_description": describe(job, &status.runner, lang),
+        "enabled": job.enabled,
+        "paused": status.paused,
+        "paused_locally": status.paused_locally,
+        "blocked": status.blocked,
+        "running": status.running,
+        "building": status.building,
+        "next_run": status.next_run,
+        "circuit_open": status.circuit_open,
+        "labels": job.labels,
+    })
+}
+
+fn run_summary(run: &JobRunReport) -> Value {
+    json!({
+        "run_id": run.run_id,
+        "started_at": run.started_at,
+        "duration_ms": millis(run.duration),
+        "outcome": run.outcome,
+        "exit_code": run.exit_code,
+        "failure_class": run.failure_class,
+        "attempts": run.attempts,
+        "commit": run.commit,
+        "trigger": run.trigger,
+        "overrides": run.overrides,
+    })
+}
+
+/// History is best-effort here: an unreadable file shows as no runs.
+fn read_runs(sot_path: &Path, job_id: &str) -> Vec<JobRunReport> {
+    history::read_runs(&git::get_history_dir(sot_path, job_id)).unwrap_or_default()
+}
+
+fn timezone_name(tz: &TimezoneConfig) -> String {
+    match tz {
+        TimezoneConfig::Utc => "UTC".to_string(),
+        TimezoneConfig::Inherit => "inherit".to_string(),
+        TimezoneConfig::Named(tz) => tz.name().to_string(),
+    }
+}
+
+fn millis(d: Duration) -> u64 {
+    d.as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    fn status(yaml: &str) -> JobStatus {
+        let (runner, mut jobs) = parse_config(yaml).unwrap();
+        JobStatus {
+            job: jobs.remove(0),
+            runner,
+            paused: true,
+            paused_locally: None,
+            blocked: None,
+            running: 0,
+            building: false,
+            next_run: None,
+            circuit_open: None,
+        }
+    }
+
+    #[test]
+    fn detail_omits_secrets() {
+        let status = status(
+            r#"
+runner:
+  timezone: Asia/Tokyo
+jobs:
+  backup:
+    schedule: "0 3 * * *"
+    run:
+      sh: ["./backup.sh", "--full"]
+      retry:
+        max: 2
+        delay: 30s
+      env:
+        API_KEY: hunter2
+    webhook:
+      - url: https://hooks.example.com/secret-token
+"#,
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let view = job_detail(&status, &dir.path().join("repo-abc"), Lang::En);
+        assert_eq!(view["id"], "backup");
+        assert_eq!(view["schedule"], "0 3 * * *");
+        assert_eq!(view["schedule_description"], "At 03:00, Asia/Tokyo");
+        assert_eq!(view["paused"], true);
+        assert_eq!(view["last_run"], Value::Null);
+        assert_eq!(view["config"]["command"], json!(["./backup.sh", "--full"]));
+        assert_eq!(view["config"]["timezone"], "Asia/Tokyo");
+        assert_eq!(view["config"]["retry"]["delay_ms"], 30000);
+        assert_eq!(view["config"]["concurrency"], "skip");
+        let text = view.to_string();
+        assert!(!text.contains("hunter2") && !text.contains("secret-token"));
+    }
+}
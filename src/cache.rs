@@ -0,0 +1,219 @@
+//! Cache hygiene. Each instance (daemon or CLI checkout) clones into
+//! `<cache>/<repo>-<suffix>`, with job directories `<repo>-<suffix>@<job>` and
+//! short-lived siblings such as `run.tmp` or `<repo>-<suffix>.reclone`. A
+//! `<repo>-<suffix>.pid` marker names the process using them, so entries
+//! left by instances that didn't shut down cleanly can be told apart and
+//! removed by `rollcron gc`.
+
+use crate::config::format_size;
+use crate::git;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// Temporary directories of a running instance older than this are leftovers
+/// of an interrupted sync
+const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Temporary siblings of a job's run directory
+const RUN_TEMPS: [&str; 2] = ["run.tmp", "run.old"];
+
+/// Marks `sot_path` and its job directories as used by this process.
+pub fn claim(sot_path: &Path) -> Result<()> {
+    let marker = marker_path(sot_path);
+    if let Some(parent) = marker.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(marker, std::process::id().to_string())?;
+    Ok(())
+}
+
+/// Removes the marker written by `claim`.
+pub fn release(sot_path: &Path) {
+    let _ = fs::remove_file(marker_path(sot_path));
+}
+
+fn marker_path(sot_path: &Path) -> PathBuf {
+    let name = sot_path.file_name().and_then(|s| s.to_str()).unwrap_or("unknown");
+    sot_path.with_file_name(format!("{}.pid", name))
+}
+
+/// A cache entry `gc` would remove.
+#[derive(Debug, PartialEq)]
+pub struct Unused {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// What the cache root holds.
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub size: u64,
+    /// Entries of stopped instances and stale temporary directories
+    pub unused: Vec<Unused>,
+}
+
+/// Walks the cache root at `now`.
+pub fn scan(root: &Path, now: SystemTime) -> Scan {
+    let mut scan = Scan::default();
+    let Ok(entries) = fs::read_dir(root) else {
+        return scan;
+    };
+    // Entries grouped by the clone they belong to; the rest (legacy state
+    // dirs, foreign files) is never touched
+    let mut owners: BTreeMap<String, Vec<(String, PathBuf)>> = BTreeMap::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        scan.size += size_of(&path);
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if let Some(owner) = owner(&name) {
+            owners.entry(owner.to_string()).or_default().push((name, path));
+        }
+    }
+
+    for (owner, entries) in owners {
+        let alive = owner_alive(&root.join(format!("{}.pid", owner)));
+        for (name, path) in entries {
+            let mut unused = Vec::new();
+            if !alive {
+                unused.push(path);
+            } else if name.contains('@') {
+                unused.extend(RUN_TEMPS.map(|temp| path.join(temp)).into_iter().filter(|p| stale(p, now)));
+            } else if name != owner && name != format!("{}.pid", owner) && stale(&path, now) {
+                unused.push(path);
+            }
+            scan.unused.extend(unused.into_iter().map(|path| Unused { size: size_of(&path), path }));
+        }
+    }
+    scan
+}
+
+/// Removes what `scan` reports as unused. Returns the removed entries.
+pub fn gc(root: &Path, now: SystemTime) -> Result<Vec<Unused>> {
+    let unused = scan(root, now).unused;
+    for entry in &unused {
+        let removed = if entry.path.is_dir() {
+            fs::remove_dir_all(&entry.path)
+        } else {
+            fs::remove_file(&entry.path)
+        };
+        match removed {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(anyhow::anyhow!("Failed to remove {}: {}", entry.path.display(), e));
+            }
+            _ => {}
+        }
+    }
+    Ok(unused)
+}
+
+/// Logs the cache's size at startup, and what `rollcron gc` could free.
+pub fn report() {
+    let root = git::cache_base();
+    let scan = scan(&root, SystemTime::now());
+    info!(dir = %root.display(), size = %format_size(scan.size), "Cache size");
+    if !scan.unused.is_empty() {
+        let size: u64 = scan.unused.iter().map(|u| u.size).sum();
+        warn!(
+            entries = scan.unused.len(),
+            size = %format_size(size),
+            "Cache holds leftovers of stopped instances or interrupted syncs; `rollcron gc` removes them"
+        );
+    }
+}
+
+/// The clone a top-level cache entry belongs to: the `<repo>-<8 hex>` prefix
+/// of `<repo>-<suffix>`, `<repo>-<suffix>@<job>` or `<repo>-<suffix>.<ext>`.
+fn owner(name: &str) -> Option<&str> {
+    let clone = name.split('@').next()?;
+    clone.match_indices('-').rev().find_map(|(i, _)| {
+        let suffix = &clone.as_bytes()[i + 1..];
+        let hex = suffix.len() >= 8 && suffix[..8].iter().all(u8::is_ascii_hexdigit);
+        (hex && (suffix.len() == 8 || suffix[8] == b'.')).then(|| &clone[..i + 9])
+    })
+}
+
+fn owner_alive(marker: &Path) -> bool {
+    let Some(pid) = fs::read_to_string(marker).ok().and_then(|s| s.trim().parse::<u32>().ok()) else {
+        return false;
+    };
+    crate::pid::alive(pid)
+}
+
+fn stale(path: &Path, now: SystemTime) -> bool {
+    let Ok(modified) = fs::symlink_metadata(path).and_then(|m| m.modified()) else {
+        return false;
+    };
+    now.duration_since(modified).is_ok_and(|age| age >= STALE_AFTER)
+}
+
+/// Bytes under `path`, without following symlinks.
+fn size_of(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| size_of(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owner_of_cache_entries() {
+        assert_eq!(owner("my-repo-1a2b3c4d"), Some("my-repo-1a2b3c4d"));
+        assert_eq!(owner("my-repo-1a2b3c4d@backup-db"), Some("my-repo-1a2b3c4d"));
+        assert_eq!(owner("my-repo-1a2b3c4d.reclone"), Some("my-repo-1a2b3c4d"));
+        assert_eq!(owner("my-repo-1a2b3c4d.pid"), Some("my-repo-1a2b3c4d"));
+        assert_eq!(owner("history"), None);
+        assert_eq!(owner("my-repo"), None);
+    }
+
+    #[test]
+    fn scan_finds_entries_of_stopped_instances() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |path: &str| {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "data").unwrap();
+        };
+        // Running: this process
+        write("live-0000000a/rollcron.yaml");
+        write("live-0000000a@backup/run/out");
+        write("live-0000000a@backup/run.tmp/out");
+        claim(&root.path().join("live-0000000a")).unwrap();
+        // Stopped without cleaning up
+        write("dead-0000000b/rollcron.yaml");
+        write("dead-0000000b@backup/run/out");
+        // Legacy state is left alone
+        write("history/repo/backup/runs.jsonl");
+
+        let now = SystemTime::now();
+        let mut unused: Vec<_> = scan(root.path(), now).unused.into_iter().map(|u| u.path).collect();
+        unused.sort();
+        assert_eq!(unused, [root.path().join("dead-0000000b"), root.path().join("dead-0000000b@backup")]);
+
+        // An hour later, the running instance's temporary dir is stale too
+        let later = now + STALE_AFTER;
+        let removed = gc(root.path(), later).unwrap();
+        assert_eq!(removed.len(), 3);
+        assert!(removed.iter().all(|u| u.size == 4));
+        assert!(!root.path().join("live-0000000a@backup/run.tmp").exists());
+        assert!(root.path().join("live-0000000a@backup/run/out").exists());
+        assert!(root.path().join("history").exists());
+        assert!(scan(root.path(), later).unused.is_empty());
+
+        release(&root.path().join("live-0000000a"));
+        assert_eq!(scan(root.path(), now).unused.len(), 2);
+    }
+}
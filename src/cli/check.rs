@@ -0,0 +1,332 @@
+//! `rollcron check`: validate a config and, with `--simulate`, list the job
+//! firings it would produce over a time range without running anything.
+
+use super::completions::job_ids;
+use crate::actor::job::{disposition, next_occurrence_from, Disposition};
+use crate::config::{self, Job, LabelSelector, RunnerConfig, TimezoneConfig};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use clap::{Args as ClapArgs, ValueEnum};
+use clap_complete::ArgValueCandidates;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Default length of the simulated range
+const DEFAULT_RANGE_DAYS: i64 = 7;
+
+#[derive(ClapArgs)]
+pub struct CheckArgs {
+    /// Local repo, remote URL, or a rollcron.yaml file
+    repo: String,
+    /// List every job firing between --from and --to (nothing is run)
+    #[arg(long)]
+    simulate: bool,
+    /// Start of the simulated range, RFC 3339 or YYYY-MM-DD (UTC) [default: now]
+    #[arg(long, requires = "simulate", value_parser = parse_time)]
+    from: Option<DateTime<Utc>>,
+    /// End of the simulated range (exclusive) [default: --from + 7 days]
+    #[arg(long, requires = "simulate", value_parser = parse_time)]
+    to: Option<DateTime<Utc>>,
+    /// Only simulate these jobs (repeatable)
+    #[arg(long = "job", value_name = "JOB_ID", requires = "simulate", add = ArgValueCandidates::new(job_ids))]
+    jobs: Vec<String>,
+    /// Only simulate jobs with one of these labels: KEY or KEY=VALUE (comma-separated or repeated)
+    #[arg(long, value_name = "LABELS", value_delimiter = ',', requires = "simulate")]
+    only_labels: Vec<LabelSelector>,
+    /// Stop after this many firings
+    #[arg(long, default_value_t = 1000, requires = "simulate")]
+    max: usize,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    output: Format,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// One scheduled start
+#[derive(Debug, Serialize, PartialEq)]
+struct Firing {
+    job_id: String,
+    at: DateTime<Utc>,
+    /// Wall-clock time in the job's timezone, with its UTC offset
+    local: String,
+    /// Set when the job's UTC offset differs from its previous firing (DST)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset_change: Option<String>,
+    /// Dropped by a `skip` blackout window
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    skipped: bool,
+    /// Moved to the end of a `defer` blackout window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deferred_to: Option<DateTime<Utc>>,
+    /// Blackout window that skipped or deferred it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blackout: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Simulation {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    firings: Vec<Firing>,
+    /// Stopped at `--max`
+    truncated: bool,
+    /// Disabled jobs (never fire)
+    disabled: Vec<String>,
+}
+
+pub async fn run(args: CheckArgs) -> Result<()> {
+    let (runner, jobs, skipped) = load(&args.repo)?;
+    if !skipped.is_empty() {
+        anyhow::bail!(
+            "{} of {} jobs are invalid (see warnings above): {}",
+            skipped.len(),
+            skipped.len() + jobs.len(),
+            skipped.join(", ")
+        );
+    }
+    if let Some(unknown) = args.jobs.iter().find(|id| !jobs.iter().any(|j| &j.id == *id)) {
+        anyhow::bail!("Unknown job '{}'", unknown);
+    }
+
+    if !args.simulate {
+        match args.output {
+            Format::Text => println!("Config OK: {} jobs", jobs.len()),
+            Format::Json => println!("{}", serde_json::json!({ "valid": true, "jobs": jobs.len() })),
+        }
+        return Ok(());
+    }
+
+    let from = args.from.unwrap_or_else(Utc::now);
+    let to = args.to.unwrap_or(from + chrono::Duration::days(DEFAULT_RANGE_DAYS));
+    if to <= from {
+        anyhow::bail!("--to must be after --from");
+    }
+    let selected: Vec<Job> = jobs
+        .into_iter()
+        .filter(|j| args.jobs.is_empty() || args.jobs.contains(&j.id))
+        .filter(|j| j.has_any_label(&args.only_labels))
+        .collect();
+    let simulation = simulate(&runner, &selected, from, to, args.max);
+    match args.output {
+        Format::Text => print!("{}", render_text(&simulation)),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&simulation)?),
+    }
+    Ok(())
+}
+
+/// Parses a config file argument as is, or the committed config of a repo.
+/// Includes resolve against the file's directory or the repo root.
+pub(super) fn load(repo: &str) -> Result<(RunnerConfig, Vec<Job>, Vec<String>)> {
+    let path = Path::new(repo);
+    if path.is_file() {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        return config::parse_config_in(&content, Some(path.parent().unwrap_or(Path::new("."))));
+    }
+    let checkout = super::Checkout::new(repo)?;
+    let config_path = checkout.path().join(config::CONFIG_FILE);
+    let content =
+        std::fs::read_to_string(&config_path).with_context(|| format!("Failed to read {}", config_path.display()))?;
+    config::parse_config_in(&content, Some(checkout.path()))
+}
+
+fn simulate(runner: &RunnerConfig, jobs: &[Job], from: DateTime<Utc>, to: DateTime<Utc>, max: usize) -> Simulation {
+    let mut firings = Vec::new();
+    let mut truncated = false;
+    for job in jobs.iter().filter(|j| j.enabled) {
+        // Occurrences are strictly after the reference time
+        let mut cursor = from - chrono::Duration::seconds(1);
+        while let Some(at) = next_occurrence_from(job, runner, cursor).filter(|at| *at < to) {
+            // Every job contributes up to `max`; the merged list is cut below
+            if firings.iter().filter(|f: &&Firing| f.job_id == job.id).count() >= max {
+                truncated = true;
+                break;
+            }
+            let mut firing = Firing {
+                job_id: job.id.clone(),
+                at,
+                local: String::new(),
+                offset_change: None,
+                skipped: false,
+                deferred_to: None,
+                blackout: None,
+            };
+            cursor = at;
+            match disposition(job, runner, at) {
+                Disposition::Run => {}
+                Disposition::Skip(window) => {
+                    firing.skipped = true;
+                    firing.blackout = Some(window);
+                }
+                // Occurrences inside the window are coalesced into the deferred run
+                Disposition::Defer(until, window) => {
+                    firing.deferred_to = Some(until);
+                    firing.blackout = Some(window);
+                    cursor = until;
+                }
+            }
+            firings.push(firing);
+        }
+    }
+    firings.sort_by(|a, b| (a.at, &a.job_id).cmp(&(b.at, &b.job_id)));
+    if firings.len() > max {
+        firings.truncate(max);
+        truncated = true;
+    }
+
+    let mut last_offset: HashMap<String, String> = HashMap::new();
+    for firing in &mut firings {
+        let job = jobs.iter().find(|j| j.id == firing.job_id).expect("firing of a simulated job");
+        let (local, offset) = local_time(firing.at, job.timezone.as_ref().unwrap_or(&runner.timezone));
+        if let Some(previous) = last_offset.insert(firing.job_id.clone(), offset.clone()) {
+            if previous != offset {
+                firing.offset_change = Some(format!("UTC offset {} -> {}", previous, offset));
+            }
+        }
+        firing.local = local;
+    }
+
+    let disabled = jobs.iter().filter(|j| !j.enabled).map(|j| j.id.clone()).collect();
+    Simulation { from, to, firings, truncated, disabled }
+}
+
+/// `(wall-clock time with zone, UTC offset)` in the job's timezone.
+pub(super) fn local_time(at: DateTime<Utc>, tz: &TimezoneConfig) -> (String, String) {
+    const FORMAT: &str = "%Y-%m-%d %H:%M:%S %Z";
+    match tz {
+        TimezoneConfig::Utc => (at.format(FORMAT).to_string(), "+00:00".to_string()),
+        TimezoneConfig::Inherit => {
+            let local = at.with_timezone(&Local);
+            (local.format("%Y-%m-%d %H:%M:%S %:z").to_string(), local.format("%:z").to_string())
+        }
+        TimezoneConfig::Named(tz) => {
+            let local = at.with_timezone(tz);
+            (local.format(FORMAT).to_string(), local.format("%:z").to_string())
+        }
+    }
+}
+
+fn render_text(sim: &Simulation) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Firings from {} to {} (UTC):\n", sim.from.format("%Y-%m-%d %H:%M:%S"), sim.to.format("%Y-%m-%d %H:%M:%S"));
+    let width = sim.firings.iter().map(|f| f.job_id.len()).max().unwrap_or(0).max(6);
+    for firing in &sim.firings {
+        let _ = write!(out, "{}  {:<width$}  {}", firing.at.format("%Y-%m-%d %H:%M:%S"), firing.job_id, firing.local);
+        if let Some(change) = &firing.offset_change {
+            let _ = write!(out, "  ({})", change);
+        }
+        match (&firing.blackout, firing.deferred_to) {
+            (Some(window), Some(until)) => {
+                let _ = write!(out, "  [deferred to {} UTC: blackout {}]", until.format("%Y-%m-%d %H:%M:%S"), window);
+            }
+            (Some(window), None) => {
+                let _ = write!(out, "  [skipped: blackout {}]", window);
+            }
+            _ => {}
+        }
+        out.push('\n');
+    }
+    let _ = write!(out, "\n{} firings", sim.firings.len());
+    let skipped = sim.firings.iter().filter(|f| f.skipped).count();
+    let deferred = sim.firings.iter().filter(|f| f.deferred_to.is_some()).count();
+    if skipped + deferred > 0 {
+        let _ = write!(out, " ({} skipped, {} deferred by blackout windows)", skipped, deferred);
+    }
+    if sim.truncated {
+        out.push_str(" (stopped at --max)");
+    }
+    out.push('\n');
+    if !sim.disabled.is_empty() {
+        let _ = writeln!(out, "Disabled (never fire): {}", sim.disabled.join(", "));
+    }
+    out
+}
+
+pub(super) fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|d| d.and_hms_opt(0, 0, 0).expect("midnight exists").and_utc())
+        .map_err(|_| format!("expected RFC 3339 or YYYY-MM-DD, got '{}'", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn simulates_firings_across_dst() {
+        let yaml = r#"
+jobs:
+  ny:
+    schedule:
+      cron: "0 12 * * *"
+      timezone: America/New_York
+    run: echo
+  hourly:
+    schedule: "0 * * * *"
+    run: echo
+  off:
+    schedule: "* * * * *"
+    run: echo
+    enabled: false
+"#;
+        let (runner, jobs) = config::parse_config(yaml).unwrap();
+
+        // US DST starts 2025-03-09 02:00 local (UTC-5 -> UTC-4)
+        let from = Utc.with_ymd_and_hms(2025, 3, 8, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2025, 3, 10, 0, 0, 0).unwrap();
+        let sim = simulate(&runner, &jobs, from, to, 1000);
+
+        let ny: Vec<&Firing> = sim.firings.iter().filter(|f| f.job_id == "ny").collect();
+        assert_eq!(ny.len(), 2);
+        assert_eq!(ny[0].at, Utc.with_ymd_and_hms(2025, 3, 8, 17, 0, 0).unwrap());
+        assert_eq!(ny[0].local, "2025-03-08 12:00:00 EST");
+        assert_eq!(ny[0].offset_change, None);
+        assert_eq!(ny[1].at, Utc.with_ymd_and_hms(2025, 3, 9, 16, 0, 0).unwrap());
+        assert_eq!(ny[1].local, "2025-03-09 12:00:00 EDT");
+        assert_eq!(ny[1].offset_change.as_deref(), Some("UTC offset -05:00 -> -04:00"));
+        assert_eq!(sim.firings.iter().filter(|f| f.job_id == "hourly").count(), 48);
+        assert_eq!(sim.firings[0].at, from, "--from is inclusive");
+        assert!(sim.firings.windows(2).all(|w| w[0].at <= w[1].at));
+        assert_eq!(sim.disabled, vec!["off"]);
+
+        let yaml = r#"
+jobs:
+  nightly:
+    schedule: "0 * * * *"
+    run: echo
+    blackout:
+      - { from: "Sat 22:00", to: "Sun 00:00" }
+      - { from: "02:00", to: "04:30", policy: defer }
+"#;
+        let (runner, blackout_jobs) = config::parse_config(yaml).unwrap();
+        let sim = simulate(&runner, &blackout_jobs, from, from + chrono::Duration::days(1), 1000);
+        let statuses: Vec<_> = sim.firings.iter().map(|f| (f.at.format("%H:%M").to_string(), f.skipped, f.deferred_to)).collect();
+        assert_eq!(statuses[2], ("02:00".to_string(), false, Some(Utc.with_ymd_and_hms(2025, 3, 8, 4, 30, 0).unwrap())));
+        assert_eq!(statuses[3].0, "05:00", "02:00-04:30 is coalesced into one deferred run");
+        assert!(statuses.iter().filter(|s| s.1).map(|s| s.0.as_str()).eq(["22:00", "23:00"]));
+
+        let sim = simulate(&runner, &jobs, from, to, 10);
+        assert!(sim.truncated);
+        assert_eq!(sim.firings.len(), 10);
+    }
+
+    #[test]
+    fn parses_range_bounds() {
+        assert_eq!(parse_time("2025-03-08").unwrap(), Utc.with_ymd_and_hms(2025, 3, 8, 0, 0, 0).unwrap());
+        assert_eq!(
+            parse_time("2025-03-08T10:00:00+09:00").unwrap(),
+            Utc.with_ymd_and_hms(2025, 3, 8, 1, 0, 0).unwrap()
+        );
+        assert!(parse_time("next tuesday").is_err());
+    }
+}
@@ -0,0 +1,75 @@
+//! `rollcron completions`: shell completion scripts.
+//!
+//! The script only registers rollcron with the shell; completions are
+//! computed by rollcron itself (`COMPLETE=<shell> rollcron ...`), so job IDs
+//! come from the config in the current directory at the time you press tab.
+
+use crate::config::CONFIG_FILE;
+use anyhow::Result;
+use clap::Args;
+use clap_complete::env::Shells;
+use clap_complete::CompletionCandidate;
+
+/// Environment variable the shell sets when it asks rollcron for completions
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// bash, zsh, fish, elvish or powershell
+    #[arg(value_parser = shell_names())]
+    shell: String,
+}
+
+fn shell_names() -> Vec<&'static str> {
+    Shells::builtins().names().collect()
+}
+
+pub fn run(args: CompletionsArgs) -> Result<()> {
+    let shells = Shells::builtins();
+    let shell = shells.completer(&args.shell).expect("clap only accepts builtin shell names");
+    // An absolute path keeps completion working when rollcron isn't on PATH
+    let completer = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.to_str().map(str::to_string))
+        .unwrap_or_else(|| "rollcron".to_string());
+    shell.write_registration(COMPLETE_VAR, "rollcron", "rollcron", &completer, &mut std::io::stdout())?;
+    Ok(())
+}
+
+/// Job IDs of `./rollcron.yaml`, for tab completion. Only the `jobs:` keys
+/// are read, so a config that doesn't validate still completes.
+pub fn job_ids() -> Vec<CompletionCandidate> {
+    let Ok(content) = std::fs::read_to_string(CONFIG_FILE) else {
+        return Vec::new();
+    };
+    job_ids_in(&content).into_iter().map(CompletionCandidate::new).collect()
+}
+
+fn job_ids_in(content: &str) -> Vec<String> {
+    let Ok(doc) = serde_yaml::from_str::<serde_yaml::Value>(content) else {
+        return Vec::new();
+    };
+    let Some(jobs) = doc.get("jobs").and_then(|jobs| jobs.as_mapping()) else {
+        return Vec::new();
+    };
+    jobs.keys().filter_map(|key| key.as_str().map(str::to_string)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_ids_from_yaml_keys() {
+        let yaml = r#"
+jobs:
+  backup:
+    schedule: { cron: "0 3 * * *" }
+  report:
+    schedule: not a valid schedule
+"#;
+        assert_eq!(job_ids_in(yaml), ["backup", "report"]);
+        assert!(job_ids_in("runner: {}").is_empty());
+        assert!(job_ids_in(": not yaml :").is_empty());
+    }
+}
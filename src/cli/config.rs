@@ -0,0 +1,348 @@
+//! `rollcron config explain`: a job's effective settings and where each came from.
+//!
+//! Settings resolve job > `defaults` > `runner` > built-in default, so the
+//! origin is the first of those layers that sets the key in the YAML as
+//! written. List settings (`webhook`, `blackout`) merge runner and job entries.
+
+use super::completions::job_ids;
+use crate::config::{self, Job, CONFIG_FILE};
+use crate::facts;
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand, ValueEnum};
+use clap_complete::ArgValueCandidates;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print every effective setting of a job and where it came from
+    Explain(ExplainArgs),
+}
+
+#[derive(Args)]
+pub struct ExplainArgs {
+    /// Local repo, remote URL, or a rollcron.yaml file
+    repo: String,
+    #[arg(add = ArgValueCandidates::new(job_ids))]
+    job_id: String,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    output: Format,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// Layer a setting was resolved from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Origin {
+    Job,
+    Defaults,
+    Runner,
+    /// Built into rollcron
+    Default,
+    /// This host's facts
+    Host,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct Setting {
+    key: String,
+    value: String,
+    origin: Origin,
+}
+
+/// The YAML layers a job's settings resolve through
+struct Layers {
+    job: Value,
+    defaults: Value,
+    runner: Value,
+}
+
+impl Layers {
+    fn get(&self, origin: Origin) -> &Value {
+        match origin {
+            Origin::Job => &self.job,
+            Origin::Defaults => &self.defaults,
+            Origin::Runner => &self.runner,
+            Origin::Default | Origin::Host => &Value::Null,
+        }
+    }
+
+    /// The first of `candidates` set in the YAML, as written.
+    fn resolve(&self, candidates: &[(Origin, &str)]) -> Option<(String, Origin)> {
+        candidates
+            .iter()
+            .find_map(|(origin, path)| lookup(self.get(*origin), path).map(|v| (render(v), *origin)))
+    }
+}
+
+pub async fn run(command: ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::Explain(args) => explain_cmd(args).await,
+    }
+}
+
+async fn explain_cmd(args: ExplainArgs) -> Result<()> {
+    let path = Path::new(&args.repo);
+    let checkout;
+    let (root, main_file) = if path.is_file() {
+        (path.parent().unwrap_or(Path::new(".")).to_path_buf(), path.to_path_buf())
+    } else {
+        checkout = super::Checkout::new(&args.repo)?;
+        (checkout.path().to_path_buf(), checkout.path().join(CONFIG_FILE))
+    };
+    let content =
+        std::fs::read_to_string(&main_file).with_context(|| format!("Failed to read {}", main_file.display()))?;
+    let (_, jobs, skipped) = config::parse_config_in(&content, Some(&root))?;
+    let Some(job) = jobs.iter().find(|j| j.id == args.job_id) else {
+        if skipped.contains(&args.job_id) {
+            anyhow::bail!("Job '{}' is invalid and would be skipped (see the warning above)", args.job_id);
+        }
+        anyhow::bail!("Unknown job '{}'", args.job_id);
+    };
+
+    let top: Value = serde_yaml::from_str(&content)?;
+    // `source` is `file:line`; included jobs live in another file
+    let file = job.source.rsplit_once(':').map_or(job.source.as_str(), |(file, _)| file);
+    let job_doc: Value = if file == CONFIG_FILE {
+        top.clone()
+    } else {
+        let path = root.join(file);
+        serde_yaml::from_str(&std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?)?
+    };
+    let layers = Layers {
+        job: job_doc.get("jobs").and_then(|jobs| jobs.get(job.id.as_str())).cloned().unwrap_or_default(),
+        defaults: top.get("defaults").cloned().unwrap_or_default(),
+        runner: top.get("runner").cloned().unwrap_or_default(),
+    };
+
+    let settings = explain(job, &layers, facts::get());
+    match args.output {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&settings)?),
+        Format::Text => {
+            println!("{} ({})", job.id, job.source);
+            let key_width = settings.iter().map(|s| s.key.len()).max().unwrap_or(0);
+            let value_width = settings.iter().map(|s| s.value.chars().count().min(60)).max().unwrap_or(0);
+            for s in &settings {
+                let origin = serde_json::to_value(s.origin).ok().and_then(|v| v.as_str().map(String::from)).unwrap_or_default();
+                println!("  {:key_width$}  {:value_width$}  {}", s.key, s.value, origin);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn explain(job: &Job, layers: &Layers, host: &facts::Facts) -> Vec<Setting> {
+    use Origin::*;
+    let mut settings = Vec::new();
+    let mut push = |key: &str, candidates: &[(Origin, &str)], fallback: String| {
+        let (value, origin) = layers.resolve(candidates).unwrap_or((fallback, Default));
+        settings.push(Setting { key: key.to_string(), value, origin });
+    };
+
+    push("name", &[(Job, "name")], job.id.clone());
+    push("schedule", &[(Job, "schedule.cron"), (Job, "schedule")], job.schedule_str().to_string());
+    push(
+        "schedule.timezone",
+        &[(Job, "schedule.timezone"), (Defaults, "timezone"), (Runner, "timezone")],
+        "UTC".to_string(),
+    );
+    push("enabled", &[(Job, "enabled")], "true".to_string());
+    push("run", &[(Job, "run.sh"), (Job, "run")], job.command.to_string());
+    push("run.fallback_command", &[(Job, "run.fallback_command")], "none".to_string());
+    push("shell", &[(Job, "shell"), (Defaults, "shell"), (Runner, "shell")], lowercase(job.shell));
+    push("run.timeout", &[(Job, "run.timeout"), (Defaults, "timeout")], format_duration(job.timeout));
+    push("run.concurrency", &[(Job, "run.concurrency")], lowercase(&job.concurrency));
+    push("run.retry", &[(Job, "run.retry"), (Defaults, "retry")], "none".to_string());
+    push("run.max_output", &[(Job, "run.max_output")], format!("{}", job.max_output));
+    push("working_dir", &[(Job, "run.working_dir"), (Job, "working_dir")], "job directory".to_string());
+    if job.build.is_some() {
+        push("build", &[(Job, "build.sh"), (Job, "build")], String::new());
+        // Falls back to the run timeout, wherever that came from
+        let mut candidates = vec![(Job, "build.timeout"), (Job, "run.timeout"), (Defaults, "timeout")];
+        candidates.dedup();
+        push("build.timeout", &candidates, format_duration(job.timeout));
+    }
+    push("severity", &[(Job, "severity"), (Defaults, "severity")], lowercase(job.severity));
+    push(
+        "notify_on_start",
+        &[(Job, "notify_on_start"), (Defaults, "notify_on_start")],
+        job.notify_on_start.to_string(),
+    );
+    push(
+        "dedupe_alerts",
+        &[(Job, "dedupe_alerts"), (Defaults, "dedupe_alerts")],
+        job.dedupe_alerts.to_string(),
+    );
+    push("renotify_after", &[(Job, "renotify_after")], "none".to_string());
+    push(
+        "notify_on_output_change",
+        &[(Job, "notify_on_output_change")],
+        job.notify_on_output_change.to_string(),
+    );
+    push("expected_duration", &[(Job, "expected_duration")], "none".to_string());
+    push("priority", &[(Job, "priority")], job.priority.to_string());
+    push("run_on_start", &[(Job, "run_on_start")], job.run_on_start.to_string());
+    push("pull_on_run", &[(Job, "pull_on_run")], job.pull_on_run.to_string());
+    push("min_interval", &[(Job, "min_interval")], "none".to_string());
+    push("circuit_breaker", &[(Job, "circuit_breaker"), (Defaults, "circuit_breaker")], "none".to_string());
+    push("log", &[(Job, "log")], "none".to_string());
+    push("audit", &[(Job, "audit")], "false".to_string());
+    for key in [
+        "limits", "security", "sandbox", "user", "group", "cleanup", "slo", "labels", "when", "success_when",
+        "artifacts", "condition",
+    ] {
+        push(key, &[(Job, key)], "none".to_string());
+    }
+    for key in ["min_free_disk", "disk_path", "max_loadavg", "policy", "max_delay"] {
+        let path = format!("guards.{}", key);
+        let fallback = match key {
+            "policy" => lowercase(job.guards.policy),
+            "max_delay" => format_duration(job.guards.max_delay),
+            _ => "none".to_string(),
+        };
+        push(&path, &[(Job, &path), (Runner, &path)], fallback);
+    }
+
+    // Lists: runner entries first, then the job's
+    for (key, origins) in [("webhook", [Runner, Job]), ("blackout", [Runner, Job])] {
+        for origin in origins {
+            if let Some(Value::Sequence(items)) = lookup(layers.get(origin), key) {
+                for item in items {
+                    settings.push(Setting { key: key.to_string(), value: render(item), origin });
+                }
+            }
+        }
+    }
+
+    // Environment: run.env > env > runner.env, per variable
+    let mut names: Vec<String> = [(Job, "run.env"), (Job, "env"), (Runner, "env")]
+        .iter()
+        .filter_map(|(origin, path)| lookup(layers.get(*origin), path)?.as_mapping())
+        .flat_map(|map| map.keys().filter_map(|k| k.as_str().map(String::from)))
+        .collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let paths = [format!("run.env.{}", name), format!("env.{}", name)];
+        let candidates = [(Job, paths[0].as_str()), (Job, paths[1].as_str()), (Runner, paths[1].as_str())];
+        if let Some((value, origin)) = layers.resolve(&candidates) {
+            settings.push(Setting { key: format!("env.{}", name), value, origin });
+        }
+    }
+    for (origin, path) in [(Job, "run.env_file"), (Job, "env_files"), (Job, "env_file"), (Runner, "env_file")] {
+        if let Some(value) = lookup(layers.get(origin), path) {
+            settings.push(Setting { key: path.to_string(), value: render(value), origin });
+        }
+    }
+
+    let host_match = match job.host_mismatch(host) {
+        Some(reason) => format!("no ({})", reason),
+        None => "yes".to_string(),
+    };
+    settings.push(Setting { key: "runs on this host".to_string(), value: host_match, origin: Host });
+    settings
+}
+
+/// Value at a dotted path; a scalar is not looked into. Env var names may
+/// contain dots, so the last segment is the rest of the path.
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = value;
+    let mut rest = path;
+    loop {
+        let map = current.as_mapping()?;
+        if let Some(found) = map.get(rest) {
+            return (!found.is_null()).then_some(found);
+        }
+        let (head, tail) = rest.split_once('.')?;
+        current = map.get(head)?;
+        rest = tail;
+    }
+}
+
+/// A YAML value on one line: scalars as written, the rest as JSON.
+fn render(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Tagged(tagged) => render(&tagged.value),
+        _ => serde_json::to_string(value).unwrap_or_default(),
+    }
+}
+
+fn lowercase<T: Serialize>(value: T) -> String {
+    serde_json::to_value(value).ok().and_then(|v| v.as_str().map(String::from)).unwrap_or_default()
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        s if s > 0 && s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s > 0 && s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = r#"
+runner:
+  timezone: Asia/Tokyo
+  shell: bash
+  env: { REGION: eu, LEVEL: info }
+  guards: { max_loadavg: 8 }
+defaults:
+  timeout: 10m
+jobs:
+  etl:
+    schedule: "0 2 * * *"
+    run:
+      sh: ./etl.sh
+      env: { LEVEL: debug }
+    severity: critical
+    guards: { policy: defer }
+"#;
+
+    fn settings() -> Vec<Setting> {
+        let (_, jobs) = config::parse_config(YAML).unwrap();
+        let top: Value = serde_yaml::from_str(YAML).unwrap();
+        let layers = Layers {
+            job: top["jobs"]["etl"].clone(),
+            defaults: top["defaults"].clone(),
+            runner: top["runner"].clone(),
+        };
+        explain(&jobs[0], &layers, &facts::Facts::default())
+    }
+
+    fn find<'a>(settings: &'a [Setting], key: &str) -> (&'a str, Origin) {
+        let s = settings.iter().find(|s| s.key == key).unwrap_or_else(|| panic!("no {}", key));
+        (s.value.as_str(), s.origin)
+    }
+
+    #[test]
+    fn settings_name_the_layer_they_come_from() {
+        let settings = settings();
+        assert_eq!(find(&settings, "run"), ("./etl.sh", Origin::Job));
+        assert_eq!(find(&settings, "severity"), ("critical", Origin::Job));
+        assert_eq!(find(&settings, "run.timeout"), ("10m", Origin::Defaults));
+        assert_eq!(find(&settings, "schedule.timezone"), ("Asia/Tokyo", Origin::Runner));
+        assert_eq!(find(&settings, "shell"), ("bash", Origin::Runner));
+        assert_eq!(find(&settings, "run.concurrency"), ("skip", Origin::Default));
+        assert_eq!(find(&settings, "guards.policy"), ("defer", Origin::Job));
+        assert_eq!(find(&settings, "guards.max_loadavg"), ("8", Origin::Runner));
+        assert_eq!(find(&settings, "guards.max_delay"), ("1h", Origin::Default));
+        assert_eq!(find(&settings, "env.LEVEL"), ("debug", Origin::Job));
+        assert_eq!(find(&settings, "env.REGION"), ("eu", Origin::Runner));
+        assert_eq!(find(&settings, "runs on this host"), ("yes", Origin::Host));
+    }
+}
@@ -0,0 +1,154 @@
+//! `rollcron cron next`: upcoming occurrences of a bare schedule expression,
+//! parsed exactly as a job's `schedule:` would be.
+
+use super::check::{local_time, parse_time};
+use crate::actor::job::next_in_timezone;
+use crate::config::{parse_schedule, parse_timezone, CronFormat, TimezoneConfig};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::{Args, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::fmt::Write as _;
+
+#[derive(Subcommand)]
+pub enum CronCommand {
+    /// Print the next occurrences of a schedule expression
+    Next(NextArgs),
+}
+
+#[derive(Args)]
+pub struct NextArgs {
+    /// Cron expression, `@` alias or English phrase (quote it)
+    expr: String,
+    /// IANA timezone, or `inherit` for the system timezone
+    #[arg(long, default_value = "UTC")]
+    tz: String,
+    /// Number of occurrences
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..=1000))]
+    count: u32,
+    /// Start after this time, RFC 3339 or YYYY-MM-DD (UTC) [default: now]
+    #[arg(long, value_parser = parse_time)]
+    from: Option<DateTime<Utc>>,
+    /// Fields of cron expressions, as `runner.cron_format` [default: 5 fields, or 6-7 with seconds]
+    #[arg(long, value_parser = ["standard", "quartz"])]
+    cron_format: Option<String>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    output: Format,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct Occurrences {
+    expr: String,
+    description: String,
+    occurrences: Vec<Occurrence>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct Occurrence {
+    at: DateTime<Utc>,
+    /// Wall-clock time in the timezone, with its UTC offset
+    local: String,
+    /// Set when the UTC offset differs from the previous occurrence (DST)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset_change: Option<String>,
+}
+
+pub fn run(command: CronCommand) -> Result<()> {
+    let CronCommand::Next(args) = command;
+    let format = match args.cron_format.as_deref() {
+        Some("standard") => Some(CronFormat::Standard),
+        Some("quartz") => Some(CronFormat::Quartz),
+        _ => None,
+    };
+    let tz = parse_timezone(&args.tz)?;
+    let result = next(&args.expr, format, &tz, args.from.unwrap_or_else(Utc::now), args.count as usize)?;
+    match args.output {
+        Format::Text => print!("{}", render_text(&result)),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+    }
+    Ok(())
+}
+
+fn next(expr: &str, format: Option<CronFormat>, tz: &TimezoneConfig, from: DateTime<Utc>, count: usize) -> Result<Occurrences> {
+    let Some(schedule) = parse_schedule(expr, format)? else {
+        anyhow::bail!("@reboot runs once at daemon startup and has no schedule");
+    };
+    let zone = match tz {
+        TimezoneConfig::Utc => "UTC",
+        TimezoneConfig::Inherit => "local time",
+        TimezoneConfig::Named(tz) => tz.name(),
+    };
+    let description = format!("{}, {}", schedule.describe().trim_end_matches('.'), zone);
+
+    let mut occurrences: Vec<Occurrence> = Vec::new();
+    let mut last_offset: Option<String> = None;
+    // Occurrences are strictly after the reference time
+    let mut cursor = from - chrono::Duration::seconds(1);
+    while occurrences.len() < count {
+        let Some(at) = next_in_timezone(&schedule, tz, cursor) else {
+            break;
+        };
+        let (local, offset) = local_time(at, tz);
+        let offset_change = match last_offset.replace(offset.clone()) {
+            Some(previous) if previous != offset => Some(format!("UTC offset {} -> {}", previous, offset)),
+            _ => None,
+        };
+        occurrences.push(Occurrence { at, local, offset_change });
+        cursor = at;
+    }
+    Ok(Occurrences { expr: expr.to_string(), description, occurrences })
+}
+
+fn render_text(result: &Occurrences) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{}\n", result.description);
+    for occurrence in &result.occurrences {
+        let _ = write!(out, "{}  {}", occurrence.at.format("%Y-%m-%d %H:%M:%S UTC"), occurrence.local);
+        if let Some(change) = &occurrence.offset_change {
+            let _ = write!(out, "  ({})", change);
+        }
+        out.push('\n');
+    }
+    if result.occurrences.is_empty() {
+        out.push_str("No upcoming occurrences\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn lists_occurrences_in_timezone() {
+        let tz = parse_timezone("Asia/Tokyo").unwrap();
+        // Friday 2025-03-07 10:00 in Tokyo
+        let from = Utc.with_ymd_and_hms(2025, 3, 7, 1, 0, 0).unwrap();
+        let result = next("0 30 9 * * Mon-Fri", None, &tz, from, 3).unwrap();
+        let local: Vec<&str> = result.occurrences.iter().map(|o| o.local.as_str()).collect();
+        assert_eq!(local, ["2025-03-10 09:30:00 JST", "2025-03-11 09:30:00 JST", "2025-03-12 09:30:00 JST"]);
+        assert_eq!(result.occurrences[0].at, Utc.with_ymd_and_hms(2025, 3, 10, 0, 30, 0).unwrap());
+        assert!(result.description.ends_with(", Asia/Tokyo"), "{}", result.description);
+
+        assert_eq!(next("0 12 * * *", None, &TimezoneConfig::Utc, from, 1).unwrap().occurrences.len(), 1);
+        assert!(next("0 12 * * *", Some(CronFormat::Quartz), &TimezoneConfig::Utc, from, 1).is_err());
+        assert!(next("@reboot", None, &TimezoneConfig::Utc, from, 1).is_err());
+    }
+
+    #[test]
+    fn marks_dst_changes() {
+        let tz = parse_timezone("America/New_York").unwrap();
+        let from = Utc.with_ymd_and_hms(2025, 3, 8, 0, 0, 0).unwrap();
+        let result = next("every day at noon", None, &tz, from, 2).unwrap();
+        assert_eq!(result.occurrences[0].offset_change, None);
+        assert_eq!(result.occurrences[1].offset_change.as_deref(), Some("UTC offset -05:00 -> -04:00"));
+    }
+}
@@ -0,0 +1,290 @@
+use crate::actor::runner::bulk::{BulkAction, BulkResult};
+use super::completions::job_ids;
+use crate::api::{self, BulkRequest, LogFilterRequest, TriggerOverrides};
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use clap_complete::ArgValueCandidates;
+use serde_json::Value;
+
+#[derive(Args)]
+pub struct CtlArgs {
+    /// Address of the daemon's control API
+    #[arg(long, default_value = api::DEFAULT_ADDR)]
+    addr: String,
+
+    /// Bearer token the daemon was started with (`--api-token`); needed for every action but `list`
+    #[arg(long, env = "ROLLCRON_API_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    #[command(subcommand)]
+    command: CtlCommand,
+}
+
+#[derive(Subcommand)]
+enum CtlCommand {
+    /// Discard a job's directory and rebuild it from the current commit
+    Resync {
+        #[arg(add = ArgValueCandidates::new(job_ids))]
+        job_id: String,
+    },
+    /// Allow a job blocked by the host policy (`--policy`) to build and run
+    ApproveJob {
+        #[arg(add = ArgValueCandidates::new(job_ids))]
+        job_id: String,
+    },
+    /// Show or replace the daemon's log filter (RUST_LOG syntax), e.g. `rollcron::git=debug`
+    LogLevel {
+        filter: Option<String>,
+        /// Restore the filter the daemon started with
+        #[arg(long, conflicts_with = "filter")]
+        reset: bool,
+    },
+    /// Show the safety brake (`runner.brake`), or release it once the cause is fixed
+    Brake {
+        /// Resume the jobs the brake paused
+        #[arg(long)]
+        release: bool,
+    },
+    /// List the daemon's jobs with their state and next run
+    List,
+    /// Stop scheduled runs of the selected jobs, until resumed (also across restarts)
+    Pause(Selection),
+    /// Resume scheduled runs of the selected jobs
+    Resume(Selection),
+    /// Run the selected jobs now, optionally overriding settings for these runs
+    Trigger(TriggerArgs),
+}
+
+#[derive(Args)]
+struct TriggerArgs {
+    #[command(flatten)]
+    selection: Selection,
+    /// Extra environment for these runs (repeatable), above `run.env`
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env)]
+    env: Vec<(String, String)>,
+    /// Run timeout for these runs, e.g. `300` or `2h`
+    #[arg(long)]
+    timeout: Option<String>,
+}
+
+fn parse_env(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", s))
+}
+
+/// Jobs to act on (exactly one of these)
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct Selection {
+    /// Job IDs (comma-separated or repeated)
+    #[arg(value_delimiter = ',', add = ArgValueCandidates::new(job_ids))]
+    jobs: Vec<String>,
+    /// All jobs
+    #[arg(long)]
+    all: bool,
+    /// Jobs with this label (key:value)
+    #[arg(long)]
+    label: Option<String>,
+}
+
+pub async fn run(args: CtlArgs) -> Result<()> {
+    let (action, selection) = match &args.command {
+        CtlCommand::Resync { job_id } => {
+            let body = post(&args, &format!("/jobs/{}/resync", job_id), None).await?;
+            println!("{}: {}", job_id, body["status"].as_str().unwrap_or("ok"));
+            return Ok(());
+        }
+        CtlCommand::ApproveJob { job_id } => {
+            let body = post(&args, &format!("/jobs/{}/approve", job_id), None).await?;
+            println!("{}: {}", job_id, body["status"].as_str().unwrap_or("ok"));
+            return Ok(());
+        }
+        CtlCommand::LogLevel { filter, reset } => {
+            let body = match (filter, reset) {
+                (None, false) => send(&args, reqwest::Method::GET, "/debug/log", None).await?,
+                (filter, _) => {
+                    let request = LogFilterRequest { filter: filter.clone() };
+                    post(&args, "/debug/log", Some(serde_json::to_value(&request)?)).await?
+                }
+            };
+            println!("{}", body["filter"].as_str().unwrap_or("unknown"));
+            return Ok(());
+        }
+        CtlCommand::Brake { release: false } => {
+            let body = send(&args, reqwest::Method::GET, "/brake", None).await?;
+            print_brake(&body);
+            return Ok(());
+        }
+        CtlCommand::Brake { release: true } => {
+            let body = post(&args, "/brake/release", None).await?;
+            let resumed: Vec<&str> = body["resumed"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+            println!("{}", body["status"].as_str().unwrap_or("ok"));
+            if !resumed.is_empty() {
+                println!("resumed: {}", resumed.join(", "));
+            }
+            return Ok(());
+        }
+        CtlCommand::List => {
+            let body = send(&args, reqwest::Method::GET, "/jobs", None).await?;
+            print_jobs(&body);
+            return Ok(());
+        }
+        CtlCommand::Pause(selection) => (BulkAction::Pause, selection),
+        CtlCommand::Resume(selection) => (BulkAction::Resume, selection),
+        CtlCommand::Trigger(trigger) => (BulkAction::Trigger, &trigger.selection),
+    };
+
+    let overrides = match &args.command {
+        CtlCommand::Trigger(trigger) => TriggerOverrides {
+            env: trigger.env.iter().cloned().collect(),
+            timeout: trigger.timeout.clone(),
+        },
+        _ => TriggerOverrides::default(),
+    };
+    let request = BulkRequest {
+        action: Some(action),
+        all: selection.all,
+        jobs: selection.jobs.clone(),
+        label: selection.label.clone(),
+        overrides,
+    };
+    let body = post(&args, "/jobs/bulk", Some(serde_json::to_value(&request)?)).await?;
+    let result: BulkResult = serde_json::from_value(body).context("Invalid response from daemon")?;
+    print_result(&result);
+
+    if !result.failed.is_empty() {
+        anyhow::bail!("{} of {} jobs failed", result.failed.len(), result.matched);
+    }
+    Ok(())
+}
+
+fn print_result(result: &BulkResult) {
+    for job_id in &result.succeeded {
+        println!("{:<24} ok", job_id);
+    }
+    for (job_id, reason) in &result.failed {
+        println!("{:<24} FAILED ({})", job_id, reason);
+    }
+    println!(
+        "{:?}: {} matched, {} succeeded, {} failed",
+        result.action,
+        result.matched,
+        result.succeeded.len(),
+        result.failed.len()
+    );
+}
+
+fn print_jobs(body: &Value) {
+    let jobs = body["jobs"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let id_width = jobs.iter().filter_map(|job| job["id"].as_str()).map(str::len).max().unwrap_or(0).max(3);
+    println!("{:id_width$}  {:<40}  NEXT RUN", "JOB", "STATE");
+    for job in jobs {
+        println!(
+            "{:id_width$}  {:<40}  {}",
+            job["id"].as_str().unwrap_or("?"),
+            job_state(job),
+            job["next_run"]
+                .as_str()
+                .and_then(|at| at.parse::<chrono::DateTime<chrono::Utc>>().ok())
+                .map_or("-".to_string(), |at| at.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        );
+    }
+}
+
+/// A job's state as `ctl list` shows it, most pressing first.
+fn job_state(job: &Value) -> String {
+    let config = if job["enabled"] == true { "enabled" } else { "disabled" };
+    if job["paused_locally"].is_string() {
+        return format!("paused locally (config says {})", config);
+    }
+    if job["enabled"] != true {
+        return "disabled".to_string();
+    }
+    if job["blocked"].is_string() {
+        return "blocked (ctl approve-job)".to_string();
+    }
+    if job["running"].as_u64().is_some_and(|n| n > 0) || job["building"] == true {
+        return "running".to_string();
+    }
+    if job["paused"] == true {
+        return "paused (safety brake)".to_string();
+    }
+    if job["circuit_open"].is_string() {
+        return "circuit open".to_string();
+    }
+    "scheduled".to_string()
+}
+
+fn print_brake(body: &Value) {
+    if body["enabled"] != true {
+        println!("disabled (no runner.brake in the config)");
+    } else if body["engaged"] != true {
+        println!("not engaged");
+    } else {
+        println!(
+            "ENGAGED since {}: {} of {} runs failed",
+            body["since"].as_str().unwrap_or("?"),
+            body["failed"],
+            body["runs"]
+        );
+        let paused: Vec<&str> = body["paused"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+        println!("paused: {}", if paused.is_empty() { "none".to_string() } else { paused.join(", ") });
+        println!("release with `rollcron ctl brake --release`");
+    }
+}
+
+/// The API only takes JSON POST bodies, so one is always sent.
+async fn post(args: &CtlArgs, path: &str, body: Option<Value>) -> Result<Value> {
+    send(args, reqwest::Method::POST, path, Some(body.unwrap_or_else(|| serde_json::json!({})))).await
+}
+
+/// Calls the control API and returns the JSON body, turning error
+/// responses into errors.
+async fn send(args: &CtlArgs, method: reqwest::Method, path: &str, body: Option<Value>) -> Result<Value> {
+    let addr = &args.addr;
+    let url = format!("http://{}{}", addr, path);
+    let mut request = reqwest::Client::new().request(method, &url);
+    if let Some(token) = &args.token {
+        request = request.bearer_auth(token);
+    }
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    let resp = request
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach rollcron at {} (is the daemon running?)", addr))?;
+    let status = resp.status();
+    let body: Value = resp.json().await.context("Invalid response from daemon")?;
+    if !status.is_success() {
+        anyhow::bail!("{}", body["error"].as_str().unwrap_or(status.as_str()));
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn local_pause_names_the_config() {
+        let job = |fields: Value| {
+            let mut job = json!({ "id": "backup", "enabled": true, "paused": false, "running": 0, "building": false });
+            job.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+            job_state(&job)
+        };
+        assert_eq!(job(json!({})), "scheduled");
+        assert_eq!(
+            job(json!({ "paused": true, "paused_locally": "2026-03-01T12:00:00Z" })),
+            "paused locally (config says enabled)"
+        );
+        assert_eq!(
+            job(json!({ "enabled": false, "paused": true, "paused_locally": "2026-03-01T12:00:00Z" })),
+            "paused locally (config says disabled)"
+        );
+        assert_eq!(job(json!({ "paused": true })), "paused (safety brake)");
+        assert_eq!(job(json!({ "running": 1 })), "running");
+    }
+}
@@ -0,0 +1,42 @@
+//! `rollcron describe`: print job schedules as sentences.
+
+use super::check::load;
+use super::completions::job_ids;
+use crate::describe::{describe, Lang};
+use anyhow::Result;
+use clap::Args;
+use clap_complete::ArgValueCandidates;
+
+#[derive(Args)]
+pub struct DescribeArgs {
+    /// Local repo, remote URL, or a rollcron.yaml file
+    repo: String,
+    /// Only these jobs [default: all]
+    #[arg(value_name = "JOB_ID", add = ArgValueCandidates::new(job_ids))]
+    jobs: Vec<String>,
+    /// Language [default: from LC_ALL, LC_MESSAGES or LANG]
+    #[arg(long, value_enum)]
+    lang: Option<Lang>,
+}
+
+pub fn run(args: DescribeArgs) -> Result<()> {
+    let (runner, mut jobs, _) = load(&args.repo)?;
+    if let Some(unknown) = args.jobs.iter().find(|id| !jobs.iter().any(|j| &j.id == *id)) {
+        anyhow::bail!("Unknown job '{}'", unknown);
+    }
+    jobs.retain(|j| args.jobs.is_empty() || args.jobs.contains(&j.id));
+    jobs.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let lang = args.lang.unwrap_or_else(Lang::from_env);
+    let id_width = jobs.iter().map(|j| j.id.len()).max().unwrap_or(0);
+    let cron_width = jobs.iter().map(|j| j.schedule_str().len()).max().unwrap_or(0);
+    for job in &jobs {
+        println!(
+            "{:id_width$}  {:cron_width$}  {}",
+            job.id,
+            job.schedule_str(),
+            describe(job, &runner, lang)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,36 @@
+//! `rollcron gc`: removes cache entries of stopped instances and stale
+//! temporary directories (see `crate::cache`).
+
+use crate::cache;
+use crate::config::format_size;
+use crate::git;
+use anyhow::Result;
+use clap::Args;
+use std::time::SystemTime;
+
+#[derive(Args)]
+pub struct GcArgs {
+    /// List what would be removed without removing it
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub fn run(args: GcArgs) -> Result<()> {
+    let root = git::cache_base();
+    let now = SystemTime::now();
+    let unused = if args.dry_run {
+        cache::scan(&root, now).unused
+    } else {
+        cache::gc(&root, now)?
+    };
+    for entry in &unused {
+        println!("{:>8}  {}", format_size(entry.size), entry.path.display());
+    }
+    let freed = format_size(unused.iter().map(|u| u.size).sum());
+    match (unused.len(), args.dry_run) {
+        (0, _) => println!("Nothing to remove in {}", root.display()),
+        (n, true) => println!("Would free {} ({} entries)", freed, n),
+        (n, false) => println!("Freed {} ({} entries)", freed, n),
+    }
+    Ok(())
+}
@@ -0,0 +1,254 @@
+use super::completions::job_ids;
+use super::import::{self, ImportFormat};
+use crate::actor::job::{artifacts_dir, degrading, JobRunReport};
+use crate::{git, history};
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use clap_complete::ArgValueCandidates;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Subcommand)]
+pub enum HistoryCommand {
+    /// List recent runs of a job
+    List {
+        /// Path to local repo or remote URL (same as the daemon argument)
+        repo: String,
+        #[arg(add = ArgValueCandidates::new(job_ids))]
+        job_id: String,
+        /// Number of runs to show (most recent first)
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+    /// Show a run's report and captured output (decompressed transparently)
+    Show {
+        /// Path to local repo or remote URL (same as the daemon argument)
+        repo: String,
+        #[arg(add = ArgValueCandidates::new(job_ids))]
+        job_id: String,
+        /// Run ID (defaults to the latest run)
+        run_id: Option<String>,
+    },
+    /// Summarize files written outside the workspace across audited runs
+    Audit {
+        /// Path to local repo or remote URL (same as the daemon argument)
+        repo: String,
+        #[arg(add = ArgValueCandidates::new(job_ids))]
+        job_id: String,
+    },
+    /// Import runs exported from another scheduler (CSV or JSON)
+    Import {
+        /// Path to local repo or remote URL (same as the daemon argument)
+        repo: String,
+        #[arg(add = ArgValueCandidates::new(job_ids))]
+        job_id: String,
+        /// Exported history file
+        file: PathBuf,
+        /// Input format [default: from the file extension]
+        #[arg(long, value_enum)]
+        format: Option<ImportFormat>,
+        /// Parse and report without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+pub fn run(cmd: HistoryCommand) -> Result<()> {
+    match cmd {
+        HistoryCommand::List { repo, job_id, limit } => list(&repo, &job_id, limit),
+        HistoryCommand::Show { repo, job_id, run_id } => show(&repo, &job_id, run_id.as_deref()),
+        HistoryCommand::Audit { repo, job_id } => audit(&repo, &job_id),
+        HistoryCommand::Import { repo, job_id, file, format, dry_run } => {
+            import(&repo, &job_id, &file, format, dry_run)
+        }
+    }
+}
+
+fn list(repo: &str, job_id: &str, limit: usize) -> Result<()> {
+    let dir = git::get_history_dir_for_source(&git::resolve_source(repo)?, job_id);
+    let runs = history::read_runs(&dir)?;
+    if runs.is_empty() {
+        println!("No runs recorded for '{}'", job_id);
+        return Ok(());
+    }
+
+    println!(
+        "{:<28} {:<25} {:<11} {:<8} {:>8} {:>10} {:>5}",
+        "RUN ID", "STARTED", "OUTCOME", "TRIGGER", "ATTEMPTS", "DURATION", "EXIT"
+    );
+    for run in runs.iter().rev().take(limit) {
+        println!(
+            "{:<28} {:<25} {:<11} {:<8} {:>8} {:>10} {:>5}",
+            run.run_id,
+            run.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            outcome_label(run),
+            run.trigger.map_or("-", |t| t.as_str()),
+            run.attempts,
+            format!("{:.3}s", run.duration.as_secs_f64()),
+            run.exit_code.map_or("-".to_string(), |c| c.to_string()),
+        );
+    }
+    if let Some(trend) = degrading(&runs) {
+        println!("\nDegrading: {}", trend);
+    }
+    Ok(())
+}
+
+fn show(repo: &str, job_id: &str, run_id: Option<&str>) -> Result<()> {
+    let dir = git::get_history_dir_for_source(&git::resolve_source(repo)?, job_id);
+    let runs = history::read_runs(&dir)?;
+    let run = match run_id {
+        Some(id) => runs.iter().find(|r| r.run_id == id),
+        None => runs.last(),
+    };
+    let Some(run) = run else {
+        anyhow::bail!("No matching run recorded for '{}'", job_id);
+    };
+
+    match run.run_number {
+        Some(number) => println!("Run:      {} (#{})", run.run_id, number),
+        None => println!("Run:      {}", run.run_id),
+    }
+    println!("Job:      {}", run.job_id);
+    println!("Started:  {}", run.started_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    println!("Duration: {:.3}s", run.duration.as_secs_f64());
+    println!("Outcome:  {}", outcome_label(run));
+    println!("Attempts: {}", run.attempts);
+    if let Some(trigger) = run.trigger {
+        if run.overrides.is_empty() {
+            println!("Trigger:  {}", trigger.as_str());
+        } else {
+            println!("Trigger:  {} (overriding {})", trigger.as_str(), run.overrides.join(", "));
+        }
+    }
+    if let Some(commit) = &run.commit {
+        println!("Commit:   {}", commit);
+    }
+    if let Some(code) = run.exit_code {
+        println!("Exit:     {}", code);
+    }
+    if let Some(error) = &run.error {
+        println!("Error:    {}", error);
+    }
+    if let Some(class) = run.failure_class {
+        println!("Category: {}", class.as_str());
+    }
+    if let Some(t) = &run.timings {
+        println!("Phases:");
+        for (label, d) in [
+            ("queued", t.queue_wait),
+            ("notify start", t.notify_start),
+            ("env load", t.env_load),
+            ("execution", t.execution),
+            ("retry wait", t.retry_wait),
+            ("notify", t.notify),
+        ] {
+            println!("  {:<14}{:.3}s", label, d.as_secs_f64());
+        }
+        println!("  {:<14}{:.3}s (all but execution and queue)", "overhead", t.overhead().as_secs_f64());
+    }
+    if !run.outside_writes.is_empty() {
+        println!("Outside writes:");
+        for path in &run.outside_writes {
+            println!("  {}", path);
+        }
+    }
+    if !run.artifacts.is_empty() {
+        let folder = artifacts_dir(&dir, &run.run_id);
+        if folder.exists() {
+            println!("Artifacts: {}", folder.display());
+        } else {
+            println!("Artifacts: (removed, beyond `keep`)");
+        }
+        for path in &run.artifacts {
+            println!("  {}", path);
+        }
+    }
+
+    match history::read_output(&dir, &run.run_id)? {
+        Some(output) => {
+            println!();
+            std::io::stdout().write_all(&output)?;
+            println!();
+        }
+        None => println!("\n(output not retained)"),
+    }
+    Ok(())
+}
+
+fn audit(repo: &str, job_id: &str) -> Result<()> {
+    let dir = git::get_history_dir_for_source(&git::resolve_source(repo)?, job_id);
+    let runs = history::read_runs(&dir)?;
+
+    // path -> (number of runs, last run ID)
+    let mut paths: BTreeMap<&str, (usize, &str)> = BTreeMap::new();
+    for run in &runs {
+        for path in &run.outside_writes {
+            let entry = paths.entry(path).or_insert((0, ""));
+            entry.0 += 1;
+            entry.1 = &run.run_id;
+        }
+    }
+
+    if paths.is_empty() {
+        println!(
+            "No writes outside the workspace recorded for '{}' ({} runs; enable `audit: true` to trace)",
+            job_id,
+            runs.len()
+        );
+        return Ok(());
+    }
+
+    println!("{:>5} {:<28} PATH", "RUNS", "LAST RUN");
+    for (path, (count, last)) in paths {
+        println!("{:>5} {:<28} {}", count, last, path);
+    }
+    Ok(())
+}
+
+fn import(repo: &str, job_id: &str, file: &Path, format: Option<ImportFormat>, dry_run: bool) -> Result<()> {
+    let content =
+        std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let format = format.unwrap_or_else(|| ImportFormat::from_path(file));
+    let runs = import::parse(&content, format, job_id)
+        .with_context(|| format!("Failed to parse {}", file.display()))?;
+
+    let total = runs.len();
+    let (Some(first), Some(last)) = (runs.iter().map(|r| r.started_at).min(), runs.iter().map(|r| r.started_at).max())
+    else {
+        println!("No runs found in {}", file.display());
+        return Ok(());
+    };
+    let failed = runs.iter().filter(|r| !r.is_success()).count();
+    println!(
+        "Parsed {} runs ({} failed) from {} to {}",
+        total,
+        failed,
+        first.format("%Y-%m-%d %H:%M:%S UTC"),
+        last.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    if dry_run {
+        return Ok(());
+    }
+
+    let dir = git::get_history_dir_for_source(&git::resolve_source(repo)?, job_id);
+    let added = history::import(&dir, runs)?;
+    println!(
+        "Imported {} runs into {} ({} already recorded)",
+        added,
+        dir.display(),
+        total - added
+    );
+    Ok(())
+}
+
+fn outcome_label(run: &JobRunReport) -> String {
+    if run.fallback {
+        format!("{} (fallback)", run.outcome.as_str())
+    } else if run.imported {
+        format!("{} (imported)", run.outcome.as_str())
+    } else {
+        run.outcome.as_str().to_string()
+    }
+}
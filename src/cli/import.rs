@@ -0,0 +1,266 @@
+//! Parsing run history exported from another scheduler.
+//!
+//! Accepts CSV with a header row, a JSON array of objects, or JSON Lines.
+//! Each record needs a start time and either an outcome or an exit code;
+//! common column names from other tools are recognized.
+
+use crate::actor::job::report::{generate_run_id, RunOutcome};
+use crate::actor::job::JobRunReport;
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use clap::ValueEnum;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+const STARTED_AT: &[&str] = &["started_at", "start_time", "start", "started"];
+const FINISHED_AT: &[&str] = &["finished_at", "end_time", "end", "finished"];
+const DURATION_MS: &[&str] = &["duration_ms"];
+const DURATION_SECS: &[&str] = &["duration_secs", "duration_seconds", "duration"];
+const OUTCOME: &[&str] = &["outcome", "status", "result", "state"];
+const EXIT_CODE: &[&str] = &["exit_code", "exit", "exit_status", "return_code"];
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ImportFormat {
+    Csv,
+    Json,
+}
+
+impl ImportFormat {
+    /// Guesses the format from the file extension (`.csv` or JSON otherwise).
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ImportFormat::Csv,
+            _ => ImportFormat::Json,
+        }
+    }
+}
+
+type Record = HashMap<String, Value>;
+
+/// Parses exported runs into reports for `job_id` (marked as imported).
+pub fn parse(content: &str, format: ImportFormat, job_id: &str) -> Result<Vec<JobRunReport>> {
+    let records = match format {
+        ImportFormat::Csv => csv_records(content)?,
+        ImportFormat::Json => json_records(content)?,
+    };
+    records
+        .iter()
+        .enumerate()
+        .map(|(i, record)| to_report(record, job_id).with_context(|| format!("record {}", i + 1)))
+        .collect()
+}
+
+fn to_report(record: &Record, job_id: &str) -> Result<JobRunReport> {
+    let started_at = field(record, STARTED_AT)
+        .map(parse_time)
+        .transpose()?
+        .with_context(|| format!("missing start time (one of: {})", STARTED_AT.join(", ")))?;
+
+    let duration = if let Some(ms) = field(record, DURATION_MS) {
+        Duration::from_millis(number(ms).context("invalid duration_ms")? as u64)
+    } else if let Some(secs) = field(record, DURATION_SECS) {
+        Duration::from_secs_f64(number(secs).filter(|s| *s >= 0.0).context("invalid duration")?)
+    } else if let Some(end) = field(record, FINISHED_AT) {
+        (parse_time(end)? - started_at).to_std().unwrap_or_default()
+    } else {
+        Duration::ZERO
+    };
+
+    let exit_code = field(record, EXIT_CODE)
+        .map(|v| number(v).map(|n| n as i32).context("invalid exit code"))
+        .transpose()?;
+    let outcome = match field(record, OUTCOME) {
+        Some(value) => parse_outcome(value)?,
+        None => match exit_code {
+            Some(0) => RunOutcome::Success,
+            Some(_) => RunOutcome::Failed,
+            None => anyhow::bail!("missing outcome or exit code"),
+        },
+    };
+
+    Ok(JobRunReport {
+        job_id: job_id.to_string(),
+        run_id: generate_run_id(started_at),
+        run_number: None,
+        commit: None,
+        started_at,
+        duration,
+        attempts: 1,
+        outcome,
+        exit_code,
+        error: (outcome != RunOutcome::Success).then(|| format!("imported run ({})", outcome.as_str())),
+        failure_class: None,
+        fallback: false,
+        stdout_tail: String::new(),
+        stderr_tail: String::new(),
+        omitted_bytes: 0,
+        outside_writes: Vec::new(),
+        imported: true,
+        timings: None,
+        trigger: None,
+        overrides: Vec::new(),
+        artifacts: Vec::new(),
+    })
+}
+
+/// First non-empty value among `names` (case-insensitive column match).
+fn field<'a>(record: &'a Record, names: &[&str]) -> Option<&'a Value> {
+    names.iter().find_map(|name| {
+        record
+            .iter()
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+            .filter(|v| !v.is_null() && v.as_str().is_none_or(|s| !s.trim().is_empty()))
+    })
+}
+
+fn number(value: &Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+/// RFC 3339, `YYYY-MM-DD HH:MM:SS[.f]` (UTC), or a Unix timestamp (seconds or milliseconds).
+fn parse_time(value: &Value) -> Result<DateTime<Utc>> {
+    if let Some(n) = number(value) {
+        let ms = if n.abs() >= 1e12 { n } else { n * 1000.0 };
+        return DateTime::from_timestamp_millis(ms as i64).context("timestamp out of range");
+    }
+    let s = value.as_str().context("time must be a string or number")?.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .map(|t| t.and_utc())
+        .with_context(|| format!("invalid time '{}'", s))
+}
+
+fn parse_outcome(value: &Value) -> Result<RunOutcome> {
+    if let Some(n) = value.as_i64() {
+        return Ok(if n == 0 { RunOutcome::Success } else { RunOutcome::Failed });
+    }
+    let s = value.as_str().context("outcome must be a string")?.trim().to_ascii_lowercase();
+    Ok(match s.as_str() {
+        "success" | "succeeded" | "ok" | "passed" | "completed" | "true" | "0" => RunOutcome::Success,
+        "failed" | "failure" | "fail" | "error" | "false" => RunOutcome::Failed,
+        "timeout" | "timed_out" | "timedout" => RunOutcome::Timeout,
+        "oom" | "oom_killed" => RunOutcome::OomKilled,
+        "exec_error" => RunOutcome::ExecError,
+        "interrupted" | "aborted" => RunOutcome::Interrupted,
+        "skipped" | "skip" => RunOutcome::Skipped,
+        other => anyhow::bail!("unknown outcome '{}'", other),
+    })
+}
+
+fn json_records(content: &str) -> Result<Vec<Record>> {
+    let content = content.trim_start();
+    if content.starts_with('[') {
+        return serde_json::from_str(content).context("invalid JSON (expected an array of objects)");
+    }
+    // JSON Lines
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).with_context(|| format!("invalid JSON on line {}", i + 1)))
+        .collect()
+}
+
+fn csv_records(content: &str) -> Result<Vec<Record>> {
+    let mut rows = csv_rows(content)?.into_iter();
+    let header = rows.next().context("empty CSV")?;
+    Ok(rows
+        .filter(|row| row.iter().any(|cell| !cell.is_empty()))
+        .map(|row| {
+            header
+                .iter()
+                .cloned()
+                .zip(row.into_iter().map(Value::String))
+                .collect()
+        })
+        .collect())
+}
+
+/// Splits CSV into rows of cells (RFC 4180 quoting, `\n` or `\r\n` line ends).
+fn csv_rows(content: &str) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if cell.is_empty() => in_quotes = true,
+            (',', false) => row.push(std::mem::take(&mut cell)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => cell.push(c),
+        }
+    }
+    if in_quotes {
+        anyhow::bail!("unterminated quoted field");
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn csv_with_quotes_and_aliases() {
+        let csv = "start_time,duration,status,note\r\n\
+                   2024-11-02 03:00:00,12.5,ok,\"multi\nline, \"\"quoted\"\"\"\r\n\
+                   1730602800,3,FAILED,\r\n";
+        let runs = parse(csv, ImportFormat::Csv, "etl").unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].started_at, Utc.with_ymd_and_hms(2024, 11, 2, 3, 0, 0).unwrap());
+        assert_eq!(runs[0].duration, Duration::from_millis(12500));
+        assert_eq!(runs[0].outcome, RunOutcome::Success);
+        assert!(runs[0].imported);
+        assert_eq!(runs[1].started_at, Utc.with_ymd_and_hms(2024, 11, 3, 3, 0, 0).unwrap());
+        assert_eq!(runs[1].outcome, RunOutcome::Failed);
+        assert_eq!(runs[1].job_id, "etl");
+    }
+
+    #[test]
+    fn json_array_and_lines() {
+        let array = r#"[{"started_at": "2024-11-02T03:00:00+09:00", "finished_at": "2024-11-02T03:01:00+09:00", "exit_code": 2}]"#;
+        let runs = parse(array, ImportFormat::Json, "etl").unwrap();
+        assert_eq!(runs[0].started_at, Utc.with_ymd_and_hms(2024, 11, 1, 18, 0, 0).unwrap());
+        assert_eq!(runs[0].duration, Duration::from_secs(60));
+        assert_eq!(runs[0].outcome, RunOutcome::Failed);
+        assert_eq!(runs[0].exit_code, Some(2));
+
+        let lines = "{\"start\": 1730516400000, \"duration_ms\": 250, \"result\": \"timeout\"}\n\n";
+        let runs = parse(lines, ImportFormat::Json, "etl").unwrap();
+        assert_eq!(runs[0].started_at, Utc.with_ymd_and_hms(2024, 11, 2, 3, 0, 0).unwrap());
+        assert_eq!(runs[0].outcome, RunOutcome::Timeout);
+    }
+
+    #[test]
+    fn errors_name_the_record() {
+        let err = parse("start,status\n2024-11-02 03:00:00,ok\nyesterday,ok\n", ImportFormat::Csv, "etl")
+            .unwrap_err();
+        assert_eq!(format!("{:#}", err), "record 2: invalid time 'yesterday'");
+
+        let err = parse(r#"[{"start": 1730516400}]"#, ImportFormat::Json, "etl").unwrap_err();
+        assert!(format!("{:#}", err).contains("missing outcome or exit code"));
+    }
+}
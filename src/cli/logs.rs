@@ -0,0 +1,72 @@
+//! `rollcron logs`: output of a job's latest run, from the daemon.
+//!
+//! With `-f` the output of a run in progress is streamed as it is written
+//! (`GET /jobs/<id>/output/stream`) until the run ends.
+
+use super::completions::job_ids;
+use crate::api;
+use anyhow::{Context, Result};
+use clap::Args;
+use clap_complete::ArgValueCandidates;
+use serde_json::Value;
+use std::io::Write;
+
+#[derive(Args)]
+pub struct LogsArgs {
+    #[arg(add = ArgValueCandidates::new(job_ids))]
+    job_id: String,
+
+    /// Stream the output of the run in progress until it ends
+    #[arg(short, long)]
+    follow: bool,
+
+    /// Address of the daemon's control API
+    #[arg(long, default_value = api::DEFAULT_ADDR)]
+    addr: String,
+
+    /// Bearer token, if the daemon was started with `--api-token`
+    #[arg(long, env = "ROLLCRON_API_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+}
+
+pub async fn run(args: LogsArgs) -> Result<()> {
+    let latest: Value = get(&args, &format!("/jobs/{}/output", args.job_id)).await?.json().await?;
+    if latest["started_at"].is_null() {
+        anyhow::bail!("'{}' has not run since the daemon started", args.job_id);
+    }
+    let running = latest["running"] == true;
+    if !args.follow || !running {
+        if args.follow {
+            eprintln!("rollcron: '{}' is not running; output of its last run:", args.job_id);
+        }
+        print!("{}", latest["output"].as_str().unwrap_or_default());
+        return Ok(());
+    }
+
+    // Everything retained from the start of the run, then the rest live
+    let mut resp = get(&args, &format!("/jobs/{}/output/stream", args.job_id)).await?;
+    let mut stdout = std::io::stdout();
+    while let Some(chunk) = resp.chunk().await.context("Lost the connection to the daemon")? {
+        stdout.write_all(&chunk)?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+async fn get(args: &LogsArgs, path: &str) -> Result<reqwest::Response> {
+    let addr = &args.addr;
+    let mut request = reqwest::Client::new().get(format!("http://{}{}", addr, path));
+    if let Some(token) = &args.token {
+        request = request.bearer_auth(token);
+    }
+    let resp = request
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach rollcron at {} (is the daemon running?)", addr))?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body: Value = resp.json().await.unwrap_or_default();
+        anyhow::bail!("{}", body["error"].as_str().unwrap_or(status.as_str()));
+    }
+    Ok(resp)
+}
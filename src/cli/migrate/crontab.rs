@@ -0,0 +1,469 @@
+//! Crontab files (vixie cron / cronie syntax) to rollcron jobs and back.
+//!
+//! Import: `NAME=value` lines before the first job become `runner` settings
+//! (`CRON_TZ`/`TZ` the timezone, `SHELL` the shell, the rest `runner.env`);
+//! later ones apply to the jobs below them. A comment right above a job
+//! becomes its name. In system crontabs, the user field becomes `user` (unless
+//! root). Export writes one line per job that `cd`s into the repository
+//! checkout first.
+
+use super::{job_id, Converted};
+use crate::config::{CommandLine, EnvValue, Job, RunnerConfig, Shell, TimezoneConfig};
+use anyhow::Result;
+use serde_yaml::{Mapping, Value};
+use std::path::Path;
+use std::str::FromStr;
+
+/// `@` shortcuts with a five-field equivalent; `@reboot` is kept (rollcron runs
+/// such jobs at startup)
+const SHORTCUTS: [(&str, &str); 8] = [
+    ("@yearly", "0 0 1 1 *"),
+    ("@annually", "0 0 1 1 *"),
+    ("@monthly", "0 0 1 * *"),
+    ("@weekly", "0 0 * * 0"),
+    ("@daily", "0 0 * * *"),
+    ("@midnight", "0 0 * * *"),
+    ("@hourly", "0 * * * *"),
+    ("@reboot", "@reboot"),
+];
+
+/// Settings an assignment line changes for the jobs after it
+#[derive(Debug, Default, Clone)]
+struct Scope {
+    timezone: Option<String>,
+    shell: Option<String>,
+    env: Mapping,
+}
+
+/// Converts a crontab. `system`: lines carry a user field (`/etc/crontab`, `cron.d`).
+pub(super) fn convert(content: &str, system: bool) -> Result<Converted> {
+    let mut converted = Converted::default();
+    let mut runner = Scope::default();
+    let mut scope = Scope::default();
+    let mut comment: Option<String> = None;
+    let mut seen_job = false;
+
+    for (i, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        let lineno = i + 1;
+        if line.is_empty() {
+            comment = None;
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            let text = text.trim();
+            // A commented-out job is not a name
+            let is_job = text.starts_with(|c: char| c.is_ascii_digit() || c == '*' || c == '@');
+            comment = (!text.is_empty() && !is_job).then(|| text.to_string());
+            continue;
+        }
+        if let Some((name, value)) = assignment(line) {
+            let target = if seen_job { &mut scope } else { &mut runner };
+            assign(target, name, value, lineno, &mut converted);
+            continue;
+        }
+
+        seen_job = true;
+        match parse_entry(line, system) {
+            Ok((schedule, user, command)) => {
+                let id = unique_id(&converted, &id_from_command(command));
+                let mut job = convert_job(&id, &schedule, command, comment.take(), &scope, &mut converted);
+                if let Some(user) = user.filter(|user| *user != "root") {
+                    job.insert("user".into(), user.into());
+                }
+                converted.jobs.insert(id.into(), Value::Mapping(job));
+            }
+            Err(e) => converted.warnings.push(format!("line {}: {}, skipping", lineno, e)),
+        }
+        comment = None;
+    }
+
+    if !converted.jobs.is_empty() {
+        converted.warnings.push(
+            "cron starts overlapping runs; rollcron skips a run while the previous one is still running \
+             (set `concurrency: parallel` to keep cron's behavior)"
+                .to_string(),
+        );
+    }
+    let mut settings = Mapping::new();
+    if let Some(tz) = runner.timezone {
+        settings.insert("timezone".into(), tz.into());
+    }
+    if let Some(shell) = runner.shell {
+        settings.insert("shell".into(), shell.into());
+    }
+    if !runner.env.is_empty() {
+        settings.insert("env".into(), Value::Mapping(runner.env));
+    }
+    converted.runner = settings;
+    Ok(converted)
+}
+
+/// `NAME=value`, `NAME = "value"`
+fn assignment(line: &str) -> Option<(&str, &str)> {
+    let (name, value) = line.split_once('=')?;
+    let name = name.trim();
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return None;
+    }
+    let value = value.trim();
+    let unquoted = ['"', '\'']
+        .iter()
+        .find_map(|q| value.strip_prefix(*q).and_then(|v| v.strip_suffix(*q)))
+        .unwrap_or(value);
+    Some((name, unquoted))
+}
+
+fn assign(scope: &mut Scope, name: &str, value: &str, lineno: usize, converted: &mut Converted) {
+    match name {
+        "CRON_TZ" | "TZ" => scope.timezone = Some(value.to_string()),
+        "SHELL" => match Path::new(value).file_name().and_then(|n| n.to_str()) {
+            Some(shell @ ("sh" | "bash" | "zsh")) => scope.shell = Some(shell.to_string()),
+            _ => converted.warnings.push(format!("line {}: SHELL={} is not supported, using sh", lineno, value)),
+        },
+        "MAILTO" | "MAILFROM" => converted
+            .warnings
+            .push(format!("line {}: {} is not converted; configure webhooks for failure alerts", lineno, name)),
+        _ => {
+            scope.env.insert(name.into(), value.into());
+        }
+    }
+}
+
+/// Schedule, user (system crontabs) and command of a job line.
+fn parse_entry(line: &str, system: bool) -> Result<(String, Option<&str>, &str)> {
+    let (schedule, rest) = if line.starts_with('@') {
+        let (shortcut, rest) = split_field(line).ok_or_else(|| anyhow::anyhow!("missing command"))?;
+        let expr = SHORTCUTS
+            .iter()
+            .find(|(name, _)| *name == shortcut)
+            .map(|(_, expr)| expr.to_string())
+            .ok_or_else(|| anyhow::anyhow!("unknown shortcut {}", shortcut))?;
+        (expr, rest)
+    } else {
+        let mut fields = Vec::with_capacity(5);
+        let mut rest = line;
+        for _ in 0..5 {
+            let (field, tail) = split_field(rest).ok_or_else(|| anyhow::anyhow!("expected 5 schedule fields and a command"))?;
+            fields.push(field);
+            rest = tail;
+        }
+        (fields.join(" "), rest)
+    };
+    if schedule != "@reboot" {
+        croner::Cron::from_str(&schedule).map_err(|e| anyhow::anyhow!("invalid schedule '{}': {}", schedule, e))?;
+    }
+    let (user, command) = match system {
+        true => split_field(rest)
+            .map(|(user, command)| (Some(user), command))
+            .ok_or_else(|| anyhow::anyhow!("missing user or command"))?,
+        false => (None, rest),
+    };
+    if command.is_empty() {
+        anyhow::bail!("missing command");
+    }
+    Ok((schedule, user, command))
+}
+
+/// First whitespace-separated field and the rest, trimmed.
+fn split_field(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    (end > 0).then(|| (&s[..end], s[end..].trim_start()))
+}
+
+fn convert_job(
+    id: &str,
+    schedule: &str,
+    command: &str,
+    name: Option<String>,
+    scope: &Scope,
+    converted: &mut Converted,
+) -> Mapping {
+    let (command, stdin) = split_percent(command);
+    if stdin {
+        converted.warn(id, "input after an unescaped % (stdin) is dropped");
+    }
+
+    let mut job = Mapping::new();
+    if let Some(name) = name {
+        job.insert("name".into(), name.into());
+    }
+    job.insert(
+        "schedule".into(),
+        match &scope.timezone {
+            Some(tz) => Value::Mapping(Mapping::from_iter([
+                ("cron".into(), schedule.into()),
+                ("timezone".into(), tz.as_str().into()),
+            ])),
+            None => schedule.into(),
+        },
+    );
+    job.insert("run".into(), command.into());
+    if let Some(shell) = &scope.shell {
+        job.insert("shell".into(), shell.as_str().into());
+    }
+    if !scope.env.is_empty() {
+        job.insert("env".into(), Value::Mapping(scope.env.clone()));
+    }
+    job
+}
+
+/// Cron turns unescaped `%` into newlines and feeds what follows the first
+/// one to stdin. Returns the command with `\%` unescaped, and whether there was stdin.
+fn split_percent(command: &str) -> (String, bool) {
+    let mut out = String::new();
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'%') => out.push(chars.next().unwrap_or('%')),
+            '%' => return (out.trim_end().to_string(), true),
+            c => out.push(c),
+        }
+    }
+    (out, false)
+}
+
+/// Job ID from the program a command runs: `cd /srv && ./backup.sh` is `backup`.
+fn id_from_command(command: &str) -> String {
+    let program = command
+        .split(['&', ';', '|'])
+        .map(str::trim)
+        .find(|part| !part.is_empty() && !part.starts_with("cd ") && *part != "cd")
+        .and_then(|part| part.split_whitespace().find(|word| assignment(word).is_none()))
+        .unwrap_or("job");
+    let base = Path::new(program).file_stem().and_then(|s| s.to_str()).unwrap_or("job");
+    let id = job_id(base.trim_matches('_'));
+    if id.is_empty() { "job".to_string() } else { id }
+}
+
+fn unique_id(converted: &Converted, base: &str) -> String {
+    (1..)
+        .map(|n| if n == 1 { base.to_string() } else { format!("{}-{}", base, n) })
+        .find(|id| !converted.jobs.contains_key(id.as_str()))
+        .unwrap_or_else(|| base.to_string())
+}
+
+/// Renders `jobs` as a crontab running each job from `dir` (the repository
+/// checkout on the cron host). Returns the crontab and what couldn't be exported.
+pub(super) fn export(runner: &RunnerConfig, jobs: &[Job], dir: &Path) -> (String, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut out = String::from("# Generated by `rollcron export crontab`\n");
+    if !runner.webhook.is_empty() {
+        warnings.push("webhook notifications are not exported".to_string());
+    }
+    if runner.env_file.is_some() || runner.env.is_some() {
+        warnings.push("runner env and env_file are not exported".to_string());
+    }
+
+    let mut jobs: Vec<&Job> = jobs.iter().collect();
+    jobs.sort_by(|a, b| a.id.cmp(&b.id));
+    let mut current_tz: Option<String> = None;
+    let mut uses_cron_tz = false;
+    let mut current_shell = "sh";
+    for job in jobs {
+        let mut warn = |message: &str| warnings.push(format!("{}: {}", job.id, message));
+        let Some(schedule) = five_fields(job.schedule_str()) else {
+            warn(&format!("schedule '{}' has no five-field equivalent, skipping", job.schedule_str()));
+            continue;
+        };
+        let shell = match job.shell {
+            Shell::Sh | Shell::None => "sh",
+            Shell::Bash => "/bin/bash",
+            Shell::Zsh => "/bin/zsh",
+            Shell::Cmd | Shell::Powershell => {
+                warn("Windows shells can't be exported, skipping");
+                continue;
+            }
+        };
+        for (unsupported, what) in [
+            (job.build.is_some(), "build command (run it once by hand)"),
+            (job.retry.is_some(), "retry"),
+            (job.fallback_command.is_some(), "fallback_command"),
+            (!job.env_files.is_empty() || job.env_file.is_some() || job.run_env_file.is_some(), "env files"),
+            (job.limits.is_some(), "limits"),
+            (job.user.is_some() || job.group.is_some(), "user/group (install the line in that user's crontab)"),
+            (!job.blackout.is_empty(), "blackout windows"),
+        ] {
+            if unsupported {
+                warn(&format!("{} not exported", what));
+            }
+        }
+
+        let tz = match job.timezone.as_ref() {
+            Some(TimezoneConfig::Utc) => Some("UTC".to_string()),
+            Some(TimezoneConfig::Named(tz)) => Some(tz.name().to_string()),
+            Some(TimezoneConfig::Inherit) | None => None,
+        };
+        if tz != current_tz {
+            match &tz {
+                Some(tz) => {
+                    out.push_str(&format!("CRON_TZ={}\n", tz));
+                    uses_cron_tz = true;
+                }
+                None => out.push_str("CRON_TZ=\n"),
+            }
+            current_tz = tz;
+        }
+        if shell != current_shell {
+            out.push_str(&format!("SHELL={}\n", if shell == "sh" { "/bin/sh" } else { shell }));
+            current_shell = shell;
+        }
+
+        let work_dir = match &job.working_dir {
+            Some(sub) => dir.join(&sub.path),
+            None => dir.to_path_buf(),
+        };
+        let mut env = String::new();
+        let vars = job.env.iter().flatten().chain(job.run_env.iter().flatten());
+        let mut vars: Vec<_> = vars.collect();
+        vars.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, value) in vars {
+            match value {
+                EnvValue::Plain(value) => env.push_str(&format!("{}={} ", key, quote(value))),
+                EnvValue::Secret(_) => warn(&format!("secret env {} not exported", key)),
+            }
+        }
+        let command = match &job.command {
+            CommandLine::Line(line) => line.clone(),
+            CommandLine::Argv(argv) => argv.iter().map(|a| quote(a)).collect::<Vec<_>>().join(" "),
+        };
+        let line = format!("cd {} && {}{}", quote(&work_dir.display().to_string()), env, command).replace('%', "\\%");
+
+        out.push_str(&format!("\n# {}: {}\n", job.id, job.name));
+        let disabled = if job.enabled { "" } else { "# (disabled) " };
+        out.push_str(&format!("{}{} {}\n", disabled, schedule, line));
+    }
+    if uses_cron_tz {
+        warnings.push("CRON_TZ is honored by cronie but not by vixie cron (rollcron schedules default to UTC)".to_string());
+    }
+    (out, warnings)
+}
+
+/// A schedule as five cron fields: seconds must be `0`, a year field `*`.
+fn five_fields(expr: &str) -> Option<String> {
+    if expr.starts_with('@') {
+        return Some(expr.to_string());
+    }
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    let fields = match fields.as_slice() {
+        [_, _, _, _, _] => &fields[..],
+        ["0", rest @ ..] if rest.len() == 5 => rest,
+        ["0", rest @ .., "*"] if rest.len() == 5 => rest,
+        _ => return None,
+    };
+    Some(fields.iter().map(|f| if *f == "?" { "*" } else { f }).collect::<Vec<_>>().join(" "))
+}
+
+/// Single-quotes `s` for sh unless it is made of safe characters only.
+fn quote(s: &str) -> String {
+    let safe = !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@".contains(c));
+    if safe { s.to_string() } else { format!("'{}'", s.replace('\'', "'\\''")) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    const CRONTAB: &str = r#"
+SHELL=/bin/bash
+PATH=/usr/local/bin:/usr/bin:/bin
+MAILTO=ops@example.com
+CRON_TZ=Asia/Tokyo
+
+# Nightly backup
+0 3 * * * cd /srv/app && ./scripts/backup.sh --full > /tmp/backup.log 2>&1
+*/5 * * * * /usr/bin/curl -fsS https://example.com/ping
+@daily /usr/local/bin/backup.sh
+# 0 * * * * old-job
+@reboot /usr/bin/start-thing
+LOG_LEVEL=debug
+15 * * * * date +\%Y-\%m-\%d%ignored
+not a cron line
+"#;
+
+    #[test]
+    fn imports_jobs_with_scoped_settings() {
+        let converted = convert(CRONTAB, false).unwrap();
+        let yaml = converted.to_yaml().unwrap();
+        let (runner, jobs) = parse_config(&yaml).unwrap();
+
+        assert_eq!(runner.shell, Shell::Bash);
+        assert!(matches!(runner.timezone, TimezoneConfig::Named(tz) if tz.name() == "Asia/Tokyo"));
+        assert!(runner.env.unwrap().contains_key("PATH"));
+
+        let ids: Vec<&str> = converted.jobs.keys().filter_map(Value::as_str).collect();
+        assert_eq!(ids, ["backup", "curl", "backup-2", "start-thing", "date"]);
+        let job = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert_eq!(job("backup").name, "Nightly backup");
+        assert_eq!(job("backup").command.to_string(), "cd /srv/app && ./scripts/backup.sh --full > /tmp/backup.log 2>&1");
+        assert_eq!(job("backup-2").schedule_str(), "0 0 * * *");
+        assert!(job("start-thing").schedule.is_none() && job("start-thing").run_on_start);
+        // `\%` unescaped, input after `%` dropped, later assignments scoped to later jobs
+        assert_eq!(job("date").command.to_string(), "date +%Y-%m-%d");
+        assert!(job("date").env.as_ref().unwrap().contains_key("LOG_LEVEL"));
+        assert!(job("backup").env.is_none());
+
+        let warnings = converted.warnings.join("\n");
+        assert!(warnings.contains("MAILTO"));
+        assert!(warnings.contains("date: input after an unescaped %"));
+        assert!(warnings.contains("line 15: expected 5 schedule fields"), "{}", warnings);
+    }
+
+    #[test]
+    fn system_crontab_keeps_user_field() {
+        let crontab = "17 * * * * root cd / && run-parts /etc/cron.hourly\n30 2 * * * backup /usr/local/bin/dump.sh\n";
+        let converted = convert(crontab, true).unwrap();
+        let (_, jobs) = parse_config(&converted.to_yaml().unwrap()).unwrap();
+        assert_eq!(jobs[0].id, "run-parts");
+        assert_eq!(jobs[0].command.to_string(), "cd / && run-parts /etc/cron.hourly");
+        assert_eq!(jobs[0].user, None);
+        assert_eq!(jobs[1].id, "dump");
+        assert_eq!(jobs[1].user.as_deref(), Some("backup"));
+    }
+
+    #[test]
+    fn exports_jobs_from_checkout_dir() {
+        let yaml = r#"
+runner:
+  timezone: inherit
+jobs:
+  report:
+    name: Daily report
+    schedule: "30 6 * * 1-5"
+    run:
+      sh: ["python3", "report.py", "--since", "1 day"]
+    working_dir: scripts
+    env: { MODE: "full run" }
+  tokyo:
+    schedule:
+      cron: "0 0 9 * * *"
+      timezone: Asia/Tokyo
+    run: date +%F
+    enabled: false
+  fast:
+    schedule: "*/10 * * * * *"
+    run: echo
+"#;
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        let (crontab, warnings) = export(&runner, &jobs, Path::new("/srv/my repo"));
+        assert!(
+            crontab.contains("\n# report: Daily report\n30 6 * * 1-5 cd '/srv/my repo/scripts' && MODE='full run' python3 report.py --since '1 day'\n"),
+            "{}",
+            crontab
+        );
+        assert!(crontab.contains("CRON_TZ=Asia/Tokyo\n\n# tokyo: tokyo\n# (disabled) 0 9 * * * cd '/srv/my repo' && date +\\%F\n"), "{}", crontab);
+        assert!(!crontab.contains("fast"));
+        assert!(warnings.iter().any(|w| w.starts_with("fast: schedule")));
+    }
+
+    #[test]
+    fn five_field_conversion() {
+        assert_eq!(five_fields("0 0 19 ? * THU *").as_deref(), Some("0 19 * * THU"));
+        assert_eq!(five_fields("@hourly").as_deref(), Some("@hourly"));
+        assert_eq!(five_fields("30 * * * * *"), None);
+    }
+}
@@ -0,0 +1,431 @@
+//! Kubernetes CronJob manifests (`batch/v1`) to rollcron jobs.
+//!
+//! The first container's `command` + `args` become the run command, executed
+//! on the host: the image, volumes and anything resolved by the cluster
+//! (`valueFrom`, `envFrom`) are dropped with a warning.
+
+use super::{duration, job_id, Converted};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CronJob {
+    #[serde(default)]
+    metadata: Metadata,
+    spec: CronJobSpec,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Metadata {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CronJobSpec {
+    schedule: String,
+    time_zone: Option<String>,
+    concurrency_policy: Option<String>,
+    suspend: Option<bool>,
+    job_template: JobTemplate,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobTemplate {
+    spec: JobSpec,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JobSpec {
+    backoff_limit: Option<u32>,
+    active_deadline_seconds: Option<u64>,
+    template: PodTemplate,
+}
+
+#[derive(Debug, Deserialize)]
+struct PodTemplate {
+    spec: PodSpec,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PodSpec {
+    containers: Vec<Container>,
+    #[serde(default)]
+    init_containers: Vec<Value>,
+    #[serde(default)]
+    volumes: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Container {
+    image: Option<String>,
+    #[serde(default)]
+    command: Vec<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: Vec<EnvVar>,
+    #[serde(default)]
+    env_from: Vec<Value>,
+    working_dir: Option<String>,
+    resources: Option<Resources>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnvVar {
+    name: String,
+    value: Option<String>,
+    value_from: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Resources {
+    #[serde(default)]
+    limits: BTreeMap<String, Value>,
+}
+
+/// Converts every CronJob in `content`; other kinds are skipped with a warning.
+pub(super) fn convert(content: &str) -> Result<Converted> {
+    let mut converted = Converted::default();
+    let mut manifests = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = Value::deserialize(document).context("Failed to parse YAML")?;
+        collect(value, &mut manifests);
+    }
+
+    for (i, manifest) in manifests.into_iter().enumerate() {
+        let kind = manifest.get("kind").and_then(Value::as_str).unwrap_or("<none>");
+        if kind != "CronJob" {
+            converted.warnings.push(format!("document {}: skipping kind {}", i + 1, kind));
+            continue;
+        }
+        let cron_job: CronJob =
+            serde_yaml::from_value(manifest).with_context(|| format!("document {}: invalid CronJob", i + 1))?;
+        let name = cron_job.metadata.name.clone().ok_or_else(|| anyhow!("document {}: CronJob has no metadata.name", i + 1))?;
+        let id = job_id(&name);
+        if converted.jobs.contains_key(id.as_str()) {
+            converted.warn(&id, "duplicate job ID, skipping");
+            continue;
+        }
+        match convert_job(&id, cron_job, &mut converted) {
+            Ok(job) => {
+                converted.jobs.insert(id.into(), Value::Mapping(job));
+            }
+            Err(e) => converted.warn(&id, format!("{:#}, skipping", e)),
+        }
+    }
+    Ok(converted)
+}
+
+/// Flattens `kind: List` into its items.
+fn collect(value: Value, out: &mut Vec<Value>) {
+    match value.get("kind").and_then(Value::as_str) {
+        Some("List") => {
+            if let Some(Value::Sequence(items)) = value.get("items") {
+                for item in items {
+                    collect(item.clone(), out);
+                }
+            }
+        }
+        _ if value.is_null() => {}
+        _ => out.push(value),
+    }
+}
+
+fn convert_job(id: &str, cron_job: CronJob, converted: &mut Converted) -> Result<Mapping> {
+    let spec = cron_job.spec;
+    let job_spec = spec.job_template.spec;
+    let pod = job_spec.template.spec;
+    let mut containers = pod.containers.into_iter();
+    let container = containers.next().context("no containers")?;
+    if containers.next().is_some() {
+        converted.warn(id, "only the first container is converted");
+    }
+    if !pod.init_containers.is_empty() {
+        converted.warn(id, "initContainers are not converted");
+    }
+    if !pod.volumes.is_empty() {
+        converted.warn(id, "volumes are not mounted; paths refer to the host");
+    }
+    if let Some(image) = &container.image {
+        converted.warn(id, format!("image '{}' is not used; the command runs on the host", image));
+    }
+
+    let argv: Vec<String> = container.command.into_iter().chain(container.args).collect();
+    if argv.is_empty() {
+        anyhow::bail!("container relies on the image entrypoint (no command or args)");
+    }
+
+    let mut job = Mapping::new();
+    job.insert(
+        "schedule".into(),
+        match spec.time_zone {
+            Some(tz) => Value::Mapping(Mapping::from_iter([
+                ("cron".into(), spec.schedule.into()),
+                ("timezone".into(), tz.into()),
+            ])),
+            None => spec.schedule.into(),
+        },
+    );
+
+    let mut run = Mapping::new();
+    run.insert("sh".into(), command(argv));
+    if let Some(secs) = job_spec.active_deadline_seconds {
+        run.insert("timeout".into(), duration(secs).into());
+    }
+    // Kubernetes defaults to Allow; rollcron defaults to skip
+    let concurrency = match spec.concurrency_policy.as_deref() {
+        None | Some("Allow") => "parallel",
+        Some("Forbid") => "skip",
+        Some("Replace") => "replace",
+        Some(other) => anyhow::bail!("unknown concurrencyPolicy '{}'", other),
+    };
+    if concurrency != "skip" {
+        run.insert("concurrency".into(), concurrency.into());
+    }
+    if let Some(max) = job_spec.backoff_limit.filter(|n| *n > 0) {
+        run.insert("retry".into(), Value::Mapping(Mapping::from_iter([("max".into(), max.into())])));
+    }
+    job.insert(
+        "run".into(),
+        match run.len() {
+            1 => run.remove("sh").unwrap_or_default(),
+            _ => Value::Mapping(run),
+        },
+    );
+
+    if spec.suspend == Some(true) {
+        job.insert("enabled".into(), false.into());
+    }
+    match container.working_dir {
+        // An image path, not one on the host
+        Some(dir) if dir.starts_with('/') => {
+            converted.warn(id, format!("workingDir {} is an image path; the command runs in the job directory", dir))
+        }
+        Some(dir) => {
+            job.insert("working_dir".into(), dir.into());
+        }
+        None => {}
+    }
+
+    let mut env = Mapping::new();
+    for var in container.env {
+        match (var.value, var.value_from) {
+            (_, Some(_)) => converted.warn(id, format!("env {} uses valueFrom and is not converted", var.name)),
+            (value, None) => {
+                env.insert(var.name.into(), value.unwrap_or_default().into());
+            }
+        }
+    }
+    if !container.env_from.is_empty() {
+        converted.warn(id, "envFrom is not converted");
+    }
+    if !env.is_empty() {
+        job.insert("env".into(), Value::Mapping(env));
+    }
+
+    if let Some(resources) = container.resources {
+        let mut limits = Mapping::new();
+        for (key, quantity) in resources.limits {
+            let text = quantity_text(&quantity);
+            let value: Option<Value> = match key.as_str() {
+                "memory" => memory(&text).map(Value::from),
+                "cpu" => cpu(&text).map(Value::from),
+                _ => {
+                    converted.warn(id, format!("limit {} is not converted", key));
+                    continue;
+                }
+            };
+            match value {
+                Some(value) => {
+                    limits.insert(key.into(), value);
+                }
+                None => converted.warn(id, format!("invalid {} limit '{}'", key, text)),
+            }
+        }
+        if !limits.is_empty() {
+            job.insert("limits".into(), Value::Mapping(limits));
+        }
+    }
+
+    Ok(job)
+}
+
+/// `sh -c 'script'` becomes a command line, anything else an argv list.
+fn command(argv: Vec<String>) -> Value {
+    let shells = ["sh", "/bin/sh", "bash", "/bin/bash"];
+    match argv.as_slice() {
+        [shell, flag, script] if shells.contains(&shell.as_str()) && flag == "-c" => script.clone().into(),
+        _ => Value::Sequence(argv.into_iter().map(Value::from).collect()),
+    }
+}
+
+fn quantity_text(quantity: &Value) -> String {
+    match quantity {
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other).unwrap_or_default().trim().to_string(),
+    }
+}
+
+/// Kubernetes memory quantity (`512Mi`, `1G`, `1.5Gi`) as a rollcron size.
+fn memory(quantity: &str) -> Option<String> {
+    const SUFFIXES: [(&str, f64); 8] = [
+        ("Ki", 1024.0),
+        ("Mi", 1048576.0),
+        ("Gi", 1073741824.0),
+        ("Ti", 1099511627776.0),
+        ("k", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+    ];
+    let (number, scale) = SUFFIXES
+        .iter()
+        .find_map(|(suffix, scale)| quantity.strip_suffix(suffix).map(|n| (n, *scale)))
+        .unwrap_or((quantity, 1.0));
+    let bytes = (number.parse::<f64>().ok()? * scale).round();
+    if !(bytes.is_finite() && bytes >= 1.0) {
+        return None;
+    }
+    let bytes = bytes as u64;
+    Some(match bytes {
+        b if b % (1 << 30) == 0 => format!("{}G", b >> 30),
+        b if b % (1 << 20) == 0 => format!("{}M", b >> 20),
+        b if b % (1 << 10) == 0 => format!("{}K", b >> 10),
+        b => b.to_string(),
+    })
+}
+
+/// Kubernetes CPU quantity (`500m`, `2`) in cores.
+fn cpu(quantity: &str) -> Option<f64> {
+    let cores = match quantity.strip_suffix('m') {
+        Some(millis) => millis.parse::<f64>().ok()? / 1000.0,
+        None => quantity.parse::<f64>().ok()?,
+    };
+    (cores.is_finite() && cores > 0.0).then_some(cores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{parse_config, CommandLine, Concurrency};
+    use std::time::Duration;
+
+    const MANIFEST: &str = r#"
+apiVersion: batch/v1
+kind: CronJob
+metadata:
+  name: db.backup
+spec:
+  schedule: "0 3 * * *"
+  timeZone: Asia/Tokyo
+  concurrencyPolicy: Forbid
+  suspend: true
+  jobTemplate:
+    spec:
+      backoffLimit: 2
+      activeDeadlineSeconds: 1800
+      template:
+        spec:
+          restartPolicy: OnFailure
+          containers:
+            - name: backup
+              image: postgres:16
+              command: ["sh", "-c"]
+              args: ["pg_dump $DB > /backups/db.sql"]
+              workingDir: /srv
+              env:
+                - name: DB
+                  value: app
+                - name: PASSWORD
+                  valueFrom:
+                    secretKeyRef: { name: db, key: password }
+              resources:
+                limits:
+                  memory: 512Mi
+                  cpu: 500m
+---
+apiVersion: v1
+kind: List
+items:
+  - apiVersion: batch/v1
+    kind: CronJob
+    metadata:
+      name: report
+    spec:
+      schedule: "*/15 * * * *"
+      jobTemplate:
+        spec:
+          template:
+            spec:
+              containers:
+                - name: report
+                  command: ["/app/report", "--since", "15m"]
+  - apiVersion: v1
+    kind: ConfigMap
+    metadata:
+      name: settings
+"#;
+
+    #[test]
+    fn converts_cron_jobs_into_valid_config() {
+        let converted = convert(MANIFEST).unwrap();
+        let (_, jobs) = parse_config(&converted.to_yaml().unwrap()).unwrap();
+        assert_eq!(jobs.len(), 2);
+
+        let backup = jobs.iter().find(|j| j.id == "db_backup").unwrap();
+        assert_eq!(backup.command, CommandLine::Line("pg_dump $DB > /backups/db.sql".to_string()));
+        assert_eq!(backup.timeout, Duration::from_secs(1800));
+        assert!(matches!(backup.concurrency, Concurrency::Skip));
+        assert_eq!(backup.retry.as_ref().unwrap().max, 2);
+        assert!(!backup.enabled);
+        assert_eq!(backup.working_dir, None);
+        assert_eq!(backup.env.as_ref().unwrap().len(), 1);
+        let limits = backup.limits.unwrap();
+        assert_eq!((limits.memory, limits.cpu), (Some(512 << 20), Some(0.5)));
+        assert!(backup.timezone.is_some());
+
+        let report = jobs.iter().find(|j| j.id == "report").unwrap();
+        assert_eq!(
+            report.command,
+            CommandLine::Argv(vec!["/app/report".to_string(), "--since".to_string(), "15m".to_string()])
+        );
+        assert!(matches!(report.concurrency, Concurrency::Parallel));
+
+        assert_eq!(
+            converted.warnings,
+            [
+                "db_backup: image 'postgres:16' is not used; the command runs on the host",
+                "db_backup: workingDir /srv is an image path; the command runs in the job directory",
+                "db_backup: env PASSWORD uses valueFrom and is not converted",
+                "document 3: skipping kind ConfigMap",
+            ]
+        );
+    }
+
+    #[test]
+    fn entrypoint_only_containers_are_skipped() {
+        let manifest = "kind: CronJob\nmetadata: { name: app }\nspec:\n  schedule: \"@daily\"\n  jobTemplate: { spec: { template: { spec: { containers: [{ name: app, image: app:1 }] } } } }\n";
+        let converted = convert(manifest).unwrap();
+        assert!(converted.jobs.is_empty());
+        assert!(converted.warnings[1].contains("image entrypoint"), "{:?}", converted.warnings);
+    }
+
+    #[test]
+    fn converts_quantities() {
+        assert_eq!(memory("512Mi").as_deref(), Some("512M"));
+        assert_eq!(memory("1.5Gi").as_deref(), Some("1536M"));
+        assert_eq!(memory("1G").as_deref(), Some("1000000000"));
+        assert_eq!(memory("lots"), None);
+        assert_eq!(cpu("250m"), Some(0.25));
+        assert_eq!(cpu("2"), Some(2.0));
+        assert_eq!(cpu("0"), None);
+    }
+}
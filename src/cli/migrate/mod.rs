@@ -0,0 +1,131 @@
+//! `rollcron import` / `rollcron export`: job definitions converted from and
+//! to other schedulers.
+//!
+//! Converters return the result plus warnings for whatever couldn't be
+//! carried over; it is printed for review, never written anywhere.
+
+mod crontab;
+mod k8s;
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use serde_yaml::{Mapping, Value};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+#[derive(Subcommand)]
+pub enum ImportCommand {
+    /// Convert Kubernetes CronJob manifests into rollcron jobs
+    K8s {
+        /// Manifest file, `-` for stdin (multiple documents and `kind: List` are accepted)
+        file: PathBuf,
+    },
+    /// Convert a crontab into a rollcron.yaml skeleton
+    Crontab {
+        /// Crontab file (e.g. from `crontab -l`), `-` for stdin
+        file: PathBuf,
+        /// System crontab format, with a user field before the command (`/etc/crontab`, `/etc/cron.d/*`)
+        #[arg(long)]
+        system: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ExportCommand {
+    /// Print the jobs as a crontab
+    Crontab {
+        /// Local repo, remote URL or config file
+        repo: String,
+        /// Where the repository is checked out on the cron host [default: REPO when local]
+        #[arg(long, value_name = "PATH")]
+        dir: Option<PathBuf>,
+    },
+}
+
+pub fn run(cmd: ImportCommand) -> Result<()> {
+    let converted = match cmd {
+        ImportCommand::K8s { file } => k8s::convert(&read_input(&file)?)?,
+        ImportCommand::Crontab { file, system } => crontab::convert(&read_input(&file)?, system)?,
+    };
+    for warning in &converted.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    print!("{}", converted.to_yaml()?);
+    Ok(())
+}
+
+pub fn export(cmd: ExportCommand) -> Result<()> {
+    let ExportCommand::Crontab { repo, dir } = cmd;
+    let dir = match dir {
+        Some(dir) => dir,
+        None => checkout_dir(&repo)?,
+    };
+    let (runner, jobs, _) = super::check::load(&repo)?;
+    let (crontab, warnings) = crontab::export(&runner, &jobs, &dir);
+    for warning in &warnings {
+        eprintln!("warning: {}", warning);
+    }
+    print!("{}", crontab);
+    Ok(())
+}
+
+/// The repository directory of a local REPO argument (a config file's directory).
+fn checkout_dir(repo: &str) -> Result<PathBuf> {
+    let path = Path::new(repo);
+    let dir = match path.is_file() {
+        true => path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new(".")),
+        false if path.is_dir() => path,
+        false => anyhow::bail!("--dir is required for remote repositories"),
+    };
+    dir.canonicalize().with_context(|| format!("Failed to resolve {}", dir.display()))
+}
+
+/// Jobs keyed by ID, in input order, runner settings they share, and what
+/// was lost converting them
+#[derive(Debug, Default)]
+struct Converted {
+    runner: Mapping,
+    jobs: Mapping,
+    warnings: Vec<String>,
+}
+
+impl Converted {
+    /// A `jobs:` block (after `runner:` if any) ready to paste into `rollcron.yaml`.
+    fn to_yaml(&self) -> Result<String> {
+        let mut root = Mapping::new();
+        if !self.runner.is_empty() {
+            root.insert("runner".into(), Value::Mapping(self.runner.clone()));
+        }
+        root.insert("jobs".into(), Value::Mapping(self.jobs.clone()));
+        Ok(serde_yaml::to_string(&root)?)
+    }
+
+    fn warn(&mut self, job_id: &str, message: impl std::fmt::Display) {
+        self.warnings.push(format!("{}: {}", job_id, message));
+    }
+}
+
+fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content).context("Failed to read stdin")?;
+        return Ok(content);
+    }
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Job ID from a foreign name: characters rollcron doesn't allow become `_`.
+fn job_id(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// `1h`, `5m` or `90s`, whichever is exact.
+fn duration(secs: u64) -> String {
+    match secs {
+        s if s > 0 && s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s > 0 && s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
@@ -0,0 +1,257 @@
+//! CLI definition and offline subcommands.
+
+mod check;
+mod completions;
+mod config;
+mod cron;
+mod ctl;
+mod describe;
+mod gc;
+mod history;
+mod import;
+mod logs;
+mod migrate;
+mod notify;
+mod run;
+mod schema;
+mod systemd_unit;
+
+use crate::clock::{self, Clock};
+use crate::config::{Job, LabelSelector, RunnerConfig};
+use crate::git;
+use crate::logging::LogTarget;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+
+pub use completions::COMPLETE_VAR;
+
+#[derive(Parser)]
+#[command(
+    name = "rollcron",
+    about = "Auto-pulling cron scheduler",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Path to local repo or remote URL (https://... or git@...)
+    #[arg(required = true)]
+    pub repo: Option<String>,
+
+    /// Pull interval in seconds
+    #[arg(long, default_value = "3600")]
+    pub pull_interval: u64,
+
+    /// Listen address for the control API
+    #[arg(long, default_value = crate::api::DEFAULT_ADDR)]
+    pub api_listen: std::net::SocketAddr,
+
+    /// Require `Authorization: Bearer <TOKEN>` on every API request; without it the API is read-only
+    #[arg(long, env = "ROLLCRON_API_TOKEN", hide_env_values = true)]
+    pub api_token: Option<String>,
+
+    /// Origin the API is also reached under, e.g. `https://cron.example.com` behind a reverse proxy (repeatable)
+    #[arg(long, value_name = "ORIGIN", value_parser = crate::api::parse_origin)]
+    pub api_allowed_origin: Vec<String>,
+
+    /// Don't serve the control API (`rollcron ctl`, dashboard, status JSON)
+    #[arg(long, conflicts_with = "api_listen")]
+    pub no_api: bool,
+
+    /// Scheduler clock (`simulated` runs schedules on a virtual clock)
+    #[arg(long, value_enum, default_value_t = ClockMode::Real)]
+    pub clock: ClockMode,
+
+    /// Virtual start time for `--clock simulated` (RFC 3339) [default: now]
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub start_at: Option<DateTime<Utc>>,
+
+    /// Virtual seconds per real second for `--clock simulated` (e.g. 3600x) [default: 60x]
+    #[arg(long, value_parser = clock::parse_speed)]
+    pub speed: Option<f64>,
+
+    /// With `--clock simulated`, skip build/run commands and record them as successful
+    #[arg(long)]
+    pub stub_jobs: bool,
+
+    /// Host policy file: commands matching its patterns need `ctl approve-job` first
+    #[arg(long, value_name = "PATH")]
+    pub policy: Option<PathBuf>,
+
+    /// Host file of trusted signing keys: every commit checked out must be signed by one of them
+    #[arg(long, value_name = "PATH", env = "ROLLCRON_ALLOWED_SIGNERS", global = true)]
+    pub allowed_signers: Option<PathBuf>,
+
+    /// Program printing extra host facts as KEY=VALUE lines (for `when:` and `ROLLCRON_FACT_*`)
+    #[arg(long, value_name = "PATH", env = "ROLLCRON_FACTS_SCRIPT", global = true)]
+    pub facts_script: Option<PathBuf>,
+
+    /// Identity of this instance in HA leader election [default: <hostname>-<pid>]
+    #[arg(long)]
+    pub node_id: Option<String>,
+
+    /// Only run jobs with one of these labels: KEY or KEY=VALUE (comma-separated or repeated)
+    #[arg(long, value_name = "LABELS", value_delimiter = ',')]
+    pub only_labels: Vec<LabelSelector>,
+
+    /// Environment for git only, e.g. GIT_SSH_COMMAND or HTTPS_PROXY (repeatable)
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_git_env)]
+    pub git_env: Vec<(String, String)>,
+
+    /// Clone and fetch only the latest N commits of the repo (for large repos)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub clone_depth: Option<u32>,
+
+    /// Append every lifecycle event to this file as JSON lines
+    #[arg(long, value_name = "PATH")]
+    pub event_log: Option<PathBuf>,
+
+    /// Where the daemon logs; journald and syslog also get run output lines [default: stdout]
+    #[arg(long, value_enum, value_name = "TARGET")]
+    pub log_target: Option<LogTarget>,
+
+    /// Log file for `--log-target file`
+    #[arg(long, value_name = "PATH", required_if_eq("log_target", "file"))]
+    pub log_file: Option<PathBuf>,
+
+    /// Run history, notification outbox and job logs [default: $XDG_STATE_HOME/rollcron]
+    #[arg(long, value_name = "PATH", env = "ROLLCRON_STATE_DIR", global = true)]
+    pub state_dir: Option<PathBuf>,
+
+    /// Clones, build and run directories; safe to delete while stopped [default: $XDG_CACHE_HOME/rollcron]
+    #[arg(long, value_name = "PATH", env = "ROLLCRON_CACHE_DIR", global = true)]
+    pub cache_dir: Option<PathBuf>,
+}
+
+fn parse_git_env(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some(("GIT_TERMINAL_PROMPT", _)) => Err("GIT_TERMINAL_PROMPT is always 0 (git never prompts)".to_string()),
+        Some((key, value)) if !key.is_empty() && !key.contains(char::is_whitespace) => {
+            Ok((key.to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got '{}'", s)),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ClockMode {
+    Real,
+    Simulated,
+}
+
+impl Args {
+    /// Clock selected by `--clock` and its options.
+    pub fn clock(&self) -> Result<Clock> {
+        match self.clock {
+            ClockMode::Real => {
+                if self.start_at.is_some() || self.speed.is_some() || self.stub_jobs {
+                    anyhow::bail!("--start-at, --speed and --stub-jobs require --clock simulated");
+                }
+                Ok(Clock::Real)
+            }
+            ClockMode::Simulated => Ok(Clock::simulated(
+                self.start_at.unwrap_or_else(Utc::now),
+                self.speed.unwrap_or(60.0),
+                self.stub_jobs,
+            )),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Validate a config and preview its schedule (`--simulate`)
+    Check(check::CheckArgs),
+    /// Print a shell completion script (job IDs complete from ./rollcron.yaml)
+    Completions(completions::CompletionsArgs),
+    /// Inspect a job's effective configuration
+    #[command(subcommand)]
+    Config(config::ConfigCommand),
+    /// Try out schedule expressions
+    #[command(subcommand)]
+    Cron(cron::CronCommand),
+    /// Control a running daemon
+    Ctl(ctl::CtlArgs),
+    /// Print job schedules as sentences
+    Describe(describe::DescribeArgs),
+    /// Remove cache entries left by stopped instances and interrupted syncs
+    Gc(gc::GcArgs),
+    /// Inspect recorded job runs
+    #[command(subcommand)]
+    History(history::HistoryCommand),
+    /// Convert jobs from another scheduler into rollcron.yaml entries
+    #[command(subcommand)]
+    Import(migrate::ImportCommand),
+    /// Convert jobs into another scheduler's format
+    #[command(subcommand)]
+    Export(migrate::ExportCommand),
+    /// Print a job's latest output from the daemon, or follow a run (`-f`)
+    Logs(logs::LogsArgs),
+    /// Check notification delivery
+    #[command(subcommand)]
+    Notify(notify::NotifyCommand),
+    /// Build and run jobs once, then print a summary (for CI)
+    Run(run::RunArgs),
+    /// Print the JSON Schema for rollcron.yaml
+    Schema(schema::SchemaArgs),
+    /// Print a hardened systemd service unit running the daemon
+    SystemdUnit(systemd_unit::SystemdUnitArgs),
+}
+
+/// Runs a subcommand to completion.
+pub async fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Check(args) => check::run(args).await,
+        Command::Completions(args) => completions::run(args),
+        Command::Config(cmd) => config::run(cmd).await,
+        Command::Cron(cmd) => cron::run(cmd),
+        Command::Ctl(args) => ctl::run(args).await,
+        Command::Describe(args) => describe::run(args),
+        Command::Gc(args) => gc::run(args),
+        Command::History(cmd) => history::run(cmd),
+        Command::Export(cmd) => migrate::export(cmd),
+        Command::Import(cmd) => migrate::run(cmd),
+        Command::Logs(args) => logs::run(args).await,
+        Command::Notify(cmd) => notify::run(cmd).await,
+        Command::Run(args) => run::run(args).await,
+        Command::Schema(args) => schema::run(args),
+        Command::SystemdUnit(args) => systemd_unit::run(args),
+    }
+}
+
+/// Temporary clone of the source repo, removed on drop.
+/// Subcommands read the committed config the same way the daemon does.
+struct Checkout {
+    path: PathBuf,
+}
+
+impl Checkout {
+    fn new(repo: &str) -> Result<Self> {
+        let source = git::resolve_source(repo)?;
+        let path = git::generate_cache_path(&source);
+        crate::cache::claim(&path)?;
+        // Dropped on failure, which removes the marker and partial clone
+        let checkout = Self { path };
+        crate::source::clone_to(&source, &checkout.path)?;
+        Ok(checkout)
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load_config(&self) -> Result<(RunnerConfig, Vec<Job>)> {
+        crate::config::load_config(&self.path)
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+        crate::cache::release(&self.path);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::config::{Job, RunnerConfig, WebhookConfig};
+use crate::{env, webhook};
+use anyhow::Result;
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub enum NotifyCommand {
+    /// Send a test message through each configured webhook and report delivery
+    Test {
+        /// Path to local repo or remote URL (same as the daemon argument)
+        repo: String,
+        /// Only test this channel: `runner` or a job ID
+        channel: Option<String>,
+    },
+}
+
+pub async fn run(cmd: NotifyCommand) -> Result<()> {
+    match cmd {
+        NotifyCommand::Test { repo, channel } => test(&repo, channel.as_deref()).await,
+    }
+}
+
+/// A webhook to test, labeled by where it is configured.
+struct Target<'a> {
+    channel: String,
+    webhook: &'a WebhookConfig,
+}
+
+async fn test(repo: &str, channel: Option<&str>) -> Result<()> {
+    let checkout = super::Checkout::new(repo)?;
+    let (runner, jobs) = checkout.load_config()?;
+
+    let targets = collect_targets(&runner, &jobs, channel);
+    if targets.is_empty() {
+        match channel {
+            Some(c) => anyhow::bail!("No webhooks configured for '{}'", c),
+            None => anyhow::bail!("No webhooks configured"),
+        }
+    }
+
+    let runner_env = env::load_runner_env(checkout.path(), &runner).await;
+    let mut failed = 0;
+    for target in &targets {
+        let url = target.webhook.to_url(runner_env.as_ref());
+        let status = if url.contains('$') {
+            Err("skipped: URL contains unexpanded variable".to_string())
+        } else if !url.starts_with("http://") && !url.starts_with("https://") {
+            Err("skipped: URL must start with http:// or https://".to_string())
+        } else {
+            webhook::send_test(&url, target.webhook.template.as_deref(), &target.channel)
+                .await
+                .map_err(|e| format!("{:#}", e))
+        };
+        match status {
+            Ok(()) => println!("{:<24} {:<8} OK", target.channel, target.webhook.webhook_type),
+            Err(e) => {
+                failed += 1;
+                println!("{:<24} {:<8} FAILED ({})", target.channel, target.webhook.webhook_type, e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} notifications failed", failed, targets.len());
+    }
+    Ok(())
+}
+
+/// Lists webhooks per channel. Jobs inherit runner webhooks, so only the
+/// job's own additions are listed under the job (unless filtering by it).
+fn collect_targets<'a>(
+    runner: &'a RunnerConfig,
+    jobs: &'a [Job],
+    channel: Option<&str>,
+) -> Vec<Target<'a>> {
+    let mut targets = Vec::new();
+    if channel.is_none_or(|c| c == "runner") {
+        targets.extend(runner.webhook.iter().map(|webhook| Target {
+            channel: "runner".to_string(),
+            webhook,
+        }));
+    }
+    for job in jobs {
+        match channel {
+            Some(c) if c == job.id => {
+                targets.extend(job.webhook.iter().map(|webhook| Target {
+                    channel: format!("job:{}", job.id),
+                    webhook,
+                }));
+            }
+            Some(_) => {}
+            None => {
+                targets.extend(
+                    job.webhook
+                        .iter()
+                        .filter(|wh| !runner.webhook.contains(wh))
+                        .map(|webhook| Target {
+                            channel: format!("job:{}", job.id),
+                            webhook,
+                        }),
+                );
+            }
+        }
+    }
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    const CONFIG: &str = r#"
+runner:
+  webhook:
+    - url: https://example.com/runner
+jobs:
+  a:
+    schedule: "* * * * *"
+    run: echo a
+    webhook:
+      - url: https://example.com/a
+  b:
+    schedule: "* * * * *"
+    run: echo b
+"#;
+
+    fn channels(targets: &[Target]) -> Vec<String> {
+        targets.iter().map(|t| t.channel.clone()).collect()
+    }
+
+    #[test]
+    fn collect_all_skips_inherited() {
+        let (runner, jobs) = parse_config(CONFIG).unwrap();
+        let targets = collect_targets(&runner, &jobs, None);
+        assert_eq!(channels(&targets), vec!["runner", "job:a"]);
+    }
+
+    #[test]
+    fn collect_job_includes_inherited() {
+        let (runner, jobs) = parse_config(CONFIG).unwrap();
+        let targets = collect_targets(&runner, &jobs, Some("a"));
+        assert_eq!(channels(&targets), vec!["job:a", "job:a"]);
+        assert_eq!(targets[0].webhook.url, "https://example.com/runner");
+    }
+
+    #[test]
+    fn collect_runner_only() {
+        let (runner, jobs) = parse_config(CONFIG).unwrap();
+        let targets = collect_targets(&runner, &jobs, Some("runner"));
+        assert_eq!(channels(&targets), vec!["runner"]);
+    }
+}
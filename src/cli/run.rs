@@ -0,0 +1,378 @@
+//! `rollcron run`: build and run jobs once, then print a summary.
+//!
+//! Exit code: 0 if every job succeeded, 1 if a job failed (or timed out /
+//! was OOM-killed), 2 if a job could not run at all (build failure or
+//! command not executable).
+
+use super::completions::job_ids;
+use crate::actor::job::{execute_build, execute_job, BuildResult, JobRunReport, RunStart, RunTrigger};
+use crate::config::{Job, LabelSelector};
+use crate::{facts, git, otel};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::{Args as ClapArgs, ValueEnum};
+use clap_complete::ArgValueCandidates;
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(ClapArgs)]
+pub struct RunArgs {
+    /// Path to local repo or remote URL (same as the daemon argument)
+    repo: String,
+    /// Jobs to run [default: all]
+    #[arg(add = ArgValueCandidates::new(job_ids))]
+    job_ids: Vec<String>,
+    /// Only run jobs with one of these labels: KEY or KEY=VALUE (comma-separated or repeated)
+    #[arg(long, value_name = "LABELS", value_delimiter = ',')]
+    only_labels: Vec<LabelSelector>,
+    /// Summary format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    /// Write the summary to a file instead of stdout
+    #[arg(long, value_name = "PATH")]
+    output_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    /// JUnit XML (one test case per job)
+    Junit,
+}
+
+/// Result of one job in a one-shot run.
+#[derive(Debug, Serialize)]
+pub struct JobResult {
+    pub job_id: String,
+    pub name: String,
+    /// `RunOutcome` of the run, or `build_failed`
+    pub outcome: &'static str,
+    #[serde(rename = "duration_ms", serialize_with = "millis")]
+    pub duration: Duration,
+    pub attempts: u32,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    pub stdout_tail: String,
+    pub stderr_tail: String,
+}
+
+impl JobResult {
+    fn from_report(job: &Job, report: JobRunReport, build_time: Duration) -> Self {
+        Self {
+            job_id: job.id.clone(),
+            name: job.name.clone(),
+            outcome: report.outcome.as_str(),
+            duration: build_time + report.duration,
+            attempts: report.attempts,
+            exit_code: report.exit_code,
+            error: report.error,
+            stdout_tail: report.stdout_tail,
+            stderr_tail: report.stderr_tail,
+        }
+    }
+
+    fn build_failed(job: &Job, error: String, stderr: String, duration: Duration) -> Self {
+        Self {
+            job_id: job.id.clone(),
+            name: job.name.clone(),
+            outcome: "build_failed",
+            duration,
+            attempts: 0,
+            exit_code: None,
+            error: Some(error),
+            stdout_tail: String::new(),
+            stderr_tail: stderr,
+        }
+    }
+
+    fn status(&self) -> Status {
+        match self.outcome {
+            // An unmet `condition` is the job working as configured
+            "success" | "skipped" => Status::Passed,
+            "build_failed" | "exec_error" => Status::NotRun,
+            _ => Status::Failed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Passed,
+    Failed,
+    NotRun,
+}
+
+/// All job results of a one-shot run.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub repo: String,
+    pub started_at: DateTime<Utc>,
+    #[serde(rename = "duration_ms", serialize_with = "millis")]
+    pub duration: Duration,
+    pub passed: usize,
+    pub failed: usize,
+    pub not_run: usize,
+    pub exit_code: i32,
+    pub jobs: Vec<JobResult>,
+}
+
+impl Summary {
+    pub fn new(repo: &str, started_at: DateTime<Utc>, duration: Duration, jobs: Vec<JobResult>) -> Self {
+        let count = |status| jobs.iter().filter(|j| j.status() == status).count();
+        let exit_code = match jobs.iter().map(JobResult::status).max() {
+            None | Some(Status::Passed) => 0,
+            Some(Status::Failed) => 1,
+            Some(Status::NotRun) => 2,
+        };
+        Self {
+            repo: repo.to_string(),
+            started_at,
+            duration,
+            passed: count(Status::Passed),
+            failed: count(Status::Failed),
+            not_run: count(Status::NotRun),
+            exit_code,
+            jobs,
+        }
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String> {
+        Ok(match format {
+            OutputFormat::Text => self.to_text(),
+            OutputFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+            OutputFormat::Junit => self.to_junit(),
+        })
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = format!(
+            "{:<24} {:<13} {:>8} {:>10} {:>5}\n",
+            "JOB", "OUTCOME", "ATTEMPTS", "DURATION", "EXIT"
+        );
+        for job in &self.jobs {
+            let _ = writeln!(
+                out,
+                "{:<24} {:<13} {:>8} {:>10} {:>5}",
+                job.job_id,
+                job.outcome,
+                job.attempts,
+                format!("{:.3}s", job.duration.as_secs_f64()),
+                job.exit_code.map_or("-".to_string(), |c| c.to_string()),
+            );
+            if let Some(error) = &job.error {
+                let _ = writeln!(out, "  {}", error);
+            }
+        }
+        let _ = writeln!(
+            out,
+            "\n{} jobs: {} passed, {} failed, {} not run in {:.3}s (exit code {})",
+            self.jobs.len(),
+            self.passed,
+            self.failed,
+            self.not_run,
+            self.duration.as_secs_f64(),
+            self.exit_code
+        );
+        out
+    }
+
+    fn to_junit(&self) -> String {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            out,
+            "<testsuites name=\"rollcron\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">",
+            self.jobs.len(),
+            self.failed,
+            self.not_run,
+            self.duration.as_secs_f64()
+        );
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\" timestamp=\"{}\">",
+            xml_escape(&self.repo),
+            self.jobs.len(),
+            self.failed,
+            self.not_run,
+            self.duration.as_secs_f64(),
+            self.started_at.format("%Y-%m-%dT%H:%M:%S")
+        );
+        for job in &self.jobs {
+            let _ = write!(
+                out,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                xml_escape(&job.job_id),
+                xml_escape(&self.repo),
+                job.duration.as_secs_f64()
+            );
+            if job.status() == Status::Passed && job.stdout_tail.is_empty() && job.stderr_tail.is_empty() {
+                out.push_str("/>\n");
+                continue;
+            }
+            out.push_str(">\n");
+            let element = match job.status() {
+                Status::Passed => None,
+                Status::Failed => Some("failure"),
+                Status::NotRun => Some("error"),
+            };
+            if let Some(element) = element {
+                let _ = writeln!(
+                    out,
+                    "      <{} type=\"{}\" message=\"{}\"/>",
+                    element,
+                    job.outcome,
+                    xml_escape(job.error.as_deref().unwrap_or(job.outcome))
+                );
+            }
+            for (element, text) in [("system-out", &job.stdout_tail), ("system-err", &job.stderr_tail)] {
+                if !text.is_empty() {
+                    let _ = writeln!(out, "      <{0}>{1}</{0}>", element, xml_escape(text));
+                }
+            }
+            out.push_str("    </testcase>\n");
+        }
+        out.push_str("  </testsuite>\n</testsuites>\n");
+        out
+    }
+}
+
+pub async fn run(args: RunArgs) -> Result<()> {
+    otel::init()?;
+    let checkout = super::Checkout::new(&args.repo)?;
+    let (runner, mut jobs) = checkout.load_config()?;
+    if let Some(signers) = crate::signers::get() {
+        git::verify_commit(checkout.path(), "HEAD", signers)?;
+    }
+    jobs.sort_by(|a, b| a.id.cmp(&b.id));
+    if !args.job_ids.is_empty() {
+        if let Some(unknown) = args.job_ids.iter().find(|id| !jobs.iter().any(|j| &j.id == *id)) {
+            anyhow::bail!("Unknown job '{}'", unknown);
+        }
+        jobs.retain(|j| args.job_ids.contains(&j.id));
+    }
+    jobs.retain(|j| j.has_any_label(&args.only_labels) && j.host_mismatch(facts::get()).is_none());
+
+    let started_at = Utc::now();
+    let start = Instant::now();
+    let mut results = Vec::with_capacity(jobs.len());
+    for job in &jobs {
+        results.push(run_job(job, checkout.path(), &runner).await);
+        git::remove_job_dir(checkout.path(), &job.id);
+    }
+    let summary = Summary::new(git::repo_name(&git::resolve_source(&args.repo)?), started_at, start.elapsed(), results);
+
+    let rendered = summary.render(args.output)?;
+    match &args.output_file {
+        Some(path) => {
+            std::fs::write(path, rendered).with_context(|| format!("Failed to write {}", path.display()))?;
+            if args.output != OutputFormat::Text {
+                print!("{}", summary.to_text());
+            }
+        }
+        None => print!("{}", rendered),
+    }
+
+    otel::shutdown().await;
+    if summary.exit_code != 0 {
+        drop(checkout);
+        std::process::exit(summary.exit_code);
+    }
+    Ok(())
+}
+
+/// Builds the job in a fresh worktree, then runs it once (with retries).
+async fn run_job(job: &Job, sot_path: &Path, runner: &crate::config::RunnerConfig) -> JobResult {
+    let start = Instant::now();
+    let build_dir = git::get_build_dir(sot_path, &job.id);
+    if let Err(e) = git::sync_to_build_dir(sot_path, &build_dir, runner.submodules, runner.lfs) {
+        return JobResult::build_failed(job, format!("build sync failed: {:#}", e), String::new(), start.elapsed());
+    }
+    if let BuildResult::Failed { error, stderr } = execute_build(job, sot_path, runner).await {
+        return JobResult::build_failed(job, format!("build failed: {}", error), stderr, start.elapsed());
+    }
+    if let Err(e) = git::copy_build_to_run(&build_dir, &git::get_run_dir(sot_path, &job.id)) {
+        return JobResult::build_failed(job, format!("copy to run directory failed: {:#}", e), String::new(), start.elapsed());
+    }
+    let build_time = start.elapsed();
+
+    let report = execute_job(job, sot_path, runner, &RunStart { trigger: RunTrigger::Manual, ..Default::default() }).await;
+    JobResult::from_report(job, report, build_time)
+}
+
+fn millis<S: serde::Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u64(d.as_millis() as u64)
+}
+
+/// Escapes text for XML attributes and content, dropping characters XML 1.0 forbids.
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c < ' ' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(job_id: &str, outcome: &'static str) -> JobResult {
+        JobResult {
+            job_id: job_id.to_string(),
+            name: job_id.to_string(),
+            outcome,
+            duration: Duration::from_millis(1500),
+            attempts: 1,
+            exit_code: (outcome == "failed").then_some(3),
+            error: (outcome != "success").then(|| "exit code 3 <bad>".to_string()),
+            stdout_tail: String::new(),
+            stderr_tail: if outcome == "failed" { "oops\u{1b}[0m".to_string() } else { String::new() },
+        }
+    }
+
+    fn summary(outcomes: &[&'static str]) -> Summary {
+        let jobs = outcomes.iter().enumerate().map(|(i, o)| result(&format!("job{}", i), o)).collect();
+        Summary::new("my-repo", Utc::now(), Duration::from_secs(3), jobs)
+    }
+
+    #[test]
+    fn exit_code_mapping() {
+        assert_eq!(summary(&[]).exit_code, 0);
+        assert_eq!(summary(&["success", "success"]).exit_code, 0);
+        assert_eq!(summary(&["success", "timeout"]).exit_code, 1);
+        assert_eq!(summary(&["failed", "build_failed", "success"]).exit_code, 2);
+        let s = summary(&["failed", "exec_error", "success", "oom_killed"]);
+        assert_eq!((s.passed, s.failed, s.not_run), (1, 2, 1));
+    }
+
+    #[test]
+    fn json_summary() {
+        let json: serde_json::Value =
+            serde_json::from_str(&summary(&["success", "failed"]).render(OutputFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["exit_code"], 1);
+        assert_eq!(json["duration_ms"], 3000);
+        assert_eq!(json["jobs"][1]["outcome"], "failed");
+        assert_eq!(json["jobs"][1]["duration_ms"], 1500);
+    }
+
+    #[test]
+    fn junit_report() {
+        let xml = summary(&["success", "failed", "build_failed"]).render(OutputFormat::Junit).unwrap();
+        assert!(xml.contains(r#"<testsuites name="rollcron" tests="3" failures="1" errors="1" time="3.000">"#));
+        assert!(xml.contains(r#"<testcase name="job0" classname="my-repo" time="1.500"/>"#));
+        assert!(xml.contains(r#"<failure type="failed" message="exit code 3 &lt;bad&gt;"/>"#));
+        assert!(xml.contains("<system-err>oops[0m</system-err>"));
+        assert!(xml.contains(r#"<error type="build_failed""#));
+    }
+}
@@ -0,0 +1,19 @@
+use anyhow::Result;
+use clap::Args;
+
+#[derive(Args)]
+pub struct SchemaArgs {
+    /// Schema for files listed in `include` (jobs only) instead of rollcron.yaml
+    #[arg(long)]
+    include: bool,
+}
+
+pub fn run(args: SchemaArgs) -> Result<()> {
+    let schema = if args.include {
+        crate::schema::include_schema()
+    } else {
+        crate::schema::schema()
+    };
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
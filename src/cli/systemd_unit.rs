@@ -0,0 +1,27 @@
+//! `rollcron systemd-unit`: print a service unit for the daemon.
+
+use crate::git;
+use anyhow::{Context, Result};
+use clap::Args;
+
+#[derive(Args)]
+pub struct SystemdUnitArgs {
+    /// Path to local repo or remote URL (same as the daemon argument)
+    repo: String,
+    /// User unit (`systemctl --user`) instead of a system service
+    #[arg(long)]
+    user: bool,
+    /// Further daemon options, e.g. `-- --pull-interval 300`
+    #[arg(last = true, value_name = "OPTIONS")]
+    options: Vec<String>,
+}
+
+pub fn run(args: SystemdUnitArgs) -> Result<()> {
+    // The unit runs without a working directory or shell expansion
+    let source = git::resolve_source(&args.repo)?;
+    let exe = std::env::current_exe().context("Failed to locate the rollcron binary")?;
+    let exe = exe.to_str().context("Path contains invalid UTF-8")?;
+    let daemon_args: Vec<String> = std::iter::once(source).chain(args.options).collect();
+    print!("{}", crate::systemd::unit(exe, &daemon_args, args.user));
+    Ok(())
+}
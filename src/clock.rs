@@ -0,0 +1,180 @@
+//! Scheduler clock: wall time, or a virtual clock for simulating schedules.
+//!
+//! `--clock simulated --start-at 2025-01-01T00:00:00Z --speed 3600x` starts
+//! the virtual clock at the given instant and advances it 3600 times faster
+//! than real time. Schedules, run timestamps and history all follow the
+//! virtual clock; timeouts and retry delays stay in real time.
+
+use chrono::{DateTime, Utc};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::info;
+
+static CLOCK: OnceLock<Clock> = OnceLock::new();
+
+static RESUMES: OnceLock<watch::Sender<Option<Resume>>> = OnceLock::new();
+
+/// How often the suspend watchdog wakes up
+pub const SUSPEND_CHECK: Duration = Duration::from_secs(10);
+
+/// Wall time beyond `SUSPEND_CHECK` between two wakeups that counts as a suspend
+pub const SUSPEND_THRESHOLD: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+pub enum Clock {
+    Real,
+    Simulated {
+        start_at: DateTime<Utc>,
+        speed: f64,
+        origin: Instant,
+        /// Skip build/run commands and report success
+        stub_jobs: bool,
+    },
+}
+
+impl Clock {
+    pub fn simulated(start_at: DateTime<Utc>, speed: f64, stub_jobs: bool) -> Self {
+        Clock::Simulated {
+            start_at,
+            speed,
+            origin: Instant::now(),
+            stub_jobs,
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        match *self {
+            Clock::Real => Utc::now(),
+            Clock::Simulated { start_at, speed, origin, .. } => {
+                let elapsed = origin.elapsed().mul_f64(speed);
+                start_at + chrono::Duration::from_std(elapsed).unwrap_or(chrono::Duration::MAX)
+            }
+        }
+    }
+
+    /// Real time to wait until the clock reads `target`.
+    pub fn until(&self, target: DateTime<Utc>) -> Duration {
+        let remaining = (target - self.now()).to_std().unwrap_or_default();
+        match *self {
+            Clock::Real => remaining,
+            Clock::Simulated { speed, .. } => remaining.div_f64(speed),
+        }
+    }
+}
+
+/// Installs the process clock. Must be called before any job is scheduled;
+/// later calls are ignored.
+pub fn init(clock: Clock) {
+    let _ = CLOCK.set(clock);
+}
+
+fn get() -> &'static Clock {
+    CLOCK.get_or_init(|| Clock::Real)
+}
+
+/// Current time on the process clock.
+pub fn now() -> DateTime<Utc> {
+    get().now()
+}
+
+/// Real time to wait until the process clock reads `target`.
+pub fn until(target: DateTime<Utc>) -> Duration {
+    get().until(target)
+}
+
+pub fn is_simulated() -> bool {
+    matches!(get(), Clock::Simulated { .. })
+}
+
+/// True if job commands are replaced by no-ops (simulation only).
+pub fn stub_jobs() -> bool {
+    matches!(get(), Clock::Simulated { stub_jobs: true, .. })
+}
+
+/// The host resumed from a suspend (or the wall clock jumped forward).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resume {
+    /// When the watchdog noticed it, at most `SUSPEND_CHECK` after the resume
+    pub at: DateTime<Utc>,
+    /// Wall time the process did not see
+    pub gap: Duration,
+}
+
+fn resume_sender() -> &'static watch::Sender<Option<Resume>> {
+    RESUMES.get_or_init(|| watch::channel(None).0)
+}
+
+/// Notified once per detected resume, so loops that sleep on the monotonic
+/// clock (which stops during suspend on Linux) can recompute their waits.
+pub fn resumes() -> watch::Receiver<Option<Resume>> {
+    resume_sender().subscribe()
+}
+
+/// Wall time missing between two watchdog wakeups `wall_elapsed` apart.
+pub fn suspend_gap(wall_elapsed: chrono::Duration) -> Option<Duration> {
+    let gap = wall_elapsed.to_std().ok()?.checked_sub(SUSPEND_CHECK)?;
+    (gap > SUSPEND_THRESHOLD).then_some(gap)
+}
+
+/// Watches for suspends by comparing wall time across short sleeps. Does
+/// nothing on a simulated clock.
+pub async fn watch_suspend() {
+    if is_simulated() {
+        return;
+    }
+    let mut last = Utc::now();
+    loop {
+        tokio::time::sleep(SUSPEND_CHECK).await;
+        let now = Utc::now();
+        if let Some(gap) = suspend_gap(now - last) {
+            info!(target: "rollcron::runner", gap_secs = gap.as_secs(), "Resumed after suspend");
+            resume_sender().send_replace(Some(Resume { at: now, gap }));
+        }
+        last = now;
+    }
+}
+
+/// Parses a speed factor like `60x` or `60`.
+pub fn parse_speed(s: &str) -> Result<f64, String> {
+    let n = s.strip_suffix(['x', 'X']).unwrap_or(s);
+    match n.parse::<f64>() {
+        Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+        _ => Err(format!("invalid speed '{}' (expected e.g. 60x)", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_speed_factor() {
+        assert_eq!(parse_speed("60x").unwrap(), 60.0);
+        assert_eq!(parse_speed("1.5").unwrap(), 1.5);
+        assert!(parse_speed("0x").is_err());
+        assert!(parse_speed("fast").is_err());
+    }
+
+    #[test]
+    fn simulated_clock_scales_waits() {
+        let start = "2025-03-09T06:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let clock = Clock::simulated(start, 3600.0, false);
+        assert!(clock.now() >= start);
+        assert!(clock.now() - start < chrono::Duration::minutes(5));
+
+        let wait = clock.until(start + chrono::Duration::hours(2));
+        assert!(wait <= Duration::from_secs(2));
+        assert!(wait > Duration::from_millis(1500));
+        assert_eq!(clock.until(start - chrono::Duration::hours(1)), Duration::ZERO);
+    }
+
+    #[test]
+    fn suspend_gap_ignores_late_wakeups() {
+        assert_eq!(suspend_gap(chrono::Duration::seconds(10)), None);
+        assert_eq!(suspend_gap(chrono::Duration::seconds(65)), None);
+        // Wall clock stepped back
+        assert_eq!(suspend_gap(chrono::Duration::seconds(-300)), None);
+        assert_eq!(suspend_gap(chrono::Duration::hours(3)), Some(Duration::from_secs(3 * 3600 - 10)));
+    }
+}
@@ -1,8 +1,16 @@
+use crate::actor::job::report::FailureClass;
+use crate::facts::{Condition, Facts};
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, Timelike, Utc, Weekday};
 use chrono_tz::Tz;
+use croner::errors::CronError;
+use croner::parser::{CronParser, Seconds, Year};
 use croner::Cron;
-use serde::Deserialize;
-use std::collections::HashMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
 use tracing::warn;
@@ -31,14 +39,167 @@ pub enum TimezoneConfig {
     Named(Tz),
 }
 
+impl TimezoneConfig {
+    /// Wall-clock time in this timezone.
+    pub fn to_local(&self, at: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            TimezoneConfig::Utc => at.naive_utc(),
+            TimezoneConfig::Inherit => at.with_timezone(&Local).naive_local(),
+            TimezoneConfig::Named(tz) => at.with_timezone(tz).naive_local(),
+        }
+    }
+}
+
 /// Webhook configuration for failure notifications
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
 pub struct WebhookConfig {
     /// Webhook type (currently only "discord" supported)
     #[serde(rename = "type", default = "default_webhook_type")]
     pub webhook_type: String,
     /// Webhook URL (supports $ENV_VAR expansion)
     pub url: String,
+    /// Which notifications this webhook receives (all by default)
+    #[serde(default)]
+    pub filter: Option<NotifyFilter>,
+    /// Tera template rendering the JSON body instead of the Discord embed
+    #[serde(default, deserialize_with = "deserialize_template")]
+    #[schemars(with = "Option<String>")]
+    pub template: Option<String>,
+}
+
+/// Rejects a webhook `template` that doesn't compile at load time rather
+/// than at the first notification.
+fn deserialize_template<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<String>, D::Error> {
+    let template = Option::<String>::deserialize(deserializer)?;
+    if let Some(template) = &template {
+        crate::webhook::check_template(template)
+            .map_err(|e| serde::de::Error::custom(format!("invalid webhook template: {:#}", e)))?;
+    }
+    Ok(template)
+}
+
+/// Kind of notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    JobStarted,
+    JobFailure,
+    /// A run was skipped because the host failed its `guards`
+    JobSkipped,
+    BuildFailure,
+    ConfigError,
+    /// A stage of the `runner.canary` check failed
+    CanaryFailure,
+    /// `runner.brake` paused non-critical jobs
+    BrakeEngaged,
+    /// A run deviated from the job's `slo`
+    JobAnomaly,
+    /// A job with `dedupe_alerts` succeeded after failing
+    JobRecovered,
+    /// A job's `circuit_breaker` opened after consecutive failed runs
+    CircuitOpen,
+    /// A job with `notify_on_output_change` printed something new
+    OutputChanged,
+}
+
+/// Severity of a job's notifications (`jobs.<id>.severity`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    #[default]
+    Error,
+    Critical,
+}
+
+/// Per-webhook routing rules; a notification is sent only if every rule matches
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(try_from = "NotifyFilterRaw")]
+#[schemars(with = "NotifyFilterRaw")]
+pub struct NotifyFilter {
+    /// Empty: every event
+    pub events: Vec<NotifyEvent>,
+    /// Every label must match the job's (never matches config errors)
+    pub labels: HashMap<String, String>,
+    /// Only inside this window, in the runner's timezone
+    pub hours: Option<TimeWindow>,
+    /// Only on these days, in the runner's timezone; empty: every day
+    pub days: Vec<Weekday>,
+    pub min_severity: Severity,
+    /// Only job failures of these classes; empty: every notification
+    pub failure_classes: Vec<FailureClass>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct NotifyFilterRaw {
+    #[serde(default)]
+    events: Vec<NotifyEvent>,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+    hours: Option<WindowRaw>,
+    #[serde(default)]
+    days: Vec<String>,
+    #[serde(default = "default_min_severity")]
+    min_severity: Severity,
+    #[serde(default)]
+    failure_classes: Vec<FailureClass>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct WindowRaw {
+    from: String,
+    to: String,
+}
+
+fn default_min_severity() -> Severity {
+    Severity::Info
+}
+
+impl TryFrom<NotifyFilterRaw> for NotifyFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: NotifyFilterRaw) -> Result<Self> {
+        let days = raw
+            .days
+            .iter()
+            .map(|d| d.parse::<Weekday>().map_err(|_| anyhow!("Invalid filter day '{}'", d)))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            events: raw.events,
+            labels: raw.labels,
+            hours: raw.hours.map(|h| TimeWindow::parse(&h.from, &h.to)).transpose()?,
+            days,
+            min_severity: raw.min_severity,
+            failure_classes: raw.failure_classes,
+        })
+    }
+}
+
+impl NotifyFilter {
+    /// `labels` is `None` for notifications not about a job; `local` is the
+    /// current time in the runner's timezone.
+    pub fn matches(
+        &self,
+        event: NotifyEvent,
+        severity: Severity,
+        labels: Option<&HashMap<String, String>>,
+        local: &NaiveDateTime,
+    ) -> bool {
+        (self.events.is_empty() || self.events.contains(&event))
+            && severity >= self.min_severity
+            && self.labels.iter().all(|(k, v)| labels.and_then(|l| l.get(k)) == Some(v))
+            && self.hours.as_ref().is_none_or(|h| h.remaining(local).is_some())
+            && (self.days.is_empty() || self.days.contains(&local.weekday()))
+            && (self.failure_classes.is_empty() || event == NotifyEvent::JobFailure)
+    }
+
+    /// `failure_classes` for a job failure of class `class`.
+    pub fn matches_class(&self, class: Option<FailureClass>) -> bool {
+        self.failure_classes.is_empty() || class.is_some_and(|c| self.failure_classes.contains(&c))
+    }
 }
 
 fn default_webhook_type() -> String {
@@ -76,24 +237,576 @@ fn expand_with_env(
     }
 }
 
+/// Shell used to run build/run commands
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    /// `sh -c` (default on Unix)
+    Sh,
+    /// `bash -c`
+    Bash,
+    /// `zsh -c`
+    Zsh,
+    /// `cmd /C` (default on Windows)
+    Cmd,
+    /// `powershell -NoProfile -NonInteractive -Command`
+    Powershell,
+    /// No shell: the command is split on whitespace and executed directly
+    None,
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        if cfg!(windows) { Shell::Cmd } else { Shell::Sh }
+    }
+}
+
+impl Shell {
+    /// Program and the arguments preceding the command string (`None` for `shell: none`).
+    pub fn invocation(&self) -> Option<(&'static str, &'static [&'static str])> {
+        match self {
+            Shell::Sh => Some(("sh", &["-c"])),
+            Shell::Bash => Some(("bash", &["-c"])),
+            Shell::Zsh => Some(("zsh", &["-c"])),
+            Shell::Cmd => Some(("cmd", &["/C"])),
+            Shell::Powershell => Some(("powershell", &["-NoProfile", "-NonInteractive", "-Command"])),
+            Shell::None => None,
+        }
+    }
+}
+
+/// A build/run command: a command line, or an argv list executed without a shell
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum CommandLine {
+    /// `sh: "./app --flag"` (run through the job's shell)
+    Line(String),
+    /// `sh: ["./app", "--flag"]` (exec'd directly, no quoting or expansion)
+    Argv(#[schemars(length(min = 1))] Vec<String>),
+}
+
+impl CommandLine {
+    /// Full argv to spawn under `shell`: the shell invocation followed by the
+    /// command line, or the program and its arguments when no shell is used.
+    pub fn argv(&self, shell: Shell) -> Vec<&str> {
+        match (self, shell.invocation()) {
+            (CommandLine::Line(line), Some((program, args))) => std::iter::once(program)
+                .chain(args.iter().copied())
+                .chain(std::iter::once(line.as_str()))
+                .collect(),
+            (CommandLine::Line(line), None) => line.split_whitespace().collect(),
+            (CommandLine::Argv(argv), _) => argv.iter().map(String::as_str).collect(),
+        }
+    }
+
+    fn validate(&self, field: &str) -> Result<()> {
+        let empty = match self {
+            CommandLine::Line(line) => line.trim().is_empty(),
+            CommandLine::Argv(argv) => argv.first().is_none_or(|p| p.is_empty()),
+        };
+        if empty {
+            anyhow::bail!("{} must not be empty", field);
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for CommandLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandLine::Line(line) => f.write_str(line),
+            CommandLine::Argv(argv) => {
+                for (i, arg) in argv.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    if arg.is_empty() || arg.contains(char::is_whitespace) {
+                        write!(f, "{:?}", arg)?;
+                    } else {
+                        f.write_str(arg)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct RunnerConfig {
     pub timezone: TimezoneConfig,
     pub env_file: Option<String>,
-    pub env: Option<HashMap<String, String>>,
+    pub env: Option<HashMap<String, EnvValue>>,
     pub webhook: Vec<WebhookConfig>,
+    pub secrets: SecretsConfig,
+    pub shell: Shell,
+    /// Read at startup only
+    pub ha: Option<HaConfig>,
+    /// Jobs whose `expected_duration` reaches this send start notifications
+    pub long_job_threshold: Duration,
+    /// Runs allowed at once across all jobs; others wait in priority order
+    pub max_concurrent_runs: Option<usize>,
+    pub metrics: MetricsConfig,
+    pub canary: Option<CanaryConfig>,
+    /// Remove directories of removed or renamed jobs after each config reload
+    pub remove_orphan_dirs: bool,
+    pub brake: Option<BrakeConfig>,
+    /// Directories outside the job directory an absolute `working_dir` may be in
+    pub allowed_work_dirs: Vec<PathBuf>,
+    pub on_resume: ResumePolicy,
+    /// Follow upstream when its history was rewritten (force push) instead of failing the sync
+    pub allow_force_reset: bool,
+    /// Check out submodules (recursively) in job build directories
+    pub submodules: bool,
+    /// Fetch git-lfs objects in job build directories instead of leaving pointer files
+    pub lfs: bool,
+    /// Record operator actions as git notes on the running commit (`refs/notes/rollcron`)
+    pub changelog: bool,
+    pub export: Option<ExportConfig>,
+}
+
+/// `runner.export`: run logs and daily history summaries shipped to object storage
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportConfig {
+    pub store: ObjectStore,
+    pub bucket: String,
+    /// Key prefix without leading or trailing `/` (may be empty)
+    pub prefix: String,
+    /// S3-compatible endpoint (MinIO, R2, ...) instead of AWS
+    pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectStore {
+    /// `s3://`, uploaded with the `aws` CLI
+    S3,
+    /// `gs://`, uploaded with the `gcloud` CLI
+    Gcs,
+}
+
+impl ExportConfig {
+    /// `s3://bucket/prefix/<key>`
+    pub fn url(&self, key: &str) -> String {
+        let scheme = match self.store {
+            ObjectStore::S3 => "s3",
+            ObjectStore::Gcs => "gs",
+        };
+        match self.prefix.as_str() {
+            "" => format!("{}://{}/{}", scheme, self.bucket, key),
+            prefix => format!("{}://{}/{}/{}", scheme, self.bucket, prefix, key),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ExportConfigRaw {
+    /// `s3://bucket/prefix` or `gs://bucket/prefix`
+    #[schemars(pattern(r"^(s3|gs)://[^/]+"))]
+    to: String,
+    endpoint: Option<String>,
+}
+
+/// `runner.brake`: pause non-critical jobs when too many runs fail
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrakeConfig {
+    /// Fraction of failed runs (exclusive, 0-1) that trips the brake
+    pub failure_rate: f64,
+    pub window: Duration,
+    /// Runs needed in the window before the rate counts
+    pub min_runs: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct BrakeConfigRaw {
+    /// Percentage of failed runs in the window that trips the brake
+    #[schemars(extend("exclusiveMinimum" = 0, "exclusiveMaximum" = 100))]
+    failure_rate: f64,
+    #[serde(default = "default_brake_window")]
+    #[schemars(with = "crate::schema::Duration")]
+    window: String,
+    /// Runs needed in the window before the rate counts
+    #[serde(default = "default_brake_min_runs")]
+    #[schemars(range(min = 1))]
+    min_runs: usize,
+}
+
+fn default_brake_window() -> String {
+    "15m".to_string()
+}
+
+fn default_brake_min_runs() -> usize {
+    5
+}
+
+/// ID of the synthetic job run by `runner.canary` (reserved while it is enabled)
+pub const CANARY_JOB_ID: &str = "_canary";
+
+/// Output the canary's command prints and its history check looks for
+pub const CANARY_OUTPUT: &str = "rollcron-canary";
+
+/// `runner.canary`: synthetic job checking the whole pipeline end to end
+#[derive(Debug, Clone)]
+pub struct CanaryConfig {
+    /// Runs `echo rollcron-canary` on the canary schedule, without retries or webhooks
+    pub job: Job,
+    /// Pinged after every passing check (dead man's switch)
+    pub ping_url: Option<String>,
+    /// Oldest successful git sync accepted [default: twice the pull interval]
+    pub max_sync_age: Option<Duration>,
+}
+
+impl CanaryConfig {
+    /// Ping URL with `$VAR` expanded like webhook URLs.
+    pub fn ping_url(&self, env_vars: Option<&std::collections::HashMap<String, String>>) -> Option<String> {
+        self.ping_url.as_deref().map(|url| expand_with_env(url, env_vars))
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CanaryConfigRaw {
+    #[serde(default = "default_canary_schedule")]
+    schedule: String,
+    #[serde(default = "default_canary_timeout")]
+    #[schemars(with = "crate::schema::Duration")]
+    timeout: String,
+    /// Requested (GET) after every passing check
+    ping_url: Option<String>,
+    /// Oldest successful git sync accepted (default: twice `--pull-interval`)
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    max_sync_age: Option<String>,
+}
+
+fn default_canary_schedule() -> String {
+    "*/15 * * * *".to_string()
+}
+
+fn default_canary_timeout() -> String {
+    "1m".to_string()
 }
 
-#[derive(Debug, Deserialize, Default)]
+/// `runner.metrics`: where run metrics are exported after every run
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// node_exporter textfile collector file, rewritten after every run
+    pub textfile: Option<String>,
+    pub statsd: Option<StatsdConfig>,
+}
+
+/// StatsD (or DogStatsD) agent receiving run metrics over UDP
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StatsdConfig {
+    /// `host:port`
+    pub address: String,
+    #[serde(default = "default_statsd_prefix")]
+    pub prefix: String,
+    /// Send the job and outcome as DogStatsD tags instead of in the metric name
+    #[serde(default)]
+    pub dogstatsd: bool,
+}
+
+fn default_statsd_prefix() -> String {
+    "rollcron".to_string()
+}
+
+/// Value of an `env` entry: a literal (shell-expanded) or a secret reference
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum EnvValue {
+    Plain(String),
+    Secret(SecretRef),
+}
+
+/// Secret fetched from an external store at run time, e.g.
+/// `{from: vault, path: secret/data/etl#password}`
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SecretRef {
+    pub from: SecretSource,
+    /// Provider path, optionally followed by `#<field>`
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretSource {
+    Vault,
+    Ssm,
+}
+
+impl SecretSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecretSource::Vault => "vault",
+            SecretSource::Ssm => "ssm",
+        }
+    }
+}
+
+/// Decryption settings for encrypted env files (`*.age`, `*.enc`)
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+pub struct SecretsConfig {
+    /// age identity file (default: ~/.config/rollcron/age.key if present)
+    pub key_file: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default, JsonSchema)]
 struct RunnerConfigRaw {
     timezone: Option<String>,
     env_file: Option<String>,
-    env: Option<HashMap<String, String>>,
+    env: Option<HashMap<String, EnvValue>>,
     #[serde(default)]
     webhook: Vec<WebhookConfig>,
+    #[serde(default)]
+    secrets: SecretsConfig,
+    #[serde(default)]
+    shell: Shell,
+    ha: Option<HaConfigRaw>,
+    #[serde(default)]
+    blackout: Vec<BlackoutWindowRaw>,
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    long_job_threshold: Option<String>,
+    /// Runs allowed at once across all jobs; others wait in priority order
+    #[schemars(range(min = 1))]
+    max_concurrent_runs: Option<usize>,
+    #[serde(default)]
+    guards: GuardsConfigRaw,
+    #[serde(default)]
+    metrics: MetricsConfig,
+    canary: Option<CanaryConfigRaw>,
+    remove_orphan_dirs: Option<bool>,
+    brake: Option<BrakeConfigRaw>,
+    cron_format: Option<CronFormat>,
+    #[serde(default)]
+    allowed_work_dirs: Vec<String>,
+    #[serde(default)]
+    on_resume: ResumePolicy,
+    // Replaced by `--allowed-signers`; only read to refuse configs still setting them
+    #[serde(default)]
+    #[schemars(schema_with = "crate::schema::removed_signers")]
+    require_signed: Option<serde::de::IgnoredAny>,
+    #[serde(default)]
+    #[schemars(schema_with = "crate::schema::removed_signers")]
+    allowed_signers: Option<serde::de::IgnoredAny>,
+    allow_force_reset: Option<bool>,
+    #[serde(default)]
+    submodules: bool,
+    #[serde(default)]
+    lfs: bool,
+    #[serde(default)]
+    changelog: bool,
+    export: Option<ExportConfigRaw>,
+}
+
+/// `runner.cron_format`: the fields of cron expressions. Unset, 5 fields are
+/// classic cron and 6 or 7 start with seconds (and end with a year).
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CronFormat {
+    /// Exactly 5 fields: minute hour day-of-month month day-of-week
+    Standard,
+    /// Seconds first, optional year last, day-of-week 1 (Sunday) to 7 (Saturday)
+    Quartz,
+}
+
+/// `runner.on_resume`: what happens to runs missed while the host was suspended.
+/// Either way, the repository is synced once on resume.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResumePolicy {
+    /// One run per job that missed any, however many it missed
+    #[default]
+    CatchUp,
+    /// None; jobs wait for their next occurrence
+    Skip,
+}
+
+/// `runner.ha`: leader election between instances sharing this config
+#[derive(Debug, Deserialize, JsonSchema)]
+struct HaConfigRaw {
+    #[serde(flatten)]
+    lock: LockConfig,
+    /// Lease duration; the leader renews every third of it
+    #[serde(default = "default_ha_ttl")]
+    #[schemars(with = "crate::schema::Duration")]
+    ttl: String,
+}
+
+fn default_ha_ttl() -> String {
+    "15s".to_string()
+}
+
+/// Lock backend for HA leader election
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum LockConfig {
+    /// Lease file on shared storage
+    File { path: String },
+    /// `redis://[:password@]host[:port][/db]`
+    Redis {
+        url: String,
+        #[serde(default = "default_lock_key")]
+        key: String,
+    },
+    /// etcd v3 HTTP gateway, e.g. `http://etcd:2379`
+    Etcd {
+        url: String,
+        #[serde(default = "default_lock_key")]
+        key: String,
+    },
+}
+
+fn default_lock_key() -> String {
+    "rollcron/leader".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HaConfig {
+    pub lock: LockConfig,
+    pub ttl: Duration,
+}
+
+/// `blackout: [{from: "Sat 22:00", to: "Sun 02:00", policy: defer}]`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BlackoutWindowRaw {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub policy: BlackoutPolicy,
+}
+
+/// What happens to a scheduled run that falls inside a blackout window
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BlackoutPolicy {
+    /// Drop the run
+    #[default]
+    Skip,
+    /// Run once when the window ends (runs inside the window are coalesced)
+    Defer,
+}
+
+/// `guards`: host conditions checked right before a run starts
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GuardsConfigRaw {
+    /// Free space required on the job directory's filesystem, e.g. `5G`
+    #[schemars(with = "Option<crate::schema::Size>")]
+    pub min_free_disk: Option<String>,
+    /// Absolute path whose filesystem `min_free_disk` checks instead, e.g. a data volume
+    pub disk_path: Option<String>,
+    /// Highest 1-minute load average at which runs still start
+    #[schemars(extend("exclusiveMinimum" = 0))]
+    pub max_loadavg: Option<f64>,
+    pub policy: Option<GuardPolicy>,
+    /// How long a deferred run waits for the host to recover before it is skipped
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    pub max_delay: Option<String>,
+}
+
+/// What happens to a run whose guards fail
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GuardPolicy {
+    /// Drop the run
+    #[default]
+    Skip,
+    /// Recheck until the guards pass or `max_delay` runs out
+    Defer,
+}
+
+/// Runner guards overridden field by field by the job's
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guards {
+    /// Bytes
+    pub min_free_disk: Option<u64>,
+    /// None: the job directory
+    pub disk_path: Option<PathBuf>,
+    pub max_loadavg: Option<f64>,
+    pub policy: GuardPolicy,
+    pub max_delay: Duration,
+}
+
+/// `guards.max_delay` default
+const DEFAULT_GUARD_MAX_DELAY: Duration = Duration::from_secs(3600);
+
+impl Default for Guards {
+    fn default() -> Self {
+        Self {
+            min_free_disk: None,
+            disk_path: None,
+            max_loadavg: None,
+            policy: GuardPolicy::default(),
+            max_delay: DEFAULT_GUARD_MAX_DELAY,
+        }
+    }
+}
+
+impl Guards {
+    /// Whether any condition is set
+    pub fn is_set(&self) -> bool {
+        self.min_free_disk.is_some() || self.max_loadavg.is_some()
+    }
+}
+
+/// Period, in the job's timezone, during which scheduled runs don't start
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlackoutWindow {
+    pub window: TimeWindow,
+    pub policy: BlackoutPolicy,
+}
+
+/// Recurring wall-clock window: weekly (`Sat 22:00` to `Sun 02:00`) or daily
+/// (`23:00` to `01:00`). A window whose end is before its start wraps around
+/// the week or day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeWindow {
+    /// Seconds from the start of the period (Monday 00:00 for weekly windows)
+    pub start: u32,
+    /// Exclusive end, same reference as `start`
+    pub end: u32,
+    pub weekly: bool,
+    /// As written, e.g. `Sat 22:00-Sun 02:00`
+    pub label: String,
+}
+
+impl TimeWindow {
+    /// Parses `from`/`to` given as `HH:MM` or `Day HH:MM`.
+    pub fn parse(from: &str, to: &str) -> Result<Self> {
+        let label = format!("{}-{}", from, to);
+        let invalid = |reason: &str| anyhow!("Invalid window '{}': {}", label, reason);
+        let (start, start_weekly) = parse_window_time(from).ok_or_else(|| invalid("expected 'HH:MM' or 'Day HH:MM'"))?;
+        let (end, end_weekly) = parse_window_time(to).ok_or_else(|| invalid("expected 'HH:MM' or 'Day HH:MM'"))?;
+        if start_weekly != end_weekly {
+            return Err(invalid("give a day on both ends or on neither"));
+        }
+        if start == end {
+            return Err(invalid("window is empty"));
+        }
+        Ok(Self { start, end, weekly: start_weekly, label })
+    }
+
+    fn period(&self) -> u32 {
+        if self.weekly { 7 * 86400 } else { 86400 }
+    }
+
+    /// Seconds until the window ends if `local` is inside it.
+    pub fn remaining(&self, local: &NaiveDateTime) -> Option<u32> {
+        let period = self.period();
+        let day = if self.weekly { local.weekday().num_days_from_monday() * 86400 } else { 0 };
+        let pos = day + local.num_seconds_from_midnight();
+        let len = (self.end + period - self.start) % period;
+        let offset = (pos + period - self.start) % period;
+        (offset < len).then(|| len - offset)
+    }
 }
 
-#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Concurrency {
     Parallel,
@@ -103,57 +816,201 @@ pub enum Concurrency {
     Replace,
 }
 
-#[derive(Debug, Deserialize)]
-struct Config {
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(title = "rollcron.yaml", extend("anyOf" = [{ "required": ["jobs"] }, { "required": ["include"] }]))]
+pub(crate) struct Config {
     #[serde(default)]
     runner: RunnerConfigRaw,
-    jobs: HashMap<String, JobConfig>,
+    #[serde(default)]
+    defaults: DefaultsConfig,
+    /// Required unless `include` is given
+    jobs: Option<JobMap>,
+    /// Files with more jobs, relative to the repository root (globs allowed)
+    #[serde(default)]
+    include: Vec<String>,
+}
+
+/// `defaults`: settings every job inherits unless it sets them itself
+#[derive(Debug, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DefaultsConfig {
+    /// `run.timeout`
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    timeout: Option<String>,
+    /// `run.retry`
+    retry: Option<RetryConfigRaw>,
+    /// `schedule.timezone` (default: `runner.timezone`)
+    timezone: Option<String>,
+    /// `shell` (default: `runner.shell`)
+    shell: Option<Shell>,
+    severity: Option<Severity>,
+    notify_on_start: Option<bool>,
+    dedupe_alerts: Option<bool>,
+    circuit_breaker: Option<CircuitBreakerConfigRaw>,
+}
+
+/// Values a job falls back to, resolved from `runner` and `defaults`
+struct JobDefaults {
+    timezone: TimezoneConfig,
+    webhook: Vec<WebhookConfig>,
+    blackout: Vec<BlackoutWindow>,
+    shell: Shell,
+    long_job_threshold: Duration,
+    timeout: Duration,
+    retry: Option<RetryConfig>,
+    severity: Severity,
+    notify_on_start: Option<bool>,
+    dedupe_alerts: bool,
+    circuit_breaker: Option<CircuitBreakerConfig>,
+    guards: Guards,
+    cron_format: Option<CronFormat>,
+}
+
+/// A file listed in `include`: jobs only
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(title = "rollcron include file")]
+pub(crate) struct IncludedConfig {
+    #[serde(default)]
+    jobs: JobMap,
+}
+
+/// `jobs:` mapping in definition order, so jobs load (and are spawned,
+/// checked and listed) in the order they are written, whatever the YAML
+/// parser's map type. IDs are the keys, so reordering never changes them.
+#[derive(Debug, Default)]
+struct JobMap(Vec<(String, JobConfig)>);
+
+impl<'de> Deserialize<'de> for JobMap {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = JobMap;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of job IDs to jobs")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> std::result::Result<JobMap, A::Error> {
+                let mut jobs: Vec<(String, JobConfig)> = Vec::new();
+                while let Some((id, job)) = map.next_entry::<String, JobConfig>()? {
+                    if jobs.iter().any(|(existing, _)| *existing == id) {
+                        return Err(serde::de::Error::custom(format!("duplicate job '{}'", id)));
+                    }
+                    jobs.push((id, job));
+                }
+                Ok(JobMap(jobs))
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+impl JsonSchema for JobMap {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "JobMap".into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "Jobs keyed by ID",
+            "type": "object",
+            "propertyNames": { "pattern": "^[A-Za-z0-9_-]+$" },
+            "additionalProperties": generator.subschema_for::<JobConfig>()
+        })
+    }
 }
 
 /// Build configuration - supports shorthand string or full object
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum BuildConfigRaw {
-    /// Shorthand: `build: "cargo build"`
-    Simple(String),
+    /// Shorthand: `build: "cargo build"` or `build: [cargo, build]`
+    Simple(CommandLine),
     /// Full: `build: { sh: "cargo build", timeout: "30m", ... }`
     Full(BuildConfigFull),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct BuildConfigFull {
-    pub sh: String,
+    pub sh: CommandLine,
+    #[schemars(with = "Option<crate::schema::Duration>")]
     pub timeout: Option<String>,
     pub env_file: Option<String>,
-    pub env: Option<HashMap<String, String>>,
-    pub working_dir: Option<String>,
+    pub env: Option<HashMap<String, EnvValue>>,
+    pub working_dir: Option<WorkingDir>,
+}
+
+/// `working_dir: ./out`, or `working_dir: { path: /data/etl, create: true }`
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(from = "WorkingDirRaw")]
+pub struct WorkingDir {
+    /// Relative to the job's build/run directory, or absolute under `runner.allowed_work_dirs`
+    pub path: String,
+    /// Create the directory (and parents) if missing instead of failing the run
+    pub create: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum WorkingDirRaw {
+    Simple(String),
+    Full(WorkingDirFull),
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct WorkingDirFull {
+    path: String,
+    #[serde(default)]
+    create: bool,
+}
+
+impl From<WorkingDirRaw> for WorkingDir {
+    fn from(raw: WorkingDirRaw) -> Self {
+        match raw {
+            WorkingDirRaw::Simple(path) => WorkingDir { path, create: false },
+            WorkingDirRaw::Full(full) => WorkingDir { path: full.path, create: full.create },
+        }
+    }
 }
 
 /// Run configuration - supports shorthand string or full object
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum RunConfigRaw {
-    /// Shorthand: `run: "./app"`
-    Simple(String),
+    /// Shorthand: `run: "./app"` or `run: ["./app", "--flag"]`
+    Simple(CommandLine),
     /// Full: `run: { sh: "./app", timeout: "5m", ... }`
-    Full(RunConfigFull),
+    Full(Box<RunConfigFull>),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct RunConfigFull {
-    pub sh: String,
-    #[serde(default = "default_timeout")]
-    pub timeout: String,
+    pub sh: CommandLine,
+    /// Command run once after all retries of `sh` have failed
+    pub fallback_command: Option<CommandLine>,
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    pub timeout: Option<String>,
+    /// Kill the run after this long without output
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    pub stall_timeout: Option<String>,
     #[serde(default)]
     pub concurrency: Concurrency,
     pub retry: Option<RetryConfigRaw>,
-    pub working_dir: Option<String>,
+    pub working_dir: Option<WorkingDir>,
     pub env_file: Option<String>,
-    pub env: Option<HashMap<String, String>>,
+    pub env: Option<HashMap<String, EnvValue>>,
+    /// Output captured per stream; beyond it only the head and tail are kept
+    #[serde(default = "default_max_output")]
+    #[schemars(with = "crate::schema::Size")]
+    pub max_output: String,
 }
 
 /// Log configuration - supports shorthand string or full object
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum LogConfigRaw {
     /// Shorthand: `log: "output.log"`
@@ -162,14 +1019,61 @@ pub enum LogConfigRaw {
     Full(LogConfigFull),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct LogConfigFull {
     pub file: Option<String>,
     #[serde(default = "default_log_max_size")]
+    #[schemars(with = "crate::schema::Size")]
     pub max_size: String,
+    #[serde(default)]
+    pub compress: Compression,
+    #[schemars(range(min = 1))]
+    pub compress_level: Option<u32>,
+    #[serde(default)]
+    pub flush: LogFlush,
+}
+
+/// `log.flush`: when a run's output reaches the log file
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFlush {
+    /// As it is produced
+    #[default]
+    Stream,
+    /// In one piece when the run ends, so concurrent runs don't interleave
+    Run,
+}
+
+/// Compression for persisted run output (history) and rotated log files
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// File extension appended to compressed files (including the dot)
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+        }
+    }
+
+    fn level_range(&self) -> Option<(u32, u32)> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some((1, 9)),
+            Compression::Zstd => Some((1, 19)),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct JobConfig {
     pub name: Option<String>,
     pub schedule: ScheduleConfigRaw,
@@ -178,18 +1082,340 @@ pub struct JobConfig {
     pub log: Option<LogConfigRaw>,
     pub enabled: Option<bool>,
     pub env_file: Option<String>,
-    pub env: Option<HashMap<String, String>>,
-    pub working_dir: Option<String>,
+    /// Additional .env files loaded in order after `env_file` (later files override earlier)
+    #[serde(default)]
+    pub env_files: Vec<String>,
+    pub env: Option<HashMap<String, EnvValue>>,
+    pub working_dir: Option<WorkingDir>,
     #[serde(default)]
     pub webhook: Vec<WebhookConfig>,
+    /// Overrides `runner.shell` for this job's build and run commands
+    pub shell: Option<Shell>,
+    /// Trace file writes outside the job workspace (requires strace)
+    #[serde(default)]
+    pub audit: bool,
+    /// Free-form labels for selecting jobs (bulk operations, `--only-labels`), e.g. `team: data`
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// Host facts this host must match for the job to run here: a glob (`db-*`), a comparison
+    /// (`>=8G`) or a list of alternatives per fact
+    #[serde(default)]
+    #[schemars(extend("propertyNames" = { "pattern": "^[A-Za-z0-9_]+$" }))]
+    pub when: BTreeMap<String, FactPatternsRaw>,
+    /// Severity of this job's notifications, matched by webhook `filter.min_severity` (default: error)
+    pub severity: Option<Severity>,
+    /// Notify webhooks when a run starts (default: `expected_duration` >= `runner.long_job_threshold`)
+    pub notify_on_start: Option<bool>,
+    /// One failure notification per failing streak, then one on recovery
+    pub dedupe_alerts: Option<bool>,
+    /// With `dedupe_alerts`, notify again while still failing after this long
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    pub renotify_after: Option<String>,
+    /// Notify when a successful run's output differs from the previous one's
+    #[serde(default)]
+    pub notify_on_output_change: bool,
+    /// Typical run time, e.g. `3h`
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    pub expected_duration: Option<String>,
+    /// Also run once when rollcron starts
+    #[serde(default)]
+    pub run_on_start: bool,
+    /// Sync the repo right before each run
+    #[serde(default)]
+    pub pull_on_run: bool,
+    /// Shortest time between run starts, e.g. `10m`
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    pub min_interval: Option<String>,
+    /// cgroup v2 limits for the build and run commands
+    pub limits: Option<LimitsConfigRaw>,
+    /// seccomp filter and/or AppArmor profile for the build and run commands
+    pub security: Option<SecurityConfig>,
+    /// Landlock paths and network access for the build and run commands
+    pub sandbox: Option<SandboxConfig>,
+    /// Account (name or uid) the build and run commands run as
+    pub user: Option<AccountRaw>,
+    /// Group (name or gid) the build and run commands run as (default: the user's)
+    pub group: Option<AccountRaw>,
+    /// Windows during which scheduled runs are skipped or deferred (added to `runner.blackout`)
+    #[serde(default)]
+    pub blackout: Vec<BlackoutWindowRaw>,
+    /// Retention for files the job produces
+    pub cleanup: Option<CleanupConfig>,
+    /// Order among runs waiting for `runner.max_concurrent_runs`
+    pub priority: Option<PriorityRaw>,
+    /// Host conditions required to start a run (override `runner.guards`)
+    #[serde(default)]
+    pub guards: GuardsConfigRaw,
+    /// Warn when runs deviate from the job's recent history
+    pub slo: Option<SloConfigRaw>,
+    /// Stop (or slow down) scheduled runs after consecutive failures
+    pub circuit_breaker: Option<CircuitBreakerConfigRaw>,
+    /// When a completed run counts as a success (default: exit code 0)
+    pub success_when: Option<SuccessWhenRaw>,
+    /// Files from the work dir kept with each run
+    pub artifacts: Option<ArtifactsConfigRaw>,
+    /// Command checked before each run; a nonzero exit skips the run
+    #[schemars(length(min = 1))]
+    pub condition: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct RetryConfigRaw {
+/// Artifacts - supports a list of globs or a full object
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ArtifactsConfigRaw {
+    /// Shorthand: `artifacts: ["reports/*.csv"]`
+    Simple(#[schemars(length(min = 1))] Vec<String>),
+    /// Full: `artifacts: { paths: ["reports/*.csv"], keep: 20, upload: "..." }`
+    Full(ArtifactsConfigFull),
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ArtifactsConfigFull {
+    #[schemars(length(min = 1))]
+    pub paths: Vec<String>,
+    /// Runs whose artifacts are kept (default: 10)
+    #[schemars(range(min = 1))]
+    pub keep: Option<usize>,
+    /// Shell command run on `$ROLLCRON_ARTIFACTS_DIR` after collection
+    pub upload: Option<String>,
+}
+
+/// Files a run wrote under its work dir that are copied to its artifacts folder
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtifactsConfig {
+    /// Globs relative to the work dir
+    pub paths: Vec<String>,
+    /// Runs whose artifacts are kept
+    pub keep: usize,
+    /// Shell command run after collection, with `ROLLCRON_ARTIFACTS_DIR` set
+    pub upload: Option<String>,
+}
+
+/// Runs whose artifacts are kept unless `artifacts.keep` says otherwise
+const DEFAULT_ARTIFACTS_KEEP: usize = 10;
+
+/// `success_when: { exit_codes: [0, 2], stdout_matches: "OK", stderr_not_matches: "FATAL" }`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SuccessWhenRaw {
+    #[schemars(length(min = 1))]
+    pub exit_codes: Option<Vec<i32>>,
+    /// Regex stdout must match
+    pub stdout_matches: Option<String>,
+    /// Regex stderr must not match
+    pub stderr_not_matches: Option<String>,
+}
+
+/// Criteria a completed run must meet to count as a success
+#[derive(Debug, Clone)]
+pub struct SuccessWhen {
+    /// Exit codes that count as success
+    pub exit_codes: Vec<i32>,
+    pub stdout_matches: Option<Regex>,
+    pub stderr_not_matches: Option<Regex>,
+}
+
+/// `circuit_breaker: { failures: 5, action: backoff, backoff: 10m, max_backoff: 24h }`
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerConfigRaw {
+    #[schemars(range(min = 1))]
+    pub failures: u32,
+    #[serde(default)]
+    pub action: BreakerAction,
+    /// Gap before the first run once open, doubled after each further failure (`action: backoff`, default: 10m)
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    pub backoff: Option<String>,
+    /// Longest gap (default: 24h)
+    #[schemars(with = "Option<crate::schema::Duration>")]
+    pub max_backoff: Option<String>,
+}
+
+/// What an open circuit breaker does to scheduled runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BreakerAction {
+    /// Skip them until the breaker is reset
+    #[default]
+    Pause,
+    /// Let one through after a delay that doubles with every further failure
+    Backoff,
+}
+
+/// Consecutive failed runs that open a job's breaker, and what it does then
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerConfig {
+    pub failures: u32,
+    pub action: BreakerAction,
+    /// `backoff`: delay after the breaker opens
+    pub backoff: Duration,
+    /// `backoff`: longest delay
+    pub max_backoff: Duration,
+}
+
+/// `slo: { duration_factor: 3, failure_rate: 20, runs: 20 }`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SloConfigRaw {
+    /// A run longer than this multiple of the median successful run
+    #[schemars(extend("exclusiveMinimum" = 1))]
+    pub duration_factor: Option<f64>,
+    /// Percentage of failed runs, alerted when crossed
+    #[schemars(extend("exclusiveMinimum" = 0, "exclusiveMaximum" = 100))]
+    pub failure_rate: Option<f64>,
+    /// Recent runs compared against
+    #[serde(default = "default_slo_runs")]
+    #[schemars(range(min = 2))]
+    pub runs: usize,
+}
+
+fn default_slo_runs() -> usize {
+    20
+}
+
+/// Anomaly thresholds over the last `runs` runs of a job
+#[derive(Debug, Clone, PartialEq)]
+pub struct SloConfig {
+    /// A run longer than this multiple of the median successful run is slow
+    pub duration_factor: Option<f64>,
+    /// Fraction of failed runs (exclusive, 0-1) that raises an alert when crossed
+    pub failure_rate: Option<f64>,
+    pub runs: usize,
+}
+
+/// `priority: high` or `priority: 5`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum PriorityRaw {
+    Level(PriorityLevel),
+    Value(i32),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PriorityLevel {
+    High,
+    Normal,
+    Low,
+}
+
+impl PriorityRaw {
+    fn value(&self) -> i32 {
+        match self {
+            PriorityRaw::Level(PriorityLevel::High) => 10,
+            PriorityRaw::Level(PriorityLevel::Normal) => 0,
+            PriorityRaw::Level(PriorityLevel::Low) => -10,
+            PriorityRaw::Value(v) => *v,
+        }
+    }
+}
+
+/// `when.<fact>`: one pattern or a list of alternatives
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum FactPatternsRaw {
+    Many(#[schemars(length(min = 1))] Vec<FactPatternRaw>),
+    One(FactPatternRaw),
+}
+
+/// `linux`, `"db-*"`, `">=8G"` or a bare number
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum FactPatternRaw {
+    Number(u64),
+    Bool(bool),
+    Text(String),
+}
+
+impl FactPatternsRaw {
+    fn into_strings(self) -> Vec<String> {
+        let text = |p: FactPatternRaw| match p {
+            FactPatternRaw::Number(n) => n.to_string(),
+            FactPatternRaw::Bool(b) => b.to_string(),
+            FactPatternRaw::Text(s) => s,
+        };
+        match self {
+            FactPatternsRaw::Many(patterns) => patterns.into_iter().map(text).collect(),
+            FactPatternsRaw::One(pattern) => vec![text(pattern)],
+        }
+    }
+}
+
+/// `user: backup` or `user: 1001` (likewise `group`)
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum AccountRaw {
+    Id(u32),
+    Name(String),
+}
+
+/// `cleanup: { keep: 7, path: "backups/*.tar" }`: after a successful run,
+/// delete all but the newest `keep` matching files
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CleanupConfig {
+    /// Glob relative to the job directory, or absolute
+    pub path: String,
+    #[schemars(range(min = 1))]
+    pub keep: usize,
+}
+
+/// `limits: { memory: "512M", cpu: "0.5" }`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsConfigRaw {
+    #[schemars(with = "Option<crate::schema::Size>")]
+    pub memory: Option<String>,
+    pub cpu: Option<CpuLimitRaw>,
+}
+
+/// `security: { seccomp: "sec/etl.bpf", apparmor: "rollcron-etl" }` (Linux only)
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SecurityConfig {
+    /// Compiled BPF filter, relative to the job's working directory
+    pub seccomp: Option<String>,
+    /// Name of a loaded AppArmor profile
+    pub apparmor: Option<String>,
+}
+
+/// `sandbox: { read: [/srv/data], write: [out], network: false }` (Linux only)
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Readable in addition to the system directories (absolute, or relative to the working directory)
+    #[serde(default)]
+    pub read: Vec<String>,
+    /// Writable in addition to the job directory
+    #[serde(default)]
+    pub write: Vec<String>,
+    /// IPv4/IPv6 sockets allowed
+    #[serde(default = "default_sandbox_network")]
+    pub network: bool,
+}
+
+fn default_sandbox_network() -> bool {
+    true
+}
+
+/// CPU limit as a number (`0.5`) or string (`"0.5"`)
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum CpuLimitRaw {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RetryConfigRaw {
     #[serde(default)]
+    #[schemars(range(min = 1))]
     pub max: u32,
     #[serde(default = "default_retry_delay")]
+    #[schemars(with = "crate::schema::Duration")]
     pub delay: String,
+    #[schemars(with = "Option<crate::schema::Duration>")]
     pub jitter: Option<String>,
 }
 
@@ -198,7 +1424,7 @@ fn default_retry_delay() -> String {
 }
 
 /// Schedule configuration - supports shorthand string or full object
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum ScheduleConfigRaw {
     /// Shorthand: `schedule: "*/5 * * * *"`
@@ -207,49 +1433,169 @@ pub enum ScheduleConfigRaw {
     Full(ScheduleConfigFull),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ScheduleConfigFull {
     pub cron: String,
     pub timezone: Option<String>,
 }
 
-fn default_timeout() -> String {
-    "1h".to_string()
-}
+/// `run.timeout` default
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// `runner.long_job_threshold` default
+const DEFAULT_LONG_JOB_THRESHOLD: Duration = Duration::from_secs(3600);
 
 fn default_log_max_size() -> String {
     "10M".to_string()
 }
 
+/// Smallest `run.max_output`: room for a useful head and tail
+const MIN_MAX_OUTPUT: u64 = 1024;
+
+fn default_max_output() -> String {
+    "1M".to_string()
+}
+
 #[derive(Debug, Clone)]
 pub struct BuildConfig {
-    pub command: String,
+    pub command: CommandLine,
     pub timeout: Duration,
     pub env_file: Option<String>,
-    pub env: Option<HashMap<String, String>>,
-    pub working_dir: Option<String>,
+    pub env: Option<HashMap<String, EnvValue>>,
+    pub working_dir: Option<WorkingDir>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Job {
     pub id: String,
     pub name: String,
-    pub schedule: Cron,
+    /// Where the job is defined: `file:line` (file only for the canary)
+    pub source: String,
+    /// None for `@reboot`: the job only runs at daemon startup
+    pub schedule: Option<Cron>,
     pub build: Option<BuildConfig>,
-    pub command: String,
+    pub command: CommandLine,
+    pub fallback_command: Option<CommandLine>,
+    /// Job `shell`, or the runner's
+    pub shell: Shell,
     pub timeout: Duration,
+    /// Runs without output on stdout or stderr for this long are killed as stalled
+    pub stall_timeout: Option<Duration>,
     pub concurrency: Concurrency,
     pub retry: Option<RetryConfig>,
-    pub working_dir: Option<String>,
+    pub working_dir: Option<WorkingDir>,
     pub enabled: bool,
     pub timezone: Option<TimezoneConfig>,
     pub env_file: Option<String>,
-    pub env: Option<HashMap<String, String>>,
+    pub env_files: Vec<String>,
+    pub env: Option<HashMap<String, EnvValue>>,
     pub run_env_file: Option<String>,
-    pub run_env: Option<HashMap<String, String>>,
+    pub run_env: Option<HashMap<String, EnvValue>>,
     pub webhook: Vec<WebhookConfig>,
     pub log_file: Option<String>,
     pub log_max_size: u64,
+    pub log_compress: Compression,
+    pub log_compress_level: Option<u32>,
+    pub log_flush: LogFlush,
+    /// Bytes of stdout and of stderr kept in memory and history (head + tail)
+    pub max_output: u64,
+    pub audit: bool,
+    pub labels: HashMap<String, String>,
+    /// `when`: host facts this host must match
+    pub when: Vec<Condition>,
+    pub severity: Severity,
+    /// Send a `job_started` notification when a run starts
+    pub notify_on_start: bool,
+    /// Notify once per failing streak, and `job_recovered` when it ends
+    pub dedupe_alerts: bool,
+    pub renotify_after: Option<Duration>,
+    /// `output_changed` when a successful run's output differs from the previous one's
+    pub notify_on_output_change: bool,
+    pub expected_duration: Option<Duration>,
+    /// Run once at daemon startup, in addition to the schedule
+    pub run_on_start: bool,
+    /// Sync the repo before each run and run the job at the commit it finds
+    pub pull_on_run: bool,
+    /// Runs starting sooner than this after the previous start are skipped
+    pub min_interval: Option<Duration>,
+    pub limits: Option<ResourceLimits>,
+    pub security: Option<SecurityConfig>,
+    pub sandbox: Option<SandboxConfig>,
+    pub user: Option<String>,
+    pub group: Option<String>,
+    /// Runner windows followed by the job's own
+    pub blackout: Vec<BlackoutWindow>,
+    pub cleanup: Option<CleanupConfig>,
+    /// Higher runs first when runs wait for a slot (`high` = 10, `low` = -10)
+    pub priority: i32,
+    pub guards: Guards,
+    pub slo: Option<SloConfig>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// None: exit code 0
+    pub success_when: Option<SuccessWhen>,
+    pub artifacts: Option<ArtifactsConfig>,
+    /// Shell command run before the command; runs where it exits nonzero are skipped
+    pub condition: Option<String>,
+}
+
+/// Resource limits applied to a job's build and run commands via a transient cgroup
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceLimits {
+    /// `memory.max` in bytes
+    pub memory: Option<u64>,
+    /// CPU cores (0.5 = half a core)
+    pub cpu: Option<f64>,
+}
+
+/// `--only-labels` entry: `key` (any value) or `key=value`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelSelector {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+impl std::str::FromStr for LabelSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (key, value) = match s.split_once(['=', ':']) {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (s, None),
+        };
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(format!("expected KEY or KEY=VALUE, got '{}'", s));
+        }
+        Ok(LabelSelector { key: key.to_string(), value })
+    }
+}
+
+impl std::fmt::Display for LabelSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}={}", self.key, value),
+            None => f.write_str(&self.key),
+        }
+    }
+}
+
+impl Job {
+    /// Whether the job's labels match one of `selectors` (always true when empty).
+    pub fn has_any_label(&self, selectors: &[LabelSelector]) -> bool {
+        selectors.is_empty()
+            || selectors.iter().any(|s| {
+                self.labels.get(&s.key).is_some_and(|value| s.value.as_ref().is_none_or(|want| want == value))
+            })
+    }
+
+    /// Why the job doesn't run on a host with `facts`, or None if its `when` matches.
+    pub fn host_mismatch(&self, facts: &Facts) -> Option<String> {
+        crate::facts::mismatch(&self.when, facts)
+    }
+
+    /// The cron pattern, or `@reboot`
+    pub fn schedule_str(&self) -> &str {
+        self.schedule.as_ref().map_or("@reboot", Cron::as_str)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -259,52 +1605,303 @@ pub struct RetryConfig {
     pub jitter: Option<Duration>,
 }
 
+/// Name of the config file at the repository root
+pub const CONFIG_FILE: &str = "rollcron.yaml";
+
+/// Loads `rollcron.yaml` in `dir` and the files it includes. Invalid jobs are
+/// skipped with a warning.
+pub fn load_config(dir: &Path) -> Result<(RunnerConfig, Vec<Job>)> {
+    let config_path = dir.join(CONFIG_FILE);
+    let content = std::fs::read_to_string(&config_path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", config_path.display(), e))?;
+    let (runner, jobs, _) = parse_config_in(&content, Some(dir))?;
+    Ok((runner, jobs))
+}
+
+/// Parses a config without includes (`include` is an error).
+#[cfg(test)]
 pub fn parse_config(content: &str) -> Result<(RunnerConfig, Vec<Job>)> {
+    let (runner, jobs, _) = parse_config_in(content, None)?;
+    Ok((runner, jobs))
+}
+
+/// Parses a config, resolving `include` against `root`. Also returns the IDs
+/// of skipped (invalid) jobs.
+pub fn parse_config_in(content: &str, root: Option<&Path>) -> Result<(RunnerConfig, Vec<Job>, Vec<String>)> {
     let config: Config =
         serde_yaml::from_str(content).map_err(|e| anyhow!("Failed to parse YAML: {}", e))?;
+    if config.jobs.is_none() && config.include.is_empty() {
+        anyhow::bail!("Failed to parse YAML: missing field `jobs`");
+    }
 
-    let timezone = match config.runner.timezone {
-        None => TimezoneConfig::Utc,
-        Some(s) if s == "inherit" => TimezoneConfig::Inherit,
-        Some(s) => TimezoneConfig::Named(
-            s.parse::<Tz>()
-                .map_err(|e| anyhow!("Invalid timezone '{}': {}", s, e))?,
-        ),
-    };
+    // Job definitions with where they come from (`file:line`)
+    let lines = job_lines(content);
+    let mut job_configs: Vec<(String, JobConfig, String)> = config
+        .jobs
+        .unwrap_or_default()
+        .0
+        .into_iter()
+        .map(|(id, job)| {
+            let source = located(CONFIG_FILE, &lines, &id);
+            (id, job, source)
+        })
+        .collect();
+    if !config.include.is_empty() {
+        let root = root.ok_or_else(|| anyhow!("include is only supported when loading a repository"))?;
+        let mut sources: HashMap<String, String> =
+            job_configs.iter().map(|(id, _, source)| (id.clone(), source.clone())).collect();
+        for pattern in &config.include {
+            for path in expand_include(root, pattern)? {
+                let file = path.strip_prefix(root).unwrap_or(&path).display().to_string();
+                let content = std::fs::read_to_string(&path).map_err(|e| anyhow!("Failed to read {}: {}", file, e))?;
+                let included: IncludedConfig =
+                    serde_yaml::from_str(&content).map_err(|e| anyhow!("Failed to parse {}: {}", file, e))?;
+                let lines = job_lines(&content);
+                for (id, job) in included.jobs.0 {
+                    let source = located(&file, &lines, &id);
+                    if let Some(first) = sources.insert(id.clone(), source.clone()) {
+                        anyhow::bail!("Duplicate job '{}' in {} (already defined in {})", id, source, first);
+                    }
+                    job_configs.push((id, job, source));
+                }
+            }
+        }
+    }
 
-    // Extract runner webhook for use in job defaults
-    let runner_webhook = config.runner.webhook;
+    let timezone = config.runner.timezone.as_deref().map(parse_timezone).transpose()?.unwrap_or_default();
+    let long_job_threshold = match config.runner.long_job_threshold {
+        Some(t) => parse_duration(&t).map_err(|e| anyhow!("Invalid runner.long_job_threshold '{}': {}", t, e))?,
+        None => DEFAULT_LONG_JOB_THRESHOLD,
+    };
+    let runner_blackout = parse_blackout(config.runner.blackout).map_err(|e| anyhow!("runner.{}", e))?;
+    if config.runner.max_concurrent_runs == Some(0) {
+        anyhow::bail!("Invalid runner.max_concurrent_runs '0': must be at least 1");
+    }
+    if let Some(textfile) = &config.runner.metrics.textfile {
+        if !textfile.ends_with(".prom") {
+            anyhow::bail!("Invalid runner.metrics.textfile '{}': node_exporter only reads *.prom files", textfile);
+        }
+    }
+    if let Some(statsd) = &config.runner.metrics.statsd {
+        if !statsd.address.rsplit_once(':').is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok()) {
+            anyhow::bail!("Invalid runner.metrics.statsd.address '{}': expected host:port", statsd.address);
+        }
+    }
+    let allowed_work_dirs = config
+        .runner
+        .allowed_work_dirs
+        .iter()
+        .map(|dir| match Path::new(dir).is_absolute() {
+            true => Ok(PathBuf::from(dir)),
+            false => Err(anyhow!("Invalid runner.allowed_work_dirs '{}': must be an absolute path", dir)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let runner_guards = parse_guards(config.runner.guards, &Guards::default()).map_err(|e| anyhow!("runner.{}", e))?;
+    // The repo can't vouch for itself: one unsigned commit could drop the check
+    if config.runner.require_signed.is_some() || config.runner.allowed_signers.is_some() {
+        anyhow::bail!(
+            "runner.require_signed and runner.allowed_signers are no longer read from the repo; \
+             list the keys in a host file and pass it with --allowed-signers"
+        );
+    }
 
     let runner = RunnerConfig {
         timezone: timezone.clone(),
         env_file: config.runner.env_file,
         env: config.runner.env,
-        webhook: runner_webhook.clone(),
+        webhook: config.runner.webhook,
+        secrets: config.runner.secrets,
+        shell: config.runner.shell,
+        ha: config.runner.ha.map(parse_ha).transpose()?,
+        long_job_threshold,
+        max_concurrent_runs: config.runner.max_concurrent_runs,
+        metrics: config.runner.metrics,
+        canary: None,
+        remove_orphan_dirs: config.runner.remove_orphan_dirs.unwrap_or(true),
+        brake: config.runner.brake.map(parse_brake).transpose().map_err(|e| anyhow!("runner.brake: {}", e))?,
+        allowed_work_dirs,
+        on_resume: config.runner.on_resume,
+        allow_force_reset: config.runner.allow_force_reset.unwrap_or(true),
+        submodules: config.runner.submodules,
+        lfs: config.runner.lfs,
+        changelog: config.runner.changelog,
+        export: config.runner.export.map(parse_export).transpose().map_err(|e| anyhow!("runner.export: {}", e))?,
     };
 
-    let jobs = config
-        .jobs
-        .into_iter()
-        .filter_map(
-            |(id, job)| match parse_job(&id, job, &timezone, &runner_webhook) {
-                Ok(job) => Some(job),
-                Err(e) => {
-                    warn!("Skipping job '{}': {}", id, e);
-                    None
-                }
-            },
-        )
-        .collect();
+    let defaults = config.defaults;
+    let job_defaults = JobDefaults {
+        timezone: match defaults.timezone {
+            Some(tz) => parse_timezone(&tz).map_err(|e| anyhow!("defaults.{}", e))?,
+            None => timezone,
+        },
+        webhook: runner.webhook.clone(),
+        blackout: runner_blackout,
+        shell: defaults.shell.unwrap_or(runner.shell),
+        long_job_threshold: runner.long_job_threshold,
+        timeout: match defaults.timeout {
+            Some(t) => parse_duration(&t).map_err(|e| anyhow!("Invalid defaults.timeout '{}': {}", t, e))?,
+            None => DEFAULT_TIMEOUT,
+        },
+        retry: defaults.retry.map(parse_retry).transpose().map_err(|e| anyhow!("defaults.{}", e))?,
+        severity: defaults.severity.unwrap_or_default(),
+        notify_on_start: defaults.notify_on_start,
+        dedupe_alerts: defaults.dedupe_alerts.unwrap_or(false),
+        circuit_breaker: defaults
+            .circuit_breaker
+            .map(parse_circuit_breaker)
+            .transpose()
+            .map_err(|e| anyhow!("defaults.circuit_breaker: {}", e))?,
+        guards: runner_guards,
+        cron_format: config.runner.cron_format,
+    };
 
-    Ok((runner, jobs))
+    let canary = config
+        .runner
+        .canary
+        .map(|raw| parse_canary(raw, &job_defaults))
+        .transpose()
+        .map_err(|e| anyhow!("runner.canary: {}", e))?;
+    if canary.is_some() {
+        if let Some((_, _, source)) = job_configs.iter().find(|(id, _, _)| id == CANARY_JOB_ID) {
+            anyhow::bail!("Job ID '{}' ({}) is reserved while runner.canary is enabled", CANARY_JOB_ID, source);
+        }
+    }
+    let runner = RunnerConfig { canary, ..runner };
+
+    let mut skipped = Vec::new();
+    let mut jobs = Vec::new();
+    for (id, job, source) in job_configs {
+        match parse_job(&id, job, &source, &job_defaults) {
+            Ok(job) => jobs.push(job),
+            Err(e) => {
+                warn!("Skipping job '{}' ({}): {}", id, source, e);
+                skipped.push(id);
+            }
+        }
+    }
+
+    Ok((runner, jobs, skipped))
+}
+
+/// Lines (1-based) of the job keys under a top-level block `jobs:` mapping.
+/// serde_yaml keeps no spans, so this reads the text; jobs it can't place
+/// (flow style) just get no line.
+fn job_lines(content: &str) -> HashMap<String, usize> {
+    let mut lines = HashMap::new();
+    let mut in_jobs = false;
+    let mut indent = None;
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let depth = line.len() - trimmed.len();
+        if depth == 0 {
+            in_jobs = trimmed
+                .strip_prefix("jobs:")
+                .is_some_and(|rest| rest.trim().is_empty() || rest.trim_start().starts_with('#'));
+            indent = None;
+            continue;
+        }
+        if in_jobs && *indent.get_or_insert(depth) == depth {
+            if let Some((key, _)) = trimmed.split_once(':') {
+                lines.entry(key.trim().trim_matches(['"', '\'']).to_string()).or_insert(i + 1);
+            }
+        }
+    }
+    lines
+}
+
+/// `file:line` of job `id`, or `file` when its line is unknown
+fn located(file: &str, lines: &HashMap<String, usize>, id: &str) -> String {
+    match lines.get(id) {
+        Some(line) => format!("{}:{}", file, line),
+        None => file.to_string(),
+    }
+}
+
+/// Files matching an `include` pattern under `root`, sorted.
+fn expand_include(root: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty() && *s != ".").collect();
+    if Path::new(pattern).is_absolute() || segments.is_empty() || segments.contains(&"..") {
+        anyhow::bail!("Invalid include '{}': must be a relative path inside the repository", pattern);
+    }
+    let matches = glob(root, pattern)?;
+    if matches.is_empty() && !pattern.contains(['*', '?']) {
+        anyhow::bail!("Included file '{}' not found", pattern);
+    }
+    Ok(matches)
+}
+
+/// Files matching `pattern` under `root`, sorted. `*` and `?` match within a
+/// path segment, `**` any number of directories (hidden entries never match
+/// a wildcard).
+pub fn glob(root: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty() && *s != ".").collect();
+    let mut matches = Vec::new();
+    walk_glob(root, &segments, &mut matches)?;
+    matches.sort();
+    matches.dedup();
+    Ok(matches)
+}
+
+fn walk_glob(dir: &Path, segments: &[&str], out: &mut Vec<PathBuf>) -> Result<()> {
+    let Some((first, rest)) = segments.split_first() else {
+        return Ok(());
+    };
+    if !first.contains(['*', '?']) {
+        let path = dir.join(first);
+        if rest.is_empty() {
+            if path.is_file() {
+                out.push(path);
+            }
+        } else if path.is_dir() {
+            walk_glob(&path, rest, out)?;
+        }
+        return Ok(());
+    }
+
+    let entries: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok().map(|e| e.path())).collect(),
+        Err(_) => return Ok(()),
+    };
+    let visible = |path: &PathBuf| !path.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.'));
+    if *first == "**" {
+        walk_glob(dir, rest, out)?;
+        for sub in entries.iter().filter(|p| p.is_dir() && visible(p)) {
+            walk_glob(sub, segments, out)?;
+        }
+        return Ok(());
+    }
+
+    let matcher = Regex::new(&format!(
+        "^{}$",
+        first
+            .chars()
+            .map(|c| match c {
+                '*' => "[^/]*".to_string(),
+                '?' => "[^/]".to_string(),
+                c => regex::escape(&c.to_string()),
+            })
+            .collect::<String>()
+    ))?;
+    for path in entries.iter().filter(|p| visible(p)) {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if !matcher.is_match(&name) {
+            continue;
+        }
+        if rest.is_empty() {
+            if path.is_file() {
+                out.push(path.clone());
+            }
+        } else if path.is_dir() {
+            walk_glob(path, rest, out)?;
+        }
+    }
+    Ok(())
 }
 
-fn parse_job(
-    id: &str,
-    job: JobConfig,
-    timezone: &TimezoneConfig,
-    runner_webhook: &[WebhookConfig],
-) -> Result<Job> {
+fn parse_job(id: &str, job: JobConfig, source: &str, defaults: &JobDefaults) -> Result<Job> {
     validate_job_id(id)?;
 
     // Extract schedule config
@@ -313,25 +1910,51 @@ fn parse_job(
         ScheduleConfigRaw::Full(full) => (full.cron, full.timezone),
     };
 
-    let schedule = parse_schedule(&cron_expr)?;
+    let schedule = parse_schedule(&cron_expr, defaults.cron_format)?;
+    let reboot = schedule.is_none();
 
     // Extract run config
-    let (run_sh, run_timeout, run_concurrency, run_retry, run_working_dir, run_env_file, run_env) =
+    let (run_sh, run_fallback, run_timeout, run_stall_timeout, run_concurrency, run_retry, run_working_dir, run_env_file, run_env, max_output) =
         match job.run {
-            RunConfigRaw::Simple(sh) => (sh, default_timeout(), Concurrency::default(), None, None, None, None),
-            RunConfigRaw::Full(full) => (
-                full.sh,
-                full.timeout,
-                full.concurrency,
-                full.retry,
-                full.working_dir,
-                full.env_file,
-                full.env,
-            ),
+            RunConfigRaw::Simple(sh) => {
+                (sh, None, None, None, Concurrency::default(), None, None, None, None, default_max_output())
+            }
+            RunConfigRaw::Full(full) => {
+                let full = *full;
+                (
+                    full.sh,
+                    full.fallback_command,
+                    full.timeout,
+                    full.stall_timeout,
+                    full.concurrency,
+                    full.retry,
+                    full.working_dir,
+                    full.env_file,
+                    full.env,
+                    full.max_output,
+                )
+            }
         };
 
-    let timeout = parse_duration(&run_timeout)
-        .map_err(|e| anyhow!("Invalid run.timeout '{}': {}", run_timeout, e))?;
+    run_sh.validate("run")?;
+    if let Some(fallback) = &run_fallback {
+        fallback.validate("run.fallback_command")?;
+    }
+
+    let timeout = run_timeout
+        .map(|t| parse_duration(&t).map_err(|e| anyhow!("Invalid run.timeout '{}': {}", t, e)))
+        .transpose()?
+        .unwrap_or(defaults.timeout);
+    let stall_timeout = run_stall_timeout
+        .map(|t| parse_duration(&t).map_err(|e| anyhow!("Invalid run.stall_timeout '{}': {}", t, e)))
+        .transpose()?;
+    if stall_timeout.is_some_and(|s| s >= timeout) {
+        anyhow::bail!("run.stall_timeout must be shorter than run.timeout");
+    }
+    let max_output = match parse_size(&max_output) {
+        Ok(bytes) if bytes >= MIN_MAX_OUTPUT => bytes,
+        _ => anyhow::bail!("Invalid run.max_output '{}': must be a size of at least 1K", max_output),
+    };
 
     // Extract build config
     let build = job
@@ -341,6 +1964,7 @@ fn parse_job(
                 BuildConfigRaw::Simple(sh) => (sh, None, None, None, None),
                 BuildConfigRaw::Full(full) => (full.sh, full.timeout, full.env_file, full.env, full.working_dir),
             };
+            build_sh.validate("build")?;
             let build_timeout = build_timeout_str
                 .map(|t| parse_duration(&t).map_err(|e| anyhow!("Invalid build.timeout '{}': {}", t, e)))
                 .transpose()?
@@ -357,142 +1981,587 @@ fn parse_job(
 
     let name = job.name.unwrap_or_else(|| id.to_string());
 
-    let retry = run_retry
-        .map(|r| {
-            if r.max == 0 {
-                anyhow::bail!(
-                    "Invalid retry.max '0': must be at least 1 (use no retry config to disable retries)"
-                );
-            }
-            let delay = parse_duration(&r.delay)
-                .map_err(|e| anyhow!("Invalid retry delay '{}': {}", r.delay, e))?;
-            let jitter = r
-                .jitter
-                .map(|j| parse_duration(&j).map_err(|e| anyhow!("Invalid retry jitter '{}': {}", j, e)))
-                .transpose()?;
-            Ok::<_, anyhow::Error>(RetryConfig {
-                max: r.max,
-                delay,
-                jitter,
-            })
-        })
-        .transpose()?;
+    let retry = match run_retry {
+        Some(r) => Some(parse_retry(r)?),
+        None => defaults.retry.clone(),
+    };
 
-    let job_timezone = schedule_timezone
-        .map(|tz| {
-            if tz == "inherit" {
-                Ok(TimezoneConfig::Inherit)
-            } else {
-                tz.parse::<Tz>()
-                    .map(TimezoneConfig::Named)
-                    .map_err(|e| anyhow!("Invalid timezone '{}': {}", tz, e))
-            }
-        })
-        .transpose()?
-        .or(Some(timezone.clone()));
+    let job_timezone = Some(match schedule_timezone {
+        Some(tz) => parse_timezone(&tz)?,
+        None => defaults.timezone.clone(),
+    });
 
     // Job webhooks extend runner webhooks
-    let mut webhook = runner_webhook.to_vec();
+    let mut webhook = defaults.webhook.clone();
     webhook.extend(job.webhook);
 
-    let (log_file, log_max_size) = match job.log {
-        Some(LogConfigRaw::Simple(file)) => (Some(file), parse_size(&default_log_max_size()).unwrap()),
+    let expected_duration = job
+        .expected_duration
+        .map(|d| parse_duration(&d).map_err(|e| anyhow!("Invalid expected_duration '{}': {}", d, e)))
+        .transpose()?;
+    let notify_on_start = job
+        .notify_on_start
+        .or(defaults.notify_on_start)
+        .unwrap_or_else(|| expected_duration.is_some_and(|d| d >= defaults.long_job_threshold));
+    let min_interval = job
+        .min_interval
+        .map(|d| parse_duration(&d).map_err(|e| anyhow!("Invalid min_interval '{}': {}", d, e)))
+        .transpose()?;
+    let dedupe_alerts = job.dedupe_alerts.unwrap_or(defaults.dedupe_alerts);
+    let renotify_after = job
+        .renotify_after
+        .map(|d| parse_duration(&d).map_err(|e| anyhow!("Invalid renotify_after '{}': {}", d, e)))
+        .transpose()?;
+    if renotify_after.is_some() && !dedupe_alerts {
+        anyhow::bail!("renotify_after needs dedupe_alerts: true");
+    }
+
+    if let Some(cleanup) = &job.cleanup {
+        if !cleanup.path.contains(['*', '?']) {
+            anyhow::bail!("Invalid cleanup.path '{}': must contain a wildcard (`*` or `?`)", cleanup.path);
+        }
+        if cleanup.keep == 0 {
+            anyhow::bail!("Invalid cleanup.keep '0': must be at least 1");
+        }
+    }
+
+    let slo = job.slo.map(parse_slo).transpose().map_err(|e| anyhow!("slo: {}", e))?;
+    let circuit_breaker = match job.circuit_breaker {
+        Some(raw) => Some(parse_circuit_breaker(raw).map_err(|e| anyhow!("circuit_breaker: {}", e))?),
+        None => defaults.circuit_breaker.clone(),
+    };
+    let success_when = job
+        .success_when
+        .map(parse_success_when)
+        .transpose()
+        .map_err(|e| anyhow!("success_when: {}", e))?;
+    let artifacts = job.artifacts.map(parse_artifacts).transpose().map_err(|e| anyhow!("artifacts: {}", e))?;
+    if job.condition.as_deref().is_some_and(|command| command.trim().is_empty()) {
+        anyhow::bail!("condition must not be empty");
+    }
+
+    let mut blackout = defaults.blackout.clone();
+    blackout.extend(parse_blackout(job.blackout)?);
+    let guards = parse_guards(job.guards, &defaults.guards)?;
+
+    let default_max_size = parse_size(&default_log_max_size()).unwrap();
+    let (log_file, log_max_size, log_compress, log_compress_level, log_flush) = match job.log {
+        Some(LogConfigRaw::Simple(file)) => (Some(file), default_max_size, Compression::None, None, LogFlush::Stream),
         Some(LogConfigRaw::Full(log)) => {
             let max_size =
                 parse_size(&log.max_size).map_err(|e| anyhow!("Invalid log.max_size: {}", e))?;
-            (log.file, max_size)
+            if let Some(level) = log.compress_level {
+                match log.compress.level_range() {
+                    None => anyhow::bail!("log.compress_level requires log.compress (gzip or zstd)"),
+                    Some((min, max)) if level < min || level > max => anyhow::bail!(
+                        "Invalid log.compress_level '{}': must be between {} and {}",
+                        level,
+                        min,
+                        max
+                    ),
+                    Some(_) => {}
+                }
+            }
+            (log.file, max_size, log.compress, log.compress_level, log.flush)
         }
-        None => (None, parse_size(&default_log_max_size()).unwrap()),
+        None => (None, default_max_size, Compression::None, None, LogFlush::Stream),
     };
 
     Ok(Job {
         id: id.to_string(),
         name,
+        source: source.to_string(),
         schedule,
         build,
         command: run_sh,
+        fallback_command: run_fallback,
+        shell: job.shell.unwrap_or(defaults.shell),
         timeout,
+        stall_timeout,
         concurrency: run_concurrency,
         retry,
         working_dir: run_working_dir.or(job.working_dir),
         enabled: job.enabled.unwrap_or(true),
         timezone: job_timezone,
         env_file: job.env_file,
+        env_files: job.env_files,
         env: job.env,
         run_env_file,
         run_env,
         webhook,
         log_file,
         log_max_size,
+        log_compress,
+        log_compress_level,
+        log_flush,
+        max_output,
+        audit: job.audit,
+        labels: job.labels,
+        when: job
+            .when
+            .into_iter()
+            .map(|(fact, patterns)| Condition::parse(&fact, &patterns.into_strings()))
+            .collect::<Result<_>>()?,
+        severity: job.severity.unwrap_or(defaults.severity),
+        notify_on_start,
+        dedupe_alerts,
+        renotify_after,
+        notify_on_output_change: job.notify_on_output_change,
+        expected_duration,
+        run_on_start: job.run_on_start || reboot,
+        pull_on_run: job.pull_on_run,
+        min_interval,
+        limits: job.limits.map(parse_limits).transpose()?,
+        security: job.security.map(parse_security).transpose()?,
+        sandbox: job.sandbox.map(parse_sandbox).transpose()?,
+        user: job.user.map(|user| parse_account("user", user)).transpose()?,
+        group: job.group.map(|group| parse_account("group", group)).transpose()?,
+        blackout,
+        cleanup: job.cleanup,
+        priority: job.priority.as_ref().map_or(0, PriorityRaw::value),
+        guards,
+        slo,
+        circuit_breaker,
+        success_when,
+        artifacts,
+        condition: job.condition,
     })
 }
 
-fn parse_duration(s: &str) -> Result<Duration> {
-    let s = s.trim();
-    if let Some(millis) = s.strip_suffix("ms") {
-        Ok(Duration::from_millis(millis.parse()?))
-    } else if let Some(secs) = s.strip_suffix('s') {
-        Ok(Duration::from_secs(secs.parse()?))
-    } else if let Some(mins) = s.strip_suffix('m') {
-        Ok(Duration::from_secs(mins.parse::<u64>()? * 60))
-    } else if let Some(hours) = s.strip_suffix('h') {
-        Ok(Duration::from_secs(hours.parse::<u64>()? * 3600))
-    } else {
-        Ok(Duration::from_secs(s.parse()?))
+fn parse_artifacts(raw: ArtifactsConfigRaw) -> Result<ArtifactsConfig> {
+    let (paths, keep, upload) = match raw {
+        ArtifactsConfigRaw::Simple(paths) => (paths, None, None),
+        ArtifactsConfigRaw::Full(full) => (full.paths, full.keep, full.upload),
+    };
+    if paths.is_empty() {
+        anyhow::bail!("list at least one path");
+    }
+    for path in &paths {
+        if Path::new(path).is_absolute() || path.split('/').any(|segment| segment == "..") {
+            anyhow::bail!("Invalid path '{}': must stay inside the work dir", path);
+        }
+    }
+    let keep = keep.unwrap_or(DEFAULT_ARTIFACTS_KEEP);
+    if keep == 0 {
+        anyhow::bail!("Invalid keep '0': must be at least 1");
+    }
+    if upload.as_deref().is_some_and(|command| command.trim().is_empty()) {
+        anyhow::bail!("upload must not be empty");
     }
+    Ok(ArtifactsConfig { paths, keep, upload })
 }
 
-fn parse_size(s: &str) -> Result<u64> {
-    let s = s.trim();
-    if let Some(n) = s.strip_suffix('G') {
-        Ok(n.parse::<u64>()? * 1024 * 1024 * 1024)
-    } else if let Some(n) = s.strip_suffix('M') {
-        Ok(n.parse::<u64>()? * 1024 * 1024)
-    } else if let Some(n) = s.strip_suffix('K') {
-        Ok(n.parse::<u64>()? * 1024)
-    } else {
-        Ok(s.parse()?)
+fn parse_success_when(raw: SuccessWhenRaw) -> Result<SuccessWhen> {
+    let exit_codes = raw.exit_codes.unwrap_or_else(|| vec![0]);
+    if exit_codes.is_empty() {
+        anyhow::bail!("exit_codes must list at least one code");
     }
+    let pattern = |field: &str, value: Option<String>| {
+        value
+            .map(|p| Regex::new(&p).map_err(|e| anyhow!("Invalid {} '{}': {}", field, p, e)))
+            .transpose()
+    };
+    Ok(SuccessWhen {
+        exit_codes,
+        stdout_matches: pattern("stdout_matches", raw.stdout_matches)?,
+        stderr_not_matches: pattern("stderr_not_matches", raw.stderr_not_matches)?,
+    })
 }
 
-/// Parse schedule expression - supports both cron syntax and English phrases
-fn parse_schedule(expr: &str) -> Result<Cron> {
-    // Try standard cron first
-    Cron::from_str(expr).or_else(|cron_err| {
-        // Try English phrase (e.g., "7pm every Thursday")
-        english_to_cron::str_cron_syntax(expr)
-            .map_err(|_| anyhow!("Invalid schedule '{}': {}", expr, cron_err))
-            .and_then(|converted| {
-                Cron::from_str(&converted)
-                    .map_err(|e| anyhow!("Invalid schedule '{}' (converted to '{}'): {}", expr, converted, e))
-            })
+fn parse_slo(raw: SloConfigRaw) -> Result<SloConfig> {
+    if raw.duration_factor.is_none() && raw.failure_rate.is_none() {
+        anyhow::bail!("set duration_factor, failure_rate or both");
+    }
+    if let Some(factor) = raw.duration_factor {
+        if factor <= 1.0 || factor.is_nan() {
+            anyhow::bail!("Invalid duration_factor '{}': must be greater than 1", factor);
+        }
+    }
+    if let Some(rate) = raw.failure_rate {
+        if !(rate > 0.0 && rate < 100.0) {
+            anyhow::bail!("Invalid failure_rate '{}': must be a percentage between 0 and 100", rate);
+        }
+    }
+    if raw.runs < 2 {
+        anyhow::bail!("Invalid runs '{}': must be at least 2", raw.runs);
+    }
+    Ok(SloConfig {
+        duration_factor: raw.duration_factor,
+        failure_rate: raw.failure_rate.map(|r| r / 100.0),
+        runs: raw.runs,
     })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn parse_simple_config() {
-        let yaml = r#"
-jobs:
-  hello:
-    schedule:
-      cron: "*/5 * * * *"
-    run:
-      sh: echo hello
-"#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs.len(), 1);
-        assert_eq!(jobs[0].id, "hello");
-        assert_eq!(jobs[0].name, "hello");
-        assert_eq!(jobs[0].command, "echo hello");
-        assert_eq!(jobs[0].timeout, Duration::from_secs(3600));
+fn parse_circuit_breaker(raw: CircuitBreakerConfigRaw) -> Result<CircuitBreakerConfig> {
+    if raw.failures == 0 {
+        anyhow::bail!("Invalid failures '0': must be at least 1");
     }
-
-    #[test]
-    fn parse_cron_with_day_of_week() {
+    if raw.action != BreakerAction::Backoff && (raw.backoff.is_some() || raw.max_backoff.is_some()) {
+        anyhow::bail!("backoff and max_backoff need action: backoff");
+    }
+    let duration = |field: &str, value: Option<String>, default: &str| {
+        let value = value.unwrap_or_else(|| default.to_string());
+        parse_duration(&value).map_err(|e| anyhow!("Invalid {} '{}': {}", field, value, e))
+    };
+    let backoff = duration("backoff", raw.backoff, "10m")?;
+    let max_backoff = duration("max_backoff", raw.max_backoff, "24h")?;
+    if backoff > max_backoff {
+        anyhow::bail!("backoff must not exceed max_backoff");
+    }
+    Ok(CircuitBreakerConfig { failures: raw.failures, action: raw.action, backoff, max_backoff })
+}
+
+/// Builds the canary job like a user job, then drops what would hide or
+/// duplicate its failures (retries, guards, job webhooks, circuit breaker).
+fn parse_canary(raw: CanaryConfigRaw, defaults: &JobDefaults) -> Result<CanaryConfig> {
+    let max_sync_age = raw
+        .max_sync_age
+        .map(|d| parse_duration(&d).map_err(|e| anyhow!("Invalid max_sync_age '{}': {}", d, e)))
+        .transpose()?;
+    let mut run = serde_yaml::Mapping::new();
+    run.insert("sh".into(), format!("echo {}", CANARY_OUTPUT).into());
+    run.insert("timeout".into(), raw.timeout.into());
+    let mut job = serde_yaml::Mapping::new();
+    job.insert("name".into(), "rollcron canary".into());
+    job.insert("schedule".into(), raw.schedule.into());
+    job.insert("run".into(), run.into());
+    let mut job = parse_job(CANARY_JOB_ID, serde_yaml::from_value(job.into())?, CONFIG_FILE, defaults)?;
+    job.retry = None;
+    job.webhook.clear();
+    job.guards = Guards::default();
+    job.notify_on_start = false;
+    job.circuit_breaker = None;
+    Ok(CanaryConfig {
+        job,
+        ping_url: raw.ping_url,
+        max_sync_age,
+    })
+}
+
+fn parse_brake(raw: BrakeConfigRaw) -> Result<BrakeConfig> {
+    if !(raw.failure_rate > 0.0 && raw.failure_rate < 100.0) {
+        anyhow::bail!("Invalid failure_rate '{}': must be a percentage between 0 and 100", raw.failure_rate);
+    }
+    if raw.min_runs == 0 {
+        anyhow::bail!("Invalid min_runs '0': must be at least 1");
+    }
+    let window = parse_duration(&raw.window).map_err(|e| anyhow!("Invalid window '{}': {}", raw.window, e))?;
+    Ok(BrakeConfig { failure_rate: raw.failure_rate / 100.0, window, min_runs: raw.min_runs })
+}
+
+fn parse_export(raw: ExportConfigRaw) -> Result<ExportConfig> {
+    let (store, rest) = if let Some(rest) = raw.to.strip_prefix("s3://") {
+        (ObjectStore::S3, rest)
+    } else if let Some(rest) = raw.to.strip_prefix("gs://") {
+        (ObjectStore::Gcs, rest)
+    } else {
+        anyhow::bail!("Invalid to '{}': expected s3://bucket[/prefix] or gs://bucket[/prefix]", raw.to);
+    };
+    let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        anyhow::bail!("Invalid to '{}': missing bucket", raw.to);
+    }
+    if raw.endpoint.is_some() && store == ObjectStore::Gcs {
+        anyhow::bail!("endpoint is only supported for s3:// buckets");
+    }
+    Ok(ExportConfig {
+        store,
+        bucket: bucket.to_string(),
+        prefix: prefix.trim_matches('/').to_string(),
+        endpoint: raw.endpoint,
+    })
+}
+
+fn parse_retry(r: RetryConfigRaw) -> Result<RetryConfig> {
+    if r.max == 0 {
+        anyhow::bail!("Invalid retry.max '0': must be at least 1 (use no retry config to disable retries)");
+    }
+    let delay = parse_duration(&r.delay).map_err(|e| anyhow!("Invalid retry delay '{}': {}", r.delay, e))?;
+    let jitter = r
+        .jitter
+        .map(|j| parse_duration(&j).map_err(|e| anyhow!("Invalid retry jitter '{}': {}", j, e)))
+        .transpose()?;
+    Ok(RetryConfig {
+        max: r.max,
+        delay,
+        jitter,
+    })
+}
+
+/// IANA timezone name, or `inherit` for the system timezone
+pub fn parse_timezone(tz: &str) -> Result<TimezoneConfig> {
+    if tz == "inherit" {
+        return Ok(TimezoneConfig::Inherit);
+    }
+    tz.parse::<Tz>()
+        .map(TimezoneConfig::Named)
+        .map_err(|e| anyhow!("Invalid timezone '{}': {}", tz, e))
+}
+
+fn parse_blackout(raw: Vec<BlackoutWindowRaw>) -> Result<Vec<BlackoutWindow>> {
+    raw.into_iter()
+        .map(|w| {
+            let window = TimeWindow::parse(&w.from, &w.to).map_err(|e| anyhow!("blackout: {}", e))?;
+            Ok(BlackoutWindow { window, policy: w.policy })
+        })
+        .collect()
+}
+
+/// Fields set in `raw` override `base`.
+fn parse_guards(raw: GuardsConfigRaw, base: &Guards) -> Result<Guards> {
+    let min_free_disk = match raw.min_free_disk {
+        Some(s) => Some(parse_size(&s).map_err(|e| anyhow!("Invalid guards.min_free_disk '{}': {}", s, e))?),
+        None => base.min_free_disk,
+    };
+    let disk_path = match raw.disk_path {
+        Some(path) if !Path::new(&path).is_absolute() => {
+            anyhow::bail!("Invalid guards.disk_path '{}': must be an absolute path", path)
+        }
+        Some(path) => Some(PathBuf::from(path)),
+        None => base.disk_path.clone(),
+    };
+    if disk_path.is_some() && min_free_disk.is_none() {
+        anyhow::bail!("guards.disk_path has no effect without guards.min_free_disk");
+    }
+    if let Some(load) = raw.max_loadavg {
+        if !(load.is_finite() && load > 0.0) {
+            anyhow::bail!("Invalid guards.max_loadavg '{}': must be a positive number", load);
+        }
+    }
+    let max_delay = match raw.max_delay {
+        Some(s) => parse_duration(&s).map_err(|e| anyhow!("Invalid guards.max_delay '{}': {}", s, e))?,
+        None => base.max_delay,
+    };
+    Ok(Guards {
+        min_free_disk,
+        disk_path,
+        max_loadavg: raw.max_loadavg.or(base.max_loadavg),
+        policy: raw.policy.unwrap_or(base.policy),
+        max_delay,
+    })
+}
+
+/// `22:00` -> (seconds from midnight, false); `Sat 22:00` -> (seconds from Monday 00:00, true)
+fn parse_window_time(s: &str) -> Option<(u32, bool)> {
+    let mut parts = s.split_whitespace();
+    let (day, time) = match (parts.next()?, parts.next(), parts.next()) {
+        (time, None, _) => (None, time),
+        (day, Some(time), None) => (Some(day.parse::<Weekday>().ok()?), time),
+        _ => return None,
+    };
+    let secs = NaiveTime::parse_from_str(time, "%H:%M").ok()?.num_seconds_from_midnight();
+    match day {
+        Some(day) => Some((day.num_days_from_monday() * 86400 + secs, true)),
+        None => Some((secs, false)),
+    }
+}
+
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    if let Some(millis) = s.strip_suffix("ms") {
+        Ok(Duration::from_millis(millis.parse()?))
+    } else if let Some(secs) = s.strip_suffix('s') {
+        Ok(Duration::from_secs(secs.parse()?))
+    } else if let Some(mins) = s.strip_suffix('m') {
+        Ok(Duration::from_secs(mins.parse::<u64>()? * 60))
+    } else if let Some(hours) = s.strip_suffix('h') {
+        Ok(Duration::from_secs(hours.parse::<u64>()? * 3600))
+    } else {
+        Ok(Duration::from_secs(s.parse()?))
+    }
+}
+
+fn parse_ha(raw: HaConfigRaw) -> Result<HaConfig> {
+    let ttl = parse_duration(&raw.ttl).map_err(|e| anyhow!("Invalid ha.ttl '{}': {}", raw.ttl, e))?;
+    if ttl < Duration::from_secs(3) {
+        anyhow::bail!("Invalid ha.ttl '{}': must be at least 3s", raw.ttl);
+    }
+    Ok(HaConfig { lock: raw.lock, ttl })
+}
+
+fn parse_limits(raw: LimitsConfigRaw) -> Result<ResourceLimits> {
+    let memory = raw
+        .memory
+        .map(|m| match parse_size(&m) {
+            Ok(0) | Err(_) => Err(anyhow!("Invalid limits.memory '{}'", m)),
+            Ok(bytes) => Ok(bytes),
+        })
+        .transpose()?;
+    let cpu = raw
+        .cpu
+        .map(|c| {
+            let (value, text) = match c {
+                CpuLimitRaw::Number(n) => (Some(n), n.to_string()),
+                CpuLimitRaw::Text(t) => (t.trim().parse::<f64>().ok(), t),
+            };
+            value
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .ok_or_else(|| anyhow!("Invalid limits.cpu '{}': must be a positive number of cores", text))
+        })
+        .transpose()?;
+    if memory.is_none() && cpu.is_none() {
+        anyhow::bail!("limits requires `memory` and/or `cpu`");
+    }
+    Ok(ResourceLimits { memory, cpu })
+}
+
+fn parse_security(raw: SecurityConfig) -> Result<SecurityConfig> {
+    if raw.seccomp.is_none() && raw.apparmor.is_none() {
+        anyhow::bail!("security requires `seccomp` and/or `apparmor`");
+    }
+    if let Some(profile) = &raw.apparmor {
+        if profile.is_empty() || profile.contains(char::is_whitespace) {
+            anyhow::bail!("Invalid security.apparmor '{}': must be a profile name", profile);
+        }
+    }
+    Ok(raw)
+}
+
+fn parse_sandbox(raw: SandboxConfig) -> Result<SandboxConfig> {
+    if let Some(path) = raw.read.iter().chain(&raw.write).find(|path| path.is_empty()) {
+        anyhow::bail!("Invalid sandbox path '{}'", path);
+    }
+    Ok(raw)
+}
+
+/// A user or group name, or a numeric ID. Resolved at run time.
+fn parse_account(field: &str, raw: AccountRaw) -> Result<String> {
+    let value = match raw {
+        AccountRaw::Id(id) => return Ok(id.to_string()),
+        AccountRaw::Name(name) => name,
+    };
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == ':') {
+        anyhow::bail!("Invalid {} '{}': must be a name or numeric ID", field, value);
+    }
+    Ok(value)
+}
+
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    if let Some(n) = s.strip_suffix('G') {
+        Ok(n.parse::<u64>()? * 1024 * 1024 * 1024)
+    } else if let Some(n) = s.strip_suffix('M') {
+        Ok(n.parse::<u64>()? * 1024 * 1024)
+    } else if let Some(n) = s.strip_suffix('K') {
+        Ok(n.parse::<u64>()? * 1024)
+    } else {
+        Ok(s.parse()?)
+    }
+}
+
+/// `3.2G`, `512M`, `900K`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [(u64, &str); 3] = [(1 << 30, "G"), (1 << 20, "M"), (1 << 10, "K")];
+    for (unit, suffix) in UNITS {
+        if bytes >= unit {
+            return format!("{:.1}{}", bytes as f64 / unit as f64, suffix);
+        }
+    }
+    format!("{}B", bytes)
+}
+
+/// Parse schedule expression - supports cron syntax in `format`, `@` aliases
+/// and English phrases. `@reboot` has no schedule (None).
+pub fn parse_schedule(expr: &str, format: Option<CronFormat>) -> Result<Option<Cron>> {
+    let expr = expr.trim();
+    if expr.eq_ignore_ascii_case("@reboot") {
+        return Ok(None);
+    }
+    let parser = cron_parser(format);
+    let cron_err = match parser.parse(expr) {
+        Ok(cron) => return Ok(Some(cron)),
+        Err(e) => e,
+    };
+    // Text starting like a cron field is never an English phrase
+    if expr.starts_with('@') || expr.split_whitespace().next().is_some_and(|f| f.chars().all(|c| "0123456789*/,-?".contains(c))) {
+        anyhow::bail!("Invalid schedule '{}': {}", expr, schedule_error(expr, format, &parser, cron_err));
+    }
+    // Try English phrase (e.g., "7pm every Thursday")
+    let converted = english_to_cron::str_cron_syntax(expr).map_err(|_| anyhow!("Invalid schedule '{}': {}", expr, cron_err))?;
+    Cron::from_str(&converted)
+        .map(Some)
+        .map_err(|e| anyhow!("Invalid schedule '{}' (converted to '{}'): {}", expr, converted, e))
+}
+
+fn cron_parser(format: Option<CronFormat>) -> CronParser {
+    match format {
+        None => CronParser::new(),
+        Some(CronFormat::Standard) => CronParser::builder().seconds(Seconds::Disallowed).year(Year::Disallowed).build(),
+        Some(CronFormat::Quartz) => {
+            CronParser::builder().seconds(Seconds::Required).year(Year::Optional).alternative_weekdays(true).build()
+        }
+    }
+}
+
+const CRON_FIELDS: [&str; 5] = ["minute", "hour", "day-of-month", "month", "day-of-week"];
+
+/// Says which part of `expr` the parser rejected: the number of fields, an
+/// alias, or the first field that doesn't parse on its own.
+fn schedule_error(expr: &str, format: Option<CronFormat>, parser: &CronParser, err: CronError) -> String {
+    if expr.starts_with('@') {
+        return "unknown alias (expected @yearly, @annually, @monthly, @weekly, @daily, @hourly or @reboot)".to_string();
+    }
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    let count_error = match (format, fields.len()) {
+        (Some(CronFormat::Standard), 5) | (Some(CronFormat::Quartz), 6 | 7) | (None, 5..=7) => None,
+        (Some(CronFormat::Standard), n) => Some(format!(
+            "{} fields, but cron_format standard takes 5 (minute hour day-of-month month day-of-week)",
+            n
+        )),
+        (Some(CronFormat::Quartz), n) => Some(format!(
+            "{} fields, but cron_format quartz takes 6 or 7 (second minute hour day-of-month month day-of-week [year])",
+            n
+        )),
+        (None, n) => Some(format!(
+            "{} fields, expected 5 (minute hour day-of-month month day-of-week), or 6-7 starting with seconds",
+            n
+        )),
+    };
+    if let Some(e) = count_error {
+        return e;
+    }
+
+    let mut names: Vec<&str> = CRON_FIELDS.to_vec();
+    if fields.len() > 5 {
+        names.insert(0, "second");
+    }
+    if fields.len() > 6 {
+        names.push("year");
+    }
+    for (i, (field, name)) in fields.iter().zip(names).enumerate() {
+        let alone: Vec<&str> = (0..fields.len()).map(|j| if j == i { *field } else { "*" }).collect();
+        if let Err(e) = parser.parse(&alone.join(" ")) {
+            return format!("{} field '{}' is invalid ({})", name, field, e);
+        }
+    }
+    err.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn parse_simple_config() {
+        let yaml = r#"
+jobs:
+  hello:
+    schedule:
+      cron: "*/5 * * * *"
+    run:
+      sh: echo hello
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, "hello");
+        assert_eq!(jobs[0].name, "hello");
+        assert_eq!(jobs[0].command.to_string(), "echo hello");
+        assert_eq!(jobs[0].timeout, Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn parse_cron_with_day_of_week() {
         // Regression test: 5-field cron with day-of-week should parse correctly
         let yaml = r#"
 jobs:
@@ -559,6 +2628,27 @@ jobs:
         assert_eq!(jobs.len(), 2);
     }
 
+    #[test]
+    fn parse_fallback_command() {
+        let yaml = r#"
+jobs:
+  with_fallback:
+    schedule: "* * * * *"
+    run:
+      sh: ./fast.sh
+      fallback_command: ./slow.sh
+      retry:
+        max: 2
+  no_fallback:
+    schedule: "* * * * *"
+    run: ./fast.sh
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert_eq!(find("with_fallback").fallback_command, Some(CommandLine::Line("./slow.sh".to_string())));
+        assert!(find("no_fallback").fallback_command.is_none());
+    }
+
     #[test]
     fn parse_duration_variants() {
         assert_eq!(parse_duration("10s").unwrap(), Duration::from_secs(10));
@@ -647,74 +2737,667 @@ jobs:
     }
 
     #[test]
-    fn parse_retry_default_delay() {
+    fn parse_retry_default_delay() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+      retry:
+        max: 2
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        let retry = jobs[0].retry.as_ref().unwrap();
+        assert_eq!(retry.max, 2);
+        assert_eq!(retry.delay, Duration::from_secs(1)); // default delay
+    }
+
+    #[test]
+    fn parse_runner_config() {
+        let yaml = r#"
+runner:
+  timezone: Asia/Tokyo
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+"#;
+        let (runner, _) = parse_config(yaml).unwrap();
+        assert_eq!(
+            runner.timezone,
+            TimezoneConfig::Named(chrono_tz::Asia::Tokyo)
+        );
+    }
+
+    #[test]
+    fn parse_runner_config_defaults() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+"#;
+        let (runner, _) = parse_config(yaml).unwrap();
+        assert_eq!(runner.timezone, TimezoneConfig::Utc);
+    }
+
+    #[test]
+    fn parse_timezone_inherit() {
+        let yaml = r#"
+runner:
+  timezone: inherit
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+"#;
+        let (runner, _) = parse_config(yaml).unwrap();
+        assert_eq!(runner.timezone, TimezoneConfig::Inherit);
+    }
+
+    #[test]
+    fn parse_working_dir() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+      working_dir: ./scripts
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(jobs[0].working_dir.as_ref().map(|w| w.path.as_str()), Some("./scripts"));
+    }
+
+    #[test]
+    fn parse_working_dir_create_and_allowed_dirs() {
+        let yaml = r#"
+runner:
+  allowed_work_dirs: [/data]
+jobs:
+  test:
+    schedule: "* * * * *"
+    run: ./etl
+    working_dir: { path: /data/etl, create: true }
+"#;
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(runner.allowed_work_dirs, [PathBuf::from("/data")]);
+        assert_eq!(jobs[0].working_dir, Some(WorkingDir { path: "/data/etl".to_string(), create: true }));
+
+        let err = parse_config("runner:\n  allowed_work_dirs: [data]\njobs: {}\n").unwrap_err();
+        assert!(err.to_string().contains("must be an absolute path"), "{}", err);
+    }
+
+    #[test]
+    fn parse_job_level_working_dir() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+    working_dir: ./job-dir
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(jobs[0].working_dir.as_ref().map(|w| w.path.as_str()), Some("./job-dir"));
+    }
+
+    #[test]
+    fn parse_working_dir_run_overrides_job() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+      working_dir: ./run-dir
+    working_dir: ./job-dir
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(jobs[0].working_dir.as_ref().map(|w| w.path.as_str()), Some("./run-dir"));
+    }
+
+    #[test]
+    fn parse_working_dir_build_inherits_job() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    build:
+      sh: cargo build
+    run:
+      sh: ./app
+    working_dir: ./job-dir
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        let build = jobs[0].build.as_ref().unwrap();
+        assert_eq!(build.working_dir.as_ref().map(|w| w.path.as_str()), Some("./job-dir"));
+    }
+
+    #[test]
+    fn parse_working_dir_build_overrides_job() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    build:
+      sh: cargo build
+      working_dir: ./build-dir
+    run:
+      sh: ./app
+    working_dir: ./job-dir
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        let build = jobs[0].build.as_ref().unwrap();
+        assert_eq!(build.working_dir.as_ref().map(|w| w.path.as_str()), Some("./build-dir"));
+    }
+
+    #[test]
+    fn parse_invalid_timezone() {
+        let yaml = r#"
+runner:
+  timezone: Invalid/Zone
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+"#;
+        assert!(parse_config(yaml).is_err());
+    }
+
+    #[test]
+    fn parse_max_output() {
+        let yaml = r#"
+jobs:
+  capped:
+    schedule: "* * * * *"
+    run:
+      sh: ./noisy
+      max_output: 64K
+  default:
+    schedule: "* * * * *"
+    run: echo test
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert_eq!(find("capped").max_output, 64 * 1024);
+        assert_eq!(find("default").max_output, 1024 * 1024);
+
+        let too_small = "jobs:\n  j:\n    schedule: \"* * * * *\"\n    run:\n      sh: x\n      max_output: \"100\"\n";
+        assert!(parse_config(too_small).unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn parse_stall_timeout() {
+        let yaml = r#"
+jobs:
+  watched:
+    schedule: "* * * * *"
+    run:
+      sh: ./export
+      timeout: 2h
+      stall_timeout: 10m
+  too_long:
+    schedule: "* * * * *"
+    run:
+      sh: ./export
+      timeout: 5m
+      stall_timeout: 10m
+"#;
+        let (_, jobs, invalid) = parse_config_in(yaml, None).unwrap();
+        assert_eq!(jobs[0].stall_timeout, Some(Duration::from_secs(600)));
+        assert_eq!(invalid, ["too_long"]);
+    }
+
+    #[test]
+    fn parse_retry_jitter() {
+        let yaml = r#"
+jobs:
+  with_retry_jitter:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+      retry:
+        max: 3
+        delay: 1s
+        jitter: 500ms
+  retry_no_jitter:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+      retry:
+        max: 2
+        delay: 1s
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+
+        let retry1 = find("with_retry_jitter").retry.as_ref().unwrap();
+        assert_eq!(retry1.jitter, Some(Duration::from_millis(500)));
+
+        let retry2 = find("retry_no_jitter").retry.as_ref().unwrap();
+        assert!(retry2.jitter.is_none());
+    }
+
+    #[test]
+    fn parse_duration_milliseconds() {
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(
+            parse_duration("1000ms").unwrap(),
+            Duration::from_millis(1000)
+        );
+    }
+
+    #[test]
+    fn skip_invalid_job_id() {
+        let yaml = r#"
+jobs:
+  "../escape":
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert!(jobs.is_empty()); // Invalid job is skipped
+    }
+
+    #[test]
+    fn skip_job_id_with_slash() {
+        let yaml = r#"
+jobs:
+  "foo/bar":
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert!(jobs.is_empty()); // Invalid job is skipped
+    }
+
+    #[test]
+    fn accept_valid_job_id() {
+        let yaml = r#"
+jobs:
+  my-job_123:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(jobs[0].id, "my-job_123");
+    }
+
+    #[test]
+    fn skip_retry_max_zero() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+      retry:
+        max: 0
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert!(jobs.is_empty()); // Invalid job is skipped
+    }
+
+    #[test]
+    fn parse_enabled_false() {
+        let yaml = r#"
+jobs:
+  test_disabled:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+    enabled: false
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert!(!jobs[0].enabled);
+    }
+
+    #[test]
+    fn parse_enabled_true() {
+        let yaml = r#"
+jobs:
+  test_enabled:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+    enabled: true
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert!(jobs[0].enabled);
+    }
+
+    #[test]
+    fn parse_enabled_default() {
+        let yaml = r#"
+jobs:
+  test_default:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert!(jobs[0].enabled);
+    }
+
+    #[test]
+    fn parse_job_with_timezone() {
+        let yaml = r#"
+runner:
+  timezone: Asia/Tokyo
+jobs:
+  job1:
+    schedule:
+      cron: "* * * * *"
+      timezone: America/New_York
+    run:
+      sh: echo test
+  job2:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+"#;
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(
+            runner.timezone,
+            TimezoneConfig::Named(chrono_tz::Asia::Tokyo)
+        );
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert_eq!(
+            find("job1").timezone,
+            Some(TimezoneConfig::Named(chrono_tz::America::New_York))
+        );
+        assert_eq!(
+            find("job2").timezone,
+            Some(TimezoneConfig::Named(chrono_tz::Asia::Tokyo))
+        );
+    }
+
+    #[test]
+    fn parse_job_timezone_inherit() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+      timezone: inherit
+    run:
+      sh: echo test
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(jobs[0].timezone, Some(TimezoneConfig::Inherit));
+    }
+
+    #[test]
+    fn skip_invalid_job_timezone() {
+        let yaml = r#"
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+      timezone: Invalid/Zone
+    run:
+      sh: echo test
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert!(jobs.is_empty()); // Invalid job is skipped
+    }
+
+    #[test]
+    fn parse_runner_env() {
+        let yaml = r#"
+runner:
+  env:
+    FOO: bar
+    BAZ: qux
+jobs:
+  test:
+    schedule:
+      cron: "* * * * *"
+    run:
+      sh: echo test
+"#;
+        let (runner, _) = parse_config(yaml).unwrap();
+        let env = runner.env.as_ref().unwrap();
+        assert_eq!(env.get("FOO"), Some(&EnvValue::Plain("bar".to_string())));
+        assert_eq!(env.get("BAZ"), Some(&EnvValue::Plain("qux".to_string())));
+    }
+
+    #[test]
+    fn parse_runner_env_file() {
         let yaml = r#"
+runner:
+  env_file: .env.global
 jobs:
   test:
     schedule:
       cron: "* * * * *"
     run:
       sh: echo test
-      retry:
-        max: 2
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        let retry = jobs[0].retry.as_ref().unwrap();
-        assert_eq!(retry.max, 2);
-        assert_eq!(retry.delay, Duration::from_secs(1)); // default delay
+        let (runner, _) = parse_config(yaml).unwrap();
+        assert_eq!(runner.env_file.as_deref(), Some(".env.global"));
     }
 
     #[test]
-    fn parse_runner_config() {
+    fn parse_runner_shell() {
         let yaml = r#"
 runner:
-  timezone: Asia/Tokyo
+  shell: powershell
 jobs:
   test:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
+    schedule: "* * * * *"
+    run: Write-Output hi
+"#;
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(runner.shell, Shell::Powershell);
+        assert_eq!(jobs[0].shell, Shell::Powershell);
+
+        let (runner, _) = parse_config("jobs: {}").unwrap();
+        assert_eq!(runner.shell, Shell::default());
+    }
+
+    #[test]
+    fn parse_runner_ha() {
+        let yaml = r#"
+runner:
+  ha:
+    backend: redis
+    url: redis://:secret@10.0.0.5:6379/1
+    ttl: 30s
+jobs: {}
 "#;
         let (runner, _) = parse_config(yaml).unwrap();
+        let ha = runner.ha.unwrap();
+        assert_eq!(ha.ttl, Duration::from_secs(30));
         assert_eq!(
-            runner.timezone,
-            TimezoneConfig::Named(chrono_tz::Asia::Tokyo)
+            ha.lock,
+            LockConfig::Redis {
+                url: "redis://:secret@10.0.0.5:6379/1".to_string(),
+                key: "rollcron/leader".to_string(),
+            }
         );
+
+        let yaml = "runner:\n  ha: { backend: file, path: /mnt/shared/rollcron.lock }\njobs: {}";
+        let ha = parse_config(yaml).unwrap().0.ha.unwrap();
+        assert_eq!(ha.lock, LockConfig::File { path: "/mnt/shared/rollcron.lock".to_string() });
+        assert_eq!(ha.ttl, Duration::from_secs(15));
+
+        assert!(parse_config("runner:\n  ha: { backend: zookeeper }\njobs: {}").is_err());
+        assert!(parse_config("runner:\n  ha: { backend: file, path: x, ttl: 1s }\njobs: {}").is_err());
     }
 
     #[test]
-    fn parse_runner_config_defaults() {
+    fn parse_job_limits() {
         let yaml = r#"
 jobs:
-  test:
-    schedule:
-      cron: "* * * * *"
+  both:
+    schedule: "* * * * *"
+    run: ./etl
+    limits:
+      memory: 512M
+      cpu: "0.5"
+  cpu_only:
+    schedule: "* * * * *"
+    run: ./etl
+    limits: { cpu: 2 }
+  bad_cpu:
+    schedule: "* * * * *"
+    run: ./etl
+    limits: { cpu: "-1" }
+  empty:
+    schedule: "* * * * *"
+    run: ./etl
+    limits: {}
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(
+            find("both").limits,
+            Some(ResourceLimits { memory: Some(512 * 1024 * 1024), cpu: Some(0.5) })
+        );
+        assert_eq!(find("cpu_only").limits, Some(ResourceLimits { memory: None, cpu: Some(2.0) }));
+    }
+
+    #[test]
+    fn parse_job_security() {
+        let job = |security: &str| {
+            let yaml = format!("jobs:\n  etl:\n    schedule: \"0 * * * *\"\n    run: ./etl\n    security: {}\n", security);
+            // An invalid job is skipped
+            parse_config(&yaml).unwrap().1.pop().map(|job| job.security)
+        };
+        assert_eq!(
+            job("{ seccomp: sec/etl.bpf, apparmor: rollcron-etl }"),
+            Some(Some(SecurityConfig { seccomp: Some("sec/etl.bpf".to_string()), apparmor: Some("rollcron-etl".to_string()) }))
+        );
+        assert_eq!(job("{}"), None);
+        assert_eq!(job("{ apparmor: \"two words\" }"), None);
+        assert!(parse_config("jobs:\n  etl:\n    schedule: \"0 * * * *\"\n    run: x\n    security: { selinux: x }\n").is_err());
+    }
+
+    #[test]
+    fn parse_job_sandbox() {
+        let job = |sandbox: &str| {
+            let yaml = format!("jobs:\n  etl:\n    schedule: \"0 * * * *\"\n    run: ./etl\n    sandbox: {}\n", sandbox);
+            parse_config(&yaml).unwrap().1.pop().map(|job| job.sandbox)
+        };
+        assert_eq!(
+            job("{ read: [/srv/data], network: false }"),
+            Some(Some(SandboxConfig { read: vec!["/srv/data".to_string()], write: vec![], network: false }))
+        );
+        assert_eq!(job("{}"), Some(Some(SandboxConfig { read: vec![], write: vec![], network: true })));
+        assert_eq!(job("{ write: [\"\"] }"), None);
+        assert!(parse_config("jobs:\n  etl:\n    schedule: \"0 * * * *\"\n    run: x\n    sandbox: { net: false }\n").is_err());
+    }
+
+    #[test]
+    fn parse_job_user_and_group() {
+        let job = |fields: &str| {
+            let yaml = format!("jobs:\n  etl:\n    schedule: \"0 * * * *\"\n    run: ./etl\n{}", fields);
+            parse_config(&yaml).unwrap().1.pop().map(|job| (job.user, job.group))
+        };
+        assert_eq!(job(""), Some((None, None)));
+        assert_eq!(
+            job("    user: backup\n    group: 1001\n"),
+            Some((Some("backup".to_string()), Some("1001".to_string())))
+        );
+        assert_eq!(job("    user: \"backup:backup\"\n"), None);
+        assert_eq!(job("    group: \"\"\n"), None);
+    }
+
+    #[test]
+    fn parse_job_shell_and_argv() {
+        let yaml = r#"
+runner:
+  shell: zsh
+jobs:
+  bash:
+    schedule: "* * * * *"
+    shell: bash
+    build: [make, "-j", "4"]
+    run: echo $BASH_VERSION
+  inherit:
+    schedule: "* * * * *"
     run:
-      sh: echo test
+      sh: ["./report", "--title", "Daily report"]
+      fallback_command: ./alert
+  empty:
+    schedule: "* * * * *"
+    run: []
 "#;
-        let (runner, _) = parse_config(yaml).unwrap();
-        assert_eq!(runner.timezone, TimezoneConfig::Utc);
+        let (_, jobs) = parse_config(yaml).unwrap();
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert_eq!(jobs.len(), 2);
+
+        let bash = find("bash");
+        assert_eq!(bash.shell, Shell::Bash);
+        assert_eq!(bash.command.argv(bash.shell), vec!["bash", "-c", "echo $BASH_VERSION"]);
+        let build = bash.build.as_ref().unwrap();
+        assert_eq!(build.command.argv(bash.shell), vec!["make", "-j", "4"]);
+
+        let inherit = find("inherit");
+        assert_eq!(inherit.shell, Shell::Zsh);
+        assert_eq!(inherit.command.argv(inherit.shell), vec!["./report", "--title", "Daily report"]);
+        assert_eq!(inherit.command.to_string(), r#"./report --title "Daily report""#);
+        assert_eq!(inherit.fallback_command, Some(CommandLine::Line("./alert".to_string())));
     }
 
     #[test]
-    fn parse_timezone_inherit() {
+    fn shell_none_splits_on_whitespace() {
+        let command = CommandLine::Line("  ./app  --flag 'x y'".to_string());
+        assert_eq!(command.argv(Shell::None), vec!["./app", "--flag", "'x", "y'"]);
+    }
+
+    #[test]
+    fn parse_runner_secrets() {
         let yaml = r#"
 runner:
-  timezone: inherit
+  secrets:
+    key_file: ~/.config/age/key.txt
 jobs:
   test:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
+    schedule: "* * * * *"
+    run: echo test
+    env_files: [secrets.env.age]
 "#;
         let (runner, _) = parse_config(yaml).unwrap();
-        assert_eq!(runner.timezone, TimezoneConfig::Inherit);
+        assert_eq!(runner.secrets.key_file.as_deref(), Some("~/.config/age/key.txt"));
+
+        let (runner, _) = parse_config("jobs: {}").unwrap();
+        assert!(runner.secrets.key_file.is_none());
     }
 
     #[test]
-    fn parse_working_dir() {
+    fn parse_job_env() {
         let yaml = r#"
 jobs:
   test:
@@ -722,400 +3405,887 @@ jobs:
       cron: "* * * * *"
     run:
       sh: echo test
-      working_dir: ./scripts
+    env:
+      KEY1: value1
+      KEY2: value2
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].working_dir.as_deref(), Some("./scripts"));
+        let env = jobs[0].env.as_ref().unwrap();
+        assert_eq!(env.get("KEY1"), Some(&EnvValue::Plain("value1".to_string())));
+        assert_eq!(env.get("KEY2"), Some(&EnvValue::Plain("value2".to_string())));
     }
 
     #[test]
-    fn parse_job_level_working_dir() {
+    fn parse_env_secret_refs() {
         let yaml = r#"
 jobs:
-  test:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-    working_dir: ./job-dir
+  etl:
+    schedule: "* * * * *"
+    run: ./etl.sh
+    env:
+      DB_USER: etl
+      DB_PASS: {from: vault, path: "secret/data/etl#password"}
+      API_KEY:
+        from: ssm
+        path: /etl/api-key
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].working_dir.as_deref(), Some("./job-dir"));
+        let env = jobs[0].env.as_ref().unwrap();
+        assert_eq!(env.get("DB_USER"), Some(&EnvValue::Plain("etl".to_string())));
+        assert_eq!(
+            env.get("DB_PASS"),
+            Some(&EnvValue::Secret(SecretRef {
+                from: SecretSource::Vault,
+                path: "secret/data/etl#password".to_string(),
+            }))
+        );
+        assert!(matches!(
+            env.get("API_KEY"),
+            Some(EnvValue::Secret(SecretRef { from: SecretSource::Ssm, .. }))
+        ));
     }
 
     #[test]
-    fn parse_working_dir_run_overrides_job() {
+    fn parse_job_audit() {
         let yaml = r#"
 jobs:
-  test:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-      working_dir: ./run-dir
-    working_dir: ./job-dir
+  a:
+    schedule: "* * * * *"
+    run: echo a
+    audit: true
+  b:
+    schedule: "* * * * *"
+    run: echo b
+"#;
+        let (_, mut jobs) = parse_config(yaml).unwrap();
+        jobs.sort_by(|x, y| x.id.cmp(&y.id));
+        assert!(jobs[0].audit);
+        assert!(!jobs[1].audit);
+    }
+
+    #[test]
+    fn parse_job_labels() {
+        let yaml = r#"
+jobs:
+  etl:
+    schedule: "* * * * *"
+    run: ./etl.sh
+    labels:
+      team: data
+      tier: batch
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].working_dir.as_deref(), Some("./run-dir"));
+        assert_eq!(jobs[0].labels.get("team").map(String::as_str), Some("data"));
+        assert_eq!(jobs[0].labels.len(), 2);
     }
 
     #[test]
-    fn parse_working_dir_build_inherits_job() {
+    fn parse_env_unknown_secret_source_fails() {
+        let yaml = r#"
+jobs:
+  etl:
+    schedule: "* * * * *"
+    run: ./etl.sh
+    env:
+      DB_PASS: {from: keychain, path: etl}
+"#;
+        assert!(parse_config(yaml).is_err());
+    }
+
+    #[test]
+    fn parse_job_env_file() {
         let yaml = r#"
 jobs:
   test:
     schedule:
       cron: "* * * * *"
-    build:
-      sh: cargo build
     run:
-      sh: ./app
-    working_dir: ./job-dir
+      sh: echo test
+    env_file: .env.job
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
-        let build = jobs[0].build.as_ref().unwrap();
-        assert_eq!(build.working_dir.as_deref(), Some("./job-dir"));
+        assert_eq!(jobs[0].env_file.as_deref(), Some(".env.job"));
     }
 
     #[test]
-    fn parse_working_dir_build_overrides_job() {
+    fn parse_job_env_files() {
         let yaml = r#"
 jobs:
   test:
-    schedule:
-      cron: "* * * * *"
-    build:
-      sh: cargo build
-      working_dir: ./build-dir
-    run:
-      sh: ./app
-    working_dir: ./job-dir
+    schedule: "* * * * *"
+    run: echo test
+    env_file: .env
+    env_files: [.env.shared, .env.production]
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
-        let build = jobs[0].build.as_ref().unwrap();
-        assert_eq!(build.working_dir.as_deref(), Some("./build-dir"));
+        assert_eq!(jobs[0].env_file.as_deref(), Some(".env"));
+        assert_eq!(jobs[0].env_files, vec![".env.shared", ".env.production"]);
     }
 
     #[test]
-    fn parse_invalid_timezone() {
+    fn parse_full_env_config() {
         let yaml = r#"
 runner:
-  timezone: Invalid/Zone
+  env_file: .env.global
+  env:
+    GLOBAL_VAR: global_value
 jobs:
   test:
     schedule:
       cron: "* * * * *"
     run:
       sh: echo test
+    env_file: .env.local
+    env:
+      LOCAL_VAR: local_value
 "#;
-        assert!(parse_config(yaml).is_err());
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(runner.env_file.as_deref(), Some(".env.global"));
+        assert_eq!(
+            runner.env.as_ref().unwrap().get("GLOBAL_VAR"),
+            Some(&EnvValue::Plain("global_value".to_string()))
+        );
+        assert_eq!(jobs[0].env_file.as_deref(), Some(".env.local"));
+        assert_eq!(
+            jobs[0].env.as_ref().unwrap().get("LOCAL_VAR"),
+            Some(&EnvValue::Plain("local_value".to_string()))
+        );
     }
 
     #[test]
-    fn parse_retry_jitter() {
+    fn parse_blackout_windows() {
         let yaml = r#"
+runner:
+  blackout:
+    - from: "Sat 22:00"
+      to: "Sun 02:00"
 jobs:
-  with_retry_jitter:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-      retry:
-        max: 3
-        delay: 1s
-        jitter: 500ms
-  retry_no_jitter:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-      retry:
-        max: 2
-        delay: 1s
+  merged:
+    schedule: "* * * * *"
+    run: echo
+    blackout:
+      - { from: "23:00", to: "01:00", policy: defer }
+  mixed:
+    schedule: "* * * * *"
+    run: echo
+    blackout:
+      - { from: "Sat 22:00", to: "02:00" }
+  empty:
+    schedule: "* * * * *"
+    run: echo
+    blackout:
+      - { from: "12:00", to: "12:00" }
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
-        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
-
-        let retry1 = find("with_retry_jitter").retry.as_ref().unwrap();
-        assert_eq!(retry1.jitter, Some(Duration::from_millis(500)));
+        assert_eq!(jobs.len(), 1, "invalid windows skip the job");
+        let windows = &jobs[0].blackout;
+        assert_eq!(windows.len(), 2);
+        assert_eq!((windows[0].window.start, windows[0].window.end, windows[0].window.weekly), (5 * 86400 + 22 * 3600, 6 * 86400 + 2 * 3600, true));
+        assert_eq!(windows[0].policy, BlackoutPolicy::Skip);
+        assert_eq!((windows[1].window.label.as_str(), windows[1].policy), ("23:00-01:00", BlackoutPolicy::Defer));
 
-        let retry2 = find("retry_no_jitter").retry.as_ref().unwrap();
-        assert!(retry2.jitter.is_none());
+        let bad_runner = "runner:\n  blackout: [{ from: \"25:00\", to: \"01:00\" }]\njobs: {}\n";
+        assert!(parse_config(bad_runner).is_err());
     }
 
     #[test]
-    fn parse_duration_milliseconds() {
-        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
-        assert_eq!(
-            parse_duration("1000ms").unwrap(),
-            Duration::from_millis(1000)
-        );
+    fn webhook_filter_routes_notifications() {
+        let yaml = r#"
+runner:
+  webhook:
+    - url: https://example.com/dashboard
+    - url: https://example.com/pager
+      filter:
+        events: [job_failure]
+        labels: { tier: critical }
+        min_severity: error
+        hours: { from: "09:00", to: "18:00" }
+        days: [Mon, Tue, Wed, Thu, Fri]
+jobs:
+  backup:
+    schedule: "* * * * *"
+    run: echo
+    labels: { tier: critical }
+    severity: critical
+"#;
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        assert!(runner.webhook[0].filter.is_none());
+        let filter = runner.webhook[1].filter.as_ref().unwrap();
+        let labels = Some(&jobs[0].labels);
+        // 2025-01-15 is a Wednesday
+        let at = |d, h| NaiveDate::from_ymd_opt(2025, 1, d).unwrap().and_hms_opt(h, 0, 0).unwrap();
+
+        assert!(filter.matches(NotifyEvent::JobFailure, jobs[0].severity, labels, &at(15, 10)));
+        assert!(!filter.matches(NotifyEvent::BuildFailure, Severity::Critical, labels, &at(15, 10)), "event");
+        assert!(!filter.matches(NotifyEvent::JobFailure, Severity::Warning, labels, &at(15, 10)), "severity");
+        assert!(!filter.matches(NotifyEvent::JobFailure, Severity::Critical, None, &at(15, 10)), "labels");
+        assert!(!filter.matches(NotifyEvent::JobFailure, Severity::Critical, labels, &at(15, 20)), "hours");
+        assert!(!filter.matches(NotifyEvent::JobFailure, Severity::Critical, labels, &at(18, 10)), "Saturday");
+
+        let bad = "runner:\n  webhook:\n    - url: https://x\n      filter: { days: [Someday] }\njobs: {}\n";
+        assert!(parse_config(bad).is_err());
+
+        let network = "runner:\n  webhook:\n    - url: https://x\n      filter: { failure_classes: [network, timeout] }\njobs: {}\n";
+        let (runner, _) = parse_config(network).unwrap();
+        let filter = runner.webhook[0].filter.as_ref().unwrap();
+        assert!(filter.matches(NotifyEvent::JobFailure, Severity::Error, None, &at(15, 10)));
+        assert!(!filter.matches(NotifyEvent::BuildFailure, Severity::Error, None, &at(15, 10)), "not a job failure");
+        assert!(filter.matches_class(Some(FailureClass::Network)));
+        assert!(!filter.matches_class(Some(FailureClass::Other)) && !filter.matches_class(None));
     }
 
     #[test]
-    fn skip_invalid_job_id() {
+    fn parse_start_options() {
         let yaml = r#"
+runner:
+  long_job_threshold: 2h
 jobs:
-  "../escape":
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
+  migration:
+    schedule: "* * * * *"
+    run: ./migrate
+    expected_duration: 3h
+  short:
+    schedule: "* * * * *"
+    run: echo
+    expected_duration: 30m
+  explicit:
+    schedule: "* * * * *"
+    run: echo
+    notify_on_start: true
+    run_on_start: true
+    pull_on_run: true
+    min_interval: 10m
+  opted_out:
+    schedule: "* * * * *"
+    run: ./migrate
+    expected_duration: 3h
+    notify_on_start: false
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert!(jobs.is_empty()); // Invalid job is skipped
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(runner.long_job_threshold, Duration::from_secs(7200));
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert!(find("migration").notify_on_start);
+        assert_eq!(find("migration").expected_duration, Some(Duration::from_secs(3 * 3600)));
+        assert!(!find("short").notify_on_start);
+        assert!(find("explicit").notify_on_start);
+        assert!(find("explicit").run_on_start);
+        assert!(!find("short").run_on_start);
+        assert!(find("explicit").pull_on_run);
+        assert!(!find("short").pull_on_run);
+        assert_eq!(find("explicit").min_interval, Some(Duration::from_secs(600)));
+        assert_eq!(find("short").min_interval, None);
+        assert!(!find("opted_out").notify_on_start);
     }
 
     #[test]
-    fn skip_job_id_with_slash() {
+    fn parse_cleanup() {
         let yaml = r#"
 jobs:
-  "foo/bar":
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
+  backup:
+    schedule: "0 3 * * *"
+    run: ./backup.sh
+    cleanup:
+      path: backups/*.tar
+      keep: 7
+  no_wildcard:
+    schedule: "0 3 * * *"
+    run: ./backup.sh
+    cleanup: { path: backups/latest.tar, keep: 7 }
+  keep_none:
+    schedule: "0 3 * * *"
+    run: ./backup.sh
+    cleanup: { path: "*.tar", keep: 0 }
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert!(jobs.is_empty()); // Invalid job is skipped
+        let (_, jobs, skipped) = parse_config_in(yaml, None).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(
+            jobs[0].cleanup,
+            Some(CleanupConfig {
+                path: "backups/*.tar".to_string(),
+                keep: 7
+            })
+        );
+        let mut skipped = skipped;
+        skipped.sort();
+        assert_eq!(skipped, ["keep_none", "no_wildcard"]);
     }
 
     #[test]
-    fn accept_valid_job_id() {
+    fn parse_guards() {
         let yaml = r#"
+runner:
+  guards:
+    min_free_disk: "5G"
+    max_loadavg: 8
 jobs:
-  my-job_123:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
+  inherited:
+    schedule: "* * * * *"
+    run: echo
+  overridden:
+    schedule: "* * * * *"
+    run: echo
+    guards: { max_loadavg: 2.5, policy: defer, max_delay: 10m }
+  bad_load:
+    schedule: "* * * * *"
+    run: echo
+    guards: { max_loadavg: 0 }
+  data:
+    schedule: "* * * * *"
+    run: echo
+    guards: { min_free_disk: 50G, disk_path: /data }
+  bad_path:
+    schedule: "* * * * *"
+    run: echo
+    guards: { disk_path: data }
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].id, "my-job_123");
+        let (_, jobs, skipped) = parse_config_in(yaml, None).unwrap();
+        assert_eq!(skipped, ["bad_load", "bad_path"]);
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert_eq!(
+            find("inherited").guards,
+            Guards {
+                min_free_disk: Some(5 << 30),
+                disk_path: None,
+                max_loadavg: Some(8.0),
+                policy: GuardPolicy::Skip,
+                max_delay: Duration::from_secs(3600),
+            }
+        );
+        assert_eq!(
+            find("overridden").guards,
+            Guards {
+                min_free_disk: Some(5 << 30),
+                disk_path: None,
+                max_loadavg: Some(2.5),
+                policy: GuardPolicy::Defer,
+                max_delay: Duration::from_secs(600),
+            }
+        );
+        assert_eq!(find("data").guards.disk_path, Some(PathBuf::from("/data")));
+        assert_eq!(find("data").guards.min_free_disk, Some(50 << 30));
+
+        let (_, jobs) = parse_config("jobs:\n  a:\n    schedule: \"* * * * *\"\n    run: echo\n").unwrap();
+        assert!(!jobs[0].guards.is_set());
+        assert!(parse_config("runner:\n  guards: { min_free_disk: lots }\njobs: {}\n").is_err());
+        assert!(parse_config("runner:\n  guards: { disk_path: /data }\njobs: {}\n").is_err());
     }
 
     #[test]
-    fn skip_retry_max_zero() {
+    fn parse_circuit_breaker() {
         let yaml = r#"
+defaults:
+  circuit_breaker: { failures: 5 }
 jobs:
-  test:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-      retry:
-        max: 0
+  inherited:
+    schedule: "* * * * *"
+    run: echo
+  backoff:
+    schedule: "* * * * *"
+    run: echo
+    circuit_breaker: { failures: 3, action: backoff, backoff: 5m, max_backoff: 2h }
+  zero:
+    schedule: "* * * * *"
+    run: echo
+    circuit_breaker: { failures: 0 }
+  backoff_on_pause:
+    schedule: "* * * * *"
+    run: echo
+    circuit_breaker: { failures: 3, backoff: 5m }
+  inverted:
+    schedule: "* * * * *"
+    run: echo
+    circuit_breaker: { failures: 3, action: backoff, backoff: 2h, max_backoff: 1h }
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert!(jobs.is_empty()); // Invalid job is skipped
+        let (_, jobs, mut skipped) = parse_config_in(yaml, None).unwrap();
+        skipped.sort();
+        assert_eq!(skipped, ["backoff_on_pause", "inverted", "zero"]);
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap().circuit_breaker.clone();
+        assert_eq!(
+            find("inherited"),
+            Some(CircuitBreakerConfig {
+                failures: 5,
+                action: BreakerAction::Pause,
+                backoff: Duration::from_secs(600),
+                max_backoff: Duration::from_secs(86400),
+            })
+        );
+        assert_eq!(
+            find("backoff"),
+            Some(CircuitBreakerConfig {
+                failures: 3,
+                action: BreakerAction::Backoff,
+                backoff: Duration::from_secs(300),
+                max_backoff: Duration::from_secs(7200),
+            })
+        );
+
+        let (_, jobs) = parse_config("jobs:\n  a:\n    schedule: \"* * * * *\"\n    run: echo\n").unwrap();
+        assert_eq!(jobs[0].circuit_breaker, None);
+        assert!(parse_config("defaults:\n  circuit_breaker: { failures: 0 }\njobs: {}\n").is_err());
     }
 
     #[test]
-    fn parse_enabled_false() {
+    fn parse_artifacts() {
         let yaml = r#"
 jobs:
-  test_disabled:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-    enabled: false
+  report:
+    schedule: "* * * * *"
+    run: ./report.sh
+    artifacts: ["reports/*.csv"]
+  backup:
+    schedule: "* * * * *"
+    run: ./backup.sh
+    artifacts:
+      paths: ["out/**/*.tar.gz", "manifest.json"]
+      keep: 3
+      upload: aws s3 cp --recursive "$ROLLCRON_ARTIFACTS_DIR" s3://backups/
+  escapes:
+    schedule: "* * * * *"
+    run: echo
+    artifacts: ["../secrets/*"]
+  absolute:
+    schedule: "* * * * *"
+    run: echo
+    artifacts: ["/etc/*.conf"]
+  empty:
+    schedule: "* * * * *"
+    run: echo
+    artifacts: []
+  keep_none:
+    schedule: "* * * * *"
+    run: echo
+    artifacts: { paths: ["a"], keep: 0 }
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].enabled, false);
+        let (_, jobs, mut skipped) = parse_config_in(yaml, None).unwrap();
+        skipped.sort();
+        assert_eq!(skipped, ["absolute", "empty", "escapes", "keep_none"]);
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap().artifacts.clone();
+        assert_eq!(
+            find("report"),
+            Some(ArtifactsConfig { paths: vec!["reports/*.csv".to_string()], keep: 10, upload: None })
+        );
+        let backup = find("backup").unwrap();
+        assert_eq!(backup.paths, ["out/**/*.tar.gz", "manifest.json"]);
+        assert_eq!(backup.keep, 3);
+        assert!(backup.upload.unwrap().starts_with("aws s3 cp"));
     }
 
     #[test]
-    fn parse_enabled_true() {
+    fn parse_condition() {
         let yaml = r#"
 jobs:
-  test_enabled:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-    enabled: true
+  batch:
+    schedule: "* * * * *"
+    run: ./batch.sh
+    condition: test -f /var/run/allow-batch
+  always:
+    schedule: "* * * * *"
+    run: echo
+  blank:
+    schedule: "* * * * *"
+    run: echo
+    condition: " "
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].enabled, true);
+        let (_, jobs, skipped) = parse_config_in(yaml, None).unwrap();
+        assert_eq!(skipped, ["blank"]);
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap().condition.clone();
+        assert_eq!(find("batch").as_deref(), Some("test -f /var/run/allow-batch"));
+        assert_eq!(find("always"), None);
     }
 
     #[test]
-    fn parse_enabled_default() {
+    fn parse_success_when() {
         let yaml = r#"
 jobs:
-  test_default:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
+  legacy:
+    schedule: "* * * * *"
+    run: echo
+    success_when: { exit_codes: [0, 2], stdout_matches: "^OK", stderr_not_matches: "FATAL" }
+  patterns_only:
+    schedule: "* * * * *"
+    run: echo
+    success_when: { stdout_matches: "done" }
+  no_codes:
+    schedule: "* * * * *"
+    run: echo
+    success_when: { exit_codes: [] }
+  bad_pattern:
+    schedule: "* * * * *"
+    run: echo
+    success_when: { stderr_not_matches: "(" }
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].enabled, true);
+        let (_, jobs, mut skipped) = parse_config_in(yaml, None).unwrap();
+        skipped.sort();
+        assert_eq!(skipped, ["bad_pattern", "no_codes"]);
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap().success_when.clone().unwrap();
+        let legacy = find("legacy");
+        assert_eq!(legacy.exit_codes, [0, 2]);
+        assert!(legacy.stdout_matches.unwrap().is_match("OK: 3 warnings"));
+        assert_eq!(legacy.stderr_not_matches.unwrap().as_str(), "FATAL");
+        let patterns_only = find("patterns_only");
+        assert_eq!(patterns_only.exit_codes, [0]);
+        assert!(patterns_only.stderr_not_matches.is_none());
     }
 
     #[test]
-    fn parse_job_with_timezone() {
+    fn parse_metrics() {
         let yaml = r#"
 runner:
-  timezone: Asia/Tokyo
-jobs:
-  job1:
-    schedule:
-      cron: "* * * * *"
-      timezone: America/New_York
-    run:
-      sh: echo test
-  job2:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
+  metrics:
+    textfile: /var/lib/node_exporter/rollcron.prom
+    statsd: { address: "localhost:8125", dogstatsd: true }
+jobs: {}
 "#;
-        let (runner, jobs) = parse_config(yaml).unwrap();
+        let (runner, _) = parse_config(yaml).unwrap();
+        assert_eq!(runner.metrics.textfile.as_deref(), Some("/var/lib/node_exporter/rollcron.prom"));
+        let statsd = runner.metrics.statsd.unwrap();
+        assert_eq!((statsd.prefix.as_str(), statsd.dogstatsd), ("rollcron", true));
+
+        assert!(parse_config("runner:\n  metrics: { textfile: /tmp/rollcron.txt }\njobs: {}\n").is_err());
+        assert!(parse_config("runner:\n  metrics: { statsd: { address: localhost } }\njobs: {}\n").is_err());
+    }
+
+    #[test]
+    fn parse_canary() {
+        let yaml = r#"
+runner:
+  timezone: UTC
+  canary:
+    schedule: "*/5 * * * *"
+    ping_url: https://hc-ping.com/$CHECK_ID
+defaults:
+  retry: { max: 3 }
+jobs: {}
+"#;
+        let (runner, _) = parse_config(yaml).unwrap();
+        let canary = runner.canary.unwrap();
+        assert_eq!(canary.job.id, CANARY_JOB_ID);
+        assert_eq!(canary.job.command.to_string(), "echo rollcron-canary");
+        assert_eq!(canary.job.timeout, Duration::from_secs(60));
+        assert!(canary.job.retry.is_none());
+        assert_eq!(canary.max_sync_age, None);
+        let env = HashMap::from([("CHECK_ID".to_string(), "abc".to_string())]);
+        assert_eq!(canary.ping_url(Some(&env)).as_deref(), Some("https://hc-ping.com/abc"));
+
+        let reserved = "runner:\n  canary: {}\njobs:\n  _canary: { schedule: \"* * * * *\", run: echo }\n";
+        assert!(parse_config(reserved).unwrap_err().to_string().contains("reserved"));
+        assert!(parse_config("runner:\n  canary: { schedule: nope }\njobs: {}\n").is_err());
+    }
+
+    #[test]
+    fn parse_brake() {
+        assert_eq!(parse_config("jobs: {}").unwrap().0.brake, None);
+        let (runner, _) = parse_config("runner:\n  brake: { failure_rate: 40, window: 30m }\njobs: {}\n").unwrap();
         assert_eq!(
-            runner.timezone,
-            TimezoneConfig::Named(chrono_tz::Asia::Tokyo)
+            runner.brake,
+            Some(BrakeConfig { failure_rate: 0.4, window: Duration::from_secs(1800), min_runs: 5 })
         );
-        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert!(parse_config("runner:\n  brake: { failure_rate: 100 }\njobs: {}\n").is_err());
+        assert!(parse_config("runner:\n  brake: { failure_rate: 50, min_runs: 0 }\njobs: {}\n").is_err());
+    }
+
+    #[test]
+    fn parse_export() {
+        assert_eq!(parse_config("jobs: {}").unwrap().0.export, None);
+        let (runner, _) = parse_config("runner:\n  export: { to: \"s3://audit/rollcron/prod/\" }\njobs: {}\n").unwrap();
+        let export = runner.export.unwrap();
         assert_eq!(
-            find("job1").timezone,
-            Some(TimezoneConfig::Named(chrono_tz::America::New_York))
+            export,
+            ExportConfig {
+                store: ObjectStore::S3,
+                bucket: "audit".to_string(),
+                prefix: "rollcron/prod".to_string(),
+                endpoint: None,
+            }
         );
+        assert_eq!(export.url("repo/backup/1.json"), "s3://audit/rollcron/prod/repo/backup/1.json");
+        let (runner, _) = parse_config("runner:\n  export: { to: \"gs://audit\" }\njobs: {}\n").unwrap();
+        assert_eq!(runner.export.unwrap().url("repo/summaries/2026-01-01.json"), "gs://audit/repo/summaries/2026-01-01.json");
+        let (runner, _) =
+            parse_config("runner:\n  export: { to: \"s3://logs\", endpoint: \"http://minio:9000\" }\njobs: {}\n").unwrap();
+        assert_eq!(runner.export.unwrap().endpoint.as_deref(), Some("http://minio:9000"));
+        assert!(parse_config("runner:\n  export: { to: \"https://audit\" }\njobs: {}\n").is_err());
+        assert!(parse_config("runner:\n  export: { to: \"s3:///prefix\" }\njobs: {}\n").is_err());
+        assert!(parse_config("runner:\n  export: { to: \"gs://a\", endpoint: \"http://x\" }\njobs: {}\n").is_err());
+    }
+
+    #[test]
+    fn parse_slo() {
+        let job = |slo: &str| {
+            let yaml = format!("jobs:\n  etl:\n    schedule: \"0 * * * *\"\n    run: ./etl\n    slo: {}\n", slo);
+            // An invalid job is skipped
+            parse_config(&yaml).unwrap().1.pop().map(|job| job.slo)
+        };
         assert_eq!(
-            find("job2").timezone,
-            Some(TimezoneConfig::Named(chrono_tz::Asia::Tokyo))
+            job("{ duration_factor: 3, failure_rate: 25 }"),
+            Some(Some(SloConfig { duration_factor: Some(3.0), failure_rate: Some(0.25), runs: 20 }))
         );
+        assert_eq!(job("{ failure_rate: 10, runs: 50 }").flatten().map(|slo| slo.runs), Some(50));
+        assert_eq!(job("{}"), None);
+        assert_eq!(job("{ duration_factor: 0.5 }"), None);
+        assert_eq!(job("{ failure_rate: 100 }"), None);
     }
 
     #[test]
-    fn parse_job_timezone_inherit() {
+    fn parse_priority() {
         let yaml = r#"
+runner:
+  max_concurrent_runs: 2
 jobs:
-  test:
-    schedule:
-      cron: "* * * * *"
-      timezone: inherit
-    run:
-      sh: echo test
+  urgent:
+    schedule: "* * * * *"
+    run: echo
+    priority: high
+  bulk:
+    schedule: "* * * * *"
+    run: echo
+    priority: low
+  custom:
+    schedule: "* * * * *"
+    run: echo
+    priority: 42
+  plain:
+    schedule: "* * * * *"
+    run: echo
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].timezone, Some(TimezoneConfig::Inherit));
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        assert_eq!(runner.max_concurrent_runs, Some(2));
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap().priority;
+        assert_eq!((find("urgent"), find("bulk"), find("custom"), find("plain")), (10, -10, 42, 0));
+
+        assert!(parse_config("runner:\n  max_concurrent_runs: 0\njobs: {}\n").is_err());
     }
 
     #[test]
-    fn skip_invalid_job_timezone() {
-        let yaml = r#"
-jobs:
-  test:
-    schedule:
-      cron: "* * * * *"
-      timezone: Invalid/Zone
-    run:
-      sh: echo test
-"#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert!(jobs.is_empty()); // Invalid job is skipped
+    fn jobs_keep_definition_order() {
+        let ids = |yaml: &str| parse_config(yaml).map(|(_, jobs)| jobs.into_iter().map(|j| j.id).collect::<Vec<_>>());
+        let job = |id: &str| format!("  {}: {{ schedule: \"* * * * *\", run: echo }}\n", id);
+        let order = ["zeta", "alpha", "mid", "beta", "omega", "gamma"];
+        let yaml = format!("jobs:\n{}", order.map(job).concat());
+        assert_eq!(ids(&yaml).unwrap(), order);
+
+        let dup = format!("jobs:\n{}{}", job("a"), job("a"));
+        assert!(ids(&dup).unwrap_err().to_string().contains("duplicate"));
     }
 
     #[test]
-    fn parse_runner_env() {
-        let yaml = r#"
-runner:
-  env:
-    FOO: bar
-    BAZ: qux
-jobs:
-  test:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-"#;
-        let (runner, _) = parse_config(yaml).unwrap();
-        let env = runner.env.as_ref().unwrap();
-        assert_eq!(env.get("FOO"), Some(&"bar".to_string()));
-        assert_eq!(env.get("BAZ"), Some(&"qux".to_string()));
+    fn parse_remove_orphan_dirs() {
+        assert!(parse_config("jobs: {}").unwrap().0.remove_orphan_dirs);
+        assert!(!parse_config("runner:\n  remove_orphan_dirs: false\njobs: {}\n").unwrap().0.remove_orphan_dirs);
     }
 
     #[test]
-    fn parse_runner_env_file() {
-        let yaml = r#"
-runner:
-  env_file: .env.global
-jobs:
-  test:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-"#;
-        let (runner, _) = parse_config(yaml).unwrap();
-        assert_eq!(runner.env_file.as_deref(), Some(".env.global"));
+    fn parse_on_resume() {
+        assert_eq!(parse_config("jobs: {}").unwrap().0.on_resume, ResumePolicy::CatchUp);
+        let (runner, _) = parse_config("runner:\n  on_resume: skip\njobs: {}\n").unwrap();
+        assert_eq!(runner.on_resume, ResumePolicy::Skip);
+        assert!(parse_config("runner:\n  on_resume: burst\njobs: {}\n").is_err());
     }
 
     #[test]
-    fn parse_job_env() {
+    fn signers_are_not_read_from_the_repo() {
+        for yaml in ["runner:\n  require_signed: false\njobs: {}\n", "runner:\n  allowed_signers: [3AA5C34371567BD2]\njobs: {}\n"] {
+            let err = parse_config(yaml).unwrap_err().to_string();
+            assert!(err.contains("--allowed-signers"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn parse_allow_force_reset() {
+        assert!(parse_config("jobs: {}").unwrap().0.allow_force_reset);
+        assert!(!parse_config("runner:\n  allow_force_reset: false\njobs: {}\n").unwrap().0.allow_force_reset);
+    }
+
+    #[test]
+    fn parse_submodules_and_lfs() {
+        let (runner, _) = parse_config("jobs: {}").unwrap();
+        assert!(!runner.submodules && !runner.lfs);
+        let (runner, _) = parse_config("runner:\n  submodules: true\n  lfs: true\njobs: {}\n").unwrap();
+        assert!(runner.submodules && runner.lfs);
+    }
+
+    #[test]
+    fn parse_changelog() {
+        assert!(!parse_config("jobs: {}").unwrap().0.changelog);
+        assert!(parse_config("runner:\n  changelog: true\njobs: {}\n").unwrap().0.changelog);
+    }
+
+    #[test]
+    fn select_by_labels() {
         let yaml = r#"
 jobs:
-  test:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-    env:
-      KEY1: value1
-      KEY2: value2
+  etl:
+    schedule: "0 2 * * *"
+    run: ./etl
+    labels: { role: etl, schedule: nightly }
+  report:
+    schedule: "0 8 * * *"
+    run: ./report
+    labels: { role: reports }
+  unlabeled:
+    schedule: "0 8 * * *"
+    run: ./other
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        let env = jobs[0].env.as_ref().unwrap();
-        assert_eq!(env.get("KEY1"), Some(&"value1".to_string()));
-        assert_eq!(env.get("KEY2"), Some(&"value2".to_string()));
+        let (_, jobs, _) = parse_config_in(yaml, None).unwrap();
+        let selected = |specs: &[&str]| {
+            let selectors: Vec<LabelSelector> = specs.iter().map(|s| s.parse().unwrap()).collect();
+            let mut ids: Vec<&str> =
+                jobs.iter().filter(|j| j.has_any_label(&selectors)).map(|j| j.id.as_str()).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(selected(&["schedule"]), ["etl"]);
+        assert_eq!(selected(&["role=etl", "role:reports"]), ["etl", "report"]);
+        assert_eq!(selected(&["role=web"]), Vec::<&str>::new());
+        assert_eq!(selected(&[]), ["etl", "report", "unlabeled"]);
+        assert!("=etl".parse::<LabelSelector>().is_err());
+        assert_eq!("role=etl".parse::<LabelSelector>().unwrap().to_string(), "role=etl");
     }
 
     #[test]
-    fn parse_job_env_file() {
+    fn parse_when() {
         let yaml = r#"
 jobs:
-  test:
-    schedule:
-      cron: "* * * * *"
-    run:
-      sh: echo test
-    env_file: .env.job
+  backup:
+    schedule: "0 3 * * *"
+    run: ./backup
+    when:
+      hostname: ["db-*", backup]
+      cpus: 4
+      memory_total: ">=8G"
+  bad:
+    schedule: "0 3 * * *"
+    run: ./backup
+    when: { memory_total: ">=lots" }
 "#;
-        let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].env_file.as_deref(), Some(".env.job"));
+        let (_, jobs, skipped) = parse_config_in(yaml, None).unwrap();
+        assert_eq!(skipped, ["bad"]);
+        let facts = |pairs: &[(&str, &str)]| -> Facts { pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect() };
+        let db = facts(&[("hostname", "db-1"), ("cpus", "4"), ("memory_total", "17179869184")]);
+        assert_eq!(jobs[0].host_mismatch(&db), None);
+        let small = facts(&[("hostname", "backup"), ("cpus", "4"), ("memory_total", "4294967296")]);
+        assert!(jobs[0].host_mismatch(&small).unwrap().contains("memory_total"));
+        assert!(jobs[0].host_mismatch(&facts(&[])).is_some());
     }
 
     #[test]
-    fn parse_full_env_config() {
+    fn parse_defaults_block() {
         let yaml = r#"
 runner:
-  env_file: .env.global
-  env:
-    GLOBAL_VAR: global_value
+  timezone: Asia/Tokyo
+  shell: bash
+defaults:
+  timeout: 10m
+  retry:
+    max: 3
+    delay: 5s
+    jitter: 2s
+  timezone: America/New_York
+  severity: critical
+  notify_on_start: true
 jobs:
-  test:
+  inherits:
+    schedule: "0 * * * *"
+    run: ./backup
+  overrides:
     schedule:
-      cron: "* * * * *"
+      cron: "0 * * * *"
+      timezone: UTC
     run:
-      sh: echo test
-    env_file: .env.local
-    env:
-      LOCAL_VAR: local_value
+      sh: ./backup
+      timeout: 30s
+      retry:
+        max: 1
+    shell: sh
+    severity: info
+    notify_on_start: false
 "#;
         let (runner, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(runner.env_file.as_deref(), Some(".env.global"));
-        assert_eq!(
-            runner.env.as_ref().unwrap().get("GLOBAL_VAR"),
-            Some(&"global_value".to_string())
-        );
-        assert_eq!(jobs[0].env_file.as_deref(), Some(".env.local"));
-        assert_eq!(
-            jobs[0].env.as_ref().unwrap().get("LOCAL_VAR"),
-            Some(&"local_value".to_string())
+        assert_eq!(runner.timezone, TimezoneConfig::Named(chrono_tz::Asia::Tokyo));
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+
+        let inherits = find("inherits");
+        assert_eq!(inherits.timeout, Duration::from_secs(600));
+        let retry = inherits.retry.as_ref().unwrap();
+        assert_eq!((retry.max, retry.delay, retry.jitter), (3, Duration::from_secs(5), Some(Duration::from_secs(2))));
+        assert_eq!(inherits.timezone, Some(TimezoneConfig::Named(chrono_tz::America::New_York)));
+        assert_eq!(inherits.shell, Shell::Bash);
+        assert_eq!(inherits.severity, Severity::Critical);
+        assert!(inherits.notify_on_start);
+
+        let overrides = find("overrides");
+        assert_eq!(overrides.timeout, Duration::from_secs(30));
+        assert_eq!(overrides.retry.as_ref().unwrap().max, 1);
+        assert_eq!(overrides.timezone, Some(TimezoneConfig::Named(chrono_tz::UTC)));
+        assert_eq!(overrides.shell, Shell::Sh);
+        assert_eq!(overrides.severity, Severity::Info);
+        assert!(!overrides.notify_on_start);
+
+        let err = parse_config("defaults:\n  timeout: soon\njobs: {}\n").unwrap_err();
+        assert!(err.to_string().contains("defaults.timeout"), "{}", err);
+        assert!(parse_config("defaults:\n  concurrency: wait\njobs: {}\n").is_err());
+    }
+
+    #[test]
+    fn job_lines_of_block_mapping() {
+        let yaml = r#"# ops jobs
+runner:
+  timezone: UTC
+
+jobs:   # all of them
+    backup:
+      schedule: "0 3 * * *"
+      run: |
+        tar czf
+        x: y
+    # cleanup: disabled
+    "cleanup":
+      schedule: "@daily"
+      run: echo
+defaults:
+  timeout: 1m
+"#;
+        let lines = job_lines(yaml);
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines["backup"], lines["cleanup"]), (6, 12));
+        assert!(job_lines("jobs: { a: { schedule: \"@daily\", run: echo } }").is_empty());
+    }
+
+    #[test]
+    fn load_config_with_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("jobs/team-a")).unwrap();
+        std::fs::create_dir_all(root.join(".hidden")).unwrap();
+        let write = |path: &str, content: &str| std::fs::write(root.join(path), content).unwrap();
+        write(
+            CONFIG_FILE,
+            "runner:\n  timezone: Asia/Tokyo\ninclude: [\"jobs/**/*.yaml\"]\njobs:\n  main:\n    schedule: \"* * * * *\"\n    run: echo\n",
         );
+        write("jobs/a.yaml", "jobs:\n  a:\n    schedule: \"* * * * *\"\n    run: echo\n");
+        write("jobs/team-a/b.yaml", "jobs:\n  b:\n    schedule: \"* * * * *\"\n    run: echo\n  broken:\n    schedule: \"nope\"\n    run: echo\n");
+        write("jobs/notes.txt", "not yaml");
+        write(".hidden/c.yaml", "jobs:\n  c:\n    schedule: \"* * * * *\"\n    run: echo\n");
+
+        let content = std::fs::read_to_string(root.join(CONFIG_FILE)).unwrap();
+        let (runner, jobs, skipped) = parse_config_in(&content, Some(root)).unwrap();
+        // rollcron.yaml first, then included files in path order
+        let ids: Vec<&str> = jobs.iter().map(|j| j.id.as_str()).collect();
+        assert_eq!(ids, ["main", "a", "b"]);
+        let sources: Vec<&str> = jobs.iter().map(|j| j.source.as_str()).collect();
+        assert_eq!(sources, ["rollcron.yaml:5", "jobs/a.yaml:2", "jobs/team-a/b.yaml:2"]);
+        assert_eq!(skipped, ["broken"]);
+        assert_eq!(jobs.iter().find(|j| j.id == "a").unwrap().timezone, Some(runner.timezone.clone()));
+
+        write("jobs/dup.yaml", "jobs:\n  main:\n    schedule: \"* * * * *\"\n    run: echo\n");
+        let err = load_config(root).unwrap_err().to_string();
+        assert_eq!(err, "Duplicate job 'main' in jobs/dup.yaml:2 (already defined in rollcron.yaml:5)");
+
+        write("jobs/dup.yaml", "runner: {}\n");
+        assert!(load_config(root).unwrap_err().to_string().starts_with("Failed to parse jobs/dup.yaml"));
+
+        assert!(parse_config("include: [../x.yaml]").is_err());
+        assert!(parse_config_in("include: [missing.yaml]", Some(root)).is_err());
     }
 
     #[test]
@@ -1192,6 +4362,53 @@ jobs:
         );
     }
 
+    #[test]
+    fn parse_webhook_template() {
+        let yaml = r#"
+runner:
+  webhook:
+    - url: https://hooks.slack.com/services/x
+      template: '{"text": "{{ job.name }} exited {{ run.exit_code }}"}'
+jobs: {}
+"#;
+        let (runner, _) = parse_config(yaml).unwrap();
+        assert!(runner.webhook[0].template.as_deref().unwrap().contains("run.exit_code"));
+
+        let bad = "runner:\n  webhook:\n    - url: https://x\n      template: '{{ job.name'\njobs: {}\n";
+        let err = format!("{:#}", parse_config(bad).unwrap_err());
+        assert!(err.contains("invalid webhook template"), "{}", err);
+    }
+
+    #[test]
+    fn parse_dedupe_alerts() {
+        let yaml = r#"
+defaults:
+  dedupe_alerts: true
+jobs:
+  inherits:
+    schedule: "0 * * * *"
+    run: ./sync
+    renotify_after: 6h
+  opted_out:
+    schedule: "0 * * * *"
+    run: ./sync
+    dedupe_alerts: false
+    notify_on_output_change: true
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert!(find("inherits").dedupe_alerts);
+        assert_eq!(find("inherits").renotify_after, Some(Duration::from_secs(6 * 3600)));
+        assert!(!find("opted_out").dedupe_alerts);
+        assert!(find("opted_out").notify_on_output_change);
+        assert!(!find("inherits").notify_on_output_change);
+
+        let bad = "jobs:\n  a:\n    schedule: \"0 * * * *\"\n    run: ./a\n    renotify_after: 1h\n";
+        let (_, jobs, invalid) = parse_config_in(bad, None).unwrap();
+        assert!(jobs.is_empty());
+        assert_eq!(invalid, ["a"]);
+    }
+
     #[test]
     fn parse_multiple_webhooks() {
         let yaml = r#"
@@ -1240,6 +4457,8 @@ jobs:
         let webhook = WebhookConfig {
             webhook_type: "discord".to_string(),
             url: "$DISCORD_WEBHOOK".to_string(),
+            filter: None,
+            template: None,
         };
 
         assert_eq!(
@@ -1255,6 +4474,8 @@ jobs:
         let webhook = WebhookConfig {
             webhook_type: "discord".to_string(),
             url: "$UNDEFINED_VAR".to_string(),
+            filter: None,
+            template: None,
         };
 
         // Undefined vars are kept as-is (caller should validate)
@@ -1275,8 +4496,8 @@ jobs:
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
         let build = jobs[0].build.as_ref().unwrap();
-        assert_eq!(build.command, "cargo build --release");
-        assert_eq!(jobs[0].command, "./target/release/app");
+        assert_eq!(build.command.to_string(), "cargo build --release");
+        assert_eq!(jobs[0].command.to_string(), "./target/release/app");
     }
 
     #[test]
@@ -1368,6 +4589,59 @@ jobs:
         assert_eq!(jobs[0].log_max_size, 10 * 1024 * 1024); // default 10M
     }
 
+    #[test]
+    fn parse_log_compression() {
+        let yaml = r#"
+jobs:
+  zstd_job:
+    schedule: "* * * * *"
+    run: echo test
+    log:
+      file: output.log
+      compress: zstd
+      compress_level: 19
+  gzip_job:
+    schedule: "* * * * *"
+    run: echo test
+    log:
+      compress: gzip
+      flush: run
+  plain_job:
+    schedule: "* * * * *"
+    run: echo test
+    log: output.log
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        let find = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert_eq!(find("zstd_job").log_compress, Compression::Zstd);
+        assert_eq!(find("zstd_job").log_compress_level, Some(19));
+        assert_eq!(find("gzip_job").log_compress, Compression::Gzip);
+        assert!(find("gzip_job").log_compress_level.is_none());
+        assert_eq!(find("plain_job").log_compress, Compression::None);
+        assert_eq!(find("gzip_job").log_flush, LogFlush::Run);
+        assert_eq!(find("plain_job").log_flush, LogFlush::Stream);
+    }
+
+    #[test]
+    fn skip_invalid_log_compress_level() {
+        let yaml = r#"
+jobs:
+  too_high:
+    schedule: "* * * * *"
+    run: echo test
+    log:
+      compress: gzip
+      compress_level: 10
+  no_algorithm:
+    schedule: "* * * * *"
+    run: echo test
+    log:
+      compress_level: 3
+"#;
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert!(jobs.is_empty());
+    }
+
     #[test]
     fn parse_no_log_config() {
         let yaml = r#"
@@ -1402,7 +4676,7 @@ jobs:
         let build = jobs[0].build.as_ref().unwrap();
         assert_eq!(build.env_file.as_deref(), Some(".env.build"));
         let env = build.env.as_ref().unwrap();
-        assert_eq!(env.get("CARGO_INCREMENTAL"), Some(&"1".to_string()));
+        assert_eq!(env.get("CARGO_INCREMENTAL"), Some(&EnvValue::Plain("1".to_string())));
     }
 
     #[test]
@@ -1421,7 +4695,7 @@ jobs:
         let (_, jobs) = parse_config(yaml).unwrap();
         assert_eq!(jobs[0].run_env_file.as_deref(), Some(".env.run"));
         let env = jobs[0].run_env.as_ref().unwrap();
-        assert_eq!(env.get("NODE_ENV"), Some(&"production".to_string()));
+        assert_eq!(env.get("NODE_ENV"), Some(&EnvValue::Plain("production".to_string())));
     }
 
     #[test]
@@ -1456,14 +4730,14 @@ jobs:
         assert_eq!(runner.env_file.as_deref(), Some(".env.global"));
         assert_eq!(
             runner.env.as_ref().unwrap().get("GLOBAL_VAR"),
-            Some(&"global".to_string())
+            Some(&EnvValue::Plain("global".to_string()))
         );
 
         // Job-level env
         assert_eq!(jobs[0].env_file.as_deref(), Some(".env.job"));
         assert_eq!(
             jobs[0].env.as_ref().unwrap().get("JOB_VAR"),
-            Some(&"job".to_string())
+            Some(&EnvValue::Plain("job".to_string()))
         );
 
         // Build-specific env
@@ -1471,14 +4745,14 @@ jobs:
         assert_eq!(build.env_file.as_deref(), Some(".env.build"));
         assert_eq!(
             build.env.as_ref().unwrap().get("BUILD_VAR"),
-            Some(&"build".to_string())
+            Some(&EnvValue::Plain("build".to_string()))
         );
 
         // Run-specific env
         assert_eq!(jobs[0].run_env_file.as_deref(), Some(".env.run"));
         assert_eq!(
             jobs[0].run_env.as_ref().unwrap().get("RUN_VAR"),
-            Some(&"run".to_string())
+            Some(&EnvValue::Plain("run".to_string()))
         );
     }
 
@@ -1493,7 +4767,7 @@ jobs:
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
         assert_eq!(jobs.len(), 1);
-        assert_eq!(jobs[0].command, "echo hello");
+        assert_eq!(jobs[0].command.to_string(), "echo hello");
     }
 
     #[test]
@@ -1506,7 +4780,7 @@ jobs:
     run: echo hello
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].command, "echo hello");
+        assert_eq!(jobs[0].command.to_string(), "echo hello");
         assert_eq!(jobs[0].timeout, Duration::from_secs(3600)); // default
         assert_eq!(jobs[0].concurrency, Concurrency::Skip); // default
     }
@@ -1522,8 +4796,8 @@ jobs:
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
         let build = jobs[0].build.as_ref().unwrap();
-        assert_eq!(build.command, "cargo build --release");
-        assert_eq!(jobs[0].command, "./target/release/app");
+        assert_eq!(build.command.to_string(), "cargo build --release");
+        assert_eq!(jobs[0].command.to_string(), "./target/release/app");
     }
 
     #[test]
@@ -1551,8 +4825,8 @@ jobs:
     log: app.log
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].build.as_ref().unwrap().command, "make");
-        assert_eq!(jobs[0].command, "./app");
+        assert_eq!(jobs[0].build.as_ref().unwrap().command.to_string(), "make");
+        assert_eq!(jobs[0].command.to_string(), "./app");
         assert_eq!(jobs[0].log_file.as_deref(), Some("app.log"));
     }
 
@@ -1568,7 +4842,7 @@ jobs:
     log: app.log
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
-        assert_eq!(jobs[0].command, "./app");
+        assert_eq!(jobs[0].command.to_string(), "./app");
         assert_eq!(jobs[0].timeout, Duration::from_secs(30));
         assert_eq!(jobs[0].log_file.as_deref(), Some("app.log"));
     }
@@ -1583,7 +4857,7 @@ jobs:
 "#;
         let (_, jobs) = parse_config(yaml).unwrap();
         assert_eq!(jobs.len(), 1);
-        assert_eq!(jobs[0].command, "echo weekly");
+        assert_eq!(jobs[0].command.to_string(), "echo weekly");
     }
 
     #[test]
@@ -1627,4 +4901,57 @@ jobs:
             Some(TimezoneConfig::Named(chrono_tz::Asia::Tokyo))
         );
     }
+
+    #[test]
+    fn parse_schedule_aliases() {
+        let daily = parse_schedule("@daily", Some(CronFormat::Standard)).unwrap().unwrap();
+        assert_eq!(daily.as_str(), "0 0 * * *");
+        assert!(parse_schedule("@hourly", Some(CronFormat::Quartz)).unwrap().is_some());
+
+        let yaml = "jobs:\n  warm:\n    schedule: \"@reboot\"\n    run: ./warm\n";
+        let (_, jobs) = parse_config(yaml).unwrap();
+        assert!(jobs[0].schedule.is_none());
+        assert!(jobs[0].run_on_start);
+        assert_eq!(jobs[0].schedule_str(), "@reboot");
+    }
+
+    #[test]
+    fn parse_schedule_cron_formats() {
+        let error = |expr: &str, format| parse_schedule(expr, format).unwrap_err().to_string();
+        // Unset: 5 fields, or seconds first
+        assert!(parse_schedule("*/10 * * * * *", None).is_ok());
+        assert!(error("*/10 * * * * *", Some(CronFormat::Standard)).contains("6 fields, but cron_format standard takes 5"));
+        assert!(error("0 3 * * *", Some(CronFormat::Quartz)).contains("5 fields, but cron_format quartz takes 6 or 7"));
+
+        // Quartz day-of-week counts from Sunday = 1
+        let quartz = parse_schedule("0 0 3 ? * 2", Some(CronFormat::Quartz)).unwrap().unwrap();
+        let monday = NaiveDate::from_ymd_opt(2026, 1, 5).unwrap().and_hms_opt(3, 0, 0).unwrap().and_utc();
+        assert!(quartz.is_time_matching(&monday).unwrap());
+
+        assert!(error("61 * * * *", None).contains("minute field '61' is invalid"));
+        assert!(error("0 3 * 13 *", Some(CronFormat::Standard)).contains("month field '13' is invalid"));
+        assert!(error("0 0 3 * * MON#9", Some(CronFormat::Quartz)).contains("day-of-week field 'MON#9' is invalid"));
+        assert!(error("@fortnightly", None).contains("unknown alias"));
+    }
+
+    #[test]
+    fn schema_refs_resolve() {
+        fn walk(node: &serde_json::Value, root: &serde_json::Value) {
+            match node {
+                serde_json::Value::Object(map) => {
+                    if let Some(r) = map.get("$ref").and_then(|r| r.as_str()) {
+                        let pointer = r.strip_prefix('#').unwrap();
+                        assert!(root.pointer(pointer).is_some(), "dangling $ref {}", r);
+                    }
+                    map.values().for_each(|v| walk(v, root));
+                }
+                serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, root)),
+                _ => {}
+            }
+        }
+        let schema = crate::schema::schema();
+        walk(&schema, &schema);
+        let include = crate::schema::include_schema();
+        walk(&include, &include);
+    }
 }
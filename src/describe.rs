@@ -0,0 +1,89 @@
+//! Schedules as sentences (`At 03:00, on Monday, ..., Asia/Tokyo`) for people
+//! who don't read cron. The wording comes from croner's cron descriptor, which
+//! speaks English and Swedish.
+
+use crate::config::{Job, RunnerConfig, TimezoneConfig};
+use clap::ValueEnum;
+use croner::describe::lang::swedish::Swedish;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum Lang {
+    #[default]
+    En,
+    Sv,
+}
+
+impl Lang {
+    /// From a locale or language tag (`sv_SE.UTF-8`, `sv-SE`, `sv`).
+    /// Untranslated languages get English.
+    pub fn from_locale(locale: &str) -> Lang {
+        let language = locale.split(['_', '-', '.', '@']).next().unwrap_or_default();
+        if language.eq_ignore_ascii_case("sv") { Lang::Sv } else { Lang::En }
+    }
+
+    /// The user's locale: `LC_ALL`, then `LC_MESSAGES`, then `LANG`.
+    pub fn from_env() -> Lang {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Lang::En, |locale| Lang::from_locale(&locale))
+    }
+}
+
+/// `job`'s schedule in `lang`, ending with the timezone it fires in.
+pub fn describe(job: &Job, runner: &RunnerConfig, lang: Lang) -> String {
+    let sentence = match (&job.schedule, lang) {
+        (Some(schedule), Lang::En) => schedule.describe(),
+        (Some(schedule), Lang::Sv) => schedule.describe_lang(Swedish),
+        (None, Lang::En) => return "At daemon startup".to_string(),
+        (None, Lang::Sv) => return "Vid start av tjänsten".to_string(),
+    };
+    let zone = match job.timezone.as_ref().unwrap_or(&runner.timezone) {
+        TimezoneConfig::Utc => "UTC",
+        TimezoneConfig::Inherit => match lang {
+            Lang::En => "local time",
+            Lang::Sv => "lokal tid",
+        },
+        TimezoneConfig::Named(tz) => tz.name(),
+    };
+    format!("{}, {}", sentence.trim_end_matches('.'), zone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    #[test]
+    fn describes_schedule_with_timezone() {
+        let yaml = r#"
+runner:
+  timezone: inherit
+jobs:
+  weekdays:
+    schedule: { cron: "0 3 * * 1-5", timezone: Asia/Tokyo }
+    run: echo
+  often:
+    schedule: "*/15 * * * *"
+    run: echo
+"#;
+        let (runner, jobs) = parse_config(yaml).unwrap();
+        let job = |id: &str| jobs.iter().find(|j| j.id == id).unwrap();
+        assert_eq!(
+            describe(job("weekdays"), &runner, Lang::En),
+            "At 03:00, on Monday, Tuesday, Wednesday, Thursday, and Friday, Asia/Tokyo"
+        );
+        assert_eq!(describe(job("often"), &runner, Lang::En), "At every 15 minutes, local time");
+        assert!(describe(job("often"), &runner, Lang::Sv).ends_with(", lokal tid"));
+    }
+
+    #[test]
+    fn language_from_locale() {
+        assert_eq!(Lang::from_locale("sv_SE.UTF-8"), Lang::Sv);
+        assert_eq!(Lang::from_locale("sv-SE"), Lang::Sv);
+        assert_eq!(Lang::from_locale("en_US.UTF-8"), Lang::En);
+        assert_eq!(Lang::from_locale("C"), Lang::En);
+        assert_eq!(Lang::from_locale("de_DE"), Lang::En);
+    }
+}
@@ -1,6 +1,9 @@
+use crate::config::{EnvValue, RunnerConfig};
+use crate::secrets;
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use tracing::warn;
 
 /// Expand shell-like variables in a string.
 /// Supports ~ (home directory) and $VAR / ${VAR} (environment variables).
@@ -11,6 +14,58 @@ pub fn expand_string(s: &str) -> String {
         .unwrap_or_else(|_| s.to_string())
 }
 
+/// Load runner-level env vars (runner.env_file + runner.env) for webhook URL expansion.
+/// Returns None on error (webhook will fall back to process env). Runs on the
+/// blocking pool, since secrets may shell out to vault/aws/age/sops.
+pub async fn load_runner_env(sot_path: &Path, runner: &RunnerConfig) -> Option<HashMap<String, String>> {
+    let (sot_path, runner) = (sot_path.to_path_buf(), runner.clone());
+    tokio::task::spawn_blocking(move || read_runner_env(&sot_path, &runner))
+        .await
+        .unwrap_or_default()
+}
+
+fn read_runner_env(sot_path: &Path, runner: &RunnerConfig) -> Option<HashMap<String, String>> {
+    let mut env_vars = HashMap::new();
+
+    if let Some(env_file_path) = &runner.env_file {
+        let expanded = expand_string(env_file_path);
+        let full_path = sot_path.join(&expanded);
+        match secrets::load_env_file(&full_path, &runner.secrets) {
+            Ok(vars) => env_vars.extend(vars),
+            Err(e) => {
+                warn!(target: "rollcron::webhook", error = %e, "Failed to load runner env_file");
+                return None;
+            }
+        }
+    }
+
+    if let Some(runner_env) = &runner.env {
+        if let Err(e) = merge_env_values(&mut env_vars, runner_env) {
+            warn!(target: "rollcron::webhook", error = %e, "Failed to resolve runner env");
+            return None;
+        }
+    }
+
+    Some(env_vars)
+}
+
+/// Merges config `env` values: literals are shell-expanded, secret
+/// references are fetched from their provider (on every call, so rotated
+/// credentials apply without a config change).
+pub fn merge_env_values(
+    env_vars: &mut HashMap<String, String>,
+    values: &HashMap<String, EnvValue>,
+) -> Result<()> {
+    for (k, v) in values {
+        let value = match v {
+            EnvValue::Plain(s) => expand_string(s),
+            EnvValue::Secret(secret) => secrets::resolve(secret)?,
+        };
+        env_vars.insert(k.clone(), value);
+    }
+    Ok(())
+}
+
 /// Load environment variables from .env file if it exists.
 /// Returns a HashMap of key-value pairs.
 /// If the .env file doesn't exist, returns an empty HashMap (no error).
@@ -28,14 +83,236 @@ pub fn load_env_from_path(path: &PathBuf) -> Result<HashMap<String, String>> {
         return Ok(HashMap::new());
     }
 
-    let iter = dotenvy::from_path_iter(path)
-        .with_context(|| format_env_error(path))?;
-    let mut vars = HashMap::new();
-    for item in iter {
-        let (key, value) = item.with_context(|| format_env_error(path))?;
-        vars.insert(key, value);
+    let content = std::fs::read_to_string(path).with_context(|| format_env_error(path))?;
+    parse_env(&content).with_context(|| format_env_error(path))
+}
+
+/// Parse .env file content.
+///
+/// Supported syntax:
+/// - `KEY=value`, `export KEY=value`, `# comments` (full-line and after values)
+/// - unquoted values must not contain whitespace; `\$` yields a literal `$`
+/// - `'single quoted'`: literal, may span multiple lines
+/// - `"double quoted"`: may span multiple lines, supports `\n`, `\r`, `\t`, `\"`, `\\`, `\$` escapes
+/// - `${VAR}` / `$VAR` interpolation in unquoted and double-quoted values, resolved against
+///   keys defined earlier in the file first, then the process environment (undefined = empty)
+pub fn parse_env(content: &str) -> Result<HashMap<String, String>> {
+    let mut parser = EnvParser {
+        chars: content.chars().collect(),
+        pos: 0,
+        line: 1,
+        vars: HashMap::new(),
+    };
+    parser.parse()?;
+    Ok(parser.vars)
+}
+
+struct EnvParser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    vars: HashMap<String, String>,
+}
+
+impl EnvParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.get(self.pos).copied()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_inline_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_to_line_end(&mut self) {
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn parse(&mut self) -> Result<()> {
+        loop {
+            self.skip_inline_whitespace();
+            match self.peek() {
+                None => return Ok(()),
+                Some('\n' | '\r') => {
+                    self.bump();
+                }
+                Some('#') => self.skip_to_line_end(),
+                Some(_) => self.parse_assignment()?,
+            }
+        }
+    }
+
+    fn parse_assignment(&mut self) -> Result<()> {
+        let line = self.line;
+        let mut key = self.read_key();
+        if key == "export" && matches!(self.peek(), Some(' ' | '\t')) {
+            self.skip_inline_whitespace();
+            key = self.read_key();
+        }
+        if key.is_empty() || key.starts_with(|c: char| c.is_ascii_digit()) {
+            anyhow::bail!("line {}: invalid variable name", line);
+        }
+
+        self.skip_inline_whitespace();
+        if self.bump() != Some('=') {
+            anyhow::bail!("line {}: expected '=' after '{}'", line, key);
+        }
+        self.skip_inline_whitespace();
+
+        let value = match self.peek() {
+            Some('\'') => self.read_single_quoted(line)?,
+            Some('"') => self.read_double_quoted(line)?,
+            _ => self.read_unquoted(line)?,
+        };
+        self.vars.insert(key, value);
+        Ok(())
+    }
+
+    fn read_key(&mut self) -> String {
+        let mut key = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                key.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        key
+    }
+
+    /// After a closing quote only whitespace and a comment may follow.
+    fn finish_quoted_line(&mut self, line: usize) -> Result<()> {
+        self.skip_inline_whitespace();
+        match self.peek() {
+            None | Some('\n' | '\r') => Ok(()),
+            Some('#') => {
+                self.skip_to_line_end();
+                Ok(())
+            }
+            Some(c) => anyhow::bail!("line {}: unexpected character '{}' after quoted value", line, c),
+        }
+    }
+
+    fn read_single_quoted(&mut self, line: usize) -> Result<String> {
+        self.bump(); // opening quote
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                None => anyhow::bail!("line {}: unterminated single-quoted value", line),
+                Some('\'') => break,
+                Some(c) => value.push(c),
+            }
+        }
+        self.finish_quoted_line(line)?;
+        Ok(value)
+    }
+
+    fn read_double_quoted(&mut self, line: usize) -> Result<String> {
+        self.bump(); // opening quote
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                None => anyhow::bail!("line {}: unterminated double-quoted value", line),
+                Some('"') => break,
+                Some('\\') => match self.bump() {
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some(c @ ('"' | '\\' | '$')) => value.push(c),
+                    Some(c) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => anyhow::bail!("line {}: unterminated double-quoted value", line),
+                },
+                Some('$') => self.read_substitution(&mut value, line)?,
+                Some(c) => value.push(c),
+            }
+        }
+        self.finish_quoted_line(line)?;
+        Ok(value)
+    }
+
+    fn read_unquoted(&mut self, line: usize) -> Result<String> {
+        let mut value = String::new();
+        let mut trailing_space = false;
+        while let Some(c) = self.peek() {
+            match c {
+                '\n' | '\r' => break,
+                '#' if trailing_space || value.is_empty() => {
+                    self.skip_to_line_end();
+                    return Ok(value);
+                }
+                ' ' | '\t' => {
+                    trailing_space = true;
+                    self.bump();
+                }
+                _ if trailing_space => {
+                    anyhow::bail!("line {}: unquoted value contains whitespace", line);
+                }
+                '\\' => {
+                    self.bump();
+                    // Only `\$` is an escape; other backslashes are kept (e.g. Windows paths)
+                    if self.peek() == Some('$') {
+                        self.bump();
+                        value.push('$');
+                    } else {
+                        value.push('\\');
+                    }
+                }
+                '$' => {
+                    self.bump();
+                    self.read_substitution(&mut value, line)?;
+                }
+                _ => {
+                    value.push(c);
+                    self.bump();
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// Reads `{VAR}` or `VAR` after a `$` and appends the resolved value.
+    fn read_substitution(&mut self, out: &mut String, line: usize) -> Result<()> {
+        let name = if self.peek() == Some('{') {
+            self.bump();
+            let name = self.read_key();
+            if self.bump() != Some('}') {
+                anyhow::bail!("line {}: unterminated '${{' substitution", line);
+            }
+            name
+        } else {
+            let name = self.read_key();
+            if name.is_empty() {
+                out.push('$');
+                return Ok(());
+            }
+            name
+        };
+
+        if let Some(v) = self.vars.get(&name) {
+            out.push_str(v);
+        } else if let Ok(v) = std::env::var(&name) {
+            out.push_str(&v);
+        }
+        Ok(())
     }
-    Ok(vars)
 }
 
 fn format_env_error(path: &Path) -> String {
@@ -64,6 +341,14 @@ mod tests {
         assert_eq!(vars.get("BAZ"), Some(&"qux".to_string()));
     }
 
+    #[test]
+    fn test_merge_env_values_plain_overrides() {
+        let mut vars = HashMap::from([("A".to_string(), "old".to_string())]);
+        let values = HashMap::from([("A".to_string(), EnvValue::Plain("new".to_string()))]);
+        merge_env_values(&mut vars, &values).unwrap();
+        assert_eq!(vars.get("A"), Some(&"new".to_string()));
+    }
+
     #[test]
     fn test_load_env_file_missing() {
         let dir = TempDir::new().unwrap();
@@ -135,6 +420,83 @@ mod tests {
         assert!(vars.is_empty());
     }
 
+    #[test]
+    fn test_parse_env_export_prefix() {
+        let vars = parse_env("export FOO=bar\nexport  BAZ=\"qux\"").unwrap();
+        assert_eq!(vars.get("FOO"), Some(&"bar".to_string()));
+        assert_eq!(vars.get("BAZ"), Some(&"qux".to_string()));
+    }
+
+    #[test]
+    fn test_parse_env_interpolation() {
+        let vars = parse_env("HOST=db\nURL=postgres://${HOST}:5432\nRAW=$HOST/x").unwrap();
+        assert_eq!(vars.get("URL"), Some(&"postgres://db:5432".to_string()));
+        assert_eq!(vars.get("RAW"), Some(&"db/x".to_string()));
+    }
+
+    #[test]
+    fn test_parse_env_interpolation_prefers_file_keys() {
+        // SAFETY: Test is single-threaded and var is unique
+        unsafe { std::env::set_var("TEST_PARSE_ENV_SHADOWED", "process") };
+        let vars = parse_env("TEST_PARSE_ENV_SHADOWED=file\nOUT=${TEST_PARSE_ENV_SHADOWED}").unwrap();
+        assert_eq!(vars.get("OUT"), Some(&"file".to_string()));
+        unsafe { std::env::remove_var("TEST_PARSE_ENV_SHADOWED") };
+    }
+
+    #[test]
+    fn test_parse_env_interpolation_falls_back_to_process_env() {
+        // SAFETY: Test is single-threaded and var is unique
+        unsafe { std::env::set_var("TEST_PARSE_ENV_PROCESS", "from-env") };
+        let vars = parse_env("OUT=${TEST_PARSE_ENV_PROCESS}\nMISSING=a${TEST_PARSE_ENV_UNDEFINED_123}b").unwrap();
+        assert_eq!(vars.get("OUT"), Some(&"from-env".to_string()));
+        assert_eq!(vars.get("MISSING"), Some(&"ab".to_string()));
+        unsafe { std::env::remove_var("TEST_PARSE_ENV_PROCESS") };
+    }
+
+    #[test]
+    fn test_parse_env_single_quotes_are_literal() {
+        let vars = parse_env("A=1\nB='${A} \\n'").unwrap();
+        assert_eq!(vars.get("B"), Some(&"${A} \\n".to_string()));
+    }
+
+    #[test]
+    fn test_parse_env_multiline_values() {
+        let vars = parse_env("DQ=\"line1\nline2\"\nSQ='a\nb'\nNEXT=ok").unwrap();
+        assert_eq!(vars.get("DQ"), Some(&"line1\nline2".to_string()));
+        assert_eq!(vars.get("SQ"), Some(&"a\nb".to_string()));
+        assert_eq!(vars.get("NEXT"), Some(&"ok".to_string()));
+    }
+
+    #[test]
+    fn test_parse_env_escapes() {
+        let vars = parse_env(r#"A="x\ny\t\"q\" \$HOME""#).unwrap();
+        assert_eq!(vars.get("A"), Some(&"x\ny\t\"q\" $HOME".to_string()));
+    }
+
+    #[test]
+    fn test_parse_env_inline_comments() {
+        let vars = parse_env("A=1 # one\nB=\"2\" # two\nC=a#b").unwrap();
+        assert_eq!(vars.get("A"), Some(&"1".to_string()));
+        assert_eq!(vars.get("B"), Some(&"2".to_string()));
+        assert_eq!(vars.get("C"), Some(&"a#b".to_string()));
+    }
+
+    #[test]
+    fn test_parse_env_crlf() {
+        let vars = parse_env("A=1\r\nB=\"2\"\r\n").unwrap();
+        assert_eq!(vars.get("A"), Some(&"1".to_string()));
+        assert_eq!(vars.get("B"), Some(&"2".to_string()));
+    }
+
+    #[test]
+    fn test_parse_env_errors_report_line() {
+        let err = parse_env("A=1\nB=\"unterminated").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+
+        let err = parse_env("A=1\n\nNOEQUALS").unwrap_err();
+        assert!(err.to_string().contains("line 3"));
+    }
+
     #[test]
     fn test_expand_string_tilde() {
         let home = std::env::var("HOME").unwrap();
@@ -0,0 +1,165 @@
+//! Machine-readable event log (`--event-log`): one JSON object per line for
+//! every lifecycle event, independent of the human log format.
+//!
+//! Every line carries `v` (schema version), `ts` (RFC 3339, UTC) and `event`.
+//! Adding events or fields keeps the version; renaming or removing them, or
+//! changing a field's type, bumps it. The schema is documented in the README.
+
+use crate::actor::job::JobRunReport;
+use crate::clock;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use tracing::warn;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+static LOG: OnceLock<Mutex<File>> = OnceLock::new();
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    DaemonStarted { source: &'a str, pid: u32, version: &'a str },
+    DaemonStopping,
+    RepoUpdated { range: &'a str },
+    SyncFailed { error: &'a str },
+    ConfigError { error: &'a str },
+    JobAdded { job_id: &'a str },
+    JobRemoved { job_id: &'a str },
+    BuildFinished {
+        job_id: &'a str,
+        success: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<&'a str>,
+    },
+    RunSkipped { job_id: &'a str, reason: &'a str },
+    RunStarted { job_id: &'a str, run_id: &'a str },
+    RunRetry { job_id: &'a str, run_id: &'a str, attempt: u32, delay_ms: u64 },
+    CanaryPassed,
+    CanaryFailed { stage: &'a str, error: &'a str },
+    RunAnomaly { job_id: &'a str, run_id: &'a str, kind: &'a str, message: &'a str },
+    BrakeEngaged { failed: usize, runs: usize, paused: &'a [String] },
+    CircuitOpened { job_id: &'a str, failures: u32 },
+    CircuitClosed { job_id: &'a str },
+    OutputChanged { job_id: &'a str, run_id: &'a str, added: usize, removed: usize },
+    BrakeReleased { resumed: &'a [String] },
+    RunFinished {
+        job_id: &'a str,
+        run_id: &'a str,
+        outcome: &'a str,
+        exit_code: Option<i32>,
+        attempts: u32,
+        duration_ms: u64,
+        fallback: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        failure_class: Option<&'a str>,
+    },
+}
+
+impl<'a> Event<'a> {
+    pub fn run_finished(report: &'a JobRunReport) -> Self {
+        Event::RunFinished {
+            job_id: &report.job_id,
+            run_id: &report.run_id,
+            outcome: report.outcome.as_str(),
+            exit_code: report.exit_code,
+            attempts: report.attempts,
+            duration_ms: report.duration.as_millis() as u64,
+            fallback: report.fallback,
+            failure_class: report.failure_class.map(|c| c.as_str()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    v: u32,
+    ts: String,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+/// Opens `path` for appending; events are dropped until this is called.
+pub fn init(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open event log {}", path.display()))?;
+    let _ = LOG.set(Mutex::new(file));
+    Ok(())
+}
+
+/// Appends `event` to the log, if one is open.
+pub fn emit(event: Event) {
+    let Some(log) = LOG.get() else {
+        return;
+    };
+    let line = render(&event);
+    // One write per line so concurrent readers never see a partial event
+    let mut file = log.lock().unwrap_or_else(|e| e.into_inner());
+    if let Err(e) = file.write_all(line.as_bytes()) {
+        warn!(target: "rollcron::runner", error = %e, "Failed to write event log");
+    }
+}
+
+fn render(event: &Event) -> String {
+    let line = Line {
+        v: SCHEMA_VERSION,
+        ts: clock::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        event,
+    };
+    let mut json = serde_json::to_string(&line).expect("events serialize to JSON");
+    json.push('\n');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn parse(event: &Event) -> Value {
+        let line = render(event);
+        assert!(line.ends_with('\n') && !line.trim_end().contains('\n'));
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn lines_carry_version_timestamp_and_event() {
+        let json = parse(&Event::RunStarted { job_id: "backup", run_id: "20260101T000000Z-0000abcd" });
+        assert_eq!(json["v"], 1);
+        assert_eq!(json["event"], "run_started");
+        assert_eq!(json["job_id"], "backup");
+        assert_eq!(json["run_id"], "20260101T000000Z-0000abcd");
+        assert!(chrono::DateTime::parse_from_rfc3339(json["ts"].as_str().unwrap()).is_ok());
+
+        assert_eq!(parse(&Event::DaemonStopping).as_object().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn optional_fields() {
+        let ok = parse(&Event::BuildFinished { job_id: "etl", success: true, error: None });
+        assert!(ok.get("error").is_none());
+        let finished = parse(&Event::RunFinished {
+            job_id: "etl",
+            run_id: "r",
+            outcome: "timeout",
+            exit_code: None,
+            attempts: 2,
+            duration_ms: 1500,
+            fallback: false,
+            failure_class: Some("timeout"),
+        });
+        // Always present, null when there is no exit code
+        assert_eq!(finished["exit_code"], Value::Null);
+        assert_eq!(finished["outcome"], "timeout");
+        assert_eq!(finished["failure_class"], "timeout");
+    }
+}
@@ -0,0 +1,394 @@
+//! Shipping of run history to object storage (`runner.export`).
+//!
+//! Each recorded run is queued as a JSON file in
+//! `~/.local/state/rollcron/export/<repo>/`. One worker uploads the run's
+//! report and stored output with the `aws` or `gcloud` CLI, so their usual
+//! credentials from the environment apply, and removes the entry once both
+//! are stored. Failed uploads are retried with backoff, also after a restart.
+//! Once a UTC day is over, the worker also uploads a summary of every job's
+//! runs that day.
+//!
+//! Objects, under the configured prefix:
+//! - `<repo>/<job-id>/<run-id>.json` and `<run-id>.log[.gz|.zst]`
+//! - `<repo>/summaries/<YYYY-MM-DD>.json`
+
+use crate::actor::job::JobRunReport;
+use crate::config::{ExportConfig, ObjectStore};
+use crate::history;
+use crate::queue::{backoff, entry_paths, read_entry, write_entry};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+/// First retry delay; doubles per failed attempt up to `MAX_DELAY`
+const BASE_DELAY: Duration = Duration::from_secs(30);
+const MAX_DELAY: Duration = Duration::from_secs(3600);
+/// Queue bound; beyond it the oldest runs are dropped
+const MAX_QUEUED: usize = 10_000;
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+/// Worker wake-up when nothing is scheduled
+const IDLE_INTERVAL: Duration = Duration::from_secs(3600);
+/// Last day a summary was made for
+const SUMMARY_MARKER: &str = "summarized";
+/// Days summarized after a long downtime, counting back from yesterday
+const MAX_CATCH_UP_DAYS: u64 = 7;
+
+static EXPORTER: OnceLock<Exporter> = OnceLock::new();
+
+struct Exporter {
+    dir: PathBuf,
+    history_root: PathBuf,
+    /// `runner.export` of the latest config seen
+    config: Mutex<Option<ExportConfig>>,
+    wake: Notify,
+}
+
+/// A run waiting to be uploaded, stored as `<created ms>-<random>.json`
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    report: JobRunReport,
+    #[serde(default)]
+    attempts: u32,
+    next_attempt_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_error: Option<String>,
+}
+
+/// Starts the upload worker. Returns the number of runs left over from a
+/// previous run.
+pub fn start(dir: PathBuf, history_root: PathBuf, config: Option<ExportConfig>) -> Result<usize> {
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let pending = entry_paths(&dir)?.len();
+    let exporter = Exporter { dir, history_root, config: Mutex::new(config), wake: Notify::new() };
+    if EXPORTER.set(exporter).is_ok() {
+        tokio::spawn(worker());
+    }
+    Ok(pending)
+}
+
+/// Queues a recorded run when `runner.export` is set. Runs recorded while it
+/// is not are never exported.
+pub fn record(config: Option<&ExportConfig>, report: &JobRunReport) {
+    let Some(exporter) = EXPORTER.get() else {
+        return;
+    };
+    *exporter.config.lock().unwrap_or_else(|e| e.into_inner()) = config.cloned();
+    if config.is_none() {
+        return;
+    }
+    let now = Utc::now();
+    let entry = Entry { report: report.clone(), attempts: 0, next_attempt_at: now, last_error: None };
+    let name = format!("{:013}-{:08x}.json", now.timestamp_millis(), rand::random::<u32>());
+    match write_entry(&exporter.dir.join(name), &entry) {
+        Ok(()) => exporter.wake.notify_one(),
+        Err(e) => warn!(target: "rollcron::export", job_id = %report.job_id, error = %e, "Failed to queue run for export"),
+    }
+}
+
+async fn worker() {
+    let Some(exporter) = EXPORTER.get() else {
+        return;
+    };
+    loop {
+        let config = exporter.config.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let mut wait = IDLE_INTERVAL;
+        if let Some(config) = config {
+            match upload_due(&config, &exporter.dir, &exporter.history_root).await {
+                Ok(Some(at)) => wait = wait.min((at - Utc::now()).to_std().unwrap_or_default()),
+                Ok(None) => {}
+                Err(e) => warn!(target: "rollcron::export", error = %e, "Failed to read export queue"),
+            }
+            if let Err(e) = summarize_due(&config, &exporter.dir, &exporter.history_root).await {
+                warn!(target: "rollcron::export", error = %e, "Failed to export daily summary");
+            }
+            wait = wait.min(until_next_day(Utc::now()));
+        }
+        tokio::select! {
+            _ = exporter.wake.notified() => {}
+            _ = tokio::time::sleep(wait) => {}
+        }
+    }
+}
+
+/// Uploads queued runs, oldest first. After a failure the rest of the queue
+/// waits for its retry. Returns when the next attempt is due.
+async fn upload_due(config: &ExportConfig, dir: &Path, history_root: &Path) -> Result<Option<DateTime<Utc>>> {
+    let mut paths = entry_paths(dir)?;
+    if paths.len() > MAX_QUEUED {
+        let excess = paths.len() - MAX_QUEUED;
+        warn!(target: "rollcron::export", dropped = excess, "Export queue full, dropping oldest runs");
+        for path in paths.drain(..excess) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    let repo = repo_name(history_root);
+    for path in paths {
+        let Some(mut entry) = read_entry::<Entry>(&path) else {
+            warn!(target: "rollcron::export", path = %path.display(), "Dropping unreadable queued run");
+            let _ = std::fs::remove_file(&path);
+            continue;
+        };
+        if entry.next_attempt_at > Utc::now() {
+            return Ok(Some(entry.next_attempt_at));
+        }
+        let report = &entry.report;
+        match upload_run(config, repo, &history_root.join(&report.job_id), report).await {
+            Ok(()) => {
+                info!(target: "rollcron::export", job_id = %report.job_id, run_id = %report.run_id, "Exported run");
+                let _ = std::fs::remove_file(&path);
+            }
+            Err(e) => {
+                entry.attempts += 1;
+                let delay = backoff(entry.attempts, BASE_DELAY, MAX_DELAY);
+                entry.next_attempt_at = Utc::now() + delay;
+                warn!(
+                    target: "rollcron::export",
+                    job_id = %report.job_id,
+                    run_id = %report.run_id,
+                    error = %e,
+                    attempts = entry.attempts,
+                    retry_in = ?delay,
+                    "Failed to export run, will retry"
+                );
+                entry.last_error = Some(e.to_string());
+                write_entry(&path, &entry)?;
+                return Ok(Some(entry.next_attempt_at));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Uploads the run's stored output (if still kept), then its report.
+async fn upload_run(config: &ExportConfig, repo: &str, dir: &Path, report: &JobRunReport) -> Result<()> {
+    let key = format!("{}/{}", repo, report.job_id);
+    if let Some(output) = history::output_file(dir, &report.run_id) {
+        let name = output.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        upload(config, Body::File(&output), &format!("{}/{}", key, name)).await?;
+    }
+    let body = serde_json::to_vec(report)?;
+    upload(config, Body::Bytes(body), &format!("{}/{}.json", key, report.run_id)).await
+}
+
+/// Uploads summaries of the days since the last one, up to yesterday (UTC).
+async fn summarize_due(config: &ExportConfig, dir: &Path, history_root: &Path) -> Result<()> {
+    let yesterday = Utc::now().date_naive() - chrono::Days::new(1);
+    let marker = dir.join(SUMMARY_MARKER);
+    let last = std::fs::read_to_string(&marker).ok().and_then(|s| s.trim().parse::<NaiveDate>().ok());
+    let oldest = yesterday - chrono::Days::new(MAX_CATCH_UP_DAYS - 1);
+    let mut day = last.and_then(|d| d.succ_opt()).unwrap_or(yesterday).max(oldest);
+    let repo = repo_name(history_root);
+    while day <= yesterday {
+        if let Some(summary) = summarize(history_root, day)? {
+            let key = format!("{}/summaries/{}.json", repo, day);
+            upload(config, Body::Bytes(serde_json::to_vec_pretty(&summary)?), &key).await?;
+            info!(target: "rollcron::export", date = %day, "Exported daily summary");
+        }
+        std::fs::write(&marker, day.to_string()).with_context(|| format!("Failed to write {}", marker.display()))?;
+        let Some(next) = day.succ_opt() else {
+            break;
+        };
+        day = next;
+    }
+    Ok(())
+}
+
+/// Every job's runs started on `day`. None when no job ran.
+fn summarize(history_root: &Path, day: NaiveDate) -> Result<Option<Value>> {
+    let Ok(entries) = std::fs::read_dir(history_root) else {
+        return Ok(None);
+    };
+    let mut jobs = BTreeMap::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let Some(job_id) = path.file_name().and_then(|n| n.to_str()).map(str::to_string) else {
+            continue;
+        };
+        if !path.is_dir() {
+            continue;
+        }
+        let runs: Vec<JobRunReport> =
+            history::read_runs(&path)?.into_iter().filter(|r| r.started_at.date_naive() == day).collect();
+        if runs.is_empty() {
+            continue;
+        }
+        let mut outcomes: BTreeMap<&str, usize> = BTreeMap::new();
+        for run in &runs {
+            *outcomes.entry(run.outcome.as_str()).or_default() += 1;
+        }
+        let durations = runs.iter().map(|r| r.duration.as_millis() as u64);
+        jobs.insert(
+            job_id,
+            json!({
+                "runs": runs.len(),
+                "outcomes": outcomes,
+                "retried": runs.iter().filter(|r| r.attempts > 1).count(),
+                "total_duration_ms": durations.clone().sum::<u64>(),
+                "max_duration_ms": durations.max().unwrap_or(0),
+            }),
+        );
+    }
+    if jobs.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(json!({ "repo": repo_name(history_root), "date": day.to_string(), "jobs": jobs })))
+}
+
+enum Body<'a> {
+    File(&'a Path),
+    /// Piped to the CLI's stdin
+    Bytes(Vec<u8>),
+}
+
+async fn upload(config: &ExportConfig, body: Body<'_>, key: &str) -> Result<()> {
+    let source = match &body {
+        Body::File(path) => path.to_string_lossy().into_owned(),
+        Body::Bytes(_) => "-".to_string(),
+    };
+    let (program, args) = command(config, &source, &config.url(key));
+    let mut child = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run {} (is it installed?)", program))?;
+    let mut stdin = child.stdin.take();
+    if let (Body::Bytes(bytes), Some(stdin)) = (&body, stdin.as_mut()) {
+        stdin.write_all(bytes).await.with_context(|| format!("Failed to write to {}", program))?;
+    }
+    drop(stdin);
+    let output = tokio::time::timeout(UPLOAD_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| anyhow::anyhow!("{} timed out after {:?}", program, UPLOAD_TIMEOUT))??;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("{} failed ({}): {}", program, output.status, stderr.trim());
+    }
+    Ok(())
+}
+
+/// The CLI invocation copying `source` (a path, or `-` for stdin) to `url`.
+fn command(config: &ExportConfig, source: &str, url: &str) -> (&'static str, Vec<String>) {
+    match config.store {
+        ObjectStore::S3 => {
+            let mut args: Vec<String> =
+                ["s3", "cp", "--only-show-errors", source, url].iter().map(|s| s.to_string()).collect();
+            if let Some(endpoint) = &config.endpoint {
+                args.extend(["--endpoint-url".to_string(), endpoint.clone()]);
+            }
+            ("aws", args)
+        }
+        ObjectStore::Gcs => ("gcloud", ["storage", "cp", "--quiet", source, url].iter().map(|s| s.to_string()).collect()),
+    }
+}
+
+/// Time left until the next UTC midnight, when a new summary is due.
+fn until_next_day(now: DateTime<Utc>) -> Duration {
+    let tomorrow = now.date_naive() + chrono::Days::new(1);
+    let midnight = tomorrow.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    (midnight - now).to_std().unwrap_or_default()
+}
+
+fn repo_name(history_root: &Path) -> &str {
+    history_root.file_name().and_then(|n| n.to_str()).unwrap_or("unknown")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_counts_each_jobs_runs_of_the_day() {
+        let root = tempfile::tempdir().unwrap();
+        let history_root = root.path().join("my-repo");
+        let run = |job: &str, started_at: &str, outcome: &str, duration_ms: u64, attempts: u32| {
+            let dir = history_root.join(job);
+            std::fs::create_dir_all(&dir).unwrap();
+            let line = json!({
+                "job_id": job,
+                "run_id": format!("{}-{}", started_at, job),
+                "started_at": started_at,
+                "duration_ms": duration_ms,
+                "attempts": attempts,
+                "outcome": outcome,
+                "exit_code": null,
+                "error": null,
+                "fallback": false,
+                "stdout_tail": "",
+                "stderr_tail": "",
+            });
+            let mut runs = std::fs::read_to_string(dir.join("runs.jsonl")).unwrap_or_default();
+            runs.push_str(&format!("{}\n", line));
+            std::fs::write(dir.join("runs.jsonl"), runs).unwrap();
+        };
+        run("backup", "2026-03-01T23:59:00Z", "success", 500, 1);
+        run("backup", "2026-03-02T03:00:00Z", "success", 1000, 1);
+        run("backup", "2026-03-02T04:00:00Z", "failed", 3000, 3);
+        run("report", "2026-03-02T06:00:00Z", "timeout", 60000, 1);
+        run("idle", "2026-03-03T00:00:00Z", "success", 10, 1);
+
+        let day = |s: &str| s.parse::<NaiveDate>().unwrap();
+        let summary = summarize(&history_root, day("2026-03-02")).unwrap().unwrap();
+        assert_eq!(summary["repo"], "my-repo");
+        assert_eq!(summary["date"], "2026-03-02");
+        assert_eq!(
+            summary["jobs"]["backup"],
+            json!({
+                "runs": 2,
+                "outcomes": { "success": 1, "failed": 1 },
+                "retried": 1,
+                "total_duration_ms": 4000,
+                "max_duration_ms": 3000,
+            })
+        );
+        assert_eq!(summary["jobs"]["report"]["outcomes"], json!({ "timeout": 1 }));
+        assert!(summary["jobs"].get("idle").is_none());
+        assert_eq!(summarize(&history_root, day("2026-02-28")).unwrap(), None);
+    }
+
+    #[test]
+    fn upload_commands() {
+        let mut config = ExportConfig {
+            store: ObjectStore::S3,
+            bucket: "audit".to_string(),
+            prefix: "rollcron".to_string(),
+            endpoint: Some("http://minio:9000".to_string()),
+        };
+        let url = config.url("repo/backup/1.json");
+        assert_eq!(
+            command(&config, "-", &url),
+            (
+                "aws",
+                ["s3", "cp", "--only-show-errors", "-", "s3://audit/rollcron/repo/backup/1.json"]
+                    .into_iter()
+                    .chain(["--endpoint-url", "http://minio:9000"])
+                    .map(String::from)
+                    .collect()
+            )
+        );
+        config.store = ObjectStore::Gcs;
+        config.endpoint = None;
+        let (program, args) = command(&config, "/tmp/1.log.gz", &config.url("repo/backup/1.log.gz"));
+        assert_eq!(program, "gcloud");
+        assert_eq!(args, ["storage", "cp", "--quiet", "/tmp/1.log.gz", "gs://audit/rollcron/repo/backup/1.log.gz"]);
+    }
+
+    #[test]
+    fn next_day_starts_at_utc_midnight() {
+        let now = "2026-03-02T23:30:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(until_next_day(now), Duration::from_secs(1800));
+    }
+}
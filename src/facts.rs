@@ -0,0 +1,324 @@
+//! Host facts: what this host is, gathered once at startup.
+//!
+//! Built-in facts (`hostname`, `os`, `arch`, `distro`, `cpus`, `memory_total`,
+//! `ips`) are merged with the `KEY=VALUE` lines printed by `--facts-script`,
+//! which override built-ins of the same name. Jobs select hosts with `when:`
+//! and see every fact as `ROLLCRON_FACT_<NAME>` in their environment.
+//!
+//! ```yaml
+//! when:
+//!   os: linux
+//!   hostname: ["db-*", "backup-?"]
+//!   memory_total: ">=8G"
+//! ```
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+static FACTS: OnceLock<Facts> = OnceLock::new();
+
+/// Fact names to values. Lists (`ips`) are comma-separated.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Facts(BTreeMap<String, String>);
+
+impl Facts {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// `ROLLCRON_FACT_<NAME>` variables for a job's command.
+    pub fn env(&self) -> Vec<(String, String)> {
+        self.iter().map(|(name, value)| (env_name(name), value.to_string())).collect()
+    }
+}
+
+impl FromIterator<(String, String)> for Facts {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Facts(iter.into_iter().collect())
+    }
+}
+
+/// Built-in facts plus those printed by `script`.
+pub fn gather(script: Option<&Path>) -> Result<Facts> {
+    let mut facts = builtin();
+    if let Some(script) = script {
+        facts.0.extend(run_script(script)?);
+    }
+    Ok(facts)
+}
+
+/// Installs the facts gathered at startup. Later calls are ignored.
+pub fn init(facts: Facts) {
+    let _ = FACTS.set(facts);
+}
+
+/// This host's facts (built-ins only, if `init` was never called).
+pub fn get() -> &'static Facts {
+    FACTS.get_or_init(builtin)
+}
+
+fn builtin() -> Facts {
+    let mut facts = BTreeMap::new();
+    facts.insert("hostname".to_string(), hostname());
+    facts.insert("os".to_string(), std::env::consts::OS.to_string());
+    facts.insert("arch".to_string(), std::env::consts::ARCH.to_string());
+    if let Ok(cpus) = std::thread::available_parallelism() {
+        facts.insert("cpus".to_string(), cpus.to_string());
+    }
+    if let Some(distro) = distro() {
+        facts.insert("distro".to_string(), distro);
+    }
+    if let Some(memory) = memory_total() {
+        facts.insert("memory_total".to_string(), memory.to_string());
+    }
+    facts.insert("ips".to_string(), ips().join(","));
+    Facts(facts)
+}
+
+/// The kernel's hostname, or `rollcron` if it can't be read.
+pub fn hostname() -> String {
+    #[cfg(unix)]
+    let host = nix::unistd::gethostname().ok().and_then(|h| h.into_string().ok());
+    #[cfg(not(unix))]
+    let host = std::env::var("COMPUTERNAME").ok();
+    host.map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "rollcron".to_string())
+}
+
+/// `ID` from os-release, e.g. `debian`
+fn distro() -> Option<String> {
+    let content = std::fs::read_to_string("/etc/os-release")
+        .or_else(|_| std::fs::read_to_string("/usr/lib/os-release"))
+        .ok()?;
+    let id = content.lines().find_map(|line| line.strip_prefix("ID="))?;
+    Some(id.trim().trim_matches(['"', '\'']).to_string()).filter(|id| !id.is_empty())
+}
+
+/// Bytes of RAM, from `/proc/meminfo`
+fn memory_total() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find_map(|line| line.strip_prefix("MemTotal:"))?;
+    let kib: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Addresses of the host's interfaces, loopback and link-local excluded
+#[cfg(unix)]
+fn ips() -> Vec<String> {
+    let Ok(addrs) = nix::ifaddrs::getifaddrs() else {
+        return vec![];
+    };
+    let mut ips: Vec<std::net::IpAddr> = addrs
+        .filter_map(|ifaddr| {
+            let addr = ifaddr.address?;
+            addr.as_sockaddr_in()
+                .map(|a| std::net::IpAddr::V4(a.ip()))
+                .or_else(|| addr.as_sockaddr_in6().map(|a| std::net::IpAddr::V6(a.ip())))
+        })
+        .filter(|ip| {
+            !ip.is_loopback()
+                && match ip {
+                    std::net::IpAddr::V4(v4) => !v4.is_link_local(),
+                    std::net::IpAddr::V6(v6) => v6.segments()[0] & 0xffc0 != 0xfe80,
+                }
+        })
+        .collect();
+    ips.sort();
+    ips.dedup();
+    ips.into_iter().map(|ip| ip.to_string()).collect()
+}
+
+#[cfg(not(unix))]
+fn ips() -> Vec<String> {
+    vec![]
+}
+
+/// Runs the facts script and parses its `KEY=VALUE` output (names are lowercased).
+fn run_script(script: &Path) -> Result<BTreeMap<String, String>> {
+    let output = std::process::Command::new(script)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .with_context(|| format!("Failed to run facts script {}", script.display()))?;
+    if !output.status.success() {
+        anyhow::bail!("Facts script {} failed: {}", script.display(), output.status);
+    }
+    parse_script_output(&String::from_utf8_lossy(&output.stdout))
+        .with_context(|| format!("Invalid output from facts script {}", script.display()))
+}
+
+fn parse_script_output(stdout: &str) -> Result<BTreeMap<String, String>> {
+    let vars = crate::env::parse_env(stdout)?;
+    vars.into_iter()
+        .map(|(name, value)| {
+            let name = name.to_ascii_lowercase();
+            validate_name(&name)?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        anyhow::bail!("Invalid fact name '{}': use letters, digits and '_'", name);
+    }
+    Ok(())
+}
+
+fn env_name(name: &str) -> String {
+    format!("ROLLCRON_FACT_{}", name.to_ascii_uppercase())
+}
+
+/// One `when:` entry: the fact must match any of the patterns.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub fact: String,
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    /// `*` and `?` wildcards, matched against the whole value
+    Glob(Regex),
+    /// `>=8G`, `<4`: the value as a number (sizes accept K/M/G)
+    Compare(Comparison, u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Ge,
+    Le,
+    Gt,
+    Lt,
+}
+
+impl Condition {
+    pub fn parse(fact: &str, patterns: &[String]) -> Result<Self> {
+        validate_name(fact)?;
+        if patterns.is_empty() {
+            anyhow::bail!("when.{}: expected a pattern or a list of patterns", fact);
+        }
+        let patterns = patterns
+            .iter()
+            .map(|p| Pattern::parse(p).with_context(|| format!("Invalid when.{} pattern '{}'", fact, p)))
+            .collect::<Result<_>>()?;
+        Ok(Condition { fact: fact.to_string(), patterns })
+    }
+
+    /// True if the fact is set and a pattern matches it (any entry, for lists).
+    pub fn matches(&self, facts: &Facts) -> bool {
+        let Some(value) = facts.get(&self.fact) else {
+            return false;
+        };
+        let values: Vec<&str> = if self.fact == "ips" { value.split(',').collect() } else { vec![value] };
+        values.iter().any(|v| self.patterns.iter().any(|p| p.matches(v)))
+    }
+}
+
+impl Pattern {
+    fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let comparison = [(">=", Comparison::Ge), ("<=", Comparison::Le), (">", Comparison::Gt), ("<", Comparison::Lt)]
+            .into_iter()
+            .find_map(|(op, cmp)| s.strip_prefix(op).map(|rest| (cmp, rest)));
+        if let Some((cmp, number)) = comparison {
+            return Ok(Pattern::Compare(cmp, crate::config::parse_size(number)?));
+        }
+        let regex = s
+            .chars()
+            .map(|c| match c {
+                '*' => ".*".to_string(),
+                '?' => ".".to_string(),
+                c => regex::escape(&c.to_string()),
+            })
+            .collect::<String>();
+        Ok(Pattern::Glob(Regex::new(&format!("^{}$", regex))?))
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Pattern::Glob(regex) => regex.is_match(value),
+            Pattern::Compare(cmp, bound) => value.parse::<u64>().is_ok_and(|v| match cmp {
+                Comparison::Ge => v >= *bound,
+                Comparison::Le => v <= *bound,
+                Comparison::Gt => v > *bound,
+                Comparison::Lt => v < *bound,
+            }),
+        }
+    }
+}
+
+/// The first condition `facts` fail, described for logs.
+pub fn mismatch(when: &[Condition], facts: &Facts) -> Option<String> {
+    when.iter().find(|c| !c.matches(facts)).map(|c| match facts.get(&c.fact) {
+        Some(value) => format!("fact {}={} does not match when.{}", c.fact, value, c.fact),
+        None => format!("fact {} is not set", c.fact),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facts(pairs: &[(&str, &str)]) -> Facts {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn when(fact: &str, patterns: &[&str]) -> Condition {
+        Condition::parse(fact, &patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn builtin_facts_describe_this_host() {
+        let facts = builtin();
+        assert_eq!(facts.get("os"), Some(std::env::consts::OS));
+        assert_eq!(facts.get("arch"), Some(std::env::consts::ARCH));
+        assert!(!facts.get("hostname").unwrap().is_empty());
+        assert!(facts.get("ips").is_some_and(|ips| !ips.contains("127.0.0.1")));
+        if cfg!(target_os = "linux") {
+            assert!(facts.get("memory_total").unwrap().parse::<u64>().unwrap() > 0);
+        }
+    }
+
+    #[test]
+    fn script_output_becomes_facts() {
+        let parsed = parse_script_output("# from the CMDB\nRACK=b12\nrole=\"db primary\"\n").unwrap();
+        assert_eq!(parsed["rack"], "b12");
+        assert_eq!(parsed["role"], "db primary");
+        assert!(parse_script_output("bad-name=1\n").is_err());
+
+        let facts = facts(&[("rack", "b12")]);
+        assert_eq!(facts.env(), vec![("ROLLCRON_FACT_RACK".to_string(), "b12".to_string())]);
+    }
+
+    #[test]
+    fn conditions_match_globs_lists_and_numbers() {
+        let host = facts(&[("hostname", "db-2"), ("memory_total", "17179869184"), ("ips", "10.0.0.5,192.168.1.9")]);
+        assert!(when("hostname", &["web-*", "db-?"]).matches(&host));
+        assert!(!when("hostname", &["db"]).matches(&host));
+        assert!(when("memory_total", &[">=8G"]).matches(&host));
+        assert!(!when("memory_total", &[">16G"]).matches(&host));
+        assert!(when("ips", &["192.168.1.*"]).matches(&host));
+        assert!(!when("rack", &["*"]).matches(&host));
+
+        let conditions = [when("hostname", &["db-*"]), when("memory_total", &["<4G"])];
+        assert_eq!(
+            mismatch(&conditions, &host).unwrap(),
+            "fact memory_total=17179869184 does not match when.memory_total"
+        );
+        assert!(Condition::parse("memory_total", &[">=lots".to_string()]).is_err());
+        assert!(Condition::parse("os", &[]).is_err());
+    }
+}
@@ -1,6 +1,68 @@
+use crate::signers::Signer;
 use anyhow::{Context, Result};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// Variables from `--git-env`, applied to git subprocesses only
+static GIT_ENV: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// `--state-dir`
+static STATE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// `--cache-dir`
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// `--clone-depth`: history kept by clones and fetches of the SoT
+static CLONE_DEPTH: OnceLock<u32> = OnceLock::new();
+
+/// Sets the environment for git subprocesses (not inherited by jobs). Later calls are ignored.
+pub fn init_env(vars: Vec<(String, String)>) {
+    let _ = GIT_ENV.set(vars);
+}
+
+/// Sets the state root from `--state-dir`. Later calls are ignored.
+pub fn init_state_dir(dir: PathBuf) {
+    let _ = STATE_DIR.set(dir);
+}
+
+/// Sets the cache root from `--cache-dir`. Later calls are ignored.
+pub fn init_cache_dir(dir: PathBuf) {
+    let _ = CACHE_DIR.set(dir);
+}
+
+/// Sets `--clone-depth`. Later calls are ignored.
+pub fn init_clone_depth(depth: u32) {
+    let _ = CLONE_DEPTH.set(depth);
+}
+
+/// A git command that fails instead of prompting: no terminal prompts, no
+/// askpass programs, and (on Unix) no controlling terminal for ssh to read from.
+/// `--git-env` may supply an askpass helper, but never re-enables terminal prompts.
+fn git_command() -> Command {
+    let mut cmd = Command::new("git");
+    cmd.stdin(Stdio::null())
+        .env("GIT_ASKPASS", "")
+        .env("SSH_ASKPASS", "")
+        .env("SSH_ASKPASS_REQUIRE", "never")
+        .env("GCM_INTERACTIVE", "never");
+    for (key, value) in GIT_ENV.get().into_iter().flatten() {
+        cmd.env(key, value);
+    }
+    cmd.env("GIT_TERMINAL_PROMPT", "0");
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // SAFETY: setsid is async-signal-safe and touches no state of this process
+        unsafe {
+            cmd.pre_exec(|| {
+                let _ = nix::unistd::setsid();
+                Ok(())
+            });
+        }
+    }
+    cmd
+}
 
 /// RAII guard that removes a directory on drop unless disarmed.
 struct TempDirGuard<'a> {
@@ -26,21 +88,70 @@ impl Drop for TempDirGuard<'_> {
     }
 }
 
-/// Generates a cache directory path with random suffix.
-pub fn generate_cache_path(source: &str) -> PathBuf {
-    let cache_base = dirs::cache_dir()
-        .unwrap_or_else(|| PathBuf::from("/tmp"))
-        .join("rollcron");
+/// Returns the rollcron cache root: `--cache-dir`, else ~/.cache/rollcron/
+pub fn cache_base() -> PathBuf {
+    if let Some(dir) = CACHE_DIR.get() {
+        return dir.clone();
+    }
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rollcron")
+}
+
+/// Returns the state root: `--state-dir`, else ~/.local/state/rollcron/
+/// (`$XDG_STATE_HOME`; the local data dir where there is no state dir).
+/// Unlike the cache, deleting it loses run history and queued notifications.
+pub fn state_base() -> PathBuf {
+    if let Some(dir) = STATE_DIR.get() {
+        return dir.clone();
+    }
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rollcron")
+}
 
-    let repo_name = source
-        .trim_end_matches('/')
+/// Extracts the repository name from a local path or remote URL.
+pub fn repo_name(source: &str) -> &str {
+    source
+        .trim_end_matches(['/', '\\'])
         .trim_end_matches(".git")
-        .rsplit('/')
+        .rsplit(['/', '\\', ':'])
         .next()
-        .unwrap_or("repo");
+        .unwrap_or("repo")
+}
+
+/// Resolves the REPO argument: expands shell variables (~, $VAR) and
+/// canonicalizes local paths. Remote URLs are returned as-is.
+pub fn resolve_source(repo: &str) -> Result<String> {
+    let expanded = crate::env::expand_string(repo);
+    if let Some(path) = expanded.strip_prefix(crate::source::DIR_SCHEME) {
+        let canonical = PathBuf::from(path).canonicalize().with_context(|| format!("Failed to resolve {}", path))?;
+        let canonical = strip_verbatim_prefix(canonical.to_str().context("Path contains invalid UTF-8")?);
+        return Ok(format!("{}{}", crate::source::DIR_SCHEME, canonical));
+    }
+    if Path::new(&expanded).is_absolute() || expanded.starts_with('.') {
+        let canonical = PathBuf::from(&expanded).canonicalize()?;
+        Ok(strip_verbatim_prefix(
+            canonical.to_str().context("Path contains invalid UTF-8")?,
+        ))
+    } else {
+        Ok(expanded)
+    }
+}
 
+/// Windows `canonicalize` returns `\\?\C:\...` paths, which git does not accept.
+fn strip_verbatim_prefix(path: &str) -> String {
+    match path.strip_prefix(r"\\?\") {
+        Some(rest) if !rest.starts_with("UNC\\") => rest.to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// Generates a cache directory path with random suffix.
+pub fn generate_cache_path(source: &str) -> PathBuf {
     let random_suffix = generate_random_suffix();
-    cache_base.join(format!("{}-{}", repo_name, random_suffix))
+    cache_base().join(format!("{}-{}", repo_name(source), random_suffix))
 }
 
 /// Clones repo to specified cache path.
@@ -48,16 +159,23 @@ pub fn clone_to(source: &str, cache_dir: &Path) -> Result<()> {
     if let Some(parent) = cache_dir.parent() {
         std::fs::create_dir_all(parent)?;
     }
-    clone_repo(source, cache_dir)
+    clone_repo(source, cache_dir, CLONE_DEPTH.get().copied())
 }
 
-fn clone_repo(source: &str, dest: &Path) -> Result<()> {
+fn clone_repo(source: &str, dest: &Path, depth: Option<u32>) -> Result<()> {
     let dest_str = dest
         .to_str()
         .context("Destination path contains invalid UTF-8")?;
-    let output = Command::new("git")
-        .args(["clone", source, dest_str])
-        .output()?;
+    let mut cmd = git_command();
+    cmd.arg("clone");
+    let source = match depth {
+        Some(depth) => {
+            cmd.args(["--depth", &depth.to_string()]);
+            shallow_source(source)
+        }
+        None => source.to_string(),
+    };
+    let output = cmd.args([source.as_str(), dest_str]).output()?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -67,10 +185,128 @@ fn clone_repo(source: &str, dest: &Path) -> Result<()> {
     Ok(())
 }
 
+/// git ignores `--depth` for plain local paths, but not for `file://` URLs.
+fn shallow_source(source: &str) -> String {
+    if !Path::new(source).is_absolute() || source.contains("://") {
+        return source.to_string();
+    }
+    let path = source.replace('\\', "/");
+    match path.starts_with('/') {
+        true => format!("file://{}", path),
+        false => format!("file:///{}", path),
+    }
+}
+
+/// `git fetch` for the SoT: drops remote branches deleted upstream, and stays
+/// shallow under `--clone-depth`.
+fn fetch_args(depth: Option<u32>) -> Vec<String> {
+    let mut args = vec!["fetch".to_string(), "--prune".to_string()];
+    if let Some(depth) = depth {
+        args.push(format!("--depth={}", depth));
+    }
+    args
+}
+
+/// Re-clones `dest` from its `origin` when git can no longer read it (missing
+/// or corrupt objects, refs or index), so a damaged cache doesn't fail every
+/// sync. Returns whether it re-cloned. With `signers`, the new HEAD must be
+/// signed by one of them before it replaces `dest`.
+pub fn ensure_repo(dest: &Path, signers: Option<&[Signer]>) -> Result<bool> {
+    let resolves = |rev: &str| {
+        git_command()
+            .args(["rev-parse", "--verify", "--quiet", rev])
+            .current_dir(dest)
+            .output()
+            .is_ok_and(|o| o.status.success())
+    };
+    // HEAD names a commit git can't read; an unborn HEAD (empty repo) is fine
+    let healthy = dest.join(".git").is_dir() && (!resolves("HEAD") || resolves("HEAD^{tree}"));
+    if healthy {
+        return Ok(false);
+    }
+    reclone(dest, signers)?;
+    Ok(true)
+}
+
+fn reclone(dest: &Path, signers: Option<&[Signer]>) -> Result<()> {
+    use tracing::warn;
+
+    let config = dest.join(".git").join("config");
+    let origin = git_command()
+        .args(["config", "--file"])
+        .arg(&config)
+        .args(["remote.origin.url"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .with_context(|| format!("Repository at {} is corrupt and has no origin to re-clone from", dest.display()))?;
+    warn!(path = %dest.display(), origin = %origin, "Repository is corrupt, re-cloning");
+
+    let fresh = dest.with_extension("reclone");
+    let _ = std::fs::remove_dir_all(&fresh);
+    let mut guard = TempDirGuard::new(&fresh);
+    clone_repo(&origin, &fresh, CLONE_DEPTH.get().copied())?;
+    if let Some(signers) = signers {
+        verify_commit(&fresh, "HEAD", signers)?;
+    }
+    std::fs::remove_dir_all(dest).with_context(|| format!("Failed to remove {}", dest.display()))?;
+    std::fs::rename(&fresh, dest)?;
+    guard.disarm();
+
+    // Job worktrees were registered in the old repository
+    prune_build_dirs(dest);
+    Ok(())
+}
+
+/// Removes the build worktree of every job of this SoT; the next sync adds it again.
+fn prune_build_dirs(sot_path: &Path) {
+    let Some(sot_name) = sot_path.file_name().and_then(|s| s.to_str()) else {
+        return;
+    };
+    let prefix = format!("{}@", sot_name);
+    let Ok(entries) = std::fs::read_dir(cache_base()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_str().is_some_and(|name| name.starts_with(&prefix)) {
+            let _ = std::fs::remove_dir_all(entry.path().join("build"));
+        }
+    }
+}
+
+/// Whether git failed because the repository itself is damaged.
+fn is_corrupt(stderr: &str) -> bool {
+    ["not a git repository", "corrupt", "bad object", "missing object", "unable to read", "broken link"]
+        .iter()
+        .any(|marker| stderr.contains(marker))
+}
+
+/// Returns the commit checked out in `dir`, if any.
+pub fn head_commit(dir: &Path) -> Option<String> {
+    git_command()
+        .args(["rev-parse", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+}
+
 /// Syncs an existing repo. Returns commit range (e.g. "abc123..def456") if new commits were fetched.
-pub fn sync_repo(dest: &Path) -> Result<Option<String>> {
+/// Nothing is fetched while `git ls-remote` reports the upstream branch unchanged.
+/// With `signers`, a fetched commit not signed by one of them is refused and HEAD stays put.
+/// A corrupt repo is re-cloned (see [`ensure_repo`]). When upstream history was rewritten
+/// (force push), HEAD follows it only with `allow_force_reset`; the range is then `old...new (forced)`.
+pub fn sync_repo(dest: &Path, signers: Option<&[Signer]>, allow_force_reset: bool) -> Result<Option<String>> {
+    // Get current HEAD before fetch
+    let old_head = head_commit(dest);
+    if ensure_repo(dest, signers)? {
+        return Ok(changed(old_head, head_commit(dest)));
+    }
+
     // git clone sets up tracking branches for both local and remote repos
-    let has_upstream = Command::new("git")
+    let has_upstream = git_command()
         .args(["rev-parse", "--abbrev-ref", "@{upstream}"])
         .current_dir(dest)
         .env("LC_ALL", "C") // Ensure consistent English output
@@ -79,29 +315,51 @@ pub fn sync_repo(dest: &Path) -> Result<Option<String>> {
         .unwrap_or(false);
 
     if has_upstream {
-        // Get current HEAD before fetch
-        let old_head = Command::new("git")
-            .args(["rev-parse", "HEAD"])
-            .current_dir(dest)
-            .output()
-            .ok()
-            .filter(|o| o.status.success())
-            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+        // Asking for one ref is much cheaper than a fetch, so short pull intervals stay cheap
+        let tracking = rev_parse(dest, "@{upstream}");
+        if tracking.is_some() && remote_head(dest) == tracking && old_head == tracking {
+            return Ok(None);
+        }
 
         // Fetch latest from remote
-        let fetch = Command::new("git")
-            .args(["fetch"])
+        let fetch = git_command()
+            .args(fetch_args(CLONE_DEPTH.get().copied()))
             .current_dir(dest)
             .env("LC_ALL", "C")
             .output()?;
 
         if !fetch.status.success() {
             let stderr = String::from_utf8_lossy(&fetch.stderr);
+            if is_corrupt(&stderr) {
+                reclone(dest, signers)?;
+                return Ok(changed(old_head, head_commit(dest)));
+            }
             anyhow::bail!("git fetch failed: {}", stderr);
         }
 
+        if let Some(signers) = signers {
+            verify_commit(dest, "@{upstream}", signers)?;
+        }
+
+        let upstream = rev_parse(dest, "@{upstream}");
+        let forced = match (&old_head, &upstream) {
+            (Some(old), Some(new)) => diverged(dest, old, new),
+            _ => false,
+        };
+        if forced {
+            let (old, new) = (old_head.as_deref().unwrap_or_default(), upstream.as_deref().unwrap_or_default());
+            if !allow_force_reset {
+                anyhow::bail!(
+                    "Upstream history was rewritten ({} is not an ancestor of {}); set runner.allow_force_reset to follow it",
+                    short_sha(old),
+                    short_sha(new)
+                );
+            }
+            tracing::warn!(old = %old, new = %new, "Upstream history was rewritten (force push), resetting to it");
+        }
+
         // Reset to upstream (handles diverged history)
-        let reset = Command::new("git")
+        let reset = git_command()
             .args(["reset", "--hard", "@{upstream}"])
             .current_dir(dest)
             .env("LC_ALL", "C")
@@ -109,31 +367,241 @@ pub fn sync_repo(dest: &Path) -> Result<Option<String>> {
 
         if !reset.status.success() {
             let stderr = String::from_utf8_lossy(&reset.stderr);
+            if is_corrupt(&stderr) {
+                reclone(dest, signers)?;
+                return Ok(changed(old_head, head_commit(dest)));
+            }
             anyhow::bail!("git reset failed: {}", stderr);
         }
 
-        // Get new HEAD after reset
-        let new_head = Command::new("git")
-            .args(["rev-parse", "HEAD"])
-            .current_dir(dest)
+        if forced {
+            let (old, new) = (old_head.unwrap_or_default(), upstream.unwrap_or_default());
+            return Ok(Some(format!("{}...{} (forced)", short_sha(&old), short_sha(&new))));
+        }
+        // Compare old and new HEAD to detect changes
+        return Ok(changed(old_head, head_commit(dest)));
+    }
+
+    Ok(None)
+}
+
+/// Creates an empty cache repo for a non-git source, remembering the source
+/// in its config (`rollcron.source`).
+pub fn init_snapshot_repo(source: &str, dest: &Path) -> Result<()> {
+    std::fs::create_dir_all(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+    git_in(dest, &["init", "--quiet"])?;
+    set_repo_config(dest, "rollcron.source", source)
+}
+
+/// Reads a key from the repo's own config; unset and empty are both `None`.
+pub fn repo_config(dir: &Path, key: &str) -> Option<String> {
+    git_command()
+        .args(["config", "--local", "--get", key])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+pub fn set_repo_config(dir: &Path, key: &str, value: &str) -> Result<()> {
+    git_in(dir, &["config", "--local", key, value])
+}
+
+/// Commits the contents of `tree` to the snapshot repo `dest` and checks it
+/// out. Returns the commit range, `None` if nothing changed. Files ignored by
+/// a `.gitignore` in `tree` are left out, as they would be from a git source.
+pub fn commit_snapshot(dest: &Path, tree: &Path, message: &str) -> Result<Option<String>> {
+    let old_head = head_commit(dest);
+    let git_dir = dest.join(".git");
+    let snapshot = |args: &[&str]| {
+        git_command()
+            .arg("--git-dir")
+            .arg(&git_dir)
+            .arg("--work-tree")
+            .arg(tree)
+            .args(["-c", "user.name=rollcron", "-c", "user.email=rollcron@localhost"])
+            .args(args)
+            .env("LC_ALL", "C")
             .output()
-            .ok()
-            .filter(|o| o.status.success())
-            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+    };
 
-        // Compare old and new HEAD to detect changes
-        match (old_head, new_head) {
-            (Some(old), Some(new)) if old == new => return Ok(None),
-            (Some(old), Some(new)) => {
-                let short_old = &old[..7.min(old.len())];
-                let short_new = &new[..7.min(new.len())];
-                return Ok(Some(format!("{}..{}", short_old, short_new)));
-            }
-            _ => return Ok(Some("updated".to_string())),
+    let add = snapshot(&["add", "--all"])?;
+    if !add.status.success() {
+        anyhow::bail!("git add failed: {}", String::from_utf8_lossy(&add.stderr).trim());
+    }
+    let unchanged = snapshot(&["diff", "--cached", "--quiet"])?.status.success();
+    if unchanged && old_head.is_some() {
+        return Ok(None);
+    }
+    let commit = snapshot(&["commit", "--quiet", "--allow-empty", "--no-verify", "-m", message])?;
+    if !commit.status.success() {
+        anyhow::bail!("git commit failed: {}", String::from_utf8_lossy(&commit.stderr).trim());
+    }
+
+    // The index already matches; bring the cache's own files in line with it
+    git_in(dest, &["reset", "--quiet", "--hard"])?;
+    git_in(dest, &["clean", "--quiet", "-ffdx"])?;
+    Ok(changed(old_head, head_commit(dest)))
+}
+
+/// Notes ref operator actions are recorded under (`runner.changelog`)
+pub const CHANGELOG_REF: &str = "refs/notes/rollcron";
+
+/// Appends `line` to the changelog note of the checked-out commit and pushes
+/// the notes to `origin`. Notes pushed by other instances are merged first,
+/// keeping every line. A source without `origin` keeps them in the cache.
+pub fn record_changelog(dest: &Path, line: &str) -> Result<()> {
+    // Concurrent appends would race on the notes ref
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let has_origin = repo_config(dest, "remote.origin.url").is_some();
+    let remote_ref = format!("{}-remote", CHANGELOG_REF);
+    if has_origin {
+        // Fails while nobody has pushed notes yet
+        let fetched = git_command()
+            .args(["fetch", "--quiet", "origin", &format!("+{}:{}", CHANGELOG_REF, remote_ref)])
+            .current_dir(dest)
+            .output()
+            .is_ok_and(|o| o.status.success());
+        if fetched {
+            git_in(dest, &["notes", "--ref", CHANGELOG_REF, "merge", "--quiet", "--strategy", "cat_sort_uniq", &remote_ref])?;
         }
     }
+    git_in(
+        dest,
+        &["-c", "user.name=rollcron", "-c", "user.email=rollcron@localhost", "notes", "--ref", CHANGELOG_REF, "append", "-m", line, "HEAD"],
+    )?;
+    if has_origin {
+        git_in(dest, &["push", "--quiet", "origin", CHANGELOG_REF])?;
+    }
+    Ok(())
+}
 
-    Ok(None)
+/// The commit the upstream branch points to on the remote, per `git ls-remote`.
+/// `None` if it can't be determined (the fetch then reports why).
+fn remote_head(dest: &Path) -> Option<String> {
+    let branch = git_command()
+        .args(["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .current_dir(dest)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())?;
+    let remote = repo_config(dest, &format!("branch.{}.remote", branch))?;
+    let merge = repo_config(dest, &format!("branch.{}.merge", branch))?;
+    let out = git_command()
+        .args(["ls-remote", "--", &remote, &merge])
+        .current_dir(dest)
+        .env("LC_ALL", "C")
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let line = stdout.lines().find(|l| l.split('\t').nth(1) == Some(merge.as_str()))?;
+    line.split('\t').next().map(str::to_string)
+}
+
+/// Commit range between two HEADs, `None` if they are the same.
+fn changed(old_head: Option<String>, new_head: Option<String>) -> Option<String> {
+    match (old_head, new_head) {
+        (Some(old), Some(new)) if old == new => None,
+        (Some(old), Some(new)) => Some(format!("{}..{}", short_sha(&old), short_sha(&new))),
+        _ => Some("updated".to_string()),
+    }
+}
+
+fn rev_parse(dir: &Path, rev: &str) -> Option<String> {
+    git_command()
+        .args(["rev-parse", "--verify", "--quiet", rev])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+}
+
+/// Whether `new` does not build on `old`, i.e. upstream was force-pushed. A
+/// shallow clone lacks the history to tell, so it never counts as diverged.
+fn diverged(dir: &Path, old: &str, new: &str) -> bool {
+    let shallow = git_command()
+        .args(["rev-parse", "--is-shallow-repository"])
+        .current_dir(dir)
+        .output()
+        .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).trim() == "true");
+    if shallow || old == new {
+        return false;
+    }
+    // Exit code 1 means "not an ancestor"; anything else is an error
+    git_command()
+        .args(["merge-base", "--is-ancestor", old, new])
+        .current_dir(dir)
+        .status()
+        .is_ok_and(|status| status.code() == Some(1))
+}
+
+/// Fails unless `rev` carries a good signature by one of `signers`. SSH keys
+/// are checked by git against an allowed-signers file holding only them; GPG
+/// keys must be in the keyring, and their fingerprint (or that of their
+/// primary key) must match.
+pub fn verify_commit(dir: &Path, rev: &str, signers: &[Signer]) -> Result<()> {
+    let allowed_file = dir.join(".git").join("rollcron-allowed-signers");
+    let ssh_keys: String = signers
+        .iter()
+        .filter_map(|s| match s {
+            Signer::Ssh(key) => Some(format!("* namespaces=\"git\" {}\n", key)),
+            Signer::Gpg(_) => None,
+        })
+        .collect();
+    std::fs::write(&allowed_file, ssh_keys).context("Failed to write allowed signers file")?;
+
+    let output = git_command()
+        .arg("-c")
+        .arg(format!("gpg.ssh.allowedSignersFile={}", allowed_file.display()))
+        .args(["log", "-1", "--format=%H%n%G?%n%GF%n%GP", rev, "--"])
+        .current_dir(dir)
+        .env("LC_ALL", "C")
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git log failed: {}", stderr);
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let commit = lines.next().unwrap_or_default();
+    let short = &commit[..7.min(commit.len())];
+    let status = lines.next().unwrap_or("N");
+    let fingerprint = lines.next().unwrap_or_default();
+    let primary = lines.next().unwrap_or_default();
+    if signature_trusted(status, fingerprint, primary, signers) {
+        return Ok(());
+    }
+    let reason = match status {
+        "N" => "it is not signed".to_string(),
+        "B" => "its signature is bad".to_string(),
+        "E" => "its signature cannot be checked (key missing from the keyring?)".to_string(),
+        "X" | "Y" => "its signature or key has expired".to_string(),
+        "R" => "it is signed by a revoked key".to_string(),
+        _ => format!("key {} is not in --allowed-signers", fingerprint),
+    };
+    anyhow::bail!("Refusing commit {}: {}", short, reason)
+}
+
+/// `%G?` status `G` (or `U` for GPG, whose trust rollcron decides itself) by an allowed key.
+fn signature_trusted(status: &str, fingerprint: &str, primary: &str, signers: &[Signer]) -> bool {
+    if fingerprint.starts_with("SHA256:") {
+        // git only reports G if the key is in the allowed signers file
+        return status == "G";
+    }
+    matches!(status, "G" | "U")
+        && signers.iter().any(|s| match s {
+            Signer::Gpg(id) => [fingerprint, primary]
+                .iter()
+                .any(|fpr| !fpr.is_empty() && fpr.to_ascii_uppercase().ends_with(id.as_str())),
+            Signer::Ssh(_) => false,
+        })
 }
 
 fn generate_random_suffix() -> String {
@@ -149,125 +617,120 @@ fn generate_random_suffix() -> String {
 
 /// Returns the base job directory: ~/.cache/rollcron/<repo>@<job-id>/
 pub fn get_job_dir(sot_path: &Path, job_id: &str) -> PathBuf {
-    let cache_base = dirs::cache_dir()
-        .unwrap_or_else(|| PathBuf::from("/tmp"))
-        .join("rollcron");
-
     let sot_name = sot_path
         .file_name()
         .and_then(|s| s.to_str())
         .unwrap_or("unknown");
 
-    cache_base.join(format!("{}@{}", sot_name, job_id))
+    cache_base().join(format!("{}@{}", sot_name, job_id))
 }
 
-/// Returns the build directory: ~/.cache/rollcron/<repo>@<job-id>/build/
-pub fn get_build_dir(sot_path: &Path, job_id: &str) -> PathBuf {
-    get_job_dir(sot_path, job_id).join("build")
+/// Returns the run history directory: ~/.local/state/rollcron/history/<repo>/<job-id>/
+/// Keyed by repo name without the random suffix, so it survives restarts.
+pub fn get_history_dir(sot_path: &Path, job_id: &str) -> PathBuf {
+    get_history_root(sot_path).join(job_id)
 }
 
-/// Returns the run directory: ~/.cache/rollcron/<repo>@<job-id>/run/
-pub fn get_run_dir(sot_path: &Path, job_id: &str) -> PathBuf {
-    get_job_dir(sot_path, job_id).join("run")
+/// Returns the parent of every job's history directory: ~/.local/state/rollcron/history/<repo>/
+pub fn get_history_root(sot_path: &Path) -> PathBuf {
+    stable_dir(sot_path, "history")
 }
 
-#[allow(dead_code)]
-#[deprecated(note = "Use sync_to_build_dir and copy_build_to_run instead")]
-pub fn sync_to_job_dir(sot_path: &Path, job_dir: &Path) -> Result<()> {
-    let job_dir_str = job_dir
-        .to_str()
-        .context("Job directory path contains invalid UTF-8")?;
-
-    // Use atomic temp directory to avoid TOCTOU race condition
-    let temp_dir = job_dir.with_extension("tmp");
-    let temp_dir_str = temp_dir
-        .to_str()
-        .context("Temp directory path contains invalid UTF-8")?;
-
-    // Clean up any leftover temp directory
-    if temp_dir.exists() {
-        std::fs::remove_dir_all(&temp_dir)?;
-    }
-    std::fs::create_dir_all(&temp_dir)?;
+/// Returns the notification outbox: ~/.local/state/rollcron/outbox/<repo>/
+pub fn get_outbox_dir(sot_path: &Path) -> PathBuf {
+    stable_dir(sot_path, "outbox")
+}
 
-    // RAII guard ensures temp_dir is cleaned up on any error path
-    let mut temp_guard = TempDirGuard::new(&temp_dir);
+/// Returns the queue of runs to export: ~/.local/state/rollcron/export/<repo>/
+pub fn get_export_dir(sot_path: &Path) -> PathBuf {
+    stable_dir(sot_path, "export")
+}
 
-    // Use git archive for all repos (both local and remote use git clone now)
-    let archive = Command::new("git")
-        .args(["archive", "HEAD"])
-        .current_dir(sot_path)
-        .output()?;
+/// Returns the directory relative `log.file` paths resolve against:
+/// ~/.local/state/rollcron/logs/<repo>/<job-id>/
+pub fn get_log_dir(sot_path: &Path, job_id: &str) -> PathBuf {
+    stable_dir(sot_path, "logs").join(job_id)
+}
 
-    if !archive.status.success() {
-        // temp_guard will clean up on drop
-        let stderr = String::from_utf8_lossy(&archive.stderr);
-        anyhow::bail!("git archive failed: {}", stderr);
-    }
+/// Kinds of per-repo state that used to live in the cache
+const STATE_KINDS: [&str; 2] = ["history", "outbox"];
+
+/// `<state>/<kind>/<repo>`: per-repo state that survives restarts. A SoT
+/// outside the cache (tests, tools) keeps its state next to it instead.
+fn stable_dir(sot_path: &Path, kind: &str) -> PathBuf {
+    let root = match sot_path.parent() {
+        Some(parent) if parent != cache_base() => parent.to_path_buf(),
+        _ => state_base(),
+    };
+    root.join(kind).join(stable_repo_name(sot_path))
+}
 
-    // Extract (git archive output uses relative paths, tar strips leading '/' by default)
-    let mut extract = Command::new("tar")
-        .args(["-x"])
-        .current_dir(&temp_dir)
-        .stdin(std::process::Stdio::piped())
-        .spawn()?;
+/// SoT directory name without its random suffix
+fn stable_repo_name(sot_path: &Path) -> &str {
+    let sot_name = sot_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown");
+    sot_name.rsplit_once('-').map_or(sot_name, |(repo, _)| repo)
+}
 
-    {
-        use std::io::Write;
-        let stdin = extract
-            .stdin
-            .as_mut()
-            .context("Failed to open tar stdin")?;
-        stdin.write_all(&archive.stdout)?;
-    }
+/// Moves history and outbox that older versions kept under the cache root
+/// into the state dir, unless the state dir already has them.
+pub fn migrate_state(sot_path: &Path) {
+    use tracing::{info, warn};
 
-    let status = extract.wait()?;
-    if !status.success() {
-        // temp_guard will clean up on drop
-        anyhow::bail!("tar extraction failed with exit code: {:?}", status.code());
+    let repo = stable_repo_name(sot_path);
+    for kind in STATE_KINDS {
+        let legacy = cache_base().join(kind).join(repo);
+        let dir = stable_dir(sot_path, kind);
+        match move_dir(&legacy, &dir) {
+            Ok(true) => info!(from = %legacy.display(), to = %dir.display(), "Moved state out of the cache"),
+            Ok(false) => {}
+            Err(e) => warn!(from = %legacy.display(), error = %e, "Failed to move state out of the cache, starting empty"),
+        }
     }
+}
 
-    // Disarm the guard before rename - we'll handle cleanup manually from here
-    temp_guard.disarm();
-
-    // Safe swap: rename old to backup, rename temp to target, then remove backup.
-    // This minimizes the window where job_dir doesn't exist.
-    let backup_dir = job_dir.with_extension("old");
-
-    // Clean up any leftover backup directory
-    if backup_dir.exists() {
-        let _ = std::fs::remove_dir_all(&backup_dir);
+/// Renames `from` to `to` if only `from` exists.
+fn move_dir(from: &Path, to: &Path) -> std::io::Result<bool> {
+    if from == to || !from.is_dir() || to.exists() {
+        return Ok(false);
     }
-
-    if job_dir.exists() {
-        // Move existing to backup first
-        std::fs::rename(job_dir, &backup_dir).with_context(|| {
-            format!("Failed to rename {} to backup", job_dir_str)
-        })?;
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
     }
+    std::fs::rename(from, to)?;
+    Ok(true)
+}
 
-    // Move new directory into place
-    std::fs::rename(&temp_dir, job_dir).with_context(|| {
-        format!("Failed to rename {} to {}", temp_dir_str, job_dir_str)
-    })?;
+/// Returns the run history directory for a REPO argument (for offline CLI commands).
+/// Falls back to the pre-state-dir location until a daemon has moved it.
+pub fn get_history_dir_for_source(source: &str, job_id: &str) -> PathBuf {
+    let dir = state_base().join("history").join(repo_name(source)).join(job_id);
+    let legacy = cache_base().join("history").join(repo_name(source)).join(job_id);
+    if !dir.exists() && legacy.exists() { legacy } else { dir }
+}
 
-    // Remove backup (ignore errors as it's cleanup)
-    if backup_dir.exists() {
-        let _ = std::fs::remove_dir_all(&backup_dir);
-    }
+/// Returns the build directory: ~/.cache/rollcron/<repo>@<job-id>/build/
+pub fn get_build_dir(sot_path: &Path, job_id: &str) -> PathBuf {
+    get_job_dir(sot_path, job_id).join("build")
+}
 
-    Ok(())
+/// Returns the run directory: ~/.cache/rollcron/<repo>@<job-id>/run/
+pub fn get_run_dir(sot_path: &Path, job_id: &str) -> PathBuf {
+    get_job_dir(sot_path, job_id).join("run")
 }
 
 /// Syncs the build directory using git worktree.
 /// First run: `git worktree add --detach <build_dir>`
 /// Subsequent: `git -C <build_dir> fetch && git -C <build_dir> reset --hard @{upstream}`
 /// Gitignored files (build cache) are preserved.
-pub fn sync_to_build_dir(sot_path: &Path, build_dir: &Path) -> Result<()> {
+/// `submodules` and `lfs` then populate submodules and LFS objects in the worktree.
+pub fn sync_to_build_dir(sot_path: &Path, build_dir: &Path, submodules: bool, lfs: bool) -> Result<()> {
     if build_dir.join(".git").exists() {
         // Worktree already exists - update it
-        let fetch = Command::new("git")
-            .args(["fetch", "--all"])
+        let fetch = git_command()
+            .args(["fetch", "--all", "--prune"])
             .current_dir(build_dir)
             .env("LC_ALL", "C")
             .output()?;
@@ -278,7 +741,7 @@ pub fn sync_to_build_dir(sot_path: &Path, build_dir: &Path) -> Result<()> {
         }
 
         // Get the upstream ref from the main repo
-        let upstream_ref = Command::new("git")
+        let upstream_ref = git_command()
             .args(["rev-parse", "HEAD"])
             .current_dir(sot_path)
             .output()?;
@@ -289,7 +752,7 @@ pub fn sync_to_build_dir(sot_path: &Path, build_dir: &Path) -> Result<()> {
 
         let commit = String::from_utf8_lossy(&upstream_ref.stdout).trim().to_string();
 
-        let reset = Command::new("git")
+        let reset = git_command()
             .args(["reset", "--hard", &commit])
             .current_dir(build_dir)
             .env("LC_ALL", "C")
@@ -309,7 +772,7 @@ pub fn sync_to_build_dir(sot_path: &Path, build_dir: &Path) -> Result<()> {
             .to_str()
             .context("Build directory path contains invalid UTF-8")?;
 
-        let worktree = Command::new("git")
+        let worktree = git_command()
             .args(["worktree", "add", "--detach", build_dir_str])
             .current_dir(sot_path)
             .env("LC_ALL", "C")
@@ -321,6 +784,35 @@ pub fn sync_to_build_dir(sot_path: &Path, build_dir: &Path) -> Result<()> {
         }
     }
 
+    if submodules {
+        // URLs may have changed in .gitmodules since the last sync
+        git_in(build_dir, &["submodule", "sync", "--quiet", "--recursive"])?;
+        git_in(build_dir, &["submodule", "update", "--init", "--recursive", "--force"])?;
+    }
+    if lfs {
+        if !git_command().args(["lfs", "version"]).output().is_ok_and(|o| o.status.success()) {
+            anyhow::bail!("runner.lfs is set but git-lfs is not installed");
+        }
+        git_in(build_dir, &["lfs", "pull"])?;
+        if submodules {
+            git_in(build_dir, &["submodule", "foreach", "--quiet", "--recursive", "git lfs pull"])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `git <args>` in `dir`, failing with its stderr.
+fn git_in(dir: &Path, args: &[&str]) -> Result<()> {
+    let out = git_command().args(args).current_dir(dir).env("LC_ALL", "C").output()?;
+    if !out.status.success() {
+        // The subcommand: the first argument that is neither an option nor a `-c` value
+        let subcommand = (0..args.len())
+            .find(|&i| !args[i].starts_with('-') && (i == 0 || args[i - 1] != "-c"))
+            .map_or("", |i| args[i]);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        anyhow::bail!("git {} failed: {}", subcommand, stderr.trim());
+    }
     Ok(())
 }
 
@@ -345,38 +837,8 @@ pub fn copy_build_to_run(build_dir: &Path, run_dir: &Path) -> Result<()> {
     // RAII guard ensures temp_dir is cleaned up on any error path
     let mut temp_guard = TempDirGuard::new(&temp_dir);
 
-    // Copy using rsync-like approach with tar (excludes .git)
-    let archive = Command::new("tar")
-        .args(["--exclude=.git", "-c", "."])
-        .current_dir(build_dir)
-        .output()?;
-
-    if !archive.status.success() {
-        let stderr = String::from_utf8_lossy(&archive.stderr);
-        anyhow::bail!("tar archive failed: {}", stderr);
-    }
-
-    std::fs::create_dir_all(&temp_dir)?;
-
-    let mut extract = Command::new("tar")
-        .args(["-x"])
-        .current_dir(&temp_dir)
-        .stdin(std::process::Stdio::piped())
-        .spawn()?;
-
-    {
-        use std::io::Write;
-        let stdin = extract
-            .stdin
-            .as_mut()
-            .context("Failed to open tar stdin")?;
-        stdin.write_all(&archive.stdout)?;
-    }
-
-    let status = extract.wait()?;
-    if !status.success() {
-        anyhow::bail!("tar extraction failed with exit code: {:?}", status.code());
-    }
+    // Copy everything except .git
+    copy_dir_excluding_git(build_dir, &temp_dir)?;
 
     // Disarm the guard before rename
     temp_guard.disarm();
@@ -402,40 +864,129 @@ pub fn copy_build_to_run(build_dir: &Path, run_dir: &Path) -> Result<()> {
         let _ = std::fs::remove_dir_all(&backup_dir);
     }
 
+    // Outside the run dir, so jobs never see it in their workspace
+    let commit_file = run_dir.with_extension("commit");
+    match head_commit(build_dir) {
+        Some(commit) => std::fs::write(&commit_file, commit)?,
+        None => {
+            let _ = std::fs::remove_file(&commit_file);
+        }
+    }
+
     Ok(())
 }
 
-/// Removes the sot_path and all associated job directories.
-pub fn cleanup_cache_dir(sot_path: &Path, job_ids: &[String]) {
-    use tracing::{info, warn};
+/// The commit the job's run directory was last copied from.
+pub fn run_commit(sot_path: &Path, job_id: &str) -> Option<String> {
+    let path = get_run_dir(sot_path, job_id).with_extension("commit");
+    std::fs::read_to_string(path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
 
-    // Remove job directories
-    for job_id in job_ids {
-        let job_dir = get_job_dir(sot_path, job_id);
-        let build_dir = get_build_dir(sot_path, job_id);
-
-        // Remove git worktree first (if it exists)
-        if build_dir.join(".git").exists() {
-            let build_dir_str = build_dir.to_string_lossy();
-            let result = Command::new("git")
-                .args(["worktree", "remove", "--force", &*build_dir_str])
-                .current_dir(sot_path)
-                .output();
-
-            if let Err(e) = result {
-                warn!(path = %build_dir.display(), error = %e, "Failed to remove git worktree");
-            }
+/// The first 7 characters of a commit SHA.
+pub fn short_sha(sha: &str) -> &str {
+    &sha[..7.min(sha.len())]
+}
+
+/// Recursively copies `src` into `dst`, skipping `.git` at any level.
+/// Symlinks are recreated rather than followed.
+fn copy_dir_excluding_git(src: &Path, dst: &Path) -> Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        if entry.file_name() == ".git" {
+            continue;
         }
+        let from = entry.path();
+        let to = dst.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            copy_symlink(&from, &to)?;
+        } else if file_type.is_dir() {
+            copy_dir_excluding_git(&from, &to)?;
+        } else {
+            std::fs::copy(&from, &to)
+                .with_context(|| format!("Failed to copy {}", from.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(from)?, to)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    let target = std::fs::read_link(from)?;
+    if from.is_dir() {
+        std::os::windows::fs::symlink_dir(target, to)?;
+    } else {
+        std::os::windows::fs::symlink_file(target, to)?;
+    }
+    Ok(())
+}
+
+/// Removes a job's directory (build worktree, run dir and temp/old variants).
+pub fn remove_job_dir(sot_path: &Path, job_id: &str) {
+    use tracing::{info, warn};
 
-        if job_dir.exists() {
-            info!(path = %job_dir.display(), "Removing job directory");
-            let _ = std::fs::remove_dir_all(&job_dir);
+    let job_dir = get_job_dir(sot_path, job_id);
+    let build_dir = get_build_dir(sot_path, job_id);
+
+    // Remove git worktree first (if it exists)
+    if build_dir.join(".git").exists() {
+        let build_dir_str = build_dir.to_string_lossy();
+        let result = git_command()
+            .args(["worktree", "remove", "--force", &*build_dir_str])
+            .current_dir(sot_path)
+            .output();
+
+        if let Err(e) = result {
+            warn!(path = %build_dir.display(), error = %e, "Failed to remove git worktree");
         }
+    }
+
+    if job_dir.exists() {
+        info!(path = %job_dir.display(), "Removing job directory");
+        let _ = std::fs::remove_dir_all(&job_dir);
+    }
+
+    // Also remove temp/old variants for run dir
+    let run_dir = get_run_dir(sot_path, job_id);
+    let _ = std::fs::remove_dir_all(run_dir.with_extension("tmp"));
+    let _ = std::fs::remove_dir_all(run_dir.with_extension("old"));
+}
 
-        // Also remove temp/old variants for run dir
-        let run_dir = get_run_dir(sot_path, job_id);
-        let _ = std::fs::remove_dir_all(run_dir.with_extension("tmp"));
-        let _ = std::fs::remove_dir_all(run_dir.with_extension("old"));
+/// Removes this SoT's job directories whose job is not in `job_ids` (removed
+/// or renamed jobs). Returns the IDs whose directories were removed.
+pub fn remove_orphan_job_dirs(sot_path: &Path, job_ids: &[String]) -> Vec<String> {
+    let sot_name = sot_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown");
+    let prefix = format!("{}@", sot_name);
+    let Ok(entries) = std::fs::read_dir(cache_base()) else {
+        return Vec::new();
+    };
+    let orphans: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|name| name.strip_prefix(&prefix).map(str::to_string))
+        .filter(|job_id| !job_ids.contains(job_id))
+        .collect();
+    for job_id in &orphans {
+        remove_job_dir(sot_path, job_id);
+    }
+    orphans
+}
+
+/// Removes the sot_path and all associated job directories.
+pub fn cleanup_cache_dir(sot_path: &Path, job_ids: &[String]) {
+    use tracing::info;
+
+    for job_id in job_ids {
+        remove_job_dir(sot_path, job_id);
     }
 
     // Remove sot_path
@@ -449,12 +1000,283 @@ pub fn cleanup_cache_dir(sot_path: &Path, job_ids: &[String]) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn git_command_never_prompts() {
+        let cmd = git_command();
+        let envs: Vec<_> = cmd.get_envs().map(|(k, v)| (k.to_str().unwrap(), v.and_then(|v| v.to_str()))).collect();
+        assert!(envs.contains(&("GIT_TERMINAL_PROMPT", Some("0"))));
+        assert!(envs.contains(&("GIT_ASKPASS", Some(""))));
+        assert!(envs.contains(&("SSH_ASKPASS_REQUIRE", Some("never"))));
+    }
+
     #[test]
     fn cache_path_from_url() {
         let dir = generate_cache_path("https://github.com/user/myrepo.git");
         assert!(dir.to_str().unwrap().contains("myrepo"));
     }
 
+    #[test]
+    fn history_dir_strips_random_suffix() {
+        let sot = Path::new("/cache/rollcron/my-repo-1a2b3c4d");
+        assert_eq!(
+            get_history_dir(sot, "backup"),
+            PathBuf::from("/cache/rollcron/history/my-repo/backup")
+        );
+    }
+
+    #[test]
+    fn state_of_cached_sot_lives_in_state_dir() {
+        let sot = cache_base().join("my-repo-1a2b3c4d");
+        assert_eq!(get_history_dir(&sot, "backup"), state_base().join("history/my-repo/backup"));
+        assert_eq!(get_outbox_dir(&sot), state_base().join("outbox/my-repo"));
+        assert_eq!(get_export_dir(&sot), state_base().join("export/my-repo"));
+        assert_eq!(get_log_dir(&sot, "backup"), state_base().join("logs/my-repo/backup"));
+    }
+
+    #[test]
+    fn orphan_job_dirs_are_removed() {
+        let sot = Path::new("/nonexistent").join(format!("orphan-test-{}", generate_random_suffix()));
+        for job_id in ["kept", "renamed"] {
+            std::fs::create_dir_all(get_run_dir(&sot, job_id)).unwrap();
+        }
+        assert_eq!(remove_orphan_job_dirs(&sot, &["kept".to_string()]), vec!["renamed".to_string()]);
+        assert!(!get_job_dir(&sot, "renamed").exists());
+        assert!(get_job_dir(&sot, "kept").exists());
+
+        assert!(remove_orphan_job_dirs(&sot, &[]).contains(&"kept".to_string()));
+        assert!(!get_job_dir(&sot, "kept").exists());
+    }
+
+    #[test]
+    fn sync_refuses_commits_not_signed_by_allowed_keys() {
+        if Command::new("ssh-keygen").arg("-?").output().is_err() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let git = |cwd: &Path, args: &[&str]| {
+            let out = Command::new("git")
+                .args(["-c", "user.name=a", "-c", "user.email=a@b", "-c", "gpg.format=ssh"])
+                .args(args)
+                .current_dir(cwd)
+                .output()
+                .unwrap();
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        };
+        let keygen = |name: &str| {
+            let path = dir.path().join(name);
+            let out = Command::new("ssh-keygen").args(["-q", "-t", "ed25519", "-N", "", "-f"]).arg(&path).output().unwrap();
+            assert!(out.status.success());
+            (path.display().to_string(), Signer::Ssh(std::fs::read_to_string(path.with_extension("pub")).unwrap()))
+        };
+        let (trusted_key, trusted) = keygen("trusted");
+        let (_, other) = keygen("other");
+
+        let upstream = dir.path().join("upstream");
+        std::fs::create_dir(&upstream).unwrap();
+        git(&upstream, &["init", "-q"]);
+        git(&upstream, &["-c", &format!("user.signingkey={}", trusted_key), "commit", "-q", "-S", "--allow-empty", "-m", "signed"]);
+        assert!(verify_commit(&upstream, "HEAD", std::slice::from_ref(&trusted)).is_ok());
+        let err = verify_commit(&upstream, "HEAD", std::slice::from_ref(&other)).unwrap_err();
+        assert!(err.to_string().contains("is not in --allowed-signers"), "{}", err);
+
+        let clone = dir.path().join("clone");
+        clone_repo(upstream.to_str().unwrap(), &clone, None).unwrap();
+        let signed_head = head_commit(&clone);
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", "unsigned"]);
+        let err = sync_repo(&clone, Some(&[trusted]), true).unwrap_err();
+        assert!(err.to_string().ends_with("it is not signed"), "{}", err);
+        assert_eq!(head_commit(&clone), signed_head);
+        assert!(sync_repo(&clone, None, true).unwrap().is_some());
+    }
+
+    fn git(cwd: &Path, args: &[&str]) -> String {
+        let out = Command::new("git")
+            .args(["-c", "user.name=a", "-c", "user.email=a@b"])
+            .args(args)
+            .current_dir(cwd)
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        String::from_utf8_lossy(&out.stdout).trim().to_string()
+    }
+
+    #[test]
+    fn shallow_clone_and_pruning_fetch() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = dir.path().join("upstream");
+        std::fs::create_dir(&upstream).unwrap();
+        git(&upstream, &["init", "-q"]);
+        for msg in ["one", "two", "three"] {
+            git(&upstream, &["commit", "-q", "--allow-empty", "-m", msg]);
+        }
+        git(&upstream, &["branch", "stale"]);
+
+        let clone = dir.path().join("clone");
+        clone_repo(upstream.to_str().unwrap(), &clone, Some(1)).unwrap();
+        assert_eq!(git(&clone, &["rev-list", "--count", "HEAD"]), "1");
+
+        git(&upstream, &["branch", "-D", "stale"]);
+        git(&clone, &fetch_args(Some(1)).iter().map(String::as_str).collect::<Vec<_>>());
+        assert!(!git(&clone, &["branch", "-r"]).contains("stale"));
+    }
+
+    #[test]
+    fn corrupt_repo_is_recloned() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = dir.path().join("upstream");
+        std::fs::create_dir(&upstream).unwrap();
+        git(&upstream, &["init", "-q"]);
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", "one"]);
+
+        let clone = dir.path().join("clone");
+        clone_repo(upstream.to_str().unwrap(), &clone, None).unwrap();
+        assert!(!ensure_repo(&clone, None).unwrap());
+        let head = head_commit(&clone);
+        std::fs::remove_dir_all(clone.join(".git").join("objects")).unwrap();
+        std::fs::create_dir(clone.join(".git").join("objects")).unwrap();
+
+        assert_eq!(sync_repo(&clone, None, true).unwrap(), None);
+        assert_eq!(head_commit(&clone), head);
+        assert!(!ensure_repo(&clone, None).unwrap());
+    }
+
+    #[test]
+    fn force_push_is_followed_only_when_allowed() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = dir.path().join("upstream");
+        std::fs::create_dir(&upstream).unwrap();
+        git(&upstream, &["init", "-q"]);
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", "one"]);
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", "two"]);
+
+        let clone = dir.path().join("clone");
+        clone_repo(upstream.to_str().unwrap(), &clone, None).unwrap();
+        let old_head = head_commit(&clone);
+        git(&upstream, &["reset", "-q", "--hard", "HEAD~1"]);
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", "rewritten"]);
+
+        let err = sync_repo(&clone, None, false).unwrap_err();
+        assert!(err.to_string().contains("Upstream history was rewritten"), "{}", err);
+        assert_eq!(head_commit(&clone), old_head);
+        let range = sync_repo(&clone, None, true).unwrap().unwrap();
+        assert!(range.ends_with("(forced)"), "{}", range);
+        assert_eq!(head_commit(&clone), head_commit(&upstream));
+
+        // A fast-forward is not a force push
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", "three"]);
+        assert!(!sync_repo(&clone, None, false).unwrap().unwrap().ends_with("(forced)"));
+    }
+
+    #[test]
+    fn changelog_notes_are_merged_and_pushed() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = dir.path().join("upstream");
+        std::fs::create_dir(&upstream).unwrap();
+        git(&upstream, &["init", "-q"]);
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", "one"]);
+
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        clone_repo(upstream.to_str().unwrap(), &first, None).unwrap();
+        clone_repo(upstream.to_str().unwrap(), &second, None).unwrap();
+        record_changelog(&first, "pause: backup").unwrap();
+        record_changelog(&second, "resume: backup").unwrap();
+        record_changelog(&first, "trigger: etl").unwrap();
+
+        let notes = git(&upstream, &["notes", "--ref", CHANGELOG_REF, "show", "HEAD"]);
+        let lines: Vec<&str> = notes.lines().filter(|l| !l.is_empty()).collect();
+        assert_eq!(lines.len(), 3, "{}", notes);
+        assert!(["pause: backup", "resume: backup", "trigger: etl"].iter().all(|l| lines.contains(l)), "{}", notes);
+    }
+
+    #[test]
+    fn remote_head_is_read_without_fetching() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = dir.path().join("upstream");
+        std::fs::create_dir(&upstream).unwrap();
+        git(&upstream, &["init", "-q"]);
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", "one"]);
+
+        let clone = dir.path().join("clone");
+        clone_repo(upstream.to_str().unwrap(), &clone, None).unwrap();
+        assert_eq!(remote_head(&clone), head_commit(&upstream));
+        assert_eq!(sync_repo(&clone, None, true).unwrap(), None);
+
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", "two"]);
+        assert_eq!(remote_head(&clone), head_commit(&upstream));
+        assert_ne!(rev_parse(&clone, "@{upstream}"), head_commit(&upstream), "ls-remote does not fetch");
+        assert!(sync_repo(&clone, None, true).unwrap().is_some());
+        assert_eq!(head_commit(&clone), head_commit(&upstream));
+    }
+
+    #[test]
+    fn shallow_source_uses_file_urls() {
+        assert_eq!(shallow_source("https://github.com/a/b"), "https://github.com/a/b");
+        #[cfg(unix)]
+        assert_eq!(shallow_source("/srv/repo"), "file:///srv/repo");
+    }
+
+    #[test]
+    fn move_dir_keeps_existing_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("cache/history/repo"), dir.path().join("state/history/repo"));
+        assert!(!move_dir(&old, &new).unwrap());
+
+        std::fs::create_dir_all(&old).unwrap();
+        std::fs::write(old.join("runs.jsonl"), "{}").unwrap();
+        assert!(move_dir(&old, &new).unwrap());
+        assert!(new.join("runs.jsonl").exists() && !old.exists());
+
+        std::fs::create_dir_all(&old).unwrap();
+        assert!(!move_dir(&old, &new).unwrap());
+        assert!(old.exists());
+    }
+
+    #[test]
+    fn repo_name_from_sources() {
+        assert_eq!(repo_name("https://github.com/user/myrepo.git"), "myrepo");
+        assert_eq!(repo_name("git@github.com:user/myrepo.git"), "myrepo");
+        assert_eq!(repo_name("/srv/jobs/"), "jobs");
+        assert_eq!(repo_name(r"C:\srv\jobs"), "jobs");
+    }
+
+    #[test]
+    fn strip_windows_verbatim_prefix() {
+        assert_eq!(strip_verbatim_prefix(r"\\?\C:\srv\jobs"), r"C:\srv\jobs");
+        assert_eq!(strip_verbatim_prefix(r"\\?\UNC\host\share"), r"\\?\UNC\host\share");
+        assert_eq!(strip_verbatim_prefix("/srv/jobs"), "/srv/jobs");
+    }
+
+    #[test]
+    fn copy_dir_skips_git() {
+        let src = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(src.path().join(".git")).unwrap();
+        std::fs::write(src.path().join(".git/HEAD"), "ref").unwrap();
+        std::fs::create_dir_all(src.path().join("sub")).unwrap();
+        std::fs::write(src.path().join("sub/a.txt"), "a").unwrap();
+        std::fs::write(src.path().join(".gitignore"), "target").unwrap();
+
+        let dst = tempfile::tempdir().unwrap();
+        let target = dst.path().join("run");
+        copy_dir_excluding_git(src.path(), &target).unwrap();
+        assert!(!target.join(".git").exists());
+        assert_eq!(std::fs::read_to_string(target.join("sub/a.txt")).unwrap(), "a");
+        assert!(target.join(".gitignore").exists());
+    }
+
+    #[test]
+    fn run_dir_copy_records_its_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let build = dir.path().join("build");
+        std::fs::create_dir(&build).unwrap();
+        git(&build, &["init", "-q"]);
+        git(&build, &["commit", "-q", "--allow-empty", "-m", "one"]);
+        let run = dir.path().join("run");
+        copy_build_to_run(&build, &run).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.path().join("run.commit")).ok(), head_commit(&build));
+        assert!(!run.join(".git").exists());
+    }
+
     #[test]
     fn cache_path_is_random() {
         let dir1 = generate_cache_path("https://github.com/user/repo.git");
@@ -0,0 +1,137 @@
+//! etcd lease via the v3 JSON gateway (`/v3/lease/*`, `/v3/kv/txn`).
+//!
+//! The key is created only if absent and is bound to an etcd lease, so it
+//! disappears on its own when the leader stops renewing.
+
+use super::LockBackend;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct EtcdLock {
+    url: String,
+    key: String,
+    client: reqwest::Client,
+    /// etcd lease ID while we hold the key
+    lease: Option<String>,
+}
+
+impl EtcdLock {
+    pub fn new(url: &str, key: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            key: key.to_string(),
+            client: reqwest::Client::new(),
+            lease: None,
+        }
+    }
+
+    async fn post(&self, path: &str, body: Value) -> Result<Value> {
+        let url = format!("{}{}", self.url, path);
+        let resp = self
+            .client
+            .post(&url)
+            .timeout(REQUEST_TIMEOUT)
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| format!("failed to reach etcd at {}", self.url))?;
+        let status = resp.status();
+        let body: Value = resp.json().await.context("invalid response from etcd")?;
+        if !status.is_success() {
+            anyhow::bail!("etcd {} returned {}: {}", path, status, body["message"].as_str().unwrap_or(""));
+        }
+        Ok(body)
+    }
+
+    /// Refreshes our lease; false if it has expired.
+    async fn keep_alive(&self, lease: &str) -> Result<bool> {
+        let body = self.post("/v3/lease/keepalive", json!({ "ID": lease })).await?;
+        let ttl = json_i64(&body["result"]["TTL"]);
+        Ok(ttl.is_some_and(|t| t > 0))
+    }
+}
+
+impl LockBackend for EtcdLock {
+    async fn try_acquire(&mut self, holder: &str, ttl: Duration) -> Result<bool> {
+        if let Some(lease) = self.lease.clone() {
+            if self.keep_alive(&lease).await? {
+                return Ok(true);
+            }
+            self.lease = None;
+        }
+
+        let grant = self.post("/v3/lease/grant", json!({ "TTL": ttl.as_secs().max(1) })).await?;
+        let lease = grant["ID"]
+            .as_str()
+            .map(String::from)
+            .or_else(|| grant["ID"].as_i64().map(|id| id.to_string()))
+            .context("etcd lease grant returned no ID")?;
+
+        let key = base64(self.key.as_bytes());
+        let txn = json!({
+            "compare": [{ "key": key, "target": "CREATE", "create_revision": "0" }],
+            "success": [{ "request_put": { "key": key, "value": base64(holder.as_bytes()), "lease": lease } }],
+        });
+        let won = self.post("/v3/kv/txn", txn).await?["succeeded"].as_bool() == Some(true);
+        if won {
+            self.lease = Some(lease);
+        } else {
+            let _ = self.post("/v3/lease/revoke", json!({ "ID": lease })).await;
+        }
+        Ok(won)
+    }
+
+    async fn release(&mut self, _holder: &str) -> Result<()> {
+        if let Some(lease) = self.lease.take() {
+            // Revoking the lease deletes the key
+            self.post("/v3/lease/revoke", json!({ "ID": lease })).await?;
+        }
+        Ok(())
+    }
+}
+
+/// The gateway encodes int64 fields as strings.
+fn json_i64(value: &Value) -> Option<i64> {
+    value.as_i64().or_else(|| value.as_str()?.parse().ok())
+}
+
+/// Standard base64 with padding (etcd's JSON encoding for bytes).
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_encoding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"rollcron/leader"), "cm9sbGNyb24vbGVhZGVy");
+    }
+
+    #[test]
+    fn int64_fields_as_strings() {
+        assert_eq!(json_i64(&json!("15")), Some(15));
+        assert_eq!(json_i64(&json!(15)), Some(15));
+        assert_eq!(json_i64(&Value::Null), None);
+    }
+}
@@ -0,0 +1,128 @@
+//! Lease file on shared storage (NFS, SMB, ...).
+//!
+//! The file holds `{"holder": ..., "expires_at_ms": ...}` and is replaced
+//! atomically via rename. After writing, the holder re-reads the file to
+//! detect a concurrent writer. Instance clocks must agree to well within
+//! the TTL.
+
+use super::LockBackend;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Delay before re-reading the lease to confirm a write won
+const CONFIRM_DELAY: Duration = Duration::from_millis(200);
+
+pub struct FileLock {
+    path: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Lease {
+    holder: String,
+    expires_at_ms: u64,
+}
+
+impl FileLock {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    async fn read(&self) -> Option<Lease> {
+        let content = tokio::fs::read(&self.path).await.ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    async fn write(&self, lease: &Lease) -> Result<()> {
+        let tmp = self.path.with_extension(format!("{}.tmp", sanitize(&lease.holder)));
+        tokio::fs::write(&tmp, serde_json::to_vec(lease)?)
+            .await
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, &self.path)
+            .await
+            .with_context(|| format!("failed to replace {}", self.path.display()))
+    }
+}
+
+impl LockBackend for FileLock {
+    async fn try_acquire(&mut self, holder: &str, ttl: Duration) -> Result<bool> {
+        let now = now_ms();
+        let renewing = match self.read().await {
+            Some(lease) if lease.holder == holder => true,
+            Some(lease) if lease.expires_at_ms > now => return Ok(false),
+            _ => false,
+        };
+
+        let lease = Lease {
+            holder: holder.to_string(),
+            expires_at_ms: now + ttl.as_millis() as u64,
+        };
+        self.write(&lease).await?;
+        if renewing {
+            return Ok(true);
+        }
+
+        // Another standby may have replaced the expired lease at the same time
+        tokio::time::sleep(CONFIRM_DELAY).await;
+        Ok(self.read().await.is_some_and(|l| l.holder == holder))
+    }
+
+    async fn release(&mut self, holder: &str) -> Result<()> {
+        if self.read().await.is_some_and(|l| l.holder == holder) {
+            tokio::fs::remove_file(&self.path)
+                .await
+                .with_context(|| format!("failed to remove {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn sanitize(holder: &str) -> String {
+    holder
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn lease_is_exclusive_until_released() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("leader.lock");
+        let ttl = Duration::from_secs(30);
+        let mut a = FileLock::new(&path);
+        let mut b = FileLock::new(&path);
+
+        assert!(a.try_acquire("a", ttl).await.unwrap());
+        assert!(!b.try_acquire("b", ttl).await.unwrap());
+        assert!(a.try_acquire("a", ttl).await.unwrap(), "renewal");
+
+        b.release("b").await.unwrap();
+        assert!(path.exists(), "release by a non-holder is a no-op");
+        a.release("a").await.unwrap();
+        assert!(b.try_acquire("b", ttl).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn expired_lease_can_be_taken() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("leader.lock");
+        let stale = Lease { holder: "a".to_string(), expires_at_ms: now_ms() - 1 };
+        std::fs::write(&path, serde_json::to_vec(&stale).unwrap()).unwrap();
+
+        let mut b = FileLock::new(&path);
+        assert!(b.try_acquire("b", Duration::from_secs(30)).await.unwrap());
+    }
+}
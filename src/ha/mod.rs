@@ -0,0 +1,218 @@
+//! High availability: leader election between rollcron instances.
+//!
+//! With `runner.ha` set, every instance polls and builds as usual, but only
+//! the holder of a shared lease runs scheduled jobs. The leader renews the
+//! lease every `ttl / 3`; if it cannot renew for two thirds of the TTL it
+//! steps down, so a standby can take over once the lease expires. A backend
+//! call that hangs is abandoned when the lease would run out.
+
+mod etcd;
+mod file;
+mod redis;
+
+use crate::config::{HaConfig, LockConfig};
+use crate::facts;
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Without HA every instance is the leader
+static LEADER: AtomicBool = AtomicBool::new(true);
+
+/// True if this instance should run scheduled jobs.
+pub fn is_leader() -> bool {
+    LEADER.load(Ordering::Relaxed)
+}
+
+/// A lease shared by all instances.
+pub trait LockBackend: Send {
+    /// Acquires the lease for `holder`, or renews it if already held.
+    /// Returns false if another holder has it.
+    fn try_acquire(&mut self, holder: &str, ttl: Duration) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Releases the lease if `holder` has it.
+    fn release(&mut self, holder: &str) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Running election; call `shutdown` to release the lease.
+pub struct Election {
+    shutdown: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+impl Election {
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.handle.await;
+    }
+}
+
+/// Starts as a standby and campaigns for leadership in the background.
+pub fn start(config: &HaConfig, node_id: String) -> Result<Election> {
+    LEADER.store(false, Ordering::Relaxed);
+    let (shutdown, rx) = watch::channel(false);
+    let ttl = config.ttl;
+    info!(target: "rollcron::ha", node_id = %node_id, ttl = ?ttl, "Starting as standby");
+    let handle = match &config.lock {
+        LockConfig::File { path } => {
+            let backend = file::FileLock::new(crate::env::expand_string(path));
+            tokio::spawn(campaign(backend, node_id, ttl, rx, &LEADER))
+        }
+        LockConfig::Redis { url, key } => {
+            let backend = redis::RedisLock::new(url, key)?;
+            tokio::spawn(campaign(backend, node_id, ttl, rx, &LEADER))
+        }
+        LockConfig::Etcd { url, key } => {
+            let backend = etcd::EtcdLock::new(url, key);
+            tokio::spawn(campaign(backend, node_id, ttl, rx, &LEADER))
+        }
+    };
+    Ok(Election { shutdown, handle })
+}
+
+/// Default node ID: `<hostname>-<pid>`.
+pub fn default_node_id() -> String {
+    let host = facts::get().get("hostname").map_or_else(facts::hostname, str::to_string);
+    format!("{}-{}", host, std::process::id())
+}
+
+async fn campaign<B: LockBackend>(
+    mut backend: B,
+    node_id: String,
+    ttl: Duration,
+    mut shutdown: watch::Receiver<bool>,
+    leader: &AtomicBool,
+) {
+    let is_leader = || leader.load(Ordering::Relaxed);
+    let set_leader = |value: bool| leader.store(value, Ordering::Relaxed);
+    let renew_every = ttl / 3;
+    // Leadership is kept until this instant unless a renewal succeeds
+    let mut held_until: Option<Instant> = None;
+
+    loop {
+        let attempt = Instant::now();
+        // A hung backend must not keep this instance leading past its lease
+        let limit = held_until.map_or(ttl - renew_every, |until| until.saturating_duration_since(attempt));
+        let acquired = tokio::time::timeout(limit, backend.try_acquire(&node_id, ttl))
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("no answer within {:?}", limit)));
+        match acquired {
+            Ok(true) => {
+                if !is_leader() {
+                    info!(target: "rollcron::ha", node_id = %node_id, "Became leader");
+                    set_leader(true);
+                }
+                held_until = Some(attempt + ttl - renew_every);
+            }
+            Ok(false) => {
+                if is_leader() {
+                    warn!(target: "rollcron::ha", node_id = %node_id, "Lease taken by another instance, stepping down");
+                    set_leader(false);
+                }
+                held_until = None;
+            }
+            Err(e) => {
+                warn!(target: "rollcron::ha", node_id = %node_id, error = %format!("{:#}", e), "Lock backend error");
+            }
+        }
+
+        if held_until.is_some_and(|until| Instant::now() >= until) {
+            warn!(target: "rollcron::ha", node_id = %node_id, "Could not renew lease, stepping down");
+            set_leader(false);
+            held_until = None;
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(renew_every) => {}
+            _ = shutdown.changed() => break,
+        }
+    }
+
+    if is_leader() {
+        set_leader(false);
+        match backend.release(&node_id).await {
+            Ok(()) => info!(target: "rollcron::ha", node_id = %node_id, "Released leadership"),
+            Err(e) => warn!(target: "rollcron::ha", error = %e, "Failed to release lease"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Backend scripted with a sequence of acquire results
+    struct Scripted(Vec<Result<bool, ()>>);
+
+    impl LockBackend for Scripted {
+        async fn try_acquire(&mut self, _holder: &str, _ttl: Duration) -> Result<bool> {
+            match self.0.remove(0) {
+                Ok(held) => Ok(held),
+                Err(()) => anyhow::bail!("unreachable"),
+            }
+        }
+
+        async fn release(&mut self, _holder: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Backend that grants the lease once, then never answers
+    struct Hangs(bool);
+
+    impl LockBackend for Hangs {
+        async fn try_acquire(&mut self, _holder: &str, _ttl: Duration) -> Result<bool> {
+            if std::mem::replace(&mut self.0, true) {
+                std::future::pending::<()>().await;
+            }
+            Ok(true)
+        }
+
+        async fn release(&mut self, _holder: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn leader_steps_down_when_the_backend_hangs() {
+        static LEADER: AtomicBool = AtomicBool::new(false);
+        let (_tx, rx) = watch::channel(false);
+        tokio::spawn(campaign(Hangs(false), "node".to_string(), Duration::from_secs(3), rx, &LEADER));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(LEADER.load(Ordering::Relaxed), "acquired");
+        // The renewal started at 1s hangs; the lease is only trusted until 2s
+        tokio::time::sleep(Duration::from_millis(1950)).await;
+        assert!(!LEADER.load(Ordering::Relaxed), "stepped down while the renewal hangs");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn leader_steps_down_when_renewals_fail() {
+        let ttl = Duration::from_secs(3);
+        let script = vec![Ok(false), Ok(true), Err(()), Err(()), Err(()), Ok(true)];
+        static LEADER: AtomicBool = AtomicBool::new(false);
+        let is_leader = || LEADER.load(Ordering::Relaxed);
+        let (tx, rx) = watch::channel(false);
+        let handle = tokio::spawn(campaign(Scripted(script), "node".to_string(), ttl, rx, &LEADER));
+
+        let step = Duration::from_millis(1100);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!is_leader(), "standby while another instance holds the lease");
+        tokio::time::sleep(step).await;
+        assert!(is_leader(), "acquired");
+        tokio::time::sleep(step).await;
+        assert!(is_leader(), "one failed renewal is tolerated");
+        tokio::time::sleep(step).await;
+        assert!(!is_leader(), "stepped down before the lease expires");
+        tokio::time::sleep(step * 2).await;
+        assert!(is_leader(), "re-acquired");
+
+        tx.send(true).unwrap();
+        handle.await.unwrap();
+        assert!(!is_leader());
+    }
+}
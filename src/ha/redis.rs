@@ -0,0 +1,223 @@
+//! Redis lease: `SET key holder PX ttl` guarded by a Lua compare-and-set.
+//! Speaks just enough RESP over a plain TCP connection (no TLS).
+
+use super::LockBackend;
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Take the key if free or ours, refreshing the expiry
+const ACQUIRE: &str = r#"local v = redis.call('get', KEYS[1])
+if v == false or v == ARGV[1] then
+  redis.call('set', KEYS[1], ARGV[1], 'PX', ARGV[2])
+  return 1
+end
+return 0"#;
+
+const RELEASE: &str = r#"if redis.call('get', KEYS[1]) == ARGV[1] then
+  return redis.call('del', KEYS[1])
+end
+return 0"#;
+
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct RedisLock {
+    addr: String,
+    password: Option<String>,
+    db: Option<u32>,
+    key: String,
+    conn: Option<BufReader<TcpStream>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Reply {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Reply>),
+}
+
+impl RedisLock {
+    pub fn new(url: &str, key: &str) -> Result<Self> {
+        let (addr, password, db) = parse_url(url)?;
+        Ok(Self {
+            addr,
+            password,
+            db,
+            key: key.to_string(),
+            conn: None,
+        })
+    }
+
+    async fn connect(&self) -> Result<BufReader<TcpStream>> {
+        tokio::time::timeout(IO_TIMEOUT, self.handshake())
+            .await
+            .with_context(|| format!("redis at {} timed out", self.addr))?
+    }
+
+    /// Connects, then authenticates and selects the database if configured.
+    async fn handshake(&self) -> Result<BufReader<TcpStream>> {
+        let stream = TcpStream::connect(&self.addr)
+            .await
+            .with_context(|| format!("failed to connect to redis at {}", self.addr))?;
+        let mut conn = BufReader::new(stream);
+        if let Some(password) = &self.password {
+            expect_ok(call(&mut conn, &["AUTH", password]).await?).context("AUTH failed")?;
+        }
+        if let Some(db) = self.db {
+            expect_ok(call(&mut conn, &["SELECT", &db.to_string()]).await?).context("SELECT failed")?;
+        }
+        Ok(conn)
+    }
+
+    /// Runs a command, reconnecting once per call; a broken connection is dropped.
+    async fn command(&mut self, args: &[&str]) -> Result<Reply> {
+        let mut conn = match self.conn.take() {
+            Some(conn) => conn,
+            None => self.connect().await?,
+        };
+        let reply = tokio::time::timeout(IO_TIMEOUT, call(&mut conn, args))
+            .await
+            .context("redis timed out")??;
+        self.conn = Some(conn);
+        match reply {
+            Reply::Error(e) => anyhow::bail!("redis error: {}", e),
+            reply => Ok(reply),
+        }
+    }
+}
+
+impl LockBackend for RedisLock {
+    async fn try_acquire(&mut self, holder: &str, ttl: Duration) -> Result<bool> {
+        let ttl_ms = ttl.as_millis().to_string();
+        let key = self.key.clone();
+        let reply = self.command(&["EVAL", ACQUIRE, "1", &key, holder, &ttl_ms]).await?;
+        Ok(reply == Reply::Integer(1))
+    }
+
+    async fn release(&mut self, holder: &str) -> Result<()> {
+        let key = self.key.clone();
+        self.command(&["EVAL", RELEASE, "1", &key, holder]).await?;
+        Ok(())
+    }
+}
+
+/// `redis://[user][:password@]host[:port][/db]` -> (`host:port`, password, db)
+fn parse_url(url: &str) -> Result<(String, Option<String>, Option<u32>)> {
+    let rest = url
+        .strip_prefix("redis://")
+        .with_context(|| format!("unsupported redis URL '{}' (expected redis://...)", url))?;
+    let (authority, db) = match rest.split_once('/') {
+        Some((authority, "")) => (authority, None),
+        Some((authority, db)) => (
+            authority,
+            Some(db.parse().with_context(|| format!("invalid redis database '{}'", db))?),
+        ),
+        None => (rest, None),
+    };
+    let (password, host) = match authority.rsplit_once('@') {
+        Some((userinfo, host)) => {
+            let password = userinfo.split_once(':').map_or(userinfo, |(_, p)| p);
+            (Some(password.to_string()).filter(|p| !p.is_empty()), host)
+        }
+        None => (None, authority),
+    };
+    if host.is_empty() {
+        anyhow::bail!("redis URL '{}' has no host", url);
+    }
+    let addr = if host.contains(':') { host.to_string() } else { format!("{}:6379", host) };
+    Ok((addr, password, db))
+}
+
+fn expect_ok(reply: Reply) -> Result<()> {
+    match reply {
+        Reply::Simple(_) => Ok(()),
+        Reply::Error(e) => anyhow::bail!("{}", e),
+        other => anyhow::bail!("unexpected reply {:?}", other),
+    }
+}
+
+async fn call(conn: &mut BufReader<TcpStream>, args: &[&str]) -> Result<Reply> {
+    conn.get_mut().write_all(&encode(args)).await?;
+    read_reply(conn).await
+}
+
+fn encode(args: &[&str]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        out.extend(format!("${}\r\n", arg.len()).as_bytes());
+        out.extend(arg.as_bytes());
+        out.extend(b"\r\n");
+    }
+    out
+}
+
+async fn read_reply<R: AsyncBufRead + Unpin + Send>(reader: &mut R) -> Result<Reply> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        anyhow::bail!("redis closed the connection");
+    }
+    let line = line.trim_end_matches("\r\n");
+    let (kind, body) = line.split_at_checked(1).context("empty redis reply")?;
+    Ok(match kind {
+        "+" => Reply::Simple(body.to_string()),
+        "-" => Reply::Error(body.to_string()),
+        ":" => Reply::Integer(body.parse().context("invalid integer reply")?),
+        "$" => match body.parse::<i64>().context("invalid bulk length")? {
+            len if len < 0 => Reply::Bulk(None),
+            len => {
+                let mut buf = vec![0; len as usize + 2];
+                reader.read_exact(&mut buf).await?;
+                buf.truncate(len as usize);
+                Reply::Bulk(Some(buf))
+            }
+        },
+        "*" => match body.parse::<i64>().context("invalid array length")? {
+            len if len < 0 => Reply::Array(Vec::new()),
+            len => {
+                let mut items = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    items.push(Box::pin(read_reply(reader)).await?);
+                }
+                Reply::Array(items)
+            }
+        },
+        other => anyhow::bail!("unknown redis reply type '{}'", other),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_redis_urls() {
+        assert_eq!(parse_url("redis://cache").unwrap(), ("cache:6379".to_string(), None, None));
+        assert_eq!(
+            parse_url("redis://:s3cret@10.0.0.5:6380/2").unwrap(),
+            ("10.0.0.5:6380".to_string(), Some("s3cret".to_string()), Some(2))
+        );
+        assert!(parse_url("rediss://cache").is_err());
+        assert!(parse_url("redis://cache/x").is_err());
+    }
+
+    #[test]
+    fn encode_command() {
+        assert_eq!(encode(&["GET", "k"]), b"*2\r\n$3\r\nGET\r\n$1\r\nk\r\n");
+    }
+
+    #[tokio::test]
+    async fn decode_replies() {
+        let mut input: &[u8] = b"+OK\r\n:1\r\n$-1\r\n$3\r\nabc\r\n*2\r\n:1\r\n-ERR no\r\n";
+        assert_eq!(read_reply(&mut input).await.unwrap(), Reply::Simple("OK".to_string()));
+        assert_eq!(read_reply(&mut input).await.unwrap(), Reply::Integer(1));
+        assert_eq!(read_reply(&mut input).await.unwrap(), Reply::Bulk(None));
+        assert_eq!(read_reply(&mut input).await.unwrap(), Reply::Bulk(Some(b"abc".to_vec())));
+        assert_eq!(
+            read_reply(&mut input).await.unwrap(),
+            Reply::Array(vec![Reply::Integer(1), Reply::Error("ERR no".to_string())])
+        );
+    }
+}
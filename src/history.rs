@@ -0,0 +1,491 @@
+//! Persistent run history.
+//!
+//! Layout of a job's history directory:
+//! ```text
+//! <history_dir>/
+//! ├── runs.jsonl            # One JobRunReport per line (oldest first)
+//! ├── run_number            # Number of the latest run (see `next_run_number`)
+//! ├── approval.json         # Host policy approval, if any (see `policy`)
+//! ├── running/<run-id>.json # Marker of a run in progress (see `mark_running`)
+//! └── <run-id>.log[.gz|.zst] # Captured output of each run (latest N kept)
+//! ```
+
+use crate::actor::job::report::{FailureClass, RunOutcome};
+use crate::actor::job::JobRunReport;
+use crate::config::Compression;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const RUNS_FILE: &str = "runs.jsonl";
+const RUNNING_DIR: &str = "running";
+const RUN_NUMBER_FILE: &str = "run_number";
+
+/// Serializes run number allocation between concurrent runs
+static RUN_NUMBER_LOCK: Mutex<()> = Mutex::new(());
+
+/// Number of per-run output files kept per job
+const KEEP_OUTPUTS: usize = 50;
+
+/// A run in progress. Removed when the run is recorded; one still present at
+/// startup means the daemon died during the run.
+#[derive(Debug, Serialize, Deserialize)]
+struct RunningMarker {
+    job_id: String,
+    run_id: String,
+    #[serde(default)]
+    run_number: Option<u64>,
+    started_at: DateTime<Utc>,
+    pid: u32,
+}
+
+/// Allocates the job's next run number (1 for its first run). The counter
+/// survives restarts; without one, it continues from the recorded runs.
+pub fn next_run_number(dir: &Path) -> Result<u64> {
+    let _guard = RUN_NUMBER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = dir.join(RUN_NUMBER_FILE);
+    let last = match fs::read_to_string(&path) {
+        Ok(content) => content.trim().parse::<u64>().with_context(|| format!("Invalid {}", path.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let runs = read_runs(dir)?;
+            runs.iter().filter_map(|r| r.run_number).max().unwrap_or(runs.len() as u64)
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let next = last + 1;
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create history dir {}", dir.display()))?;
+    let tmp = dir.join(format!("{}.tmp", RUN_NUMBER_FILE));
+    fs::write(&tmp, format!("{}\n", next))?;
+    fs::rename(&tmp, &path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(next)
+}
+
+/// Notes that a run started, so a crash during it is not silently lost.
+pub fn mark_running(
+    dir: &Path,
+    job_id: &str,
+    run_id: &str,
+    run_number: Option<u64>,
+    started_at: DateTime<Utc>,
+) -> Result<()> {
+    let running = dir.join(RUNNING_DIR);
+    fs::create_dir_all(&running).with_context(|| format!("Failed to create {}", running.display()))?;
+    let marker = RunningMarker {
+        job_id: job_id.to_string(),
+        run_id: run_id.to_string(),
+        run_number,
+        started_at,
+        pid: std::process::id(),
+    };
+    fs::write(marker_path(dir, run_id), serde_json::to_vec(&marker)?)?;
+    Ok(())
+}
+
+/// Records runs whose marker outlived the daemon that started them as
+/// `interrupted`. Markers of a daemon that is still alive are left alone.
+/// Returns the recorded runs.
+pub fn recover_interrupted(dir: &Path) -> Result<Vec<JobRunReport>> {
+    let Ok(entries) = fs::read_dir(dir.join(RUNNING_DIR)) else {
+        return Ok(Vec::new());
+    };
+    let mut recovered = Vec::new();
+    for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+        let Some(marker) = fs::read(&path).ok().and_then(|data| serde_json::from_slice::<RunningMarker>(&data).ok())
+        else {
+            let _ = fs::remove_file(&path);
+            continue;
+        };
+        if marker.pid != std::process::id() && crate::pid::alive(marker.pid) {
+            continue;
+        }
+        let report = JobRunReport {
+            job_id: marker.job_id,
+            run_id: marker.run_id,
+            run_number: marker.run_number,
+            commit: None,
+            started_at: marker.started_at,
+            duration: std::time::Duration::ZERO,
+            attempts: 1,
+            outcome: RunOutcome::Interrupted,
+            exit_code: None,
+            error: Some(format!("rollcron (pid {}) stopped while the run was in progress", marker.pid)),
+            failure_class: Some(FailureClass::Killed),
+            fallback: false,
+            stdout_tail: String::new(),
+            stderr_tail: String::new(),
+            omitted_bytes: 0,
+            outside_writes: Vec::new(),
+            imported: false,
+            timings: None,
+            trigger: None,
+            overrides: Vec::new(),
+            artifacts: Vec::new(),
+        };
+        record(dir, &report, b"", Compression::None, None)?;
+        recovered.push(report);
+    }
+    recovered.sort_by_key(|r| r.started_at);
+    Ok(recovered)
+}
+
+/// `recover_interrupted` for every job under `root` (one directory per job).
+pub fn recover_all_interrupted(root: &Path) -> Vec<JobRunReport> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut recovered = Vec::new();
+    for dir in entries.filter_map(|e| e.ok().map(|e| e.path())).filter(|p| p.is_dir()) {
+        match recover_interrupted(&dir) {
+            Ok(runs) => recovered.extend(runs),
+            Err(e) => tracing::warn!(dir = %dir.display(), error = %e, "Failed to record interrupted runs"),
+        }
+    }
+    recovered
+}
+
+fn marker_path(dir: &Path, run_id: &str) -> PathBuf {
+    dir.join(RUNNING_DIR).join(format!("{}.json", run_id))
+}
+
+/// Records a finished run: appends the report, stores its output and clears
+/// its in-progress marker.
+pub fn record(
+    dir: &Path,
+    report: &JobRunReport,
+    output: &[u8],
+    compression: Compression,
+    level: Option<u32>,
+) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create history dir {}", dir.display()))?;
+
+    let mut line = serde_json::to_string(report)?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(RUNS_FILE))?;
+    file.write_all(line.as_bytes())?;
+
+    write_output(dir, &report.run_id, output, compression, level)?;
+    prune_outputs(dir, KEEP_OUTPUTS);
+    let _ = fs::remove_file(marker_path(dir, &report.run_id));
+    Ok(())
+}
+
+/// Reads all recorded runs (oldest first). Unparseable lines are skipped.
+pub fn read_runs(dir: &Path) -> Result<Vec<JobRunReport>> {
+    let path = dir.join(RUNS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Merges runs imported from elsewhere into the history, keeping it sorted
+/// by start time. Runs starting at the same instant as an already recorded
+/// run are skipped. Returns the number of runs added.
+pub fn import(dir: &Path, runs: Vec<JobRunReport>) -> Result<usize> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create history dir {}", dir.display()))?;
+
+    let mut merged = read_runs(dir)?;
+    let mut known: HashSet<DateTime<Utc>> = merged.iter().map(|r| r.started_at).collect();
+    let before = merged.len();
+    merged.extend(runs.into_iter().filter(|r| known.insert(r.started_at)));
+    let added = merged.len() - before;
+    if added == 0 {
+        return Ok(0);
+    }
+    // Stable: runs recorded in the same instant keep their order
+    merged.sort_by_key(|r| r.started_at);
+
+    let mut content = String::new();
+    for run in &merged {
+        content.push_str(&serde_json::to_string(run)?);
+        content.push('\n');
+    }
+    let path = dir.join(RUNS_FILE);
+    let tmp = dir.join(format!("{}.tmp", RUNS_FILE));
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, &path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(added)
+}
+
+/// Reads the stored output of a run, decompressing it if needed.
+/// Returns None if no output was kept for the run.
+pub fn read_output(dir: &Path, run_id: &str) -> Result<Option<Vec<u8>>> {
+    for compression in [Compression::None, Compression::Gzip, Compression::Zstd] {
+        let path = output_path(dir, run_id, compression);
+        if path.exists() {
+            let data = fs::read(&path)?;
+            return decompress(&data, compression)
+                .with_context(|| format!("Failed to decompress {}", path.display()))
+                .map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// The file holding a run's stored output, if it was kept.
+pub fn output_file(dir: &Path, run_id: &str) -> Option<PathBuf> {
+    [Compression::None, Compression::Gzip, Compression::Zstd]
+        .into_iter()
+        .map(|compression| output_path(dir, run_id, compression))
+        .find(|path| path.exists())
+}
+
+fn output_path(dir: &Path, run_id: &str, compression: Compression) -> PathBuf {
+    dir.join(format!("{}.log{}", run_id, compression.extension()))
+}
+
+fn write_output(
+    dir: &Path,
+    run_id: &str,
+    output: &[u8],
+    compression: Compression,
+    level: Option<u32>,
+) -> Result<()> {
+    let data = compress(output, compression, level)?;
+    fs::write(output_path(dir, run_id, compression), data)?;
+    Ok(())
+}
+
+/// Removes the oldest output files, keeping the latest `keep`.
+/// Run IDs start with a timestamp, so name order is chronological.
+fn prune_outputs(dir: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut outputs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.contains(".log"))
+        })
+        .collect();
+    if outputs.len() <= keep {
+        return;
+    }
+    outputs.sort();
+    for path in &outputs[..outputs.len() - keep] {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Compresses data into a gzip or zstd stream (the format's default level
+/// unless `level` is given).
+pub fn compress(data: &[u8], compression: Compression, level: Option<u32>) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Gzip => {
+            let level = level.map_or(flate2::Compression::default(), flate2::Compression::new);
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        }
+        // Level 0 is zstd's default
+        Compression::Zstd => Ok(zstd::encode_all(data, level.map_or(0, |level| level as i32))?),
+    }
+}
+
+/// Decompresses a gzip or zstd stream, including ones written by the
+/// `gzip`/`zstd` binaries.
+pub fn decompress(data: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Gzip => {
+            let mut out = Vec::new();
+            flate2::read::MultiGzDecoder::new(data).read_to_end(&mut out).context("Invalid gzip data")?;
+            Ok(out)
+        }
+        Compression::Zstd => zstd::decode_all(data).context("Invalid zstd data"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    fn make_report(run_id: &str) -> JobRunReport {
+        JobRunReport {
+            job_id: "test".to_string(),
+            run_id: run_id.to_string(),
+            run_number: None,
+            commit: None,
+            started_at: Utc::now(),
+            duration: Duration::from_millis(10),
+            attempts: 1,
+            outcome: RunOutcome::Success,
+            exit_code: Some(0),
+            error: None,
+            failure_class: None,
+            fallback: false,
+            stdout_tail: "hello".to_string(),
+            stderr_tail: String::new(),
+            omitted_bytes: 0,
+            outside_writes: Vec::new(),
+            imported: false,
+            timings: None,
+            trigger: None,
+            overrides: Vec::new(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn record_and_read_runs() {
+        let dir = tempdir().unwrap();
+        record(dir.path(), &make_report("run-1"), b"out 1", Compression::None, None).unwrap();
+        record(dir.path(), &make_report("run-2"), b"out 2", Compression::None, None).unwrap();
+
+        let runs = read_runs(dir.path()).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].run_id, "run-1");
+        assert_eq!(runs[1].run_id, "run-2");
+        assert_eq!(read_output(dir.path(), "run-2").unwrap().unwrap(), b"out 2");
+    }
+
+    #[test]
+    fn import_merges_sorted_without_duplicates() {
+        let dir = tempdir().unwrap();
+        let at = |secs: i64| DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+        let mut recorded = make_report("recorded");
+        recorded.started_at = at(100);
+        record(dir.path(), &recorded, b"", Compression::None, None).unwrap();
+
+        let imported: Vec<JobRunReport> = [(50, "old"), (100, "dup"), (10, "older")]
+            .into_iter()
+            .map(|(secs, id)| {
+                let mut run = make_report(id);
+                run.started_at = at(secs);
+                run.imported = true;
+                run
+            })
+            .collect();
+        assert_eq!(import(dir.path(), imported).unwrap(), 2);
+
+        let ids: Vec<String> = read_runs(dir.path()).unwrap().into_iter().map(|r| r.run_id).collect();
+        assert_eq!(ids, vec!["older", "old", "recorded"]);
+    }
+
+    #[test]
+    fn interrupted_runs_are_recorded_once() {
+        let dir = tempdir().unwrap();
+        let started_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        mark_running(dir.path(), "backup", "run-1", Some(7), started_at).unwrap();
+        mark_running(dir.path(), "backup", "run-2", None, started_at).unwrap();
+        record(dir.path(), &make_report("run-2"), b"", Compression::None, None).unwrap();
+
+        let recovered = recover_interrupted(dir.path()).unwrap();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].run_id, "run-1");
+        assert_eq!(recovered[0].run_number, Some(7));
+        assert_eq!(recovered[0].outcome, RunOutcome::Interrupted);
+        let runs = read_runs(dir.path()).unwrap();
+        assert_eq!(runs.iter().map(|r| r.run_id.as_str()).collect::<Vec<_>>(), ["run-2", "run-1"]);
+        assert!(recover_interrupted(dir.path()).unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_of_a_live_daemon_are_left_alone() {
+        let dir = tempdir().unwrap();
+        let marker = RunningMarker {
+            job_id: "backup".into(),
+            run_id: "run-1".into(),
+            run_number: None,
+            started_at: Utc::now(),
+            pid: 1,
+        };
+        fs::create_dir_all(dir.path().join(RUNNING_DIR)).unwrap();
+        fs::write(marker_path(dir.path(), "run-1"), serde_json::to_vec(&marker).unwrap()).unwrap();
+        assert!(recover_interrupted(dir.path()).unwrap().is_empty());
+        assert!(marker_path(dir.path(), "run-1").exists());
+    }
+
+    #[test]
+    fn run_numbers_continue_across_restarts() {
+        let dir = tempdir().unwrap();
+        // Existing history without numbers: continue after it
+        record(dir.path(), &make_report("a"), b"", Compression::None, None).unwrap();
+        record(dir.path(), &make_report("b"), b"", Compression::None, None).unwrap();
+        assert_eq!(next_run_number(dir.path()).unwrap(), 3);
+        assert_eq!(next_run_number(dir.path()).unwrap(), 4);
+        assert_eq!(fs::read_to_string(dir.path().join(RUN_NUMBER_FILE)).unwrap(), "4\n");
+
+        let fresh = tempdir().unwrap();
+        assert_eq!(next_run_number(&fresh.path().join("job")).unwrap(), 1);
+    }
+
+    #[test]
+    fn read_runs_missing_dir() {
+        let dir = tempdir().unwrap();
+        assert!(read_runs(&dir.path().join("nope")).unwrap().is_empty());
+        assert!(read_output(dir.path(), "nope").unwrap().is_none());
+    }
+
+    #[test]
+    fn gzip_roundtrip() {
+        let dir = tempdir().unwrap();
+        let output = "line\n".repeat(1000);
+        record(dir.path(), &make_report("run-gz"), output.as_bytes(), Compression::Gzip, Some(9)).unwrap();
+
+        let stored = fs::read(dir.path().join("run-gz.log.gz")).unwrap();
+        assert!(stored.len() < output.len());
+        assert_eq!(read_output(dir.path(), "run-gz").unwrap().unwrap(), output.as_bytes());
+    }
+
+    #[test]
+    fn zstd_roundtrip() {
+        let data = b"hello zstd".repeat(100);
+        let compressed = compress(&data, Compression::Zstd, Some(3)).unwrap();
+        assert_eq!(decompress(&compressed, Compression::Zstd).unwrap(), data);
+    }
+
+    #[test]
+    #[ignore = "needs the gzip and zstd binaries"]
+    fn reads_output_compressed_by_the_binaries() {
+        let data = b"written by an older rollcron\n".repeat(100);
+        for (program, compression) in [("gzip", Compression::Gzip), ("zstd", Compression::Zstd)] {
+            let mut child = std::process::Command::new(program)
+                .args(["-c", "-q"])
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .spawn()
+                .unwrap();
+            child.stdin.take().unwrap().write_all(&data).unwrap();
+            let output = child.wait_with_output().unwrap();
+            assert_eq!(decompress(&output.stdout, compression).unwrap(), data, "{}", program);
+        }
+    }
+
+    #[test]
+    fn prune_keeps_latest_outputs() {
+        let dir = tempdir().unwrap();
+        for i in 0..5 {
+            fs::write(dir.path().join(format!("run-{}.log", i)), b"x").unwrap();
+        }
+        fs::write(dir.path().join(RUNS_FILE), b"").unwrap();
+        prune_outputs(dir.path(), 2);
+
+        assert!(!dir.path().join("run-2.log").exists());
+        assert!(dir.path().join("run-3.log").exists());
+        assert!(dir.path().join("run-4.log").exists());
+        assert!(dir.path().join(RUNS_FILE).exists());
+    }
+}
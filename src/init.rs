@@ -0,0 +1,110 @@
+//! Init duties when rollcron is PID 1 (a container entrypoint without tini).
+//!
+//! PID 1 inherits every orphaned process, such as a job's grandchild whose
+//! parent exited first, and has to reap it or it stays a zombie. As PID 1,
+//! rollcron starts itself again as a child and becomes a minimal init: it
+//! forwards signals to that child and reaps every process that exits until
+//! the child does, then exits with its status.
+//!
+//! Set `ROLLCRON_NO_INIT=1` to run directly as PID 1 (e.g. under `docker --init`).
+
+use anyhow::{Context, Result};
+use nix::sys::signal::{kill, SigSet, SigmaskHow, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::Pid;
+use std::os::unix::process::CommandExt;
+
+/// Set in the child so it doesn't become an init again
+const CHILD_ENV: &str = "ROLLCRON_INIT_CHILD";
+
+/// Opt-out for containers that already have an init
+const DISABLE_ENV: &str = "ROLLCRON_NO_INIT";
+
+/// Passed on to the child; SIGCHLD is handled here
+const FORWARDED: [Signal; 6] = [
+    Signal::SIGTERM,
+    Signal::SIGINT,
+    Signal::SIGHUP,
+    Signal::SIGQUIT,
+    Signal::SIGUSR1,
+    Signal::SIGUSR2,
+];
+
+/// Acts as init if this is PID 1, returning the child's exit code.
+/// Must run before any other thread is started.
+pub fn run_if_pid1() -> Result<Option<i32>> {
+    if std::process::id() != 1 || std::env::var_os(CHILD_ENV).is_some() || std::env::var_os(DISABLE_ENV).is_some() {
+        return Ok(None);
+    }
+
+    // Blocked before the child exists so no SIGCHLD or forwarded signal is missed
+    let mut signals = SigSet::empty();
+    signals.add(Signal::SIGCHLD);
+    for signal in FORWARDED {
+        signals.add(signal);
+    }
+    signals.thread_block().context("Failed to block signals")?;
+
+    let exe = std::env::current_exe().context("Failed to locate the rollcron executable")?;
+    let mut cmd = std::process::Command::new(exe);
+    cmd.args(std::env::args_os().skip(1)).env(CHILD_ENV, "1");
+    // SAFETY: sigprocmask is async-signal-safe and touches no state of this process
+    unsafe {
+        cmd.pre_exec(move || {
+            nix::sys::signal::sigprocmask(SigmaskHow::SIG_UNBLOCK, Some(&signals), None)?;
+            Ok(())
+        });
+    }
+    let child = cmd.spawn().context("Failed to start rollcron under init")?;
+    let child = Pid::from_raw(child.id() as i32);
+
+    loop {
+        match signals.wait().context("Failed to wait for signals")? {
+            Signal::SIGCHLD => {
+                if let Some(code) = reap(child) {
+                    return Ok(Some(code));
+                }
+            }
+            signal => {
+                // Logging belongs to the child; this process only has stderr
+                if let Err(e) = kill(child, signal) {
+                    eprintln!("rollcron init: failed to forward {}: {}", signal, e);
+                }
+            }
+        }
+    }
+}
+
+/// Reaps every exited process; returns the child's exit code once it is among them.
+fn reap(child: Pid) -> Option<i32> {
+    let mut code = None;
+    loop {
+        match waitpid(None, Some(WaitPidFlag::WNOHANG)) {
+            Ok(WaitStatus::StillAlive) | Err(_) => return code,
+            Ok(status) if status.pid() == Some(child) => code = exit_code(status).or(code),
+            Ok(_) => {}
+        }
+    }
+}
+
+/// Shell-style exit code: the exit status, or 128 + signal number.
+fn exit_code(status: WaitStatus) -> Option<i32> {
+    match status {
+        WaitStatus::Exited(_, code) => Some(code),
+        WaitStatus::Signaled(_, signal, _) => Some(128 + signal as i32),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_code_follows_shell_convention() {
+        let pid = Pid::from_raw(42);
+        assert_eq!(exit_code(WaitStatus::Exited(pid, 3)), Some(3));
+        assert_eq!(exit_code(WaitStatus::Signaled(pid, Signal::SIGTERM, false)), Some(143));
+        assert_eq!(exit_code(WaitStatus::Stopped(pid, Signal::SIGSTOP)), None);
+    }
+}
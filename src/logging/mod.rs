@@ -0,0 +1,146 @@
+#[cfg(unix)]
+mod sink;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::Layered;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+/// Where log lines go (`--log-target`)
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum LogTarget {
+    Stdout,
+    Stderr,
+    /// The systemd journal, with priorities and fields such as JOB_ID
+    Journald,
+    /// The local syslog daemon (`/dev/log`), facility daemon
+    Syslog,
+    /// Appended to `--log-file`, with timestamps
+    File,
+}
+
+type Filtered = Layered<reload::Layer<EnvFilter, Registry>, Registry>;
+
+/// Filter set at startup and a handle to replace it while running
+struct Reloadable {
+    initial: String,
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+static FILTER: OnceLock<Reloadable> = OnceLock::new();
+
+/// Whether run output is logged line by line (journald and syslog)
+static FORWARD_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Initialize the logging system with per-service filtering.
+///
+/// Log levels can be controlled via RUST_LOG environment variable:
+/// - RUST_LOG=info                    - All services at info level
+/// - RUST_LOG=rollcron=debug          - rollcron at debug, others at default
+/// - RUST_LOG=rollcron::scheduler=trace,rollcron::git=warn
+///
+/// Available targets:
+/// - rollcron           - main application
+/// - rollcron::scheduler - job scheduling
+/// - rollcron::git      - git operations
+/// - rollcron::webhook  - webhook notifications
+/// - rollcron::output   - run output lines (journald and syslog targets only)
+///
+/// The filter can be replaced at runtime with `rollcron ctl log-level`.
+///
+/// Subcommands log to stderr so their stdout can be piped (e.g. `run --output json`);
+/// the daemon logs to stdout unless `target` says otherwise.
+pub fn init(to_stderr: bool, target: Option<LogTarget>, file: Option<&Path>) -> Result<()> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let initial = filter.to_string();
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let target = target.unwrap_or(if to_stderr { LogTarget::Stderr } else { LogTarget::Stdout });
+    let output: Box<dyn Layer<Filtered> + Send + Sync> = match target {
+        LogTarget::Stdout => Box::new(fmt::layer().with_target(true).without_time().with_writer(std::io::stdout)),
+        LogTarget::Stderr => Box::new(fmt::layer().with_target(true).without_time().with_writer(std::io::stderr)),
+        LogTarget::File => {
+            let path = file.context("--log-target file needs --log-file")?;
+            let log = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file {}", path.display()))?;
+            let writer = BoxMakeWriter::new(Mutex::new(log));
+            Box::new(fmt::layer().with_target(true).with_ansi(false).with_writer(writer))
+        }
+        LogTarget::Journald | LogTarget::Syslog => socket_layer(target)?,
+    };
+    tracing_subscriber::registry().with(filter).with(output).init();
+    let _ = FILTER.set(Reloadable { initial, handle });
+    Ok(())
+}
+
+#[cfg(unix)]
+fn socket_layer(target: LogTarget) -> Result<Box<dyn Layer<Filtered> + Send + Sync>> {
+    let layer = if target == LogTarget::Journald {
+        sink::SocketLayer::journald().context("Failed to connect to the journal")?
+    } else {
+        sink::SocketLayer::syslog().context("Failed to connect to syslog")?
+    };
+    FORWARD_OUTPUT.store(true, Ordering::Relaxed);
+    Ok(Box::new(layer))
+}
+
+#[cfg(not(unix))]
+fn socket_layer(_target: LogTarget) -> Result<Box<dyn Layer<Filtered> + Send + Sync>> {
+    anyhow::bail!("--log-target journald and syslog are only available on Unix")
+}
+
+/// Whether run output should be passed to [`job_output`].
+pub fn forwards_output() -> bool {
+    FORWARD_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Logs a line a job's run wrote: stdout at info, stderr at warn.
+pub fn job_output(job_id: &str, stream: &str, line: &str) {
+    if stream == "stderr" {
+        tracing::warn!(target: "rollcron::output", job_id, stream, "{}", line);
+    } else {
+        tracing::info!(target: "rollcron::output", job_id, stream, "{}", line);
+    }
+}
+
+/// The active filter, in `RUST_LOG` syntax.
+pub fn current_filter() -> Option<String> {
+    FILTER.get()?.handle.with_current(|f| f.to_string()).ok()
+}
+
+/// Replaces the active filter (`None` restores the startup filter).
+/// Returns the filter now in effect.
+pub fn set_filter(spec: Option<&str>) -> Result<String> {
+    let reloadable = FILTER.get().context("Logging is not initialized")?;
+    let filter = parse_filter(spec.unwrap_or(&reloadable.initial))?;
+    let applied = filter.to_string();
+    reloadable.handle.reload(filter).context("Failed to apply log filter")?;
+    Ok(applied)
+}
+
+fn parse_filter(spec: &str) -> Result<EnvFilter> {
+    EnvFilter::builder()
+        .parse(spec)
+        .map_err(|e| anyhow::anyhow!("Invalid log filter '{}': {}", spec, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filter_directives() {
+        let filter = parse_filter("info,rollcron::git=debug").unwrap();
+        assert!(filter.to_string().contains("rollcron::git=debug"));
+        assert!(parse_filter("rollcron::git=loud").is_err());
+    }
+}
@@ -0,0 +1,199 @@
+//! `--log-target journald|syslog`: log events sent to the local journal or
+//! syslog daemon over their Unix datagram sockets, with the event's level as
+//! the priority.
+//!
+//! The journal gets structured fields (`JOB_ID=`, `RUN_ID=`, ...) besides
+//! `MESSAGE=`; syslog gets the message with the fields appended as `key=value`.
+
+use std::fmt::{self, Write as _};
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_SOCKETS: [&str; 2] = ["/dev/log", "/var/run/syslog"];
+const IDENTIFIER: &str = "rollcron";
+/// `LOG_DAEMON`
+const FACILITY: u8 = 3;
+
+#[derive(Clone, Copy)]
+enum Format {
+    Journald,
+    Syslog,
+}
+
+pub struct SocketLayer {
+    format: Format,
+    socket: UnixDatagram,
+}
+
+impl SocketLayer {
+    pub fn journald() -> std::io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(JOURNAL_SOCKET)?;
+        Ok(Self { format: Format::Journald, socket })
+    }
+
+    pub fn syslog() -> std::io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        let mut last_err = None;
+        for path in SYSLOG_SOCKETS.iter().map(Path::new) {
+            match socket.connect(path) {
+                Ok(()) => return Ok(Self { format: Format::Syslog, socket }),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| std::io::ErrorKind::NotFound.into()))
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SocketLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let meta = event.metadata();
+        let datagram = match self.format {
+            Format::Journald => journal_entry(*meta.level(), meta.target(), &fields),
+            Format::Syslog => syslog_line(*meta.level(), meta.target(), &fields, chrono::Local::now()),
+        };
+        // Nowhere left to report a failed log write
+        let _ = self.socket.send(&datagram);
+    }
+}
+
+/// An event's message and its other fields in order.
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: Vec<(&'static str, String)>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.rest.push((field.name(), value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.rest.push((field.name(), format!("{:?}", value)));
+        }
+    }
+}
+
+/// syslog(3) severity of a level.
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        Level::DEBUG | Level::TRACE => 7,
+    }
+}
+
+/// Native journal protocol: `KEY=value` lines, or the length-prefixed form
+/// for values containing newlines.
+fn journal_entry(level: Level, target: &str, fields: &Fields) -> Vec<u8> {
+    let mut entry = Vec::new();
+    let mut push = |key: &str, value: &str| {
+        entry.extend_from_slice(key.as_bytes());
+        if value.contains('\n') {
+            entry.push(b'\n');
+            entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            entry.push(b'=');
+        }
+        entry.extend_from_slice(value.as_bytes());
+        entry.push(b'\n');
+    };
+    push("PRIORITY", &severity(level).to_string());
+    push("SYSLOG_IDENTIFIER", IDENTIFIER);
+    push("MESSAGE", &fields.message);
+    push("TARGET", target);
+    for (name, value) in &fields.rest {
+        push(&journal_field_name(name), value);
+    }
+    entry
+}
+
+/// Journal field names are uppercase letters, digits and underscores, and
+/// must not start with an underscore (those are trusted fields).
+fn journal_field_name(name: &str) -> String {
+    let upper: String =
+        name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+    let upper = upper.trim_start_matches('_');
+    match upper.chars().next() {
+        None => "FIELD".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("F_{}", upper),
+        Some(_) => upper.to_string(),
+    }
+}
+
+/// RFC 3164 line as syslog(3) sends it to the local socket.
+fn syslog_line(level: Level, target: &str, fields: &Fields, now: chrono::DateTime<chrono::Local>) -> Vec<u8> {
+    let mut line = format!(
+        "<{}>{} {}[{}]: {}: {}",
+        FACILITY * 8 + severity(level),
+        now.format("%b %e %H:%M:%S"),
+        IDENTIFIER,
+        std::process::id(),
+        target,
+        fields.message
+    );
+    for (name, value) in &fields.rest {
+        let _ = write!(line, " {}={}", name, value);
+    }
+    line.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn fields() -> Fields {
+        Fields {
+            message: "Job failed".to_string(),
+            rest: vec![("job_id", "backup".to_string()), ("error", "exit 1\nsee log".to_string())],
+        }
+    }
+
+    #[test]
+    fn journal_entry_fields() {
+        let entry = journal_entry(Level::WARN, "rollcron::job", &fields());
+        let text = String::from_utf8_lossy(&entry);
+        assert!(text.starts_with("PRIORITY=4\nSYSLOG_IDENTIFIER=rollcron\nMESSAGE=Job failed\nTARGET=rollcron::job\n"));
+        assert!(text.contains("JOB_ID=backup\n"));
+        // Multi-line values are length-prefixed
+        let mut error = b"ERROR\n".to_vec();
+        error.extend_from_slice(&14u64.to_le_bytes());
+        error.extend_from_slice(b"exit 1\nsee log\n");
+        assert!(entry.ends_with(&error));
+    }
+
+    #[test]
+    fn journal_field_names() {
+        assert_eq!(journal_field_name("job_id"), "JOB_ID");
+        assert_eq!(journal_field_name("_secret"), "SECRET");
+        assert_eq!(journal_field_name("run.id"), "RUN_ID");
+        assert_eq!(journal_field_name("2xx"), "F_2XX");
+    }
+
+    #[test]
+    fn syslog_line_format() {
+        let now = chrono::Local.with_ymd_and_hms(2025, 3, 7, 9, 5, 0).unwrap();
+        let line = String::from_utf8(syslog_line(Level::ERROR, "rollcron::job", &fields(), now)).unwrap();
+        let expected = format!(
+            "<27>Mar  7 09:05:00 rollcron[{}]: rollcron::job: Job failed job_id=backup error=exit 1\nsee log",
+            std::process::id()
+        );
+        assert_eq!(line, expected);
+    }
+}
@@ -1,56 +1,162 @@
+#![recursion_limit = "256"]
+
 mod actor;
+mod api;
+mod cache;
+mod cli;
+mod clock;
 mod config;
+mod describe;
 mod env;
+mod events;
+mod export;
+mod facts;
 mod git;
+mod ha;
+mod history;
+#[cfg(unix)]
+mod init;
 mod logging;
+mod metrics;
+mod otel;
+mod outbox;
+mod pid;
+mod policy;
+mod queue;
+mod schema;
+mod secrets;
+mod signers;
+mod source;
+mod systemd;
 mod webhook;
 
 use actor::runner::{GetJobIds, GracefulShutdown, Initialize, RunnerActor};
-use anyhow::{Context, Result};
-use clap::Parser;
-use std::path::{Path, PathBuf};
+use anyhow::Result;
+use clap::{CommandFactory, Parser};
 use std::time::Duration;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use xtra::prelude::*;
 
-const CONFIG_FILE: &str = "rollcron.yaml";
-
-#[derive(Parser)]
-#[command(name = "rollcron", about = "Auto-pulling cron scheduler")]
-struct Args {
-    /// Path to local repo or remote URL (https://... or git@...)
-    repo: String,
-
-    /// Pull interval in seconds
-    #[arg(long, default_value = "3600")]
-    pull_interval: u64,
+fn main() -> Result<()> {
+    // Before the runtime starts any threads
+    #[cfg(unix)]
+    if let Some(code) = init::run_if_pid1()? {
+        std::process::exit(code);
+    }
+    // Answers the shell's completion requests (see `rollcron completions`)
+    clap_complete::CompleteEnv::with_factory(cli::Args::command).var(cli::COMPLETE_VAR).complete();
+    systemd::init();
+    run()
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    logging::init();
-    let args = Args::parse();
+async fn run() -> Result<()> {
+    let args = cli::Args::parse();
+    logging::init(args.command.is_some(), args.log_target, args.log_file.as_deref())?;
+    if let Some(dir) = &args.state_dir {
+        git::init_state_dir(dir.clone());
+    }
+    if let Some(dir) = &args.cache_dir {
+        git::init_cache_dir(dir.clone());
+    }
+    if let Some(script) = &args.facts_script {
+        facts::init(facts::gather(Some(script))?);
+    }
+    if let Some(path) = &args.allowed_signers {
+        signers::init(signers::load(path)?);
+    }
+
+    if let Some(command) = args.command {
+        return cli::run(command).await;
+    }
+    let repo = args.repo.clone().expect("clap enforces REPO without a subcommand");
+    let clock = args.clock()?;
+    if let clock::Clock::Simulated { start_at, speed, stub_jobs, .. } = clock {
+        info!(start_at = %start_at, speed, stub_jobs, "Using simulated clock");
+    }
+    clock::init(clock);
+    tokio::spawn(clock::watch_suspend());
+    if let Some(path) = &args.policy {
+        let host_policy = policy::Policy::load(path)?;
+        info!(path = %path.display(), rules = host_policy.len(), "Loaded host policy");
+        policy::init(host_policy);
+    }
+    if let Some(path) = &args.event_log {
+        events::init(path)?;
+        info!(path = %path.display(), "Writing event log");
+    }
+    if let Some(endpoint) = otel::init()? {
+        info!(endpoint = %endpoint, "Exporting traces over OTLP");
+    }
+
+    git::init_env(args.git_env.clone());
+    if let Some(depth) = args.clone_depth {
+        git::init_clone_depth(depth);
+    }
 
     // Expand shell variables (~, $VAR) and canonicalize local paths
-    let expanded_repo = env::expand_string(&args.repo);
-    let source = if expanded_repo.starts_with('/') || expanded_repo.starts_with('.') {
-        PathBuf::from(&expanded_repo)
-            .canonicalize()?
-            .to_str()
-            .context("Path contains invalid UTF-8")?
-            .to_string()
-    } else {
-        expanded_repo
-    };
+    let source = git::resolve_source(&repo)?;
 
     info!(source = %source, pull_interval = args.pull_interval, "Starting rollcron");
+    events::emit(events::Event::DaemonStarted {
+        source: &source,
+        pid: std::process::id(),
+        version: env!("CARGO_PKG_VERSION"),
+    });
+    let host_facts = facts::get();
+    info!(facts = host_facts.len(), hostname = host_facts.get("hostname").unwrap_or_default(), "Gathered host facts");
+    if !args.only_labels.is_empty() {
+        let labels: Vec<String> = args.only_labels.iter().map(ToString::to_string).collect();
+        info!(labels = %labels.join(","), "Only running jobs with these labels");
+    }
 
     // Initial clone
     let sot_path = git::generate_cache_path(&source);
-    git::clone_to(&source, &sot_path)?;
-    info!(cache = %sot_path.display(), "Repository ready");
+    cache::claim(&sot_path)?;
+    let mut clone_span = otel::Span::root("git.clone").with_attr("repo.source", source.as_str());
+    if let Err(e) = source::clone_to(&source, &sot_path) {
+        clone_span.fail(&e);
+        drop(clone_span);
+        cache::release(&sot_path);
+        otel::shutdown().await;
+        return Err(e);
+    }
+    drop(clone_span);
+    info!(cache = %sot_path.display(), kind = source::backend(&source).kind(), "Repository ready");
+    git::migrate_state(&sot_path);
+    tokio::task::spawn_blocking(cache::report);
+    for run in history::recover_all_interrupted(&git::get_history_root(&sot_path)) {
+        warn!(job_id = %run.job_id, run_id = %run.run_id, "Run was interrupted by the previous shutdown, recorded as interrupted");
+        events::emit(events::Event::run_finished(&run));
+    }
+
+    let outbox_dir = git::get_outbox_dir(&sot_path);
+    match outbox::start(outbox_dir.clone()) {
+        Ok(0) => {}
+        Ok(pending) => info!(pending, "Resuming queued notifications"),
+        Err(e) => warn!(dir = %outbox_dir.display(), error = %e, "Notification outbox unavailable, sending directly"),
+    }
+
+    let (initial_runner, initial_jobs) = config::load_config(&sot_path)?;
+    let export_dir = git::get_export_dir(&sot_path);
+    match export::start(export_dir.clone(), git::get_history_root(&sot_path), initial_runner.export.clone()) {
+        Ok(0) => {}
+        Ok(pending) => info!(pending, "Resuming queued run exports"),
+        Err(e) => warn!(dir = %export_dir.display(), error = %e, "Export queue unavailable, runs are not exported"),
+    }
+    if let Some(signers) = signers::get() {
+        git::verify_commit(&sot_path, "HEAD", signers)?;
+        info!(signers = signers.len(), "Verified commit signature");
+    }
 
-    let (initial_runner, initial_jobs) = load_config(&sot_path)?;
+    // Campaign for leadership before any job is scheduled
+    let election = match &initial_runner.ha {
+        Some(ha_config) => {
+            let node_id = args.node_id.clone().unwrap_or_else(ha::default_node_id);
+            Some(ha::start(ha_config, node_id)?)
+        }
+        None => None,
+    };
 
     // Spawn Runner actor
     let runner = xtra::spawn_tokio(
@@ -58,6 +164,7 @@ async fn main() -> Result<()> {
             Duration::from_secs(args.pull_interval),
             sot_path.clone(),
             initial_runner,
+            args.only_labels.clone(),
         ),
         Mailbox::unbounded(),
     );
@@ -68,25 +175,51 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    // Serve the control API
+    let api_handle = (!args.no_api).then(|| {
+        let (token, origins) = (args.api_token.clone(), args.api_allowed_origin.clone());
+        tokio::spawn(api::serve(args.api_listen, runner.clone(), token, origins))
+    });
+    tokio::spawn(systemd::supervise(runner.clone()));
+
     // Wait for shutdown signal
-    tokio::signal::ctrl_c().await?;
+    shutdown_signal().await?;
     info!("Shutting down...");
+    systemd::notify("STOPPING=1");
+    events::emit(events::Event::DaemonStopping);
 
     // Get job IDs for cleanup
     let job_ids = runner.send(GetJobIds).await.unwrap_or_default();
 
     // Graceful shutdown
+    if let Some(handle) = api_handle {
+        handle.abort();
+    }
     let _ = runner.send(GracefulShutdown).await;
+    if let Some(election) = election {
+        election.shutdown().await;
+    }
 
     // Cleanup cache directories
     git::cleanup_cache_dir(&sot_path, &job_ids);
+    cache::release(&sot_path);
+    otel::shutdown().await;
 
     Ok(())
 }
 
-fn load_config(sot_path: &Path) -> Result<(config::RunnerConfig, Vec<config::Job>)> {
-    let config_path = sot_path.join(CONFIG_FILE);
-    let content = std::fs::read_to_string(&config_path)
-        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", config_path.display(), e))?;
-    config::parse_config(&content)
+/// Ctrl+C, or SIGTERM (`docker stop`, `kill`).
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
 }
@@ -0,0 +1,302 @@
+//! Run metrics (`runner.metrics`), exported after every finished run.
+//!
+//! The textfile exporter rewrites a node_exporter textfile collector file
+//! with every job's counters and latest-run gauges; counters start at zero
+//! when the daemon starts. The StatsD exporter sends each run's metrics over
+//! UDP as it finishes. Both are fire-and-forget: failures are logged and never
+//! affect the run.
+
+use crate::actor::job::report::FailureClass;
+use crate::actor::job::JobRunReport;
+use crate::config::{MetricsConfig, StatsdConfig};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::UdpSocket;
+use std::path::Path;
+use tracing::warn;
+
+/// Outcomes counted per job (every `RunOutcome`)
+const OUTCOMES: [&str; 7] = ["success", "failed", "timeout", "exec_error", "oom_killed", "interrupted", "skipped"];
+
+/// Latest-run gauge: name, help, value (None leaves the job out)
+type Gauge = (&'static str, &'static str, fn(&JobMetrics) -> Option<String>);
+
+const GAUGES: [Gauge; 5] = [
+    ("rollcron_job_last_success", "1 if the latest run succeeded.", |j| {
+        Some(u8::from(j.last_success).to_string())
+    }),
+    ("rollcron_job_last_run_timestamp_seconds", "When the latest run finished.", |j| {
+        Some(j.last_run_timestamp.to_string())
+    }),
+    ("rollcron_job_last_success_timestamp_seconds", "When the latest successful run finished.", |j| {
+        j.last_success_timestamp.map(|t| t.to_string())
+    }),
+    ("rollcron_job_last_duration_seconds", "Duration of the latest run.", |j| {
+        Some(j.last_duration_secs.to_string())
+    }),
+    ("rollcron_job_last_attempts", "Attempts made by the latest run.", |j| Some(j.last_attempts.to_string())),
+];
+
+/// Per-job state behind the textfile
+#[derive(Debug, Default)]
+pub struct Registry {
+    jobs: BTreeMap<String, JobMetrics>,
+}
+
+#[derive(Debug, Default)]
+struct JobMetrics {
+    runs: BTreeMap<&'static str, u64>,
+    failures: BTreeMap<&'static str, u64>,
+    last_success: bool,
+    last_run_timestamp: f64,
+    last_success_timestamp: Option<f64>,
+    last_duration_secs: f64,
+    last_attempts: u32,
+    last_commit: Option<String>,
+}
+
+impl Registry {
+    /// Records a finished run and exports it as configured.
+    pub fn record(&mut self, report: &JobRunReport, config: &MetricsConfig) {
+        let outcome = report.outcome.as_str();
+        let finished = report.started_at + chrono::Duration::from_std(report.duration).unwrap_or_default();
+        let finished = finished.timestamp_millis() as f64 / 1000.0;
+
+        let job = self.jobs.entry(report.job_id.clone()).or_default();
+        *job.runs.entry(outcome).or_default() += 1;
+        if let Some(class) = report.failure_class {
+            *job.failures.entry(class.as_str()).or_default() += 1;
+        }
+        job.last_success = report.is_success();
+        job.last_run_timestamp = finished;
+        if report.is_success() {
+            job.last_success_timestamp = Some(finished);
+        }
+        job.last_duration_secs = report.duration.as_secs_f64();
+        job.last_attempts = report.attempts;
+        job.last_commit = report.commit.clone();
+
+        if let Some(path) = &config.textfile {
+            if let Err(e) = write_textfile(Path::new(&crate::env::expand_string(path)), &self.render()) {
+                warn!(target: "rollcron::runner", path = %path, error = %e, "Failed to write metrics textfile");
+            }
+        }
+        if let Some(statsd) = &config.statsd {
+            if let Err(e) = send_statsd(statsd, report) {
+                warn!(target: "rollcron::runner", address = %statsd.address, error = %e, "Failed to send StatsD metrics");
+            }
+        }
+    }
+
+    /// Prometheus text exposition format.
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let mut family = |name: &str, kind: &str, help: &str, samples: &mut dyn Iterator<Item = (String, String)>| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+            }
+        };
+
+        family(
+            "rollcron_job_runs_total",
+            "counter",
+            "Finished runs by outcome since the daemon started.",
+            &mut self.jobs.iter().flat_map(|(id, job)| {
+                OUTCOMES.iter().map(move |outcome| {
+                    let count = job.runs.get(outcome).copied().unwrap_or(0);
+                    (format!("job=\"{}\",outcome=\"{}\"", escape(id), outcome), count.to_string())
+                })
+            }),
+        );
+        family(
+            "rollcron_job_failures_total",
+            "counter",
+            "Failed runs by failure class since the daemon started.",
+            &mut self.jobs.iter().flat_map(|(id, job)| {
+                FailureClass::ALL.iter().map(move |class| {
+                    let count = job.failures.get(class.as_str()).copied().unwrap_or(0);
+                    (format!("job=\"{}\",class=\"{}\"", escape(id), class.as_str()), count.to_string())
+                })
+            }),
+        );
+        family(
+            "rollcron_job_last_run_info",
+            "gauge",
+            "Always 1; labels the commit the latest run executed.",
+            &mut self.jobs.iter().filter_map(|(id, job)| {
+                let commit = job.last_commit.as_deref()?;
+                Some((format!("job=\"{}\",commit=\"{}\"", escape(id), escape(crate::git::short_sha(commit))), "1".to_string()))
+            }),
+        );
+        for (name, help, value) in GAUGES {
+            family(
+                name,
+                "gauge",
+                help,
+                &mut self
+                    .jobs
+                    .iter()
+                    .filter_map(|(id, job)| value(job).map(|v| (format!("job=\"{}\"", escape(id)), v))),
+            );
+        }
+        out
+    }
+}
+
+/// Label value escaping (job IDs are already restricted; kept for safety)
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Replaces `path` atomically so the collector never reads a partial file.
+fn write_textfile(path: &Path, content: &str) -> Result<()> {
+    let tmp = path.with_extension("prom.tmp");
+    std::fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+fn send_statsd(config: &StatsdConfig, report: &JobRunReport) -> Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(&config.address)?;
+    for line in statsd_lines(config, report) {
+        socket.send(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// One datagram per metric: run counter, duration timer and attempts gauge,
+/// plus a failure counter by class for failed runs.
+fn statsd_lines(config: &StatsdConfig, report: &JobRunReport) -> Vec<String> {
+    let prefix = &config.prefix;
+    let outcome = report.outcome.as_str();
+    let millis = report.duration.as_millis();
+    let class = report.failure_class.map(|c| c.as_str());
+    if config.dogstatsd {
+        let mut tags = format!("#job:{},outcome:{}", report.job_id, outcome);
+        if let Some(commit) = &report.commit {
+            tags.push_str(&format!(",commit:{}", crate::git::short_sha(commit)));
+        }
+        let mut lines = vec![
+            format!("{}.job.runs:1|c|{}", prefix, tags),
+            format!("{}.job.duration:{}|ms|{}", prefix, millis, tags),
+            format!("{}.job.attempts:{}|g|{}", prefix, report.attempts, tags),
+        ];
+        lines.extend(class.map(|class| format!("{}.job.failures:1|c|#job:{},class:{}", prefix, report.job_id, class)));
+        lines
+    } else {
+        let job = format!("{}.job.{}", prefix, report.job_id);
+        let mut lines = vec![
+            format!("{}.runs.{}:1|c", job, outcome),
+            format!("{}.duration:{}|ms", job, millis),
+            format!("{}.attempts:{}|g", job, report.attempts),
+        ];
+        lines.extend(class.map(|class| format!("{}.failures.{}:1|c", job, class)));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::job::report::RunOutcome;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    fn report(job_id: &str, outcome: RunOutcome, start_secs: i64) -> JobRunReport {
+        JobRunReport {
+            job_id: job_id.to_string(),
+            run_id: format!("run-{}", start_secs),
+            run_number: None,
+            commit: None,
+            started_at: Utc.timestamp_opt(start_secs, 0).unwrap(),
+            duration: Duration::from_millis(1500),
+            attempts: 2,
+            outcome,
+            exit_code: None,
+            error: None,
+            failure_class: None,
+            fallback: false,
+            stdout_tail: String::new(),
+            stderr_tail: String::new(),
+            omitted_bytes: 0,
+            outside_writes: vec![],
+            imported: false,
+            timings: None,
+            trigger: None,
+            overrides: Vec::new(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn textfile_accumulates_runs_per_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rollcron.prom");
+        let config = MetricsConfig {
+            textfile: Some(path.display().to_string()),
+            statsd: None,
+        };
+        let mut registry = Registry::default();
+        registry.record(&report("backup", RunOutcome::Success, 1000), &config);
+        let timeout = JobRunReport { failure_class: Some(FailureClass::Timeout), ..report("backup", RunOutcome::Timeout, 2000) };
+        registry.record(&timeout, &config);
+        let etl = JobRunReport { commit: Some("0123456789abcdef".to_string()), ..report("etl", RunOutcome::Success, 3000) };
+        registry.record(&etl, &config);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("# TYPE rollcron_job_runs_total counter\n"));
+        assert!(text.contains("rollcron_job_runs_total{job=\"backup\",outcome=\"success\"} 1\n"));
+        assert!(text.contains("rollcron_job_runs_total{job=\"backup\",outcome=\"timeout\"} 1\n"));
+        assert!(text.contains("rollcron_job_runs_total{job=\"etl\",outcome=\"failed\"} 0\n"));
+        assert!(text.contains("rollcron_job_failures_total{job=\"backup\",class=\"timeout\"} 1\n"));
+        assert!(text.contains("rollcron_job_failures_total{job=\"etl\",class=\"network\"} 0\n"));
+        assert!(text.contains("rollcron_job_last_success{job=\"backup\"} 0\n"));
+        assert!(text.contains("rollcron_job_last_run_timestamp_seconds{job=\"backup\"} 2001.5\n"));
+        assert!(text.contains("rollcron_job_last_success_timestamp_seconds{job=\"backup\"} 1001.5\n"));
+        assert!(text.contains("rollcron_job_last_duration_seconds{job=\"etl\"} 1.5\n"));
+        assert!(text.contains("rollcron_job_last_run_info{job=\"etl\",commit=\"0123456\"} 1\n"));
+        assert!(!text.contains("rollcron_job_last_run_info{job=\"backup\""));
+        assert!(!dir.path().join("rollcron.prom.tmp").exists());
+    }
+
+    #[test]
+    fn statsd_lines_with_and_without_tags() {
+        let mut config = StatsdConfig {
+            address: "127.0.0.1:8125".to_string(),
+            prefix: "cron".to_string(),
+            dogstatsd: false,
+        };
+        let run = report("backup", RunOutcome::Failed, 0);
+        assert_eq!(
+            statsd_lines(&config, &run),
+            ["cron.job.backup.runs.failed:1|c", "cron.job.backup.duration:1500|ms", "cron.job.backup.attempts:2|g"]
+        );
+        let run = JobRunReport { failure_class: Some(FailureClass::Network), ..run };
+        assert_eq!(statsd_lines(&config, &run)[3], "cron.job.backup.failures.network:1|c");
+        config.dogstatsd = true;
+        let lines = statsd_lines(&config, &run);
+        assert_eq!(lines[0], "cron.job.runs:1|c|#job:backup,outcome:failed");
+        assert_eq!(lines[3], "cron.job.failures:1|c|#job:backup,class:network");
+    }
+
+    #[test]
+    fn statsd_sends_datagrams() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let config = MetricsConfig {
+            textfile: None,
+            statsd: Some(StatsdConfig {
+                address: agent.local_addr().unwrap().to_string(),
+                prefix: "rollcron".to_string(),
+                dogstatsd: false,
+            }),
+        };
+        Registry::default().record(&report("backup", RunOutcome::Success, 0), &config);
+        let mut buf = [0; 256];
+        let n = agent.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"rollcron.job.backup.runs.success:1|c");
+    }
+}
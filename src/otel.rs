@@ -0,0 +1,432 @@
+//! OpenTelemetry trace export (OTLP over HTTP with JSON encoding).
+//!
+//! Disabled unless an endpoint is configured through the standard variables:
+//! - `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` (used as is) or
+//!   `OTEL_EXPORTER_OTLP_ENDPOINT` (`/v1/traces` appended)
+//! - `OTEL_EXPORTER_OTLP_[TRACES_]HEADERS`: `key=value,...`
+//! - `OTEL_EXPORTER_OTLP_[TRACES_]TIMEOUT`: milliseconds [default: 10000]
+//! - `OTEL_EXPORTER_OTLP_[TRACES_]PROTOCOL`: only `http/json` is supported
+//! - `OTEL_SERVICE_NAME` [default: rollcron], `OTEL_RESOURCE_ATTRIBUTES`
+//! - `OTEL_SDK_DISABLED=true` or `OTEL_TRACES_EXPORTER=none` turn export off
+//!
+//! Spans are ended on drop and shipped in batches by a background task.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::fmt::Display;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
+
+/// Max spans per export request
+const MAX_BATCH: usize = 512;
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+static EXPORTER: OnceLock<Exporter> = OnceLock::new();
+
+struct Exporter {
+    tx: mpsc::UnboundedSender<Message>,
+    timeout: Duration,
+}
+
+enum Message {
+    Span(SpanData),
+    Flush(oneshot::Sender<()>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub endpoint: String,
+    pub headers: Vec<(String, String)>,
+    pub timeout: Duration,
+    pub resource: Vec<(String, String)>,
+}
+
+impl Config {
+    /// Reads the exporter config; `None` if export is disabled or no endpoint is set.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>> {
+        let var = |name: &str| var(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        // Signal-specific variables take precedence over the generic ones
+        let signal_var = |suffix: &str| {
+            var(&format!("OTEL_EXPORTER_OTLP_TRACES_{}", suffix))
+                .or_else(|| var(&format!("OTEL_EXPORTER_OTLP_{}", suffix)))
+        };
+
+        if var("OTEL_SDK_DISABLED").is_some_and(|v| v.eq_ignore_ascii_case("true"))
+            || var("OTEL_TRACES_EXPORTER").is_some_and(|v| v == "none")
+        {
+            return Ok(None);
+        }
+        if let Some(exporter) = var("OTEL_TRACES_EXPORTER").filter(|v| v != "otlp") {
+            anyhow::bail!("Unsupported OTEL_TRACES_EXPORTER '{}' (supported: otlp, none)", exporter);
+        }
+        let endpoint = match (var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"), var("OTEL_EXPORTER_OTLP_ENDPOINT")) {
+            (Some(endpoint), _) => endpoint,
+            (None, Some(base)) => format!("{}/v1/traces", base.trim_end_matches('/')),
+            (None, None) => return Ok(None),
+        };
+        if let Some(protocol) = signal_var("PROTOCOL").filter(|p| p != "http/json") {
+            anyhow::bail!("Unsupported OTLP protocol '{}' (supported: http/json)", protocol);
+        }
+        let timeout = match signal_var("TIMEOUT") {
+            Some(ms) => Duration::from_millis(ms.parse().with_context(|| format!("Invalid OTLP timeout '{}'", ms))?),
+            None => DEFAULT_TIMEOUT,
+        };
+        let headers = parse_pairs(signal_var("HEADERS").as_deref().unwrap_or(""))
+            .context("Invalid OTEL_EXPORTER_OTLP_HEADERS")?;
+        let mut resource = parse_pairs(var("OTEL_RESOURCE_ATTRIBUTES").as_deref().unwrap_or(""))
+            .context("Invalid OTEL_RESOURCE_ATTRIBUTES")?;
+        let service_name = var("OTEL_SERVICE_NAME")
+            .or_else(|| resource.iter().find(|(k, _)| k == "service.name").map(|(_, v)| v.clone()))
+            .unwrap_or_else(|| "rollcron".to_string());
+        resource.retain(|(k, _)| k != "service.name");
+        resource.insert(0, ("service.name".to_string(), service_name));
+
+        Ok(Some(Self { endpoint, headers, timeout, resource }))
+    }
+}
+
+/// Parses `key=value,key2=value2` (values may be percent-encoded).
+fn parse_pairs(input: &str) -> Result<Vec<(String, String)>> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').with_context(|| format!("Expected key=value, got '{}'", pair))?;
+            Ok((key.trim().to_string(), percent_decode(value.trim())))
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Starts the exporter from the `OTEL_*` environment. Returns the endpoint if enabled.
+pub fn init() -> Result<Option<String>> {
+    let Some(config) = Config::from_env(|name| std::env::var(name).ok())? else {
+        return Ok(None);
+    };
+    let (tx, rx) = mpsc::unbounded_channel();
+    let endpoint = config.endpoint.clone();
+    let timeout = config.timeout;
+    tokio::spawn(export_loop(config, rx));
+    let _ = EXPORTER.set(Exporter { tx, timeout });
+    Ok(Some(endpoint))
+}
+
+/// Exports spans still queued. Waits at most the exporter timeout.
+pub async fn shutdown() {
+    let Some(exporter) = EXPORTER.get() else {
+        return;
+    };
+    let (done_tx, done_rx) = oneshot::channel();
+    if exporter.tx.send(Message::Flush(done_tx)).is_ok() {
+        let _ = tokio::time::timeout(exporter.timeout, done_rx).await;
+    }
+}
+
+async fn export_loop(config: Config, mut rx: mpsc::UnboundedReceiver<Message>) {
+    let client = match reqwest::Client::builder().timeout(config.timeout).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(target: "rollcron::otel", error = %e, "Failed to create OTLP client, spans are dropped");
+            return;
+        }
+    };
+    let mut batch = Vec::new();
+    let mut ticker = tokio::time::interval(EXPORT_INTERVAL);
+    loop {
+        tokio::select! {
+            message = rx.recv() => match message {
+                Some(Message::Span(span)) => {
+                    batch.push(span);
+                    if batch.len() >= MAX_BATCH {
+                        export(&client, &config, &mut batch).await;
+                    }
+                }
+                Some(Message::Flush(done)) => {
+                    export(&client, &config, &mut batch).await;
+                    let _ = done.send(());
+                }
+                None => break,
+            },
+            _ = ticker.tick() => export(&client, &config, &mut batch).await,
+        }
+    }
+}
+
+/// Sends and clears the batch. A failed export is logged and its spans dropped.
+async fn export(client: &reqwest::Client, config: &Config, batch: &mut Vec<SpanData>) {
+    if batch.is_empty() {
+        return;
+    }
+    let body = encode(&config.resource, batch);
+    let count = batch.len();
+    batch.clear();
+
+    let mut request = client.post(&config.endpoint).json(&body);
+    for (key, value) in &config.headers {
+        request = request.header(key, value);
+    }
+    match request.send().await {
+        Ok(resp) if resp.status().is_success() => {}
+        Ok(resp) => {
+            warn!(target: "rollcron::otel", endpoint = %config.endpoint, status = %resp.status(), spans = count, "OTLP export rejected");
+        }
+        Err(e) => {
+            warn!(target: "rollcron::otel", endpoint = %config.endpoint, error = %e, spans = count, "OTLP export failed");
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrValue {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl From<&str> for AttrValue {
+    fn from(v: &str) -> Self {
+        Self::Str(v.to_string())
+    }
+}
+
+impl From<String> for AttrValue {
+    fn from(v: String) -> Self {
+        Self::Str(v)
+    }
+}
+
+impl From<i64> for AttrValue {
+    fn from(v: i64) -> Self {
+        Self::Int(v)
+    }
+}
+
+impl From<u32> for AttrValue {
+    fn from(v: u32) -> Self {
+        Self::Int(v.into())
+    }
+}
+
+impl From<bool> for AttrValue {
+    fn from(v: bool) -> Self {
+        Self::Bool(v)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SpanData {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_id: Option<[u8; 8]>,
+    name: &'static str,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, AttrValue)>,
+    error: Option<String>,
+}
+
+/// A span, exported when dropped. A no-op when export is disabled.
+#[derive(Debug)]
+pub struct Span(Option<SpanData>);
+
+impl Span {
+    /// Starts a new trace.
+    pub fn root(name: &'static str) -> Self {
+        if EXPORTER.get().is_none() {
+            return Self(None);
+        }
+        Self(Some(SpanData::new(name, random_id(), None)))
+    }
+
+    /// Starts a span in the same trace, parented to this one.
+    pub fn child(&self, name: &'static str) -> Self {
+        Self(self.0.as_ref().map(|parent| SpanData::new(name, parent.trace_id, Some(parent.span_id))))
+    }
+
+    pub fn with_attr(mut self, key: &'static str, value: impl Into<AttrValue>) -> Self {
+        self.set_attr(key, value);
+        self
+    }
+
+    pub fn set_attr(&mut self, key: &'static str, value: impl Into<AttrValue>) {
+        if let Some(span) = &mut self.0 {
+            span.attributes.push((key, value.into()));
+        }
+    }
+
+    /// Marks the span as failed.
+    pub fn fail(&mut self, message: impl Display) {
+        if let Some(span) = &mut self.0 {
+            span.error = Some(message.to_string());
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let (Some(mut span), Some(exporter)) = (self.0.take(), EXPORTER.get()) else {
+            return;
+        };
+        span.end = SystemTime::now();
+        let _ = exporter.tx.send(Message::Span(span));
+    }
+}
+
+impl SpanData {
+    fn new(name: &'static str, trace_id: [u8; 16], parent_id: Option<[u8; 8]>) -> Self {
+        let now = SystemTime::now();
+        Self {
+            trace_id,
+            span_id: random_id(),
+            parent_id,
+            name,
+            start: now,
+            end: now,
+            attributes: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+/// Random non-zero ID (all-zero IDs are invalid in OTLP).
+fn random_id<const N: usize>() -> [u8; N] {
+    loop {
+        let id: [u8; N] = std::array::from_fn(|_| rand::random());
+        if id.iter().any(|&b| b != 0) {
+            return id;
+        }
+    }
+}
+
+/// OTLP/JSON `ExportTraceServiceRequest` body.
+fn encode(resource: &[(String, String)], spans: &[SpanData]) -> Value {
+    let resource: Vec<Value> = resource.iter().map(|(k, v)| attribute(k, &AttrValue::Str(v.clone()))).collect();
+    let spans: Vec<Value> = spans.iter().map(encode_span).collect();
+    json!({
+        "resourceSpans": [{
+            "resource": { "attributes": resource },
+            "scopeSpans": [{
+                "scope": { "name": "rollcron", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+fn encode_span(span: &SpanData) -> Value {
+    let status = match &span.error {
+        Some(message) => json!({ "code": 2, "message": message }),
+        None => json!({ "code": 1 }),
+    };
+    json!({
+        "traceId": hex(&span.trace_id),
+        "spanId": hex(&span.span_id),
+        "parentSpanId": span.parent_id.map(|id| hex(&id)).unwrap_or_default(),
+        "name": span.name,
+        "kind": 1,
+        "startTimeUnixNano": unix_nanos(span.start),
+        "endTimeUnixNano": unix_nanos(span.end),
+        "attributes": span.attributes.iter().map(|(k, v)| attribute(k, v)).collect::<Vec<_>>(),
+        "status": status,
+    })
+}
+
+fn attribute(key: &str, value: &AttrValue) -> Value {
+    let value = match value {
+        AttrValue::Str(s) => json!({ "stringValue": s }),
+        // 64-bit integers are strings in OTLP/JSON
+        AttrValue::Int(i) => json!({ "intValue": i.to_string() }),
+        AttrValue::Bool(b) => json!({ "boolValue": b }),
+    };
+    json!({ "key": key, "value": value })
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> Result<Option<Config>> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Config::from_env(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn config_from_standard_env() {
+        assert_eq!(config(&[]).unwrap(), None);
+        assert_eq!(config(&[("OTEL_EXPORTER_OTLP_ENDPOINT", "http://c:4318"), ("OTEL_SDK_DISABLED", "true")]).unwrap(), None);
+
+        let c = config(&[
+            ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318/"),
+            ("OTEL_EXPORTER_OTLP_HEADERS", "x-api-key=abc%3D,tenant = ops"),
+            ("OTEL_EXPORTER_OTLP_TRACES_TIMEOUT", "2500"),
+            ("OTEL_RESOURCE_ATTRIBUTES", "service.name=cron-a,deployment.environment=prod"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(c.endpoint, "http://collector:4318/v1/traces");
+        assert_eq!(c.headers, vec![("x-api-key".into(), "abc=".into()), ("tenant".into(), "ops".into())]);
+        assert_eq!(c.timeout, Duration::from_millis(2500));
+        assert_eq!(
+            c.resource,
+            vec![("service.name".into(), "cron-a".into()), ("deployment.environment".into(), "prod".into())]
+        );
+
+        let c = config(&[("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "http://c/traces"), ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://x")])
+            .unwrap()
+            .unwrap();
+        assert_eq!((c.endpoint.as_str(), c.resource[0].1.as_str()), ("http://c/traces", "rollcron"));
+
+        assert!(config(&[("OTEL_EXPORTER_OTLP_ENDPOINT", "http://c"), ("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc")]).is_err());
+    }
+
+    #[test]
+    fn encodes_otlp_json() {
+        let mut span = SpanData::new("job.run", [1; 16], Some([2; 8]));
+        span.attributes.push(("job.id", "backup".into()));
+        span.attributes.push(("job.attempts", 3u32.into()));
+        span.error = Some("exit code 1".to_string());
+        let body = encode(&[("service.name".into(), "rollcron".into())], &[span]);
+
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(resource["resource"]["attributes"][0]["value"]["stringValue"], "rollcron");
+        let span = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], "01".repeat(16));
+        assert_eq!(span["parentSpanId"], "02".repeat(8));
+        assert_eq!(span["name"], "job.run");
+        assert_eq!(span["attributes"][1]["value"]["intValue"], "3");
+        assert_eq!(span["status"], json!({ "code": 2, "message": "exit code 1" }));
+        assert!(span["startTimeUnixNano"].as_str().unwrap().parse::<u128>().is_ok());
+    }
+}
@@ -0,0 +1,267 @@
+//! Persistent queue of outgoing webhook notifications (the outbox).
+//!
+//! Each notification is a JSON file in `~/.cache/rollcron/outbox/<repo>/`,
+//! written before delivery is attempted and removed once the webhook accepts
+//! it. One worker delivers them in order with exponential backoff, so a chat
+//! outage neither drops alerts nor blocks job execution, and anything still
+//! queued is retried after a restart.
+
+use crate::queue::{backoff, entry_paths, read_entry, write_entry};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+/// First retry delay; doubles per failed attempt up to `MAX_DELAY`
+const BASE_DELAY: Duration = Duration::from_secs(5);
+const MAX_DELAY: Duration = Duration::from_secs(600);
+/// Undelivered notifications older than this are dropped
+const MAX_AGE: Duration = Duration::from_secs(24 * 3600);
+/// Queue bound; beyond it the oldest notifications are dropped
+const MAX_QUEUED: usize = 1000;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Worker wake-up when nothing is scheduled
+const IDLE_INTERVAL: Duration = Duration::from_secs(3600);
+
+static OUTBOX: OnceLock<Outbox> = OnceLock::new();
+
+struct Outbox {
+    dir: PathBuf,
+    wake: Notify,
+}
+
+/// A queued notification, stored as `<created ms>-<random>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    payload: Value,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    attempts: u32,
+    next_attempt_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_error: Option<String>,
+}
+
+/// Why a delivery attempt failed
+#[derive(Debug, PartialEq)]
+enum Failure {
+    /// Worth retrying (network error, 429, 5xx), optionally after the server's `Retry-After`
+    Transient { error: String, retry_after: Option<Duration> },
+    /// Retrying cannot help (e.g. 404 for a deleted webhook)
+    Permanent(String),
+}
+
+/// Starts the delivery worker for `dir`. Returns the number of notifications
+/// left over from a previous run.
+pub fn start(dir: PathBuf) -> Result<usize> {
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let pending = entry_paths(&dir)?.len();
+    if OUTBOX.set(Outbox { dir, wake: Notify::new() }).is_ok() {
+        tokio::spawn(worker());
+    }
+    Ok(pending)
+}
+
+/// True once `start` was called. Without a worker, notifications are sent directly.
+pub fn is_running() -> bool {
+    OUTBOX.get().is_some()
+}
+
+/// Persists a notification and wakes the worker.
+pub fn enqueue(url: &str, payload: Value) -> Result<()> {
+    let outbox = OUTBOX.get().context("Notification outbox is not running")?;
+    let now = Utc::now();
+    let entry = Entry {
+        url: url.to_string(),
+        payload,
+        created_at: now,
+        attempts: 0,
+        next_attempt_at: now,
+        last_error: None,
+    };
+    let name = format!("{:013}-{:08x}.json", now.timestamp_millis(), rand::random::<u32>());
+    write_entry(&outbox.dir.join(name), &entry)?;
+    outbox.wake.notify_one();
+    Ok(())
+}
+
+async fn worker() {
+    let Some(outbox) = OUTBOX.get() else {
+        return;
+    };
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            error!(target: "rollcron::webhook", error = %e, "Failed to create HTTP client, notifications stay queued");
+            return;
+        }
+    };
+    loop {
+        let next = match deliver_due(&client, &outbox.dir).await {
+            Ok(next) => next,
+            Err(e) => {
+                warn!(target: "rollcron::webhook", error = %e, "Failed to read notification outbox");
+                Some(Utc::now() + BASE_DELAY)
+            }
+        };
+        let wait = next.map_or(IDLE_INTERVAL, |at| (at - Utc::now()).to_std().unwrap_or_default());
+        tokio::select! {
+            _ = outbox.wake.notified() => {}
+            _ = tokio::time::sleep(wait) => {}
+        }
+    }
+}
+
+/// Delivers every due notification, oldest first. Returns when the next one is due.
+async fn deliver_due(client: &reqwest::Client, dir: &Path) -> Result<Option<DateTime<Utc>>> {
+    let mut paths = entry_paths(dir)?;
+    if paths.len() > MAX_QUEUED {
+        let excess = paths.len() - MAX_QUEUED;
+        warn!(target: "rollcron::webhook", dropped = excess, "Notification outbox full, dropping oldest");
+        for path in paths.drain(..excess) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    // After a failure, later notifications to the same URL wait for the same retry
+    let mut backing_off: HashMap<String, DateTime<Utc>> = HashMap::new();
+    let mut next: Option<DateTime<Utc>> = None;
+    for path in paths {
+        let Some(mut entry) = read_entry::<Entry>(&path) else {
+            warn!(target: "rollcron::webhook", path = %path.display(), "Dropping unreadable queued notification");
+            let _ = std::fs::remove_file(&path);
+            continue;
+        };
+        let now = Utc::now();
+        if now - entry.created_at > chrono::Duration::from_std(MAX_AGE).unwrap_or(chrono::Duration::MAX) {
+            error!(
+                target: "rollcron::webhook",
+                url = %entry.url,
+                attempts = entry.attempts,
+                last_error = entry.last_error.as_deref().unwrap_or("-"),
+                "Dropping notification undelivered for {:?}",
+                MAX_AGE
+            );
+            let _ = std::fs::remove_file(&path);
+            continue;
+        }
+        if let Some(&retry_at) = backing_off.get(&entry.url) {
+            if entry.next_attempt_at < retry_at {
+                entry.next_attempt_at = retry_at;
+                write_entry(&path, &entry)?;
+            }
+        }
+        if entry.next_attempt_at > now {
+            next = Some(next.map_or(entry.next_attempt_at, |n| n.min(entry.next_attempt_at)));
+            continue;
+        }
+
+        match post(client, &entry.url, &entry.payload).await {
+            Ok(()) => {
+                info!(target: "rollcron::webhook", url = %entry.url, attempts = entry.attempts + 1, "Notification sent");
+                let _ = std::fs::remove_file(&path);
+            }
+            Err(Failure::Permanent(e)) => {
+                error!(target: "rollcron::webhook", url = %entry.url, error = %e, "Notification rejected, not retrying");
+                let _ = std::fs::remove_file(&path);
+            }
+            Err(Failure::Transient { error, retry_after }) => {
+                entry.attempts += 1;
+                let delay = retry_after.unwrap_or_else(|| backoff(entry.attempts, BASE_DELAY, MAX_DELAY));
+                entry.next_attempt_at = Utc::now() + delay;
+                warn!(
+                    target: "rollcron::webhook",
+                    url = %entry.url,
+                    error = %error,
+                    attempts = entry.attempts,
+                    retry_in = ?delay,
+                    "Failed to send notification, will retry"
+                );
+                entry.last_error = Some(error);
+                write_entry(&path, &entry)?;
+                backing_off.insert(entry.url.clone(), entry.next_attempt_at);
+                next = Some(next.map_or(entry.next_attempt_at, |n| n.min(entry.next_attempt_at)));
+            }
+        }
+    }
+    Ok(next)
+}
+
+async fn post(client: &reqwest::Client, url: &str, payload: &Value) -> Result<(), Failure> {
+    let resp = client.post(url).json(payload).send().await.map_err(|e| Failure::Transient {
+        error: e.to_string(),
+        retry_after: None,
+    })?;
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let error = format!("HTTP {}", status);
+    if status.as_u16() == 429 || status.is_server_error() || status.as_u16() == 408 {
+        let retry_after = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(|secs| Duration::from_secs(secs).min(MAX_DELAY));
+        Err(Failure::Transient { error, retry_after })
+    } else {
+        Err(Failure::Permanent(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn retries_transient_failures_and_drops_permanent_ones() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Answers 503 (Retry-After: 1) on /down and 404 on /gone
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0; 4096];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let status = if buf[..n].starts_with(b"POST /down") { "503 Unavailable\r\nRetry-After: 1" } else { "404 Not Found" };
+                let resp = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                let _ = stream.write_all(resp.as_bytes()).await;
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now();
+        let entry = |path: &str| Entry {
+            url: format!("http://{}/{}", addr, path),
+            payload: serde_json::json!({ "text": "job failed" }),
+            created_at: now,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+        };
+        write_entry(&dir.path().join("1-a.json"), &entry("down")).unwrap();
+        write_entry(&dir.path().join("2-b.json"), &entry("down")).unwrap();
+        write_entry(&dir.path().join("3-c.json"), &entry("gone")).unwrap();
+
+        let client = reqwest::Client::new();
+        let next = deliver_due(&client, dir.path()).await.unwrap().unwrap();
+        assert!(next > now && next <= Utc::now() + Duration::from_secs(1));
+
+        let paths = entry_paths(dir.path()).unwrap();
+        assert_eq!(paths.len(), 2, "404 dropped, 503s kept");
+        let first: Entry = read_entry(&paths[0]).unwrap();
+        assert_eq!((first.attempts, first.last_error.as_deref()), (1, Some("HTTP 503 Service Unavailable")));
+        let second: Entry = read_entry(&paths[1]).unwrap();
+        assert_eq!(second.attempts, 0, "same URL waits for the first retry");
+        assert_eq!(second.next_attempt_at, first.next_attempt_at);
+    }
+}
@@ -0,0 +1,34 @@
+//! Liveness of processes named in on-disk markers (cache clones, history
+//! run markers).
+
+/// Whether `pid` names a running process. A process owned by another user
+/// (EPERM) counts as alive.
+#[cfg(unix)]
+pub fn alive(pid: u32) -> bool {
+    let pid = nix::unistd::Pid::from_raw(pid as i32);
+    !matches!(nix::sys::signal::kill(pid, None), Err(nix::errno::Errno::ESRCH))
+}
+
+/// Without a way to probe, a marker's process is assumed to be alive, so
+/// nothing it may still be using is removed or reported as interrupted.
+#[cfg(not(unix))]
+pub fn alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_processes() {
+        assert!(alive(std::process::id()));
+        // init runs as root; unprivileged, the probe fails with EPERM
+        assert!(alive(1));
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert!(!alive(pid));
+    }
+}
@@ -0,0 +1,233 @@
+//! Host command policy: jobs whose commands match a dangerous pattern are
+//! loaded blocked until approved (`rollcron ctl approve-job <id>`).
+//!
+//! The policy file lives on the host (`--policy`), not in the repo, so a
+//! commit cannot loosen it. An approval records the exact commands it
+//! covers; changing a flagged command blocks the job again.
+//!
+//! ```yaml
+//! require_approval:
+//!   - pattern: 'rm\s+-rf\s+/(\s|$)'
+//!     reason: deletes the root filesystem
+//!   - 'curl[^|]*\|\s*(ba)?sh'
+//! ```
+
+use crate::config::Job;
+use crate::git;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const APPROVAL_FILE: &str = "approval.json";
+
+static POLICY: OnceLock<Policy> = OnceLock::new();
+
+#[derive(Debug, Default)]
+pub struct Policy {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    pattern: Regex,
+    reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    require_approval: Vec<RuleRaw>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RuleRaw {
+    Pattern(String),
+    Full { pattern: String, reason: Option<String> },
+}
+
+/// Approval of a job's flagged commands, stored in its history dir.
+#[derive(Debug, Serialize, Deserialize)]
+struct Approval {
+    commands: Vec<String>,
+    approved_at: DateTime<Utc>,
+}
+
+impl Policy {
+    pub fn parse(content: &str) -> Result<Self> {
+        let file: PolicyFile = serde_yaml::from_str(content).context("Invalid policy file")?;
+        let rules = file
+            .require_approval
+            .into_iter()
+            .map(|raw| {
+                let (pattern, reason) = match raw {
+                    RuleRaw::Pattern(pattern) => (pattern, None),
+                    RuleRaw::Full { pattern, reason } => (pattern, reason),
+                };
+                let regex = Regex::new(&pattern).with_context(|| format!("Invalid pattern '{}'", pattern))?;
+                Ok(Rule { pattern: regex, reason })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid policy {}", path.display()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Commands of `job` matching a rule, with the match descriptions.
+    fn flagged(&self, job: &Job) -> (Vec<String>, Vec<String>) {
+        let mut commands = Vec::new();
+        let mut reasons = Vec::new();
+        for (field, command) in job_commands(job) {
+            let mut matched = false;
+            for rule in &self.rules {
+                if rule.pattern.is_match(&command) {
+                    matched = true;
+                    let why = rule.reason.as_deref().unwrap_or(rule.pattern.as_str());
+                    reasons.push(format!("{} matches '{}'", field, why));
+                }
+            }
+            if matched {
+                commands.push(command);
+            }
+        }
+        (commands, reasons)
+    }
+}
+
+/// Installs the host policy. Later calls are ignored.
+pub fn init(policy: Policy) {
+    let _ = POLICY.set(policy);
+}
+
+fn get() -> &'static Policy {
+    POLICY.get_or_init(Policy::default)
+}
+
+/// Why `job` may not run yet, or None if nothing is flagged or it was approved.
+pub fn blocked_reason(job: &Job, sot_path: &Path) -> Option<String> {
+    check(get(), job, &approval_path(sot_path, &job.id))
+}
+
+/// Approves the job's currently flagged commands. Returns false if nothing was flagged.
+pub fn approve(job: &Job, sot_path: &Path) -> Result<bool> {
+    let (commands, _) = get().flagged(job);
+    if commands.is_empty() {
+        return Ok(false);
+    }
+    write_approval(&approval_path(sot_path, &job.id), commands)?;
+    Ok(true)
+}
+
+fn check(policy: &Policy, job: &Job, approval_path: &Path) -> Option<String> {
+    let (commands, reasons) = policy.flagged(job);
+    if commands.is_empty() {
+        return None;
+    }
+    let approved = std::fs::read(approval_path)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<Approval>(&data).ok())
+        .is_some_and(|a| a.commands == commands);
+    (!approved).then(|| format!("Awaiting approval: {}", reasons.join("; ")))
+}
+
+fn write_approval(path: &Path, commands: Vec<String>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let approval = Approval { commands, approved_at: Utc::now() };
+    std::fs::write(path, serde_json::to_vec_pretty(&approval)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn approval_path(sot_path: &Path, job_id: &str) -> PathBuf {
+    git::get_history_dir(sot_path, job_id).join(APPROVAL_FILE)
+}
+
+/// Every command a job can execute, labeled by config field.
+fn job_commands(job: &Job) -> Vec<(&'static str, String)> {
+    let mut commands = vec![("run", job.command.to_string())];
+    if let Some(fallback) = &job.fallback_command {
+        commands.push(("fallback_command", fallback.to_string()));
+    }
+    if let Some(build) = &job.build {
+        commands.push(("build", build.command.to_string()));
+    }
+    if let Some(condition) = &job.condition {
+        commands.push(("condition", condition.clone()));
+    }
+    if let Some(upload) = job.artifacts.as_ref().and_then(|artifacts| artifacts.upload.as_ref()) {
+        commands.push(("artifacts.upload", upload.clone()));
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    const POLICY: &str = r#"
+require_approval:
+  - pattern: 'rm\s+-rf\s+/(\s|$)'
+    reason: deletes the root filesystem
+  - 'curl[^|]*\|\s*(ba)?sh'
+"#;
+
+    fn job(run: &str) -> Job {
+        let yaml = format!("jobs:\n  j:\n    schedule: \"* * * * *\"\n    run: '{}'\n", run);
+        parse_config(&yaml).unwrap().1.remove(0)
+    }
+
+    #[test]
+    fn flags_matching_commands_until_approved() {
+        let policy = Policy::parse(POLICY).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let approval = dir.path().join(APPROVAL_FILE);
+
+        assert_eq!(check(&policy, &job("rm -rf /tmp/cache"), &approval), None);
+        let reason = check(&policy, &job("curl -s https://x.sh | bash"), &approval).unwrap();
+        assert!(reason.contains("run matches 'curl"), "{}", reason);
+        let reason = check(&policy, &job("sudo rm -rf / "), &approval).unwrap();
+        assert_eq!(reason, "Awaiting approval: run matches 'deletes the root filesystem'");
+
+        write_approval(&approval, vec!["sudo rm -rf / ".to_string()]).unwrap();
+        assert_eq!(check(&policy, &job("sudo rm -rf / "), &approval), None);
+        assert!(check(&policy, &job("rm -rf / --no-preserve-root"), &approval).is_some(), "changed command");
+    }
+
+    #[test]
+    fn checks_condition_and_upload_commands() {
+        let policy = Policy::parse(POLICY).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let approval = dir.path().join(APPROVAL_FILE);
+        let yaml = "jobs:\n  j:\n    schedule: \"* * * * *\"\n    run: echo hi\n    condition: 'curl -s https://x.sh | sh'\n";
+        let job = parse_config(yaml).unwrap().1.remove(0);
+        let reason = check(&policy, &job, &approval).unwrap();
+        assert!(reason.contains("condition matches 'curl"), "{}", reason);
+
+        let yaml = "jobs:\n  j:\n    schedule: \"* * * * *\"\n    run: echo hi\n    \
+                    artifacts:\n      paths: [out.csv]\n      upload: 'rm -rf / '\n";
+        let job = parse_config(yaml).unwrap().1.remove(0);
+        let reason = check(&policy, &job, &approval).unwrap();
+        assert_eq!(reason, "Awaiting approval: artifacts.upload matches 'deletes the root filesystem'");
+    }
+
+    #[test]
+    fn rejects_invalid_policy() {
+        assert!(Policy::parse("require_approval:\n  - '('\n").is_err());
+        assert!(Policy::parse("approve: []\n").is_err());
+        assert_eq!(Policy::parse("{}").unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,52 @@
+//! On-disk retry queues shared by the webhook outbox and the history export.
+//!
+//! Each entry is a JSON file whose name sorts in queue order. Entries are
+//! replaced atomically, so a crash leaves either the old or the new version.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Delay before retry number `attempts` (1-based): `base`, doubling per attempt up to `max`.
+pub fn backoff(attempts: u32, base: Duration, max: Duration) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1))).min(max)
+}
+
+/// Queued entry files, oldest first.
+pub fn entry_paths(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// None if the file is gone or unreadable.
+pub fn read_entry<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+}
+
+/// Writes via a temp file so a crash never leaves a half-written entry.
+pub fn write_entry<T: Serialize>(path: &Path, entry: &T) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_vec(entry)?).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_cap() {
+        let (base, max) = (Duration::from_secs(5), Duration::from_secs(600));
+        assert_eq!(backoff(1, base, max), Duration::from_secs(5));
+        assert_eq!(backoff(2, base, max), Duration::from_secs(10));
+        assert_eq!(backoff(4, base, max), Duration::from_secs(40));
+        assert_eq!(backoff(50, base, max), max);
+    }
+}
@@ -0,0 +1,106 @@
+//! JSON Schema for `rollcron.yaml`, for editor completion and validation.
+//!
+//! Generated with schemars from the raw config structs in `config.rs`, so a
+//! new config key shows up here as soon as it is parsed; doc comments on
+//! those structs become the descriptions. The tests below validate every
+//! config fixture the parser accepts (config tests, README examples)
+//! against it.
+
+use schemars::generate::SchemaSettings;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde_json::Value;
+use std::borrow::Cow;
+
+/// Schema for `rollcron.yaml`.
+pub fn schema() -> Value {
+    generate::<crate::config::Config>()
+}
+
+/// Schema for a file listed in `include` (jobs only).
+pub fn include_schema() -> Value {
+    generate::<crate::config::IncludedConfig>()
+}
+
+fn generate<T: JsonSchema>() -> Value {
+    SchemaSettings::draft2020_12().into_generator().into_root_schema_for::<T>().to_value()
+}
+
+/// Duration string, for `#[schemars(with = "...")]` on fields parsed by `parse_duration`
+pub(crate) struct Duration;
+
+impl JsonSchema for Duration {
+    fn schema_name() -> Cow<'static, str> {
+        "Duration".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Duration such as `30s`, `5m`, `1h` or `1d`",
+            "type": "string"
+        })
+    }
+}
+
+/// Size string, for `#[schemars(with = "...")]` on fields parsed by `parse_size`
+pub(crate) struct Size;
+
+impl JsonSchema for Size {
+    fn schema_name() -> Cow<'static, str> {
+        "Size".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Size such as `512K`, `10M` or `1G`",
+            "type": "string"
+        })
+    }
+}
+
+/// A key that is still parsed only so that configs setting it are refused.
+pub(crate) fn removed_signers(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "description": "Removed: pass the host's trusted keys with `--allowed-signers`",
+        "not": {}
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text between each `open` and the next `close`
+    fn blocks<'a>(content: &'a str, open: &str, close: &str) -> Vec<&'a str> {
+        content.split(open).skip(1).filter_map(|block| block.split_once(close).map(|(yaml, _)| yaml)).collect()
+    }
+
+    /// The config tests' YAML, the README's `yaml` blocks and the repo's own `rollcron.yaml`
+    fn fixtures() -> Vec<(String, &'static str)> {
+        let tests = blocks(include_str!("config.rs"), "r#\"\n", "\"#");
+        let readme = blocks(include_str!("../README.md"), "```yaml\n", "```");
+        let mut fixtures: Vec<(String, &str)> = Vec::new();
+        for (file, yamls) in [("config.rs fixture", tests), ("README.md yaml block", readme)] {
+            fixtures.extend(yamls.into_iter().enumerate().map(|(i, yaml)| (format!("{} #{}", file, i + 1), yaml)));
+        }
+        fixtures.push(("rollcron.yaml".to_string(), include_str!("../rollcron.yaml")));
+        fixtures
+    }
+
+    #[test]
+    fn fixtures_match_schema() {
+        let validator = jsonschema::validator_for(&schema()).unwrap();
+        let mut checked = 0;
+        for (name, yaml) in fixtures() {
+            // Fragments and configs the parser rejects are out of scope
+            if !matches!(crate::config::parse_config_in(yaml, None), Ok((_, _, skipped)) if skipped.is_empty()) {
+                continue;
+            }
+            let instance: Value = serde_yaml::from_str(yaml).unwrap();
+            let errors: Vec<String> =
+                validator.iter_errors(&instance).map(|e| format!("{}: {}", e.instance_path, e)).collect();
+            assert!(errors.is_empty(), "{} doesn't match the schema:\n{}\n{}", name, errors.join("\n"), yaml);
+            checked += 1;
+        }
+        assert!(checked >= 100, "only {} fixtures parsed", checked);
+    }
+}
@@ -0,0 +1,153 @@
+//! Encrypted .env files and secret references.
+//!
+//! Env files ending in `.age` are decrypted with `age`, files ending in `.enc`
+//! with `sops` (dotenv format). Decryption happens at spawn time, so plaintext
+//! secrets never touch the repo or the job directories.
+//! Secret references in `env` values are handled by `provider`.
+//! Both run their CLIs synchronously; async callers load env through
+//! `spawn_blocking`.
+
+mod provider;
+
+pub use provider::resolve;
+
+use crate::config::SecretsConfig;
+use crate::env;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Default age identity file: ~/.config/rollcron/age.key
+const DEFAULT_KEY_FILE: &str = "rollcron/age.key";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encryption {
+    Age,
+    Sops,
+}
+
+impl Encryption {
+    fn detect(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("age") => Some(Encryption::Age),
+            Some("enc") => Some(Encryption::Sops),
+            _ => None,
+        }
+    }
+}
+
+/// Loads an env file, decrypting it first if it is encrypted.
+/// Like `env::load_env_from_path`, a missing file yields an empty map.
+pub fn load_env_file(path: &PathBuf, config: &SecretsConfig) -> Result<HashMap<String, String>> {
+    let Some(encryption) = Encryption::detect(path) else {
+        return env::load_env_from_path(path);
+    };
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let plaintext = decrypt(path, encryption, config)?;
+    env::parse_env(&plaintext)
+        .with_context(|| format!("failed to parse decrypted env file: {}", path.display()))
+}
+
+fn key_file(config: &SecretsConfig) -> Option<PathBuf> {
+    match &config.key_file {
+        Some(path) => Some(PathBuf::from(env::expand_string(path))),
+        None => dirs::config_dir()
+            .map(|dir| dir.join(DEFAULT_KEY_FILE))
+            .filter(|p| p.exists()),
+    }
+}
+
+fn decrypt(path: &Path, encryption: Encryption, config: &SecretsConfig) -> Result<String> {
+    let key = key_file(config);
+    if let Some(key) = &key {
+        if !key.exists() {
+            anyhow::bail!("secrets key file not found: {}", key.display());
+        }
+    }
+
+    let mut cmd = match encryption {
+        Encryption::Age => {
+            let key = key.context(
+                "age-encrypted env file requires a key (set runner.secrets.key_file or create ~/.config/rollcron/age.key)",
+            )?;
+            let mut cmd = Command::new("age");
+            cmd.arg("--decrypt").arg("-i").arg(key).arg(path);
+            cmd
+        }
+        Encryption::Sops => {
+            let mut cmd = Command::new("sops");
+            cmd.args(["--decrypt", "--input-type", "dotenv", "--output-type", "dotenv"])
+                .arg(path);
+            if let Some(key) = key {
+                cmd.env("SOPS_AGE_KEY_FILE", key);
+            }
+            cmd
+        }
+    };
+
+    let program = match encryption {
+        Encryption::Age => "age",
+        Encryption::Sops => "sops",
+    };
+    let output = cmd
+        .output()
+        .with_context(|| format!("failed to run {} (is it installed?)", program))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "failed to decrypt {} with {}: {}",
+            path.display(),
+            program,
+            stderr.trim()
+        );
+    }
+    String::from_utf8(output.stdout)
+        .with_context(|| format!("decrypted {} is not valid UTF-8", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn detect_encryption_by_extension() {
+        assert_eq!(Encryption::detect(Path::new("secrets.env.age")), Some(Encryption::Age));
+        assert_eq!(Encryption::detect(Path::new("secrets.env.enc")), Some(Encryption::Sops));
+        assert_eq!(Encryption::detect(Path::new(".env")), None);
+        assert_eq!(Encryption::detect(Path::new(".env.production")), None);
+    }
+
+    #[test]
+    fn plain_env_file_passes_through() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".env");
+        fs::write(&path, "FOO=bar").unwrap();
+        let vars = load_env_file(&path, &SecretsConfig::default()).unwrap();
+        assert_eq!(vars.get("FOO"), Some(&"bar".to_string()));
+    }
+
+    #[test]
+    fn missing_encrypted_file_is_empty() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("secrets.env.age");
+        assert!(load_env_file(&path, &SecretsConfig::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn missing_key_file_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("secrets.env.age");
+        fs::write(&path, "ciphertext").unwrap();
+        let config = SecretsConfig {
+            key_file: Some(dir.path().join("missing.key").display().to_string()),
+        };
+        let err = load_env_file(&path, &config).unwrap_err();
+        assert!(err.to_string().contains("key file not found"));
+    }
+}
@@ -0,0 +1,123 @@
+//! Secret references in `env` values, resolved at run time.
+//!
+//! `{from: vault, path: secret/data/etl#password}` reads field `password`
+//! of the Vault secret at `secret/data/etl`; `{from: ssm, path: /etl/db}`
+//! reads an AWS SSM parameter. Lookups shell out to the `vault` / `aws` CLI,
+//! so their usual credentials (VAULT_ADDR/VAULT_TOKEN, AWS profile) apply.
+
+use crate::config::{SecretRef, SecretSource};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::process::Command;
+
+/// Fetches a secret value. `key` is the part after `#` in the path, if any.
+pub trait SecretProvider {
+    fn fetch(&self, path: &str, key: Option<&str>) -> Result<String>;
+}
+
+/// Resolves a secret reference with the provider named in `from`.
+pub fn resolve(secret: &SecretRef) -> Result<String> {
+    let (path, key) = match secret.path.split_once('#') {
+        Some((path, key)) => (path, Some(key)),
+        None => (secret.path.as_str(), None),
+    };
+    provider(secret.from)
+        .fetch(path, key)
+        .with_context(|| format!("failed to resolve {} secret '{}'", secret.from.as_str(), secret.path))
+}
+
+fn provider(source: SecretSource) -> &'static dyn SecretProvider {
+    match source {
+        SecretSource::Vault => &Vault,
+        SecretSource::Ssm => &Ssm,
+    }
+}
+
+/// HashiCorp Vault via `vault read -format=json <path>` (KV v1 and v2).
+struct Vault;
+
+impl SecretProvider for Vault {
+    fn fetch(&self, path: &str, key: Option<&str>) -> Result<String> {
+        let json = run_json("vault", &["read", "-format=json", path])?;
+        // KV v2 nests the secret under data.data
+        let data = json["data"]
+            .get("data")
+            .filter(|d| d.is_object())
+            .unwrap_or(&json["data"]);
+        select_field(data, key)
+    }
+}
+
+/// AWS SSM Parameter Store via `aws ssm get-parameter`.
+/// With `#key`, the parameter value is parsed as JSON and the key selected.
+struct Ssm;
+
+impl SecretProvider for Ssm {
+    fn fetch(&self, path: &str, key: Option<&str>) -> Result<String> {
+        let json = run_json(
+            "aws",
+            &["ssm", "get-parameter", "--with-decryption", "--output", "json", "--name", path],
+        )?;
+        let value = json["Parameter"]["Value"]
+            .as_str()
+            .context("unexpected aws ssm response (missing Parameter.Value)")?;
+        match key {
+            None => Ok(value.to_string()),
+            Some(_) => {
+                let parsed: Value =
+                    serde_json::from_str(value).context("parameter value is not JSON")?;
+                select_field(&parsed, key)
+            }
+        }
+    }
+}
+
+/// Picks `key` from a JSON object; without a key the object must have exactly one field.
+fn select_field(data: &Value, key: Option<&str>) -> Result<String> {
+    let object = data.as_object().context("secret is not a key/value object")?;
+    let value = match key {
+        Some(key) => object
+            .get(key)
+            .with_context(|| format!("secret has no field '{}'", key))?,
+        None if object.len() == 1 => object.values().next().unwrap(),
+        None => anyhow::bail!("secret has {} fields; select one with '#<field>'", object.len()),
+    };
+    Ok(match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    })
+}
+
+fn run_json(program: &str, args: &[&str]) -> Result<Value> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run {} (is it installed?)", program))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("{} failed: {}", program, stderr.trim());
+    }
+    serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("{} returned invalid JSON", program))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn select_named_field() {
+        let data = json!({"user": "etl", "password": "hunter2", "port": 5432});
+        assert_eq!(select_field(&data, Some("password")).unwrap(), "hunter2");
+        assert_eq!(select_field(&data, Some("port")).unwrap(), "5432");
+        assert!(select_field(&data, Some("missing")).is_err());
+    }
+
+    #[test]
+    fn select_single_field_without_key() {
+        assert_eq!(select_field(&json!({"token": "abc"}), None).unwrap(), "abc");
+        let err = select_field(&json!({"a": "1", "b": "2"}), None).unwrap_err();
+        assert!(err.to_string().contains("#<field>"));
+    }
+}
@@ -0,0 +1,106 @@
+//! Trusted commit signing keys. With `--allowed-signers`, every commit
+//! rollcron checks out must be signed by one of them.
+//!
+//! The file lives on the host, not in the repo: keys read from the config
+//! being verified would let one unsigned commit remove the check or add its
+//! own key. One key per line; blank lines and `#` comments are ignored.
+//!
+//! ```text
+//! # alice
+//! ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAI... alice@laptop
+//! 3AA5C34371567BD2
+//! ```
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::sync::OnceLock;
+
+static SIGNERS: OnceLock<Vec<Signer>> = OnceLock::new();
+
+/// A trusted commit signing key
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signer {
+    /// Public key line, e.g. `ssh-ed25519 AAAA... alice@laptop`
+    Ssh(String),
+    /// GPG fingerprint or long key ID, uppercase hex
+    Gpg(String),
+}
+
+/// Reads an allowed signers file; it must list at least one key.
+pub fn load(path: &Path) -> Result<Vec<Signer>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let signers = parse(&content).with_context(|| format!("Invalid allowed signers file {}", path.display()))?;
+    if signers.is_empty() {
+        anyhow::bail!("{} lists no keys", path.display());
+    }
+    Ok(signers)
+}
+
+/// Sets the daemon's signers; the first call wins.
+pub fn init(signers: Vec<Signer>) {
+    let _ = SIGNERS.set(signers);
+}
+
+/// The keys commits must be signed by, if `--allowed-signers` was given.
+pub fn get() -> Option<&'static [Signer]> {
+    SIGNERS.get().map(Vec::as_slice)
+}
+
+fn parse(content: &str) -> Result<Vec<Signer>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| parse_signer(line).with_context(|| format!("line {}", i + 1)))
+        .collect()
+}
+
+/// An SSH public key line, or a GPG fingerprint / long key ID (spaces allowed).
+fn parse_signer(s: &str) -> Result<Signer> {
+    let s = s.trim();
+    let mut fields = s.split_whitespace();
+    if let (Some(kind), Some(key)) = (fields.next(), fields.next()) {
+        if kind.starts_with("ssh-") || kind.starts_with("ecdsa-") || kind.starts_with("sk-") {
+            if !key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=')) {
+                anyhow::bail!("Invalid key '{}': malformed base64", s);
+            }
+            return Ok(Signer::Ssh(s.to_string()));
+        }
+    }
+    let hex: String = s.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_ascii_uppercase();
+    let hex = hex.strip_prefix("0X").unwrap_or(&hex).to_string();
+    if !matches!(hex.len(), 16 | 40 | 64) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid key '{}': expected an SSH public key or a GPG fingerprint / 16-digit key ID", s);
+    }
+    Ok(Signer::Gpg(hex))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_allowed_signers_file() {
+        let content = "\
+# alice
+ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIG9v alice@laptop
+
+3AA5 C343 7156 7BD2
+";
+        assert_eq!(
+            parse(content).unwrap(),
+            [
+                Signer::Ssh("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIG9v alice@laptop".to_string()),
+                Signer::Gpg("3AA5C34371567BD2".to_string()),
+            ]
+        );
+        let err = parse("3AA5C34371567BD2\nalice\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2");
+        assert!(parse("ssh-ed25519 not*base64").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("allowed_signers");
+        std::fs::write(&path, "# nobody yet\n").unwrap();
+        assert!(load(&path).unwrap_err().to_string().ends_with("lists no keys"));
+    }
+}
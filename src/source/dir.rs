@@ -0,0 +1,75 @@
+//! A local directory (e.g. a mounted config volume), snapshotted on every
+//! poll.
+
+use super::{refuse_signed, SourceBackend};
+use crate::config::RunnerConfig;
+use crate::git;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+pub(super) struct Dir {
+    source: String,
+    path: PathBuf,
+}
+
+impl Dir {
+    pub(super) fn new(source: &str, path: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            path: PathBuf::from(path),
+        }
+    }
+
+    fn snapshot(&self, dest: &Path) -> Result<Option<String>> {
+        if !self.path.is_dir() {
+            anyhow::bail!("{} is not a directory", self.path.display());
+        }
+        git::commit_snapshot(dest, &self.path, &format!("Snapshot of {}", self.path.display()))
+    }
+}
+
+impl SourceBackend for Dir {
+    fn kind(&self) -> &'static str {
+        "dir"
+    }
+
+    fn clone_to(&self, dest: &Path) -> Result<()> {
+        git::init_snapshot_repo(&self.source, dest)?;
+        self.snapshot(dest).map(drop)
+    }
+
+    fn sync(&self, dest: &Path, _runner: &RunnerConfig) -> Result<Option<String>> {
+        refuse_signed()?;
+        self.snapshot(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source;
+
+    #[test]
+    fn changes_become_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = dir.path().join("content");
+        std::fs::create_dir(&content).unwrap();
+        std::fs::write(content.join("rollcron.yaml"), "jobs: {}\n").unwrap();
+        std::fs::write(content.join("old.sh"), "true\n").unwrap();
+        std::fs::write(content.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(content.join("debug.log"), "").unwrap();
+
+        let cache = dir.path().join("cache");
+        source::clone_to(&format!("dir://{}", content.display()), &cache).unwrap();
+        assert!(cache.join("old.sh").exists());
+        assert!(!cache.join("debug.log").exists());
+        let runner = crate::config::parse_config("jobs: {}").unwrap().0;
+        assert_eq!(source::sync(&cache, &runner).unwrap(), None);
+
+        std::fs::remove_file(content.join("old.sh")).unwrap();
+        std::fs::write(content.join("new.sh"), "true\n").unwrap();
+        assert!(source::sync(&cache, &runner).unwrap().is_some());
+        assert!(!cache.join("old.sh").exists());
+        assert!(cache.join("new.sh").exists());
+        assert_eq!(source::sync(&cache, &runner).unwrap(), None);
+    }
+}
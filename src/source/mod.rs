@@ -0,0 +1,106 @@
+//! Where the repository comes from.
+//!
+//! The cache is always a git repository: jobs build in worktrees of it and
+//! runs record its commits. A git source is cloned and fetched as usual; the
+//! other backends commit a snapshot of their content to a local repository
+//! whenever it changes, remembering the source in its config so the poller
+//! can find the backend again.
+
+mod dir;
+mod tarball;
+
+use crate::config::RunnerConfig;
+use crate::{git, signers};
+use anyhow::Result;
+use std::path::Path;
+
+/// Prefix of a local directory source, e.g. `dir:///etc/rollcron`
+pub const DIR_SCHEME: &str = "dir://";
+
+const TARBALL_SUFFIXES: [&str; 5] = [".tar.gz", ".tgz", ".tar", ".tar.bz2", ".tar.xz"];
+
+/// A kind of source the cache can be populated from.
+pub trait SourceBackend {
+    /// `git`, `tarball` or `dir`, for logs.
+    fn kind(&self) -> &'static str;
+
+    /// Creates the cache at `dest`.
+    fn clone_to(&self, dest: &Path) -> Result<()>;
+
+    /// Brings the cache at `dest` up to date. Returns the commit range if it
+    /// changed.
+    fn sync(&self, dest: &Path, runner: &RunnerConfig) -> Result<Option<String>>;
+}
+
+/// Selects the backend by the form of a resolved source: `dir://` is a local
+/// directory, an `http(s)://` URL of a tar archive is a tarball, and anything
+/// else is a git repository.
+pub fn backend(source: &str) -> Box<dyn SourceBackend> {
+    if let Some(path) = source.strip_prefix(DIR_SCHEME) {
+        return Box::new(dir::Dir::new(source, path));
+    }
+    let is_http = source.starts_with("https://") || source.starts_with("http://");
+    let path = source.split(['?', '#']).next().unwrap_or(source);
+    if is_http && TARBALL_SUFFIXES.iter().any(|suffix| path.ends_with(suffix)) {
+        return Box::new(tarball::Tarball::new(source));
+    }
+    Box::new(Git { source: source.to_string() })
+}
+
+/// Clones `source` into `dest` with its backend.
+pub fn clone_to(source: &str, dest: &Path) -> Result<()> {
+    backend(source).clone_to(dest)
+}
+
+/// Syncs the cache at `dest` with the backend it was created by. Calls are
+/// serialized: the poll loop and `pull_on_run` jobs may sync at the same time.
+pub fn sync(dest: &Path, runner: &RunnerConfig) -> Result<Option<String>> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    match git::repo_config(dest, "rollcron.source") {
+        Some(source) => backend(&source).sync(dest, runner),
+        None => Git { source: String::new() }.sync(dest, runner),
+    }
+}
+
+/// Snapshots can't be signed: refuse rather than run unverified content.
+fn refuse_signed() -> Result<()> {
+    if signers::get().is_some() {
+        anyhow::bail!("--allowed-signers needs a git source");
+    }
+    Ok(())
+}
+
+struct Git {
+    source: String,
+}
+
+impl SourceBackend for Git {
+    fn kind(&self) -> &'static str {
+        "git"
+    }
+
+    fn clone_to(&self, dest: &Path) -> Result<()> {
+        git::clone_to(&self.source, dest)
+    }
+
+    fn sync(&self, dest: &Path, runner: &RunnerConfig) -> Result<Option<String>> {
+        git::sync_repo(dest, signers::get(), runner.allow_force_reset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backend_by_source_form() {
+        let kind = |source: &str| backend(source).kind();
+        assert_eq!(kind("/srv/repo"), "git");
+        assert_eq!(kind("https://github.com/a/b.git"), "git");
+        assert_eq!(kind("git@github.com:a/b.tar.gz"), "git");
+        assert_eq!(kind("https://example.com/app.tar.gz?token=x"), "tarball");
+        assert_eq!(kind("http://example.com/app.tgz"), "tarball");
+        assert_eq!(kind("dir:///etc/rollcron"), "dir");
+    }
+}
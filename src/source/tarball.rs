@@ -0,0 +1,153 @@
+//! A tar archive over HTTP(S), e.g. a release asset or an object in a bucket.
+//!
+//! Each poll sends the `ETag` of the last download as `If-None-Match`, so an
+//! unchanged archive costs a `304`. Servers without ETags are downloaded in
+//! full and only committed when the content differs.
+
+use super::{refuse_signed, SourceBackend};
+use crate::config::RunnerConfig;
+use crate::git;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+const ETAG_KEY: &str = "rollcron.etag";
+
+pub(super) struct Tarball {
+    url: String,
+}
+
+struct Download {
+    body: Vec<u8>,
+    etag: Option<String>,
+}
+
+impl Tarball {
+    pub(super) fn new(url: &str) -> Self {
+        Self { url: url.to_string() }
+    }
+
+    /// `None` if the server reports the archive unchanged since `etag`.
+    fn download(&self, etag: Option<&str>) -> Result<Option<Download>> {
+        // The blocking client must not run on a runtime thread
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let client = reqwest::blocking::Client::builder().timeout(DOWNLOAD_TIMEOUT).build()?;
+                    let mut request = client.get(&self.url);
+                    if let Some(etag) = etag {
+                        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+                    }
+                    let response = request.send()?;
+                    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+                        return Ok(None);
+                    }
+                    let response = response.error_for_status()?;
+                    let etag = response
+                        .headers()
+                        .get(reqwest::header::ETAG)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string);
+                    let body = response.bytes()?.to_vec();
+                    Ok(Some(Download { body, etag }))
+                })
+                .join()
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("download thread panicked")))
+        })
+        .with_context(|| format!("Failed to download {}", redact(&self.url)))
+    }
+
+    fn snapshot(&self, dest: &Path) -> Result<Option<String>> {
+        let etag = git::repo_config(dest, ETAG_KEY);
+        let Some(download) = self.download(etag.as_deref())? else {
+            return Ok(None);
+        };
+
+        let extract_dir = dest.with_extension("extract");
+        let _ = std::fs::remove_dir_all(&extract_dir);
+        let result = extract(&download.body, &extract_dir)
+            .and_then(|root| git::commit_snapshot(dest, &root, &format!("Snapshot of {}", redact(&self.url))));
+        let _ = std::fs::remove_dir_all(&extract_dir);
+        let range = result?;
+
+        // Empty when the server stopped sending one
+        git::set_repo_config(dest, ETAG_KEY, download.etag.as_deref().unwrap_or_default())?;
+        Ok(range)
+    }
+}
+
+impl SourceBackend for Tarball {
+    fn kind(&self) -> &'static str {
+        "tarball"
+    }
+
+    fn clone_to(&self, dest: &Path) -> Result<()> {
+        git::init_snapshot_repo(&self.url, dest)?;
+        self.snapshot(dest).map(drop)
+    }
+
+    fn sync(&self, dest: &Path, _runner: &RunnerConfig) -> Result<Option<String>> {
+        refuse_signed()?;
+        self.snapshot(dest)
+    }
+}
+
+/// Unpacks `archive` (compression detected by tar) into `dir`. Returns the
+/// content root: the archive's single top-level directory if it has one, as
+/// in GitHub and `git archive --prefix` tarballs.
+fn extract(archive: &[u8], dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let file = dir.with_extension("tar");
+    std::fs::write(&file, archive)?;
+    let out = Command::new("tar").arg("-xf").arg(&file).arg("-C").arg(dir).output();
+    let _ = std::fs::remove_file(&file);
+    let out = out.context("Failed to run tar")?;
+    if !out.status.success() {
+        anyhow::bail!("tar failed: {}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+
+    let entries: Vec<_> = std::fs::read_dir(dir)?.collect::<std::io::Result<_>>()?;
+    match entries.as_slice() {
+        [only] if only.file_type()?.is_dir() => Ok(only.path()),
+        _ => Ok(dir.to_path_buf()),
+    }
+}
+
+/// The URL without its query string, which often carries a signature or token.
+fn redact(url: &str) -> &str {
+    url.split('?').next().unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(dir: &Path, args: &[&str]) -> Vec<u8> {
+        let out = Command::new("tar").args(["-cf", "-"]).args(args).current_dir(dir).output().unwrap();
+        assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        out.stdout
+    }
+
+    #[test]
+    fn extract_strips_single_top_level_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("app-1.0")).unwrap();
+        std::fs::write(dir.path().join("app-1.0/rollcron.yaml"), "jobs: {}\n").unwrap();
+        std::fs::write(dir.path().join("README"), "").unwrap();
+
+        let root = extract(&archive(dir.path(), &["app-1.0"]), &dir.path().join("one")).unwrap();
+        assert!(root.ends_with("app-1.0") && root.join("rollcron.yaml").exists());
+
+        let root = extract(&archive(dir.path(), &["app-1.0", "README"]), &dir.path().join("two")).unwrap();
+        assert!(root.ends_with("two") && root.join("README").exists());
+
+        assert!(extract(b"not a tarball", &dir.path().join("bad")).is_err());
+    }
+
+    #[test]
+    fn redact_drops_query() {
+        assert_eq!(redact("https://b.s3.amazonaws.com/app.tar.gz?X-Amz-Signature=abc"), "https://b.s3.amazonaws.com/app.tar.gz");
+    }
+}
@@ -0,0 +1,212 @@
+//! Running under systemd: the `Type=notify` protocol and the unit
+//! `rollcron systemd-unit` prints.
+//!
+//! `READY=1` is sent once the repository is cloned and the jobs are scheduled,
+//! `STATUS=` carries job counts, and with `WatchdogSec=` the watchdog is pinged
+//! only after the runner actor answers, so a wedged scheduler gets restarted.
+//! Outside systemd (no `$NOTIFY_SOCKET`) everything here is a no-op.
+
+use crate::actor::job::GetStatus;
+use crate::actor::runner::{GetJobActors, RunnerActor};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::{debug, warn};
+use xtra::Address;
+
+/// Status refresh when the watchdog is off
+const STATUS_INTERVAL: Duration = Duration::from_secs(30);
+
+struct Notify {
+    socket: std::ffi::OsString,
+    watchdog: Option<Duration>,
+}
+
+static NOTIFY: OnceLock<Notify> = OnceLock::new();
+
+/// Takes the notify socket and watchdog settings out of the environment so
+/// jobs don't inherit them. Call before any other thread starts.
+pub fn init() {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let for_us = std::env::var("WATCHDOG_PID").map_or(true, |pid| pid == std::process::id().to_string());
+    let watchdog = std::env::var("WATCHDOG_USEC")
+        .ok()
+        .and_then(|usec| usec.parse().ok())
+        .filter(|usec| for_us && *usec > 0)
+        .map(Duration::from_micros);
+    // SAFETY: single-threaded, the runtime has not started yet
+    unsafe {
+        std::env::remove_var("NOTIFY_SOCKET");
+        std::env::remove_var("WATCHDOG_USEC");
+        std::env::remove_var("WATCHDOG_PID");
+    }
+    let _ = NOTIFY.set(Notify { socket, watchdog });
+}
+
+/// Sends newline-separated `KEY=VALUE` assignments to the service manager.
+pub fn notify(state: &str) {
+    let Some(notify) = NOTIFY.get() else {
+        return;
+    };
+    if let Err(e) = send(&notify.socket, state) {
+        warn!(error = %e, "Failed to notify systemd");
+    }
+}
+
+#[cfg(unix)]
+fn send(socket: &std::ffi::OsStr, state: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let sock = UnixDatagram::unbound()?;
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(socket);
+    match bytes.strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            sock.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        _ => {
+            sock.send_to(state.as_bytes(), socket)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send(_socket: &std::ffi::OsStr, _state: &str) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Reports readiness, then keeps `STATUS=` current and pings the watchdog
+/// while the runner keeps answering. Runs until the daemon exits.
+pub async fn supervise(runner: Address<RunnerActor>) {
+    let Some(settings) = NOTIFY.get() else {
+        return;
+    };
+    notify(&format!("READY=1\nSTATUS={}", status(&runner).await.unwrap_or_default()));
+    // systemd recommends pinging at half the timeout
+    let interval = settings.watchdog.map_or(STATUS_INTERVAL, |timeout| timeout / 2);
+    loop {
+        tokio::time::sleep(interval).await;
+        let reply = match settings.watchdog {
+            Some(timeout) => tokio::time::timeout(timeout, status(&runner)).await.ok().flatten(),
+            None => status(&runner).await,
+        };
+        match (reply, settings.watchdog) {
+            (Some(status), Some(_)) => notify(&format!("WATCHDOG=1\nSTATUS={}", status)),
+            (Some(status), None) => notify(&format!("STATUS={}", status)),
+            (None, _) => debug!("Runner did not answer, skipping watchdog ping"),
+        }
+    }
+}
+
+/// `STATUS=` line, e.g. `12 jobs, 2 running, 1 paused`
+async fn status(runner: &Address<RunnerActor>) -> Option<String> {
+    let actors = runner.send(GetJobActors).await.ok()?;
+    let (mut running, mut paused) = (0, 0);
+    for addr in actors.values() {
+        if let Ok(status) = addr.send(GetStatus).await {
+            running += usize::from(status.running > 0);
+            paused += usize::from(status.paused);
+        }
+    }
+    Some(format!("{} jobs, {} running, {} paused", actors.len(), running, paused))
+}
+
+/// A hardened service unit running `exec` with `args`. `user` makes it a
+/// user unit (`systemctl --user`), which can't use the kernel protections.
+pub fn unit(exec: &str, args: &[String], user: bool) -> String {
+    let command: Vec<String> = std::iter::once(exec).chain(args.iter().map(String::as_str)).map(quote).collect();
+    let mut unit = format!(
+        "\
+# Generated by `rollcron systemd-unit`
+[Unit]
+Description=rollcron auto-pulling cron scheduler
+Documentation=https://github.com/aster-void/rollcron
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+Type=notify
+NotifyAccess=main
+ExecStart={}
+Restart=on-failure
+RestartSec=10s
+WatchdogSec=2min
+# SIGTERM to rollcron only: it stops its jobs itself before exiting
+KillMode=mixed
+TimeoutStopSec=5min
+StateDirectory=rollcron
+CacheDirectory=rollcron
+Environment=XDG_STATE_HOME=%S XDG_CACHE_HOME=%C
+UMask=0027
+NoNewPrivileges=yes
+RestrictSUIDSGID=yes
+RestrictRealtime=yes
+LockPersonality=yes
+",
+        command.join(" ")
+    );
+    if user {
+        unit.push_str("\n[Install]\nWantedBy=default.target\n");
+    } else {
+        unit.push_str(
+            "\
+PrivateTmp=yes
+ProtectSystem=full
+ProtectKernelTunables=yes
+ProtectKernelModules=yes
+ProtectKernelLogs=yes
+ProtectClock=yes
+ProtectHostname=yes
+# Lets `limits` create job cgroups below the service's own
+Delegate=yes
+
+[Install]
+WantedBy=multi-user.target
+",
+        );
+    }
+    unit
+}
+
+/// Quotes an `ExecStart=` word: specifiers (`%`) and variables (`$`) are
+/// escaped, and words with spaces or quotes are double-quoted.
+fn quote(word: &str) -> String {
+    let escaped = word.replace('%', "%%").replace('$', "$$");
+    if !escaped.is_empty() && !escaped.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';')) {
+        return escaped;
+    }
+    format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_start_quoting() {
+        assert_eq!(quote("/srv/repo"), "/srv/repo");
+        assert_eq!(quote("/srv/my repo"), "\"/srv/my repo\"");
+        assert_eq!(quote("--git-env=A=$HOME"), "--git-env=A=$$HOME");
+        assert_eq!(quote("100%"), "100%%");
+        assert_eq!(quote(r#"a"b"#), r#""a\"b""#);
+        assert_eq!(quote(""), "\"\"");
+    }
+
+    #[test]
+    fn user_unit_skips_kernel_protections() {
+        let args = ["/srv/repo".to_string()];
+        let system = unit("/usr/bin/rollcron", &args, false);
+        assert!(system.contains("ExecStart=/usr/bin/rollcron /srv/repo\n"));
+        assert!(system.contains("Type=notify"));
+        assert!(system.contains("ProtectSystem=full"));
+        assert!(system.contains("WantedBy=multi-user.target"));
+
+        let user = unit("/usr/bin/rollcron", &args, true);
+        assert!(!user.contains("ProtectSystem"));
+        assert!(user.contains("WantedBy=default.target"));
+    }
+}
@@ -1,58 +1,357 @@
-//! Discord webhook notifications.
+//! Webhook notifications: a Discord embed, or the body a webhook's Tera
+//! `template` renders from the notification's fields.
 
+use crate::actor::job::report::FailureClass;
+use crate::config::{Job, NotifyEvent, RunnerConfig, Severity, WebhookConfig};
+use crate::{clock, outbox};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde::Serialize;
-use tracing::{error, info};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
 
 /// Information about a failed job.
 pub struct JobFailure<'a> {
     pub job_id: &'a str,
     pub job_name: &'a str,
+    /// `file:line` of the job definition
+    pub source: &'a str,
+    pub run_id: &'a str,
+    pub started_at: DateTime<Utc>,
+    pub duration: Duration,
+    pub exit_code: Option<i32>,
     pub error: String,
+    pub stdout: String,
     pub stderr: String,
     pub attempts: u32,
+    pub class: Option<FailureClass>,
+    /// Commit the run was executing
+    pub commit: Option<&'a str>,
+}
+
+/// Information about a run that just started.
+pub struct JobStart<'a> {
+    pub job_id: &'a str,
+    pub job_name: &'a str,
+    pub started_at: DateTime<Utc>,
+    pub expected_duration: Option<Duration>,
+}
+
+/// Information about a run skipped by its guards.
+pub struct JobSkipped<'a> {
+    pub job_id: &'a str,
+    pub job_name: &'a str,
+    pub reason: &'a str,
+}
+
+/// Information about a run that deviated from the job's `slo`.
+pub struct JobAnomaly<'a> {
+    pub job_id: &'a str,
+    pub job_name: &'a str,
+    pub run_id: &'a str,
+    pub message: String,
+}
+
+/// Information about a job with `dedupe_alerts` that succeeded after failing.
+pub struct JobRecovered<'a> {
+    pub job_id: &'a str,
+    pub job_name: &'a str,
+    pub run_id: &'a str,
+    pub failing_since: DateTime<Utc>,
+    /// Failed runs in the streak
+    pub failures: u32,
+}
+
+/// Information about a job whose `circuit_breaker` opened.
+pub struct CircuitOpen<'a> {
+    pub job_id: &'a str,
+    pub job_name: &'a str,
+    pub run_id: &'a str,
+    /// Consecutive failed runs
+    pub failures: u32,
+    /// `pause` or `backoff`
+    pub action: &'a str,
+}
+
+/// Information about a successful run whose output differs from the previous one's.
+pub struct OutputChanged<'a> {
+    pub job_id: &'a str,
+    pub job_name: &'a str,
+    pub run_id: &'a str,
+    pub added: &'a [String],
+    pub removed: &'a [String],
 }
 
 /// Information about a failed build.
 pub struct BuildFailure<'a> {
     pub job_id: &'a str,
     pub job_name: &'a str,
+    pub source: &'a str,
     pub error: String,
     pub stderr: String,
 }
 
-/// Send a Discord notification for a job failure.
-pub async fn send_job_failure(url: &str, failure: &JobFailure<'_>) {
-    let payload = build_job_failure_payload(failure);
-    send_discord(url, &payload).await;
+/// Whether a webhook's `filter` lets a notification through right now.
+/// `job` is `None` for runner-level notifications (config errors).
+pub fn accepts(webhook: &WebhookConfig, event: NotifyEvent, job: Option<&Job>, runner: &RunnerConfig) -> bool {
+    let Some(filter) = &webhook.filter else {
+        return true;
+    };
+    let severity = match event {
+        NotifyEvent::JobStarted | NotifyEvent::OutputChanged => Severity::Info,
+        NotifyEvent::JobSkipped | NotifyEvent::JobAnomaly => Severity::Warning,
+        NotifyEvent::BrakeEngaged => Severity::Critical,
+        _ => job.map_or(Severity::Error, |j| j.severity),
+    };
+    let local = runner.timezone.to_local(clock::now());
+    let accepted = filter.matches(event, severity, job.map(|j| &j.labels), &local);
+    if !accepted {
+        debug!(
+            target: "rollcron::webhook",
+            job_id = job.map_or("-", |j| j.id.as_str()),
+            event = ?event,
+            "Notification filtered out for webhook"
+        );
+    }
+    accepted
 }
 
-/// Send a Discord notification for a build failure.
-pub async fn send_build_failure(url: &str, failure: &BuildFailure<'_>) {
-    let payload = build_build_failure_payload(failure);
-    send_discord(url, &payload).await;
+/// `accepts` for a failed run of class `class`, also applying `filter.failure_classes`.
+pub fn accepts_failure(webhook: &WebhookConfig, job: &Job, runner: &RunnerConfig, class: Option<FailureClass>) -> bool {
+    accepts(webhook, NotifyEvent::JobFailure, Some(job), runner)
+        && webhook.filter.as_ref().is_none_or(|f| f.matches_class(class))
 }
 
-/// Send a Discord notification for a config parse error.
-pub async fn send_config_error(url: &str, error: &str) {
-    let payload = build_config_error_payload(error);
-    send_discord(url, &payload).await;
+/// Send a notification that a run started.
+pub async fn send_job_started(url: &str, template: Option<&str>, start: &JobStart<'_>) -> bool {
+    let context = json!({
+        "event": "job_started",
+        "job": { "id": start.job_id, "name": start.job_name },
+        "run": {
+            "started_at": start.started_at,
+            "expected_duration_secs": start.expected_duration.map(|d| d.as_secs()),
+        },
+    });
+    send(url, template, build_job_started_payload(start), context).await
 }
 
-async fn send_discord(url: &str, payload: &DiscordPayload) {
-    let client = reqwest::Client::new();
-    match client.post(url).json(payload).send().await {
-        Ok(resp) if resp.status().is_success() => {
+/// Send a notification for a job failure. Returns false if it could
+/// neither be delivered nor queued.
+pub async fn send_job_failure(url: &str, template: Option<&str>, failure: &JobFailure<'_>) -> bool {
+    let context = json!({
+        "event": "job_failure",
+        "job": { "id": failure.job_id, "name": failure.job_name, "source": failure.source },
+        "run": {
+            "id": failure.run_id,
+            "started_at": failure.started_at,
+            "duration_secs": failure.duration.as_secs_f64(),
+            "exit_code": failure.exit_code,
+            "error": failure.error,
+            "stdout_tail": failure.stdout,
+            "stderr_tail": failure.stderr,
+            "attempts": failure.attempts,
+            "class": failure.class.map(|c| c.as_str()),
+            "commit": failure.commit,
+        },
+    });
+    send(url, template, build_job_failure_payload(failure), context).await
+}
+
+/// Send a notification that a run was skipped.
+pub async fn send_job_skipped(url: &str, template: Option<&str>, skipped: &JobSkipped<'_>) -> bool {
+    let context = json!({
+        "event": "job_skipped",
+        "job": { "id": skipped.job_id, "name": skipped.job_name },
+        "reason": skipped.reason,
+    });
+    send(url, template, build_job_skipped_payload(skipped), context).await
+}
+
+/// Send a notification that a run deviated from the job's `slo`.
+pub async fn send_job_anomaly(url: &str, template: Option<&str>, anomaly: &JobAnomaly<'_>) -> bool {
+    let context = json!({
+        "event": "job_anomaly",
+        "job": { "id": anomaly.job_id, "name": anomaly.job_name },
+        "run": { "id": anomaly.run_id },
+        "message": anomaly.message,
+    });
+    send(url, template, build_job_anomaly_payload(anomaly), context).await
+}
+
+/// Send a notification that a job is succeeding again.
+pub async fn send_job_recovered(url: &str, template: Option<&str>, recovered: &JobRecovered<'_>) -> bool {
+    let context = json!({
+        "event": "job_recovered",
+        "job": { "id": recovered.job_id, "name": recovered.job_name },
+        "run": { "id": recovered.run_id },
+        "failing_since": recovered.failing_since,
+        "failures": recovered.failures,
+    });
+    send(url, template, build_job_recovered_payload(recovered), context).await
+}
+
+/// Send a notification that a job's circuit breaker opened.
+pub async fn send_circuit_open(url: &str, template: Option<&str>, open: &CircuitOpen<'_>) -> bool {
+    let context = json!({
+        "event": "circuit_open",
+        "job": { "id": open.job_id, "name": open.job_name },
+        "run": { "id": open.run_id },
+        "failures": open.failures,
+        "action": open.action,
+    });
+    send(url, template, build_circuit_open_payload(open), context).await
+}
+
+/// Send a notification that a job's output changed.
+pub async fn send_output_changed(url: &str, template: Option<&str>, changed: &OutputChanged<'_>) -> bool {
+    let context = json!({
+        "event": "output_changed",
+        "job": { "id": changed.job_id, "name": changed.job_name },
+        "run": { "id": changed.run_id },
+        "added": changed.added,
+        "removed": changed.removed,
+        "diff": diff_lines(changed),
+    });
+    send(url, template, build_output_changed_payload(changed), context).await
+}
+
+/// Send a notification for a build failure. Returns false if it could
+/// neither be delivered nor queued.
+pub async fn send_build_failure(url: &str, template: Option<&str>, failure: &BuildFailure<'_>) -> bool {
+    let context = json!({
+        "event": "build_failure",
+        "job": { "id": failure.job_id, "name": failure.job_name, "source": failure.source },
+        "build": { "error": failure.error, "stderr_tail": failure.stderr },
+    });
+    send(url, template, build_build_failure_payload(failure), context).await
+}
+
+/// Send a notification for a config parse error.
+pub async fn send_config_error(url: &str, template: Option<&str>, error: &str) {
+    let context = json!({ "event": "config_error", "error": error });
+    send(url, template, build_config_error_payload(error), context).await;
+}
+
+/// Send a notification that a stage of the canary check failed.
+pub async fn send_canary_failure(url: &str, template: Option<&str>, stage: &str, error: &str) {
+    let context = json!({ "event": "canary_failure", "stage": stage, "error": error });
+    send(url, template, build_canary_failure_payload(stage, error), context).await;
+}
+
+/// Send a notification that `runner.brake` paused jobs.
+pub async fn send_brake_engaged(
+    url: &str,
+    template: Option<&str>,
+    failed: usize,
+    runs: usize,
+    window: Duration,
+    paused: &[String],
+) {
+    let context = json!({
+        "event": "brake_engaged",
+        "brake": { "failed": failed, "runs": runs, "window_secs": window.as_secs(), "paused": paused },
+    });
+    send(url, template, build_brake_engaged_payload(failed, runs, window, paused), context).await;
+}
+
+/// Send a synthetic test notification. Unlike the other senders, delivery
+/// and template errors are returned instead of logged so the caller can
+/// report them.
+pub async fn send_test(url: &str, template: Option<&str>, channel: &str) -> Result<()> {
+    let default = build_test_payload(channel);
+    let body = match template {
+        Some(template) => render(template, with_title(json!({ "event": "test", "channel": channel }), &default))?,
+        None => serde_json::to_value(&default)?,
+    };
+    post(url, &body).await
+}
+
+/// Checks that `template` compiles, for config validation.
+pub fn check_template(template: &str) -> Result<()> {
+    compile(template).map(drop)
+}
+
+/// Queues the notification in the daemon's outbox, or sends it right away
+/// when there is none (one-shot subcommands).
+async fn send(url: &str, template: Option<&str>, default: DiscordPayload, context: Value) -> bool {
+    let body = body(template, default, context);
+    if outbox::is_running() {
+        return match outbox::enqueue(url, body) {
+            Ok(()) => true,
+            Err(e) => {
+                error!(target: "rollcron::webhook", url = %url, error = %e, "Failed to queue notification");
+                false
+            }
+        };
+    }
+    match post(url, &body).await {
+        Ok(()) => {
             info!(target: "rollcron::webhook", url = %url, "Notification sent");
-        }
-        Ok(resp) => {
-            error!(target: "rollcron::webhook", url = %url, status = %resp.status(), "Failed to send notification");
+            true
         }
         Err(e) => {
             error!(target: "rollcron::webhook", url = %url, error = %e, "Failed to send notification");
+            false
         }
     }
 }
 
+async fn post(url: &str, body: &Value) -> Result<()> {
+    let client = reqwest::Client::new();
+    let resp = client.post(url).json(body).send().await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("HTTP {}", resp.status());
+    }
+    Ok(())
+}
+
+/// The request body: `template` rendered with `context`, or the Discord
+/// embed when there is no template or it fails to render.
+fn body(template: Option<&str>, default: DiscordPayload, context: Value) -> Value {
+    if let Some(template) = template {
+        match render(template, with_title(context, &default)) {
+            Ok(body) => return body,
+            Err(e) => {
+                warn!(target: "rollcron::webhook", error = %format!("{:#}", e), "Notification template failed, sending the default payload");
+            }
+        }
+    }
+    serde_json::to_value(default).unwrap_or_default()
+}
+
+/// Adds the default embed's title, e.g. `[rollcron] Job 'backup' failed`.
+fn with_title(mut context: Value, default: &DiscordPayload) -> Value {
+    if let (Value::Object(map), Some(embed)) = (&mut context, default.embeds.first()) {
+        map.insert("title".to_string(), Value::String(embed.title.clone()));
+    }
+    context
+}
+
+const TEMPLATE_NAME: &str = "webhook.json";
+
+fn compile(template: &str) -> Result<tera::Tera> {
+    let mut tera = tera::Tera::default();
+    tera.add_raw_template(TEMPLATE_NAME, template)?;
+    // Values land inside JSON strings: escape for those rather than for HTML
+    tera.autoescape_on(vec![TEMPLATE_NAME]);
+    tera.set_escape_fn(escape_json);
+    Ok(tera)
+}
+
+/// Renders `template`, which must produce JSON.
+fn render(template: &str, context: Value) -> Result<Value> {
+    let tera = compile(template)?;
+    let rendered = tera.render(TEMPLATE_NAME, &tera::Context::from_value(context)?)?;
+    serde_json::from_str(&rendered).map_err(|e| anyhow::anyhow!("template did not render valid JSON: {}", e))
+}
+
+/// A string's JSON encoding without the surrounding quotes.
+fn escape_json(s: &str) -> String {
+    let quoted = Value::String(s.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
 // === Internal ===
 
 #[derive(Serialize)]
@@ -74,6 +373,49 @@ struct DiscordField {
     inline: bool,
 }
 
+fn build_job_started_payload(start: &JobStart<'_>) -> DiscordPayload {
+    let mut fields = vec![
+        DiscordField {
+            name: "Job",
+            value: format!("`{}`", start.job_id),
+            inline: true,
+        },
+        DiscordField {
+            name: "Started",
+            value: format!("<t:{}:f>", start.started_at.timestamp()),
+            inline: true,
+        },
+    ];
+
+    if let Some(expected) = start.expected_duration {
+        fields.push(DiscordField {
+            name: "Expected duration",
+            value: humantime_secs(expected),
+            inline: true,
+        });
+    }
+
+    DiscordPayload {
+        embeds: vec![DiscordEmbed {
+            title: format!("[rollcron] Job '{}' started", start.job_name),
+            color: 0x5865F2, // Discord blurple
+            fields,
+        }],
+    }
+}
+
+/// `2h 30m`, `45m`, `30s`
+fn humantime_secs(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    let parts: Vec<String> = [(h, "h"), (m, "m"), (s, "s")]
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, unit)| format!("{}{}", n, unit))
+        .collect();
+    if parts.is_empty() { "0s".to_string() } else { parts.join(" ") }
+}
+
 fn build_job_failure_payload(failure: &JobFailure<'_>) -> DiscordPayload {
     let mut fields = vec![
         DiscordField {
@@ -81,11 +423,26 @@ fn build_job_failure_payload(failure: &JobFailure<'_>) -> DiscordPayload {
             value: format!("`{}`", failure.job_id),
             inline: true,
         },
+        DiscordField {
+            name: "Defined in",
+            value: format!("`{}`", failure.source),
+            inline: true,
+        },
         DiscordField {
             name: "Attempts",
             value: failure.attempts.to_string(),
             inline: true,
         },
+        DiscordField {
+            name: "Category",
+            value: failure.class.map_or("-", |c| c.as_str()).to_string(),
+            inline: true,
+        },
+        DiscordField {
+            name: "Commit",
+            value: failure.commit.map_or("-".to_string(), |c| format!("`{}`", crate::git::short_sha(c))),
+            inline: true,
+        },
         DiscordField {
             name: "Error",
             value: failure.error.clone(),
@@ -111,6 +468,142 @@ fn build_job_failure_payload(failure: &JobFailure<'_>) -> DiscordPayload {
     }
 }
 
+fn build_job_skipped_payload(skipped: &JobSkipped<'_>) -> DiscordPayload {
+    DiscordPayload {
+        embeds: vec![DiscordEmbed {
+            title: format!("[rollcron] Job '{}' skipped", skipped.job_name),
+            color: 0xFEE75C, // Discord yellow
+            fields: vec![
+                DiscordField {
+                    name: "Job",
+                    value: format!("`{}`", skipped.job_id),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Reason",
+                    value: skipped.reason.to_string(),
+                    inline: false,
+                },
+            ],
+        }],
+    }
+}
+
+fn build_job_anomaly_payload(anomaly: &JobAnomaly<'_>) -> DiscordPayload {
+    DiscordPayload {
+        embeds: vec![DiscordEmbed {
+            title: format!("[rollcron] Job '{}' is off its usual pattern", anomaly.job_name),
+            color: 0xFEE75C, // Discord yellow
+            fields: vec![
+                DiscordField {
+                    name: "Job",
+                    value: format!("`{}`", anomaly.job_id),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Run",
+                    value: format!("`{}`", anomaly.run_id),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Anomaly",
+                    value: anomaly.message.clone(),
+                    inline: false,
+                },
+            ],
+        }],
+    }
+}
+
+fn build_job_recovered_payload(recovered: &JobRecovered<'_>) -> DiscordPayload {
+    DiscordPayload {
+        embeds: vec![DiscordEmbed {
+            title: format!("[rollcron] Job '{}' recovered", recovered.job_name),
+            color: 0x57F287, // Discord green
+            fields: vec![
+                DiscordField {
+                    name: "Job",
+                    value: format!("`{}`", recovered.job_id),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Failing since",
+                    value: format!("<t:{}:f>", recovered.failing_since.timestamp()),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Failed runs",
+                    value: recovered.failures.to_string(),
+                    inline: true,
+                },
+            ],
+        }],
+    }
+}
+
+fn build_circuit_open_payload(open: &CircuitOpen<'_>) -> DiscordPayload {
+    let effect = match open.action {
+        "backoff" => "Scheduled runs back off until one succeeds",
+        _ => "Scheduled runs are paused until `rollcron ctl resume` or a new commit",
+    };
+    DiscordPayload {
+        embeds: vec![DiscordEmbed {
+            title: format!("[rollcron] Circuit breaker opened for job '{}'", open.job_name),
+            color: 0xED4245, // Discord red
+            fields: vec![
+                DiscordField {
+                    name: "Job",
+                    value: format!("`{}`", open.job_id),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Consecutive failures",
+                    value: open.failures.to_string(),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Effect",
+                    value: effect.to_string(),
+                    inline: false,
+                },
+            ],
+        }],
+    }
+}
+
+fn build_output_changed_payload(changed: &OutputChanged<'_>) -> DiscordPayload {
+    DiscordPayload {
+        embeds: vec![DiscordEmbed {
+            title: format!("[rollcron] Output of job '{}' changed", changed.job_name),
+            color: 0x5865F2, // Discord blurple
+            fields: vec![
+                DiscordField {
+                    name: "Job",
+                    value: format!("`{}`", changed.job_id),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Run",
+                    value: format!("`{}`", changed.run_id),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Changes",
+                    value: format!("```diff\n{}\n```", truncate(&diff_lines(changed), 1000)),
+                    inline: false,
+                },
+            ],
+        }],
+    }
+}
+
+/// Removed lines as `- line`, then added lines as `+ line`.
+fn diff_lines(changed: &OutputChanged<'_>) -> String {
+    let removed = changed.removed.iter().map(|line| format!("- {}", line));
+    let added = changed.added.iter().map(|line| format!("+ {}", line));
+    removed.chain(added).collect::<Vec<_>>().join("\n")
+}
+
 fn build_build_failure_payload(failure: &BuildFailure<'_>) -> DiscordPayload {
     let mut fields = vec![
         DiscordField {
@@ -118,6 +611,11 @@ fn build_build_failure_payload(failure: &BuildFailure<'_>) -> DiscordPayload {
             value: format!("`{}`", failure.job_id),
             inline: true,
         },
+        DiscordField {
+            name: "Defined in",
+            value: format!("`{}`", failure.source),
+            inline: true,
+        },
         DiscordField {
             name: "Error",
             value: failure.error.clone(),
@@ -158,10 +656,112 @@ fn build_config_error_payload(err: &str) -> DiscordPayload {
     }
 }
 
+fn build_canary_failure_payload(stage: &str, err: &str) -> DiscordPayload {
+    let truncated = truncate(err, 1000);
+    DiscordPayload {
+        embeds: vec![DiscordEmbed {
+            title: "[rollcron] Canary failed".to_string(),
+            color: 0xED4245, // Discord red
+            fields: vec![
+                DiscordField {
+                    name: "Stage",
+                    value: format!("`{}`", stage),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Error",
+                    value: format!("```\n{}\n```", truncated),
+                    inline: false,
+                },
+            ],
+        }],
+    }
+}
+
+fn build_brake_engaged_payload(failed: usize, runs: usize, window: Duration, paused: &[String]) -> DiscordPayload {
+    let paused = if paused.is_empty() {
+        "none".to_string()
+    } else {
+        truncate(&paused.join(", "), 1000).to_string()
+    };
+    DiscordPayload {
+        embeds: vec![DiscordEmbed {
+            title: "[rollcron] Safety brake engaged".to_string(),
+            color: 0xED4245, // Discord red
+            fields: vec![
+                DiscordField {
+                    name: "Failed runs",
+                    value: format!("{} of {} in the last {}", failed, runs, humantime_secs(window)),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Paused jobs",
+                    value: paused,
+                    inline: false,
+                },
+                DiscordField {
+                    name: "Resume",
+                    value: "`rollcron ctl brake --release`".to_string(),
+                    inline: false,
+                },
+            ],
+        }],
+    }
+}
+
+fn build_test_payload(channel: &str) -> DiscordPayload {
+    DiscordPayload {
+        embeds: vec![DiscordEmbed {
+            title: "[rollcron] Test notification".to_string(),
+            color: 0x57F287, // Discord green
+            fields: vec![
+                DiscordField {
+                    name: "Channel",
+                    value: format!("`{}`", channel),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Message",
+                    value: "Sent by `rollcron notify test`. No action needed.".to_string(),
+                    inline: false,
+                },
+            ],
+        }],
+    }
+}
+
 fn truncate(s: &str, max_len: usize) -> &str {
     if s.len() <= max_len {
-        s
-    } else {
-        &s[..max_len]
+        return s;
+    }
+    let mut end = max_len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_renders_escaped_json() {
+        let template = r#"{"text": "{{ title }}: exit {{ run.exit_code }}\n{{ run.stderr_tail }}"}"#;
+        let context = json!({ "run": { "exit_code": 2, "stderr_tail": "line \"one\"\nline two" } });
+        let default = build_config_error_payload("x");
+        let body = body(Some(template), default, context);
+        assert_eq!(body["text"], "[rollcron] Config parse error: exit 2\nline \"one\"\nline two");
+    }
+
+    #[test]
+    fn broken_template_falls_back_to_embed() {
+        assert!(check_template("{{ job.name").is_err());
+        // Renders, but not to JSON
+        let not_json = body(Some("Job {{ job.name }} failed"), build_config_error_payload("x"), json!({ "job": { "name": "a" } }));
+        assert_eq!(not_json["embeds"][0]["title"], "[rollcron] Config parse error");
+        // Field missing for this event
+        let missing = body(Some(r#"{"code": {{ run.exit_code }}}"#), build_config_error_payload("x"), json!({}));
+        assert!(missing.get("embeds").is_some());
     }
 }